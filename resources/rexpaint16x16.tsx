@@ -0,0 +1,4 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" name="rexpaint16x16" tilewidth="16" tileheight="16" tilecount="256" columns="16">
+ <image source="rexpaint16x16.png" width="256" height="256"/>
+</tileset>
@@ -1,4 +1,7 @@
 use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::collections::HashMap;
 
 use serde::{Serialize, Deserialize};
 
@@ -8,12 +11,14 @@ use crate::movement::*;
 use crate::messaging::*;
 use crate::utils::*;
 use crate::config::Config;
-use crate::map::Wall;
+use crate::map::{Surface, TileType, Visibility};
 
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Ai {
     Basic,
+    Follow,
+    Critter,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
@@ -21,6 +26,9 @@ pub enum Behavior {
     Idle,
     Investigating(Pos),
     Attacking(EntityId),
+    Following(EntityId),
+    RaisingAlarm(EntityId),
+    Fleeing(Pos),
 }
 
 impl fmt::Display for Behavior {
@@ -29,6 +37,9 @@ impl fmt::Display for Behavior {
             Behavior::Idle => write!(f, "idle"),
             Behavior::Investigating(pos) => write!(f, "investigating {} {}", pos.x, pos.y),
             Behavior::Attacking(entity_id) => write!(f, "attacking {}", entity_id),
+            Behavior::Following(entity_id) => write!(f, "following {}", entity_id),
+            Behavior::RaisingAlarm(entity_id) => write!(f, "raising_alarm {}", entity_id),
+            Behavior::Fleeing(pos) => write!(f, "fleeing {} {}", pos.x, pos.y),
         }
     }
 }
@@ -45,16 +56,97 @@ impl Behavior {
             Behavior::Idle => "idle".to_string(),
             Behavior::Investigating(_position) => "investigating".to_string(),
             Behavior::Attacking(_obj_id) => "attacking".to_string(),
+            Behavior::Following(_obj_id) => "following".to_string(),
+            Behavior::RaisingAlarm(_obj_id) => "raising alarm".to_string(),
+            Behavior::Fleeing(_position) => "fleeing".to_string(),
         }
     }
 
     pub fn is_aware(&self) -> bool {
-        return matches!(self, Behavior::Attacking(_));
+        return matches!(self, Behavior::Attacking(_) | Behavior::RaisingAlarm(_));
+    }
+}
+
+/// A limit on how many paths the AI may search for in one turn, so a level full of
+/// monsters does not stall the game. Once the limit is reached, the remaining monsters
+/// follow the last path they found, or step straight toward where they are going.
+/// The limit counts searches rather than time, so replays play out the same way.
+///
+/// While AI turns are being decided in parallel, searches are counted but never refused,
+/// as which monster ran out would depend on how the threads were scheduled.
+#[derive(Debug, Default)]
+pub struct AiBudget {
+    pub limit: usize, // 0 for no limit
+    pub planning: bool,
+    pub searches: AtomicUsize,
+    pub tripped: AtomicBool,
+    pub trips: AtomicUsize, // the number of turns in which the limit was reached
+    pub paths: Mutex<HashMap<EntityId, Vec<Pos>>>,
+}
+
+impl AiBudget {
+    pub fn start_turn(&mut self, limit: usize) {
+        self.limit = limit;
+        self.searches.store(0, Ordering::Relaxed);
+        self.tripped.store(false, Ordering::Relaxed);
+    }
+
+    /// Take one search from the budget, returning false if there are none left this turn.
+    pub fn try_search(&self) -> bool {
+        if self.limit == 0 || self.planning || self.searches.load(Ordering::Relaxed) < self.limit {
+            self.searches.fetch_add(1, Ordering::Relaxed);
+            return true;
+        }
+
+        if !self.tripped.swap(true, Ordering::Relaxed) {
+            self.trips.fetch_add(1, Ordering::Relaxed);
+        }
+        return false;
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        return self.tripped.load(Ordering::Relaxed);
+    }
+
+    pub fn num_trips(&self) -> usize {
+        return self.trips.load(Ordering::Relaxed);
+    }
+
+    pub fn remember(&self, monster_id: EntityId, path: &Vec<Pos>) {
+        self.paths.lock().unwrap().insert(monster_id, path.clone());
+    }
+
+    pub fn forget(&self, monster_id: EntityId) {
+        self.paths.lock().unwrap().remove(&monster_id);
+    }
+}
+
+impl Clone for AiBudget {
+    fn clone(&self) -> AiBudget {
+        return AiBudget {
+            limit: self.limit,
+            planning: self.planning,
+            searches: AtomicUsize::new(self.searches.load(Ordering::Relaxed)),
+            tripped: AtomicBool::new(self.is_tripped()),
+            trips: AtomicUsize::new(self.num_trips()),
+            paths: Mutex::new(self.paths.lock().unwrap().clone()),
+        };
+    }
+}
+
+impl PartialEq for AiBudget {
+    fn eq(&self, other: &AiBudget) -> bool {
+        return self.limit == other.limit &&
+               self.planning == other.planning &&
+               self.searches.load(Ordering::Relaxed) == other.searches.load(Ordering::Relaxed) &&
+               self.is_tripped() == other.is_tripped() &&
+               self.num_trips() == other.num_trips() &&
+               *self.paths.lock().unwrap() == *other.paths.lock().unwrap();
     }
 }
 
 pub fn ai_take_turn(monster_id: EntityId,
-                    data: &mut GameData,
+                    data: &GameData,
                     config: &Config,
                     msg_log: &mut MsgLog) {
     if data.entities.status[&monster_id].alive {
@@ -63,6 +155,14 @@ pub fn ai_take_turn(monster_id: EntityId,
                 basic_ai_take_turn(monster_id, data, msg_log, config);
             }
 
+            Some(Ai::Follow) => {
+                follow_ai_take_turn(monster_id, data, msg_log, config);
+            }
+
+            Some(Ai::Critter) => {
+                critter_ai_take_turn(monster_id, data, msg_log, config);
+            }
+
             None => {
                 panic!("AI didn't have an ai entry!");
             }
@@ -71,15 +171,16 @@ pub fn ai_take_turn(monster_id: EntityId,
 }
 
 pub fn basic_ai_take_turn(monster_id: EntityId,
-                          data: &mut GameData,
+                          data: &GameData,
                           msg_log: &mut MsgLog,
                           config: &Config) {
     let monster_pos = data.entities.pos[&monster_id];
 
     if data.map.is_within_bounds(monster_pos) {
-        if data.entities.status[&monster_id].frozen == 0 {
+        if data.entities.status[&monster_id].frozen == 0 &&
+           data.entities.status[&monster_id].knocked_out == 0 {
             match data.entities.behavior[&monster_id] {
-                Behavior::Idle => {
+                Behavior::Idle | Behavior::Following(_) | Behavior::Fleeing(_) => {
                     ai_idle(monster_id, data, msg_log, config);
                 }
 
@@ -88,16 +189,189 @@ pub fn basic_ai_take_turn(monster_id: EntityId,
                 }
 
                 Behavior::Attacking(object_id) => {
-                    ai_attack(monster_id, object_id, data, msg_log, config);
+                    // an alarm close by is raised before joining a fight with the player
+                    let fighting_player = data.entities.typ[&object_id] == EntityType::Player;
+                    if let (true, Some(alarm_id)) = (fighting_player, ai_find_alarm(monster_id, data, config)) {
+                        msg_log.log(Msg::StateChange(monster_id, Behavior::RaisingAlarm(alarm_id)));
+                    } else {
+                        ai_attack(monster_id, object_id, data, msg_log, config);
+                    }
                 }
+
+                Behavior::RaisingAlarm(alarm_id) => {
+                    ai_raise_alarm(monster_id, alarm_id, data, msg_log);
+                }
+            }
+        }
+    }
+}
+
+/// A friendly entity either waits where it is, or follows the entity it was asked
+/// to follow, keeping out of sight of enemies where it can.
+pub fn follow_ai_take_turn(follower_id: EntityId,
+                           data: &GameData,
+                           msg_log: &mut MsgLog,
+                           config: &Config) {
+    if data.entities.status[&follower_id].frozen == 0 &&
+       data.entities.status[&follower_id].knocked_out == 0 {
+        if let Behavior::Following(leader_id) = data.entities.behavior[&follower_id] {
+            ai_follow(follower_id, leader_id, data, msg_log, config);
+        }
+    }
+}
+
+/// Critters wander about until something disturbs them- the player coming close without
+/// crouching, or a noise made by the player or another critter. Then they scatter noisily,
+/// which any monsters nearby will come to look into. Birds fly off, while rats run away.
+pub fn critter_ai_take_turn(critter_id: EntityId,
+                            data: &GameData,
+                            msg_log: &mut MsgLog,
+                            config: &Config) {
+    if data.entities.status[&critter_id].frozen > 0 ||
+       data.entities.status[&critter_id].knocked_out > 0 {
+        return;
+    }
+
+    if let Behavior::Fleeing(from_pos) = data.entities.behavior[&critter_id] {
+        ai_flee(critter_id, from_pos, data, msg_log, config);
+    } else if let Some(disturbed_pos) = ai_critter_disturbed(critter_id, data, config) {
+        msg_log.log(Msg::Scattered(critter_id));
+
+        if data.entities.name[&critter_id] == EntityName::Bird {
+            msg_log.log(Msg::TookTurn(critter_id));
+        } else {
+            msg_log.log(Msg::StateChange(critter_id, Behavior::Fleeing(disturbed_pos)));
+        }
+    } else {
+        ai_wander(critter_id, data, msg_log);
+    }
+}
+
+/// Where a critter was disturbed from, if it was disturbed this turn.
+fn ai_critter_disturbed(critter_id: EntityId, data: &GameData, config: &Config) -> Option<Pos> {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let critter_pos = data.entities.pos[&critter_id];
+    let player_pos = data.entities.pos[&player_id];
+
+    if data.entities.stance[&player_id] != Stance::Crouching &&
+       distance_maximum(critter_pos, player_pos) <= config.critter_disturb_distance &&
+       data.map.line_of_sight(critter_pos, player_pos) != Visibility::Blocked {
+        return Some(player_pos);
+    }
+
+    for message in data.entities.messages[&critter_id].iter() {
+        if let Message::Sound(cause_id, sound_pos) = message {
+            if *cause_id == player_id || data.entities.faction.get(cause_id) == Some(&Faction::Wildlife) {
+                return Some(*sound_pos);
             }
         }
     }
+
+    return None;
+}
+
+/// Run directly away from a position until far enough from it, or cornered.
+fn ai_flee(critter_id: EntityId, from_pos: Pos, data: &GameData, msg_log: &mut MsgLog, config: &Config) {
+    let critter_pos = data.entities.pos[&critter_id];
+    let current_dist = distance(critter_pos, from_pos);
+
+    let mut best: Option<(Direction, i32)> = None;
+    if current_dist < config.critter_flee_distance {
+        for direction in Direction::move_actions().iter() {
+            let next_pos = direction.offset_pos(critter_pos, 1);
+            let next_dist = distance(next_pos, from_pos);
+            if next_dist > best.map_or(current_dist, |(_dir, dist)| dist) && ai_critter_can_step(next_pos, data) {
+                best = Some((*direction, next_dist));
+            }
+        }
+    }
+
+    if let Some((direction, _dist)) = best {
+        msg_log.log(Msg::TryMove(critter_id, direction, 1, MoveMode::Walk));
+    } else {
+        msg_log.log(Msg::TookTurn(critter_id));
+        msg_log.log(Msg::StateChange(critter_id, Behavior::Idle));
+    }
+}
+
+/// Keep walking in the same direction, turning now and then or when the way is blocked.
+fn ai_wander(critter_id: EntityId, data: &GameData, msg_log: &mut MsgLog) {
+    let critter_pos = data.entities.pos[&critter_id];
+
+    let mut direction = data.entities.direction[&critter_id];
+    let ahead = direction.offset_pos(critter_pos, 1);
+    if !ai_critter_can_step(ahead, data) || rand_from_pos(ahead) < CRITTER_TURN_CHANCE {
+        direction = Direction::from_f32(rand_from_pos(critter_pos));
+    }
+
+    let next_pos = direction.offset_pos(critter_pos, 1);
+    if ai_critter_can_step(next_pos, data) {
+        msg_log.log(Msg::TryMove(critter_id, direction, 1, MoveMode::Walk));
+    } else {
+        msg_log.log(Msg::FaceTowards(critter_id, next_pos));
+        msg_log.log(Msg::TookTurn(critter_id));
+    }
+}
+
+fn ai_critter_can_step(pos: Pos, data: &GameData) -> bool {
+    return data.map.is_within_bounds(pos) &&
+           !data.map[pos].block_move &&
+           data.map[pos].tile_type != TileType::Water &&
+           data.has_blocking_entity(pos).is_none();
+}
+
+pub fn ai_follow(follower_id: EntityId,
+                 leader_id: EntityId,
+                 data: &GameData,
+                 msg_log: &mut MsgLog,
+                 config: &Config) {
+    let follower_pos = data.entities.pos[&follower_id];
+    let leader_pos = data.entities.pos[&leader_id];
+
+    // staying next to the leader is enough
+    if distance_maximum(follower_pos, leader_pos) <= 1 {
+        return;
+    }
+
+    let enemies = data.entities.ids.iter()
+                                   .filter(|id| data.entities.typ[*id] == EntityType::Enemy)
+                                   .filter(|id| data.entities.status[*id].alive)
+                                   .filter(|id| data.entities.status[*id].knocked_out == 0)
+                                   .map(|id| *id)
+                                   .collect::<Vec<EntityId>>();
+
+    // tiles within sight of an enemy cost more to cross, so the follower takes
+    // the long way around if there is one.
+    let cost_fun = |_start, _prev, next: Pos, data: &GameData| {
+        if enemies.iter().any(|enemy_id| data.pos_in_fov(*enemy_id, next, config)) {
+            return Some(FOLLOW_SEEN_COST);
+        } else {
+            return Some(1);
+        }
+    };
+
+    let path;
+    if data.ai_budget.try_search() {
+        let reach = data.entities.movement[&follower_id];
+        let must_reach = false;
+        let traps_block = true;
+        path = data.path_between(follower_pos, leader_pos, reach, must_reach, traps_block, Some(&cost_fun));
+        data.ai_budget.remember(follower_id, &path);
+    } else {
+        path = ai_budget_path(follower_id, leader_pos, data);
+    }
+
+    // the last position in the path is the leader's own tile
+    if path.len() > 2 {
+        if let Some(direction) = Direction::from_positions(follower_pos, path[1]) {
+            msg_log.log(Msg::TryMove(follower_id, direction, 1, MoveMode::Walk));
+        }
+    }
 }
 
 pub fn ai_attack(monster_id: EntityId,
                  target_id: EntityId,
-                 data: &mut GameData,
+                 data: &GameData,
                  msg_log: &mut MsgLog,
                  _config: &Config) {
     let target_pos = data.entities.pos[&target_id];
@@ -109,7 +383,7 @@ pub fn ai_attack(monster_id: EntityId,
 }
 
 pub fn ai_idle(monster_id: EntityId,
-               data: &mut GameData,
+               data: &GameData,
                msg_log: &mut MsgLog,
                config: &Config) {
     let player_id = data.find_by_name(EntityName::Player).unwrap();
@@ -120,7 +394,9 @@ pub fn ai_idle(monster_id: EntityId,
         msg_log.log(Msg::Sound(monster_id, monster_pos, config.sound_golem_idle_radius, should_animate));
     }
 
-    if ai_is_in_fov(monster_id, player_id, data, config) {
+    let player_hostile = data.relation(monster_id, player_id, config) == Relation::Hostile;
+
+    if player_hostile && ai_is_in_fov(monster_id, player_id, data, config) {
         let player_pos = data.entities.pos[&player_id];
         msg_log.log(Msg::FaceTowards(monster_id, player_pos));
 
@@ -129,37 +405,51 @@ pub fn ai_idle(monster_id: EntityId,
         } else {
             msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(player_pos)));
         }
-    } else if let Some(Message::Attack(entity_id)) = data.entities.was_attacked(monster_id) {
+    } else if let Some(body_id) = ai_find_body(monster_id, data, config) {
+        let body_pos = data.entities.pos[&body_id];
+        msg_log.log(Msg::FaceTowards(monster_id, body_pos));
+        msg_log.log(Msg::BodyDiscovered(monster_id, body_id));
+    } else if let Some(message@Message::Distraction(distraction_pos)) = data.entities.distracted(monster_id) {
+        msg_log.log(Msg::FaceTowards(monster_id, distraction_pos));
+        msg_log.log(Msg::ReadMessage(monster_id, message));
+        msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(distraction_pos)));
+    } else if let Some(message@Message::Attack(entity_id)) = data.entities.was_attacked(monster_id) {
     let entity_pos = data.entities.pos[&entity_id];
         msg_log.log(Msg::FaceTowards(monster_id, entity_pos));
+        msg_log.log(Msg::ReadMessage(monster_id, message));
 
         if data.entities.attack.get(&monster_id).is_some() {
             msg_log.log(Msg::StateChange(monster_id, Behavior::Attacking(entity_id)));
         } else {
             msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(entity_pos)));
         }
-    } else if let Some(Message::Sound(entity_id, sound_pos)) = data.entities.heard_sound(monster_id) {
+    } else if let Some(message@Message::Sound(entity_id, sound_pos)) = data.entities.heard_sound(monster_id) {
         let is_player = entity_id == player_id;
+        let is_wildlife = data.entities.faction.get(&entity_id) == Some(&Faction::Wildlife);
 
-        let needs_investigation = is_player;
+        let needs_investigation = is_player || is_wildlife;
 
         if needs_investigation {
             msg_log.log(Msg::FaceTowards(monster_id, sound_pos));
+            msg_log.log(Msg::ReadMessage(monster_id, message));
             msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(sound_pos)));
+        } else {
+            msg_log.log(Msg::ReadMessage(monster_id, message));
         }
     }
 }
 
 pub fn ai_investigate(target_pos: Pos, 
                       monster_id: EntityId,
-                      data: &mut GameData,
+                      data: &GameData,
                       msg_log: &mut MsgLog,
                       config: &Config) {
     let player_id = data.find_by_name(EntityName::Player).unwrap();
 
     let monster_pos = data.entities.pos[&monster_id];
 
-    let player_in_fov = ai_is_in_fov(monster_id, player_id, data, config);
+    let player_in_fov = data.relation(monster_id, player_id, config) == Relation::Hostile &&
+                        ai_is_in_fov(monster_id, player_id, data, config);
 
     if player_in_fov {
         //let fov_path_clear = data.map.path_blocked_fov(monster_pos, player_pos).is_none();
@@ -170,18 +460,33 @@ pub fn ai_investigate(target_pos: Pos,
             msg_log.log(Msg::StateChange(monster_id, Behavior::Attacking(player_id)));
         } else {
             // if the monster cannot attack, just keep walking towards the target.
+            msg_log.log(Msg::TookTurn(monster_id));
             ai_move_towards_target(player_pos, monster_id, data, msg_log);
 
-            data.entities.took_turn[&monster_id] = true;
             msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(player_pos)));
         }
     } else { // the monster can't see the player
-        if let Some(Message::Sound(_entity_id, pos)) = data.entities.heard_sound(monster_id) {
+        if let (Some(target_id), true) = (ai_find_hostile(monster_id, data, config), data.entities.attack.get(&monster_id).is_some()) {
+            // an alerted monster turns on any enemy it comes across, not just the player
+            let target_pos = data.entities.pos[&target_id];
+            msg_log.log(Msg::FaceTowards(monster_id, target_pos));
+            msg_log.log(Msg::StateChange(monster_id, Behavior::Attacking(target_id)));
+        } else if let Some(body_id) = ai_find_body(monster_id, data, config) {
+            let body_pos = data.entities.pos[&body_id];
+            msg_log.log(Msg::FaceTowards(monster_id, body_pos));
+            msg_log.log(Msg::BodyDiscovered(monster_id, body_id));
+        } else if let Some(message@Message::Distraction(distraction_pos)) = data.entities.distracted(monster_id) {
+            msg_log.log(Msg::ReadMessage(monster_id, message));
+            msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(distraction_pos)));
+        } else if let Some(message@Message::Sound(_entity_id, pos)) = data.entities.heard_sound(monster_id) {
+            msg_log.log(Msg::ReadMessage(monster_id, message));
             msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(pos)));
         } else {
-            if target_pos == monster_pos { 
-                // monster reached their target position
-                data.entities.took_turn[&monster_id] = true;
+            let lost_interest = data.entities.interest.get(&monster_id) == Some(&0);
+
+            if target_pos == monster_pos || lost_interest {
+                // monster reached their target position, or gave up looking
+                msg_log.log(Msg::TookTurn(monster_id));
                 msg_log.log(Msg::StateChange(monster_id, Behavior::Idle));
             } else {
                 ai_move_towards_target(target_pos, monster_id, data, msg_log);
@@ -190,7 +495,75 @@ pub fn ai_investigate(target_pos: Pos,
     }
 }
 
-fn ai_move_towards_target(target_pos: Pos, monster_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog) {
+/// Look for the closest living entity within sight whose faction is hostile to the monster's.
+fn ai_find_hostile(monster_id: EntityId, data: &GameData, config: &Config) -> Option<EntityId> {
+    let monster_pos = data.entities.pos[&monster_id];
+
+    let mut closest: Option<(EntityId, i32)> = None;
+    for entity_id in data.entities.ids.iter() {
+        if *entity_id != monster_id &&
+           data.entities.status[entity_id].alive &&
+           data.entities.status[entity_id].knocked_out == 0 &&
+           data.relation(monster_id, *entity_id, config) == Relation::Hostile &&
+           ai_is_in_fov(monster_id, *entity_id, data, config) {
+            let dist = distance(monster_pos, data.entities.pos[entity_id]);
+            if closest.map_or(true, |(_id, closest_dist)| dist < closest_dist) {
+                closest = Some((*entity_id, dist));
+            }
+        }
+    }
+
+    return closest.map(|(entity_id, _dist)| entity_id);
+}
+
+/// Look for an alarm which hasn't been raised yet, close enough to run to.
+fn ai_find_alarm(monster_id: EntityId, data: &GameData, config: &Config) -> Option<EntityId> {
+    let monster_pos = data.entities.pos[&monster_id];
+
+    for entity_id in data.entities.ids.iter() {
+        if data.entities.name[entity_id] == EntityName::Alarm &&
+           data.entities.armed.get(entity_id) == Some(&true) &&
+           distance(monster_pos, data.entities.pos[entity_id]) <= config.alarm_distance {
+            return Some(*entity_id);
+        }
+    }
+
+    return None;
+}
+
+/// Run to an alarm and raise it, then go back to attacking the player. If someone
+/// else got there first, there is nothing left to do but attack.
+fn ai_raise_alarm(monster_id: EntityId, alarm_id: EntityId, data: &GameData, msg_log: &mut MsgLog) {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let monster_pos = data.entities.pos[&monster_id];
+    let alarm_pos = data.entities.pos[&alarm_id];
+
+    if data.entities.armed.get(&alarm_id) != Some(&true) {
+        msg_log.log(Msg::StateChange(monster_id, Behavior::Attacking(player_id)));
+    } else if distance_maximum(monster_pos, alarm_pos) <= 1 {
+        msg_log.log(Msg::AlarmRung(monster_id, alarm_id));
+        msg_log.log(Msg::TookTurn(monster_id));
+        msg_log.log(Msg::StateChange(monster_id, Behavior::Attacking(player_id)));
+    } else {
+        ai_move_towards_target(alarm_pos, monster_id, data, msg_log);
+    }
+}
+
+/// Look for a knocked out monster within sight.
+fn ai_find_body(monster_id: EntityId, data: &GameData, config: &Config) -> Option<EntityId> {
+    for entity_id in data.entities.ids.iter() {
+        if *entity_id != monster_id &&
+           data.entities.typ[entity_id] == EntityType::Enemy &&
+           data.entities.status[entity_id].knocked_out > 0 &&
+           data.is_in_fov(monster_id, *entity_id, config) {
+            return Some(*entity_id);
+        }
+    }
+
+    return None;
+}
+
+fn ai_move_towards_target(target_pos: Pos, monster_id: EntityId, data: &GameData, msg_log: &mut MsgLog) {
     let monster_pos = data.entities.pos[&monster_id];
 
     // if the monster has not reached its target, move towards the target.
@@ -288,9 +661,14 @@ pub fn ai_target_pos_cost(monster_id: EntityId,
         return None;
     }
 
+    if !data.ai_budget.try_search() {
+        return None;
+    }
+
     let must_reach = true;
-    let traps_block = true;
-    let path = data.path_between(monster_pos, check_pos, movement, must_reach, traps_block, None);
+    let traps_block = false;
+    let cost_fun = |start, prev, next, data: &GameData| ai_astar_cost(monster_id, start, prev, next, data);
+    let path = data.path_between(monster_pos, check_pos, movement, must_reach, traps_block, Some(&cost_fun));
 
     // paths contain the starting square, so less than 2 is no path at all
     if path.len() < 2 {
@@ -344,8 +722,12 @@ pub fn ai_can_hit_target(data: &mut GameData,
     // which contains the player, while path_blocked_move only checks the map
     // up to and including the player pos.
     let clear_path = data.clear_path_up_to(monster_pos, target_pos, traps_block);
-    let clear_map = data.entities.attack_type[&monster_id] == AttackType::Ranged ||
-                    data.map.path_blocked_move(monster_pos, target_pos).is_none();
+    let clear_map =
+        if data.entities.attack_type[&monster_id] == AttackType::Ranged {
+            data.map.line_of_sight(monster_pos, target_pos) != Visibility::Blocked
+        } else {
+            data.map.path_blocked_move(monster_pos, target_pos).is_none()
+        };
 
     if within_fov && clear_path && clear_map {
         // get all locations they can hit
@@ -402,6 +784,9 @@ pub fn ai_move_to_attack_pos(monster_id: EntityId,
     // if there is a solution, get the best one and use it
     if let Some(best_sol) = path_solutions.iter().min_by(|a, b| a.0.partial_cmp(&b.0).unwrap()) {
         new_pos = best_sol.1;
+    } else if data.ai_budget.is_tripped() {
+        // without the paths to compare, head for the target itself
+        new_pos = data.entities.pos[&target_id];
     }
 
     // step towards the closest location that lets us hit the target
@@ -410,39 +795,81 @@ pub fn ai_move_to_attack_pos(monster_id: EntityId,
 }
 
 // NOTE perhaps this should be merged into is_in_fov?
-pub fn ai_is_in_fov(monster_id: EntityId, target_id: EntityId, data: &mut GameData, config: &Config) -> bool {
+pub fn ai_is_in_fov(monster_id: EntityId, target_id: EntityId, data: &GameData, config: &Config) -> bool {
     let monster_pos = data.entities.pos[&monster_id];
     let target_pos = data.entities.pos[&target_id];
 
-    let within_fov = data.pos_in_fov(monster_id, target_pos, config);
-    let move_blocked = data.map.path_blocked_move(monster_pos, target_pos);
+    let mut within_fov = data.pos_in_fov(monster_id, target_pos, config);
+
+    // a target hidden in grass can only be spotted from closer by
+    let concealment = ai_concealment(target_id, data, config);
+    if concealment > 0 {
+        let view_dist = std::cmp::max(1, data.fov_radius(monster_id) - concealment);
+        within_fov &= distance_maximum(monster_pos, target_pos) <= view_dist;
+    }
 
-    if within_fov && move_blocked.is_some() {
-        let move_blocked = move_blocked.unwrap();
-        let blocked_by_short_wall = move_blocked.wall_type == Wall::ShortWall;
-        let target_stance = data.entities.stance[&target_id];
+    match data.map.line_of_sight(monster_pos, target_pos) {
+        Visibility::Clear => {
+            return within_fov;
+        }
 
-        return blocked_by_short_wall && target_stance != Stance::Crouching;
-    } else {
-        return within_fov;
+        Visibility::Partial(cover) => {
+            // a crouching target can hide behind enough cover, such as a short wall
+            let crouching = data.entities.stance[&target_id] == Stance::Crouching;
+            return within_fov && !(crouching && cover >= HIDING_COVER);
+        }
+
+        Visibility::Blocked => {
+            return false;
+        }
     }
 }
 
-fn ai_astar_cost(_start: Pos, _prev: Pos, next: Pos, data: &GameData) -> Option<i32> {
+/// How much harder a target is to spot because of where it is hiding.
+/// Crouching in grass conceals the target, while standing in it does not.
+pub fn ai_concealment(target_id: EntityId, data: &GameData, config: &Config) -> i32 {
+    let target_pos = data.entities.pos[&target_id];
+    let crouching = data.entities.stance.get(&target_id) == Some(&Stance::Crouching);
+
+    if crouching && data.map.is_within_bounds(target_pos) && data.map[target_pos].surface == Surface::Grass {
+        return config.grass_concealment;
+    }
+
+    return 0;
+}
+
+fn ai_astar_cost(monster_id: EntityId, _start: Pos, _prev: Pos, next: Pos, data: &GameData) -> Option<i32> {
     let mut cost = Some(1);
 
     // check for an armed trap in the list of entities on this tile
     for entity_id in data.has_entities(next) {
-        if data.entities.trap.get(&entity_id).is_some() &&
-           data.entities.armed.get(&entity_id) == Some(&true) {
+        if ai_avoids_trap(monster_id, entity_id, data) {
                // NOTE determined randomly. could be infinite, or smaller?
                cost = None;
         }
     }
 
+    // monsters that would sink never path through water
+    if data.map[next].tile_type == TileType::Water && data.sinks_in_water(monster_id) {
+        cost = None;
+    }
+
+    // walking through fire is possible, but only if there is no other way around
+    if cost.is_some() && data.map[next].surface == Surface::Burning {
+        cost = Some(FIRE_PATH_COST);
+    }
+
     return cost;
 }
 
+/// Monsters avoid armed traps that are visible, as well as hidden traps
+/// that they have seen go off.
+pub fn ai_avoids_trap(monster_id: EntityId, trap_id: EntityId, data: &GameData) -> bool {
+    let is_trap = data.entities.trap.get(&trap_id).is_some();
+    let armed = data.entities.armed.get(&trap_id) == Some(&true);
+    return is_trap && armed && !data.trap_hidden_from(monster_id, trap_id);
+}
+
 fn ai_astar_step(monster_id: EntityId,
                  target_pos: Pos,
                  must_reach: bool,
@@ -450,13 +877,49 @@ fn ai_astar_step(monster_id: EntityId,
     let reach = data.entities.movement[&monster_id];
     let monster_pos = data.entities.pos[&monster_id];
 
-    let traps_block = true;
+    let traps_block = false;
+
+    if !data.ai_budget.try_search() {
+        return ai_budget_path(monster_id, target_pos, data);
+    }
 
-    let path = data.path_between(monster_pos, target_pos, reach, must_reach, traps_block, Some(ai_astar_cost));
+    let cost_fun = |start, prev, next, data: &GameData| ai_astar_cost(monster_id, start, prev, next, data);
+    let path = data.path_between(monster_pos, target_pos, reach, must_reach, traps_block, Some(&cost_fun));
+    data.ai_budget.remember(monster_id, &path);
 
     return path;
 }
 
+/// The path taken when the AI budget for this turn is spent. This is the rest of the
+/// last path found, if the monster is still on it and it still leads closer to the
+/// target, or otherwise a single step straight toward the target if nothing is in the way.
+fn ai_budget_path(monster_id: EntityId, target_pos: Pos, data: &GameData) -> Vec<Pos> {
+    let monster_pos = data.entities.pos[&monster_id];
+
+    let can_step = |next_pos: Pos| {
+        return data.has_blocking_entity(next_pos).is_none() && data.map.path_blocked_move(monster_pos, next_pos).is_none();
+    };
+
+    if let Some(path) = data.ai_budget.paths.lock().unwrap().get(&monster_id) {
+        if let Some(index) = path.iter().position(|pos| *pos == monster_pos) {
+            let rest = &path[index..];
+            let closer = rest.last().map_or(false, |end| distance(*end, target_pos) < distance(monster_pos, target_pos));
+
+            if rest.len() > 1 && closer && can_step(rest[1]) {
+                return rest.to_vec();
+            }
+        }
+    }
+    data.ai_budget.forget(monster_id);
+
+    let next_pos = add_pos(monster_pos, step_towards(monster_pos, target_pos));
+    if next_pos != monster_pos && can_step(next_pos) {
+        return vec!(monster_pos, next_pos);
+    }
+
+    return vec!(monster_pos);
+}
+
 fn ai_take_astar_step(monster_id: EntityId,
                       target_pos: Pos,
                       must_reach: bool,
@@ -0,0 +1,247 @@
+use std::fmt;
+
+use oorandom::Rand32;
+
+use serde::{Serialize, Deserialize};
+
+use crate::types::*;
+use crate::map::Visibility;
+use crate::movement::{MoveMode, AttackType, Direction};
+use crate::messaging::*;
+use crate::config::Config;
+use crate::constants::*;
+use crate::utils::{rng_range_u32, is_behind, is_in_front};
+
+
+/// The kind of damage an attack does. Blunt attacks crush shields rather than being
+/// blocked by them, and piercing attacks ignore some of a target's defense.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DamageType {
+    Blunt,
+    Pierce,
+    Slash,
+}
+
+impl fmt::Display for DamageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DamageType::Blunt => write!(f, "blunt"),
+            DamageType::Pierce => write!(f, "pierce"),
+            DamageType::Slash => write!(f, "slash"),
+        }
+    }
+}
+
+/// How a single attack turned out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttackOutcome {
+    Missed,
+    Hit(Hp),
+    Critical(Hp),
+    ShieldBlocked,
+    ShieldCrushed(EntityId),
+}
+
+/// The kind of damage an entity does with its attacks, given what it is holding.
+pub fn damage_type(entity_id: EntityId, data: &GameData) -> DamageType {
+    if data.using(entity_id, Item::Hammer).is_some() {
+        return DamageType::Blunt;
+    } else if data.using(entity_id, Item::Sword).is_some() {
+        return DamageType::Slash;
+    } else if data.using(entity_id, Item::Dagger).is_some() {
+        return DamageType::Pierce;
+    } else if data.entities.attack_type.get(&entity_id) == Some(&AttackType::Ranged) {
+        return DamageType::Pierce;
+    } else if data.entities.typ[&entity_id] == EntityType::Player ||
+              data.entities.name[&entity_id] == EntityName::Rook {
+        return DamageType::Blunt;
+    }
+
+    return DamageType::Slash;
+}
+
+/// The percent chance of an attack landing. Targets which can see the attack coming are
+/// harder to hit, unless the attacker is sneaking, and ranged attacks can be stopped by cover.
+pub fn hit_chance(attacker: EntityId, target: EntityId, data: &GameData, config: &Config) -> u32 {
+    let mut chance: i32 = 100;
+
+    if !is_behind(data, attacker, target) && data.is_in_fov(target, attacker, config) {
+        chance -= config.hit_chance_facing_penalty as i32;
+
+        if data.entities.move_mode.get(&attacker) == Some(&MoveMode::Sneak) {
+            chance += config.hit_chance_stealth_bonus as i32;
+        }
+    }
+
+    if data.entities.attack_type.get(&attacker) == Some(&AttackType::Ranged) {
+        let attacker_pos = data.entities.pos[&attacker];
+        let target_pos = data.entities.pos[&target];
+        match data.map.line_of_sight(attacker_pos, target_pos) {
+            Visibility::Clear => {},
+            Visibility::Partial(cover) => chance = chance * (100 - cover as i32) / 100,
+            Visibility::Blocked => chance = 0,
+        }
+    }
+
+    return std::cmp::max(0, std::cmp::min(100, chance)) as u32;
+}
+
+/// The damage done by a hit, after the target's defense. Piercing attacks ignore some
+/// of that defense, and critical hits do extra damage.
+pub fn attack_damage(attacker: EntityId, target: EntityId, damage_type: DamageType, critical: bool, data: &GameData) -> Hp {
+    let power = data.entities.fighter.get(&attacker).map_or(0, |f| f.power);
+    let mut defense = data.entities.fighter.get(&target).map_or(0, |f| f.defense);
+
+    if damage_type == DamageType::Pierce {
+        defense -= defense * PIERCE_DEFENSE_IGNORED / 100;
+    }
+
+    let mut damage = power - defense;
+    if critical {
+        damage *= CRITICAL_MULTIPLIER;
+    }
+
+    return damage;
+}
+
+/// Decide how an attack turns out, without applying it. The rng is only used when
+/// the attack could miss.
+pub fn roll_attack(attacker: EntityId, target: EntityId, data: &GameData, rng: &mut Rand32, config: &Config) -> AttackOutcome {
+    let chance = hit_chance(attacker, target, data, config);
+    if chance < 100 && rng_range_u32(rng, 0, 100) >= chance {
+        return AttackOutcome::Missed;
+    }
+
+    let damage_type = damage_type(attacker, data);
+
+    // a shield only protects against attacks from the direction its holder is facing
+    if let (Some(shield_id), true) = (data.using(target, Item::Shield), is_in_front(data, attacker, target)) {
+        if damage_type == DamageType::Blunt {
+            return AttackOutcome::ShieldCrushed(shield_id);
+        } else {
+            return AttackOutcome::ShieldBlocked;
+        }
+    }
+
+    // attacks from behind are critical hits
+    let critical = is_behind(data, attacker, target);
+    let damage = attack_damage(attacker, target, damage_type, critical, data);
+
+    if critical {
+        return AttackOutcome::Critical(damage);
+    } else {
+        return AttackOutcome::Hit(damage);
+    }
+}
+
+/// A hammer blow damages whatever it hits, and if that survives, stuns it and knocks it back
+/// a tile. Being knocked into a wall or another entity does extra damage.
+pub fn hammer_hit(entity: EntityId, target: EntityId, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
+    data.entities.take_damage(target, HAMMER_DAMAGE);
+    data.entities.messages[&target].push(Message::Attack(entity));
+    msg_log.log(Msg::Attack(entity, target, HAMMER_DAMAGE));
+
+    if !data.entities.status[&target].alive {
+        data.entities.blocks[&target] = false;
+        msg_log.log(Msg::Killed(Some(entity), target, HAMMER_DAMAGE));
+        return;
+    }
+
+    msg_log.log(Msg::Froze(target, HAMMER_STUN_TURNS));
+
+    let diff = data.entities.pos[&target] - data.entities.pos[&entity];
+    if let Some(direction) = Direction::from_dxy(diff.x.signum(), diff.y.signum()) {
+        let damage = config.knockback_damage + HAMMER_KNOCKBACK_DAMAGE;
+        msg_log.log(Msg::Knockback(entity, target, direction, damage));
+    }
+}
+
+/// A stealth kill takes the target down without a struggle, so it makes little noise.
+pub fn stealth_kill(entity: EntityId, target: EntityId, data: &mut GameData, msg_log: &mut MsgLog) {
+    let damage = data.entities.fighter.get(&target).map_or(0, |f| f.hp);
+
+    data.entities.status[&target].alive = false;
+    data.entities.blocks[&target] = false;
+
+    msg_log.log(Msg::StealthKill(entity, target));
+    msg_log.log(Msg::Killed(Some(entity), target, damage));
+}
+
+/// Using a weapon or shield wears it down by one use. The log warns when an item is
+/// nearly worn out, and once it has no uses left it breaks. An item without a durability
+/// breaks on its first use.
+pub fn wear_item(entity: EntityId, item_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog) {
+    let durability = data.entities.durability.get(&item_id).map_or(0, |durability| *durability);
+
+    if durability > 1 {
+        data.entities.durability[&item_id] = durability - 1;
+
+        if durability - 1 <= DURABILITY_WARNING {
+            msg_log.log(Msg::ItemWorn(entity, item_id, durability - 1));
+        }
+    } else {
+        data.used_up_item(entity, item_id);
+        msg_log.log(Msg::ItemBroke(entity, item_id));
+    }
+}
+
+pub fn attack(entity: EntityId, target: EntityId, data: &mut GameData, msg_log: &mut MsgLog, rng: &mut Rand32, config: &Config) {
+    if data.using(entity, Item::Hammer).is_some() {
+        hammer_hit(entity, target, data, msg_log, config);
+        return;
+    }
+
+    let outcome = roll_attack(entity, target, data, rng, config);
+    match outcome {
+        AttackOutcome::Missed => {
+            msg_log.log(Msg::AttackMissed(entity, target));
+        }
+
+        AttackOutcome::ShieldBlocked => {
+            data.entities.messages[&target].push(Message::Attack(entity));
+            msg_log.log(Msg::ShieldBlocked(entity, target));
+
+            if let Some(shield_id) = data.using(target, Item::Shield) {
+                wear_item(target, shield_id, data, msg_log);
+            }
+
+            // blocking an attack knocks the blocker back, away from the attacker
+            let diff = data.entities.pos[&target] - data.entities.pos[&entity];
+            if let Some(direction) = Direction::from_dxy(diff.x.signum(), diff.y.signum()) {
+                msg_log.log(Msg::Knockback(entity, target, direction, config.knockback_damage));
+            }
+        }
+
+        AttackOutcome::ShieldCrushed(shield_id) => {
+            data.used_up_item(target, shield_id);
+            data.entities.messages[&target].push(Message::Attack(entity));
+
+            msg_log.log(Msg::ShieldCrushed(entity, target));
+        }
+
+        AttackOutcome::Hit(damage) | AttackOutcome::Critical(damage) => {
+            if data.using(entity, Item::Sword).is_some() {
+                msg_log.log(Msg::Attack(entity, target, SWORD_DAMAGE));
+                msg_log.log(Msg::Killed(Some(entity), target, SWORD_DAMAGE));
+            } else if damage > 0 && data.entities.status[&target].alive {
+                data.entities.take_damage(target, damage);
+
+                if let AttackOutcome::Critical(_) = outcome {
+                    msg_log.log(Msg::CriticalHit(entity, target, damage));
+                } else {
+                    msg_log.log(Msg::Attack(entity, target, damage));
+                }
+
+                // TODO consider moving this to the Attack msg
+                if data.entities.fighter[&target].hp <= 0 {
+                    data.entities.status[&target].alive = false;
+                    data.entities.blocks[&target] = false;
+
+                    msg_log.log(Msg::Killed(Some(entity), target, damage));
+                }
+
+                data.entities.messages[&target].push(Message::Attack(entity));
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::ops::{Index, IndexMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Serialize, Deserialize};
+
+
+/// An entity id is a generational index packed into a u64- the low 32 bits are the
+/// slot the entity's components are stored in, and the high 32 bits are the entity's
+/// generation. Slots are reused once an entity is removed, but each use of a slot gets
+/// a new generation, so an id that is held onto after its entity is removed finds
+/// nothing instead of finding whatever entity took its slot.
+pub type EntityId = u64;
+
+// generations are shared by every set of entities, so ids stay unique between
+// levels, and between the entities built up separately during level generation.
+static GENERATION_COUNT: AtomicU64 = AtomicU64::new(0);
+
+pub fn entity_index(entity_id: EntityId) -> usize {
+    return (entity_id & 0xFFFF_FFFF) as usize;
+}
+
+pub fn entity_generation(entity_id: EntityId) -> u32 {
+    return (entity_id >> 32) as u32;
+}
+
+/// Hands out entity ids, reusing the slots of removed entities.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct EntityAllocator {
+    next_index: u32,
+    free: Vec<u32>,
+}
+
+impl EntityAllocator {
+    pub fn allocate(&mut self) -> EntityId {
+        let index =
+            if let Some(index) = self.free.pop() {
+                index
+            } else {
+                self.next_index += 1;
+                self.next_index - 1
+            };
+
+        let generation = GENERATION_COUNT.fetch_add(1, Ordering::SeqCst) as u32;
+
+        return ((generation as u64) << 32) | index as u64;
+    }
+
+    pub fn free(&mut self, entity_id: EntityId) {
+        self.free.push(entity_index(entity_id) as u32);
+    }
+}
+
+/// The storage for one component, with a slot for each entity index. Each slot
+/// keeps the full id of the entity that owns it, so looking up a removed entity's
+/// id misses rather than returning the component of an entity that reused its slot.
+///
+/// Iteration goes in slot order, and since slots are reused this is not the order
+/// entities were created in. Anything that needs creation order, such as turn order
+/// or drawing, should walk `Entities::ids` instead.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompStore<T> {
+    slots: Vec<Option<(EntityId, T)>>,
+    len: usize,
+}
+
+impl<T> Default for CompStore<T> {
+    fn default() -> CompStore<T> {
+        return CompStore { slots: Vec::new(), len: 0 };
+    }
+}
+
+impl<T> CompStore<T> {
+    pub fn new() -> CompStore<T> {
+        return CompStore::default();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.len;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+
+    pub fn contains_key(&self, entity_id: &EntityId) -> bool {
+        return self.get(entity_id).is_some();
+    }
+
+    pub fn get(&self, entity_id: &EntityId) -> Option<&T> {
+        match self.slots.get(entity_index(*entity_id)) {
+            Some(Some((slot_id, comp))) if slot_id == entity_id => Some(comp),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, entity_id: &EntityId) -> Option<&mut T> {
+        match self.slots.get_mut(entity_index(*entity_id)) {
+            Some(Some((slot_id, comp))) if slot_id == entity_id => Some(comp),
+            _ => None,
+        }
+    }
+
+    /// Set an entity's component, returning the component it replaces, if any.
+    pub fn insert(&mut self, entity_id: EntityId, comp: T) -> Option<T> {
+        let index = entity_index(entity_id);
+        if index >= self.slots.len() {
+            self.slots.resize_with(index + 1, || None);
+        }
+
+        let old = self.slots[index].replace((entity_id, comp));
+        match old {
+            Some((old_id, old_comp)) if old_id == entity_id => {
+                return Some(old_comp);
+            }
+
+            Some(_) => {
+                // a removed entity's component was left in the slot
+                return None;
+            }
+
+            None => {
+                self.len += 1;
+                return None;
+            }
+        }
+    }
+
+    pub fn remove(&mut self, entity_id: &EntityId) -> Option<T> {
+        if !self.contains_key(entity_id) {
+            return None;
+        }
+
+        self.len -= 1;
+        return self.slots[entity_index(*entity_id)].take().map(|(_id, comp)| comp);
+    }
+
+    /// Get an entity's component, inserting one made by the given function if it
+    /// does not have one yet.
+    pub fn get_or_insert_with<F: FnOnce() -> T>(&mut self, entity_id: EntityId, make_comp: F) -> &mut T {
+        if !self.contains_key(&entity_id) {
+            self.insert(entity_id, make_comp());
+        }
+
+        return self.get_mut(&entity_id).unwrap();
+    }
+
+    /// Keep only the components for which the given function returns true.
+    pub fn retain<F: FnMut(&EntityId, &mut T) -> bool>(&mut self, mut keep: F) {
+        for slot in self.slots.iter_mut() {
+            if let Some((entity_id, comp)) = slot {
+                if !keep(entity_id, comp) {
+                    *slot = None;
+                    self.len -= 1;
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.len = 0;
+    }
+
+    /// Each entity with this component, along with its component, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item=(&EntityId, &T)> {
+        return self.slots.iter().filter_map(|slot| slot.as_ref().map(|(entity_id, comp)| (entity_id, comp)));
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item=(&EntityId, &mut T)> {
+        return self.slots.iter_mut().filter_map(|slot| slot.as_mut().map(|(entity_id, comp)| (&*entity_id, comp)));
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item=&EntityId> {
+        return self.iter().map(|(entity_id, _comp)| entity_id);
+    }
+
+    pub fn values(&self) -> impl Iterator<Item=&T> {
+        return self.iter().map(|(_entity_id, comp)| comp);
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item=&mut T> {
+        return self.iter_mut().map(|(_entity_id, comp)| comp);
+    }
+}
+
+impl<T> Index<&EntityId> for CompStore<T> {
+    type Output = T;
+
+    fn index(&self, entity_id: &EntityId) -> &T {
+        return self.get(entity_id).unwrap_or_else(|| panic!("Entity {} does not have this component!", entity_id));
+    }
+}
+
+impl<T> IndexMut<&EntityId> for CompStore<T> {
+    fn index_mut(&mut self, entity_id: &EntityId) -> &mut T {
+        return self.get_mut(entity_id).unwrap_or_else(|| panic!("Entity {} does not have this component!", entity_id));
+    }
+}
+
+#[test]
+pub fn test_comp_store_generations() {
+    let mut allocator = EntityAllocator::default();
+    let mut store = CompStore::new();
+
+    let first = allocator.allocate();
+    let second = allocator.allocate();
+    store.insert(first, "first");
+    store.insert(second, "second");
+    assert_eq!(2, store.len());
+
+    // a removed entity's slot is reused, but its id no longer finds anything
+    store.remove(&first);
+    allocator.free(first);
+    let third = allocator.allocate();
+    assert_eq!(entity_index(first), entity_index(third));
+    assert_ne!(first, third);
+
+    store.insert(third, "third");
+    assert_eq!(None, store.get(&first));
+    assert_eq!(Some(&"third"), store.get(&third));
+    assert_eq!(vec!(&"third", &"second"), store.values().collect::<Vec<&&str>>());
+
+    store.retain(|_entity_id, name| *name != "second");
+    assert_eq!(1, store.len());
+    assert!(!store.contains_key(&second));
+}
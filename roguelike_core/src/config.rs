@@ -1,16 +1,199 @@
 use std::fs::File;
 use std::io::Read;
+use std::collections::BTreeMap;
+use std::fmt;
 
 use crate::types::*;
 use crate::map::*;
+use crate::constants::CONFIG_VERSION;
 
 
 use serde_derive::*;
 use serde_yaml;
 
 
+/// Where to send the text narration of the game, for screen readers.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum NarrationOutput {
+    Off,
+    Stdout,
+    Socket(String), // address to connect to, such as 127.0.0.1:9000
+}
+
+impl Default for NarrationOutput {
+    fn default() -> NarrationOutput {
+        return NarrationOutput::Off;
+    }
+}
+
+/// The names of the colors in the config, which color palettes can replace.
+pub const COLOR_NAMES: &[&str] = &[
+    "color_dark_brown",
+    "color_medium_brown",
+    "color_light_green",
+    "color_tile_blue_light",
+    "color_tile_blue_dark",
+    "color_light_brown",
+    "color_ice_blue",
+    "color_dark_blue",
+    "color_very_dark_blue",
+    "color_orange",
+    "color_red",
+    "color_medium_grey",
+    "color_mint_green",
+    "color_blueish_grey",
+    "color_pink",
+    "color_rose_red",
+    "color_light_orange",
+    "color_bone_white",
+    "color_warm_grey",
+    "color_soft_green",
+    "color_light_grey",
+    "color_shadow",
+    "color_background",
+    "color_tile",
+    "color_wall",
+    "color_cracked_wall",
+    "color_water",
+    "color_grass",
+    "color_rubble",
+    "color_grid",
+    "color_fog",
+    "color_bar_outline",
+    "color_sound",
+    "color_debris",
+    "color_direction",
+    "color_attack_effect",
+    "color_stealth_kill",
+    "color_overlay_sound",
+    "color_overlay_enemy_fov",
+    "color_overlay_path",
+    "color_overlay_selection",
+    "color_overlay_debug",
+    "color_overlay_vision_idle",
+    "color_overlay_vision_investigating",
+    "color_overlay_vision_attacking",
+    "color_overlay_threat",
+];
+
+/// A named set of colors which replace the config's colors of the same name, such as
+/// colors which stay distinct for players with red-green colorblindness.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct ColorPalette {
+    pub name: String,
+    pub colors: BTreeMap<String, Color>,
+}
+
+/// One of the config's number or true/false settings, as it is edited while the game runs.
+#[derive(Clone, Copy, Debug)]
+pub enum ConfigValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+}
+
+impl ConfigValue {
+    fn from_yaml(value: &serde_yaml::Value) -> Option<ConfigValue> {
+        match value {
+            serde_yaml::Value::Bool(value) => return Some(ConfigValue::Bool(*value)),
+            serde_yaml::Value::Number(number) if number.is_f64() => return number.as_f64().map(ConfigValue::Float),
+            serde_yaml::Value::Number(number) => return number.as_i64().map(ConfigValue::Int),
+            _ => return None,
+        }
+    }
+
+    fn to_yaml(&self) -> serde_yaml::Value {
+        match self {
+            ConfigValue::Bool(value) => return serde_yaml::Value::Bool(*value),
+            ConfigValue::Int(value) => return serde_yaml::Value::Number((*value).into()),
+            ConfigValue::Float(value) => return serde_yaml::Value::Number((*value).into()),
+        }
+    }
+}
+
+// most of the config's numbers are f32s, so floats are compared and written at that precision,
+// keeping a setting of 0.1 from being saved as 0.10000000149011612. A float setting may be
+// written without a decimal point, so whole numbers compare equal to floats.
+impl PartialEq for ConfigValue {
+    fn eq(&self, other: &ConfigValue) -> bool {
+        match (self, other) {
+            (ConfigValue::Bool(value), ConfigValue::Bool(other)) => return value == other,
+            (ConfigValue::Int(value), ConfigValue::Int(other)) => return value == other,
+            (ConfigValue::Float(value), ConfigValue::Float(other)) => return *value as f32 == *other as f32,
+            (ConfigValue::Int(value), ConfigValue::Float(other)) => return *value as f32 == *other as f32,
+            (ConfigValue::Float(value), ConfigValue::Int(other)) => return *value as f32 == *other as f32,
+            _ => return false,
+        }
+    }
+}
+
+impl fmt::Display for ConfigValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigValue::Bool(value) => write!(f, "{}", value),
+            ConfigValue::Int(value) => write!(f, "{}", value),
+            ConfigValue::Float(value) if *value as f32 as f64 == *value => write!(f, "{:?}", *value as f32),
+            ConfigValue::Float(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+/// How hard a run is, chosen before it starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Custom,
+}
+
+impl Difficulty {
+    /// The difficulty after this one, for cycling through them on the main menu.
+    pub fn next(&self) -> Difficulty {
+        match self {
+            Difficulty::Easy => return Difficulty::Normal,
+            Difficulty::Normal => return Difficulty::Hard,
+            Difficulty::Hard => return Difficulty::Custom,
+            Difficulty::Custom => return Difficulty::Easy,
+        }
+    }
+}
+
+impl Default for Difficulty {
+    fn default() -> Difficulty {
+        return Difficulty::Normal;
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "easy"),
+            Difficulty::Normal => write!(f, "normal"),
+            Difficulty::Hard => write!(f, "hard"),
+            Difficulty::Custom => write!(f, "custom"),
+        }
+    }
+}
+
+/// The settings a difficulty changes. Each one which is given replaces the config's
+/// setting of the same name, leaving the rest as they are.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct DifficultyConfig {
+    pub spawn_budget: Option<usize>,
+    pub spawn_budget_per_level: Option<usize>,
+    pub fov_radius_monster: Option<i32>,
+    pub investigate_turns: Option<usize>,
+    pub player_health_bonus: Option<i32>,
+    pub player_stamina: Option<u32>,
+}
+
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    pub version: u32,
     pub color_dark_brown: Color,
     pub color_medium_brown: Color,
     pub color_light_green: Color,
@@ -33,10 +216,36 @@ pub struct Config {
     pub color_soft_green: Color,
     pub color_light_grey: Color,
     pub color_shadow: Color,
-    pub load_map_file_every_frame: bool,
+    pub color_background: Color,
+    pub color_tile: Color,
+    pub color_wall: Color,
+    pub color_cracked_wall: Color,
+    pub color_water: Color,
+    pub color_grass: Color,
+    pub color_rubble: Color,
+    pub color_grid: Color,
+    pub color_fog: Color,
+    pub color_bar_outline: Color,
+    pub color_sound: Color,
+    pub color_debris: Color,
+    pub color_direction: Color,
+    pub color_attack_effect: Color,
+    pub color_stealth_kill: Color,
+    pub color_overlay_sound: Color,
+    pub color_overlay_enemy_fov: Color,
+    pub color_overlay_path: Color,
+    pub color_overlay_selection: Color,
+    pub color_overlay_debug: Color,
+    pub color_overlay_vision_idle: Color,
+    pub color_overlay_vision_investigating: Color,
+    pub color_overlay_vision_attacking: Color,
+    pub color_overlay_threat: Color,
+    pub reload_map_file: bool,
     pub tile_noise_scaler: f64,
     pub highlight_player_move: u8,
     pub highlight_alpha_attack: u8,
+    pub highlight_alpha_vision: u8,
+    pub highlight_alpha_threat: u8,
     pub sound_alpha: u8,
     pub grid_alpha: u8,
     pub grid_alpha_visible: u8,
@@ -47,6 +256,9 @@ pub struct Config {
     pub draw_mouse_line: bool,
     pub draw_star_path: bool,
     pub frame_rate: usize,
+    pub wait_for_animations: bool,
+    pub clip_seconds: f32,
+    pub clip_width: u32,
     pub item_throw_speed: f32,
     pub key_speed: f32,
     pub player_attack_speed: f32,
@@ -58,26 +270,68 @@ pub struct Config {
     pub swap_radius: usize,
     pub fog_of_war: bool,
     pub player_health: i32,
+    pub player_health_bonus: i32,
+    pub player_stamina: u32,
+    pub stamina_run_cost: u32,
+    pub stamina_vault_cost: u32,
+    pub stamina_wall_kick_cost: u32,
+    pub stamina_regen: u32,
     pub explored_alpha: u8,
     pub fov_edge_alpha: u8,
     pub sound_rubble_radius: usize,
     pub sound_golem_idle_radius: usize,
     pub sound_grass_radius: usize,
+    pub sound_water_radius: usize,
     pub sound_radius_crushed: usize,
     pub sound_radius_attack: usize,
+    pub sound_radius_stealth_kill: usize,
     pub sound_radius_trap: usize,
     pub sound_radius_monster: usize,
     pub sound_radius_stone: usize,
+    pub sound_radius_noisemaker: usize,
+    pub noisemaker_fuse: usize,
+    pub durability_dagger: u32,
+    pub durability_sword: u32,
+    pub durability_hammer: u32,
+    pub durability_shield: u32,
+    pub lockpick_turns: usize,
+    pub coins_per_pile: usize,
+    pub carry_capacity_base: usize,
+    pub sound_encumbered_radius: usize,
+    pub sound_radius_smash: usize,
+    pub sound_radius_grapple: usize,
+    pub grapple_range: usize,
     pub sound_radius_player: usize,
     pub sound_radius_hammer: usize,
+    pub sound_radius_wall_destroyed: usize,
     pub freeze_trap_radius: usize,
+    pub sound_radius_tripwire: usize,
+    pub sound_radius_mechanism: usize,
+    pub sound_radius_alarm: usize,
+    pub fire_spread_chance: f32,
+    pub fire_burn_out_chance: f32,
+    pub gas_spread: f32,
+    pub gas_decay: f32,
+    pub sound_decay: f32,
+    pub gas_threshold: f32,
+    pub smoke_bomb_amount: f32,
+    pub poison_bomb_amount: f32,
+    pub trap_disarm_fail_chance: f32,
     pub push_stun_turns: usize,
+    pub knockback_damage: i32,
+    pub knockout_turns: usize,
     pub overlay_directions: bool,
     pub overlay_player_fov: bool,
     pub overlay_fov_alg: bool,
     pub overlay_floodfill: bool,
     pub fov_radius_monster: i32,
     pub fov_radius_player: i32,
+    pub investigate_turns: usize,
+    pub ai_path_budget: usize,
+    pub ai_parallel: bool,
+    pub grass_concealment: i32,
+    pub hit_chance_facing_penalty: u32,
+    pub hit_chance_stealth_bonus: u32,
     pub sound_radius_sneak: usize,
     pub sound_radius_walk: usize,
     pub sound_radius_run: usize,
@@ -90,25 +344,359 @@ pub struct Config {
     pub cursor_long: i32,
     pub repeat_delay: f32,
     pub write_map_distribution: bool,
+    pub write_wfc_map: bool,
+    pub wfc_map_file: String,
     pub print_key_log: bool,
     pub idle_animations: bool,
     pub recording: bool,
+    pub telemetry: bool,
+    pub telemetry_file: String,
+    pub ghost_replay: bool,
+    pub ghost_alpha: u8,
     pub fire_speed: f32,
     pub beam_duration: usize,
+    pub debris_duration: usize,
+    pub stealth_kill_duration: usize,
     pub draw_directional_arrow: bool,
+    pub undo_history: usize,
+    pub editor_file: String,
+    pub rooms_max: usize,
+    pub room_min_size: i32,
+    pub room_max_size: i32,
+    pub cave_fill_chance: f32,
+    pub cave_iterations: usize,
+    pub map_escape_routes: usize,
+    pub map_generation_attempts: usize,
+    pub spawn_budget: usize,
+    pub spawn_budget_per_level: usize,
+    pub spawn_noise_per_budget: usize,
+    pub spawn_distance_player: i32,
+    pub spawn_distance_path: i32,
+    pub alarm_distance: i32,
+    pub sound_radius_alarm_bell: usize,
+    pub alarm_wave_monster: EntityName,
+    pub alarm_wave_size: usize,
+    pub alarm_wave_delay: usize,
+    pub alarm_wave_interval: usize,
+    pub faction_hostility: Vec<(Faction, Faction)>,
+    pub critter_disturb_distance: i32,
+    pub critter_flee_distance: i32,
+    pub sound_radius_rat: usize,
+    pub sound_radius_bird: usize,
+    pub async_map_generation: bool,
+    pub objectives: Vec<ObjectiveType>,
+    pub survive_turns: usize,
+    pub win_level: usize,
+    pub palettes: Vec<ColorPalette>,
+    pub difficulty: Difficulty,
+    pub difficulties: BTreeMap<Difficulty, DifficultyConfig>,
+    pub narration: NarrationOutput,
+    pub locale: String,
+    pub text_font: String,
+    pub text_font_size: u16,
 }
 
 impl Config {
     pub fn from_file(file_name: &str) -> Config {
+        match Config::load(file_name) {
+            Ok(config) => return config,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Read and validate a config file, describing what is wrong with it if it can't be used.
+    pub fn load(file_name: &str) -> Result<Config, String> {
         let mut file =
-            File::open(file_name).expect(&format!("Could not open/parse config file {}", file_name));
+            File::open(file_name).map_err(|err| format!("Could not open config file {}: {}", file_name, err))?;
         let mut config_string = String::new();
         file.read_to_string(&mut config_string)
-            .expect(&format!("Could not read contents of {}", file_name));
+            .map_err(|err| format!("Could not read contents of {}: {}", file_name, err))?;
+
+        return Config::from_str(&config_string).map_err(|err| format!("{}: {}", file_name, err));
+    }
+
+    /// Parse a config, checking that it is the current version of the config's layout,
+    /// and that it has every field with nothing misspelled or left over. Errors give the
+    /// line they were found on, when there is one.
+    pub fn from_str(config_string: &str) -> Result<Config, String> {
+        let value: serde_yaml::Value =
+            serde_yaml::from_str(config_string).map_err(|err| describe_yaml_error(config_string, err))?;
+
+        match value.get("version").and_then(|version| version.as_u64()) {
+            Some(version) if version == CONFIG_VERSION as u64 => {
+            }
+
+            Some(version) => {
+                return Err(format!("config is version {}, but version {} is expected", version, CONFIG_VERSION));
+            }
+
+            None => {
+                return Err(format!("config has no version, but version {} is expected", CONFIG_VERSION));
+            }
+        }
+
+        let config: Config =
+            serde_yaml::from_str(config_string).map_err(|err| describe_yaml_error(config_string, err))?;
+
+        for palette in config.palettes.iter() {
+            for name in palette.colors.keys() {
+                if !COLOR_NAMES.contains(&name.as_str()) {
+                    return Err(format!("palette {} has unknown color {}", palette.name, name));
+                }
+            }
+        }
+
+        return Ok(config);
+    }
+
+    pub fn color(&self, name: &str) -> Option<Color> {
+        let mut config = self.clone();
+        return config.color_mut(name).map(|color| *color);
+    }
+
+    pub fn color_mut(&mut self, name: &str) -> Option<&mut Color> {
+        match name {
+            "color_dark_brown" => Some(&mut self.color_dark_brown),
+            "color_medium_brown" => Some(&mut self.color_medium_brown),
+            "color_light_green" => Some(&mut self.color_light_green),
+            "color_tile_blue_light" => Some(&mut self.color_tile_blue_light),
+            "color_tile_blue_dark" => Some(&mut self.color_tile_blue_dark),
+            "color_light_brown" => Some(&mut self.color_light_brown),
+            "color_ice_blue" => Some(&mut self.color_ice_blue),
+            "color_dark_blue" => Some(&mut self.color_dark_blue),
+            "color_very_dark_blue" => Some(&mut self.color_very_dark_blue),
+            "color_orange" => Some(&mut self.color_orange),
+            "color_red" => Some(&mut self.color_red),
+            "color_medium_grey" => Some(&mut self.color_medium_grey),
+            "color_mint_green" => Some(&mut self.color_mint_green),
+            "color_blueish_grey" => Some(&mut self.color_blueish_grey),
+            "color_pink" => Some(&mut self.color_pink),
+            "color_rose_red" => Some(&mut self.color_rose_red),
+            "color_light_orange" => Some(&mut self.color_light_orange),
+            "color_bone_white" => Some(&mut self.color_bone_white),
+            "color_warm_grey" => Some(&mut self.color_warm_grey),
+            "color_soft_green" => Some(&mut self.color_soft_green),
+            "color_light_grey" => Some(&mut self.color_light_grey),
+            "color_shadow" => Some(&mut self.color_shadow),
+            "color_background" => Some(&mut self.color_background),
+            "color_tile" => Some(&mut self.color_tile),
+            "color_wall" => Some(&mut self.color_wall),
+            "color_cracked_wall" => Some(&mut self.color_cracked_wall),
+            "color_water" => Some(&mut self.color_water),
+            "color_grass" => Some(&mut self.color_grass),
+            "color_rubble" => Some(&mut self.color_rubble),
+            "color_grid" => Some(&mut self.color_grid),
+            "color_fog" => Some(&mut self.color_fog),
+            "color_bar_outline" => Some(&mut self.color_bar_outline),
+            "color_sound" => Some(&mut self.color_sound),
+            "color_debris" => Some(&mut self.color_debris),
+            "color_direction" => Some(&mut self.color_direction),
+            "color_attack_effect" => Some(&mut self.color_attack_effect),
+            "color_stealth_kill" => Some(&mut self.color_stealth_kill),
+            "color_overlay_sound" => Some(&mut self.color_overlay_sound),
+            "color_overlay_enemy_fov" => Some(&mut self.color_overlay_enemy_fov),
+            "color_overlay_path" => Some(&mut self.color_overlay_path),
+            "color_overlay_selection" => Some(&mut self.color_overlay_selection),
+            "color_overlay_debug" => Some(&mut self.color_overlay_debug),
+            "color_overlay_vision_idle" => Some(&mut self.color_overlay_vision_idle),
+            "color_overlay_vision_investigating" => Some(&mut self.color_overlay_vision_investigating),
+            "color_overlay_vision_attacking" => Some(&mut self.color_overlay_vision_attacking),
+            "color_overlay_threat" => Some(&mut self.color_overlay_threat),
+            _ => None,
+        }
+    }
+
+    /// The config's current colors, as a palette which can be used to switch back to them.
+    pub fn as_palette(&self, name: &str) -> ColorPalette {
+        let mut config = self.clone();
+
+        let mut colors = BTreeMap::new();
+        for name in COLOR_NAMES.iter() {
+            colors.insert(name.to_string(), *config.color_mut(name).unwrap());
+        }
+
+        return ColorPalette { name: name.to_string(), colors };
+    }
+
+    /// Use a palette's colors. Palettes with unknown color names are rejected when the
+    /// config is loaded, so any left over here are ignored.
+    pub fn apply_palette(&mut self, palette: &ColorPalette) {
+        for (name, color) in palette.colors.iter() {
+            if let Some(config_color) = self.color_mut(name) {
+                *config_color = *color;
+            }
+        }
+    }
+
+    /// The config's number and true/false settings by name, in the order they are declared.
+    /// They are found through the config's serialization, so new settings are listed as
+    /// soon as they are added.
+    pub fn fields(&self) -> Vec<(String, ConfigValue)> {
+        let mut fields = Vec::new();
+
+        if let Ok(serde_yaml::Value::Mapping(mapping)) = serde_yaml::to_value(self) {
+            for (key, value) in mapping.iter() {
+                if let (Some(name), Some(value)) = (key.as_str(), ConfigValue::from_yaml(value)) {
+                    fields.push((name.to_string(), value));
+                }
+            }
+        }
+
+        return fields;
+    }
+
+    /// Change one of the settings listed by fields. A value which doesn't suit the
+    /// setting, such as a negative number for a count, is refused.
+    pub fn set_field(&mut self, name: &str, value: ConfigValue) -> Result<(), String> {
+        let mut mapping =
+            match serde_yaml::to_value(&*self) {
+                Ok(serde_yaml::Value::Mapping(mapping)) => mapping,
+                _ => return Err("config could not be serialized".to_string()),
+            };
+
+        let key = serde_yaml::Value::String(name.to_string());
+        if mapping.get(&key).and_then(ConfigValue::from_yaml).is_none() {
+            return Err(format!("config has no setting {}", name));
+        }
+        mapping.insert(key, value.to_yaml());
+
+        *self = serde_yaml::from_value(serde_yaml::Value::Mapping(mapping)).map_err(|err| format!("{}: {}", name, err))?;
+        return Ok(());
+    }
+
+    /// Write the config's settings back to a config file. Only the lines for settings
+    /// which have changed are rewritten, so the file's comments and layout are kept.
+    pub fn save_fields(&self, file_name: &str) -> Result<(), String> {
+        let config_string =
+            std::fs::read_to_string(file_name).map_err(|err| format!("Could not read contents of {}: {}", file_name, err))?;
+
+        return std::fs::write(file_name, self.update_fields(&config_string))
+                   .map_err(|err| format!("Could not write {}: {}", file_name, err));
+    }
+
+    /// The text of a config file, with the settings on their own lines changed to this
+    /// config's values.
+    pub fn update_fields(&self, config_string: &str) -> String {
+        let fields = self.fields();
 
-        let config = serde_yaml::from_str(&config_string).expect(&format!("Could not parse {} file!", file_name));
+        let mut lines = Vec::new();
+        for line in config_string.lines() {
+            let mut new_line = line.to_string();
 
-        return config
+            // settings are at the top level, so indented lines belong to something else
+            if let (Some((name, text)), false) = (line.split_once(':'), line.starts_with(' ') || line.starts_with('#')) {
+                if let Some((_name, value)) = fields.iter().find(|(field, _value)| field == name) {
+                    let old_value = serde_yaml::from_str(text.trim()).ok().and_then(|old| ConfigValue::from_yaml(&old));
+                    if old_value != Some(*value) {
+                        new_line = format!("{}: {}", name, value);
+                    }
+                }
+            }
+
+            lines.push(new_line);
+        }
+
+        let mut new_string = lines.join("\n");
+        if config_string.ends_with('\n') {
+            new_string.push('\n');
+        }
+
+        return new_string;
+    }
+
+    /// The config's current difficulty settings, which can be used to switch back to them.
+    pub fn as_difficulty(&self) -> DifficultyConfig {
+        return DifficultyConfig {
+            spawn_budget: Some(self.spawn_budget),
+            spawn_budget_per_level: Some(self.spawn_budget_per_level),
+            fov_radius_monster: Some(self.fov_radius_monster),
+            investigate_turns: Some(self.investigate_turns),
+            player_health_bonus: Some(self.player_health_bonus),
+            player_stamina: Some(self.player_stamina),
+        };
+    }
+
+    pub fn apply_difficulty(&mut self, difficulty: &DifficultyConfig) {
+        if let Some(spawn_budget) = difficulty.spawn_budget {
+            self.spawn_budget = spawn_budget;
+        }
+        if let Some(spawn_budget_per_level) = difficulty.spawn_budget_per_level {
+            self.spawn_budget_per_level = spawn_budget_per_level;
+        }
+        if let Some(fov_radius_monster) = difficulty.fov_radius_monster {
+            self.fov_radius_monster = fov_radius_monster;
+        }
+        if let Some(investigate_turns) = difficulty.investigate_turns {
+            self.investigate_turns = investigate_turns;
+        }
+        if let Some(player_health_bonus) = difficulty.player_health_bonus {
+            self.player_health_bonus = player_health_bonus;
+        }
+        if let Some(player_stamina) = difficulty.player_stamina {
+            self.player_stamina = player_stamina;
+        }
+    }
+}
+
+/// Describe a YAML error along with the line it was found on.
+fn describe_yaml_error(config_string: &str, error: serde_yaml::Error) -> String {
+    if let Some(location) = error.location() {
+        if let Some(line) = config_string.lines().nth(location.line().saturating_sub(1)) {
+            return format!("{}\n    {}", error, line.trim_end());
+        }
     }
+
+    return error.to_string();
 }
 
+#[test]
+pub fn test_config_validation() {
+    let config_string = std::fs::read_to_string("../config.yaml").unwrap();
+    assert!(Config::from_str(&config_string).is_ok());
+
+    // an unknown field is named, along with the line it is on
+    let misspelled = config_string.replace("frame_rate:", "frame_rat:");
+    let error = Config::from_str(&misspelled).unwrap_err();
+    assert!(error.contains("unknown field `frame_rat`"));
+    assert!(error.contains("frame_rat: 25"));
+
+    let missing = config_string.replace("frame_rate: 25\n", "");
+    assert!(Config::from_str(&missing).unwrap_err().contains("missing field `frame_rate`"));
+
+    let old_version = config_string.replace(&format!("version: {}\n", CONFIG_VERSION), "version: 0\n");
+    assert!(Config::from_str(&old_version).unwrap_err().contains("version 0"));
+
+    // a palette color with a typo is caught before the palette is used
+    let bad_palette = config_string.replace("      color_light_green: {r: 86", "      color_light_gren: {r: 86");
+    assert!(Config::from_str(&bad_palette).unwrap_err().contains("palette deuteranopia has unknown color color_light_gren"));
+}
+
+#[test]
+pub fn test_config_fields() {
+    let config_string = std::fs::read_to_string("../config.yaml").unwrap();
+    let mut config = Config::from_str(&config_string).unwrap();
+
+    let fields = config.fields();
+    assert!(fields.contains(&("frame_rate".to_string(), ConfigValue::Int(config.frame_rate as i64))));
+    assert!(fields.contains(&("fog_of_war".to_string(), ConfigValue::Bool(config.fog_of_war))));
+    assert!(fields.iter().all(|(name, _value)| name != "map_file"));
+
+    // an unchanged config writes the file back as it was
+    assert_eq!(config_string, config.update_fields(&config_string));
+
+    config.set_field("frame_rate", ConfigValue::Int(30)).unwrap();
+    config.set_field("idle_speed", ConfigValue::Float(0.25)).unwrap();
+    assert_eq!(30, config.frame_rate);
+    assert_eq!(0.25, config.idle_speed);
+
+    assert!(config.set_field("frame_rate", ConfigValue::Int(-1)).is_err());
+    assert!(config.set_field("frame_rat", ConfigValue::Int(1)).is_err());
+    assert_eq!(30, config.frame_rate);
+
+    // only the changed lines are rewritten
+    let saved = config.update_fields(&config_string);
+    assert!(saved.contains("\nframe_rate: 30\n"));
+    assert!(saved.contains("\nidle_speed: 0.25\n"));
+    assert_eq!(config_string.lines().count(), saved.lines().count());
+    assert_eq!(config.fields(), Config::from_str(&saved).unwrap().fields());
+}
@@ -10,7 +10,16 @@ pub const SCREEN_HEIGHT: u32 = 800;
 pub const INVENTORY_WIDTH: usize = 300;
 pub const INVENTORY_HEIGHT: usize = 500;
 
+/* Map zoom, as a multiple of the size that fits the whole map on screen */
+pub const MIN_MAP_ZOOM: f32 = 0.5;
+pub const MAX_MAP_ZOOM: f32 = 2.0;
+pub const MAP_ZOOM_STEP: f32 = 0.25; // zoom per notch of the mouse wheel
+pub const MAP_ZOOM_SPEED: f32 = 10.0; // fraction of the way to the wheel's zoom covered per second
+
+pub const MINIMAP_MARGIN: i32 = 4; // pixels between the minimap and the edge of the map area
+
 /* General Settings */
+pub const CONFIG_VERSION: u32 = 2; // increased when config fields are renamed or removed
 pub const INITIAL_OBJECT_CAPACITY: usize = 1000;
 pub const BLINK_RADIUS: usize = 4;
 pub const TILE_FILL_METRIC_DIST: usize = 3;
@@ -23,6 +32,11 @@ pub const GOLEM_ATTACK_HORIZ: u8 = 181;
 /* Monster Settings */
 pub const MAX_MONSTERS: i32 = 15;
 
+// reinforcements arrive at up to this many tiles near the map's edge, spaced apart
+pub const NUM_ENTRY_POINTS: usize = 4;
+pub const ENTRY_POINT_EDGE: i32 = 2;
+pub const ENTRY_POINT_SPACING: i32 = 6;
+
 pub const GOL_ATTACK_DISTANCE: usize = 5;
 pub const GOL_MOVE_DISTANCE: usize = 1;
 
@@ -35,20 +49,53 @@ pub const PAWN_ATTACK_DISTANCE: usize = 1;
 pub const ROOK_MOVE_DISTANCE: usize = 1;
 pub const ROOK_ATTACK_DISTANCE: usize = 1;
 
+pub const KNIGHT_MOVE_DISTANCE: usize = 1;
+pub const KNIGHT_ATTACK_DISTANCE: usize = 1;
+
 pub const ARMIL_MOVE_DISTANCE: usize = 1;
 
+pub const PRISONER_MOVE_DISTANCE: usize = 1;
+// extra path cost for a follower stepping onto a tile an enemy can see
+pub const FOLLOW_SEEN_COST: i32 = 10;
+
+pub const CRITTER_MOVE_DISTANCE: usize = 1;
+// chance for a wandering critter to change direction each turn
+pub const CRITTER_TURN_CHANCE: f32 = 0.25;
+
 pub const KEY_ATTACK_DISTANCE: usize = 3;
 pub const KEY_MOVE_DISTANCE: usize = 2;
 
 pub const MAX_MOMENTUM: i32 = 2;
 
 pub const SPIKE_DAMAGE: i32 = 20;
-pub const HAMMER_DAMAGE: i32 = 40;
+pub const BLADE_DAMAGE: i32 = 20;
+pub const FIRE_DAMAGE: i32 = 2;
+pub const POISON_DAMAGE: i32 = 1;
+pub const HAMMER_DAMAGE: i32 = 8;
+// extra damage when a hammer knocks an entity into a wall or another entity
+pub const HAMMER_KNOCKBACK_DAMAGE: i32 = 8;
+pub const HAMMER_STUN_TURNS: usize = 1;
 pub const SWORD_DAMAGE: i32 = 20;
 
 pub const TRIGGER_WALL_DAMAGE: i32 = 20;
 
 pub const NOT_IN_FOV_COST: usize = 5;
+pub const FIRE_PATH_COST: i32 = 10;
+
+/* Combat */
+// percent of a target's defense ignored by piercing attacks
+pub const PIERCE_DEFENSE_IGNORED: i32 = 50;
+pub const CRITICAL_MULTIPLIER: i32 = 2;
+// an item with this many uses left or fewer gets a warning in the log
+pub const DURABILITY_WARNING: u32 = 1;
+
+/* Cover */
+// percent of a target covered by each short wall or tile of grass between it and whoever is looking
+pub const SHORT_WALL_COVER: u32 = 50;
+pub const GRASS_COVER: u32 = 25;
+pub const MAX_COVER: u32 = 90;
+// a crouching target with at least this much cover is hidden
+pub const HIDING_COVER: u8 = 50;
 
 /* Skill Settings */
 pub const SKILL_GRASS_SHOES_TURNS: usize = 6;
@@ -63,14 +110,28 @@ pub const SKILL_SPRINT_AMOUNT: usize = 3;
 pub const LANTERN_ILLUMINATE_RADIUS: usize = 2;
 pub const ILLUMINATE_AMOUNT: usize = LANTERN_ILLUMINATE_RADIUS;
 
+/* Container Settings */
+// most items a container is stocked with when a level is generated
+pub const CONTAINER_MAX_LOOT: usize = 3;
+
+/* Gas Settings */
+// gas thinner than this disappears
+pub const GAS_MIN_AMOUNT: f32 = 0.05;
+pub const SOUND_MIN_AMOUNT: f32 = 0.05; // quieter sounds are no longer heard on a tile
+
 /* Trap Settings */
 pub const FREEZE_TRAP_NUM_TURNS: usize = 3;
+pub const NET_TRAP_NUM_TURNS: usize = 3;
+pub const TRIPWIRE_REARM_TURNS: usize = 5;
+pub const BLADE_TRAP_REARM_TURNS: usize = 3;
 
 /* Player */
 pub const PLAYER_THROW_DIST: usize = 5;
 
 pub const PLAYER_MAX_ITEMS: usize = 3;
 
+pub const DROP_RADIUS: i32 = 1; // how far away the player can choose to put down an item
+
 /* UI */
 pub const BAR_WIDTH: u32 = 20;
 pub const PANEL_HEIGHT: u32 = 7;
@@ -80,10 +141,38 @@ pub const MSG_X: u32 = BAR_WIDTH + 2;
 pub const MSG_WIDTH: u32 = SCREEN_WIDTH - BAR_WIDTH - 2;
 pub const MSG_HEIGHT: u32 = PANEL_HEIGHT - 1;
 
+pub const MAX_VOLUME: usize = 10;
+pub const MAX_SEED_DIGITS: usize = 19;
+pub const PROMPT_WIDTH: usize = 24;
+pub const HELP_LINES: usize = 20; // lines of the help screen shown at once
+pub const MAX_HINTS: usize = 4; // action hints shown in the info panel at once
+pub const FORECAST_TURNS: usize = 2; // turns shown in the turn order forecast
+pub const NUM_QUICK_SLOTS: usize = 4; // quick slots on the hotbar, used with the number keys 1 to 4
+pub const HAND_SWAP_SECONDS: f32 = 0.3; // time for held items to trade places in the player panel
+pub const SCREENSHOT_DIR: &str = "screenshots";
+pub const CLIP_DIR: &str = "clips";
+pub const THUMBNAIL_DIR: &str = "thumbnails"; // images of the map at the end of each run on the leaderboard
+pub const GHOST_DIR: &str = "ghosts"; // the input of the best run on each seed, raced as a ghost
+pub const THUMBNAIL_WIDTH: u32 = 240;
+pub const THUMBNAIL_HEIGHT: u32 = 240;
+
+/* Scoring */
+pub const SCORE_PER_LEVEL: i64 = 1000;
+pub const SCORE_WIN_BONUS: i64 = 2000;
+pub const SCORE_GHOST_BONUS: i64 = 500;
+pub const SCORE_PER_KILL: i64 = 100;
+pub const SCORE_PER_TURN: i64 = 1;
+pub const SCORE_PER_NOISE: i64 = 2;
+pub const SCORE_PER_ITEM_USED: i64 = 20;
+pub const LEADERBOARD_SIZE: usize = 10;
+
 /* Map and View */
 pub const MAP_WIDTH: i32 = 30;
 pub const MAP_HEIGHT: i32 = 30;
 
+// the most FOV results remembered before they are all forgotten
+pub const FOV_MEMO_SIZE: usize = 100_000;
+
 pub const ISLAND_DISTANCE: i32 = 7;
 
 pub const ISLAND_RADIUS: i32 = 15;
@@ -91,11 +180,22 @@ pub const ISLAND_NUM_OBSTACLES: i32 = 15;
 pub const ISLAND_NUM_SUBTRACTIONS_ATTEMPTS: i32 = 50;
 pub const ISLAND_NUM_ADDITION_ATTEMPTS: i32 = 50;
 
+/* Vaults */
+pub const VAULT_WEIGHT_COMMON: f32 = 1.0;
+pub const VAULT_WEIGHT_MEDIUM: f32 = 0.4;
+pub const VAULT_WEIGHT_RARE: f32 = 0.1;
+// levels before this are 'early' and this level and after are 'late'
+pub const VAULT_LATE_LEVEL: usize = 3;
+
+pub const WFC_FIXED_ATTEMPTS: usize = 5;
+
 pub const MAP_LAYER_GROUND: usize = 0;
 pub const MAP_LAYER_ENVIRONMENT: usize = 1;
 pub const MAP_LAYER_ENTITIES: usize = 2;
+pub const MAP_LAYER_LINKS: usize = 3;
 
 pub const MAP_GROUND: u8 = 154;
+pub const MAP_GROUND_PATTERN: u8 = 224;
 pub const MAP_WATER_XP: u8 = 171;
 pub const MAP_WATER: u8 = 171;
 pub const MAP_EMPTY: u8 = 32;
@@ -146,6 +246,10 @@ pub const MAP_STAR: u8 = 15;
 pub const MAP_ORB: u8 = 135;
 pub const MAP_WALL: u8 = 150;
 
+pub const MAP_DOOR_CLOSED: u8 = 176;
+pub const MAP_DOOR_OPEN: u8 = 177;
+pub const MAP_DOOR_LOCKED: u8 = 178;
+
 pub const MAP_ROOK: u8 = 130;
 
 /* Items */
@@ -159,12 +263,20 @@ pub const ENTITY_SWORD: u8 = 248;
 pub const ENTITY_SHIELD: u8 = 255;
 pub const ENTITY_HAMMER: u8 = 251;
 pub const ENTITY_LANTERN: u8 = 134;
+pub const ENTITY_SMOKE_BOMB: u8 = 8;
+pub const ENTITY_POISON_BOMB: u8 = 18;
+pub const ENTITY_NOISEMAKER: u8 = ENTITY_STONE;
+pub const ENTITY_GRAPPLE: u8 = ENTITY_KEY;
+pub const ENTITY_COINS: u8 = 36;
 
 /* Entities */
 pub const ENTITY_GOL: u8 = 152;
 pub const ENTITY_PLAYER: u8 = 139;
 pub const ENTITY_CLOAK_GUY: u8 = 141;
 pub const ENTITY_ELF: u8 = 165;
+pub const ENTITY_PRISONER: u8 = ENTITY_CLOAK_GUY;
+pub const ENTITY_RAT: u8 = 114;
+pub const ENTITY_BIRD: u8 = 118;
 pub const ENTITY_UNKNOWN: u8 = 239;
 pub const ENTITY_ENERGY: u8 = 6;
 
@@ -191,11 +303,28 @@ pub const SHADOW_INTERTILE_DOWN_LEFT: u8 = 4;
 /* Triggers */
 pub const ENTITY_GATE_TRIGGER: u8 = ENTITY_TRIGGER_2;
 
+/* Mechanisms */
+pub const ENTITY_PRESSURE_PLATE: u8 = MAP_SMALL_DOT_MIDDLE;
+pub const ENTITY_LEVER_UP: u8 = 92;
+pub const ENTITY_LEVER_DOWN: u8 = 47;
+pub const ENTITY_GATE_CLOSED: u8 = MAP_DOOR_LOCKED;
+pub const ENTITY_GATE_OPEN: u8 = MAP_DOOR_OPEN;
+
+/* Alarms */
+pub const ENTITY_ALARM: u8 = EXCLAMATION_POINT;
+
+/* Containers */
+pub const ENTITY_CHEST: u8 = 236;
+pub const ENTITY_CRATE: u8 = 254;
+
 /* Traps */
 pub const ENTITY_SPIKE_TRAP: u8 = 136;
 pub const ENTITY_BLINK_TRAP: u8 = ENTITY_TRIGGER_1;
 pub const ENTITY_TRAP_SOUND: u8 = ENTITY_TRIGGER_5;
 pub const ENTITY_FREEZE_TRAP: u8 = ENTITY_TRIGGER_4;
+pub const ENTITY_TRIPWIRE: u8 = ENTITY_TRIGGER_3;
+pub const ENTITY_NET_TRAP: u8 = 229;
+pub const ENTITY_BLADE_TRAP: u8 = MAP_WIDE_SPIKES;
 
 /* Arrows */
 pub const ARROW_HORIZ: u8 = 16;
@@ -206,3 +335,9 @@ pub const EXCLAMATION_POINT: u8 = 33 as u8;
 pub const QUESTION_MARK: u8 = 63 as u8;
 pub const ASTERISK: u8 = 239; // NOTE there is no asterisk currently, so this is a marker
 
+
+/* Agents */
+pub const AGENT_MAX_STEPS: usize = 5000; // inputs an agent is given before its run is given up on
+
+/* Spectating */
+pub const SPECTATOR_WRITE_TIMEOUT_MS: u64 = 50; // a spectator whose frames can't be sent in this long is dropped
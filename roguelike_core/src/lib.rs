@@ -1,10 +1,13 @@
 pub mod types;
+pub mod comp;
 pub mod map;
 pub mod movement;
 pub mod constants;
 pub mod config;
 pub mod ai;
+pub mod combat;
 pub mod utils;
 pub mod messaging;
+pub mod locale;
 pub mod line;
 pub mod perlin;
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+
+use crate::types::EntityName;
+
+
+/// The locale used when a key is missing from the chosen one. It is built into the
+/// game, so there is always text to fall back on.
+pub const DEFAULT_LOCALE: &str = "en";
+
+const DEFAULT_LOCALE_TEXT: &str = include_str!("../../resources/locale/en.yaml");
+
+/// The text shown to the player, looked up by key, such as "msg.picked_up" or "name.dagger".
+///
+/// Text can name parameters in braces, like "{entity} picked up a {item}", which are
+/// filled in when it is looked up. A key missing from a locale is looked up in its
+/// fallback instead, and if no locale has it the key itself is shown, so a partial
+/// translation is still playable.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Locale {
+    pub name: String,
+    pub strings: HashMap<String, String>,
+    pub fallback: Option<Box<Locale>>,
+}
+
+impl Locale {
+    /// The built in English text.
+    pub fn english() -> Locale {
+        return Locale::from_str(DEFAULT_LOCALE, DEFAULT_LOCALE_TEXT);
+    }
+
+    /// Parse a locale from the contents of its file- a map from each key to its text.
+    pub fn from_str(name: &str, locale_string: &str) -> Locale {
+        let strings = serde_yaml::from_str(locale_string).expect(&format!("Could not parse locale {}!", name));
+
+        return Locale { name: name.to_string(), strings, fallback: None };
+    }
+
+    /// Load the locale with the given name from a directory of locale files, such as
+    /// resources/locale/fr.yaml, falling back to English for anything it is missing.
+    pub fn from_dir(dir: &str, name: &str) -> Result<Locale, String> {
+        if name == DEFAULT_LOCALE {
+            return Ok(Locale::english());
+        }
+
+        let file_name = format!("{}/{}.yaml", dir, name);
+        let mut file =
+            File::open(&file_name).map_err(|err| format!("could not read locale file {}: {}", file_name, err))?;
+        let mut locale_string = String::new();
+        file.read_to_string(&mut locale_string)
+            .map_err(|err| format!("could not read locale file {}: {}", file_name, err))?;
+
+        let strings = serde_yaml::from_str(&locale_string)
+            .map_err(|err| format!("could not parse locale file {}: {}", file_name, err))?;
+
+        return Ok(Locale { name: name.to_string(), strings, fallback: Some(Box::new(Locale::english())) });
+    }
+
+    fn lookup(&self, key: &str) -> Option<&String> {
+        if let Some(text) = self.strings.get(key) {
+            return Some(text);
+        }
+
+        return self.fallback.as_ref().and_then(|fallback| fallback.lookup(key));
+    }
+
+    pub fn text(&self, key: &str) -> String {
+        return self.text_with(key, &[]);
+    }
+
+    /// Look up text, replacing each "{param}" in it with the value given for that param.
+    pub fn text_with(&self, key: &str, params: &[(&str, String)]) -> String {
+        let mut text = self.lookup(key).cloned().unwrap_or(key.to_string());
+
+        for (param, value) in params.iter() {
+            text = text.replace(&format!("{{{}}}", param), value);
+        }
+
+        return text;
+    }
+
+    /// Every character used in this locale's text or its fallback, so a font can
+    /// prepare the glyphs needed to show it.
+    pub fn characters(&self) -> String {
+        let mut characters = self.strings.values().map(|text| text.as_str()).collect::<String>();
+
+        if let Some(fallback) = &self.fallback {
+            characters.push_str(&fallback.characters());
+        }
+
+        return characters;
+    }
+
+    /// The name of an entity as it is shown to the player.
+    pub fn name(&self, name: EntityName) -> String {
+        return self.text(&format!("name.{}", name));
+    }
+}
+
+#[test]
+pub fn test_locale_lookup() {
+    let mut locale = Locale::from_str("test", "msg.picked_up: \"{item} ramassé par {entity}\"\nname.dagger: dague\n");
+    locale.fallback = Some(Box::new(Locale::english()));
+
+    // parameters are filled in by name, in whatever order the text uses them
+    let params = [("entity", locale.name(EntityName::Player)), ("item", locale.name(EntityName::Dagger))];
+    assert_eq!("dague ramassé par player", locale.text_with("msg.picked_up", &params));
+
+    // missing keys fall back to English, and then to the key itself
+    assert_eq!("player killed pawn",
+               locale.text_with("msg.killed", &[("attacker", "player".to_string()), ("attacked", "pawn".to_string())]));
+    assert_eq!("msg.not_a_message", locale.text("msg.not_a_message"));
+}
@@ -1,7 +1,7 @@
 use std::ops::{Index, IndexMut};
 use std::collections::{HashSet, HashMap};
 use std::iter;
-use std::cell::RefCell;
+use std::sync::RwLock;
 use std::str::FromStr;
 use std::fmt;
 
@@ -29,6 +29,41 @@ use crate::movement::Direction;
 use crate::line::*;
 
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DoorState {
+    Open,
+    Closed,
+    Locked,
+}
+
+impl fmt::Display for DoorState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DoorState::Open => write!(f, "open"),
+            DoorState::Closed => write!(f, "closed"),
+            DoorState::Locked => write!(f, "locked"),
+        }
+    }
+}
+
+impl FromStr for DoorState {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let s: &mut str = &mut string.to_string();
+        s.make_ascii_lowercase();
+        if s == "open" {
+            return Ok(DoorState::Open);
+        } else if s == "closed" {
+            return Ok(DoorState::Closed);
+        } else if s == "locked" {
+            return Ok(DoorState::Locked);
+        }
+
+        return Err(format!("Could not parse '{}' as DoorState", s));
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum TileType {
     Empty,
@@ -36,6 +71,7 @@ pub enum TileType {
     Wall,
     Water,
     Exit,
+    Door(DoorState),
 }
 
 impl fmt::Display for TileType {
@@ -46,6 +82,7 @@ impl fmt::Display for TileType {
             TileType::Wall => write!(f, "wall"),
             TileType::Water => write!(f, "water"),
             TileType::Exit => write!(f, "exit"),
+            TileType::Door(state) => write!(f, "{}door", state),
         }
     }
 }
@@ -66,6 +103,9 @@ impl FromStr for TileType {
             return Ok(TileType::Water);
         } else if s == "exit" {
             return Ok(TileType::Exit);
+        } else if s.ends_with("door") {
+            let state = s.trim_end_matches("door").parse::<DoorState>()?;
+            return Ok(TileType::Door(state));
         }
 
         return Err(format!("Could not parse '{}' as TileType", s));
@@ -80,8 +120,16 @@ impl TileType {
             TileType::Wall => true,
             TileType::Water => false,
             TileType::Exit => false,
+            TileType::Door(_) => false,
         }
     }
+
+    pub fn is_door(&self) -> bool {
+        if let TileType::Door(_) = self {
+            return true;
+        }
+        return false;
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -117,6 +165,15 @@ impl Aoe {
     }
 }
 
+/// How well one position can be seen, or shot at, from another. Partial gives
+/// the percent of the target which is behind cover, such as short walls and grass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Visibility {
+    Clear,
+    Partial(u8),
+    Blocked,
+}
+
 /// This structure describes a movement between two
 /// tiles that was blocked due to a wall or blocked tile.
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
@@ -154,8 +211,12 @@ pub enum MapLoadConfig {
     TestVaults,
     TestTraps,
     FromFile(String),
+    Tiled(String),
+    Text(String),
     VaultFile(String),
     ProcGen(String),
+    Rooms(String),
+    Caves(String),
 }
 
 impl Default for MapLoadConfig {
@@ -176,8 +237,12 @@ impl fmt::Display for MapLoadConfig {
             MapLoadConfig::TestVaults => write!(f, "test_vaults"),
             MapLoadConfig::TestTraps => write!(f, "test_traps"),
             MapLoadConfig::FromFile(file) => write!(f, "from_file {}", file),
+            MapLoadConfig::Tiled(file) => write!(f, "tiled {}", file),
+            MapLoadConfig::Text(file) => write!(f, "text {}", file),
             MapLoadConfig::VaultFile(file) => write!(f, "vault_file {}", file),
             MapLoadConfig::ProcGen(file) => write!(f, "procgen {}", file),
+            MapLoadConfig::Rooms(file) => write!(f, "rooms {}", file),
+            MapLoadConfig::Caves(file) => write!(f, "caves {}", file),
         }
     }
 }
@@ -207,12 +272,24 @@ impl FromStr for MapLoadConfig {
         } else if s.starts_with("from_file") {
             let args = s.split(" ").collect::<Vec<&str>>();
             return Ok(MapLoadConfig::FromFile(args[1].to_string()));
+        } else if s.starts_with("tiled") {
+            let args = s.split(" ").collect::<Vec<&str>>();
+            return Ok(MapLoadConfig::Tiled(args[1].to_string()));
+        } else if s.starts_with("text") {
+            let args = s.split(" ").collect::<Vec<&str>>();
+            return Ok(MapLoadConfig::Text(args[1].to_string()));
         } else if s.starts_with("vault_file") {
             let args = s.split(" ").collect::<Vec<&str>>();
             return Ok(MapLoadConfig::VaultFile(args[1].to_string()));
         } else if s.starts_with("procgen") {
             let args = s.split(" ").collect::<Vec<&str>>();
             return Ok(MapLoadConfig::ProcGen(args[1].to_string()));
+        } else if s.starts_with("rooms") {
+            let args = s.split(" ").collect::<Vec<&str>>();
+            return Ok(MapLoadConfig::Rooms(args[1].to_string()));
+        } else if s.starts_with("caves") {
+            let args = s.split(" ").collect::<Vec<&str>>();
+            return Ok(MapLoadConfig::Caves(args[1].to_string()));
         }
 
         return Err(format!("Could not parse '{}' as MapLoadConfig", s));
@@ -225,6 +302,8 @@ pub enum Surface {
     Floor,
     Rubble,
     Grass,
+    Burning,
+    Dirt,
 }
 
 impl fmt::Display for Surface {
@@ -233,6 +312,8 @@ impl fmt::Display for Surface {
             Surface::Floor => write!(f, "floor"),
             Surface::Rubble => write!(f, "rubble"),
             Surface::Grass => write!(f, "grass"),
+            Surface::Burning => write!(f, "burning"),
+            Surface::Dirt => write!(f, "dirt"),
         }
     }
 }
@@ -249,6 +330,10 @@ impl FromStr for Surface {
             return Ok(Surface::Rubble);
         } else if s == "grass" {
             return Ok(Surface::Grass);
+        } else if s == "burning" {
+            return Ok(Surface::Burning);
+        } else if s == "dirt" {
+            return Ok(Surface::Dirt);
         }
 
         return Err(format!("Could not parse '{}' as Surface", s));
@@ -256,6 +341,65 @@ impl FromStr for Surface {
 }
 
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GasType {
+    Smoke,
+    Poison,
+}
+
+impl fmt::Display for GasType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GasType::Smoke => write!(f, "smoke"),
+            GasType::Poison => write!(f, "poison"),
+        }
+    }
+}
+
+impl FromStr for GasType {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let s: &mut str = &mut string.to_string();
+        s.make_ascii_lowercase();
+        if s == "smoke" {
+            return Ok(GasType::Smoke);
+        } else if s == "poison" {
+            return Ok(GasType::Poison);
+        }
+
+        return Err(format!("Could not parse '{}' as GasType", s));
+    }
+}
+
+/// The amount of each type of gas hanging over a tile.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Gas {
+    pub smoke: f32,
+    pub poison: f32,
+}
+
+impl Gas {
+    pub fn amount(&self, typ: GasType) -> f32 {
+        match typ {
+            GasType::Smoke => return self.smoke,
+            GasType::Poison => return self.poison,
+        }
+    }
+
+    pub fn add(&mut self, typ: GasType, amount: f32) {
+        match typ {
+            GasType::Smoke => self.smoke += amount,
+            GasType::Poison => self.poison += amount,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.smoke == 0.0 && self.poison == 0.0;
+    }
+}
+
+
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum BlockedType {
     Fov,
@@ -282,6 +426,9 @@ pub struct Tile {
     pub left_wall: Wall,
     pub chr: u8,
     pub surface: Surface,
+    pub cracked: bool,
+    // mechanisms toggle the tiles sharing their link id. 0 means the tile is not linked.
+    pub link: u8,
 }
 
 impl Tile {
@@ -295,12 +442,16 @@ impl Tile {
             left_wall: Wall::Empty,
             chr: ' ' as u8,
             surface: Surface::Floor,
+            cracked: false,
+            link: 0,
         }
     }
 
+    /// Deep water. Water does not block movement directly- wading through it is
+    /// handled when resolving a move, as some monsters are too heavy to enter it.
     pub fn water() -> Self {
         Tile {
-            block_move: true,
+            block_move: false,
             block_sight: false,
             explored: false,
             tile_type: TileType::Water,
@@ -308,6 +459,8 @@ impl Tile {
             left_wall: Wall::Empty,
             chr: ' ' as u8,
             surface: Surface::Floor,
+            cracked: false,
+            link: 0,
         }
     }
 
@@ -336,6 +489,8 @@ impl Tile {
                left_wall: Wall::Empty,
                chr: chr as u8,
                surface: Surface::Floor,
+            cracked: false,
+            link: 0,
         }
     }
 
@@ -353,6 +508,8 @@ impl Tile {
             left_wall: Wall::Empty,
             chr: chr as u8,
             surface: Surface::Floor,
+            cracked: false,
+            link: 0,
         }
     }
 
@@ -366,9 +523,50 @@ impl Tile {
             left_wall: Wall::Empty,
             chr: ' ' as u8,
             surface: Surface::Floor,
+            cracked: false,
+            link: 0,
         }
     }
 
+    /// A door tile. Closed doors block sight, but only locked doors block movement
+    /// directly- moving into a closed door is handled when resolving a move, so
+    /// that monsters can path through doors they are able to open.
+    pub fn door(state: DoorState) -> Self {
+        let mut tile = Tile::empty();
+        tile.set_door(state);
+        return tile;
+    }
+
+    pub fn set_door(&mut self, state: DoorState) {
+        self.tile_type = TileType::Door(state);
+        match state {
+            DoorState::Open => {
+                self.block_move = false;
+                self.block_sight = false;
+                self.chr = MAP_DOOR_OPEN;
+            }
+
+            DoorState::Closed => {
+                self.block_move = false;
+                self.block_sight = true;
+                self.chr = MAP_DOOR_CLOSED;
+            }
+
+            DoorState::Locked => {
+                self.block_move = true;
+                self.block_sight = true;
+                self.chr = MAP_DOOR_LOCKED;
+            }
+        }
+    }
+
+    pub fn door_state(&self) -> Option<DoorState> {
+        if let TileType::Door(state) = self.tile_type {
+            return Some(state);
+        }
+        return None;
+    }
+
     pub fn does_tile_block(self, block_type: BlockedType) -> bool {
         match block_type {
             BlockedType::Fov => {
@@ -604,18 +802,155 @@ impl Wall {
     }
 }
 
+/// A change made to a tile during a turn: who made it, where, and the tile
+/// before and after. Edits made by the environment, such as fire, have no entity.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TileEdit {
+    pub turn: usize,
+    pub entity_id: Option<EntityId>,
+    pub pos: Pos,
+    pub from: Tile,
+    pub to: Tile,
+}
+
+/// The changes made to a map's terrain, turn by turn. Nothing is recorded until the
+/// first call to Map::begin_edits, so generating a map does not fill the journal.
+/// Tiles are borrowed mutably without saying what will change, so the tile as it was
+/// is kept when it is first borrowed, and compared against when the edits are committed.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MapJournal {
+    pub edits: Vec<TileEdit>,
+    pub recording: bool,
+    pub turn: usize,
+    pub entity_id: Option<EntityId>,
+    pub pending: Vec<(Pos, Tile)>,
+}
+
+impl MapJournal {
+    fn borrowed(&mut self, pos: Pos, tile: Tile) {
+        if self.recording && !self.pending.iter().any(|(pending_pos, _tile)| *pending_pos == pos) {
+            self.pending.push((pos, tile));
+        }
+    }
+
+    pub fn turn_edits(&self, turn: usize) -> impl Iterator<Item=&TileEdit> {
+        return self.edits.iter().filter(move |edit| edit.turn == turn);
+    }
+}
+
+/// FOV results for the map as it was at a given revision. Entries from an older
+/// revision are dropped the next time the cache is used, so any change to a tile,
+/// such as a door opening or a wall being hammered down, invalidates them.
+/// AI turns are decided on several threads at once, which all share this cache,
+/// so it is behind a lock rather than a RefCell.
+#[derive(Debug, Default)]
+pub struct FovCache(RwLock<FovMemo>);
+
+#[derive(Debug, Default)]
+struct FovMemo {
+    revision: u64,
+    shadowcast: HashMap<Pos, Vec<Pos>>,        // positions visible from each start position
+    in_fov: HashMap<(Pos, Pos, i32, bool), bool>, // is_in_fov results, by start, end, radius, and whether low
+}
+
+impl FovMemo {
+    fn update(&mut self, revision: u64) {
+        if self.revision != revision {
+            self.shadowcast.clear();
+            self.in_fov.clear();
+            self.revision = revision;
+        }
+    }
+}
+
+impl FovCache {
+    pub fn new() -> FovCache {
+        return FovCache::default();
+    }
+
+    /// Whether end_pos is visible from start_pos, if FOV has been computed from start_pos
+    /// at this revision of the map.
+    pub fn is_visible(&self, revision: u64, start_pos: Pos, end_pos: Pos) -> Option<bool> {
+        let memo = self.0.read().unwrap();
+        if memo.revision != revision {
+            return None;
+        }
+        return memo.shadowcast.get(&start_pos).map(|visible| visible.contains(&end_pos));
+    }
+
+    pub fn insert(&self, revision: u64, start_pos: Pos, visible: Vec<Pos>) {
+        let mut memo = self.0.write().unwrap();
+        memo.update(revision);
+        memo.shadowcast.insert(start_pos, visible);
+    }
+
+    pub fn in_fov(&self, revision: u64, key: (Pos, Pos, i32, bool)) -> Option<bool> {
+        let memo = self.0.read().unwrap();
+        if memo.revision != revision {
+            return None;
+        }
+        return memo.in_fov.get(&key).map(|in_fov| *in_fov);
+    }
+
+    pub fn insert_in_fov(&self, revision: u64, key: (Pos, Pos, i32, bool), in_fov: bool) {
+        let mut memo = self.0.write().unwrap();
+        memo.update(revision);
+
+        // the map can go a long time without changing, so this is kept from growing without bound
+        if memo.in_fov.len() >= FOV_MEMO_SIZE {
+            memo.in_fov.clear();
+        }
+        memo.in_fov.insert(key, in_fov);
+    }
+
+    pub fn clear(&self) {
+        let mut memo = self.0.write().unwrap();
+        memo.shadowcast.clear();
+        memo.in_fov.clear();
+    }
+}
+
+impl Clone for FovCache {
+    fn clone(&self) -> FovCache {
+        let memo = self.0.read().unwrap();
+        return FovCache(RwLock::new(FovMemo { revision: memo.revision,
+                                              shadowcast: memo.shadowcast.clone(),
+                                              in_fov: memo.in_fov.clone() }));
+    }
+}
+
+// the cache only remembers what can be worked out from the map, so it takes no part in comparing maps
+impl PartialEq for FovCache {
+    fn eq(&self, _other: &FovCache) -> bool {
+        return true;
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Map {
     pub tiles: Vec<Vec<Tile>>,
-    pub fov_cache: RefCell<HashMap<Pos, Vec<Pos>>>,
+    pub gas: Vec<Vec<Gas>>,
+    pub sound: Vec<Vec<f32>>, // how loud the sounds heard on each tile recently were, from 0 to 1
+    #[serde(skip)]
+    pub fov_cache: FovCache,
+    #[serde(default)]
+    pub revision: u64, // increased whenever a tile may have changed
+    #[serde(default)]
+    pub journal: MapJournal,
 }
 
 impl Map {
     pub fn with_vec(tiles: Vec<Vec<Tile>>) -> Map {
+        let gas = tiles.iter().map(|column| vec!(Gas::default(); column.len())).collect();
+        let sound = tiles.iter().map(|column| vec!(0.0; column.len())).collect();
         let map =
             Map {
                 tiles,
-                fov_cache: RefCell::new(HashMap::new()),
+                gas,
+                sound,
+                fov_cache: FovCache::new(),
+                revision: 0,
+                journal: MapJournal::default(),
             };
 
         return map;
@@ -623,10 +958,16 @@ impl Map {
 
     pub fn from_dims(width: u32, height: u32) -> Map {
         let tiles = vec!(vec!(Tile::empty(); height as usize); width as usize);
+        let gas = vec!(vec!(Gas::default(); height as usize); width as usize);
+        let sound = vec!(vec!(0.0; height as usize); width as usize);
         let map =
             Map {
                 tiles,
-                fov_cache: RefCell::new(HashMap::new()),
+                gas,
+                sound,
+                fov_cache: FovCache::new(),
+                revision: 0,
+                journal: MapJournal::default(),
             };
 
         return map;
@@ -636,7 +977,11 @@ impl Map {
         let map =
             Map {
                 tiles: Vec::new(),
-                fov_cache: RefCell::new(HashMap::new()),
+                gas: Vec::new(),
+                sound: Vec::new(),
+                fov_cache: FovCache::new(),
+                revision: 0,
+                journal: MapJournal::default(),
             };
 
         return map;
@@ -909,6 +1254,40 @@ impl Map {
         return None;
     }
 
+    /// Whether end_pos can be seen from start_pos, and how much cover is in the way. Tall walls,
+    /// whether whole tiles or the thin walls between tiles, block sight, while short walls and
+    /// grass can be seen over but give cover. A wall tile at end_pos can itself be seen.
+    pub fn line_of_sight(&self, start_pos: Pos, end_pos: Pos) -> Visibility {
+        let mut cover = 0;
+
+        let positions = iter::once(start_pos).chain(line(start_pos, end_pos).into_iter());
+        for (pos, next_pos) in positions.tuple_windows() {
+            if let Some(blocked) = self.move_blocked(pos, next_pos, BlockedType::Fov) {
+                if next_pos != end_pos || !blocked.blocked_tile {
+                    return Visibility::Blocked;
+                }
+            } else if self.move_blocked(pos, next_pos, BlockedType::FovLow).is_some() {
+                cover += SHORT_WALL_COVER;
+            }
+
+            if self.is_within_bounds(next_pos) {
+                if self[next_pos].tile_type == TileType::ShortWall && next_pos != end_pos {
+                    cover += SHORT_WALL_COVER;
+                }
+
+                if self[next_pos].surface == Surface::Grass {
+                    cover += GRASS_COVER;
+                }
+            }
+        }
+
+        if cover == 0 {
+            return Visibility::Clear;
+        } else {
+            return Visibility::Partial(std::cmp::min(cover, MAX_COVER) as u8);
+        }
+    }
+
     pub fn is_empty(&self, pos: Pos) -> bool {
         return self[pos].tile_type == TileType::Empty;
     }
@@ -932,7 +1311,68 @@ impl Map {
         return self.tiles[0].len() as i32;
     }
 
+    /// Commit the edits made so far, and record those made from now on as made in the
+    /// given turn by the given entity, or by the environment if there is no entity.
+    pub fn begin_edits(&mut self, turn: usize, entity_id: Option<EntityId>) {
+        self.commit_edits();
+        self.journal.recording = true;
+        self.journal.turn = turn;
+        self.journal.entity_id = entity_id;
+    }
+
+    /// Add the tiles which changed since they were borrowed to the journal. Changes to
+    /// whether a tile is explored are not changes to the terrain, so they are left out.
+    pub fn commit_edits(&mut self) {
+        let pending = std::mem::replace(&mut self.journal.pending, Vec::new());
+
+        for (pos, from) in pending {
+            let to = self.tiles[pos.x as usize][pos.y as usize];
+
+            let mut explored_from = from;
+            explored_from.explored = to.explored;
+            if explored_from != to {
+                let edit = TileEdit { turn: self.journal.turn, entity_id: self.journal.entity_id, pos, from, to };
+                self.journal.edits.push(edit);
+            }
+        }
+    }
+
+    /// Undo the edits made in the given turn and every turn after it, newest first,
+    /// returning how many were undone.
+    pub fn undo_edits(&mut self, turn: usize) -> usize {
+        self.commit_edits();
+
+        let mut num_undone = 0;
+        while let Some(edit) = self.journal.edits.last() {
+            if edit.turn < turn {
+                break;
+            }
+
+            let edit = self.journal.edits.pop().unwrap();
+            self.tiles[edit.pos.x as usize][edit.pos.y as usize] = edit.from;
+            num_undone += 1;
+        }
+
+        if num_undone > 0 {
+            self.revision += 1;
+        }
+
+        return num_undone;
+    }
+
     pub fn is_in_fov(&self, start_pos: Pos, end_pos: Pos, radius: i32, low: bool) -> bool {
+        let key = (start_pos, end_pos, radius, low);
+        if let Some(in_fov) = self.fov_cache.in_fov(self.revision, key) {
+            return in_fov;
+        }
+
+        let in_fov = self.compute_in_fov(start_pos, end_pos, radius, low);
+        self.fov_cache.insert_in_fov(self.revision, key, in_fov);
+
+        return in_fov;
+    }
+
+    fn compute_in_fov(&self, start_pos: Pos, end_pos: Pos, radius: i32, low: bool) -> bool {
         let alg_fov = self.is_in_fov_shadowcast(start_pos, end_pos);
         
         let path_fov =
@@ -955,8 +1395,8 @@ impl Map {
     }
 
     pub fn is_in_fov_shadowcast(&self, start_pos: Pos, end_pos: Pos) -> bool {
-        if let Some(visible) = self.fov_cache.borrow_mut().get(&start_pos) {
-            return visible.contains(&end_pos);
+        if let Some(visible) = self.fov_cache.is_visible(self.revision, start_pos, end_pos) {
+            return visible;
         }
 
         // NOTE(perf) this pre-allocation speeds up FOV significantly
@@ -982,7 +1422,7 @@ impl Map {
         compute_fov((start_pos.x as isize, start_pos.y as isize), &mut is_blocking, &mut mark_fov);
 
         let in_fov = visible_positions.contains(&end_pos);
-        self.fov_cache.borrow_mut().insert(start_pos, visible_positions);
+        self.fov_cache.insert(self.revision, start_pos, visible_positions);
 
         return in_fov;
     }
@@ -1083,6 +1523,109 @@ impl Map {
                          .collect::<Vec<Pos>>();
     }
 
+    pub fn gas(&self, pos: Pos) -> Gas {
+        return self.gas[pos.x as usize][pos.y as usize];
+    }
+
+    pub fn add_gas(&mut self, pos: Pos, typ: GasType, amount: f32) {
+        self.gas[pos.x as usize][pos.y as usize].add(typ, amount);
+    }
+
+    /// Whether smoke at least as thick as the threshold lies on the line between
+    /// two positions, including the end position but not the start.
+    pub fn smoke_blocks_sight(&self, start_pos: Pos, end_pos: Pos, threshold: f32) -> bool {
+        return line(start_pos, end_pos).iter()
+                                       .any(|pos| self.is_within_bounds(*pos) && self.gas(*pos).smoke >= threshold);
+    }
+
+    /// Let each tile's gas drift into the tiles around it, through any path
+    /// not blocked by a wall, and then thin out.
+    pub fn diffuse_gas(&mut self, spread: f32, decay: f32) {
+        let mut new_gas = self.gas.clone();
+
+        for pos in self.get_all_pos() {
+            let gas = self.gas(pos);
+            if gas.is_empty() {
+                continue;
+            }
+
+            for neighbor in self.reachable_neighbors(pos) {
+                if !self.is_within_bounds(neighbor) {
+                    continue;
+                }
+
+                for typ in [GasType::Smoke, GasType::Poison].iter() {
+                    let moved = gas.amount(*typ) * spread / 8.0;
+                    new_gas[neighbor.x as usize][neighbor.y as usize].add(*typ, moved);
+                    new_gas[pos.x as usize][pos.y as usize].add(*typ, -moved);
+                }
+            }
+        }
+
+        for column in new_gas.iter_mut() {
+            for gas in column.iter_mut() {
+                gas.smoke *= 1.0 - decay;
+                gas.poison *= 1.0 - decay;
+
+                if gas.smoke < GAS_MIN_AMOUNT {
+                    gas.smoke = 0.0;
+                }
+
+                if gas.poison < GAS_MIN_AMOUNT {
+                    gas.poison = 0.0;
+                }
+            }
+        }
+
+        self.gas = new_gas;
+    }
+
+    pub fn sound(&self, pos: Pos) -> f32 {
+        return self.sound[pos.x as usize][pos.y as usize];
+    }
+
+    /// Record a sound heard on each tile of its area of effect. Sounds are loudest at
+    /// their source and fade toward the edge of the area, and a tile keeps the loudest
+    /// sound heard on it.
+    pub fn add_sound(&mut self, sound_aoe: &Aoe) {
+        let num_rings = sound_aoe.positions.len();
+        for (dist, positions) in sound_aoe.positions.iter().enumerate() {
+            let amount = 1.0 - dist as f32 / num_rings as f32;
+
+            for pos in positions.iter() {
+                if self.is_within_bounds(*pos) {
+                    let sound = &mut self.sound[pos.x as usize][pos.y as usize];
+                    *sound = sound.max(amount);
+                }
+            }
+        }
+    }
+
+    /// Let the sounds heard on each tile fade, as a turn passes.
+    pub fn decay_sound(&mut self, decay: f32) {
+        for column in self.sound.iter_mut() {
+            for sound in column.iter_mut() {
+                *sound *= 1.0 - decay;
+
+                if *sound < SOUND_MIN_AMOUNT {
+                    *sound = 0.0;
+                }
+            }
+        }
+    }
+
+    /// All positions whose tiles share the given link id.
+    pub fn linked_positions(&self, link: u8) -> Vec<Pos> {
+        return self.get_all_pos().into_iter()
+                                 .filter(|pos| link != 0 && self[*pos].link == link)
+                                 .collect::<Vec<Pos>>();
+    }
+
+    /// The largest link id used on the map, so newly placed links can be numbered after it.
+    pub fn max_link(&self) -> u8 {
+        return self.get_all_pos().iter().map(|pos| self[*pos].link).max().unwrap_or(0);
+    }
+
     pub fn get_empty_pos(&self) -> Vec<Pos> {
         let (width, height) = self.size();
         return (0..width).cartesian_product(0..height)
@@ -1118,7 +1661,8 @@ impl Index<(i32, i32)> for Map {
 
 impl IndexMut<(i32, i32)> for Map {
     fn index_mut(&mut self, index: (i32, i32)) -> &mut Tile {
-        self.fov_cache.borrow_mut().clear();
+        self.revision += 1;
+        self.journal.borrowed(Pos::new(index.0, index.1), self.tiles[index.0 as usize][index.1 as usize]);
         &mut self.tiles[index.0 as usize][index.1 as usize]
     }
 }
@@ -1133,7 +1677,8 @@ impl Index<Pos> for Map {
 
 impl IndexMut<Pos> for Map {
     fn index_mut(&mut self, index: Pos) -> &mut Tile {
-        self.fov_cache.borrow_mut().clear();
+        self.revision += 1;
+        self.journal.borrowed(index, self.tiles[index.x as usize][index.y as usize]);
         &mut self.tiles[index.x as usize][index.y as usize]
     }
 }
@@ -1396,6 +1941,70 @@ fn test_fov_blocked_by_wall_right() {
     assert_eq!(false, map.is_in_fov(Pos::new(4, 5), Pos::new(9, 5), radius, false));
 }
 
+#[test]
+fn test_fov_memo_invalidated() {
+    let radius = 10;
+    let mut map = Map::from_dims(10, 10);
+
+    let start_pos = Pos::new(2, 5);
+    let end_pos = Pos::new(7, 5);
+    for y_pos in 0..10 {
+        map[(5, y_pos)] = Tile::wall();
+    }
+    map[(5, 5)] = Tile::door(DoorState::Closed);
+    assert_eq!(false, map.is_in_fov(start_pos, end_pos, radius, false));
+
+    // opening the door changes the map's revision, so the remembered result is not used
+    let revision = map.revision;
+    map[(5, 5)].set_door(DoorState::Open);
+    assert!(map.revision > revision);
+    assert_eq!(true, map.is_in_fov(start_pos, end_pos, radius, false));
+
+    // as does knocking the door down and building a wall in its place
+    map[(5, 5)] = Tile::wall();
+    assert_eq!(false, map.is_in_fov(start_pos, end_pos, radius, false));
+
+    // remembered results are kept when copying the map, but not when saving it
+    let copy = map.clone();
+    assert_eq!(Some(false), copy.fov_cache.in_fov(copy.revision, (start_pos, end_pos, radius, false)));
+    let loaded: Map = serde_yaml::from_str(&serde_yaml::to_string(&map).unwrap()).unwrap();
+    assert_eq!(map.revision, loaded.revision);
+    assert_eq!(None, loaded.fov_cache.in_fov(loaded.revision, (start_pos, end_pos, radius, false)));
+}
+
+#[test]
+fn test_map_journal() {
+    let mut map = Map::from_dims(10, 10);
+
+    // changes are not recorded until the first turn begins
+    map[(1, 1)] = Tile::wall();
+    map.begin_edits(1, Some(7));
+    assert_eq!(0, map.journal.edits.len());
+
+    map[(2, 2)] = Tile::wall();
+    map[(3, 3)].explored = true;
+    map[(4, 4)] = Tile::wall();
+    map[(4, 4)] = Tile::empty();
+
+    // only tiles which ended the turn with different terrain are recorded
+    map.begin_edits(2, None);
+    assert_eq!(1, map.journal.turn_edits(1).count());
+    let edit = map.journal.edits[0];
+    assert_eq!((1, Some(7), Pos::new(2, 2)), (edit.turn, edit.entity_id, edit.pos));
+    assert_eq!((Tile::empty(), Tile::wall()), (edit.from, edit.to));
+
+    map[(2, 2)] = Tile::short_wall();
+    map.commit_edits();
+    assert_eq!(1, map.journal.turn_edits(2).count());
+
+    // undoing a turn restores the tiles as they were at its start
+    assert_eq!(1, map.undo_edits(2));
+    assert_eq!(Tile::wall(), map[(2, 2)]);
+    assert_eq!(1, map.undo_edits(0));
+    assert_eq!(Tile::empty(), map[(2, 2)]);
+    assert_eq!(Tile::wall(), map[(1, 1)]);
+}
+
 #[test]
 fn test_fov_blocked_by_wall_left() {
     let radius = 10;
@@ -1445,7 +2054,7 @@ fn test_fov_blocked_by_wall_down() {
 fn test_blocked_by_wall() {
     let mut map = Map::from_dims(10, 10);
 
-    map[(5, 5)] = Tile::water();
+    map[(5, 5)] = Tile::wall();
   
     assert!(map.path_blocked_move(Pos::new(4, 5), Pos::new(5, 5)).is_some());
     assert!(map.path_blocked_move(Pos::new(4, 5), Pos::new(7, 5)).is_some());
@@ -1485,3 +2094,28 @@ fn test_path_blocked_all() {
     assert_eq!(Wall::TallWall, blocked_positions[3].wall_type);
 }
 
+
+#[test]
+fn test_line_of_sight() {
+    let mut map = Map::from_dims(10, 10);
+    let start = Pos::new(0, 0);
+
+    assert_eq!(Visibility::Clear, map.line_of_sight(start, Pos::new(5, 0)));
+
+    // short walls and grass can be seen over, but give cover
+    map[(2, 0)] = Tile::short_wall();
+    map[(5, 0)] = Tile::grass();
+    assert_eq!(Visibility::Partial(75), map.line_of_sight(start, Pos::new(5, 0)));
+
+    // thin walls count as well, with short ones giving cover and tall ones blocking sight
+    map[(3, 1)].left_wall = Wall::ShortWall;
+    assert_eq!(Visibility::Partial(SHORT_WALL_COVER as u8), map.line_of_sight(Pos::new(0, 1), Pos::new(5, 1)));
+
+    map[(3, 1)].left_wall = Wall::TallWall;
+    assert_eq!(Visibility::Blocked, map.line_of_sight(Pos::new(0, 1), Pos::new(5, 1)));
+
+    // a wall can itself be seen, but not what is behind it
+    map[(3, 2)] = Tile::wall();
+    assert_eq!(Visibility::Clear, map.line_of_sight(Pos::new(0, 2), Pos::new(3, 2)));
+    assert_eq!(Visibility::Blocked, map.line_of_sight(Pos::new(0, 2), Pos::new(5, 2)));
+}
@@ -8,6 +8,7 @@ use crate::map::*;
 use crate::movement::{MoveType, MoveMode, Direction};
 use crate::ai::Behavior;
 use crate::movement::Attack;
+use crate::locale::Locale;
 
 
 #[derive(Copy, Clone, PartialEq, Debug, Deserialize, Serialize)]
@@ -21,25 +22,61 @@ pub enum Msg {
     BlinkTrapTriggered(EntityId, EntityId), // trap, entity
     Blink(EntityId),
     FreezeTrapTriggered(EntityId, EntityId), // trap, entity
+    TripwireTriggered(EntityId, EntityId), // trap, entity
+    NetTrapTriggered(EntityId, EntityId), // trap, entity
+    BladeTrapTriggered(EntityId, EntityId), // trap, entity
+    DisarmFailed(EntityId, EntityId), // entity, trap
     GateTriggered(EntityId, EntityId), // trap, entity
+    MechanismActivated(EntityId, EntityId), // mechanism, entity
+    AlarmRung(EntityId, EntityId), // entity, alarm
+    Reinforcement(EntityId, Pos), // monster answering an alarm, where it arrived
+    Scattered(EntityId), // a disturbed critter
+    ToggleLinked(EntityId, EntityId), // mechanism, linked entity
     Froze(EntityId, usize), // entity, num turns
+    KnockedOut(EntityId, EntityId), // attacker, target
+    Talk(EntityId, EntityId), // entity, entity talked to
+    OpenContainer(EntityId, EntityId), // entity, container
+    PickLock(EntityId, EntityId), // entity, container
+    Smash(EntityId, EntityId), // entity, container
+    Distract(EntityId, Pos, usize), // cause, position, radius
+    Grapple(EntityId, Pos), // entity, position towards which the hook is thrown
+    Grappled(EntityId, Pos), // entity, anchor position
+    WokeUp(EntityId),
+    BodyDiscovered(EntityId, EntityId), // monster, body
+    Ignite(Pos),
+    Burned(EntityId),
+    GasReleased(EntityId, Pos, GasType), // item, position, type of gas
+    Poisoned(EntityId),
+    BurnedOut(Pos),
     PlayerDeath,
     PickedUp(EntityId, EntityId), // entity, item
+    PickedUpCoins(EntityId, usize), // entity, number of coins
     PickUp(EntityId), // entity trying to pick up an item
     ItemThrow(EntityId, EntityId, Pos, Pos), // thrower, stone id, start, end
     TryAttack(EntityId, Attack, Pos), // attacker, attack description, attack pos
     Attack(EntityId, EntityId, Hp), // attacker, attacked, hp lost
-    Killed(EntityId, EntityId, Hp), // attacker, attacked, hp lost
+    AttackMissed(EntityId, EntityId), // attacker, attacked
+    CriticalHit(EntityId, EntityId, Hp), // attacker, attacked, hp lost
+    ShieldBlocked(EntityId, EntityId), // attacker, attacked
+    ShieldCrushed(EntityId, EntityId), // attacker, attacked
+    Knockback(EntityId, EntityId, Direction, Hp), // attacker, knocked back, direction, hp lost if knocked into something
+    Drowned(EntityId),
+    KnockedInto(EntityId, Pos, Hp), // knocked back, position hit, hp lost
+    Killed(Option<EntityId>, EntityId, Hp), // attacker (none for fire and gas), attacked, hp lost
     Push(EntityId, Direction, usize), // attacker, direction, amount
     Pushed(EntityId, EntityId, Direction, usize, bool), // attacker, attacked, direction, amount, move into pushed square
     TryMove(EntityId, Direction, usize, MoveMode),
     Moved(EntityId, MoveType, Pos),
     Interact(EntityId, Pos),
+    OpenDoor(EntityId, Pos),
+    CloseDoor(EntityId, Pos),
     JumpWall(EntityId, Pos, Pos), // current pos, new pos
     WallKick(EntityId, Pos),
     StateChange(EntityId, Behavior),
     Collided(EntityId, Pos),
     Yell(EntityId),
+    TookTurn(EntityId), // the entity's turn ends once this is resolved
+    ReadMessage(EntityId, Message), // the entity acted on a message, so it is removed
     ChangeMoveMode(EntityId, bool), // true = increase, false = decrease
     MoveMode(EntityId, MoveMode),
     TriedRunWithHeavyEquipment,
@@ -47,16 +84,23 @@ pub enum Msg {
     HammerSwing(EntityId, EntityId, Pos), // entity, item, position swung at
     HammerHitEntity(EntityId, EntityId), // entity, hit entity
     HammerHitWall(EntityId, Blocked),
+    WallDestroyed(EntityId, Blocked),
     Stabbed(EntityId, EntityId), // entity, hit entity
+    StealthKill(EntityId, EntityId), // attacker, attacked
+    ItemWorn(EntityId, EntityId, u32), // holder, item, uses left
+    ItemBroke(EntityId, EntityId), // holder, item
     FailedBlink(EntityId),
     NotEnoughEnergy(EntityId),
+    SkillOnCooldown(EntityId, Skill, usize), // entity, skill, turns left
     DropFailed(EntityId),
     DroppedItem(EntityId, EntityId),
+    ForcedDrop(EntityId, EntityId, Pos), // entity, item, position dropped at
+    PlaceItem(EntityId, EntityId, Pos), // entity, item, position chosen to put it down
     PlayerTurn,
     Triggered(EntityId, EntityId), // trap, entity
     Untriggered(EntityId, EntityId), // trap, entity
     AddClass(EntityClass),
-    SwapPrimaryItem,
+    SwapPrimaryItem(EntityId), // entity swapping the items in its hands
     DropItem(EntityId, EntityId), // entity, item
     GrassThrow(EntityId, Direction),
     GrassShoes(EntityId, ActionMode),
@@ -71,6 +115,7 @@ pub enum Msg {
     PassWall(EntityId, Pos),
     UseItem(EntityId, Pos, EntityId), // holding entity, position, item id
     ArmDisarmTrap(EntityId, EntityId), // acting entity, trap id
+    Shove(EntityId, EntityId, Direction), // acting entity, shoved entity, direction
     PlaceTrap(EntityId, Pos, EntityId), // placing entity, position, trap id
     SpawnedObject(EntityId, EntityType, Pos, EntityName, Direction),
     FaceTowards(EntityId, Pos),
@@ -92,10 +137,35 @@ impl fmt::Display for Msg {
             Msg::BlinkTrapTriggered(trap_id, entity_id) => write!(f, "blink_trap_triggered {} {}", trap_id, entity_id),
             Msg::Blink(entity_id) => write!(f, "blink {}", entity_id),
             Msg::FreezeTrapTriggered(trap_id, entity_id) => write!(f, "freeze_trap_triggered {} {}", trap_id, entity_id),
+            Msg::TripwireTriggered(trap_id, entity_id) => write!(f, "tripwire_triggered {} {}", trap_id, entity_id),
+            Msg::NetTrapTriggered(trap_id, entity_id) => write!(f, "net_trap_triggered {} {}", trap_id, entity_id),
+            Msg::BladeTrapTriggered(trap_id, entity_id) => write!(f, "blade_trap_triggered {} {}", trap_id, entity_id),
+            Msg::DisarmFailed(entity_id, trap_id) => write!(f, "disarm_failed {} {}", entity_id, trap_id),
             Msg::GateTriggered(trap_id, entity_id) => write!(f, "gate_triggered {} {}", trap_id, entity_id),
+            Msg::MechanismActivated(mechanism_id, entity_id) => write!(f, "mechanism_activated {} {}", mechanism_id, entity_id),
+            Msg::AlarmRung(entity_id, alarm_id) => write!(f, "alarm_rung {} {}", entity_id, alarm_id),
+            Msg::Reinforcement(entity_id, pos) => write!(f, "reinforcement {} {} {}", entity_id, pos.x, pos.y),
+            Msg::Scattered(entity_id) => write!(f, "scattered {}", entity_id),
+            Msg::ToggleLinked(mechanism_id, linked_id) => write!(f, "toggle_linked {} {}", mechanism_id, linked_id),
             Msg::Froze(entity_id, turns) => write!(f, "froze {} {}", entity_id, turns),
+            Msg::KnockedOut(entity_id, target_id) => write!(f, "knocked_out {} {}", entity_id, target_id),
+            Msg::Talk(entity_id, target_id) => write!(f, "talk {} {}", entity_id, target_id),
+            Msg::OpenContainer(entity_id, container_id) => write!(f, "open_container {} {}", entity_id, container_id),
+            Msg::PickLock(entity_id, container_id) => write!(f, "pick_lock {} {}", entity_id, container_id),
+            Msg::Smash(entity_id, container_id) => write!(f, "smash {} {}", entity_id, container_id),
+            Msg::WokeUp(entity_id) => write!(f, "woke_up {}", entity_id),
+            Msg::Distract(entity_id, pos, radius) => write!(f, "distract {} {} {} {}", entity_id, pos.x, pos.y, radius),
+            Msg::Grapple(entity_id, pos) => write!(f, "grapple {} {} {}", entity_id, pos.x, pos.y),
+            Msg::Grappled(entity_id, pos) => write!(f, "grappled {} {} {}", entity_id, pos.x, pos.y),
+            Msg::BodyDiscovered(entity_id, body_id) => write!(f, "body_discovered {} {}", entity_id, body_id),
+            Msg::Ignite(pos) => write!(f, "ignite {} {}", pos.x, pos.y),
+            Msg::Burned(entity_id) => write!(f, "burned {}", entity_id),
+            Msg::GasReleased(item_id, pos, typ) => write!(f, "gas_released {} {} {} {}", item_id, pos.x, pos.y, typ),
+            Msg::Poisoned(entity_id) => write!(f, "poisoned {}", entity_id),
+            Msg::BurnedOut(pos) => write!(f, "burned_out {} {}", pos.x, pos.y),
             Msg::PlayerDeath => write!(f, "player_death"),
             Msg::PickedUp(entity_id, item_id) => write!(f, "picked_up {} {}", entity_id, item_id),
+            Msg::PickedUpCoins(entity_id, amount) => write!(f, "picked_up_coins {} {}", entity_id, amount),
             Msg::PickUp(entity_id) => write!(f, "pickup {}", entity_id),
             Msg::ItemThrow(entity_id, item_id, start, end) => write!(f, "item_throw {} {} {} {} {} {}", entity_id, item_id, start.x, start.y, end.x, end.y),
             Msg::TryAttack(entity_id, attack, pos) => {
@@ -106,12 +176,22 @@ impl fmt::Display for Msg {
                 }
             }
             Msg::Attack(entity_id, target_id, hp) => write!(f, "attack {} {} {}", entity_id, target_id, hp),
-            Msg::Killed(entity_id, target_id, hp) => write!(f, "killed {} {} {}", entity_id, target_id, hp),
+            Msg::AttackMissed(entity_id, target_id) => write!(f, "attack_missed {} {}", entity_id, target_id),
+            Msg::CriticalHit(entity_id, target_id, hp) => write!(f, "critical_hit {} {} {}", entity_id, target_id, hp),
+            Msg::ShieldBlocked(entity_id, target_id) => write!(f, "shield_blocked {} {}", entity_id, target_id),
+            Msg::ShieldCrushed(entity_id, target_id) => write!(f, "shield_crushed {} {}", entity_id, target_id),
+            Msg::Knockback(entity_id, target_id, direction, hp) => write!(f, "knockback {} {} {} {}", entity_id, target_id, direction, hp),
+            Msg::Drowned(entity_id) => write!(f, "drowned {}", entity_id),
+            Msg::KnockedInto(entity_id, pos, hp) => write!(f, "knocked_into {} {} {} {}", entity_id, pos.x, pos.y, hp),
+            Msg::Killed(Some(entity_id), target_id, hp) => write!(f, "killed {} {} {}", entity_id, target_id, hp),
+            Msg::Killed(None, target_id, hp) => write!(f, "died {} {}", target_id, hp),
             Msg::Push(entity_id, direction, amount) => write!(f, "pushed {} {} {}", entity_id, direction, amount),
             Msg::Pushed(entity_id, target_id, direction, amount, follow) => write!(f, "pushed {} {} {} {} {}", entity_id, target_id, direction, amount, follow),
             Msg::TryMove(entity_id, direction, amount, move_mode) => write!(f, "try_move {} {} {} {}", entity_id, direction, amount, move_mode),
             Msg::Moved(entity_id, move_type, pos) => write!(f, "moved {} {} {} {}", entity_id, move_type, pos.x, pos.y),
             Msg::Interact(entity_id, pos) => write!(f, "interact {} {} {}", entity_id, pos.x, pos.y),
+            Msg::OpenDoor(entity_id, pos) => write!(f, "open_door {} {} {}", entity_id, pos.x, pos.y),
+            Msg::CloseDoor(entity_id, pos) => write!(f, "close_door {} {} {}", entity_id, pos.x, pos.y),
             Msg::JumpWall(entity_id, pos, new_pos) => write!(f, "jump_wall {} {} {} {} {}", entity_id, pos.x, pos.y, new_pos.x, new_pos.y),
             Msg::WallKick(entity_id, pos) => write!(f, "wall_kick {} {} {}", entity_id, pos.y, pos.y),
             Msg::StateChange(entity_id, behavior) => {
@@ -119,10 +199,21 @@ impl fmt::Display for Msg {
                     Behavior::Idle => write!(f, "state_change_idle {}", entity_id),
                     Behavior::Investigating(pos) => write!(f, "state_change_investigating {} {} {}", entity_id, pos.x, pos.y),
                     Behavior::Attacking(target_id) => write!(f, "state_change_attacking {} {}", entity_id, target_id),
+                    Behavior::Following(target_id) => write!(f, "state_change_following {} {}", entity_id, target_id),
+                    Behavior::RaisingAlarm(alarm_id) => write!(f, "state_change_raising_alarm {} {}", entity_id, alarm_id),
+                    Behavior::Fleeing(pos) => write!(f, "state_change_fleeing {} {} {}", entity_id, pos.x, pos.y),
                 }
             }
             Msg::Collided(entity_id, pos) => write!(f, "collided {} {} {}", entity_id, pos.x, pos.y),
             Msg::Yell(entity_id) => write!(f, "yell {}", entity_id),
+            Msg::TookTurn(entity_id) => write!(f, "took_turn {}", entity_id),
+            Msg::ReadMessage(entity_id, message) => {
+                match message {
+                    Message::Sound(cause_id, pos) => write!(f, "read_message_sound {} {} {} {}", entity_id, cause_id, pos.x, pos.y),
+                    Message::Attack(attacker_id) => write!(f, "read_message_attack {} {}", entity_id, attacker_id),
+                    Message::Distraction(pos) => write!(f, "read_message_distraction {} {} {}", entity_id, pos.x, pos.y),
+                }
+            }
             Msg::ChangeMoveMode(entity_id, upwards) => write!(f, "chage_move_mode {} {}", entity_id, upwards),
             Msg::MoveMode(entity_id, move_mode) => write!(f, "move_mode {} {}", entity_id, move_mode),
             Msg::TriedRunWithHeavyEquipment => write!(f, "tried_run_with_heavy_equipment"),
@@ -130,16 +221,23 @@ impl fmt::Display for Msg {
             Msg::HammerSwing(entity_id, item_id, pos) => write!(f, "hammer_swing {} {} {} {}", entity_id, item_id, pos.x, pos.y),
             Msg::HammerHitEntity(entity_id, target_id) => write!(f, "hammer_hit_entity {} {}", entity_id, target_id),
             Msg::HammerHitWall(entity_id, blocked) => write!(f, "hammer_hit_wall {} {} {} {} {} {}", entity_id, blocked.start_pos, blocked.end_pos, blocked.direction, blocked.blocked_tile, blocked.wall_type),
+            Msg::WallDestroyed(entity_id, blocked) => write!(f, "wall_destroyed {} {} {} {} {} {}", entity_id, blocked.start_pos, blocked.end_pos, blocked.direction, blocked.blocked_tile, blocked.wall_type),
             Msg::Stabbed(entity_id, target_id) => write!(f, "stabbed {} {}", entity_id, target_id),
+            Msg::StealthKill(attacker, attacked) => write!(f, "stealth_kill {} {}", attacker, attacked),
+            Msg::ItemWorn(entity_id, item_id, uses) => write!(f, "item_worn {} {} {}", entity_id, item_id, uses),
+            Msg::ItemBroke(entity_id, item_id) => write!(f, "item_broke {} {}", entity_id, item_id),
             Msg::FailedBlink(entity_id) => write!(f, "failed_blink {}", entity_id),
             Msg::NotEnoughEnergy(entity_id) => write!(f, "not_enough_energy {}", entity_id),
+            Msg::SkillOnCooldown(entity_id, skill, turns) => write!(f, "skill_on_cooldown {} {:?} {}", entity_id, skill, turns),
             Msg::DropFailed(entity_id) => write!(f, "drop_failed {}", entity_id),
             Msg::DroppedItem(entity_id, trap_id) => write!(f, "dropped_item {} {}", entity_id, trap_id),
+            Msg::ForcedDrop(entity_id, item_id, pos) => write!(f, "forced_drop {} {} {} {}", entity_id, item_id, pos.x, pos.y),
+            Msg::PlaceItem(entity_id, item_id, pos) => write!(f, "place_item {} {} {} {}", entity_id, item_id, pos.x, pos.y),
             Msg::PlayerTurn => write!(f, "player_turn"),
             Msg::Triggered(trap_id, entity_id) => write!(f, "triggered {} {}", trap_id, entity_id),
             Msg::Untriggered(trap_id, entity_id) => write!(f, "untriggered {} {}", trap_id, entity_id),
             Msg::AddClass(entity_class) => write!(f, "add_class {}", entity_class),
-            Msg::SwapPrimaryItem => write!(f, "swap_primary_item"),
+            Msg::SwapPrimaryItem(entity_id) => write!(f, "swap_primary_item {}", entity_id),
             Msg::DropItem(entity_id, item_id) => write!(f, "drop_item {} {}", entity_id, item_id),
             Msg::GrassThrow(entity_id, direction) => write!(f, "grass_throw {} {}", entity_id, direction),
             Msg::GrassShoes(entity_id, action_mode) => write!(f, "grass_shoes {} {}", entity_id, action_mode),
@@ -154,6 +252,7 @@ impl fmt::Display for Msg {
             Msg::PassWall(entity_id, pos) => write!(f, "pass_wall {} {} {}", entity_id, pos.x, pos.y),
             Msg::UseItem(entity_id, pos, item_id) => write!(f, "use_item {} {} {} {}", entity_id, pos.x, pos.y, item_id),
             Msg::ArmDisarmTrap(entity_id, trap_id) => write!(f, "arm_disarm_trap {} {}", entity_id, trap_id),
+            Msg::Shove(entity_id, shoved_id, direction) => write!(f, "shove {} {} {}", entity_id, shoved_id, direction),
             Msg::PlaceTrap(entity_id, pos, trap_id) => write!(f, "place_trap {} {} {} {}", entity_id, pos.x, pos.y, trap_id),
             Msg::SpawnedObject(entity_id, entity_type, pos, entity_name, facing) => write!(f, "spawned {} {} {} {} {} {}", entity_id, entity_type, pos.x, pos.y, entity_name, facing),
             Msg::FaceTowards(entity_id, pos) => write!(f, "face_towards {} {} {}", entity_id, pos.x, pos.y),
@@ -167,57 +266,94 @@ impl fmt::Display for Msg {
 
 
 impl Msg {
-    pub fn msg_line(&self, data: &GameData) -> String {
+    /// The line shown in the message log for this message, in the given locale.
+    /// Messages which are not shown give an empty line.
+    pub fn msg_line(&self, data: &GameData, locale: &Locale) -> String {
+        let name = |entity_id: &EntityId| locale.name(data.entities.name[entity_id]);
+
         match self {
             Msg::StartTurn => {
-                return "Starting turn".to_string();
+                return locale.text("msg.start_turn");
             }
 
             Msg::Crushed(_obj_id, _pos) => {
-                return "An object has been crushed".to_string();
+                return locale.text("msg.crushed");
             }
 
             Msg::Pass => {
-                return "Player passed their turn".to_string();
+                return locale.text("msg.pass");
             }
 
             Msg::BlinkTrapTriggered(_trap, _entity_id) => {
-                return "Blink trap triggered".to_string();
+                return locale.text("msg.blink_trap_triggered");
             }
 
             Msg::Blink(entity_id) => {
-                return format!("{:?} blinked",
-                               data.entities.name[entity_id].clone());
+                return locale.text_with("msg.blink", &[("entity", name(entity_id))]);
             }
 
             Msg::SoundTrapTriggered(_trap, _entity_id) => {
-                return "Sound trap triggered".to_string();
+                return locale.text("msg.sound_trap_triggered");
             }
 
             Msg::SpikeTrapTriggered(_trap, _entity_id) => {
-                return "Spike trap triggered".to_string();
+                return locale.text("msg.spike_trap_triggered");
             }
 
             Msg::FreezeTrapTriggered(_trap, _entity_id) => {
-                return "Freeze trap triggered".to_string();
+                return locale.text("msg.freeze_trap_triggered");
+            }
+
+            Msg::TripwireTriggered(_trap, _entity_id) => {
+                return locale.text("msg.tripwire_triggered");
+            }
+
+            Msg::NetTrapTriggered(_trap, entity_id) => {
+                return locale.text_with("msg.net_trap_triggered", &[("entity", name(entity_id))]);
+            }
+
+            Msg::BladeTrapTriggered(_trap, _entity_id) => {
+                return locale.text("msg.blade_trap_triggered");
+            }
+
+            Msg::DisarmFailed(entity_id, trap_id) => {
+                return locale.text_with("msg.disarm_failed", &[("entity", name(entity_id)), ("trap", name(trap_id))]);
             }
 
             Msg::GateTriggered(_trap, _entity_id) => {
-                return "Gate activated".to_string();
+                return locale.text("msg.gate_triggered");
+            }
+
+            Msg::MechanismActivated(mechanism_id, _entity_id) => {
+                return locale.text_with("msg.mechanism_activated", &[("mechanism", name(mechanism_id))]);
+            }
+
+            Msg::AlarmRung(entity_id, _alarm_id) => {
+                return locale.text_with("msg.alarm_rung", &[("entity", name(entity_id))]);
+            }
+
+            Msg::Reinforcement(entity_id, _pos) => {
+                return locale.text_with("msg.reinforcement", &[("entity", name(entity_id))]);
+            }
+
+            Msg::Scattered(entity_id) => {
+                return locale.text_with("msg.scattered", &[("entity", name(entity_id))]);
             }
 
             Msg::PlayerDeath => {
-                return "Player died!".to_string();
+                return locale.text("msg.player_death");
             }
 
             Msg::PickedUp(entity_id, item_id) => {
-                return format!("{:?} picked up a {:?}",
-                               data.entities.name[entity_id].clone(),
-                               data.entities.name[item_id].clone());
+                return locale.text_with("msg.picked_up", &[("entity", name(entity_id)), ("item", name(item_id))]);
+            }
+
+            Msg::PickedUpCoins(entity_id, amount) => {
+                return locale.text_with("msg.picked_up_coins", &[("entity", name(entity_id)), ("amount", amount.to_string())]);
             }
 
             Msg::ItemThrow(_thrower, _item, _start, _end) => {
-                return "Item throw".to_string();
+                return locale.text("msg.item_throw");
             }
 
             Msg::TryAttack(_entity_id, _attack_info, _attack_pos) => {
@@ -225,14 +361,47 @@ impl Msg {
             }
 
             Msg::Attack(attacker, attacked, damage) => {
-                return format!("{:?} attacked {:?} for {} damage",
-                               data.entities.name[attacker],
-                               data.entities.name[attacked],
-                               damage);
+                return locale.text_with("msg.attack", &[("attacker", name(attacker)),
+                                                        ("attacked", name(attacked)),
+                                                        ("damage", damage.to_string())]);
+            }
+
+            Msg::AttackMissed(attacker, attacked) => {
+                return locale.text_with("msg.attack_missed", &[("attacker", name(attacker)), ("attacked", name(attacked))]);
+            }
+
+            Msg::CriticalHit(attacker, attacked, damage) => {
+                return locale.text_with("msg.critical_hit", &[("attacker", name(attacker)),
+                                                              ("attacked", name(attacked)),
+                                                              ("damage", damage.to_string())]);
+            }
+
+            Msg::ShieldBlocked(attacker, attacked) => {
+                return locale.text_with("msg.shield_blocked", &[("attacker", name(attacker)), ("attacked", name(attacked))]);
+            }
+
+            Msg::ShieldCrushed(attacker, attacked) => {
+                return locale.text_with("msg.shield_crushed", &[("attacker", name(attacker)), ("attacked", name(attacked))]);
+            }
+
+            Msg::Knockback(_attacker, _attacked, _direction, _damage) => {
+                return "".to_string();
+            }
+
+            Msg::Drowned(entity_id) => {
+                return locale.text_with("msg.drowned", &[("entity", name(entity_id))]);
+            }
+
+            Msg::KnockedInto(entity_id, _pos, damage) => {
+                return locale.text_with("msg.knocked_into", &[("entity", name(entity_id)), ("damage", damage.to_string())]);
             }
 
-            Msg::Killed(attacker, attacked, _damage) => {
-                return format!("{:?} killed {:?}", data.entities.name[attacker], data.entities.name[attacked]);
+            Msg::Killed(Some(attacker), attacked, _damage) => {
+                return locale.text_with("msg.killed", &[("attacker", name(attacker)), ("attacked", name(attacked))]);
+            }
+
+            Msg::Killed(None, attacked, _damage) => {
+                return locale.text_with("msg.died", &[("entity", name(attacked))]);
             }
 
             Msg::Push(_attacker, _direction, _amount) => {
@@ -240,18 +409,26 @@ impl Msg {
             }
 
             Msg::Pushed(attacker, attacked, _direction, _amount, _move_into) => {
-                return format!("{:?} pushed {:?}", data.entities.name[attacker], data.entities.name[attacked]);
+                return locale.text_with("msg.pushed", &[("attacker", name(attacker)), ("attacked", name(attacked))]);
             }
 
             Msg::Interact(_entity_id, _pos) => {
                 return "".to_string();
             }
 
+            Msg::OpenDoor(entity_id, _pos) => {
+                return locale.text_with("msg.open_door", &[("entity", name(entity_id))]);
+            }
+
+            Msg::CloseDoor(entity_id, _pos) => {
+                return locale.text_with("msg.close_door", &[("entity", name(entity_id))]);
+            }
+
             Msg::Moved(entity_id, move_type, pos) => {
                 if let MoveType::Pass = move_type {
-                    return format!("{:?} passed their turn", data.entities.name[entity_id]);
+                    return locale.text_with("msg.passed", &[("entity", name(entity_id))]);
                 } else {
-                    return format!("{:?} moved to {}", data.entities.name[entity_id], pos);
+                    return locale.text_with("msg.moved", &[("entity", name(entity_id)), ("pos", pos.to_string())]);
                 }
             }
 
@@ -260,23 +437,23 @@ impl Msg {
             }
 
             Msg::JumpWall(_entity_id, _start, _end) => {
-                return "Jumped a wall".to_string();
+                return locale.text("msg.jump_wall");
             }
 
             Msg::WallKick(_entity_id, _pos) => {
-                return "Did a wallkick".to_string();
+                return locale.text("msg.wall_kick");
             }
 
             Msg::StateChange(_entity_id, behavior) => {
-                return format!("Changed state to {:?}", *behavior);
+                return locale.text_with("msg.state_change", &[("behavior", format!("{:?}", *behavior))]);
             }
 
             Msg::Yell(entity_id) => {
-                return format!("{:?} yelled", data.entities.name[entity_id]);
+                return locale.text_with("msg.yell", &[("entity", name(entity_id))]);
             }
 
             Msg::Collided(_entity_id, _pos) => {
-                return "Collided".to_string();
+                return locale.text("msg.collided");
             }
 
             Msg::ChangeMoveMode(_entity_id, _increase) => {
@@ -286,141 +463,218 @@ impl Msg {
             Msg::MoveMode(entity_id, move_mode) => {
                 match move_mode {
                     MoveMode::Sneak => {
-                        return format!("{:?} is now sneaking", data.entities.name[entity_id]);
+                        return locale.text_with("msg.move_mode_sneak", &[("entity", name(entity_id))]);
                     }
 
                     MoveMode::Walk => {
-                        return format!("{:?} is now walking", data.entities.name[entity_id]);
+                        return locale.text_with("msg.move_mode_walk", &[("entity", name(entity_id))]);
                     }
 
                     MoveMode::Run => {
-                        return format!("{:?} is now running", data.entities.name[entity_id]);
+                        return locale.text_with("msg.move_mode_run", &[("entity", name(entity_id))]);
                     }
                 }
             }
 
             Msg::TriedRunWithHeavyEquipment => {
-                return "Your equipment is too heavy to run!".to_string();
+                return locale.text("msg.tried_run_with_heavy_equipment");
             }
 
             Msg::SwordSwing(entity_id, _item_id, _pos) => {
-                return format!("{:?} swung their sword", data.entities.name[entity_id]);
+                return locale.text_with("msg.sword_swing", &[("entity", name(entity_id))]);
             }
 
             Msg::HammerSwing(entity_id, _item_id, _pos) => {
-                return format!("{:?} swung their hammer", data.entities.name[entity_id]);
+                return locale.text_with("msg.hammer_swing", &[("entity", name(entity_id))]);
             }
 
             Msg::HammerHitEntity(entity_id, hit_entity) => {
-                let entity_name = &data.entities.name[entity_id];
-                let hit_entity_name = &data.entities.name[hit_entity];
-                return format!("{:?} hit {:?} with their hammer", entity_name, hit_entity_name);
+                return locale.text_with("msg.hammer_hit_entity", &[("entity", name(entity_id)), ("target", name(hit_entity))]);
             }
 
             Msg::HammerHitWall(entity_id, _blocked) => {
-                return format!("{:?} hit a wall with their hammer", data.entities.name[entity_id]);
+                return locale.text_with("msg.hammer_hit_wall", &[("entity", name(entity_id))]);
+            }
+
+            Msg::WallDestroyed(entity_id, _blocked) => {
+                return locale.text_with("msg.wall_destroyed", &[("entity", name(entity_id))]);
             }
 
             Msg::Stabbed(attacker, attacked) => {
-                let entity_name = &data.entities.name[attacker];
-                let hit_entity_name = &data.entities.name[attacked];
-                return format!("{:?} stabbed {:?} with a dagger!", entity_name, hit_entity_name);
+                return locale.text_with("msg.stabbed", &[("attacker", name(attacker)), ("attacked", name(attacked))]);
+            }
+
+            Msg::StealthKill(attacker, attacked) => {
+                return locale.text_with("msg.stealth_kill", &[("attacker", name(attacker)), ("attacked", name(attacked))]);
+            }
+
+            Msg::ItemWorn(entity_id, item_id, uses) => {
+                return locale.text_with("msg.item_worn", &[("entity", name(entity_id)), ("item", name(item_id)), ("uses", uses.to_string())]);
+            }
+
+            Msg::ItemBroke(entity_id, item_id) => {
+                return locale.text_with("msg.item_broke", &[("entity", name(entity_id)), ("item", name(item_id))]);
             }
 
             Msg::FailedBlink(entity_id) => {
-                return format!("{:?} failed to blink!", data.entities.name[entity_id]);
+                return locale.text_with("msg.failed_blink", &[("entity", name(entity_id))]);
             }
 
             Msg::NotEnoughEnergy(entity_id) => {
-                return format!("{:?} does not have enough energy for that", data.entities.name[entity_id]);
+                return locale.text_with("msg.not_enough_energy", &[("entity", name(entity_id))]);
+            }
+
+            Msg::SkillOnCooldown(_entity_id, skill, turns) => {
+                return locale.text_with("msg.skill_on_cooldown", &[("skill", format!("{:?}", skill)), ("turns", turns.to_string())]);
             }
 
             Msg::DropFailed(entity_id) => {
-                return format!("{:?} tried to drop an item, but its too crowded!", data.entities.name[entity_id]);
+                return locale.text_with("msg.drop_failed", &[("entity", name(entity_id))]);
+            }
+
+            Msg::ForcedDrop(entity_id, item_id, _pos) => {
+                return locale.text_with("msg.forced_drop", &[("entity", name(entity_id)), ("item", name(item_id))]);
+            }
+
+            Msg::PlaceItem(entity_id, item_id, _pos) => {
+                return locale.text_with("msg.place_item", &[("entity", name(entity_id)), ("item", name(item_id))]);
+            }
+
+            Msg::KnockedOut(entity_id, target_id) => {
+                return locale.text_with("msg.knocked_out", &[("entity", name(entity_id)), ("target", name(target_id))]);
+            }
+
+            Msg::WokeUp(entity_id) => {
+                return locale.text_with("msg.woke_up", &[("entity", name(entity_id))]);
+            }
+
+            Msg::Talk(_entity_id, target_id) => {
+                return locale.text_with("msg.talk", &[("target", name(target_id))]);
+            }
+
+            Msg::OpenContainer(entity_id, container_id) => {
+                return locale.text_with("msg.open_container", &[("entity", name(entity_id)), ("container", name(container_id))]);
+            }
+
+            Msg::PickLock(entity_id, container_id) => {
+                return locale.text_with("msg.pick_lock", &[("entity", name(entity_id)), ("container", name(container_id))]);
+            }
+
+            Msg::Smash(entity_id, container_id) => {
+                return locale.text_with("msg.smash", &[("entity", name(entity_id)), ("container", name(container_id))]);
+            }
+
+            Msg::Distract(entity_id, _pos, _radius) => {
+                if data.entities.name[entity_id] == EntityName::Noisemaker {
+                    return locale.text_with("msg.distract", &[("entity", name(entity_id))]);
+                } else {
+                    return "".to_string();
+                }
+            }
+
+            Msg::Grappled(entity_id, _pos) => {
+                return locale.text_with("msg.grappled", &[("entity", name(entity_id))]);
+            }
+
+            Msg::BodyDiscovered(entity_id, _body_id) => {
+                return locale.text_with("msg.body_discovered", &[("entity", name(entity_id))]);
             }
 
             Msg::Froze(entity_id, _num_turns) => {
-                return format!("{:?} was frozen!", data.entities.name[entity_id]);
+                return locale.text_with("msg.froze", &[("entity", name(entity_id))]);
+            }
+
+            Msg::Burned(entity_id) => {
+                return locale.text_with("msg.burned", &[("entity", name(entity_id))]);
+            }
+
+            Msg::GasReleased(_item_id, _pos, typ) => {
+                return locale.text_with("msg.gas_released", &[("gas", typ.to_string())]);
+            }
+
+            Msg::Poisoned(entity_id) => {
+                return locale.text_with("msg.poisoned", &[("entity", name(entity_id))]);
             }
 
             Msg::Triggered(_trap, entity_id) => {
-                return format!("{:?} triggered something!", data.entities.name[entity_id]);
+                return locale.text_with("msg.triggered", &[("entity", name(entity_id))]);
             }
 
             Msg::Untriggered(_trap, entity_id) => {
-                return format!("{:?} stepped off something!", data.entities.name[entity_id]);
+                return locale.text_with("msg.untriggered", &[("entity", name(entity_id))]);
             }
 
             Msg::AddClass(class) => {
-                return format!("Player chose class {:?}!", class);
+                return locale.text_with("msg.add_class", &[("class", format!("{:?}", class))]);
             }
             
-            Msg::SwapPrimaryItem => {
-                return format!("Player swapped primary and secondary item");
+            Msg::SwapPrimaryItem(entity_id) => {
+                let primary = data.entities.held_item(*entity_id, Hand::Primary).map_or(String::new(), |item_id| name(&item_id));
+                return locale.text_with("msg.swap_primary_item", &[("entity", name(entity_id)), ("item", primary)]);
             }
 
-            Msg::DropItem(entity_id, item_index) => {
-                if let Some(item_id) = data.entities.inventory[entity_id].get(*item_index as usize) {
-                    return format!("{:?} dropped a {:?}!", data.entities.name[entity_id], item_id);
-                } else {
-                    return "".to_string();
-                }
+            Msg::DropItem(entity_id, item_id) => {
+                return locale.text_with("msg.drop_item", &[("entity", name(entity_id)), ("item", name(item_id))]);
             }
 
             Msg::GrassThrow(entity_id, direction) => {
-                return format!("{:?} threw grass {}", data.entities.name[entity_id], direction);
+                return locale.text_with("msg.grass_throw", &[("entity", name(entity_id)), ("direction", direction.to_string())]);
             }
 
             Msg::GrassShoes(entity_id, _action_mode) => {
-                return format!("{:?} used grass shoes", data.entities.name[entity_id]);
+                return locale.text_with("msg.grass_shoes", &[("entity", name(entity_id))]);
             }
 
             Msg::GrassBlade(entity_id, _action_mode, _direction) => {
-                return format!("{:?} used grass blade", data.entities.name[entity_id]);
+                return locale.text_with("msg.grass_blade", &[("entity", name(entity_id))]);
             }
 
             Msg::Illuminate(entity_id, _pos, _amount) => {
-                return format!("{:?} illuminated their surroundings", data.entities.name[entity_id]);
+                return locale.text_with("msg.illuminate", &[("entity", name(entity_id))]);
             }
 
             Msg::Heal(entity_id, amount) => {
-                return format!("{:?} healed by {}", data.entities.name[entity_id], amount);
+                return locale.text_with("msg.heal", &[("entity", name(entity_id)), ("amount", amount.to_string())]);
             }
 
             Msg::FarSight(entity_id, amount) => {
-                return format!("{:?} has farsight ({})", data.entities.name[entity_id], amount);
+                return locale.text_with("msg.far_sight", &[("entity", name(entity_id)), ("amount", amount.to_string())]);
             }
 
             Msg::Sprint(entity_id, _direction, _amount) => {
-                return format!("{:?} has sprinted!", data.entities.name[entity_id]);
+                return locale.text_with("msg.sprint", &[("entity", name(entity_id))]);
             }
 
             Msg::Rubble(entity_id, pos) => {
-                return format!("{:?} turned a wall to rubble at {}", data.entities.name[entity_id], pos);
+                return locale.text_with("msg.rubble", &[("entity", name(entity_id)), ("pos", pos.to_string())]);
             }
 
             Msg::Reform(entity_id, _pos) => {
-                return format!("{:?} turns rubble into wall", data.entities.name[entity_id]);
+                return locale.text_with("msg.reform", &[("entity", name(entity_id))]);
             }
 
             Msg::Swap(entity_id, other_id) => {
-                return format!("{:?} swaps with {:?}", data.entities.name[entity_id], data.entities.name[other_id]);
+                return locale.text_with("msg.swap", &[("entity", name(entity_id)), ("other", name(other_id))]);
             }
             
            Msg::PassWall(entity_id, pos) => { 
-                return format!("{:?} passes through {}", data.entities.name[entity_id], pos);
+                return locale.text_with("msg.pass_wall", &[("entity", name(entity_id)), ("pos", pos.to_string())]);
             }
 
             Msg::UseItem(entity_id, pos, item_id) => {
-                return format!("{:?} used {:?} on {}", data.entities.name[entity_id], data.entities.name[item_id], pos);
+                return locale.text_with("msg.use_item", &[("entity", name(entity_id)), ("item", name(item_id)), ("pos", pos.to_string())]);
             }
 
             Msg::ArmDisarmTrap(entity_id, trap_id) => {
-                return format!("{:?} fiddles with {:?}", data.entities.name[entity_id], data.entities.name[trap_id]);
+                return locale.text_with("msg.arm_disarm_trap", &[("entity", name(entity_id)), ("trap", name(trap_id))]);
+            }
+
+            Msg::Shove(entity_id, shoved_id, _direction) => {
+                return locale.text_with("msg.shove", &[("entity", name(entity_id)), ("target", name(shoved_id))]);
             }
 
             Msg::PlaceTrap(entity_id, pos, trap_id) => {
-                return format!("{:?} place {:?} at {}", data.entities.name[entity_id], data.entities.name[trap_id], pos);
+                return locale.text_with("msg.place_trap", &[("entity", name(entity_id)), ("trap", name(trap_id)), ("pos", pos.to_string())]);
             }
 
             Msg::SpawnedObject(_entity_id, _entity_type, _pos, _entity_name, _facing) => {
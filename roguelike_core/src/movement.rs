@@ -477,6 +477,9 @@ pub enum Reach {
     Single(usize),
     Diag(usize),
     Horiz(usize),
+    Knight(usize), // an L shaped jump, dist one way and twice dist the other, like a chess knight
+    Line(usize),   // every tile in a line up to dist away
+    Cone(usize),   // a cone widening out to dist away
 }
 
 impl Reach {
@@ -496,11 +499,26 @@ impl Reach {
         return Reach::Horiz(dist);
     }
 
+    pub fn knight(dist: usize) -> Reach {
+        return Reach::Knight(dist);
+    }
+
+    pub fn line(dist: usize) -> Reach {
+        return Reach::Line(dist);
+    }
+
+    pub fn cone(dist: usize) -> Reach {
+        return Reach::Cone(dist);
+    }
+
     pub fn dist(&self) -> usize {
         match self {
             Reach::Single(dist) => *dist,
             Reach::Diag(dist) => *dist,
             Reach::Horiz(dist) => *dist,
+            Reach::Knight(dist) => *dist,
+            Reach::Line(dist) => *dist,
+            Reach::Cone(dist) => *dist,
         }
     }
 
@@ -509,22 +527,14 @@ impl Reach {
             Reach::Single(_) => Reach::Single(dist),
             Reach::Diag(_) => Reach::Diag(dist),
             Reach::Horiz(_) => Reach::Horiz(dist),
+            Reach::Knight(_) => Reach::Knight(dist),
+            Reach::Line(_) => Reach::Line(dist),
+            Reach::Cone(_) => Reach::Cone(dist),
         }
     }
 
     pub fn furthest_in_direction(&self, pos: Pos, dir: Direction) -> Option<Pos> {
-        let valid = 
-            match self {
-                Reach::Diag(_) => dir.diag(),
-                Reach::Horiz(_) => dir.horiz(),
-                Reach::Single(_) => true,
-            };
-
-        if valid {
-            return Some(dir.offset_pos(pos, self.dist() as i32));
-        } else {
-            return None;
-        }
+        return self.move_with_reach(&dir).map(|offset| add_pos(pos, offset));
     }
 
     pub fn closest_to(&self, pos: Pos, other: Pos) -> Pos {
@@ -543,15 +553,35 @@ impl Reach {
     }
 
     pub fn attacks_with_reach(&self, move_action: &Direction) -> Vec<Pos> {
-        let mut positions = Vec::new();
+        return self.offsets_in_direction(*move_action);
+    }
 
-        if let Some(pos) = self.move_with_reach(move_action) {
-            for pos in line_inclusive(Pos::new(0, 0), pos) {
-                positions.push(Pos::from(pos));
+    /// The offsets covered when used in a single direction. Lines and cones cover
+    /// every tile out to their distance, while a knight's jump covers only where it lands.
+    pub fn offsets_in_direction(&self, dir: Direction) -> Vec<Pos> {
+        match self {
+            Reach::Knight(_) => {
+                return self.move_with_reach(&dir).into_iter().collect();
             }
-        }
 
-        return positions;
+            Reach::Cone(dist) => {
+                // the cone starts at the origin, which is not itself reached
+                return Cone::new(Pos::new(0, 0), dir, *dist as i32 + 1).skip(1).collect();
+            }
+
+            _ => {
+                let mut positions = Vec::new();
+
+                if let Some(pos) = self.move_with_reach(&dir) {
+                    for pos in line_inclusive(Pos::new(0, 0), pos) {
+                        positions.push(Pos::from(pos));
+                    }
+                }
+                positions.dedup();
+
+                return positions;
+            }
+        }
     }
 
     pub fn move_with_reach(&self, move_action: &Direction) -> Option<Pos> {
@@ -600,9 +630,35 @@ impl Reach {
                     Direction::UpRight => None,
                 }
             }
+
+            // each direction gives the jump just clockwise of it, so all eight jumps can be made
+            Reach::Knight(dist) => {
+                let dist = (*dist) as i32;
+                let long = dist * 2;
+                match move_action {
+                    Direction::Up => Some(Pos::new(dist, -long)),
+                    Direction::UpRight => Some(Pos::new(long, -dist)),
+                    Direction::Right => Some(Pos::new(long, dist)),
+                    Direction::DownRight => Some(Pos::new(dist, long)),
+                    Direction::Down => Some(Pos::new(-dist, long)),
+                    Direction::DownLeft => Some(Pos::new(-long, dist)),
+                    Direction::Left => Some(Pos::new(-long, -dist)),
+                    Direction::UpLeft => Some(Pos::new(-dist, -long)),
+                }
+            }
+
+            Reach::Line(dist) | Reach::Cone(dist) => {
+                return Some(move_action.offset_pos(Pos::new(0, 0), *dist as i32));
+            }
         }
     }
 
+    pub fn reachables_in_direction(&self, start: Pos, dir: Direction) -> Vec<Pos> {
+        return self.offsets_in_direction(dir).iter()
+                                             .map(|off| add_pos(start, *off))
+                                             .collect::<Vec<Pos>>();
+    }
+
     pub fn reachables(&self, start: Pos) -> Vec<Pos> {
         let offsets = self.offsets();
         return offsets.iter()
@@ -610,53 +666,22 @@ impl Reach {
                       .collect::<Vec<Pos>>();
     }
 
-    pub fn offsets(&self) -> Vec<Pos> {
-        let end_points: Vec<Pos>;
-
-        match self {
-            Reach::Single(dist) => {
-                let dist = (*dist) as i32;
-                let offsets =
-                    vec!( (0, dist),      (-dist, dist), (-dist,  0),
-                          (-dist, -dist), (0,  -dist),   (dist, -dist),
-                          (dist,  0), (dist, dist));
-                end_points = offsets.iter().map(|pair| Pos::from(*pair)).collect();
-            },
-
-            Reach::Horiz(dist) => {
-                let dist = (*dist) as i32;
-                let mut offsets = vec!();
-                for dist in 1..=dist {
-                    offsets.push((dist, 0));
-                    offsets.push((0, dist));
-                    offsets.push((-1 * dist, 0));
-                    offsets.push((0, -1 * dist));
-                }
-                end_points = offsets.iter().map(|pair| Pos::from(*pair)).collect();
-            },
-
-
-            Reach::Diag(dist) => {
-                let mut offsets = vec!();
-                let dist = (*dist) as i32;
-                for dist in 1..=dist {
-                    offsets.push((dist, dist));
-                    offsets.push((-1 * dist, dist));
-                    offsets.push((dist, -1 * dist));
-                    offsets.push((-1 * dist, -1 * dist));
-                }
-                end_points = offsets.iter().map(|pair| Pos::from(*pair)).collect();
-            },
-        }
-
-        let mut offsets = Vec::new();
-        for end in end_points {
-            for pos in line_inclusive(Pos::new(0, 0), end) {
-                offsets.push(Pos::from(pos));
-            }
-        }
+    /// Every offset reached in any direction, without repeats.
+    pub fn iter_offsets(&self) -> impl Iterator<Item=Pos> + '_ {
+        let mut seen = Vec::new();
+        return Direction::move_actions().into_iter()
+                                        .flat_map(move |dir| self.offsets_in_direction(dir))
+                                        .filter(move |offset| {
+                                            if seen.contains(offset) {
+                                                return false;
+                                            }
+                                            seen.push(*offset);
+                                            return true;
+                                        });
+    }
 
-        return offsets;
+    pub fn offsets(&self) -> Vec<Pos> {
+        return self.iter_offsets().collect();
     }
 }
 
@@ -986,3 +1011,38 @@ pub fn direction(value: i32) -> i32 {
     }
 }
 
+
+#[test]
+pub fn test_reach_offsets_knight() {
+    let knight = Reach::Knight(1);
+    let offsets = knight.offsets();
+
+    let expected_pos =
+        vec!((1, 2), (2, 1), (-1, 2), (-2, 1), (1, -2), (2, -1), (-1, -2), (-2, -1)).iter()
+                                                                                 .map(|p| Pos::from(*p))
+                                                                                 .collect::<Vec<Pos>>();
+    assert_eq!(8, offsets.len());
+    assert!(offsets.iter().all(|p| expected_pos.iter().any(|other| other == p)));
+
+    // a knight jumps, so only its landing tile is attacked
+    assert_eq!(vec!(Pos::new(2, 1)), knight.attacks_with_reach(&Direction::Right));
+}
+
+#[test]
+pub fn test_reach_offsets_line_and_cone() {
+    let line = Reach::Line(3);
+    assert_eq!(vec!(Pos::new(1, 0), Pos::new(2, 0), Pos::new(3, 0)), line.offsets_in_direction(Direction::Right));
+    assert_eq!(24, line.offsets().len());
+
+    // a cone widens by a tile on each side for each tile it reaches out
+    let cone = Reach::Cone(2);
+    let offsets = cone.offsets_in_direction(Direction::Up);
+    assert_eq!(8, offsets.len());
+    assert!(offsets.contains(&Pos::new(0, -1)));
+    assert!(offsets.contains(&Pos::new(-2, -2)));
+    assert!(!offsets.contains(&Pos::new(0, 0)));
+    assert_eq!(Some(Pos::new(5, 3)), cone.furthest_in_direction(Pos::new(5, 5), Direction::Up));
+
+    // every tile within the cone's distance can be reached facing one way or another
+    assert_eq!(24, cone.offsets().len());
+}
@@ -1,5 +1,4 @@
 use std::collections::VecDeque;
-use std::sync::atomic::{AtomicU64, Ordering};
 use std::default::Default;
 use std::fmt;
 use std::str::FromStr;
@@ -14,7 +13,7 @@ use symbol::Symbol;
 
 use euclid::Point2D;
 
-use crate::ai::{Ai, Behavior};
+use crate::ai::{Ai, AiBudget, Behavior};
 use crate::map::*;
 use crate::movement::*;
 use crate::utils::*;
@@ -25,9 +24,7 @@ use crate::constants::*;
 
 pub type Name = Symbol;
 
-pub type EntityId = u64;
-
-pub type CompStore<T> = IndexMap<EntityId, T>;
+pub use crate::comp::{EntityId, CompStore, EntityAllocator};
 
 pub type Pos = Point2D<i32, ()>;
 
@@ -35,6 +32,21 @@ pub type Pos = Point2D<i32, ()>;
 pub struct GameData {
     pub map: Map,
     pub entities: Entities,
+    pub objective: Objective,
+    pub entry_points: Vec<Pos>, // tiles at the map's edge where reinforcements arrive
+    pub reinforcements: Vec<Reinforcement>, // monsters on their way after an alarm
+    #[serde(skip)]
+    pub ai_budget: AiBudget,
+}
+
+/// A monster answering an alarm. It arrives at an entry point once its turns run out,
+/// and heads for where the alarm was raised.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reinforcement {
+    pub name: EntityName,
+    pub pos: Pos,
+    pub target: Pos,
+    pub turns: usize,
 }
 
 impl GameData {
@@ -42,6 +54,10 @@ impl GameData {
         GameData {
             map,
             entities,
+            objective: Objective::default(),
+            entry_points: Vec::new(),
+            reinforcements: Vec::new(),
+            ai_budget: AiBudget::default(),
         }
     }
 
@@ -64,7 +80,7 @@ impl GameData {
                         reach: Reach,
                         must_reach: bool,
                         traps_block: bool,
-                        cost_fun: Option<fn(Pos, Pos, Pos, &GameData) -> Option<i32>>) -> Vec<Pos> {
+                        cost_fun: Option<&dyn Fn(Pos, Pos, Pos, &GameData) -> Option<i32>>) -> Vec<Pos> {
         let result;
 
         let maybe_results =
@@ -144,7 +160,7 @@ impl GameData {
         return self.fov_check(entity_id, other_pos, crouching, config);
     }
 
-    fn fov_check(&self, entity_id: EntityId, other_pos: Pos, crouching: bool, _config: &Config) -> bool {
+    fn fov_check(&self, entity_id: EntityId, other_pos: Pos, crouching: bool, config: &Config) -> bool {
         if other_pos.x < 0 || other_pos.y < 0 {
             return false;
         }
@@ -153,6 +169,7 @@ impl GameData {
 
         let radius: i32 = self.fov_radius(entity_id);
 
+        let in_fov;
         if self.entities.typ[&entity_id] == EntityType::Player {
             let mut can_see = self.map.is_in_fov(pos, other_pos, radius, crouching);
 
@@ -174,14 +191,17 @@ impl GameData {
                 }
             }
 
-            return can_see;
+            in_fov = can_see;
         } else {
             if let Some(dir) = self.entities.direction.get(&entity_id) {
-                return self.map.is_in_fov_direction(pos, other_pos, radius, *dir, crouching);
+                in_fov = self.map.is_in_fov_direction(pos, other_pos, radius, *dir, crouching);
             } else {
                 panic!(format!("tried to perform is_in_fov on entity without facing"));
             }
         }
+
+        // nothing sees into or through thick smoke
+        return in_fov && !self.map.smoke_blocks_sight(pos, other_pos, config.gas_threshold);
     }
 
     pub fn find_by_name(&self, name: EntityName) -> Option<EntityId> {
@@ -194,7 +214,7 @@ impl GameData {
         return None;
     }
 
-    pub fn get_entities_at_pos(&mut self, check_pos: Pos) -> Vec<EntityId> {
+    pub fn get_entities_at_pos(&self, check_pos: Pos) -> Vec<EntityId> {
         let mut object_ids: Vec<EntityId> = Vec::new();
 
         for key in self.entities.ids.iter() {
@@ -297,6 +317,17 @@ impl GameData {
         return None;
     }
 
+    /// Whether an entity is unaware of a trap- the trap is hidden, and the
+    /// entity has not seen it go off.
+    pub fn trap_hidden_from(&self, entity_id: EntityId, trap_id: EntityId) -> bool {
+        if let Some(trap) = self.entities.trap.get(&trap_id) {
+            let known = self.entities.known_traps.get(&entity_id).map_or(false, |traps| traps.contains(&trap_id));
+            return !trap.visible && !known;
+        }
+
+        return false;
+    }
+
     pub fn is_in_inventory(&self, entity_id: EntityId, item: Item) -> Option<EntityId> {
         for item_key in self.entities.inventory[&entity_id].iter() {
             if self.entities.item[item_key] == item {
@@ -307,18 +338,25 @@ impl GameData {
         return None;
     }
 
+    /// The item an entity is holding in its primary hand, if it is the given kind of item.
     pub fn using(&self, entity_id: EntityId, item: Item) -> Option<EntityId> {
-        if let Some(inventory) = self.entities.inventory.get(&entity_id) {
-            if let Some(item_id) = inventory.get(0) {
-                if self.entities.item[item_id] == item {
-                    return Some(*item_id);
-                }
+        if let Some(item_id) = self.entities.held_item(entity_id, Hand::Primary) {
+            if self.entities.item[&item_id] == item {
+                return Some(item_id);
             }
         }
 
         return None;
     }
 
+    /// How one entity regards another. Entities without a faction are neutral to everyone.
+    pub fn relation(&self, entity_id: EntityId, other_id: EntityId, config: &Config) -> Relation {
+        match (self.entities.faction.get(&entity_id), self.entities.faction.get(&other_id)) {
+            (Some(faction), Some(other)) => return faction.relation(*other, config),
+            _ => return Relation::Neutral,
+        }
+    }
+
     pub fn used_up_item(&mut self, entity_id: EntityId, item_id: EntityId) {
         if let Some(inventory) = self.entities.inventory.get_mut(&entity_id) {
             let item_index = inventory.iter()
@@ -363,7 +401,14 @@ impl GameData {
         // the player can't push the enemies
         let player_pushing = entity_type == EntityType::Player && other_type == EntityType::Enemy;
         let enemies_pushing_each_other = entity_type == EntityType::Enemy && other_type == EntityType::Enemy;
-        return !(player_pushing || enemies_pushing_each_other);
+        // gates are fixed in place, and only move when their mechanism is used
+        let pushing_gate = self.entities.name[&other_id] == EntityName::Gate;
+        return !(player_pushing || enemies_pushing_each_other || pushing_gate);
+    }
+
+    /// Gols are too heavy to swim, and sink in deep water, so they will not enter it.
+    pub fn sinks_in_water(&self, entity_id: EntityId) -> bool {
+        return self.entities.name[&entity_id] == EntityName::Gol;
     }
 
     // clear all entities, except those in the given vector.
@@ -387,14 +432,50 @@ impl GameData {
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
-pub enum Trap {
+pub enum TrapType {
     Spikes,
     Sound,
     Blink,
     Freeze,
+    Tripwire,
+    Net,
+    Blade,
 }
 
+/// When a trap goes off.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum TrapTrigger {
+    Step,     // an entity steps onto the trap
+    Adjacent, // an entity steps onto the trap, or next to it
+}
+
+/// The trap component. Whether a trap is armed is kept separately, in the 'armed' component.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Trap {
+    pub typ: TrapType,
+    pub trigger: TrapTrigger,
+    pub visible: bool,
+    pub rearm_turns: usize, // 0 for traps that are used up when triggered
+    pub rearm_count: usize, // turns left until the trap rearms itself
+}
+
+impl Trap {
+    pub fn new(typ: TrapType) -> Trap {
+        return Trap { typ, trigger: TrapTrigger::Step, visible: true, rearm_turns: 0, rearm_count: 0 };
+    }
+
+    /// Whether an entity at the given position sets off a trap at 'trap_pos'
+    pub fn triggered_by(&self, trap_pos: Pos, pos: Pos) -> bool {
+        match self.trigger {
+            TrapTrigger::Step => return trap_pos == pos,
+            TrapTrigger::Adjacent => {
+                return (trap_pos.x - pos.x).abs() <= 1 && (trap_pos.y - pos.y).abs() <= 1;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Skill {
     GrassThrow,
     GrassBlade,
@@ -411,6 +492,32 @@ pub enum Skill {
     Sprint,
 }
 
+impl Skill {
+    /// The energy spent to use a skill. Skills currently all cost a single energy.
+    pub fn energy_cost(&self) -> u32 {
+        return 1;
+    }
+
+    /// The number of turns after using a skill before it can be used again.
+    pub fn cooldown(&self) -> usize {
+        match self {
+            Skill::GrassThrow => 3,
+            Skill::GrassBlade => 2,
+            Skill::GrassShoes => 6,
+            Skill::Blink => 4,
+            Skill::PassWall => 3,
+            Skill::Rubble => 2,
+            Skill::Reform => 2,
+            Skill::Swap => 3,
+            Skill::Push => 3,
+            Skill::Illuminate => 5,
+            Skill::Heal => 8,
+            Skill::FarSight => 5,
+            Skill::Sprint => 4,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize, Default)]
 pub struct Color {
     pub r: u8,
@@ -445,7 +552,20 @@ pub enum GameState {
     Inventory,
     SkillMenu,
     ClassMenu,
-    ConfirmQuit,
+    Prompt,
+    Dialogue,
+    Container,
+    Selection,
+    Shop,
+    Sandbox,
+    Loading,
+    LevelComplete,
+    NewGame,
+    MainMenu,
+    Paused,
+    Options,
+    Help,
+    Error,
     Exit,
 }
 
@@ -458,7 +578,20 @@ impl fmt::Display for GameState {
             GameState::Inventory => write!(f, "inventory"),
             GameState::SkillMenu => write!(f, "skillmenu"),
             GameState::ClassMenu => write!(f, "classmenu"),
-            GameState::ConfirmQuit => write!(f, "confirmquit"),
+            GameState::Prompt => write!(f, "prompt"),
+            GameState::Dialogue => write!(f, "dialogue"),
+            GameState::Container => write!(f, "container"),
+            GameState::Selection => write!(f, "selection"),
+            GameState::Shop => write!(f, "shop"),
+            GameState::Sandbox => write!(f, "sandbox"),
+            GameState::Loading => write!(f, "loading"),
+            GameState::LevelComplete => write!(f, "levelcomplete"),
+            GameState::NewGame => write!(f, "newgame"),
+            GameState::MainMenu => write!(f, "mainmenu"),
+            GameState::Paused => write!(f, "paused"),
+            GameState::Options => write!(f, "options"),
+            GameState::Help => write!(f, "help"),
+            GameState::Error => write!(f, "error"),
             GameState::Exit => write!(f, "exit"),
         }
     }
@@ -474,10 +607,107 @@ impl GameState {
     pub fn is_menu(self) -> bool {
         return self == GameState::Inventory ||
                self == GameState::SkillMenu ||
-               self == GameState::ClassMenu;
+               self == GameState::ClassMenu ||
+               self == GameState::NewGame ||
+               self == GameState::MainMenu ||
+               self == GameState::Paused ||
+               self == GameState::Options ||
+               self == GameState::Prompt ||
+               self == GameState::Dialogue ||
+               self == GameState::Container ||
+               self == GameState::Shop ||
+               self == GameState::LevelComplete ||
+               self == GameState::Win ||
+               self == GameState::Lose ||
+               self == GameState::Error;
+    }
+}
+
+
+/// The kinds of objective a level can be given.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ObjectiveType {
+    KeyAndExit,
+    Rescue,
+    Assassinate,
+    Steal,
+    Survive,
+}
+
+impl fmt::Display for ObjectiveType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjectiveType::KeyAndExit => write!(f, "keyandexit"),
+            ObjectiveType::Rescue => write!(f, "rescue"),
+            ObjectiveType::Assassinate => write!(f, "assassinate"),
+            ObjectiveType::Steal => write!(f, "steal"),
+            ObjectiveType::Survive => write!(f, "survive"),
+        }
     }
 }
 
+impl FromStr for ObjectiveType {
+    type Err = String;
+
+    fn from_str(original_str: &str) -> Result<Self, Self::Err> {
+        let s: &mut str = &mut original_str.to_string();
+        s.make_ascii_lowercase();
+
+        if s == "keyandexit" {
+            return Ok(ObjectiveType::KeyAndExit);
+        } else if s == "rescue" {
+            return Ok(ObjectiveType::Rescue);
+        } else if s == "assassinate" {
+            return Ok(ObjectiveType::Assassinate);
+        } else if s == "steal" {
+            return Ok(ObjectiveType::Steal);
+        } else if s == "survive" {
+            return Ok(ObjectiveType::Survive);
+        }
+
+        return Err(format!("Could not parse '{}' as ObjectiveType", s));
+    }
+}
+
+/// What the player has to do to finish the current level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Objective {
+    KeyAndExit, // reach the exit carrying the key
+    Rescue, // reach the exit carrying the key, with every prisoner on the level beside you
+    Assassinate(EntityId), // kill the target, then reach the exit
+    Steal(EntityId, bool), // item to carry to the exit, whether the player has been noticed
+    Survive(usize), // turns left to survive
+}
+
+impl Objective {
+    pub fn typ(&self) -> ObjectiveType {
+        match self {
+            Objective::KeyAndExit => ObjectiveType::KeyAndExit,
+            Objective::Rescue => ObjectiveType::Rescue,
+            Objective::Assassinate(_) => ObjectiveType::Assassinate,
+            Objective::Steal(_, _) => ObjectiveType::Steal,
+            Objective::Survive(_) => ObjectiveType::Survive,
+        }
+    }
+}
+
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Objective::KeyAndExit => write!(f, "keyandexit"),
+            Objective::Rescue => write!(f, "rescue"),
+            Objective::Assassinate(target_id) => write!(f, "assassinate {}", target_id),
+            Objective::Steal(item_id, noticed) => write!(f, "steal {} {}", item_id, noticed),
+            Objective::Survive(turns) => write!(f, "survive {}", turns),
+        }
+    }
+}
+
+impl Default for Objective {
+    fn default() -> Objective {
+        return Objective::KeyAndExit;
+    }
+}
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
 pub enum ActionMode {
@@ -524,6 +754,14 @@ pub enum Item {
     SoundTrap,
     BlinkTrap,
     FreezeTrap,
+    Tripwire,
+    NetTrap,
+    BladeTrap,
+    SmokeBomb,
+    PoisonBomb,
+    Noisemaker,
+    Grapple,
+    Coins,
 }
 
 impl fmt::Display for Item {
@@ -540,6 +778,14 @@ impl fmt::Display for Item {
             Item::SoundTrap => write!(f, "soundtrap"),
             Item::BlinkTrap => write!(f, "blinktrap"),
             Item::FreezeTrap => write!(f, "freezetrap"),
+            Item::Tripwire => write!(f, "tripwire"),
+            Item::NetTrap => write!(f, "nettrap"),
+            Item::BladeTrap => write!(f, "bladetrap"),
+            Item::SmokeBomb => write!(f, "smokebomb"),
+            Item::PoisonBomb => write!(f, "poisonbomb"),
+            Item::Noisemaker => write!(f, "noisemaker"),
+            Item::Grapple => write!(f, "grapple"),
+            Item::Coins => write!(f, "coins"),
         }
     }
 }
@@ -570,6 +816,22 @@ impl FromStr for Item {
             return Ok(Item::BlinkTrap);
         } else if s == "freezetrap" {
             return Ok(Item::FreezeTrap);
+        } else if s == "tripwire" {
+            return Ok(Item::Tripwire);
+        } else if s == "nettrap" {
+            return Ok(Item::NetTrap);
+        } else if s == "bladetrap" {
+            return Ok(Item::BladeTrap);
+        } else if s == "smokebomb" {
+            return Ok(Item::SmokeBomb);
+        } else if s == "poisonbomb" {
+            return Ok(Item::PoisonBomb);
+        } else if s == "noisemaker" {
+            return Ok(Item::Noisemaker);
+        } else if s == "grapple" {
+            return Ok(Item::Grapple);
+        } else if s == "coins" {
+            return Ok(Item::Coins);
         }
 
         return Err(format!("Could not parse '{}' as Item", s));
@@ -590,6 +852,34 @@ impl Item {
             Item::SoundTrap => ItemClass::Secondary,
             Item::BlinkTrap => ItemClass::Secondary,
             Item::FreezeTrap => ItemClass::Secondary,
+            Item::Tripwire => ItemClass::Secondary,
+            Item::NetTrap => ItemClass::Secondary,
+            Item::BladeTrap => ItemClass::Secondary,
+            Item::SmokeBomb => ItemClass::Secondary,
+            Item::PoisonBomb => ItemClass::Secondary,
+            Item::Noisemaker => ItemClass::Secondary,
+            Item::Grapple => ItemClass::Secondary,
+            Item::Coins => ItemClass::Secondary,
+        }
+    }
+
+    /// How heavy an item is to carry. When swimming, the heaviest item is dropped.
+    pub fn weight(&self) -> usize {
+        match self {
+            Item::Hammer => 4,
+            Item::Shield => 3,
+            Item::Sword => 3,
+            Item::SpikeTrap | Item::SoundTrap | Item::BlinkTrap | Item::FreezeTrap |
+            Item::Tripwire | Item::NetTrap | Item::BladeTrap => 2,
+            Item::Dagger => 1,
+            Item::Stone => 1,
+            Item::Lantern => 1,
+            Item::SmokeBomb => 1,
+            Item::PoisonBomb => 1,
+            Item::Noisemaker => 1,
+            Item::Grapple => 2,
+            Item::Key => 0,
+            Item::Coins => 0,
         }
     }
 
@@ -606,6 +896,14 @@ impl Item {
             Item::SoundTrap => EntityName::SoundTrap,
             Item::BlinkTrap => EntityName::BlinkTrap,
             Item::FreezeTrap => EntityName::FreezeTrap,
+            Item::Tripwire => EntityName::Tripwire,
+            Item::NetTrap => EntityName::NetTrap,
+            Item::BladeTrap => EntityName::BladeTrap,
+            Item::SmokeBomb => EntityName::SmokeBomb,
+            Item::PoisonBomb => EntityName::PoisonBomb,
+            Item::Noisemaker => EntityName::Noisemaker,
+            Item::Grapple => EntityName::Grapple,
+            Item::Coins => EntityName::Coins,
         }
     }
 }
@@ -616,6 +914,23 @@ pub enum ItemClass {
     Secondary,
 }
 
+/// The two hands an entity can hold a primary item in. The primary hand's item is the one
+/// attacked with and used first, and the secondary hand holds a second weapon or a shield.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum Hand {
+    Primary,
+    Secondary,
+}
+
+impl fmt::Display for Hand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hand::Primary => write!(f, "primary"),
+            Hand::Secondary => write!(f, "secondary"),
+        }
+    }
+}
+
 pub type Hp = i32;
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
@@ -638,6 +953,14 @@ impl Default for InventoryAction {
     }
 }
 
+/// What a hotbar slot holds. An item slot uses any item of that kind in the inventory,
+/// so picking up another stone refills a slot holding stones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuickSlot {
+    Item(Item),
+    Skill(Skill),
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
 pub enum Stance {
     Crouching,
@@ -666,6 +989,52 @@ impl fmt::Display for Stance {
     }
 }
 
+/// The side an entity fights for. Members of a faction never fight each other, and
+/// which factions are at war is given in the config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum Faction {
+    Player,
+    Gols,
+    Elves,
+    Wildlife,
+}
+
+impl Faction {
+    pub fn relation(&self, other: Faction, config: &Config) -> Relation {
+        if *self == other {
+            return Relation::Friendly;
+        }
+
+        let hostile = config.faction_hostility.iter().any(|(first, second)| {
+            return (*first == *self && *second == other) || (*first == other && *second == *self);
+        });
+
+        if hostile {
+            return Relation::Hostile;
+        } else {
+            return Relation::Neutral;
+        }
+    }
+}
+
+impl fmt::Display for Faction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Faction::Player => write!(f, "player"),
+            Faction::Gols => write!(f, "gols"),
+            Faction::Elves => write!(f, "elves"),
+            Faction::Wildlife => write!(f, "wildlife"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Relation {
+    Friendly,
+    Neutral,
+    Hostile,
+}
+
 impl FromStr for Stance {
     type Err = String;
 
@@ -690,6 +1059,7 @@ pub enum EntityName {
     Gol,
     Pawn,
     Rook,
+    Knight,
     Column,
     Key,
     Exit,
@@ -704,8 +1074,26 @@ pub enum EntityName {
     BlinkTrap,
     FreezeTrap,
     SoundTrap,
+    Tripwire,
+    NetTrap,
+    BladeTrap,
     GateTrigger,
+    PressurePlate,
+    Lever,
+    Gate,
+    Chest,
+    Crate,
+    Alarm,
     Stone,
+    SmokeBomb,
+    PoisonBomb,
+    Noisemaker,
+    Grapple,
+    Coins,
+    Prisoner,
+    Partner,
+    Rat,
+    Bird,
     Mouse,
     Cursor,
     Energy,
@@ -725,6 +1113,7 @@ impl fmt::Display for EntityName {
             EntityName::Gol => write!(f, "gol"),
             EntityName::Pawn => write!(f, "pawn"),
             EntityName::Rook => write!(f, "rook"),
+            EntityName::Knight => write!(f, "knight"),
             EntityName::Column => write!(f, "column"),
             EntityName::Key => write!(f, "key"),
             EntityName::Exit => write!(f, "exit"),
@@ -739,8 +1128,26 @@ impl fmt::Display for EntityName {
             EntityName::BlinkTrap => write!(f, "blinktrap"),
             EntityName::FreezeTrap => write!(f, "freezetrap"),
             EntityName::SoundTrap => write!(f, "soundtrap"),
+            EntityName::Tripwire => write!(f, "tripwire"),
+            EntityName::NetTrap => write!(f, "nettrap"),
+            EntityName::BladeTrap => write!(f, "bladetrap"),
             EntityName::GateTrigger => write!(f, "gatetrigger"),
+            EntityName::PressurePlate => write!(f, "pressureplate"),
+            EntityName::Lever => write!(f, "lever"),
+            EntityName::Gate => write!(f, "gate"),
+            EntityName::Chest => write!(f, "chest"),
+            EntityName::Crate => write!(f, "crate"),
+            EntityName::Alarm => write!(f, "alarm"),
             EntityName::Stone => write!(f, "stone"),
+            EntityName::SmokeBomb => write!(f, "smokebomb"),
+            EntityName::PoisonBomb => write!(f, "poisonbomb"),
+            EntityName::Noisemaker => write!(f, "noisemaker"),
+            EntityName::Grapple => write!(f, "grapple"),
+            EntityName::Coins => write!(f, "coins"),
+            EntityName::Prisoner => write!(f, "prisoner"),
+            EntityName::Partner => write!(f, "partner"),
+            EntityName::Rat => write!(f, "rat"),
+            EntityName::Bird => write!(f, "bird"),
             EntityName::Mouse => write!(f, "mouse"),
             EntityName::Cursor => write!(f, "cursor"),
             EntityName::Energy => write!(f, "energy"),
@@ -764,6 +1171,8 @@ impl FromStr for EntityName {
             return Ok(EntityName::Pawn);
         } else if s == "rook" {
             return Ok(EntityName::Rook);
+        } else if s == "knight" {
+            return Ok(EntityName::Knight);
         } else if s == "column" {
             return Ok(EntityName::Column);
         } else if s == "key" {
@@ -792,10 +1201,46 @@ impl FromStr for EntityName {
             return Ok(EntityName::FreezeTrap);
         } else if s == "soundtrap" {
             return Ok(EntityName::SoundTrap);
+        } else if s == "tripwire" {
+            return Ok(EntityName::Tripwire);
+        } else if s == "nettrap" {
+            return Ok(EntityName::NetTrap);
+        } else if s == "bladetrap" {
+            return Ok(EntityName::BladeTrap);
         } else if s == "gatetrigger" {
             return Ok(EntityName::GateTrigger);
+        } else if s == "pressureplate" {
+            return Ok(EntityName::PressurePlate);
+        } else if s == "lever" {
+            return Ok(EntityName::Lever);
+        } else if s == "gate" {
+            return Ok(EntityName::Gate);
+        } else if s == "chest" {
+            return Ok(EntityName::Chest);
+        } else if s == "crate" {
+            return Ok(EntityName::Crate);
+        } else if s == "alarm" {
+            return Ok(EntityName::Alarm);
         } else if s == "stone" {
             return Ok(EntityName::Stone);
+        } else if s == "smokebomb" {
+            return Ok(EntityName::SmokeBomb);
+        } else if s == "poisonbomb" {
+            return Ok(EntityName::PoisonBomb);
+        } else if s == "noisemaker" {
+            return Ok(EntityName::Noisemaker);
+        } else if s == "grapple" {
+            return Ok(EntityName::Grapple);
+        } else if s == "coins" {
+            return Ok(EntityName::Coins);
+        } else if s == "prisoner" {
+            return Ok(EntityName::Prisoner);
+        } else if s == "partner" {
+            return Ok(EntityName::Partner);
+        } else if s == "rat" {
+            return Ok(EntityName::Rat);
+        } else if s == "bird" {
+            return Ok(EntityName::Bird);
         } else if s == "mouse" {
             return Ok(EntityName::Mouse);
         } else if s == "cursor" {
@@ -906,6 +1351,7 @@ impl EntityClass {
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct StatusEffect {
     pub frozen: usize, // turns
+    pub knocked_out: usize, // turns
     pub soft_steps: usize, // turns
     pub illuminate: usize, // radius
     pub extra_fov: usize, // amount
@@ -918,14 +1364,14 @@ pub struct StatusEffect {
 pub enum Message {
     Sound(EntityId, Pos),
     Attack(EntityId),
+    Distraction(Pos),
 }
 
-// ensure that each entity has a unique ID, up to 2^64 entities
-static OBJECT_ID_COUNT: AtomicU64 = AtomicU64::new(0);
-
 #[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
 pub struct Entities {
+    // ids in the order the entities were created
     pub ids: Vec<EntityId>,
+    pub allocator: EntityAllocator,
     pub pos: CompStore<Pos>,
     pub chr: CompStore<char>,
     pub name: CompStore<EntityName>,
@@ -941,22 +1387,31 @@ pub struct Entities {
     pub trap: CompStore<Trap>,
     pub armed: CompStore<bool>,
     pub energy: CompStore<u32>,
+    pub stamina: CompStore<u32>,
     pub count_down: CompStore<usize>,
+    pub fuse: CompStore<usize>,
+    pub durability: CompStore<u32>,
+    pub locked: CompStore<usize>, // turns of lock picking left before a container opens
+    pub coins: CompStore<usize>, // coins carried, or the number in a pile of coins
     pub move_mode: CompStore<MoveMode>,
     pub direction: CompStore<Direction>,
     pub selected_item: CompStore<EntityId>,
     pub class: CompStore<EntityClass>,
     pub skills: CompStore<Vec<Skill>>,
+    pub cooldowns: CompStore<IndexMap<Skill, usize>>,
     pub status: CompStore<StatusEffect>,
     pub gate_pos: CompStore<Option<Pos>>,
     pub stance: CompStore<Stance>,
+    pub faction: CompStore<Faction>,
+    pub interest: CompStore<usize>, // turns an investigating monster keeps searching without a new clue
     pub took_turn: CompStore<bool>,
 
     // NOTE not sure about keeping these ones, or packaging into larger ones
-    pub sound: CompStore<Pos>, // source position
     pub typ: CompStore<EntityType>,
     pub color: CompStore<Color>,
     pub blocks: CompStore<bool>,
+    pub pushable: CompStore<bool>,
+    pub known_traps: CompStore<Vec<EntityId>>,
     pub needs_removal: CompStore<bool>,
 
     // NOTE should be able to remove this
@@ -977,18 +1432,88 @@ impl Entities {
         self.inventory[&entity_id].remove(index);
     }
 
+    /// The item held in one of an entity's hands. Held items are the primary items at the
+    /// front of the inventory- the first is in the primary hand and the second in the
+    /// secondary hand. Everything after them is carried in the pack.
+    pub fn held_item(&self, entity_id: EntityId, hand: Hand) -> Option<EntityId> {
+        let index =
+            match hand {
+                Hand::Primary => 0,
+                Hand::Secondary => 1,
+            };
+
+        let inventory = self.inventory.get(&entity_id)?;
+        let held = inventory.iter()
+                            .take(2)
+                            .take_while(|item_id| self.item[item_id].class() == ItemClass::Primary)
+                            .nth(index);
+        return held.copied();
+    }
+
+    /// The items an entity is carrying in its pack, rather than holding.
+    pub fn pack_items(&self, entity_id: EntityId) -> Vec<EntityId> {
+        let num_held = [Hand::Primary, Hand::Secondary].iter()
+                                                        .filter(|hand| self.held_item(entity_id, **hand).is_some())
+                                                        .count();
+        return self.inventory[&entity_id].iter().skip(num_held).copied().collect();
+    }
+
+    /// Swap the items in an entity's hands. This only does anything when both hands hold an item.
+    pub fn swap_hands(&mut self, entity_id: EntityId) -> bool {
+        if self.held_item(entity_id, Hand::Secondary).is_none() {
+            return false;
+        }
+
+        self.inventory[&entity_id].swap(0, 1);
+        return true;
+    }
+
+    /// The total weight of the items an entity is carrying.
+    pub fn carried_weight(&self, entity_id: EntityId) -> usize {
+        return self.inventory.get(&entity_id).map_or(0, |inventory| {
+            inventory.iter().map(|item_id| self.item.get(item_id).map_or(0, |item| item.weight())).sum()
+        });
+    }
+
+    /// How much weight an entity can carry before being encumbered. Stronger entities can carry more.
+    pub fn carry_capacity(&self, entity_id: EntityId, config: &Config) -> usize {
+        let power = self.fighter.get(&entity_id).map_or(0, |fighter| std::cmp::max(0, fighter.power));
+        return config.carry_capacity_base + power as usize;
+    }
+
+    pub fn is_encumbered(&self, entity_id: EntityId, config: &Config) -> bool {
+        return self.carried_weight(entity_id) > self.carry_capacity(entity_id, config);
+    }
+
+    /// The heaviest item an entity is carrying, if any. Ties go to the item
+    /// earliest in the inventory.
+    pub fn heaviest_item(&self, entity_id: EntityId) -> Option<EntityId> {
+        let mut heaviest: Option<EntityId> = None;
+
+        if let Some(inventory) = self.inventory.get(&entity_id) {
+            for item_id in inventory.iter() {
+                let weight = self.item[item_id].weight();
+                if heaviest.map_or(true, |heaviest_id| weight > self.item[&heaviest_id].weight()) {
+                    heaviest = Some(*item_id);
+                }
+            }
+        }
+
+        return heaviest;
+    }
+
     pub fn pick_up_item(&mut self, entity_id: EntityId, item_id: EntityId) {
         let item = self.item[&item_id];
         let item_class = item.class();
 
         match item_class {
             ItemClass::Primary => {
-                if item_primary_at(entity_id, self, 0) &&
-                   item_primary_at(entity_id, self, 1) {
+                // with both hands full, the item in the primary hand is dropped for the new one
+                if let Some(dropped_id) = self.held_item(entity_id, Hand::Secondary).and(self.held_item(entity_id, Hand::Primary)) {
                     self.inventory[&entity_id][0] = item_id;
 
                     let obj_pos = self.pos[&entity_id];
-                    self.set_pos(entity_id, obj_pos);
+                    self.set_pos(dropped_id, obj_pos);
                 } else {
                     self.inventory[&entity_id].push_front(item_id);
                 }
@@ -1003,7 +1528,7 @@ impl Entities {
     }
 
     pub fn create_entity(&mut self, x: i32, y: i32, typ: EntityType, chr: char, color: Color, name: EntityName, blocks: bool) -> EntityId {
-        let id = OBJECT_ID_COUNT.fetch_add(1, Ordering::SeqCst);
+        let id = self.allocator.allocate();
         self.ids.push(id);
 
         // add fields that all entities share
@@ -1022,6 +1547,16 @@ impl Entities {
         return id;
     }
 
+    /// Put an item into a container, taking it off the map.
+    pub fn store_item(&mut self, container_id: EntityId, item_id: EntityId) {
+        self.inventory[&container_id].push_back(item_id);
+        self.set_xy(item_id, -1, -1);
+    }
+
+    pub fn is_container(&self, entity_id: EntityId) -> bool {
+        return matches!(self.name.get(&entity_id), Some(EntityName::Chest) | Some(EntityName::Crate));
+    }
+
     pub fn set_xy(&mut self, entity_id: EntityId, x: i32, y: i32) {
         self.pos[&entity_id] = Pos::new(x, y);
     }
@@ -1075,33 +1610,37 @@ impl Entities {
     pub fn triggered_traps(&self, pos: Pos) -> Vec<EntityId> {
         let mut traps: Vec<EntityId> = Vec::new();
         for key in self.ids.iter() {
-            if self.trap.get(key).is_some()       && // key is a trap
-               self.armed.get(key) == Some(&true) && // trap is armed
-               !self.needs_removal[key]           && // not being removed
-               self.pos[key] == pos {
-                traps.push(*key);
+            if let Some(trap) = self.trap.get(key) {
+                if self.armed.get(key) == Some(&true) && // trap is armed
+                   !self.needs_removal[key]           && // not being removed
+                   trap.triggered_by(self.pos[key], pos) {
+                    traps.push(*key);
+                }
             }
         }
 
         return traps;
     }
 
-    pub fn was_attacked(&mut self, entity_id: EntityId) -> Option<Message> {
-        if let Some(index) = self.messages[&entity_id].iter().position(|msg| matches!(msg, Message::Attack(_))) {
-            return Some(self.messages[&entity_id].remove(index));
-        } else {
-            return None;
-        }
+    pub fn was_attacked(&self, entity_id: EntityId) -> Option<Message> {
+        return self.messages[&entity_id].iter().find(|msg| matches!(msg, Message::Attack(_))).map(|msg| *msg);
     }
 
-    pub fn heard_sound(&mut self, entity_id: EntityId) -> Option<Message> {
-        if let Some(index) = self.messages[&entity_id].iter().position(|msg| matches!(msg, Message::Sound(_, _))) {
-            return Some(self.messages[&entity_id].remove(index));
-        } else {
-            return None;
+    pub fn distracted(&self, entity_id: EntityId) -> Option<Message> {
+        return self.messages[&entity_id].iter().find(|msg| matches!(msg, Message::Distraction(_))).map(|msg| *msg);
+    }
+
+    /// Remove a message once the entity has acted on it, so it is not acted on again.
+    pub fn read_message(&mut self, entity_id: EntityId, message: Message) {
+        if let Some(index) = self.messages[&entity_id].iter().position(|msg| *msg == message) {
+            self.messages[&entity_id].remove(index);
         }
     }
 
+    pub fn heard_sound(&self, entity_id: EntityId) -> Option<Message> {
+        return self.messages[&entity_id].iter().find(|msg| matches!(msg, Message::Sound(_, _))).map(|msg| *msg);
+    }
+
     pub fn face(&mut self, entity_id: EntityId, face_pos: Pos) {
         let diff = sub_pos(face_pos, self.pos[&entity_id]);
         if let Some(dir) = Direction::from_dxy(diff.x, diff.y) {
@@ -1155,6 +1694,15 @@ impl Entities {
         }
     }
 
+    /// The number of turns left before the entity can use the given skill again.
+    pub fn skill_cooldown(&self, entity_id: EntityId, skill: Skill) -> usize {
+        if let Some(cooldowns) = self.cooldowns.get(&entity_id) {
+            return *cooldowns.get(&skill).unwrap_or(&0);
+        }
+
+        return 0;
+    }
+
     pub fn mark_for_removal(&mut self, entity_id: EntityId) {
         // removing the player is handled specially
         if self.typ[&entity_id] != EntityType::Player {
@@ -1183,7 +1731,7 @@ impl Entities {
     // NOTE cloning entities may not remap all entity ids that an entity tracks!
     // this could cause subtle problems, so this is really only for level generation.
     pub fn clone_entity(&mut self, other: &Entities, entity_id: EntityId) {
-        let new_id = OBJECT_ID_COUNT.fetch_add(1, Ordering::SeqCst);
+        let new_id = self.allocator.allocate();
 
         self.ids.push(new_id);
 
@@ -1211,19 +1759,28 @@ impl Entities {
         move_component!(attack);
         move_component!(trap);
         move_component!(energy);
+        move_component!(stamina);
         move_component!(count_down);
+        move_component!(fuse);
+        move_component!(durability);
+        move_component!(locked);
+        move_component!(coins);
+        move_component!(faction);
+        move_component!(interest);
         move_component!(move_mode);
         move_component!(direction);
         move_component!(selected_item);
         move_component!(class);
         move_component!(skills);
-        move_component!(sound);
+        move_component!(cooldowns);
         move_component!(typ);
         move_component!(status);
         move_component!(gate_pos);
         move_component!(took_turn);
         move_component!(color);
         move_component!(blocks);
+        move_component!(pushable);
+        move_component!(known_traps);
         move_component!(needs_removal);
         move_component!(messages);
 
@@ -1239,9 +1796,14 @@ impl Entities {
         }
     }
 
+    /// Remove an entity and all of its components. Other entities' references to it,
+    /// such as items in an inventory, are removed as well so no id is left dangling.
     pub fn remove_entity(&mut self, id: EntityId) {
         let ix_pos = self.ids.iter().position(|val| *val == id).unwrap();
         self.ids.remove(ix_pos);
+        self.allocator.free(id);
+
+        self.clear_references(id);
 
         self.pos.remove(&id);
         self.chr.remove(&id);
@@ -1259,21 +1821,74 @@ impl Entities {
         self.trap.remove(&id);
         self.armed.remove(&id);
         self.energy.remove(&id);
+        self.stamina.remove(&id);
         self.count_down.remove(&id);
+        self.fuse.remove(&id);
+        self.durability.remove(&id);
+        self.locked.remove(&id);
+        self.coins.remove(&id);
+        self.faction.remove(&id);
+        self.interest.remove(&id);
         self.move_mode.remove(&id);
         self.direction.remove(&id);
         self.selected_item.remove(&id);
         self.class.remove(&id);
         self.skills.remove(&id);
-        self.sound.remove(&id);
+        self.cooldowns.remove(&id);
         self.typ.remove(&id);
         self.status.remove(&id);
         self.gate_pos.remove(&id);
         self.took_turn.remove(&id);
         self.color.remove(&id);
         self.blocks.remove(&id);
+        self.pushable.remove(&id);
+        self.known_traps.remove(&id);
         self.needs_removal.remove(&id);
         self.messages.remove(&id);
     }
+
+    /// Clear the references other entities hold to an entity being removed. An AI
+    /// attacking it goes to investigate where it was instead, and one following it,
+    /// or raising the alarm it was going to, stops where it is.
+    fn clear_references(&mut self, id: EntityId) {
+        let last_pos = self.pos[&id];
+
+        for inventory in self.inventory.values_mut() {
+            inventory.retain(|item_id| *item_id != id);
+        }
+        for known_traps in self.known_traps.values_mut() {
+            known_traps.retain(|trap_id| *trap_id != id);
+        }
+        self.selected_item.retain(|_entity_id, item_id| *item_id != id);
+
+        for behavior in self.behavior.values_mut() {
+            match behavior {
+                Behavior::Attacking(target_id) if *target_id == id => {
+                    *behavior = Behavior::Investigating(last_pos);
+                }
+
+                Behavior::Following(leader_id) if *leader_id == id => {
+                    *behavior = Behavior::Idle;
+                }
+
+                Behavior::RaisingAlarm(alarm_id) if *alarm_id == id => {
+                    *behavior = Behavior::Idle;
+                }
+
+                _ => {
+                }
+            }
+        }
+
+        for messages in self.messages.values_mut() {
+            messages.retain(|message| {
+                match message {
+                    Message::Sound(source_id, _pos) => *source_id != id,
+                    Message::Attack(attacker_id) => *attacker_id != id,
+                    Message::Distraction(_pos) => true,
+                }
+            });
+        }
+    }
 }
 
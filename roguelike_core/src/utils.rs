@@ -6,8 +6,7 @@ use std::hash::{Hash, Hasher};
 use oorandom::Rand32;
 
 use crate::ai::Behavior;
-use crate::constants::{HAMMER_DAMAGE, SWORD_DAMAGE, TILE_FILL_METRIC_DIST};
-use crate::map::{Surface};
+use crate::constants::{TILE_FILL_METRIC_DIST};
 use crate::types::*;
 use crate::movement::{Reach, MoveMode, check_collision, MoveType, Direction};
 use crate::messaging::*;
@@ -177,7 +176,7 @@ pub fn push_attack(entity_id: EntityId,
     }
 
     if killed {
-        msg_log.log(Msg::Killed(entity_id, target, damage));
+        msg_log.log(Msg::Killed(Some(entity_id), target, damage));
     } else {
         data.entities.messages[&target].push(Message::Attack(entity_id));
     }
@@ -193,78 +192,21 @@ pub fn crush(handle: EntityId, target: EntityId, entities: &mut Entities, msg_lo
         entities.status[&target].alive = false;
         entities.blocks[&target] = false;
 
-        msg_log.log(Msg::Killed(handle, target, damage));
+        msg_log.log(Msg::Killed(Some(handle), target, damage));
     }
 }
 
-pub fn attack(entity: EntityId, target: EntityId, data: &mut GameData, msg_log: &mut MsgLog) {
-    if data.using(entity, Item::Hammer).is_some() {
-        data.entities.status[&target].alive = false;
-        data.entities.blocks[&target] = false;
-
-        data.entities.take_damage(target, HAMMER_DAMAGE);
-        data.entities.messages[&target].push(Message::Attack(entity));
-
-        // NOTE assumes that this kills the enemy
-        msg_log.log(Msg::Killed(entity, target, HAMMER_DAMAGE));
-
-        let hit_pos = data.entities.pos[&target];
-        // NOTE this creates rubble even if the player somehow is hit by a hammer...
-        if data.map[hit_pos].surface == Surface::Floor {
-            data.map[hit_pos].surface = Surface::Rubble;
-        }
-    } else if data.using(target, Item::Shield).is_some() {
-        let pos = data.entities.pos[&entity];
-        let other_pos = data.entities.pos[&target];
-        let diff = other_pos - pos;
-
-        let x_diff = diff.x.signum();
-        let y_diff = diff.y.signum();
-
-        let past_pos = move_by(other_pos, Pos::new(x_diff, y_diff));
-
-        if !data.map.path_blocked_move(other_pos, Pos::new(x_diff, y_diff)).is_some() &&
-           !data.has_blocking_entity(past_pos).is_some() {
-            data.entities.set_pos(target, past_pos);
-            data.entities.set_pos(entity, other_pos);
-
-            data.entities.messages[&target].push(Message::Attack(entity));
-        }
-    } else if data.using(entity, Item::Sword).is_some() {
-        msg_log.log(Msg::Attack(entity, target, SWORD_DAMAGE));
-        msg_log.log(Msg::Killed(entity, target, SWORD_DAMAGE));
-    } else {
-        // NOTE could add another section for the sword- currently the same as normal attacks
-        let damage = data.entities.fighter.get(&entity).map_or(0, |f| f.power) -
-                     data.entities.fighter.get(&target).map_or(0, |f| f.defense);
-        if damage > 0 && data.entities.status[&target].alive {
-            data.entities.take_damage(target, damage);
-
-            msg_log.log(Msg::Attack(entity, target, damage));
-            // TODO consider moving this to the Attack msg
-            if data.entities.fighter[&target].hp <= 0 {
-                data.entities.status[&target].alive = false;
-                data.entities.blocks[&target] = false;
-
-                msg_log.log(Msg::Killed(entity, target, damage));
-            }
-
-            data.entities.messages[&target].push(Message::Attack(entity));
-        }
-    }
-}
-
-pub fn stab(entity_id: EntityId, target: EntityId, entities: &mut Entities, msg_log: &mut MsgLog) {
+pub fn stab(entity_id: EntityId, target: EntityId, backstab: bool, entities: &mut Entities, msg_log: &mut MsgLog) {
     let damage = entities.fighter.get(&target).map_or(0, |f| f.hp);
 
     if damage != 0 {
-        if entities.behavior[&target] == Behavior::Idle {
+        if entities.behavior[&target] == Behavior::Idle || backstab {
             msg_log.log(Msg::Attack(entity_id, target, damage));
 
             entities.status[&target].alive = false;
             entities.blocks[&target] = false;
 
-            msg_log.log(Msg::Killed(entity_id, target, damage));
+            msg_log.log(Msg::Killed(Some(entity_id), target, damage));
 
             entities.messages[&target].push(Message::Attack(entity_id));
         } else {
@@ -444,9 +386,72 @@ pub fn can_stab(data: &GameData, entity: EntityId, target: EntityId) -> bool {
     let is_enemy = data.entities.typ[&target] == EntityType::Enemy;
     let using_dagger = data.using(entity, Item::Dagger).is_some();
     let clear_path = data.clear_path_up_to(entity_pos, target_pos, false);
-    let not_attacking = !matches!(data.entities.behavior.get(&target), Some(Behavior::Attacking(_)));
+    let not_attacking = !data.entities.behavior.get(&target).map_or(false, |behavior| behavior.is_aware());
+
+    return (is_enemy && using_dagger && clear_path && not_attacking) || can_backstab(data, entity, target);
+}
+
+/// The player can knock out an enemy that has not noticed them.
+pub fn can_knock_out(data: &GameData, entity: EntityId, target: EntityId) -> bool {
+    let is_player = data.entities.typ[&entity] == EntityType::Player;
+    let is_enemy = data.entities.typ[&target] == EntityType::Enemy;
+    let conscious = data.entities.status[&target].alive && data.entities.status[&target].knocked_out == 0;
+    let unaware = !data.entities.behavior.get(&target).map_or(false, |behavior| behavior.is_aware());
+
+    return is_player && is_enemy && conscious && unaware;
+}
 
-    return is_enemy && using_dagger && clear_path && not_attacking;
+/// An entity is behind its target if it is on the opposite side
+/// of the direction the target is facing.
+pub fn is_behind(data: &GameData, entity: EntityId, target: EntityId) -> bool {
+    if let Some(facing) = data.entities.direction.get(&target) {
+        let facing = facing.into_move();
+        let offset = sub_pos(data.entities.pos[&entity], data.entities.pos[&target]);
+        return facing.x * offset.x + facing.y * offset.y < 0;
+    }
+
+    return false;
+}
+
+pub fn is_in_front(data: &GameData, entity: EntityId, target: EntityId) -> bool {
+    if let Some(facing) = data.entities.direction.get(&target) {
+        let facing = facing.into_move();
+        let offset = sub_pos(data.entities.pos[&entity], data.entities.pos[&target]);
+        return facing.x * offset.x + facing.y * offset.y > 0;
+    }
+
+    return false;
+}
+
+/// A player sneaking up behind an enemy can take it down outright, with or without a dagger.
+pub fn can_backstab(data: &GameData, entity: EntityId, target: EntityId) -> bool {
+    let entity_pos = data.entities.pos[&entity];
+    let target_pos = data.entities.pos[&target];
+
+    let is_player = data.entities.typ[&entity] == EntityType::Player;
+    let is_enemy = data.entities.typ[&target] == EntityType::Enemy;
+    let sneaking = data.entities.move_mode.get(&entity) == Some(&MoveMode::Sneak);
+    let adjacent = distance_maximum(entity_pos, target_pos) == 1;
+    let clear_path = data.clear_path_up_to(entity_pos, target_pos, false);
+
+    return is_player && is_enemy && sneaking && adjacent && clear_path && is_behind(data, entity, target);
+}
+
+/// A sneaking entity holding a dagger can kill an adjacent enemy outright if that
+/// enemy has not noticed it, no matter which way the enemy is facing.
+pub fn can_stealth_kill(data: &GameData, entity: EntityId, target: EntityId) -> bool {
+    let entity_pos = data.entities.pos[&entity];
+    let target_pos = data.entities.pos[&target];
+
+    let is_enemy = data.entities.typ[&target] == EntityType::Enemy;
+    let alive = data.entities.status[&target].alive;
+    let using_dagger = data.using(entity, Item::Dagger).is_some();
+    let sneaking = data.entities.move_mode.get(&entity) == Some(&MoveMode::Sneak);
+    let adjacent = distance_maximum(entity_pos, target_pos) == 1;
+    let clear_path = data.clear_path_up_to(entity_pos, target_pos, false);
+    let unaware = !data.entities.behavior.get(&target).map_or(false, |behavior| behavior.is_aware());
+
+    return is_enemy && alive && using_dagger && sneaking && adjacent && clear_path && unaware;
 }
 
 pub fn dxy(start_pos: Pos, end_pos: Pos) -> (i32, i32) {
@@ -487,8 +492,13 @@ pub fn sound_dampening(map: &Map, start_pos: Pos, end_pos: Pos, config: &Config)
 
     let mut dampen = 0;
     if let Some(blocked) = map.path_blocked_move(start_pos, end_pos) {
+        // doors block sight but not sound
+        let door = map.is_within_bounds(end_pos) && map[end_pos].tile_type.is_door();
+
         if blocked.blocked_tile {
-            dampen += config.dampen_blocked_tile;
+            if !door {
+                dampen += config.dampen_blocked_tile;
+            }
         } else if blocked.wall_type == Wall::TallWall {
             dampen += config.dampen_tall_wall;
         } else if blocked.wall_type == Wall::ShortWall {
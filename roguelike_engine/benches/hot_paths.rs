@@ -0,0 +1,118 @@
+use criterion::{criterion_group, criterion_main, Criterion, BatchSize, black_box};
+
+use roguelike_core::types::*;
+use roguelike_core::map::*;
+use roguelike_core::config::Config;
+use roguelike_core::constants::*;
+use roguelike_core::utils::aoe_fill;
+use roguelike_core::messaging::Msg;
+use roguelike_core::movement::MoveMode;
+
+use roguelike_engine::game::Game;
+use roguelike_engine::make_map::make_map;
+use roguelike_engine::generation::make_gol;
+use roguelike_engine::procgen::{generate_rooms_map, place_key_and_goal, find_structures};
+use roguelike_engine::step::step_logic;
+
+
+// benchmarks are run from the roguelike_engine directory, like the tests
+const CONFIG_FILE: &str = "../config.yaml";
+
+const BENCH_SEED: u64 = 1;
+const BENCH_ENEMIES: usize = 50;
+
+/// A game on a generated rooms map, with the player on its first clear tile. The map
+/// generator is seeded, so every run benchmarks the same map.
+fn rooms_game() -> Game {
+    let config = Config::from_file(CONFIG_FILE);
+    let mut game = Game::new(BENCH_SEED, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+
+    game.data.map = generate_rooms_map(MAP_WIDTH as u32, MAP_HEIGHT as u32, &config, &mut game.rng);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = game.data.get_clear_pos()[0];
+    game.data.entities.set_pos(player_id, player_pos);
+
+    return game;
+}
+
+/// The rooms map with enemies spread over its clear tiles.
+fn rooms_game_with_enemies(num_enemies: usize) -> Game {
+    let mut game = rooms_game();
+
+    let clear_pos = game.data.get_clear_pos();
+    let spacing = std::cmp::max(1, clear_pos.len() / (num_enemies + 1));
+    for pos in clear_pos.iter().skip(spacing).step_by(spacing).take(num_enemies) {
+        make_gol(&mut game.data.entities, &game.config, *pos, &mut game.msg_log);
+    }
+
+    return game;
+}
+
+fn bench_fov(c: &mut Criterion) {
+    let game = rooms_game();
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = game.data.entities.pos[&player_id];
+    let radius = game.data.fov_radius(player_id);
+
+    c.bench_function("compute_fov", |b| b.iter(|| {
+        // FOV is cached by position, so the cache is cleared to measure computing it
+        game.data.map.fov_cache.clear();
+
+        let visible = game.data.map.get_all_pos().iter()
+                                   .filter(|pos| game.data.map.is_in_fov(player_pos, **pos, radius, false))
+                                   .count();
+        return black_box(visible);
+    }));
+}
+
+fn bench_aoe_fill(c: &mut Criterion) {
+    let game = rooms_game();
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = game.data.entities.pos[&player_id];
+
+    c.bench_function("aoe_fill", |b| b.iter(|| {
+        return aoe_fill(&game.data.map, AoeEffect::Sound, black_box(player_pos), 6, &game.config);
+    }));
+}
+
+fn bench_place_key_and_goal(c: &mut Criterion) {
+    let game = rooms_game();
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = game.data.entities.pos[&player_id];
+
+    c.bench_function("place_key_and_goal", |b| b.iter_batched(|| game.clone(), |mut game| {
+        place_key_and_goal(&mut game, player_pos);
+        return game;
+    }, BatchSize::SmallInput));
+}
+
+fn bench_find_structures(c: &mut Criterion) {
+    let game = rooms_game();
+
+    c.bench_function("find_structures", |b| b.iter(|| {
+        return find_structures(black_box(&game.data.map));
+    }));
+}
+
+fn bench_step_logic(c: &mut Criterion) {
+    let game = rooms_game_with_enemies(BENCH_ENEMIES);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let direction = game.data.entities.direction[&player_id];
+
+    // the player passes their turn, so every enemy takes one as well
+    let passed = || {
+        let mut game = game.clone();
+        game.msg_log.log(Msg::TryMove(player_id, direction, 0, MoveMode::Walk));
+        return game;
+    };
+
+    c.bench_function("step_logic_50_enemies", |b| b.iter_batched(passed, |mut game| {
+        step_logic(&mut game);
+        return game;
+    }, BatchSize::SmallInput));
+}
+
+criterion_group!(benches, bench_fov, bench_aoe_fill, bench_place_key_and_goal, bench_find_structures, bench_step_logic);
+criterion_main!(benches);
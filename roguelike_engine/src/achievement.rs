@@ -0,0 +1,149 @@
+use std::fmt;
+use std::str::FromStr;
+use std::fs;
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+use roguelike_core::types::*;
+use roguelike_core::ai::Behavior;
+use roguelike_core::messaging::Msg;
+
+use crate::score::RunStats;
+use crate::error::{GameError, GameResult, read_yaml};
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Achievement {
+    Unseen,
+    Pacifist,
+    Escaped,
+    Rescuer,
+}
+
+impl fmt::Display for Achievement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Achievement::Unseen => write!(f, "unseen"),
+            Achievement::Pacifist => write!(f, "pacifist"),
+            Achievement::Escaped => write!(f, "escaped"),
+            Achievement::Rescuer => write!(f, "rescuer"),
+        }
+    }
+}
+
+impl FromStr for Achievement {
+    type Err = String;
+
+    fn from_str(original_str: &str) -> Result<Self, Self::Err> {
+        let s: &mut str = &mut original_str.to_string();
+        s.make_ascii_lowercase();
+
+        if s == "unseen" {
+            return Ok(Achievement::Unseen);
+        } else if s == "pacifist" {
+            return Ok(Achievement::Pacifist);
+        } else if s == "escaped" {
+            return Ok(Achievement::Escaped);
+        } else if s == "rescuer" {
+            return Ok(Achievement::Rescuer);
+        }
+
+        return Err(format!("Could not parse '{}' as Achievement", s));
+    }
+}
+
+impl Achievement {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Achievement::Unseen => "finish a level without being seen",
+            Achievement::Pacifist => "win without killing",
+            Achievement::Escaped => "win a run",
+            Achievement::Rescuer => "finish a rescue level",
+        }
+    }
+}
+
+/// The achievements earned so far, kept between runs.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Unlocks {
+    pub achievements: Vec<Achievement>,
+}
+
+impl Unlocks {
+    /// Load the unlocks, starting with nothing unlocked if the file doesn't exist yet.
+    pub fn from_file(file_name: &str) -> GameResult<Unlocks> {
+        if !Path::new(file_name).exists() {
+            return Ok(Unlocks::default());
+        }
+
+        return read_yaml(file_name);
+    }
+
+    pub fn save(&self, file_name: &str) -> GameResult<()> {
+        let unlocks_string = serde_yaml::to_string(self).map_err(|err| GameError::write(file_name, err))?;
+        return fs::write(file_name, unlocks_string).map_err(|err| GameError::write(file_name, err));
+    }
+
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        return self.achievements.contains(&achievement);
+    }
+
+    /// Unlock an achievement, returning whether it is newly unlocked.
+    pub fn unlock(&mut self, achievement: Achievement) -> bool {
+        if self.is_unlocked(achievement) {
+            return false;
+        }
+
+        self.achievements.push(achievement);
+        return true;
+    }
+}
+
+/// Watches the messages of a run for the things achievements are earned by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AchievementTracker {
+    pub seen_on_level: bool,
+}
+
+impl AchievementTracker {
+    pub fn record(&mut self, msg: &Msg, player_id: EntityId) {
+        if let Msg::StateChange(_entity_id, Behavior::Attacking(target_id)) = msg {
+            if *target_id == player_id {
+                self.seen_on_level = true;
+            }
+        }
+    }
+
+    /// The achievements earned by finishing a level. The next level starts unseen.
+    pub fn level_finished(&mut self, objective: Objective) -> Vec<Achievement> {
+        let mut earned = Vec::new();
+
+        if !self.seen_on_level {
+            earned.push(Achievement::Unseen);
+        }
+
+        if objective == Objective::Rescue {
+            earned.push(Achievement::Rescuer);
+        }
+
+        self.seen_on_level = false;
+
+        return earned;
+    }
+
+    /// The achievements earned by the way a run ended.
+    pub fn run_ended(&self, stats: &RunStats, won: bool) -> Vec<Achievement> {
+        let mut earned = Vec::new();
+
+        if won {
+            earned.push(Achievement::Escaped);
+
+            if stats.kills == 0 {
+                earned.push(Achievement::Pacifist);
+            }
+        }
+
+        return earned;
+    }
+}
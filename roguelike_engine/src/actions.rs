@@ -13,6 +13,13 @@ use roguelike_core::utils::{scale_pos, distance, sub_pos, add_pos, next_from_to,
 use crate::game::*;
 use crate::input::*;
 use crate::make_map;
+use crate::prompt::confirm;
+use crate::help::help_lines;
+use crate::inspector::inspect_at;
+use crate::selection::{Selection, SelectionAction, start_selection, handle_input_selection};
+
+#[cfg(test)]
+use proptest::prelude::*;
 
 
 
@@ -36,6 +43,7 @@ pub enum InputAction {
     ItemFacing(ActionMode, usize),
     Pass(MoveMode),
     Pickup,
+    SwapPrimaryItem,
     DropItem,
     DropItemByIndex(usize),
     Yell,
@@ -55,11 +63,25 @@ pub enum InputAction {
     ExploreAll,
     RegenerateMap,
     GodMode,
+    Undo,
+    Sandbox,
+    SaveMap,
     IncreaseMoveMode,
     DecreaseMoveMode,
     OverlayOn,
     OverlayOff,
+    CycleOverlay,
+    Minimap,
+    PerfOverlay,
+    SkipAnimations,
+    Screenshot,
+    SaveClip,
+    ResumeCheckpoint,
     SelectItem(usize),
+    QuickSlot(usize), // use the item or skill in a hotbar slot
+    AssignQuickSlot(usize), // put a menu entry on the hotbar
+    Confirm,
+    Help,
     None,
 }
 
@@ -89,6 +111,7 @@ impl fmt::Display for InputAction {
             InputAction::MapClick(loc, cell) => write!(f, "click {} {} {} {}", loc.x, loc.y, cell.x, cell.y),
             InputAction::MouseButton(click, keydir) => write!(f, "mousebutton {:?} {:?}", click, keydir),
             InputAction::Pickup => write!(f, "pickup"),
+            InputAction::SwapPrimaryItem => write!(f, "swapitems"),
             InputAction::DropItem => write!(f, "drop"),
             InputAction::DropItemByIndex(target) => write!(f, "droptarget {}", target),
             InputAction::Inventory => write!(f, "inventory"),
@@ -100,12 +123,26 @@ impl fmt::Display for InputAction {
             InputAction::ExploreAll => write!(f, "exploreall"),
             InputAction::RegenerateMap => write!(f, "regenmap"),
             InputAction::GodMode => write!(f, "godmode"),
+            InputAction::Undo => write!(f, "undo"),
+            InputAction::Sandbox => write!(f, "sandbox"),
+            InputAction::SaveMap => write!(f, "savemap"),
             InputAction::Yell => write!(f, "yell"),
             InputAction::IncreaseMoveMode => write!(f, "faster"),
             InputAction::DecreaseMoveMode => write!(f, "slower"),
             InputAction::OverlayOn => write!(f, "overlayon"),
             InputAction::OverlayOff => write!(f, "overlayoff"),
+            InputAction::CycleOverlay => write!(f, "cycleoverlay"),
+            InputAction::Minimap => write!(f, "minimap"),
+            InputAction::PerfOverlay => write!(f, "perfoverlay"),
+            InputAction::SkipAnimations => write!(f, "skipanimations"),
+            InputAction::Screenshot => write!(f, "screenshot"),
+            InputAction::SaveClip => write!(f, "saveclip"),
+            InputAction::ResumeCheckpoint => write!(f, "resume_checkpoint"),
             InputAction::SelectItem(item) => write!(f, "selectitem {}", item),
+            InputAction::QuickSlot(slot) => write!(f, "quickslot {}", slot),
+            InputAction::AssignQuickSlot(item) => write!(f, "assignquickslot {}", item),
+            InputAction::Confirm => write!(f, "confirm"),
+            InputAction::Help => write!(f, "help"),
             InputAction::UseItem(dir, target) => write!(f, "use, {:?} {}", dir, target),
             InputAction::Interact(dir) => write!(f, "interact {:?}", dir),
             InputAction::CursorMove(dir, relative, long) => write!(f, "cursormove {:?} {} {}", dir, relative, long),
@@ -116,6 +153,12 @@ impl fmt::Display for InputAction {
     }
 }
 
+/// Parse an argument of an action written to the action log.
+fn parse_arg<T: FromStr>(args: &Vec<&str>, index: usize) -> Result<T, String> {
+    let arg = args.get(index).ok_or(format!("Missing argument {} in '{}'", index, args.join(" ")))?;
+    return arg.parse::<T>().map_err(|_| format!("Could not parse argument '{}' in '{}'", arg, args.join(" ")));
+}
+
 impl FromStr for InputAction {
     type Err = String;
 
@@ -125,93 +168,107 @@ impl FromStr for InputAction {
         let args = s.split(" ").collect::<Vec<&str>>();
 
         if args[0] == "left" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
+            let move_mode = parse_arg::<MoveMode>(&args, 1)?;
             return Ok(InputAction::Move(Direction::Left, move_mode));
         } else if args[0] == "right" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
+            let move_mode = parse_arg::<MoveMode>(&args, 1)?;
             return Ok(InputAction::Move(Direction::Right, move_mode));
         } else if args[0] == "up" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
+            let move_mode = parse_arg::<MoveMode>(&args, 1)?;
             return Ok(InputAction::Move(Direction::Up, move_mode));
         } else if args[0] == "down" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
+            let move_mode = parse_arg::<MoveMode>(&args, 1)?;
             return Ok(InputAction::Move(Direction::Down, move_mode));
         } else if args[0] == "upleft" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
+            let move_mode = parse_arg::<MoveMode>(&args, 1)?;
             return Ok(InputAction::Move(Direction::UpLeft, move_mode));
         } else if args[0] == "upright" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
+            let move_mode = parse_arg::<MoveMode>(&args, 1)?;
             return Ok(InputAction::Move(Direction::UpRight, move_mode));
         } else if args[0] == "downleft" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
+            let move_mode = parse_arg::<MoveMode>(&args, 1)?;
             return Ok(InputAction::Move(Direction::DownLeft, move_mode));
         } else if args[0] == "downright" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
+            let move_mode = parse_arg::<MoveMode>(&args, 1)?;
             return Ok(InputAction::Move(Direction::DownRight, move_mode));
         } else if args[0] == "pass" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
+            let move_mode = parse_arg::<MoveMode>(&args, 1)?;
             return Ok(InputAction::Pass(move_mode));
         } else if args[0] == "movetowardscursor" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
+            let move_mode = parse_arg::<MoveMode>(&args, 1)?;
             return Ok(InputAction::MoveTowardsCursor(move_mode));
         } else if args[0] == "skilldir" {
-            let dir = args[1].parse::<Direction>().unwrap();
-            let action_mode = args[2].parse::<ActionMode>().unwrap();
-            let index = args[3].parse::<usize>().unwrap();
+            let dir = parse_arg::<Direction>(&args, 1)?;
+            let action_mode = parse_arg::<ActionMode>(&args, 2)?;
+            let index = parse_arg::<usize>(&args, 3)?;
             return Ok(InputAction::SkillDir(dir, action_mode, index));
         } else if args[0] == "itemdir" {
-            let dir = args[1].parse::<Direction>().unwrap();
-            let action_mode = args[2].parse::<ActionMode>().unwrap();
-            let index = args[3].parse::<usize>().unwrap();
+            let dir = parse_arg::<Direction>(&args, 1)?;
+            let action_mode = parse_arg::<ActionMode>(&args, 2)?;
+            let index = parse_arg::<usize>(&args, 3)?;
             return Ok(InputAction::ItemDir(dir, action_mode, index));
         } else if args[0] == "skillpos" {
-            let x = args[1].parse::<i32>().unwrap();
-            let y = args[2].parse::<i32>().unwrap();
-            let action_mode = args[3].parse::<ActionMode>().unwrap();
-            let index = args[4].parse::<usize>().unwrap();
+            let x = parse_arg::<i32>(&args, 1)?;
+            let y = parse_arg::<i32>(&args, 2)?;
+            let action_mode = parse_arg::<ActionMode>(&args, 3)?;
+            let index = parse_arg::<usize>(&args, 4)?;
             return Ok(InputAction::SkillPos(Pos::new(x, y), action_mode, index));
         } else if args[0] == "itempos" {
-            let x = args[1].parse::<i32>().unwrap();
-            let y = args[2].parse::<i32>().unwrap();
-            let action_mode = args[3].parse::<ActionMode>().unwrap();
-            let index = args[4].parse::<usize>().unwrap();
+            let x = parse_arg::<i32>(&args, 1)?;
+            let y = parse_arg::<i32>(&args, 2)?;
+            let action_mode = parse_arg::<ActionMode>(&args, 3)?;
+            let index = parse_arg::<usize>(&args, 4)?;
             return Ok(InputAction::ItemPos(Pos::new(x, y), action_mode, index));
         } else if args[0] == "skillfacing" {
-            let action_mode = args[1].parse::<ActionMode>().unwrap();
-            let index = args[2].parse::<usize>().unwrap();
+            let action_mode = parse_arg::<ActionMode>(&args, 1)?;
+            let index = parse_arg::<usize>(&args, 2)?;
             return Ok(InputAction::SkillFacing(action_mode, index));
         } else if args[0] == "itemfacing" {
-            let action_mode = args[1].parse::<ActionMode>().unwrap();
-            let index = args[2].parse::<usize>().unwrap();
+            let action_mode = parse_arg::<ActionMode>(&args, 1)?;
+            let index = parse_arg::<usize>(&args, 2)?;
             return Ok(InputAction::ItemFacing(action_mode, index));
         } else if args[0] == "pickup" {
             return Ok(InputAction::Pickup);
+        } else if args[0] == "swapitems" {
+            return Ok(InputAction::SwapPrimaryItem);
         } else if args[0] == "drop" {
             return Ok(InputAction::DropItem);
         } else if args[0] == "droptarget" {
-            let target = args[1].parse::<usize>().unwrap();
+            let target = parse_arg::<usize>(&args, 1)?;
             return Ok(InputAction::DropItemByIndex(target));
         } else if args[0] == "yell" {
             return Ok(InputAction::Yell);
         } else if args[0] == "inventory" {
             return Ok(InputAction::Inventory);
         } else if args[0] == "use" {
-            let direction = args[1].parse::<Direction>().unwrap();
-            let target = args[1].parse::<usize>().unwrap();
+            let direction = parse_arg::<Direction>(&args, 1)?;
+            let target = parse_arg::<usize>(&args, 1)?;
             return Ok(InputAction::UseItem(direction, target));
         } else if s.starts_with("selectitem") {
-            let selection = args[1].parse::<usize>().unwrap();
+            let selection = parse_arg::<usize>(&args, 1)?;
             return Ok(InputAction::SelectItem(selection));
+        } else if args[0] == "quickslot" {
+            let slot = parse_arg::<usize>(&args, 1)?;
+            return Ok(InputAction::QuickSlot(slot));
+        } else if args[0] == "assignquickslot" {
+            let selection = parse_arg::<usize>(&args, 1)?;
+            return Ok(InputAction::AssignQuickSlot(selection));
         } else if args[0] == "interact" {
-            let dir = args[1].parse::<Direction>().ok();
+            let dir = args.get(1).and_then(|arg| arg.parse::<Direction>().ok());
             return Ok(InputAction::Interact(dir));
         } else if args[0] == "godmode" {
             return Ok(InputAction::GodMode);
+        } else if args[0] == "undo" {
+            return Ok(InputAction::Undo);
+        } else if args[0] == "sandbox" {
+            return Ok(InputAction::Sandbox);
+        } else if args[0] == "savemap" {
+            return Ok(InputAction::SaveMap);
         } else if s.starts_with("click") {
-            let loc_x = args[1].parse::<i32>().unwrap();
-            let loc_y = args[2].parse::<i32>().unwrap();
-            let cell_x = args[3].parse::<i32>().unwrap();
-            let cell_y = args[4].parse::<i32>().unwrap();
+            let loc_x = parse_arg::<i32>(&args, 1)?;
+            let loc_y = parse_arg::<i32>(&args, 2)?;
+            let cell_x = parse_arg::<i32>(&args, 3)?;
+            let cell_y = parse_arg::<i32>(&args, 4)?;
             return Ok(InputAction::MapClick(Pos::new(loc_x, loc_y), Pos::new(cell_x, cell_y)));
         } else if args[0] == "skill" {
             return Ok(InputAction::SkillMenu);
@@ -219,6 +276,24 @@ impl FromStr for InputAction {
             return Ok(InputAction::ClassMenu);
         } else if args[0] == "esc" {
             return Ok(InputAction::Esc);
+        } else if args[0] == "confirm" {
+            return Ok(InputAction::Confirm);
+        } else if args[0] == "help" {
+            return Ok(InputAction::Help);
+        } else if args[0] == "cycleoverlay" {
+            return Ok(InputAction::CycleOverlay);
+        } else if args[0] == "minimap" {
+            return Ok(InputAction::Minimap);
+        } else if args[0] == "perfoverlay" {
+            return Ok(InputAction::PerfOverlay);
+        } else if args[0] == "skipanimations" {
+            return Ok(InputAction::SkipAnimations);
+        } else if args[0] == "screenshot" {
+            return Ok(InputAction::Screenshot);
+        } else if args[0] == "saveclip" {
+            return Ok(InputAction::SaveClip);
+        } else if args[0] == "resume_checkpoint" {
+            return Ok(InputAction::ResumeCheckpoint);
         } else if args[0] == "force_exit" {
             return Ok(InputAction::ForceExit);
         } else if args[0] == "faster" {
@@ -226,9 +301,9 @@ impl FromStr for InputAction {
         } else if args[0] == "slower" {
             return Ok(InputAction::DecreaseMoveMode);
         } else if args[0] == "cursormove" {
-            let dir = Direction::from_str(args[1]).unwrap();
-            let relative = bool::from_str(args[2]).unwrap();
-            let long = bool::from_str(args[3]).unwrap();
+            let dir = parse_arg::<Direction>(&args, 1)?;
+            let relative = parse_arg::<bool>(&args, 2)?;
+            let long = parse_arg::<bool>(&args, 3)?;
             return Ok(InputAction::CursorMove(dir, relative, long));
         } else if args[0] == "cursorreturn" {
             return Ok(InputAction::CursorReturn);
@@ -242,6 +317,19 @@ impl FromStr for InputAction {
 
 pub fn handle_input_universal(input_action: InputAction, game: &mut Game) -> bool {
     match input_action {
+        InputAction::Help => {
+            // help can be opened over any screen, and closing it goes back to that screen
+            if game.settings.state == GameState::Help {
+                let help_return = game.settings.help_return;
+                change_state(&mut game.settings, help_return);
+            } else {
+                game.settings.help_return = game.settings.state;
+                game.settings.help_scroll = 0;
+                change_state(&mut game.settings, GameState::Help);
+            }
+            return true;
+        }
+
         InputAction::ExploreAll => {
             for x in 0..game.data.map.width() {
                 for y in 0..game.data.map.height() {
@@ -254,7 +342,9 @@ pub fn handle_input_universal(input_action: InputAction, game: &mut Game) -> boo
         }
 
         InputAction::RegenerateMap => {
-            let _position = make_map::make_map(&game.config.map_load.clone(), game);
+            if let Err(err) = make_map::make_map(&game.config.map_load.clone(), game) {
+                game.show_error(err);
+            }
             return true;
         }
 
@@ -270,18 +360,50 @@ pub fn handle_input_universal(input_action: InputAction, game: &mut Game) -> boo
             return true;
         }
 
+        InputAction::Undo => {
+            // undoing turns is only allowed while in god mode
+            if game.settings.god_mode && game.settings.state == GameState::Playing {
+                game.undo_turn();
+            }
+            return true;
+        }
+
+        InputAction::Sandbox => {
+            // entering the sandbox pauses the game, and leaving it plays from
+            // the map as it was edited.
+            if game.settings.state == GameState::Playing {
+                if game.settings.cursor.is_none() {
+                    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+                    game.settings.cursor = Some(game.data.entities.pos[&player_id]);
+                }
+                change_state(&mut game.settings, GameState::Sandbox);
+            } else if game.settings.state == GameState::Sandbox {
+                change_state(&mut game.settings, GameState::Playing);
+            }
+            return true;
+        }
+
         InputAction::ForceExit => {
             change_state(&mut game.settings, GameState::Exit);
             return true;
         }
 
+        InputAction::ResumeCheckpoint => {
+            // a checkpoint which can't be read leaves the game where it was
+            if let Err(err) = game.resume_checkpoint() {
+                eprintln!("Checkpoint not resumed- {}", err);
+            }
+            return true;
+        }
+
         InputAction::Exit => {
-            if game.settings.state != GameState::ConfirmQuit {
-                change_state(&mut game.settings, GameState::ConfirmQuit);
-                return true;
-            } else {
-                return false;
+            // there is no run to lose from the main menu
+            if game.settings.state == GameState::MainMenu {
+                change_state(&mut game.settings, GameState::Exit);
+            } else if game.settings.state != GameState::Prompt {
+                confirm(&mut game.settings, "Quit the game? This run will be lost.", InputAction::ForceExit);
             }
+            return true;
         }
 
         _ => {
@@ -290,7 +412,7 @@ pub fn handle_input_universal(input_action: InputAction, game: &mut Game) -> boo
     }
 }
 
-pub fn handle_input_inventory(input: InputAction, settings: &mut GameSettings) {
+pub fn handle_input_inventory(input: InputAction, data: &GameData, settings: &mut GameSettings) {
     match input {
         InputAction::Inventory => {
             change_state(settings, GameState::Playing);
@@ -308,6 +430,47 @@ pub fn handle_input_inventory(input: InputAction, settings: &mut GameSettings) {
             change_state(settings, GameState::ClassMenu);
         }
 
+        InputAction::SelectItem(item_index) => {
+            // dropping an item asks where to put it down
+            let player_id = data.find_by_name(EntityName::Player).unwrap();
+            if settings.inventory_action == InventoryAction::Drop {
+                if let Some(item_id) = data.entities.inventory[&player_id].get(item_index) {
+                    let selection = Selection::new(SelectionAction::PlaceItem(*item_id), DROP_RADIUS);
+                    start_selection(selection, settings);
+                }
+            }
+        }
+
+        InputAction::AssignQuickSlot(item_index) => {
+            let player_id = data.find_by_name(EntityName::Player).unwrap();
+            if let Some(item_id) = data.entities.inventory[&player_id].get(item_index) {
+                if let Some(item) = data.entities.item.get(item_id) {
+                    settings.assign_quick_slot(QuickSlot::Item(*item));
+                }
+            }
+        }
+
+        _ => {
+        }
+    }
+}
+
+pub fn handle_input_help(input: InputAction, settings: &mut GameSettings, config: &Config) {
+    match input {
+        InputAction::Move(Direction::Up, _) => {
+            settings.help_scroll = settings.help_scroll.saturating_sub(1);
+        }
+
+        InputAction::Move(Direction::Down, _) => {
+            let last_line = help_lines(config).len().saturating_sub(1);
+            settings.help_scroll = std::cmp::min(settings.help_scroll + 1, last_line);
+        }
+
+        InputAction::Esc => {
+            let help_return = settings.help_return;
+            change_state(settings, help_return);
+        }
+
         _ => {
         }
     }
@@ -336,6 +499,13 @@ pub fn handle_input_skill_menu(input: InputAction,
             change_state(settings, GameState::Playing);
         }
 
+        InputAction::AssignQuickSlot(skill_index) => {
+            let player_id = data.find_by_name(EntityName::Player).unwrap();
+            if let Some(skill) = data.entities.skills[&player_id].get(skill_index) {
+                settings.assign_quick_slot(QuickSlot::Skill(*skill));
+            }
+        }
+
         InputAction::Esc => {
             change_state(settings, GameState::Playing);
         }
@@ -381,13 +551,10 @@ pub fn handle_input_class_menu(input: InputAction,
     }
 }
 
-pub fn handle_input_confirm_quit(input: InputAction, settings: &mut GameSettings) {
-    match input {
-        InputAction::Esc => {
-            change_state(settings, GameState::Playing);
-        }
-
-        InputAction::Exit => {
+/// The error screen only lets the player exit, as the game has no map or assets to continue with.
+pub fn handle_input_error(input_action: InputAction, settings: &mut GameSettings) {
+    match input_action {
+        InputAction::Confirm | InputAction::Esc | InputAction::ForceExit => {
             change_state(settings, GameState::Exit);
         }
 
@@ -406,14 +573,12 @@ pub fn handle_input(input_action: InputAction,
             handle_input_playing(input_action, data, settings, msg_log, config);
         }
 
-        GameState::Win => {
-        }
-
-        GameState::Lose => {
+        GameState::Win | GameState::Lose => {
+            // the end of a run can start another one, so it is handled by menu::handle_input_menu instead.
         }
 
         GameState::Inventory => {
-            handle_input_inventory(input_action, settings);
+            handle_input_inventory(input_action, data, settings);
         }
 
         GameState::SkillMenu => {
@@ -424,8 +589,58 @@ pub fn handle_input(input_action: InputAction,
             handle_input_class_menu(input_action, data, settings, msg_log);
         }
 
-        GameState::ConfirmQuit => {
-            handle_input_confirm_quit(input_action, settings);
+        GameState::Help => {
+            handle_input_help(input_action, settings, config);
+        }
+
+        GameState::Prompt => {
+            // prompts play the chosen action from the state they were opened over,
+            // so they are handled by prompt::handle_input_prompt before any other input.
+        }
+
+        GameState::Dialogue => {
+            // conversations change the entity being spoken to directly, so they are
+            // handled by dialogue::handle_input_dialogue instead.
+        }
+
+        GameState::Container => {
+            // moving items in and out of a container changes both inventories directly,
+            // so it is handled by container::handle_input_container instead.
+        }
+
+        GameState::Shop => {
+            // buying items makes new entities and starts the next level, so it is
+            // handled by shop::handle_input_shop instead.
+        }
+
+        GameState::Selection => {
+            handle_input_selection(input_action, data, settings, msg_log);
+        }
+
+        GameState::Sandbox => {
+            // the sandbox edits the game's data directly, so it is handled
+            // by sandbox::handle_input_sandbox instead.
+        }
+
+        GameState::Loading => {
+        }
+
+        GameState::LevelComplete => {
+            // leaving the end of a level starts the next one, so it is handled by menu::handle_input_menu instead.
+        }
+
+        GameState::NewGame => {
+            // choosing a loadout sets up the player directly, so it is handled
+            // by loadout::handle_input_new_game instead.
+        }
+
+        GameState::MainMenu | GameState::Paused | GameState::Options => {
+            // menus can start a new run, so they are handled by menu::handle_input_menu instead.
+        }
+
+        GameState::Error => {
+            // there may be no map after an error, so it is handled by Game::step_game
+            // before any other input.
         }
 
         GameState::Exit => {
@@ -443,6 +658,12 @@ pub fn handle_input_playing(input_action: InputAction,
 
     let player_alive = data.entities.status[&player_id].alive;
 
+    // the key is needed to leave the level, so check before letting go of it
+    if !settings.prompt_confirmed && drops_goal(input_action, data) {
+        confirm(settings, "Drop the key? It is needed to leave the level.", input_action);
+        return;
+    }
+
     match (input_action, player_alive) {
         (InputAction::Move(direction, move_mode), true) => {
             let move_amount = move_mode.move_amount();
@@ -462,6 +683,10 @@ pub fn handle_input_playing(input_action: InputAction,
             handle_item(item_index, ActionLoc::Dir(dir), action_mode, data, msg_log);
         }
 
+        (InputAction::UseItem(dir, item_index), true) => {
+            handle_item(item_index, ActionLoc::Dir(dir), ActionMode::Primary, data, msg_log);
+        }
+
         (InputAction::SkillDir(dir, action_mode, skill_index), true) => {
             handle_skill(skill_index, ActionLoc::Dir(dir), action_mode, data, msg_log);
         }
@@ -482,6 +707,12 @@ pub fn handle_input_playing(input_action: InputAction,
             handle_skill(skill_index, ActionLoc::Facing, action_mode, data, msg_log);
         }
 
+        (InputAction::QuickSlot(slot), true) => {
+            if let Some(action) = quick_slot_action(slot, data, settings) {
+                handle_input_playing(action, data, settings, msg_log, config);
+            }
+        }
+
         (InputAction::CursorReturn, _) => {
             if settings.cursor.is_some() {
                 settings.cursor = Some(player_pos);
@@ -539,8 +770,14 @@ pub fn handle_input_playing(input_action: InputAction,
             msg_log.log(Msg::PickUp(player_id));
         }
 
-        // NOTE this should be removeable
-        (InputAction::MapClick(_map_loc, _map_cell), _) => {
+        (InputAction::SwapPrimaryItem, true) => {
+            msg_log.log(Msg::SwapPrimaryItem(player_id));
+        }
+
+        (InputAction::MapClick(_map_loc, map_cell), _) => {
+            if settings.god_mode {
+                inspect_at(map_cell, data, settings);
+            }
         }
 
         (InputAction::Yell, true) => {
@@ -556,11 +793,23 @@ pub fn handle_input_playing(input_action: InputAction,
         }
 
         (InputAction::OverlayOn, _) => {
-            settings.overlay = true;
+            settings.overlay_held = true;
         }
 
         (InputAction::OverlayOff, _) => {
-            settings.overlay = false;
+            settings.overlay_held = false;
+        }
+
+        (InputAction::CycleOverlay, _) => {
+            settings.overlays = settings.overlays.next();
+        }
+
+        (InputAction::Minimap, _) => {
+            settings.minimap = !settings.minimap;
+        }
+
+        (InputAction::PerfOverlay, _) => {
+            settings.perf_overlay = !settings.perf_overlay;
         }
 
         (InputAction::Inventory, true) => {
@@ -589,11 +838,69 @@ pub fn handle_input_playing(input_action: InputAction,
                 msg_log.log(Msg::Interact(player_id, interact_pos));
         }
 
+        (InputAction::Esc, _) => {
+            change_state(settings, GameState::Paused);
+        }
+
         (_, _) => {
         }
     }
 }
 
+/// The action a hotbar slot stands for, used the same way as the item and skill keys:
+/// towards the cursor when it is shown, or in the direction the player is facing.
+/// Nothing happens for an empty slot, or an item the player no longer has.
+pub fn quick_slot_action(slot: usize, data: &GameData, settings: &GameSettings) -> Option<InputAction> {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+
+    match settings.quick_slots.get(slot) {
+        Some(Some(QuickSlot::Item(item))) => {
+            let index = data.entities.inventory[&player_id].iter()
+                            .position(|item_id| data.entities.item.get(item_id) == Some(item))?;
+
+            if let Some(cursor_pos) = settings.cursor {
+                return Some(InputAction::ItemPos(cursor_pos, ActionMode::Alternate, index));
+            } else {
+                return Some(InputAction::ItemFacing(ActionMode::Primary, index));
+            }
+        }
+
+        Some(Some(QuickSlot::Skill(skill))) => {
+            let index = data.entities.skills[&player_id].iter().position(|other| other == skill)?;
+
+            if let Some(cursor_pos) = settings.cursor {
+                return Some(InputAction::SkillPos(cursor_pos, ActionMode::Primary, index));
+            } else {
+                return Some(InputAction::SkillFacing(ActionMode::Primary, index));
+            }
+        }
+
+        _ => {
+            return None;
+        }
+    }
+}
+
+/// Whether an action would drop or throw away the player's key.
+fn drops_goal(input_action: InputAction, data: &GameData) -> bool {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+
+    let item_index =
+        match input_action {
+            InputAction::DropItemByIndex(index) => index,
+            InputAction::ItemDir(_, ActionMode::Alternate, index) => index,
+            InputAction::ItemPos(_, ActionMode::Alternate, index) => index,
+            InputAction::ItemFacing(ActionMode::Alternate, index) => index,
+            _ => return false,
+        };
+
+    if let Some(item_id) = data.entities.inventory[&player_id].get(item_index) {
+        return data.entities.item.get(item_id) == Some(&Item::Key);
+    }
+
+    return false;
+}
+
 pub fn handle_skill(skill_index: usize,
                     action_loc: ActionLoc,
                     action_mode: ActionMode,
@@ -602,17 +909,25 @@ pub fn handle_skill(skill_index: usize,
     let player_id = data.find_by_name(EntityName::Player).unwrap();
 
     /* Check for Valid Skill Use */
-    // # check if we have enough energy to carry out the skill
-    if data.entities.energy[&player_id] <= 0 {
-        msg_log.log(Msg::NotEnoughEnergy(player_id));
-        return;
-    }
-
     // get the skill in the player's list of skills
     if skill_index >= data.entities.skills[&player_id].len() {
         // NOTE we may want a message indicating that the skill index was invalid
         return;
     }
+    let skill = data.entities.skills[&player_id][skill_index];
+
+    // # check that the skill has recharged since it was last used
+    let cooldown = data.entities.skill_cooldown(player_id, skill);
+    if cooldown > 0 {
+        msg_log.log(Msg::SkillOnCooldown(player_id, skill, cooldown));
+        return;
+    }
+
+    // # check if we have enough energy to carry out the skill
+    if data.entities.energy[&player_id] < skill.energy_cost() {
+        msg_log.log(Msg::NotEnoughEnergy(player_id));
+        return;
+    }
 
     let reach = Reach::single(1);
 
@@ -655,7 +970,7 @@ pub fn handle_skill(skill_index: usize,
     let direction: Option<Direction> = Direction::from_dxy(dxy.x, dxy.y);
 
     /* Carry Out Skill */
-    match data.entities.skills[&player_id][skill_index] {
+    match skill {
         Skill::GrassThrow => {
             if let Some(direction) = Direction::from_dxy(dxy.x, dxy.y) {
                 msg_log.log(Msg::GrassThrow(player_id, direction));
@@ -876,7 +1191,7 @@ fn handle_item(target: usize,
 }
 
 // TODO is this println okay to leave in? seems like it should be in stderr?
-fn change_state(settings: &mut GameSettings, new_state: GameState) {
+pub fn change_state(settings: &mut GameSettings, new_state: GameState) {
     if new_state != settings.state {
         settings.state = new_state;
         match new_state {
@@ -895,16 +1210,67 @@ fn change_state(settings: &mut GameSettings, new_state: GameState) {
                 println!("CONSOLE: Selecting a class");
             }
 
-            GameState::ConfirmQuit => {
-                println!("CONSOLE: Confirm quit");
+            GameState::Prompt => {
+            }
+
+            GameState::Dialogue => {
+                println!("CONSOLE: Talking");
+            }
+
+            GameState::Container => {
+                println!("CONSOLE: Looking in a container");
+            }
+
+            GameState::Shop => {
+                println!("CONSOLE: Visiting the shop");
+            }
+
+            GameState::Selection => {
+                println!("CONSOLE: Choosing a tile");
+            }
+
+            GameState::Sandbox => {
+                println!("CONSOLE: Sandbox mode");
+            }
+
+            GameState::Loading => {
+                println!("CONSOLE: Generating level");
+            }
+
+            GameState::LevelComplete => {
+                println!("CONSOLE: Level complete");
+            }
+
+            GameState::NewGame => {
+                println!("CONSOLE: Choosing a loadout");
+            }
+
+            GameState::MainMenu => {
+                println!("CONSOLE: Main menu");
+            }
+
+            GameState::Paused => {
+                println!("CONSOLE: Paused");
+            }
+
+            GameState::Options => {
+                println!("CONSOLE: Options");
+            }
+
+            GameState::Help => {
+                println!("CONSOLE: Help");
             }
 
             GameState::Win => {
-                println!("CONSOLE: Won Level!");
+                println!("CONSOLE: Won the run!");
             }
 
             GameState::Lose => {
-                println!("CONSOLE: Lost Level!");
+                println!("CONSOLE: Lost the run!");
+            }
+
+            GameState::Error => {
+                println!("CONSOLE: Error");
             }
 
             GameState::Exit => {
@@ -914,3 +1280,41 @@ fn change_state(settings: &mut GameSettings, new_state: GameState) {
     }
 }
 
+
+/// Lines of an action log- the names of actions followed by arguments which may be
+/// missing or the wrong type, along with anything at all.
+#[cfg(test)]
+fn action_line() -> impl Strategy<Value = String> {
+    let names = vec!("left", "downright", "pass", "movetowardscursor", "skilldir", "itemdir", "skillpos", "itempos",
+                     "skillfacing", "itemfacing", "droptarget", "use", "selectitem", "quickslot", "assignquickslot",
+                     "interact", "click", "cursormove", "pickup");
+    let arg = prop_oneof![Just("walk".to_string()), Just("left".to_string()), Just("true".to_string()), "-?[0-9]{1,3}", "[a-z]{0,6}"];
+    let action = (prop::sample::select(names), prop::collection::vec(arg, 0..6))
+        .prop_map(|(name, args)| format!("{} {}", name, args.join(" ")));
+
+    return prop_oneof![action, ".{0,24}"];
+}
+
+#[cfg(test)]
+proptest! {
+    #[test]
+    fn fuzz_input_action_from_str(line in action_line()) {
+        // malformed lines are errors rather than panics
+        let _ = InputAction::from_str(&line);
+    }
+
+    #[test]
+    fn fuzz_input_action_round_trip(dir in prop::sample::select(Direction::directions()), pass in any::<bool>()) {
+        let action = if pass { InputAction::Pass(MoveMode::Sneak) } else { InputAction::Move(dir, MoveMode::Run) };
+        prop_assert_eq!(Ok(action), InputAction::from_str(&action.to_string()));
+    }
+}
+
+#[test]
+fn test_input_action_errors() {
+    assert_eq!(Ok(InputAction::Move(Direction::Left, MoveMode::Walk)), InputAction::from_str("left walk"));
+    assert_eq!(Ok(InputAction::Interact(None)), InputAction::from_str("interact"));
+    assert!(InputAction::from_str("left").is_err());
+    assert!(InputAction::from_str("skillpos 1 x walk 0").is_err());
+    assert!(InputAction::from_str("cursormove up true").is_err());
+}
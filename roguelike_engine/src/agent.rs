@@ -0,0 +1,231 @@
+use std::fmt;
+use std::ops::Range;
+use std::collections::BTreeMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use oorandom::Rand32;
+
+use roguelike_core::types::*;
+use roguelike_core::map::*;
+use roguelike_core::movement::{Direction, MoveMode};
+use roguelike_core::utils::choose;
+
+use crate::game::Game;
+use crate::actions::InputAction;
+use crate::make_map::make_map;
+use crate::error::GameResult;
+
+
+/// Something which plays the game in place of the player, choosing an input each
+/// step from what it sees of the level.
+pub trait Agent {
+    fn name(&self) -> String;
+    fn observe(&mut self, data: &GameData) -> InputAction;
+}
+
+/// Stumbles around, moving in a random direction or waiting each step.
+pub struct RandomAgent {
+    rng: Rand32,
+}
+
+impl RandomAgent {
+    pub fn new(seed: u64) -> RandomAgent {
+        return RandomAgent { rng: Rand32::new(seed) };
+    }
+}
+
+impl Agent for RandomAgent {
+    fn name(&self) -> String {
+        return "random".to_string();
+    }
+
+    fn observe(&mut self, _data: &GameData) -> InputAction {
+        match choose(&mut self.rng, &Direction::directions()) {
+            Some(direction) => return InputAction::Move(direction, MoveMode::Walk),
+            None => return InputAction::Pass(MoveMode::Walk),
+        }
+    }
+}
+
+/// Walks straight to the key, and then to the exit, attacking anything in the way.
+/// It ignores every objective but the key and exit.
+pub struct SeekerAgent;
+
+impl Agent for SeekerAgent {
+    fn name(&self) -> String {
+        return "seeker".to_string();
+    }
+
+    fn observe(&mut self, data: &GameData) -> InputAction {
+        let player_id = data.find_by_name(EntityName::Player).unwrap();
+        let player_pos = data.entities.pos[&player_id];
+
+        let target =
+            if data.is_in_inventory(player_id, Item::Key).is_some() {
+                data.find_by_name(EntityName::Exit)
+            } else {
+                data.find_by_name(EntityName::Key)
+            };
+
+        let target_pos =
+            match target {
+                Some(target_id) => data.entities.pos[&target_id],
+                None => return InputAction::Pass(MoveMode::Walk),
+            };
+
+        if target_pos == player_pos {
+            if data.is_in_inventory(player_id, Item::Key).is_none() {
+                return InputAction::Pickup;
+            }
+            return InputAction::Pass(MoveMode::Walk);
+        }
+
+        let path = astar_path(&data.map, player_pos, target_pos, None, None);
+        match path.get(1).and_then(|next_pos| Direction::from_positions(player_pos, *next_pos)) {
+            Some(direction) => return InputAction::Move(direction, MoveMode::Walk),
+            None => return InputAction::Pass(MoveMode::Walk),
+        }
+    }
+}
+
+/// The agents which can be chosen by name.
+pub fn make_agent(name: &str, seed: u64) -> Option<Box<dyn Agent>> {
+    match name {
+        "random" => return Some(Box::new(RandomAgent::new(seed))),
+        "seeker" => return Some(Box::new(SeekerAgent)),
+        _ => return None,
+    }
+}
+
+/// How an agent's run ended.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AgentOutcome {
+    Won,
+    Died(String), // what killed the player
+    TimedOut,
+    Panicked(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AgentRun {
+    pub seed: u64,
+    pub outcome: AgentOutcome,
+    pub turns: usize,
+    pub levels: usize,
+}
+
+/// Play a run with an agent, without displaying anything, until it is won or lost or the
+/// agent runs out of steps. Menus opened along the way, like the shop, are left straight away.
+pub fn play_agent(agent: &mut dyn Agent, game: &mut Game, max_steps: usize) -> AgentRun {
+    let mut steps = 0;
+    while steps < max_steps && game.settings.state != GameState::Win && game.settings.state != GameState::Lose {
+        let input_action =
+            if game.settings.state == GameState::Playing {
+                agent.observe(&game.data)
+            } else {
+                InputAction::Esc
+            };
+
+        game.step_game(input_action, 0.1);
+        game.msg_log.clear();
+
+        steps += 1;
+    }
+
+    let outcome =
+        match game.settings.state {
+            GameState::Win => AgentOutcome::Won,
+            GameState::Lose => AgentOutcome::Died(game.settings.death_cause.clone().unwrap_or("unknown".to_string())),
+            _ => AgentOutcome::TimedOut,
+        };
+
+    return AgentRun { seed: game.settings.seed, outcome, turns: game.settings.turn_count, levels: game.settings.level_num };
+}
+
+/// The runs of an agent on a number of generated levels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AgentSummary {
+    pub agent: String,
+    pub runs: Vec<AgentRun>,
+}
+
+/// Play a run with the agent for each seed in a range. The given game provides the config,
+/// vaults, and shop to play with. A run which panics is recorded rather than ending the rest,
+/// but a map which can't be loaded stops them all.
+pub fn agent_stats(agent: &mut dyn Agent,
+                   game: &Game,
+                   map_load_config: &MapLoadConfig,
+                   seeds: Range<u64>,
+                   max_steps: usize) -> GameResult<AgentSummary> {
+    let mut summary = AgentSummary { agent: agent.name(), runs: Vec::new() };
+
+    let mut config = game.config.clone();
+    config.async_map_generation = false;
+    config.map_load = map_load_config.clone();
+
+    for seed in seeds {
+        let mut run_game = Game::new(seed, config.clone());
+        run_game.vaults = game.vaults.clone();
+        run_game.shop = game.shop.clone();
+        run_game.dialogue = game.dialogue.clone();
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            make_map(map_load_config, &mut run_game)?;
+            return Ok(play_agent(agent, &mut run_game, max_steps));
+        }));
+
+        match result {
+            Ok(Ok(run)) => summary.runs.push(run),
+            Ok(Err(err)) => return Err(err),
+            Err(error) => {
+                let msg = error.downcast_ref::<&str>().map(|msg| msg.to_string())
+                               .or(error.downcast_ref::<String>().cloned())
+                               .unwrap_or("unknown".to_string());
+                summary.runs.push(AgentRun { seed, outcome: AgentOutcome::Panicked(msg), turns: 0, levels: 0 });
+            }
+        }
+    }
+
+    return Ok(summary);
+}
+
+impl fmt::Display for AgentSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let num_runs = self.runs.len();
+        let count = |matches: fn(&AgentOutcome) -> bool| self.runs.iter().filter(|run| matches(&run.outcome)).count();
+        let won = count(|outcome| *outcome == AgentOutcome::Won);
+        let timed_out = count(|outcome| *outcome == AgentOutcome::TimedOut);
+        let win_percent = if num_runs > 0 { 100.0 * won as f32 / num_runs as f32 } else { 0.0 };
+
+        writeln!(f, "{} runs by the {} agent", num_runs, self.agent)?;
+        writeln!(f, "won: {} ({:.1}%), timed out: {}", won, win_percent, timed_out)?;
+
+        let finished = self.runs.iter().filter(|run| !matches!(run.outcome, AgentOutcome::Panicked(_))).collect::<Vec<&AgentRun>>();
+        if finished.len() > 0 {
+            let turns = finished.iter().map(|run| run.turns).sum::<usize>() as f32 / finished.len() as f32;
+            let levels = finished.iter().map(|run| run.levels).sum::<usize>() as f32 / finished.len() as f32;
+            writeln!(f, "mean turns {:.1}, mean levels finished {:.1}", turns, levels)?;
+        }
+
+        let mut deaths = BTreeMap::new();
+        for run in self.runs.iter() {
+            if let AgentOutcome::Died(cause) = &run.outcome {
+                *deaths.entry(cause.clone()).or_insert(0) += 1;
+            }
+        }
+        writeln!(f, "deaths: {}", deaths.values().sum::<usize>())?;
+        for (cause, count) in deaths.iter() {
+            writeln!(f, "  {}: {}", cause, count)?;
+        }
+
+        let panics = self.runs.iter().filter(|run| matches!(run.outcome, AgentOutcome::Panicked(_))).collect::<Vec<&AgentRun>>();
+        write!(f, "panics: {}", panics.len())?;
+        for run in panics.iter() {
+            if let AgentOutcome::Panicked(msg) = &run.outcome {
+                write!(f, "\n  seed {}: {}", run.seed, msg)?;
+            }
+        }
+
+        return Ok(());
+    }
+}
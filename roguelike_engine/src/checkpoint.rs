@@ -0,0 +1,86 @@
+use std::fs;
+use std::path::Path;
+
+use oorandom::Rand32;
+
+use serde::{Serialize, Deserialize};
+
+use roguelike_core::types::*;
+
+use crate::game::{Game, GameSettings};
+use crate::actions::InputAction;
+use crate::error::{GameError, GameResult, read_yaml};
+
+
+pub const CHECKPOINT_NAME: &str = "checkpoint.yaml";
+
+/// Created when the game starts and removed when it exits cleanly, so finding it on
+/// start means the last session crashed.
+pub const CRASH_MARKER_NAME: &str = "running.marker";
+
+/// A run saved at the start of a level, or when the game exits, so it can be picked
+/// up again if the game crashes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub data: GameData,
+    pub settings: GameSettings,
+    pub rng: (u64, u64),
+    pub run_actions: Vec<InputAction>, // kept so a resumed run can still be saved as a ghost
+}
+
+impl Checkpoint {
+    pub fn from_game(game: &Game) -> Checkpoint {
+        let mut settings = game.settings.clone();
+
+        // a run is resumed into play, rather than whichever menu it was saved from
+        if settings.state != GameState::NewGame {
+            settings.state = GameState::Playing;
+        }
+        settings.prompt = None;
+        settings.running = true;
+
+        return Checkpoint { data: game.data.clone(), settings, rng: game.rng.state(), run_actions: game.run_actions.clone() };
+    }
+
+    pub fn load(file_name: &str) -> GameResult<Checkpoint> {
+        return read_yaml(file_name);
+    }
+
+    pub fn save(&self, file_name: &str) -> GameResult<()> {
+        let contents = serde_yaml::to_string(self).map_err(|err| GameError::write(file_name, err))?;
+        return fs::write(file_name, contents).map_err(|err| GameError::write(file_name, err));
+    }
+
+    /// Put the saved run in place of the game's current one.
+    pub fn restore(self, game: &mut Game) {
+        game.data = self.data;
+        game.settings = self.settings;
+        game.rng = Rand32::from_state(self.rng);
+        game.run_actions = self.run_actions;
+        game.ghost = None;
+        game.snapshots.clear();
+        game.map_generation = None;
+        game.msg_log.clear();
+
+        // the config is the file's own until the run's options are applied to it
+        game.set_difficulty(game.settings.difficulty);
+        game.set_color_palette(game.settings.color_palette);
+    }
+}
+
+/// Mark the game as running, returning whether the mark was already there- meaning the
+/// last session did not exit cleanly.
+pub fn start_session(marker_file: &str) -> bool {
+    let crashed = Path::new(marker_file).exists();
+
+    if let Err(err) = fs::write(marker_file, "") {
+        eprintln!("Could not write {}- {}", marker_file, err);
+    }
+
+    return crashed;
+}
+
+/// Remove the running mark once the game has exited cleanly.
+pub fn end_session(marker_file: &str) {
+    let _ = fs::remove_file(marker_file);
+}
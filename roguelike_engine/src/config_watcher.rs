@@ -0,0 +1,106 @@
+use std::fs;
+use std::time::SystemTime;
+
+use roguelike_core::config::Config;
+
+use crate::game::Game;
+
+
+pub const CONFIG_NAME: &str = "config.yaml";
+
+/// What changed when the config file was checked.
+#[derive(Clone, Debug)]
+pub enum ConfigChange {
+    Reloaded(Config), // the game is using the new config- this is the config it replaced
+    Invalid(String),  // the file changed, but could not be used, so the old config is kept
+}
+
+/// Watches the config file, reloading the game's config whenever the file is modified.
+/// This is shared by every way of running the game, so they all pick up edits the same way.
+///
+/// The file is read as YAML, and as JSON is also YAML, a config written as JSON works as well.
+#[derive(Clone, Debug)]
+pub struct ConfigWatcher {
+    pub file_name: String,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    pub fn new(file_name: &str) -> ConfigWatcher {
+        return ConfigWatcher { file_name: file_name.to_string(), modified: modified_time(file_name) };
+    }
+
+    /// Read the config, failing if it is not a valid config.
+    pub fn load(&self) -> Config {
+        return Config::from_file(&self.file_name);
+    }
+
+    /// Whether the file has been modified since the last time this was checked.
+    pub fn changed(&mut self) -> bool {
+        let modified = modified_time(&self.file_name);
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+
+        self.modified = modified;
+        return true;
+    }
+
+    /// Reload the game's config if the file was modified, returning what changed so other
+    /// systems can react to it.
+    pub fn check(&mut self, game: &mut Game) -> Option<ConfigChange> {
+        if !self.changed() {
+            return None;
+        }
+
+        match Config::load(&self.file_name) {
+            Ok(config) => {
+                let old_config = game.config.clone();
+                game.reload_config(config);
+                return Some(ConfigChange::Reloaded(old_config));
+            }
+
+            Err(error) => {
+                return Some(ConfigChange::Invalid(error));
+            }
+        }
+    }
+}
+
+fn modified_time(file_name: &str) -> Option<SystemTime> {
+    return fs::metadata(file_name).and_then(|metadata| metadata.modified()).ok();
+}
+
+#[test]
+pub fn test_config_watcher() {
+    use std::time::Duration;
+
+    let config_string = fs::read_to_string(format!("../{}", CONFIG_NAME)).unwrap();
+    let file_name = std::env::temp_dir().join("test_config_watcher.yaml").to_str().unwrap().to_string();
+    fs::write(&file_name, &config_string).unwrap();
+
+    let mut watcher = ConfigWatcher::new(&file_name);
+    let mut game = Game::new(0, watcher.load());
+    assert!(watcher.check(&mut game).is_none());
+
+    let touch = |file_name: &str, contents: &str, secs: u64| {
+        fs::write(file_name, contents).unwrap();
+        let file = fs::File::options().write(true).open(file_name).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)).unwrap();
+    };
+
+    // a change is reloaded, and the replaced config is given back
+    touch(&file_name, &config_string.replace("frame_rate: 25", "frame_rate: 60"), 1);
+    match watcher.check(&mut game) {
+        Some(ConfigChange::Reloaded(old_config)) => assert_eq!(25, old_config.frame_rate),
+        change => panic!("Expected a reload, got {:?}", change),
+    }
+    assert_eq!(60, game.config.frame_rate);
+
+    // a broken config is reported, and the last good one kept
+    touch(&file_name, &config_string.replace("frame_rate:", "frame_rat:"), 2);
+    assert!(matches!(watcher.check(&mut game), Some(ConfigChange::Invalid(_))));
+    assert_eq!(60, game.config.frame_rate);
+
+    fs::remove_file(&file_name).unwrap();
+}
@@ -0,0 +1,75 @@
+use roguelike_core::types::*;
+
+use crate::game::Game;
+use crate::actions::{InputAction, change_state};
+
+
+/// Show the transfer menu for a container the player has opened.
+pub fn open_container(container_id: EntityId, game: &mut Game) {
+    game.settings.container = Some(container_id);
+    change_state(&mut game.settings, GameState::Container);
+}
+
+pub fn close_container(game: &mut Game) {
+    game.settings.container = None;
+    change_state(&mut game.settings, GameState::Playing);
+}
+
+/// Handle input while a container is open. The menu lists the container's items
+/// followed by the player's. Choosing one of the container's items takes it, and
+/// choosing one of the player's puts it in the container.
+pub fn handle_input_container(input_action: InputAction, game: &mut Game) {
+    match input_action {
+        InputAction::SelectItem(index) => {
+            if let Some(container_id) = game.settings.container {
+                let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+                let num_stored = game.data.entities.inventory[&container_id].len();
+
+                if index < num_stored {
+                    take_item(player_id, container_id, index, &mut game.data.entities);
+                } else if index - num_stored < game.data.entities.inventory[&player_id].len() {
+                    let item_id = game.data.entities.inventory[&player_id][index - num_stored];
+                    game.data.entities.remove_item(player_id, item_id);
+                    game.data.entities.store_item(container_id, item_id);
+                }
+            }
+        }
+
+        InputAction::Esc => {
+            close_container(game);
+        }
+
+        _ => {
+        }
+    }
+}
+
+/// The choices in a container's transfer menu, in the order they are selected.
+pub fn transfer_options(container_id: EntityId, game: &Game) -> Vec<String> {
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    let mut options = Vec::new();
+    for item_id in game.data.entities.inventory[&container_id].iter() {
+        options.push(format!("take {}", game.locale.name(game.data.entities.name[item_id])));
+    }
+    for item_id in game.data.entities.inventory[&player_id].iter() {
+        options.push(format!("leave {}", game.locale.name(game.data.entities.name[item_id])));
+    }
+
+    return options;
+}
+
+/// Take an item out of a container. If the player's hands are already full,
+/// the primary item they were holding is left in the container in its place.
+fn take_item(player_id: EntityId, container_id: EntityId, index: usize, entities: &mut Entities) {
+    let item_id = entities.inventory[&container_id][index];
+    entities.inventory[&container_id].remove(index);
+
+    if entities.item[&item_id].class() == ItemClass::Primary && entities.held_item(player_id, Hand::Secondary).is_some() {
+        let swapped_id = entities.held_item(player_id, Hand::Primary).unwrap();
+        entities.remove_item(player_id, swapped_id);
+        entities.store_item(container_id, swapped_id);
+    }
+
+    entities.pick_up_item(player_id, item_id);
+}
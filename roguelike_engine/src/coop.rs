@@ -0,0 +1,216 @@
+use std::str::FromStr;
+use std::io::{BufRead, BufReader};
+use std::net::{TcpListener, TcpStream, SocketAddr};
+use std::sync::mpsc::{channel, Receiver};
+use std::collections::VecDeque;
+use std::thread;
+
+use roguelike_core::types::*;
+use roguelike_core::messaging::Msg;
+use roguelike_core::utils::add_pos;
+use roguelike_core::movement::Direction;
+
+use crate::game::Game;
+use crate::actions::InputAction;
+use crate::generation::make_partner;
+use crate::step::remove_entity;
+use crate::spectate::SpectatorServer;
+
+
+/// Whether an action is one of a co-op turn's, which waits for the other player's before
+/// either is played. Only moving and passing are supported by the partner for now.
+pub fn takes_turn(input_action: InputAction) -> bool {
+    return matches!(input_action, InputAction::Move(_, _) | InputAction::Pass(_));
+}
+
+/// The messages for the partner's part of a turn.
+pub fn partner_msgs(input_action: InputAction, partner_id: EntityId, data: &GameData) -> Vec<Msg> {
+    match input_action {
+        InputAction::Move(direction, move_mode) => {
+            return vec!(Msg::TryMove(partner_id, direction, move_mode.move_amount(), move_mode));
+        }
+
+        InputAction::Pass(move_mode) => {
+            return vec!(Msg::TryMove(partner_id, data.entities.direction[&partner_id], 0, move_mode));
+        }
+
+        _ => {
+            return Vec::new();
+        }
+    }
+}
+
+/// Put the partner next to the player, if they are not already on the level. Returns
+/// the partner, or None if there is nowhere clear beside the player.
+pub fn place_partner(game: &mut Game) -> Option<EntityId> {
+    if let Some(partner_id) = game.data.find_by_name(EntityName::Partner) {
+        return Some(partner_id);
+    }
+
+    let player_id = game.data.find_by_name(EntityName::Player)?;
+    let player_pos = game.data.entities.pos[&player_id];
+    let clear_pos = game.data.get_clear_pos();
+    let pos = Direction::directions().iter()
+                                     .map(|direction| add_pos(player_pos, direction.into_move()))
+                                     .find(|pos| clear_pos.contains(pos))?;
+
+    return Some(make_partner(&mut game.data.entities, &game.config, pos, &mut game.msg_log));
+}
+
+/// Hosts a co-op game on a LAN. The host's game is the only one played- the partner sends their
+/// input, written as in the action log, and is streamed the game to draw as a spectator would.
+/// A turn is played once both players have chosen their action for it.
+pub struct CoopHost {
+    listener: TcpListener,
+    partner_input: Option<Receiver<InputAction>>,
+    stream: SpectatorServer,
+    host_action: Option<InputAction>,
+    partner_actions: VecDeque<InputAction>,
+}
+
+impl CoopHost {
+    pub fn bind(address: &str) -> Result<CoopHost, String> {
+        let listener = TcpListener::bind(address).map_err(|err| format!("Could not host co-op on {}: {}", address, err))?;
+        listener.set_nonblocking(true).map_err(|err| err.to_string())?;
+
+        return Ok(CoopHost { listener,
+                             partner_input: None,
+                             stream: SpectatorServer::new(),
+                             host_action: None,
+                             partner_actions: VecDeque::new() });
+    }
+
+    pub fn address(&self) -> Option<SocketAddr> {
+        return self.listener.local_addr().ok();
+    }
+
+    pub fn connected(&self) -> bool {
+        return self.partner_input.is_some();
+    }
+
+    /// Whether the host has chosen their action for the turn, and is waiting on the partner.
+    pub fn waiting(&self) -> bool {
+        return self.host_action.is_some();
+    }
+
+    /// Step the game with the host's input. Until a partner joins, and after they leave or die,
+    /// the game plays as it would alone. Otherwise the host's moves are held until the partner
+    /// has sent one too, and the two are played as one turn.
+    pub fn step_game(&mut self, game: &mut Game, input_action: InputAction, dt: f32) -> Result<bool, String> {
+        self.accept()?;
+        if self.receive() {
+            self.leave(game);
+        }
+
+        let partner =
+            if self.connected() && game.settings.state == GameState::Playing {
+                place_partner(game)
+            } else {
+                None
+            };
+
+        let mut input_action = input_action;
+        if let Some(partner_id) = partner.filter(|partner_id| game.data.entities.status[partner_id].alive) {
+            if takes_turn(input_action) {
+                if self.host_action.is_none() {
+                    self.host_action = Some(input_action);
+                }
+                input_action = InputAction::None;
+            }
+
+            if let (Some(host_action), Some(partner_action)) = (self.host_action, self.partner_actions.front().cloned()) {
+                self.host_action = None;
+                self.partner_actions.pop_front();
+
+                for msg in partner_msgs(partner_action, partner_id, &game.data) {
+                    game.msg_log.log(msg);
+                }
+                input_action = host_action;
+            }
+        }
+
+        let running = game.step_game(input_action, dt);
+        self.stream.update(game)?;
+
+        // a partner who stops reading the game is dropped from the stream, and so from the game
+        if self.connected() && self.stream.num_spectators() == 0 {
+            self.leave(game);
+        }
+
+        return Ok(running);
+    }
+
+    /// The partner has gone, so the host plays on alone and another partner may join.
+    /// Anything waiting on the partner is forgotten, and their character leaves the level.
+    fn leave(&mut self, game: &mut Game) {
+        println!("CONSOLE: Co-op partner left");
+        self.partner_input = None;
+        self.partner_actions.clear();
+        self.host_action = None;
+
+        if let Some(partner_id) = game.data.find_by_name(EntityName::Partner) {
+            remove_entity(partner_id, &mut game.data, &mut game.settings, &mut game.msg_log);
+        }
+    }
+
+    fn accept(&mut self) -> Result<(), String> {
+        if self.connected() {
+            return Ok(());
+        }
+
+        if let Ok((stream, _address)) = self.listener.accept() {
+            let reader = stream.try_clone().map_err(|err| err.to_string())?;
+            self.stream.join(stream)?;
+            self.partner_input = Some(read_input(reader));
+            println!("CONSOLE: Co-op partner joined");
+        }
+
+        return Ok(());
+    }
+
+    /// Take the actions the partner has sent, returning whether they have left.
+    fn receive(&mut self) -> bool {
+        if let Some(partner_input) = &self.partner_input {
+            loop {
+                match partner_input.try_recv() {
+                    Ok(input_action) => {
+                        if takes_turn(input_action) {
+                            self.partner_actions.push_back(input_action);
+                        }
+                    }
+
+                    Err(std::sync::mpsc::TryRecvError::Empty) => break,
+
+                    Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        return false;
+    }
+}
+
+/// Read a player's input from a connection, a line at a time, until it closes. Lines
+/// which are not an action are skipped.
+fn read_input(stream: TcpStream) -> Receiver<InputAction> {
+    let (sender, receiver) = channel();
+
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+
+            if let Ok(input_action) = InputAction::from_str(&line) {
+                if sender.send(input_action).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    return receiver;
+}
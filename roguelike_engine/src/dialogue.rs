@@ -0,0 +1,112 @@
+use serde::{Serialize, Deserialize};
+
+use roguelike_core::types::*;
+use roguelike_core::ai::Behavior;
+
+use crate::game::Game;
+use crate::error::{GameResult, read_yaml};
+use crate::actions::{InputAction, change_state};
+
+
+/// One line spoken by a friendly entity, and the ways the player can answer it.
+/// Nodes are referred to by their index in the dialogue file, and the first node
+/// starts every conversation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DialogueNode {
+    pub text: String,
+    pub responses: Vec<DialogueResponse>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DialogueResponse {
+    pub text: String,
+    #[serde(default)]
+    pub next: Option<usize>, // node to continue with, or None to end the conversation
+    #[serde(default)]
+    pub action: Option<DialogueAction>,
+}
+
+/// What answering a response asks the entity being spoken to to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DialogueAction {
+    Follow,
+    Wait,
+}
+
+/// A conversation in progress- who is being spoken to, and which node they are on.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Conversation {
+    pub speaker: EntityId,
+    pub node: usize,
+}
+
+impl DialogueNode {
+    pub fn from_file(file_name: &str) -> GameResult<Vec<DialogueNode>> {
+        return read_yaml(file_name);
+    }
+}
+
+/// Start talking to an entity, if there is any dialogue to show.
+pub fn start_conversation(speaker: EntityId, game: &mut Game) {
+    if game.dialogue.len() > 0 {
+        game.settings.conversation = Some(Conversation { speaker, node: 0 });
+        change_state(&mut game.settings, GameState::Dialogue);
+    }
+}
+
+pub fn end_conversation(game: &mut Game) {
+    game.settings.conversation = None;
+    change_state(&mut game.settings, GameState::Playing);
+}
+
+/// Handle input during a conversation. Choosing a response carries out its action
+/// and moves on to its next node, ending the conversation if there is none.
+pub fn handle_input_dialogue(input_action: InputAction, game: &mut Game) {
+    match input_action {
+        InputAction::SelectItem(index) => {
+            if let Some(mut conversation) = game.settings.conversation {
+                let node = &game.dialogue[conversation.node];
+
+                if index < node.responses.len() {
+                    let response = node.responses[index].clone();
+
+                    if let Some(action) = response.action {
+                        apply_dialogue_action(action, conversation.speaker, game);
+                    }
+
+                    match response.next {
+                        Some(next) if next < game.dialogue.len() => {
+                            conversation.node = next;
+                            game.settings.conversation = Some(conversation);
+                        }
+
+                        _ => {
+                            end_conversation(game);
+                        }
+                    }
+                }
+            }
+        }
+
+        InputAction::Esc => {
+            end_conversation(game);
+        }
+
+        _ => {
+        }
+    }
+}
+
+fn apply_dialogue_action(action: DialogueAction, speaker: EntityId, game: &mut Game) {
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    match action {
+        DialogueAction::Follow => {
+            game.data.entities.behavior[&speaker] = Behavior::Following(player_id);
+        }
+
+        DialogueAction::Wait => {
+            game.data.entities.behavior[&speaker] = Behavior::Idle;
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use oorandom::Rand32;
+
+use roguelike_core::types::*;
+use roguelike_core::config::Config;
+use roguelike_core::constants::*;
+use roguelike_core::map::astar_path;
+use roguelike_core::utils::{distance, rng_range_u32};
+
+use crate::game::Game;
+use crate::procgen::ProcCmd;
+
+
+/// How much of a level's spawn budget a monster uses up, or None for entities
+/// which are not monsters, such as prisoners and containers.
+pub fn spawn_cost(name: EntityName) -> Option<usize> {
+    match name {
+        EntityName::Gol | EntityName::Pawn | EntityName::Armil => return Some(1),
+        EntityName::Spire | EntityName::Rook => return Some(2),
+        EntityName::Knight => return Some(3),
+        _ => return None,
+    }
+}
+
+/// The monster budget for a level. Deeper levels get more monsters, and a noisy
+/// previous level draws more of them to the next one.
+pub fn spawn_budget(level_num: usize, last_level_noise: usize, config: &Config) -> usize {
+    let mut budget = config.spawn_budget + level_num * config.spawn_budget_per_level;
+
+    if config.spawn_noise_per_budget > 0 {
+        budget += last_level_noise / config.spawn_noise_per_budget;
+    }
+
+    return budget;
+}
+
+/// Choose the monsters for a level from the procgen file's Entities ranges. Each type's
+/// minimum is always spawned, and the rest of the budget is spent on random types which
+/// are still under their maximum, until nothing more is affordable.
+pub fn choose_spawns(cmds: &Vec<ProcCmd>, budget: usize, rng: &mut Rand32) -> Vec<EntityName> {
+    let mut spawns = Vec::new();
+    let mut limits = Vec::new();
+    let mut spent = 0;
+
+    for cmd in cmds.iter() {
+        if let ProcCmd::Entities(typ, min, max) = cmd {
+            if let Some(cost) = spawn_cost(*typ) {
+                for _ in 0..*min {
+                    spawns.push(*typ);
+                    spent += cost;
+                }
+
+                if max > min {
+                    limits.push((*typ, cost, max - min));
+                }
+            }
+        }
+    }
+
+    loop {
+        let remaining = budget.saturating_sub(spent);
+        let affordable = limits.iter()
+                               .enumerate()
+                               .filter(|(_index, (_typ, cost, left))| *left > 0 && *cost <= remaining)
+                               .map(|(index, _limit)| index)
+                               .collect::<Vec<usize>>();
+        if affordable.len() == 0 {
+            break;
+        }
+
+        let choice = affordable[rng_range_u32(rng, 0, affordable.len() as u32) as usize];
+        let (typ, cost, left) = limits[choice];
+        limits[choice] = (typ, cost, left - 1);
+
+        spawns.push(typ);
+        spent += cost;
+    }
+
+    return spawns;
+}
+
+/// The tiles a monster may start on- clear tiles far enough from the player's start
+/// and from the paths to the key and exit that the player isn't caught straight away.
+/// If the level is too cramped for that, only the distance from the player is kept.
+pub fn spawn_positions(game: &Game, player_pos: Pos) -> Vec<Pos> {
+    let mut key_path = Vec::new();
+    let key = game.data.find_by_name(EntityName::Key).map(|key_id| game.data.entities.pos[&key_id]);
+    let exit = game.data.find_by_name(EntityName::Exit).map(|exit_id| game.data.entities.pos[&exit_id]);
+    if let Some(key_pos) = key {
+        key_path.extend(astar_path(&game.data.map, player_pos, key_pos, None, None));
+
+        if let Some(exit_pos) = exit {
+            key_path.extend(astar_path(&game.data.map, key_pos, exit_pos, None, None));
+        }
+    }
+
+    let clear_pos =
+        game.data.get_clear_pos()
+                 .into_iter()
+                 .filter(|pos| distance(player_pos, *pos) >= game.config.spawn_distance_player)
+                 .collect::<Vec<Pos>>();
+
+    let positions =
+        clear_pos.iter()
+                 .filter(|pos| key_path.iter().all(|path_pos| distance(*path_pos, **pos) >= game.config.spawn_distance_path))
+                 .map(|pos| *pos)
+                 .collect::<Vec<Pos>>();
+
+    if positions.len() == 0 {
+        return clear_pos;
+    }
+
+    return positions;
+}
+
+/// The tiles where reinforcements enter the level- clear tiles near the map's edge,
+/// as far from the player's start as possible and spaced out from each other.
+pub fn find_entry_points(data: &GameData, player_pos: Pos) -> Vec<Pos> {
+    let (width, height) = data.map.size();
+
+    let mut candidates =
+        data.get_clear_pos()
+            .into_iter()
+            .filter(|pos| pos.x < ENTRY_POINT_EDGE || pos.y < ENTRY_POINT_EDGE ||
+                          pos.x >= width - ENTRY_POINT_EDGE || pos.y >= height - ENTRY_POINT_EDGE)
+            .collect::<Vec<Pos>>();
+    candidates.sort_by_key(|pos| -distance(player_pos, *pos));
+
+    let mut entry_points: Vec<Pos> = Vec::new();
+    for pos in candidates {
+        if entry_points.len() >= NUM_ENTRY_POINTS {
+            break;
+        }
+
+        if entry_points.iter().all(|entry_pos| distance(*entry_pos, pos) >= ENTRY_POINT_SPACING) {
+            entry_points.push(pos);
+        }
+    }
+
+    return entry_points;
+}
+
+#[test]
+fn test_spawn_budget() {
+    let mut config = Config::from_file("../config.yaml");
+    config.spawn_budget = 4;
+    config.spawn_budget_per_level = 2;
+    config.spawn_noise_per_budget = 10;
+
+    assert_eq!(4, spawn_budget(0, 0, &config));
+    assert_eq!(8, spawn_budget(2, 0, &config));
+    assert_eq!(11, spawn_budget(2, 35, &config));
+}
+
+#[test]
+fn test_choose_spawns() {
+    let mut rng = Rand32::new(0);
+    let cmds = vec!(ProcCmd::Entities(EntityName::Gol, 1, 3),
+                    ProcCmd::Entities(EntityName::Knight, 1, 1),
+                    ProcCmd::Entities(EntityName::Chest, 1, 1));
+
+    // minimums are spawned even over budget, and containers aren't counted
+    let spawns = choose_spawns(&cmds, 0, &mut rng);
+    assert_eq!(vec!(EntityName::Gol, EntityName::Knight), spawns);
+
+    // the rest of the budget is spent without passing any maximum
+    let spawns = choose_spawns(&cmds, 100, &mut rng);
+    assert_eq!(3, spawns.iter().filter(|name| **name == EntityName::Gol).count());
+    assert_eq!(1, spawns.iter().filter(|name| **name == EntityName::Knight).count());
+
+    let spawns = choose_spawns(&cmds, 5, &mut rng);
+    assert_eq!(2, spawns.iter().filter(|name| **name == EntityName::Gol).count());
+}
@@ -0,0 +1,69 @@
+use std::fmt::Display;
+use std::fs;
+
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+
+use thiserror::Error;
+
+
+/// The ways loading the game's maps and assets, or saving a checkpoint, can fail. These are shown on the
+/// error screen rather than stopping the game with a panic.
+#[derive(Clone, Debug, PartialEq, Error, Serialize, Deserialize)]
+pub enum GameError {
+    #[error("could not read {file}: {msg}")]
+    Read { file: String, msg: String },
+
+    #[error("could not write {file}: {msg}")]
+    Write { file: String, msg: String },
+
+    #[error("could not parse {file}: {msg}")]
+    Parse { file: String, msg: String },
+
+    #[error("{0}")]
+    Asset(String), // loaders which describe their own errors, like vaults, REXPaint maps, and locales
+
+    #[error("level {level} has no map- {file} only lists {count}")]
+    NoMap { file: String, level: usize, count: usize },
+
+    #[error("map generation stopped without making a map")]
+    MapGeneration,
+}
+
+impl GameError {
+    pub fn read(file_name: &str, err: impl Display) -> GameError {
+        return GameError::Read { file: file_name.to_string(), msg: err.to_string() };
+    }
+
+    pub fn write(file_name: &str, err: impl Display) -> GameError {
+        return GameError::Write { file: file_name.to_string(), msg: err.to_string() };
+    }
+
+    pub fn parse(file_name: &str, err: impl Display) -> GameError {
+        return GameError::Parse { file: file_name.to_string(), msg: err.to_string() };
+    }
+}
+
+pub type GameResult<T> = Result<T, GameError>;
+
+/// Read a YAML asset file, such as the loadouts or the shop.
+pub fn read_yaml<T: DeserializeOwned>(file_name: &str) -> GameResult<T> {
+    let contents = fs::read_to_string(file_name).map_err(|err| GameError::read(file_name, err))?;
+    return serde_yaml::from_str(&contents).map_err(|err| GameError::parse(file_name, err));
+}
+
+#[test]
+fn test_read_yaml_errors() {
+    let missing = read_yaml::<Vec<String>>("no_such_file.yaml");
+    assert!(matches!(missing, Err(GameError::Read { .. })));
+
+    let file_name = std::env::temp_dir().join("bad_asset.yaml");
+    fs::write(&file_name, "[unclosed").unwrap();
+    let file_name = file_name.to_str().unwrap();
+
+    let bad = read_yaml::<Vec<String>>(file_name);
+    assert!(matches!(bad, Err(GameError::Parse { .. })));
+    assert!(bad.unwrap_err().to_string().starts_with(&format!("could not parse {}", file_name)));
+
+    fs::remove_file(file_name).unwrap();
+}
@@ -1,4 +1,8 @@
 use std::default::Default;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
 
 use oorandom::Rand32;
 
@@ -7,7 +11,9 @@ use serde::{Serialize, Deserialize};
 use roguelike_core::types::*;
 use roguelike_core::config::*;
 use roguelike_core::map::*;
-use roguelike_core::messaging::MsgLog;
+use roguelike_core::messaging::{Msg, MsgLog};
+use roguelike_core::locale::Locale;
+use roguelike_core::constants::{MAX_VOLUME, NUM_QUICK_SLOTS};
 
 use crate::actions;
 use crate::actions::InputAction;
@@ -16,8 +22,29 @@ use crate::make_map::make_map;
 use crate::step::step_logic;
 use crate::input::*;
 use crate::vault::*;
+use crate::sandbox::{self, PaletteEntry};
+use crate::loadout::{self, Loadout};
+use crate::menu;
+use crate::prompt::{self, Prompt};
+use crate::dialogue::{self, DialogueNode, Conversation};
+use crate::container;
+use crate::checkpoint::Checkpoint;
+use crate::ghost::{self, Ghost};
+use crate::shop::{self, ShopStock};
+use crate::selection::Selection;
+use crate::score::{RunStats, ScoreEntry, Leaderboard, LevelSummary, death_cause};
+use crate::achievement::{Achievement, AchievementTracker, Unlocks};
+use crate::overlay::Overlays;
+use crate::error::{GameError, GameResult};
 
 
+/// A level being generated on a separate thread, which sends back
+/// the game it generated the level in when it is done, or why it could not.
+#[derive(Clone, Debug)]
+pub struct MapGeneration {
+    receiver: Arc<Mutex<Receiver<GameResult<Game>>>>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Game {
     pub config: Config,
@@ -26,11 +53,35 @@ pub struct Game {
     pub msg_log: MsgLog,
     pub rng: Rand32,
     pub vaults: Vec<Vault>,
+    pub loadouts: Vec<Loadout>,
+    pub dialogue: Vec<DialogueNode>,
+    pub shop: Vec<ShopStock>,
+    pub locale: Locale,
+    pub locale_dir: Option<String>,
+    pub leaderboard: Leaderboard,
+    pub leaderboard_file: Option<String>,
+    pub unlocks: Unlocks,
+    pub unlocks_file: Option<String>,
+    pub checkpoint_file: Option<String>, // where the run is saved at the start of each level
+    pub ghost_dir: Option<String>, // where the best run on each seed is saved, to be raced as a ghost
+    pub ghost: Option<Box<Ghost>>,
+    pub run_actions: Vec<InputAction>, // the input given since the run started
     pub input: Input,
+    pub snapshots: VecDeque<String>,
+    pub map_failures: HashMap<String, usize>,
+    pub vaults_placed: usize, // vaults placed in the current generated map
+    pub map_generation: Option<MapGeneration>,
+    pub default_palette: ColorPalette, // the colors from the config file, before any palette
+    pub default_difficulty: DifficultyConfig, // the config file's own settings, before any difficulty
 }
 
 impl Game {
-    pub fn new(seed: u64, config: Config) -> Game {
+    pub fn new(seed: u64, mut config: Config) -> Game {
+        let default_difficulty = config.as_difficulty();
+        if let Some(difficulty) = config.difficulties.get(&config.difficulty) {
+            config.apply_difficulty(&difficulty.clone());
+        }
+
         let entities = Entities::new();
         let rng: Rand32 = Rand32::new(seed);
 
@@ -45,28 +96,282 @@ impl Game {
 
         let vaults: Vec<Vault> = Vec::new();
 
+        let mut settings = GameSettings::new(0, false);
+        settings.seed = seed;
+        settings.difficulty = config.difficulty;
+
+        let default_palette = config.as_palette("default");
+
         let state = Game {
             config,
             data,
-            settings: GameSettings::new(0, false),
+            settings,
             msg_log,
             rng: rng,
             vaults,
+            loadouts: Vec::new(),
+            dialogue: Vec::new(),
+            shop: Vec::new(),
+            locale: Locale::english(),
+            locale_dir: None,
+            leaderboard: Leaderboard::default(),
+            leaderboard_file: None,
+            unlocks: Unlocks::default(),
+            unlocks_file: None,
+            checkpoint_file: None,
+            ghost_dir: None,
+            ghost: None,
+            run_actions: Vec::new(),
             input: Input::new(),
+            snapshots: VecDeque::new(),
+            map_failures: HashMap::new(),
+            vaults_placed: 0,
+            map_generation: None,
+            default_palette,
+            default_difficulty,
         };
 
         return state;
     }
 
-    pub fn load_vaults(&mut self, path: &str) {
-        for entry in std::fs::read_dir(path).unwrap() {
-            let entry = entry.unwrap();
+    pub fn load_vaults(&mut self, path: &str) -> GameResult<()> {
+        for entry in std::fs::read_dir(path).map_err(|err| GameError::read(path, err))? {
+            let entry = entry.map_err(|err| GameError::read(path, err))?;
             let path = entry.path();
-            let vault_file_name = path.to_str().unwrap();
+            let vault_file_name = path.to_string_lossy();
             if !vault_file_name.ends_with(".csv") {
                 continue;
             }
-            self.vaults.push(parse_vault(vault_file_name, &self.config));
+
+            // a broken vault is left out rather than stopping the game- the vault linter says what is wrong with it
+            match parse_vault(&vault_file_name, &self.config) {
+                Ok(vault) => self.vaults.push(vault),
+                Err(err) => eprintln!("Vault not loaded- {}", err),
+            }
+        }
+
+        return Ok(());
+    }
+
+    pub fn load_loadouts(&mut self, file_name: &str) -> GameResult<()> {
+        self.loadouts = Loadout::from_file(file_name)?;
+        return Ok(());
+    }
+
+    pub fn load_dialogue(&mut self, file_name: &str) -> GameResult<()> {
+        self.dialogue = DialogueNode::from_file(file_name)?;
+        return Ok(());
+    }
+
+    pub fn load_shop(&mut self, file_name: &str) -> GameResult<()> {
+        self.shop = ShopStock::from_file(file_name)?;
+        return Ok(());
+    }
+
+    /// Load the locale chosen in the config from a directory of locale files. The
+    /// directory is kept so a different locale can be loaded if the config changes.
+    pub fn load_locale(&mut self, dir: &str) -> GameResult<()> {
+        self.locale = Locale::from_dir(dir, &self.config.locale).map_err(GameError::Asset)?;
+        self.locale_dir = Some(dir.to_string());
+        return Ok(());
+    }
+
+    /// Load the leaderboard, which is saved back to the same file whenever a run ends.
+    pub fn load_leaderboard(&mut self, file_name: &str) -> GameResult<()> {
+        self.leaderboard = Leaderboard::from_file(file_name)?;
+        self.leaderboard_file = Some(file_name.to_string());
+        return Ok(());
+    }
+
+    /// Load the achievements unlocked in earlier runs, which are saved back to the same file
+    /// whenever a new one is unlocked.
+    pub fn load_unlocks(&mut self, file_name: &str) -> GameResult<()> {
+        self.unlocks = Unlocks::from_file(file_name)?;
+        self.unlocks_file = Some(file_name.to_string());
+        return Ok(());
+    }
+
+    /// Save the run to the given file at the start of each level, so it can be resumed after a crash.
+    pub fn set_checkpoint_file(&mut self, file_name: &str) {
+        self.checkpoint_file = Some(file_name.to_string());
+    }
+
+    /// Save the run, if there is a checkpoint file. A checkpoint which can't be saved is
+    /// reported but doesn't stop the game.
+    pub fn save_checkpoint(&self) {
+        if let Some(file_name) = &self.checkpoint_file {
+            if let Err(err) = Checkpoint::from_game(self).save(file_name) {
+                eprintln!("Checkpoint not saved- {}", err);
+            }
+        }
+    }
+
+    /// Remove the checkpoint once its run has ended, so there is nothing to resume.
+    pub fn remove_checkpoint(&self) {
+        if let Some(file_name) = &self.checkpoint_file {
+            let _ = std::fs::remove_file(file_name);
+        }
+    }
+
+    /// Continue the run saved in the checkpoint file.
+    pub fn resume_checkpoint(&mut self) -> GameResult<()> {
+        if let Some(file_name) = self.checkpoint_file.clone() {
+            Checkpoint::load(&file_name)?.restore(self);
+            println!("CONSOLE: Resumed level {} of seed {}", self.settings.level_num, self.settings.seed);
+        }
+        return Ok(());
+    }
+
+    /// Save the best run on each seed to the given directory, and race against it when the seed is played again.
+    pub fn set_ghost_dir(&mut self, dir: &str) {
+        self.ghost_dir = Some(dir.to_string());
+    }
+
+    /// Start the ghost of the best earlier run on this seed, if there is one and ghosts are turned on.
+    pub fn start_ghost(&mut self) {
+        self.ghost = None;
+
+        if let (Some(dir), true) = (&self.ghost_dir, self.config.ghost_replay) {
+            let file_name = ghost::ghost_file(dir, self.settings.seed);
+            if std::path::Path::new(&file_name).exists() {
+                match ghost::read_ghost(&file_name) {
+                    Ok(actions) => self.ghost = Some(Box::new(Ghost::new(self, actions))),
+                    Err(err) => eprintln!("Ghost not loaded- {}", err),
+                }
+            }
+        }
+    }
+
+    /// Stop the game on an error loading a map or asset, showing it to the player until
+    /// they exit rather than panicking.
+    pub fn show_error(&mut self, error: GameError) {
+        eprintln!("{}", error);
+        self.settings.error = Some(error);
+        actions::change_state(&mut self.settings, GameState::Error);
+    }
+
+    /// Throw away the current run and start a new one from the given seed. The player's
+    /// options and difficulty are kept, and a loadout is chosen first if there are any to choose from.
+    pub fn new_run(&mut self, seed: u64) {
+        let mut config = self.config.clone();
+        config.apply_difficulty(&self.default_difficulty);
+        config.difficulty = self.settings.difficulty;
+
+        let mut game = Game::new(seed, config);
+        game.vaults = self.vaults.clone();
+        game.loadouts = self.loadouts.clone();
+        game.dialogue = self.dialogue.clone();
+        game.shop = self.shop.clone();
+        game.locale = self.locale.clone();
+        game.locale_dir = self.locale_dir.clone();
+        game.leaderboard = self.leaderboard.clone();
+        game.leaderboard_file = self.leaderboard_file.clone();
+        game.unlocks = self.unlocks.clone();
+        game.unlocks_file = self.unlocks_file.clone();
+        game.checkpoint_file = self.checkpoint_file.clone();
+        game.ghost_dir = self.ghost_dir.clone();
+
+        game.settings.volume = self.settings.volume;
+        game.settings.overlays = self.settings.overlays;
+        game.settings.render_map = self.settings.render_map;
+        game.settings.minimap = self.settings.minimap;
+        game.settings.color_palette = self.settings.color_palette;
+        game.default_palette = self.default_palette.clone();
+
+        match make_map(&game.config.map_load.clone(), &mut game) {
+            Ok(()) => {
+                if game.loadouts.len() > 0 {
+                    game.settings.state = GameState::NewGame;
+                }
+                game.save_checkpoint();
+                game.start_ghost();
+            }
+
+            Err(err) => game.show_error(err),
+        }
+
+        println!("CONSOLE: New run with seed {}", seed);
+
+        *self = game;
+    }
+
+    /// The palettes which can be chosen, starting with the config file's own colors.
+    pub fn color_palettes(&self) -> Vec<ColorPalette> {
+        let mut palettes = vec!(self.default_palette.clone());
+        palettes.extend(self.config.palettes.iter().cloned());
+        return palettes;
+    }
+
+    /// Switch to one of the color palettes. Entities are given their colors when they
+    /// are made, so any entity with one of the old palette's colors is recolored as well.
+    pub fn set_color_palette(&mut self, index: usize) {
+        let palettes = self.color_palettes();
+        if index >= palettes.len() {
+            return;
+        }
+
+        let old_config = self.config.clone();
+
+        self.settings.color_palette = index;
+        self.config.apply_palette(&self.default_palette);
+        self.config.apply_palette(&palettes[index]);
+
+        self.recolor_entities(&old_config);
+
+        if let Some(locale_dir) = self.locale_dir.clone() {
+            if self.config.locale != old_config.locale {
+                // the current locale is kept if the new one can't be loaded
+                if let Err(err) = self.load_locale(&locale_dir) {
+                    eprintln!("Locale not loaded- {}", err);
+                }
+            }
+        }
+    }
+
+    /// Choose the difficulty for new runs. It applies to the current run as well, but the
+    /// player and monsters already made keep their settings.
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.settings.difficulty = difficulty;
+        self.config.apply_difficulty(&self.default_difficulty);
+        if let Some(difficulty_config) = self.config.difficulties.get(&difficulty) {
+            self.config.apply_difficulty(&difficulty_config.clone());
+        }
+    }
+
+    /// Write the settings changed while the game runs back to the config file. The settings
+    /// the difficulty changes are saved as the file had them, so a difficulty's settings
+    /// don't become the file's own.
+    pub fn save_config(&self, file_name: &str) -> Result<(), String> {
+        let mut config = self.config.clone();
+        config.apply_difficulty(&self.default_difficulty);
+        return config.save_fields(file_name);
+    }
+
+    /// Use a newly loaded config, such as after config.yaml is edited, keeping the current
+    /// palette and difficulty.
+    pub fn reload_config(&mut self, config: Config) {
+        let old_config = self.config.clone();
+
+        self.config = config;
+        self.default_palette = self.config.as_palette("default");
+        if let Some(palette) = self.color_palettes().get(self.settings.color_palette) {
+            self.config.apply_palette(palette);
+        }
+
+        self.default_difficulty = self.config.as_difficulty();
+        self.set_difficulty(self.settings.difficulty);
+
+        self.recolor_entities(&old_config);
+    }
+
+    fn recolor_entities(&mut self, old_config: &Config) {
+        let old_colors = old_config.as_palette("old").colors;
+
+        for entity_id in self.data.entities.ids.iter() {
+            let color = self.data.entities.color[entity_id];
+            if let Some((name, _color)) = old_colors.iter().find(|(_name, old_color)| **old_color == color) {
+                self.data.entities.color[entity_id] = self.config.color(name).unwrap();
+            }
         }
     }
 
@@ -74,35 +379,143 @@ impl Game {
         self.settings.dt = dt;
         self.settings.time += dt;
 
+        // input is ignored while the next level is generated, other than exiting the game
+        if self.settings.state == GameState::Loading {
+            if input_action == InputAction::ForceExit {
+                self.settings.state = GameState::Exit;
+            } else {
+                self.poll_map_generation();
+            }
+            return self.settings.state != GameState::Exit;
+        }
+
+        // there may be no map to play on after an error, so only exiting is handled
+        if self.settings.state == GameState::Error {
+            actions::handle_input_error(input_action, &mut self.settings);
+            return self.settings.state != GameState::Exit;
+        }
+
+        // input ignored above is left out, so playing the run's input again plays the same run
+        if input_action != InputAction::None {
+            self.run_actions.push(input_action);
+        }
+
+        // an open prompt takes all input. Choosing an option closes the prompt and
+        // handles that option's action as if it had been entered directly.
+        let mut input_action = input_action;
+        if self.settings.state == GameState::Prompt {
+            input_action = prompt::handle_input_prompt(input_action, &mut self.settings).unwrap_or(InputAction::None);
+            self.settings.prompt_confirmed = input_action != InputAction::None;
+        }
+
         let input_handled = actions::handle_input_universal(input_action, self);
 
         if !input_handled {
-            actions::handle_input(input_action,
-                                  &self.data,
-                                  &mut self.settings,
-                                  &mut self.msg_log,
-                                  &self.config);
+            if self.settings.state == GameState::Sandbox {
+                sandbox::handle_input_sandbox(input_action, self);
+            } else if self.settings.state == GameState::NewGame {
+                loadout::handle_input_new_game(input_action, self);
+            } else if self.settings.state == GameState::Dialogue {
+                dialogue::handle_input_dialogue(input_action, self);
+            } else if self.settings.state == GameState::Container {
+                container::handle_input_container(input_action, self);
+            } else if self.settings.state == GameState::Shop {
+                shop::handle_input_shop(input_action, self);
+            } else if self.settings.state == GameState::MainMenu ||
+                      self.settings.state == GameState::Paused ||
+                      self.settings.state == GameState::Options ||
+                      self.settings.state == GameState::LevelComplete ||
+                      self.settings.state == GameState::Win ||
+                      self.settings.state == GameState::Lose {
+                menu::handle_input_menu(input_action, self);
+            } else {
+                actions::handle_input(input_action,
+                                      &self.data,
+                                      &mut self.settings,
+                                      &mut self.msg_log,
+                                      &self.config);
+            }
         }
 
+        self.settings.prompt_confirmed = false;
+
         if self.msg_log.messages.len() > 0 {
+            // snapshots are only needed if the turn can be undone
+            let snapshot =
+                if self.settings.god_mode && self.config.undo_history > 0 {
+                    Some(self.snapshot())
+                } else {
+                    None
+                };
+            let turn_count = self.settings.turn_count;
+            let first_turn_msg = self.msg_log.turn_messages.len();
+
             let finished_level = step_logic(self);
 
-            if finished_level {
-                // NOTE this is not a very general way to handle ending a level.
-                let player_id = self.data.find_by_name(EntityName::Player).unwrap();
-                let key_id = self.data.is_in_inventory(player_id, Item::Key).expect("Won level without key!");
-                self.data.entities.remove_item(player_id, key_id);
+            let player_id = self.data.find_by_name(EntityName::Player).unwrap();
+            let mut talked_to = None;
+            let mut opened = None;
+            for msg in self.msg_log.turn_messages.iter().skip(first_turn_msg) {
+                self.settings.stats.record(msg, player_id);
+                self.settings.tracker.record(msg, player_id);
 
-                self.settings.state = GameState::Playing;
+                if let Msg::Talk(_entity_id, speaker) = msg {
+                    talked_to = Some(*speaker);
+                }
+
+                if let Msg::OpenContainer(entity_id, container_id) = msg {
+                    if *entity_id == player_id {
+                        opened = Some(*container_id);
+                    }
+                }
+            }
+
+            // talking to an entity opens a conversation with them
+            if let Some(speaker) = talked_to {
+                dialogue::start_conversation(speaker, self);
+            }
+
+            // opening a container shows what is inside
+            if let Some(container_id) = opened {
+                container::open_container(container_id, self);
+            }
+
+            if !self.data.entities.status[&player_id].alive && self.settings.state == GameState::Playing {
+                let msgs = self.msg_log.turn_messages.iter().cloned().collect::<Vec<Msg>>();
+                self.settings.death_cause = death_cause(&msgs, &self.data, player_id);
+                self.end_run(false);
+            }
 
-                self.settings.level_num += 1;
+            if let Some(snapshot) = snapshot {
+                if self.settings.turn_count != turn_count {
+                    self.snapshots.push_back(snapshot);
+                    while self.snapshots.len() > self.config.undo_history {
+                        self.snapshots.pop_front();
+                    }
+                }
+            }
 
-                make_map(&self.config.map_load.clone(), self);
+            if finished_level {
+                self.finish_level();
             }
         }
 
-        /* Check for explored tiles */
-        let player_id = self.data.find_by_name(EntityName::Player).unwrap();
+        self.explore();
+
+        if let Some(ghost) = &mut self.ghost {
+            ghost.follow(self.settings.turn_count);
+        }
+
+        return self.settings.state != GameState::Exit;
+    }
+
+    /// Mark the tiles the player can see as explored.
+    pub fn explore(&mut self) {
+        let player_id = match self.data.find_by_name(EntityName::Player) {
+            Some(player_id) => player_id,
+            None => return,
+        };
+
         // TODO make this to a map function like 'explore_from_position'.
         for pos in self.data.map.get_all_pos() {
             let visible =
@@ -114,8 +527,229 @@ impl Game {
                 self.data.map[pos].explored = visible;
             }
         }
+    }
 
-        return self.settings.state != GameState::Exit;
+    /// Wrap up a finished level. The run ends if that was the last level, and otherwise
+    /// the shopkeeper is visited, if they have anything to sell, before the next level.
+    pub fn finish_level(&mut self) {
+        // NOTE this is not a very general way to handle ending a level.
+        let player_id = self.data.find_by_name(EntityName::Player).unwrap();
+        // not every objective needs the key, but it never carries over to the next level
+        if let Some(key_id) = self.data.is_in_inventory(player_id, Item::Key) {
+            self.data.entities.remove_item(player_id, key_id);
+        }
+
+        self.settings.state = GameState::Playing;
+
+        self.settings.level_num += 1;
+        self.settings.stats.levels += 1;
+
+        let level_stats = self.settings.stats.since(&self.settings.level_start_stats);
+        let fighter = self.data.entities.fighter[&player_id];
+        self.settings.level_summary = Some(LevelSummary { level: self.settings.level_num,
+                                                          turns: self.settings.turn_count - self.settings.level_start_turn,
+                                                          stats: level_stats,
+                                                          hp: fighter.hp,
+                                                          max_hp: fighter.max_hp });
+
+        // a noisy level draws more monsters to the next one
+        self.settings.last_level_noise = level_stats.noise;
+        self.settings.level_start_stats = self.settings.stats;
+        self.settings.level_start_turn = self.settings.turn_count;
+
+        let earned = self.settings.tracker.level_finished(self.data.objective);
+        self.unlock(earned);
+
+        // turns from the previous level cannot be undone
+        self.snapshots.clear();
+
+        if self.config.win_level > 0 && self.settings.level_num >= self.config.win_level {
+            self.end_run(true);
+        } else {
+            actions::change_state(&mut self.settings, GameState::LevelComplete);
+        }
+    }
+
+    /// Leave the screen between levels for the shop, if it has anything to sell, or otherwise
+    /// the next level.
+    pub fn leave_level_complete(&mut self) {
+        if shop::stock_for_level(&self.shop, self.settings.level_num).is_some() {
+            shop::open_shop(self);
+        } else {
+            self.settings.state = GameState::Playing;
+            self.start_next_level();
+        }
+    }
+
+    /// Make the map for the next level. The player, and everything they carry, is kept.
+    pub fn start_next_level(&mut self) {
+        if self.config.async_map_generation {
+            self.start_map_generation();
+        } else {
+            match make_map(&self.config.map_load.clone(), self) {
+                Ok(()) => self.save_checkpoint(),
+                Err(err) => self.show_error(err),
+            }
+        }
+    }
+
+    /// Score the run and add it to the leaderboard, saving the leaderboard if it was loaded from a file.
+    pub fn end_run(&mut self, won: bool) {
+        let turns = self.settings.turn_count;
+        let entry = ScoreEntry { seed: self.settings.seed,
+                                 score: self.settings.stats.score(turns, won),
+                                 won,
+                                 turns,
+                                 stats: self.settings.stats,
+                                 difficulty: self.settings.difficulty,
+                                 thumbnail: None };
+
+        let earned = self.settings.tracker.run_ended(&self.settings.stats, won);
+        self.unlock(earned);
+
+        self.settings.rank = self.leaderboard.add(entry.clone());
+        self.settings.last_score = Some(entry);
+
+        self.save_leaderboard();
+        self.remove_checkpoint();
+
+        // the best run on a seed is kept for racing against
+        if let (Some(dir), Some(0)) = (&self.ghost_dir, self.settings.rank) {
+            let file_name = ghost::ghost_file(dir, self.settings.seed);
+            if let Err(err) = ghost::write_ghost(&file_name, &self.run_actions) {
+                eprintln!("Ghost not saved- {}", err);
+            }
+        }
+
+        if won {
+            actions::change_state(&mut self.settings, GameState::Win);
+        } else {
+            actions::change_state(&mut self.settings, GameState::Lose);
+        }
+    }
+
+    /// Record the image saved of the end of the run that just ended, along with its leaderboard
+    /// entry, saving the leaderboard if it was loaded from a file.
+    pub fn set_thumbnail(&mut self, file_name: &str) {
+        if let Some(entry) = &mut self.settings.last_score {
+            if let Some(board_entry) = self.leaderboard.entries.iter_mut().find(|other| *other == entry) {
+                board_entry.thumbnail = Some(file_name.to_string());
+            }
+            entry.thumbnail = Some(file_name.to_string());
+
+            self.save_leaderboard();
+        }
+    }
+
+    /// Save the leaderboard, if it was loaded from a file. A leaderboard which can't be saved
+    /// is reported but doesn't stop the game.
+    fn save_leaderboard(&self) {
+        if let Some(file_name) = &self.leaderboard_file {
+            if let Err(err) = self.leaderboard.save(file_name) {
+                eprintln!("Leaderboard not saved- {}", err);
+            }
+        }
+    }
+
+    /// Unlock any achievements not already unlocked, saving them if they were loaded from a file.
+    pub fn unlock(&mut self, achievements: Vec<Achievement>) {
+        let mut any_unlocked = false;
+        for achievement in achievements {
+            if self.unlocks.unlock(achievement) {
+                println!("CONSOLE: Achievement unlocked: {}", achievement.description());
+                self.settings.new_unlocks.push(achievement);
+                any_unlocked = true;
+            }
+        }
+
+        if any_unlocked {
+            if let Some(file_name) = &self.unlocks_file {
+                if let Err(err) = self.unlocks.save(file_name) {
+                    eprintln!("Unlocks not saved- {}", err);
+                }
+            }
+        }
+    }
+
+    /// Generate the next level on a separate thread, entering the loading state
+    /// until it is ready. Generation with WFC can take several seconds.
+    pub fn start_map_generation(&mut self) {
+        let mut game = self.clone();
+        game.map_generation = None;
+        game.ghost = None;
+        game.msg_log.clear();
+
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            let result = make_map(&game.config.map_load.clone(), &mut game).map(|_| game);
+
+            // the game may have exited while this level was generated
+            let _ = sender.send(result);
+        });
+
+        self.map_generation = Some(MapGeneration { receiver: Arc::new(Mutex::new(receiver)) });
+        self.settings.state = GameState::Loading;
+        println!("CONSOLE: Generating level");
+    }
+
+    /// Check whether a level being generated is ready, and if so swap in its game data.
+    /// Returns whether a new level was loaded.
+    pub fn poll_map_generation(&mut self) -> bool {
+        let result;
+        if let Some(map_generation) = &self.map_generation {
+            result = map_generation.receiver.lock().unwrap().try_recv();
+        } else {
+            return false;
+        }
+
+        match result {
+            Ok(Ok(game)) => {
+                self.data = game.data;
+                self.rng = game.rng;
+                self.map_failures = game.map_failures;
+                self.vaults_placed = game.vaults_placed;
+                for msg in game.msg_log.messages {
+                    self.msg_log.log(msg);
+                }
+
+                self.map_generation = None;
+                self.settings.state = GameState::Playing;
+                self.save_checkpoint();
+                return true;
+            }
+
+            Err(TryRecvError::Empty) => {
+                return false;
+            }
+
+            Ok(Err(err)) => {
+                self.map_generation = None;
+                self.show_error(err);
+                return false;
+            }
+
+            Err(TryRecvError::Disconnected) => {
+                self.map_generation = None;
+                self.show_error(GameError::MapGeneration);
+                return false;
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> String {
+        return serde_yaml::to_string(&self.data).expect("Could not serialize game data!");
+    }
+
+    /// Restore the game data from before the last turn, returning whether
+    /// there was a turn to undo.
+    pub fn undo_turn(&mut self) -> bool {
+        if let Some(snapshot) = self.snapshots.pop_back() {
+            self.data = serde_yaml::from_str(&snapshot).expect("Could not deserialize game data!");
+            self.settings.turn_count = self.settings.turn_count.saturating_sub(1);
+            return true;
+        }
+
+        return false;
     }
 }
 
@@ -125,14 +759,44 @@ pub struct GameSettings {
     pub god_mode: bool,
     pub map_type: MapGenType,
     pub state: GameState,
-    pub overlay: bool,
+    pub overlays: Overlays, // overlays chosen to be drawn
+    pub overlay_held: bool, // all gameplay overlays are drawn while the overlay key is held
     pub time: f32,
     pub dt: f32,
     pub render_map: bool,
+    pub minimap: bool,
+    pub perf_overlay: bool, // frame times and draw counts are shown over the game
     pub inventory_action: InventoryAction,
     pub level_num: usize,
     pub running: bool,
     pub cursor: Option<Pos>,
+    pub palette: PaletteEntry,
+    pub return_state: GameState, // state to go back to when leaving a menu
+    pub seed_entry: Option<String>, // digits typed so far when entering a seed
+    pub volume: usize,
+    pub prompt: Option<Prompt>,
+    pub prompt_confirmed: bool, // whether the action being handled was confirmed by a prompt
+    pub conversation: Option<Conversation>,
+    pub container: Option<EntityId>, // container whose transfer menu is open
+    pub selection: Option<Selection>, // tile being chosen for an action
+    pub seed: u64,
+    pub stats: RunStats,
+    pub level_start_stats: RunStats, // the run's stats when the current level started
+    pub level_start_turn: usize,
+    pub level_summary: Option<LevelSummary>, // the level just finished, shown between levels
+    pub last_level_noise: usize, // noise the player made on the previous level
+    pub last_score: Option<ScoreEntry>, // the score of the run that just ended
+    pub rank: Option<usize>, // where that run placed on the leaderboard for its seed
+    pub death_cause: Option<String>, // what killed the player, if the run was lost
+    pub tracker: AchievementTracker,
+    pub new_unlocks: Vec<Achievement>, // achievements unlocked during this run
+    pub help_scroll: usize, // first line of the help screen shown
+    pub help_return: GameState, // state to go back to when closing the help screen
+    pub color_palette: usize, // index into Game::color_palettes
+    pub difficulty: Difficulty,
+    pub inspecting: Option<EntityId>, // entity shown in the inspector, chosen by clicking it in god mode
+    pub quick_slots: Vec<Option<QuickSlot>>, // items and skills on the hotbar
+    pub error: Option<GameError>, // shown on the error screen
 }
 
 impl GameSettings {
@@ -142,15 +806,67 @@ impl GameSettings {
             god_mode,
             map_type: MapGenType::Island,
             state: GameState::Playing,
-            overlay: false,
+            overlays: Overlays::none(),
+            overlay_held: false,
             time: 0.0,
             dt: 0.0,
             render_map: true,
+            minimap: true,
+            perf_overlay: false,
             inventory_action: InventoryAction::default(),
             level_num: 0,
             running: true,
             cursor: None,
+            palette: PaletteEntry::default(),
+            return_state: GameState::Playing,
+            seed_entry: None,
+            volume: MAX_VOLUME,
+            prompt: None,
+            prompt_confirmed: false,
+            conversation: None,
+            container: None,
+            selection: None,
+            seed: 0,
+            stats: RunStats::default(),
+            level_start_stats: RunStats::default(),
+            level_start_turn: 0,
+            level_summary: None,
+            last_level_noise: 0,
+            last_score: None,
+            rank: None,
+            death_cause: None,
+            tracker: AchievementTracker::default(),
+            new_unlocks: Vec::new(),
+            help_scroll: 0,
+            help_return: GameState::Playing,
+            color_palette: 0,
+            difficulty: Difficulty::default(),
+            inspecting: None,
+            quick_slots: vec![None; NUM_QUICK_SLOTS],
+            error: None,
         };
     }
+
+    /// The overlays to draw- the chosen ones, and all of the gameplay ones while the
+    /// overlay key is held.
+    pub fn shown_overlays(&self) -> Overlays {
+        if self.overlay_held {
+            return self.overlays.union(Overlays::gameplay());
+        }
+        return self.overlays;
+    }
+
+    /// Put an item or skill on the hotbar, in the first empty slot or in place of the
+    /// first slot when they are all taken. Assigning something already on the hotbar
+    /// takes it off again.
+    pub fn assign_quick_slot(&mut self, quick_slot: QuickSlot) {
+        if let Some(index) = self.quick_slots.iter().position(|slot| *slot == Some(quick_slot)) {
+            self.quick_slots[index] = None;
+            return;
+        }
+
+        let index = self.quick_slots.iter().position(|slot| slot.is_none()).unwrap_or(0);
+        self.quick_slots[index] = Some(quick_slot);
+    }
 }
 
@@ -30,11 +30,12 @@ pub enum MapGenType {
 
 pub fn make_player(entities: &mut Entities, config: &Config, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(0, 0, EntityType::Player, ENTITY_PLAYER as char, Color::white(), EntityName::Player, true);
+    let hp = std::cmp::max(1, config.player_health + config.player_health_bonus);
 
     entities.status[&entity_id].alive = true;
     entities.fighter.insert(entity_id,
-        Fighter { max_hp: config.player_health,
-                  hp: config.player_health,
+        Fighter { max_hp: hp,
+                  hp: hp,
                   defense: 0,
                   power: 5 });
     entities.movement.insert(entity_id,  Reach::Single(1));
@@ -44,13 +45,17 @@ pub fn make_player(entities: &mut Entities, config: &Config, msg_log: &mut MsgLo
     entities.inventory.insert(entity_id,  VecDeque::new());
     entities.stance.insert(entity_id,  Stance::Standing);
     entities.fov_radius.insert(entity_id,  config.fov_radius_player);
+    entities.faction.insert(entity_id,  Faction::Player);
 
     let skill_set = Vec::new();
     entities.skills.insert(entity_id,  skill_set);
+    entities.cooldowns.insert(entity_id,  Default::default());
 
     entities.class.insert(entity_id, EntityClass::General);
 
     entities.energy.insert(entity_id, 3);
+    entities.stamina.insert(entity_id, config.player_stamina);
+    entities.coins.insert(entity_id, 0);
 
     msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], Pos::new(0, 0), EntityName::Player, entities.direction[&entity_id]));
 
@@ -59,6 +64,7 @@ pub fn make_player(entities: &mut Entities, config: &Config, msg_log: &mut MsgLo
 
 pub fn make_column(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Column, MAP_COLUMN as char, Color::white(), EntityName::Column, true);
+    entities.pushable.insert(entity_id, true);
 
     msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Column, entities.direction[&entity_id]));
 
@@ -72,40 +78,44 @@ pub fn make_energy(entities: &mut Entities, _config: &Config, pos: Pos, msg_log:
     return entity_id;
 }
 
-pub fn make_dagger(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+pub fn make_dagger(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_DAGGER as char, Color::white(), EntityName::Dagger, false);
 
     entities.item.insert(entity_id,  Item::Dagger);
+    entities.durability.insert(entity_id, config.durability_dagger);
 
     msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Dagger, entities.direction[&entity_id]));
 
     return entity_id;
 }
 
-pub fn make_hammer(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+pub fn make_hammer(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_HAMMER as char, Color::white(), EntityName::Hammer, false);
 
     entities.item.insert(entity_id,  Item::Hammer);
+    entities.durability.insert(entity_id, config.durability_hammer);
 
     msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Hammer, entities.direction[&entity_id]));
 
     return entity_id;
 }
 
-pub fn make_sword(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+pub fn make_sword(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_SWORD as char, Color::white(), EntityName::Sword, false);
 
     entities.item.insert(entity_id,  Item::Sword);
+    entities.durability.insert(entity_id, config.durability_sword);
 
     msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Sword, entities.direction[&entity_id]));
 
     return entity_id;
 }
 
-pub fn make_shield(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+pub fn make_shield(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_SHIELD as char, Color::white(), EntityName::Shield, false);
 
     entities.item.insert(entity_id,  Item::Shield);
+    entities.durability.insert(entity_id, config.durability_shield);
 
     msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Shield, entities.direction[&entity_id]));
 
@@ -153,6 +163,7 @@ pub fn make_gol(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mu
     entities.fighter.insert(entity_id,  Fighter { max_hp: 10, hp: 10, defense: 0, power: 1, });
     entities.ai.insert(entity_id,  Ai::Basic);
     entities.behavior.insert(entity_id,  Behavior::Idle);
+    entities.faction.insert(entity_id,  Faction::Gols);
     entities.movement.insert(entity_id,  Reach::Single(GOL_MOVE_DISTANCE));
     entities.attack.insert(entity_id,  Reach::Diag(GOL_ATTACK_DISTANCE));
     entities.status[&entity_id].alive = true;
@@ -167,12 +178,80 @@ pub fn make_gol(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mu
     return entity_id;
 } 
 
+pub fn make_prisoner(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Other, ENTITY_PRISONER as char, config.color_light_orange, EntityName::Prisoner, true);
+
+    entities.fighter.insert(entity_id,  Fighter { max_hp: 5, hp: 5, defense: 0, power: 0, });
+    entities.ai.insert(entity_id,  Ai::Follow);
+    entities.behavior.insert(entity_id,  Behavior::Idle);
+    entities.movement.insert(entity_id,  Reach::Single(PRISONER_MOVE_DISTANCE));
+    entities.status[&entity_id].alive = true;
+    entities.direction.insert(entity_id,  Direction::Down);
+    entities.stance.insert(entity_id,  Stance::Standing);
+    entities.move_mode.insert(entity_id,  MoveMode::Walk);
+    entities.fov_radius.insert(entity_id,  config.fov_radius_monster);
+
+    msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Prisoner, entities.direction[&entity_id]));
+
+    return entity_id;
+}
+
+/// The second player in a co-op game. They fight like the player, but have no items or
+/// skills, and are only moved by the other player's input.
+pub fn make_partner(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Other, ENTITY_PLAYER as char, config.color_light_green, EntityName::Partner, true);
+    let hp = std::cmp::max(1, config.player_health);
+
+    entities.status[&entity_id].alive = true;
+    entities.fighter.insert(entity_id,  Fighter { max_hp: hp, hp: hp, defense: 0, power: 5 });
+    entities.movement.insert(entity_id,  Reach::Single(1));
+    entities.attack.insert(entity_id,  Reach::Single(1));
+    entities.move_mode.insert(entity_id,  MoveMode::Walk);
+    entities.direction.insert(entity_id,  Direction::Up);
+    entities.stance.insert(entity_id,  Stance::Standing);
+    entities.fov_radius.insert(entity_id,  config.fov_radius_player);
+    entities.faction.insert(entity_id,  Faction::Player);
+
+    msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Partner, entities.direction[&entity_id]));
+
+    return entity_id;
+}
+
+/// A small creature which wanders about, and scatters noisily when disturbed.
+pub fn make_critter(entities: &mut Entities, config: &Config, pos: Pos, name: EntityName, chr: u8, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Other, chr as char, config.color_light_grey, name, false);
+
+    entities.fighter.insert(entity_id,  Fighter { max_hp: 1, hp: 1, defense: 0, power: 0, });
+    entities.ai.insert(entity_id,  Ai::Critter);
+    entities.behavior.insert(entity_id,  Behavior::Idle);
+    entities.faction.insert(entity_id,  Faction::Wildlife);
+    entities.movement.insert(entity_id,  Reach::Single(CRITTER_MOVE_DISTANCE));
+    entities.status[&entity_id].alive = true;
+    entities.direction.insert(entity_id,  Direction::from_f32(rand_from_pos(pos)));
+    entities.stance.insert(entity_id,  Stance::Standing);
+    entities.move_mode.insert(entity_id,  MoveMode::Walk);
+    entities.fov_radius.insert(entity_id,  config.critter_disturb_distance);
+
+    msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, name, entities.direction[&entity_id]));
+
+    return entity_id;
+}
+
+pub fn make_rat(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    return make_critter(entities, config, pos, EntityName::Rat, ENTITY_RAT, msg_log);
+}
+
+pub fn make_bird(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    return make_critter(entities, config, pos, EntityName::Bird, ENTITY_BIRD, msg_log);
+}
+
 pub fn make_spire(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Enemy, '\u{15}', Color::white(), EntityName::Spire, true);
 
     entities.fighter.insert(entity_id,  Fighter { max_hp: 16, hp: 16, defense: 0, power: 1, });
     entities.ai.insert(entity_id,  Ai::Basic);
     entities.behavior.insert(entity_id,  Behavior::Idle);
+    entities.faction.insert(entity_id,  Faction::Gols);
     entities.movement.insert(entity_id,  Reach::Single(SPIRE_MOVE_DISTANCE));
     entities.attack.insert(entity_id,  Reach::Single(SPIRE_ATTACK_DISTANCE));
     entities.status[&entity_id].alive = true;
@@ -193,6 +272,7 @@ pub fn make_pawn(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &m
     entities.fighter.insert(entity_id,  Fighter { max_hp: 16, hp: 16, defense: 0, power: 1, });
     entities.ai.insert(entity_id,  Ai::Basic);
     entities.behavior.insert(entity_id,  Behavior::Idle);
+    entities.faction.insert(entity_id,  Faction::Elves);
     entities.movement.insert(entity_id,  Reach::Single(PAWN_MOVE_DISTANCE));
     entities.attack.insert(entity_id,  Reach::Single(PAWN_ATTACK_DISTANCE));
     entities.status[&entity_id].alive = true;
@@ -213,6 +293,7 @@ pub fn make_rook(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &m
     entities.fighter.insert(entity_id,  Fighter { max_hp: 16, hp: 16, defense: 0, power: 1, });
     entities.ai.insert(entity_id,  Ai::Basic);
     entities.behavior.insert(entity_id,  Behavior::Idle);
+    entities.faction.insert(entity_id,  Faction::Elves);
     entities.movement.insert(entity_id,  Reach::Horiz(ROOK_MOVE_DISTANCE));
     entities.attack.insert(entity_id,  Reach::Horiz(ROOK_ATTACK_DISTANCE));
     entities.status[&entity_id].alive = true;
@@ -227,12 +308,34 @@ pub fn make_rook(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &m
     return entity_id;
 }
 
+pub fn make_knight(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Enemy, '\u{A5}', Color::white(), EntityName::Knight, true);
+
+    entities.fighter.insert(entity_id,  Fighter { max_hp: 16, hp: 16, defense: 0, power: 1, });
+    entities.ai.insert(entity_id,  Ai::Basic);
+    entities.behavior.insert(entity_id,  Behavior::Idle);
+    entities.faction.insert(entity_id,  Faction::Elves);
+    entities.movement.insert(entity_id,  Reach::Single(KNIGHT_MOVE_DISTANCE));
+    entities.attack.insert(entity_id,  Reach::Knight(KNIGHT_ATTACK_DISTANCE));
+    entities.status[&entity_id].alive = true;
+    entities.direction.insert(entity_id,  Direction::from_f32(rand_from_pos(pos)));
+    entities.stance.insert(entity_id,  Stance::Standing);
+    entities.move_mode.insert(entity_id,  MoveMode::Walk);
+    entities.attack_type.insert(entity_id,  AttackType::Melee);
+    entities.fov_radius.insert(entity_id,  config.fov_radius_monster);
+
+    msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Knight, entities.direction[&entity_id]));
+
+    return entity_id;
+}
+
 pub fn make_armil(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Enemy, '\u{98}', Color::white(), EntityName::Armil, true);
 
     entities.fighter.insert(entity_id,  Fighter { max_hp: 10, hp: 10, defense: 0, power: 1, });
     entities.ai.insert(entity_id,  Ai::Basic);
     entities.behavior.insert(entity_id,  Behavior::Idle);
+    entities.faction.insert(entity_id,  Faction::Gols);
     entities.movement.insert(entity_id,  Reach::Single(ARMIL_MOVE_DISTANCE));
     entities.status[&entity_id].alive = true;
     entities.direction.insert(entity_id,  Direction::from_f32(rand_from_pos(pos)));
@@ -249,7 +352,7 @@ pub fn make_armil(entities: &mut Entities, _config: &Config, pos: Pos, msg_log:
 pub fn make_sound_trap(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let sound = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_TRAP_SOUND as char, Color::white(), EntityName::SoundTrap, false);
 
-    entities.trap.insert(sound,  Trap::Sound);
+    entities.trap.insert(sound,  Trap::new(TrapType::Sound));
     entities.armed.insert(sound,  true);
     entities.item.insert(sound,  Item::SoundTrap);
 
@@ -261,7 +364,7 @@ pub fn make_sound_trap(entities: &mut Entities, _config: &Config, pos: Pos, msg_
 pub fn make_spike_trap(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let spikes = entities.create_entity(pos.x, pos.y, EntityType::Item, MAP_TALL_SPIKES as char, Color::white(), EntityName::SpikeTrap, false);
 
-    entities.trap.insert(spikes,  Trap::Spikes);
+    entities.trap.insert(spikes,  Trap::new(TrapType::Spikes));
     entities.armed.insert(spikes,  true);
     entities.item.insert(spikes,  Item::SpikeTrap);
 
@@ -273,7 +376,7 @@ pub fn make_spike_trap(entities: &mut Entities, _config: &Config, pos: Pos, msg_
 pub fn make_blink_trap(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let blink = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_BLINK_TRAP as char, Color::white(), EntityName::BlinkTrap, false);
 
-    entities.trap.insert(blink,  Trap::Blink);
+    entities.trap.insert(blink,  Trap::new(TrapType::Blink));
     entities.armed.insert(blink,  true);
     entities.item.insert(blink,  Item::BlinkTrap);
 
@@ -285,7 +388,7 @@ pub fn make_blink_trap(entities: &mut Entities, _config: &Config, pos: Pos, msg_
 pub fn make_freeze_trap(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let freeze = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_FREEZE_TRAP as char, Color::white(), EntityName::FreezeTrap, false);
 
-    entities.trap.insert(freeze,  Trap::Freeze);
+    entities.trap.insert(freeze,  Trap::new(TrapType::Freeze));
     entities.armed.insert(freeze,  true);
     entities.item.insert(freeze,  Item::FreezeTrap);
 
@@ -294,6 +397,54 @@ pub fn make_freeze_trap(entities: &mut Entities, _config: &Config, pos: Pos, msg
     return freeze;
 }
 
+/// A hidden tripwire which sounds an alarm, and is reset a few turns later.
+pub fn make_tripwire(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let tripwire = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_TRIPWIRE as char, Color::white(), EntityName::Tripwire, false);
+
+    let mut trap = Trap::new(TrapType::Tripwire);
+    trap.visible = false;
+    trap.rearm_turns = TRIPWIRE_REARM_TURNS;
+    entities.trap.insert(tripwire,  trap);
+    entities.armed.insert(tripwire,  true);
+    entities.item.insert(tripwire,  Item::Tripwire);
+
+    msg_log.log(Msg::SpawnedObject(tripwire, entities.typ[&tripwire], pos, EntityName::Tripwire, entities.direction[&tripwire]));
+
+    return tripwire;
+}
+
+/// A hidden net which holds whoever steps on it in place for a few turns.
+pub fn make_net_trap(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let net = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_NET_TRAP as char, Color::white(), EntityName::NetTrap, false);
+
+    let mut trap = Trap::new(TrapType::Net);
+    trap.visible = false;
+    entities.trap.insert(net,  trap);
+    entities.armed.insert(net,  true);
+    entities.item.insert(net,  Item::NetTrap);
+
+    msg_log.log(Msg::SpawnedObject(net, entities.typ[&net], pos, EntityName::NetTrap, entities.direction[&net]));
+
+    return net;
+}
+
+/// A swinging blade which hits anything stepping on or next to it. The blade
+/// swings back into place a few turns after it is triggered.
+pub fn make_blade_trap(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let blade = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_BLADE_TRAP as char, Color::white(), EntityName::BladeTrap, false);
+
+    let mut trap = Trap::new(TrapType::Blade);
+    trap.trigger = TrapTrigger::Adjacent;
+    trap.rearm_turns = BLADE_TRAP_REARM_TURNS;
+    entities.trap.insert(blade,  trap);
+    entities.armed.insert(blade,  true);
+    entities.item.insert(blade,  Item::BladeTrap);
+
+    msg_log.log(Msg::SpawnedObject(blade, entities.typ[&blade], pos, EntityName::BladeTrap, entities.direction[&blade]));
+
+    return blade;
+}
+
 pub fn make_gate_trigger(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let gate = entities.create_entity(pos.x, pos.y, EntityType::Trigger, ENTITY_GATE_TRIGGER as char, Color::white(), EntityName::GateTrigger, false);
 
@@ -304,6 +455,91 @@ pub fn make_gate_trigger(entities: &mut Entities, _config: &Config, pos: Pos, ms
     return gate;
 }
 
+/// A plate which activates its linked mechanisms when stepped on, and again when stepped off.
+pub fn make_pressure_plate(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let plate = entities.create_entity(pos.x, pos.y, EntityType::Trigger, ENTITY_PRESSURE_PLATE as char, Color::white(), EntityName::PressurePlate, false);
+
+    msg_log.log(Msg::SpawnedObject(plate, entities.typ[&plate], pos, EntityName::PressurePlate, entities.direction[&plate]));
+
+    return plate;
+}
+
+/// A lever which activates its linked mechanisms each time it is pulled.
+pub fn make_lever(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let lever = entities.create_entity(pos.x, pos.y, EntityType::Trigger, ENTITY_LEVER_UP as char, Color::white(), EntityName::Lever, false);
+
+    msg_log.log(Msg::SpawnedObject(lever, entities.typ[&lever], pos, EntityName::Lever, entities.direction[&lever]));
+
+    return lever;
+}
+
+/// A gate which blocks movement, but not sight, until a linked mechanism raises it.
+pub fn make_gate(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let gate = entities.create_entity(pos.x, pos.y, EntityType::Other, ENTITY_GATE_CLOSED as char, Color::white(), EntityName::Gate, true);
+
+    msg_log.log(Msg::SpawnedObject(gate, entities.typ[&gate], pos, EntityName::Gate, entities.direction[&gate]));
+
+    return gate;
+}
+
+/// A chest holds items, which can be taken out by interacting with it.
+pub fn make_chest(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let chest = entities.create_entity(pos.x, pos.y, EntityType::Other, ENTITY_CHEST as char, Color::white(), EntityName::Chest, true);
+
+    entities.inventory.insert(chest, VecDeque::new());
+
+    msg_log.log(Msg::SpawnedObject(chest, entities.typ[&chest], pos, EntityName::Chest, entities.direction[&chest]));
+
+    return chest;
+}
+
+/// A crate holds items like a chest, but is locked. It can be smashed open,
+/// or its lock picked over a few turns while sneaking.
+pub fn make_crate(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let crate_id = entities.create_entity(pos.x, pos.y, EntityType::Other, ENTITY_CRATE as char, Color::white(), EntityName::Crate, true);
+
+    entities.inventory.insert(crate_id, VecDeque::new());
+    entities.locked.insert(crate_id, config.lockpick_turns);
+
+    msg_log.log(Msg::SpawnedObject(crate_id, entities.typ[&crate_id], pos, EntityName::Crate, entities.direction[&crate_id]));
+
+    return crate_id;
+}
+
+/// An alarm bell. An enemy who spots the player may run to ring it, calling a
+/// wave of reinforcements onto the level. It can only be rung once.
+pub fn make_alarm(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let alarm = entities.create_entity(pos.x, pos.y, EntityType::Other, ENTITY_ALARM as char, Color::white(), EntityName::Alarm, true);
+
+    entities.armed.insert(alarm, true);
+
+    msg_log.log(Msg::SpawnedObject(alarm, entities.typ[&alarm], pos, EntityName::Alarm, entities.direction[&alarm]));
+
+    return alarm;
+}
+
+/// Fill a container with items chosen from a loot table of items and their weights.
+pub fn stock_container(container_id: EntityId, loot: &Vec<(Item, usize)>, num_items: usize, game: &mut Game) {
+    let total_weight: usize = loot.iter().map(|(_item, weight)| *weight).sum();
+    if total_weight == 0 {
+        return;
+    }
+
+    let pos = game.data.entities.pos[&container_id];
+    for _ in 0..num_items {
+        let mut roll = rng_range_u32(&mut game.rng, 0, total_weight as u32) as usize;
+
+        for (item, weight) in loot.iter() {
+            if roll < *weight {
+                let item_id = make_item(&mut game.data.entities, &game.config, *item, pos, &mut game.msg_log);
+                game.data.entities.store_item(container_id, item_id);
+                break;
+            }
+            roll -= *weight;
+        }
+    }
+}
+
 pub fn make_exit(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let exit = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_EXIT as char, Color::white(), EntityName::Exit, false);
 
@@ -324,6 +560,19 @@ pub fn make_stone(entities: &mut Entities, _config: &Config, pos: Pos, msg_log:
     return stone;
 }
 
+pub fn make_coins(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let coins = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_COINS as char, config.color_orange, EntityName::Coins, true);
+
+    entities.item.insert(coins,  Item::Coins);
+    entities.coins.insert(coins,  config.coins_per_pile);
+    entities.status[&coins].alive = false;
+    entities.blocks.insert(coins,  false);
+
+    msg_log.log(Msg::SpawnedObject(coins, entities.typ[&coins], pos, EntityName::Coins, entities.direction[&coins]));
+
+    return coins;
+}
+
 pub fn make_lantern(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let lantern = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_LANTERN as char, Color::white(), EntityName::Lantern, true);
 
@@ -337,6 +586,52 @@ pub fn make_lantern(entities: &mut Entities, _config: &Config, pos: Pos, msg_log
     return lantern;
 }
 
+/// A bomb which bursts into a cloud of smoke where it lands, hiding anything in the cloud.
+pub fn make_smoke_bomb(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let bomb = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_SMOKE_BOMB as char, config.color_light_grey, EntityName::SmokeBomb, false);
+
+    entities.item.insert(bomb,  Item::SmokeBomb);
+
+    msg_log.log(Msg::SpawnedObject(bomb, entities.typ[&bomb], pos, EntityName::SmokeBomb, entities.direction[&bomb]));
+
+    return bomb;
+}
+
+/// A bomb which bursts into a cloud of poison where it lands, hurting anything in the cloud.
+pub fn make_poison_bomb(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let bomb = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_POISON_BOMB as char, config.color_mint_green, EntityName::PoisonBomb, false);
+
+    entities.item.insert(bomb,  Item::PoisonBomb);
+
+    msg_log.log(Msg::SpawnedObject(bomb, entities.typ[&bomb], pos, EntityName::PoisonBomb, entities.direction[&bomb]));
+
+    return bomb;
+}
+
+/// A noisemaker rattles a short while after it is thrown, drawing monsters to it.
+/// It can be picked up and thrown again.
+pub fn make_noisemaker(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let noisemaker = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_NOISEMAKER as char, config.color_light_orange, EntityName::Noisemaker, false);
+
+    entities.item.insert(noisemaker,  Item::Noisemaker);
+
+    msg_log.log(Msg::SpawnedObject(noisemaker, entities.typ[&noisemaker], pos, EntityName::Noisemaker, entities.direction[&noisemaker]));
+
+    return noisemaker;
+}
+
+/// A grappling hook catches on a wall or column a few tiles away and pulls its user across to it,
+/// even over water.
+pub fn make_grapple(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let grapple = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_GRAPPLE as char, config.color_light_grey, EntityName::Grapple, false);
+
+    entities.item.insert(grapple,  Item::Grapple);
+
+    msg_log.log(Msg::SpawnedObject(grapple, entities.typ[&grapple], pos, EntityName::Grapple, entities.direction[&grapple]));
+
+    return grapple;
+}
+
 pub fn make_item(entities: &mut Entities, config: &Config, item: Item, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     return make_entity(entities, config, item.name(), pos, msg_log);
 }
@@ -355,13 +650,34 @@ pub fn make_entity(entities: &mut Entities, config: &Config, entity_name: Entity
         EntityName::Gol => make_gol(entities, config, pos, msg_log),
         EntityName::Spire => make_spire(entities, config, pos, msg_log),
         EntityName::Pawn => make_pawn(entities, config, pos, msg_log),
+        EntityName::Knight => make_knight(entities, config, pos, msg_log),
+        EntityName::Rook => make_rook(entities, config, pos, msg_log),
+        EntityName::Armil => make_armil(entities, config, pos, msg_log),
+        EntityName::Prisoner => make_prisoner(entities, config, pos, msg_log),
+        EntityName::Partner => make_partner(entities, config, pos, msg_log),
+        EntityName::Rat => make_rat(entities, config, pos, msg_log),
+        EntityName::Bird => make_bird(entities, config, pos, msg_log),
         EntityName::SoundTrap => make_sound_trap(entities, config, pos, msg_log),
         EntityName::SpikeTrap => make_spike_trap(entities, config, pos, msg_log),
         EntityName::FreezeTrap => make_freeze_trap(entities, config, pos, msg_log),
         EntityName::BlinkTrap => make_blink_trap(entities, config, pos, msg_log),
+        EntityName::Tripwire => make_tripwire(entities, config, pos, msg_log),
+        EntityName::NetTrap => make_net_trap(entities, config, pos, msg_log),
+        EntityName::BladeTrap => make_blade_trap(entities, config, pos, msg_log),
         EntityName::GateTrigger => make_gate_trigger(entities, config, pos, msg_log),
+        EntityName::PressurePlate => make_pressure_plate(entities, config, pos, msg_log),
+        EntityName::Lever => make_lever(entities, config, pos, msg_log),
+        EntityName::Gate => make_gate(entities, config, pos, msg_log),
+        EntityName::Chest => make_chest(entities, config, pos, msg_log),
+        EntityName::Crate => make_crate(entities, config, pos, msg_log),
+        EntityName::Alarm => make_alarm(entities, config, pos, msg_log),
         EntityName::Exit => make_exit(entities, config, pos, msg_log),
         EntityName::Stone => make_stone(entities, config, pos, msg_log),
+        EntityName::SmokeBomb => make_smoke_bomb(entities, config, pos, msg_log),
+        EntityName::PoisonBomb => make_poison_bomb(entities, config, pos, msg_log),
+        EntityName::Noisemaker => make_noisemaker(entities, config, pos, msg_log),
+        EntityName::Grapple => make_grapple(entities, config, pos, msg_log),
+        EntityName::Coins => make_coins(entities, config, pos, msg_log),
         _ => {
             panic!(format!("Cannot create {:?} this way", entity_name));
         }
@@ -509,7 +825,7 @@ pub fn make_island(data: &mut GameData,
     let edge_pos = edge_positions[rng_range_u32(rng, 0, edge_positions.len() as u32) as usize];
 
     // make the random edge position the exit
-    data.map.tiles[edge_pos.x as usize][edge_pos.y as usize] = Tile::exit();
+    data.map[edge_pos] = Tile::exit();
 
     /* Ensure that objects placed outside of the island are removed */
     for pos in water_tile_positions {
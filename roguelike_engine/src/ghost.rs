@@ -0,0 +1,93 @@
+use std::fs;
+use std::str::FromStr;
+use std::collections::VecDeque;
+
+use roguelike_core::types::*;
+
+use crate::game::Game;
+use crate::actions::InputAction;
+use crate::error::{GameError, GameResult};
+
+
+/// The time given to each of the ghost's steps. Nothing it does is drawn, so its
+/// animations only need to move along.
+const GHOST_STEP_DT: f32 = 0.1;
+
+/// The best earlier run on a seed, played again beside the player from its recorded
+/// input so they can race it.
+#[derive(Clone, Debug)]
+pub struct Ghost {
+    pub game: Game,
+    pub actions: VecDeque<InputAction>,
+}
+
+impl Ghost {
+    /// Start a ghost from a run as it was when it began, before any input was given.
+    pub fn new(game: &Game, actions: Vec<InputAction>) -> Ghost {
+        let mut ghost_game = game.clone();
+
+        // the ghost only plays along, so it saves nothing and makes its levels straight away
+        ghost_game.ghost = None;
+        ghost_game.leaderboard_file = None;
+        ghost_game.unlocks_file = None;
+        ghost_game.checkpoint_file = None;
+        ghost_game.ghost_dir = None;
+        ghost_game.config.async_map_generation = false;
+        ghost_game.config.ghost_replay = false;
+
+        return Ghost { game: ghost_game, actions: actions.into_iter().collect() };
+    }
+
+    /// Play the recorded input until the ghost has taken as many turns as the player,
+    /// or has no input left.
+    pub fn follow(&mut self, turn_count: usize) {
+        while self.game.settings.turn_count < turn_count && !self.finished() {
+            let input_action = self.actions.pop_front().unwrap();
+            self.game.step_game(input_action, GHOST_STEP_DT);
+            self.game.msg_log.clear();
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        return self.actions.len() == 0 ||
+               self.game.settings.state == GameState::Win ||
+               self.game.settings.state == GameState::Lose ||
+               self.game.settings.state == GameState::Exit;
+    }
+
+    /// Where the ghost is, if it is on the given level. It is only shown on the same
+    /// level as the player, since any other level's map is different.
+    pub fn player_pos(&self, level_num: usize) -> Option<Pos> {
+        if self.game.settings.level_num != level_num {
+            return None;
+        }
+
+        let player_id = self.game.data.find_by_name(EntityName::Player)?;
+        if !self.game.data.entities.status[&player_id].alive {
+            return None;
+        }
+
+        return Some(self.game.data.entities.pos[&player_id]);
+    }
+}
+
+/// The file the best run on a seed is kept in.
+pub fn ghost_file(dir: &str, seed: u64) -> String {
+    return format!("{}/run_{}.txt", dir, seed);
+}
+
+/// Read a run's input, written as in the action log with one action to a line.
+/// Lines which can't be read are skipped.
+pub fn read_ghost(file_name: &str) -> GameResult<Vec<InputAction>> {
+    let contents = fs::read_to_string(file_name).map_err(|err| GameError::read(file_name, err))?;
+    return Ok(contents.lines().filter_map(|line| InputAction::from_str(line).ok()).collect());
+}
+
+pub fn write_ghost(file_name: &str, actions: &Vec<InputAction>) -> GameResult<()> {
+    if let Some(dir) = std::path::Path::new(file_name).parent() {
+        fs::create_dir_all(dir).map_err(|err| GameError::write(file_name, err))?;
+    }
+
+    let lines = actions.iter().map(|action| format!("{}\n", action)).collect::<String>();
+    return fs::write(file_name, lines).map_err(|err| GameError::write(file_name, err));
+}
@@ -0,0 +1,89 @@
+use roguelike_core::config::Config;
+
+use crate::actions::InputAction;
+use crate::input::KEY_BINDINGS;
+
+
+/// Keys which are not in the key binding table, as they depend on what is held
+/// or which mode the player is in.
+const FIXED_KEYS: &[(&str, &str)] = &[
+    ("1-9", "move, 5 to pass"),
+    ("space", "cursor mode"),
+    ("z/x/c", "use item"),
+    ("a/s/d", "use skill"),
+    ("f+1-4", "use hotbar slot, or add to hotbar in a menu"),
+    ("alt", "interact, with a direction"),
+    ("esc", "pause, or leave a menu"),
+    ("F1", "help"),
+    ("F3", "performance overlay"),
+    ("F11", "save the last few seconds as a GIF"),
+    ("F12", "screenshot"),
+];
+
+/// A short description of what an action does, for the help screen.
+pub fn describe_action(action: InputAction) -> &'static str {
+    match action {
+        InputAction::Exit => "quit",
+        InputAction::Pickup => "pick up",
+        InputAction::SwapPrimaryItem => "swap held items",
+        InputAction::DropItem => "drop item",
+        InputAction::Inventory => "inventory",
+        InputAction::Yell => "yell",
+        InputAction::ExploreAll => "explore map (debug)",
+        InputAction::GodMode => "god mode (debug)",
+        InputAction::Undo => "undo, in god mode",
+        InputAction::Sandbox => "sandbox",
+        InputAction::SaveMap => "save map, in sandbox",
+        InputAction::RegenerateMap => "new level (debug)",
+        InputAction::IncreaseMoveMode => "move faster",
+        InputAction::DecreaseMoveMode => "move slower",
+        InputAction::OverlayOff => "overlay, while held",
+        InputAction::CycleOverlay => "cycle overlays",
+        InputAction::SkillMenu => "skills",
+        InputAction::ClassMenu => "class",
+        InputAction::Minimap => "minimap",
+        InputAction::SkipAnimations => "skip animations",
+        InputAction::Help => "help",
+        _ => "",
+    }
+}
+
+/// The key bindings, one per line, generated from the key binding table.
+pub fn key_binding_lines() -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for (key, description) in FIXED_KEYS.iter() {
+        lines.push(format!("{:<6} {}", key, description));
+    }
+
+    for (key, action) in KEY_BINDINGS.iter() {
+        lines.push(format!("{:<6} {}", key, describe_action(*action)));
+    }
+
+    return lines;
+}
+
+/// Every line of the help screen- the key bindings followed by a short explanation
+/// of the mechanics that are not obvious from playing. Lines are wrapped when drawn.
+pub fn help_lines(config: &Config) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    lines.push("Keys".to_string());
+    lines.extend(key_binding_lines());
+    lines.push("".to_string());
+
+    lines.push("Move modes".to_string());
+    lines.push("'e' and 'w' switch between sneaking, walking, and running. Sneaking is quiet, and running moves two tiles at a time.".to_string());
+    lines.push("".to_string());
+
+    lines.push("Sound".to_string());
+    lines.push(format!("Moving makes a sound golems can hear: radius {} sneaking, {} walking, and {} running. Grass dampens sound and rubble makes it louder.",
+                       config.sound_radius_sneak, config.sound_radius_walk, config.sound_radius_run));
+    lines.push("".to_string());
+
+    lines.push("Stamina".to_string());
+    lines.push(format!("Running costs {} stamina a move, vaulting a short wall {}, and a wall kick {}. Sneaking or passing a turn recovers it.",
+                       config.stamina_run_cost, config.stamina_vault_cost, config.stamina_wall_kick_cost));
+
+    return lines;
+}
@@ -0,0 +1,137 @@
+use roguelike_core::types::*;
+use roguelike_core::map::DoorState;
+use roguelike_core::movement::{Direction, MoveMode};
+use roguelike_core::constants::MAX_HINTS;
+use roguelike_core::utils::can_knock_out;
+
+use crate::actions::InputAction;
+use crate::input::{ITEM_KEYS, key_for_action, digit_from_direction};
+
+
+/// A rule looks at what is on and around the player's tile, and gives a hint for
+/// each action that rule knows can be taken there.
+type HintRule = fn(&GameData, EntityId) -> Vec<String>;
+
+/// The rules checked for hints, in the order their hints are listed.
+const HINT_RULES: &[HintRule] = &[pick_up_hints, interact_hints, hammer_hints];
+
+/// Hints for the actions the player can take from where they are standing,
+/// such as "g: pick up dagger" or "alt 6: open door".
+pub fn action_hints(data: &GameData) -> Vec<String> {
+    let mut hints = Vec::new();
+
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    if !data.entities.status[&player_id].alive {
+        return hints;
+    }
+
+    for rule in HINT_RULES.iter() {
+        hints.extend(rule(data, player_id));
+    }
+    hints.truncate(MAX_HINTS);
+
+    return hints;
+}
+
+fn pick_up_hints(data: &GameData, player_id: EntityId) -> Vec<String> {
+    let mut hints = Vec::new();
+
+    let player_pos = data.entities.pos[&player_id];
+    if let (Some(item_id), Some(key)) = (data.item_at_pos(player_pos), key_for_action(InputAction::Pickup)) {
+        hints.push(format!("{}: pick up {}", key, data.entities.name[&item_id]));
+    }
+
+    return hints;
+}
+
+/// Interacting checks the same things, in the same order, as resolving the interaction does.
+fn interact_hints(data: &GameData, player_id: EntityId) -> Vec<String> {
+    let mut hints = Vec::new();
+
+    let player_pos = data.entities.pos[&player_id];
+    for direction in Direction::move_actions() {
+        let pos = direction.offset_pos(player_pos, 1);
+        if !data.map.is_within_bounds(pos) {
+            continue;
+        }
+
+        let mut hint = None;
+        if let Some(state) = data.map[pos].door_state() {
+            if state == DoorState::Open {
+                hint = Some("close door".to_string());
+            } else {
+                hint = Some("open door".to_string());
+            }
+        } else if let Some(blocking_id) = data.has_blocking_entity(pos) {
+            if data.entities.pushable.get(&blocking_id) == Some(&true) {
+                hint = Some(format!("shove {}", data.entities.name[&blocking_id]));
+            }
+        }
+
+        if hint.is_none() {
+            for other_id in data.has_entities(pos) {
+                if can_knock_out(data, player_id, other_id) {
+                    hint = Some(format!("knock out {}", data.entities.name[&other_id]));
+                } else if data.entities.trap.get(&other_id).is_some() {
+                    if data.entities.armed.get(&other_id) == Some(&true) {
+                        hint = Some("disarm trap".to_string());
+                    } else {
+                        hint = Some("arm trap".to_string());
+                    }
+                } else if data.entities.name[&other_id] == EntityName::Prisoner &&
+                          data.entities.status[&other_id].alive {
+                    hint = Some("talk".to_string());
+                } else if data.entities.name[&other_id] == EntityName::Lever {
+                    hint = Some("pull lever".to_string());
+                } else if data.entities.is_container(other_id) {
+                    let name = data.entities.name[&other_id];
+                    if data.entities.locked.get(&other_id).map_or(false, |turns| *turns > 0) {
+                        if data.entities.move_mode.get(&player_id) == Some(&MoveMode::Sneak) {
+                            hint = Some(format!("pick {} lock", name));
+                        } else {
+                            hint = Some(format!("smash {}", name));
+                        }
+                    } else {
+                        hint = Some(format!("open {}", name));
+                    }
+                }
+
+                if hint.is_some() {
+                    break;
+                }
+            }
+        }
+
+        if let Some(hint) = hint {
+            hints.push(format!("alt {}: {}", digit_from_direction(direction), hint));
+        }
+    }
+
+    return hints;
+}
+
+/// A hammer can knock down any wall next to the player. All the directions
+/// with a wall are listed in one hint, rather than a hint for each wall.
+fn hammer_hints(data: &GameData, player_id: EntityId) -> Vec<String> {
+    let mut hints = Vec::new();
+
+    let hammer_index = data.entities.inventory[&player_id].iter().position(|item_id| {
+        data.entities.item.get(item_id) == Some(&Item::Hammer)
+    });
+
+    if let Some(key) = hammer_index.and_then(|index| ITEM_KEYS.get(index)) {
+        let player_pos = data.entities.pos[&player_id];
+
+        let digits = Direction::move_actions().iter()
+                                              .map(|direction| (*direction, direction.offset_pos(player_pos, 1)))
+                                              .filter(|(_direction, pos)| data.map.is_within_bounds(*pos))
+                                              .filter(|(_direction, pos)| data.map.path_blocked_move(player_pos, *pos).is_some())
+                                              .map(|(direction, _pos)| digit_from_direction(direction).to_string())
+                                              .collect::<Vec<String>>();
+        if digits.len() > 0 {
+            hints.push(format!("{} {}: hammer wall", key, digits.join("/")));
+        }
+    }
+
+    return hints;
+}
@@ -9,13 +9,44 @@ use roguelike_core::types::*;
 use roguelike_core::movement::Direction;
 use roguelike_core::config::Config;
 use roguelike_core::movement::MoveMode;
+use roguelike_core::constants::NUM_QUICK_SLOTS;
 
 use crate::game::*;
 use crate::actions::*;
 
 
-const SKILL_KEYS: &[char] = &['a', 's', 'd'];
-const ITEM_KEYS: &[char] = &['z', 'x', 'c'];
+pub const SKILL_KEYS: &[char] = &['a', 's', 'd'];
+pub const ITEM_KEYS: &[char] = &['z', 'x', 'c'];
+
+/// Held while pressing a number key to use a hotbar slot instead of moving, or to put
+/// a menu entry on the hotbar.
+pub const QUICK_SLOT_KEY: char = 'f';
+
+/// The actions taken when a key is released, other than movement, skills, and items.
+/// The help screen is generated from this table, so it is the one place to change a binding.
+pub const KEY_BINDINGS: &[(char, InputAction)] = &[
+    ('q', InputAction::Exit),
+    ('g', InputAction::Pickup),
+    ('r', InputAction::SwapPrimaryItem),
+    (',', InputAction::DropItem),
+    ('i', InputAction::Inventory),
+    ('y', InputAction::Yell),
+    ('l', InputAction::ExploreAll),
+    ('t', InputAction::GodMode),
+    ('u', InputAction::Undo),
+    ('b', InputAction::Sandbox),
+    ('m', InputAction::SaveMap),
+    ('p', InputAction::RegenerateMap),
+    ('e', InputAction::IncreaseMoveMode),
+    ('w', InputAction::DecreaseMoveMode),
+    ('o', InputAction::OverlayOff),
+    ('v', InputAction::CycleOverlay),
+    ('j', InputAction::SkillMenu),
+    ('h', InputAction::ClassMenu),
+    ('n', InputAction::Minimap),
+    ('k', InputAction::SkipAnimations),
+    ('?', InputAction::Help),
+];
 
 
 #[derive(Clone, Debug, Copy, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
@@ -113,6 +144,9 @@ pub enum InputEvent {
     MouseButton(MouseClick, Pos, Option<Pos>, KeyDir), // button clicked, mouse position, screen square, keydir
     Esc,
     Tab,
+    Screenshot,
+    SaveClip,
+    PerfOverlay,
     Quit,
 }
 
@@ -199,9 +233,25 @@ impl Input {
                 action = InputAction::CursorReturn;
             }
 
+            InputEvent::Screenshot => {
+                action = InputAction::Screenshot;
+            }
+
+            InputEvent::SaveClip => {
+                action = InputAction::SaveClip;
+            }
+
+            InputEvent::PerfOverlay => {
+                action = InputAction::PerfOverlay;
+            }
+
             InputEvent::Enter(dir) => {
                 if dir == KeyDir::Up {
-                    action = InputAction::MoveTowardsCursor(self.move_mode());
+                    if settings.state.is_menu() {
+                        action = InputAction::Confirm;
+                    } else {
+                        action = InputAction::MoveTowardsCursor(self.move_mode());
+                    }
                 }
             }
 
@@ -271,7 +321,11 @@ impl Input {
 
         if settings.state.is_menu() {
             if chr.is_ascii_digit() {
-                return InputAction::SelectItem(chr.to_digit(10).unwrap() as usize);
+                let index = chr.to_digit(10).unwrap() as usize;
+                if self.char_down_order.contains(&QUICK_SLOT_KEY) {
+                    return InputAction::AssignQuickSlot(index);
+                }
+                return InputAction::SelectItem(index);
             }
         }
 
@@ -306,6 +360,17 @@ impl Input {
     fn apply_char(&mut self, chr: char, settings: &GameSettings) -> InputAction {
         let mut action: InputAction = InputAction::None;
 
+        // with the quick slot key held, the number keys use the hotbar instead of moving
+        if self.char_down_order.contains(&QUICK_SLOT_KEY) {
+            if let Some(digit) = chr.to_digit(10) {
+                let digit = digit as usize;
+                if digit >= 1 && digit <= NUM_QUICK_SLOTS {
+                    return InputAction::QuickSlot(digit - 1);
+                }
+                return action;
+            }
+        }
+
         // check if the key being released is the one that set the input direction.
         if let Some(input_dir) = InputDirection::from_chr(chr) {
             if self.direction == Some(input_dir) {
@@ -453,11 +518,13 @@ impl Input {
                     action = self.apply_char(chr, settings);
 
                     if action == InputAction::OverlayOff   ||
+                       action == InputAction::CycleOverlay ||
                        action == InputAction::Inventory    ||
                        action == InputAction::SkillMenu    ||
                        action == InputAction::Exit         ||
                        action == InputAction::CursorToggle ||
-                       action == InputAction::ClassMenu {
+                       action == InputAction::ClassMenu    ||
+                       matches!(action, InputAction::QuickSlot(_)) {
                         action = InputAction::None;
                     } else {
                         self.char_held.insert(chr, held_state.repeated());
@@ -487,67 +554,30 @@ impl Input {
 }
 
 pub fn alpha_up_to_action(chr: char) -> InputAction {
-    let input_action: InputAction;
-
-    match chr {
-        'q' => {
-            input_action = InputAction::Exit;
-        }
-
-        'g' => {
-            input_action = InputAction::Pickup;
-        }
-
-        'd' => {
-            input_action = InputAction::DropItem;
-        }
-
-        'i' => {
-            input_action = InputAction::Inventory;
-        }
-
-        'y' => {
-            input_action = InputAction::Yell;
-        }
-
-        'l' => {
-            input_action = InputAction::ExploreAll;
-        }
-
-        't' => {
-            input_action = InputAction::GodMode;
-        }
-
-        'p' => {
-            input_action = InputAction::RegenerateMap;
-        }
-
-        'e' => {
-            input_action = InputAction::IncreaseMoveMode;
-        }
-
-        'w' => {
-            input_action = InputAction::DecreaseMoveMode;
-        }
-
-        'o' => {
-            input_action = InputAction::OverlayOff;
-        }
+    if let Some((_key, action)) = KEY_BINDINGS.iter().find(|(key, _action)| *key == chr) {
+        return *action;
+    }
 
-        'j' => {
-            input_action = InputAction::SkillMenu;
-        }
+    return InputAction::None;
+}
 
-        'h' => {
-            input_action = InputAction::ClassMenu;
-        }
+/// The key an action is bound to in the key binding table, if any.
+pub fn key_for_action(action: InputAction) -> Option<char> {
+    return KEY_BINDINGS.iter().find(|(_key, other)| *other == action).map(|(key, _action)| *key);
+}
 
-        _ => {
-            input_action = InputAction::None;
-        }
+/// The number key which moves in a direction.
+pub fn digit_from_direction(direction: Direction) -> char {
+    match direction {
+        Direction::Left => '4',
+        Direction::Right => '6',
+        Direction::Up => '8',
+        Direction::Down => '2',
+        Direction::DownLeft => '1',
+        Direction::DownRight => '3',
+        Direction::UpLeft => '7',
+        Direction::UpRight => '9',
     }
-
-    return input_action;
 }
 
 fn direction_from_digit(chr: char) -> Option<Direction> {
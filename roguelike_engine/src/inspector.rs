@@ -0,0 +1,95 @@
+use roguelike_core::types::*;
+
+use crate::game::GameSettings;
+
+
+/// Choose the entity shown in the inspector by clicking on its tile. The first entity
+/// on the tile is chosen, and clicking an empty tile closes the inspector.
+pub fn inspect_at(pos: Pos, data: &GameData, settings: &mut GameSettings) {
+    settings.inspecting = data.get_entities_at_pos(pos).first().map(|entity_id| *entity_id);
+}
+
+/// A line for each component an entity has, for looking inside it while debugging.
+/// Components the entity doesn't have are left out.
+pub fn inspect_entity(entity_id: EntityId, data: &GameData) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    if !data.entities.ids.contains(&entity_id) {
+        lines.push(format!("entity {} removed", entity_id));
+        return lines;
+    }
+
+    let entities = &data.entities;
+
+    lines.push(format!("{} ({})", entities.name[&entity_id], entity_id));
+    lines.push(format!("type {:?}", entities.typ[&entity_id]));
+    lines.push(format!("pos ({}, {})", entities.pos[&entity_id].x, entities.pos[&entity_id].y));
+
+    if let Some(fighter) = entities.fighter.get(&entity_id) {
+        lines.push(format!("hp {}/{}", fighter.hp, fighter.max_hp));
+        lines.push(format!("power {} defense {}", fighter.power, fighter.defense));
+    }
+
+    if let Some(ai) = entities.ai.get(&entity_id) {
+        lines.push(format!("ai {:?}", ai));
+    }
+
+    if let Some(behavior) = entities.behavior.get(&entity_id) {
+        lines.push(format!("{}", behavior));
+    }
+
+    if let Some(interest) = entities.interest.get(&entity_id) {
+        lines.push(format!("interest {}", interest));
+    }
+
+    if let Some(faction) = entities.faction.get(&entity_id) {
+        lines.push(format!("faction {}", faction));
+    }
+
+    if let Some(direction) = entities.direction.get(&entity_id) {
+        lines.push(format!("facing {}", direction));
+    }
+
+    if let Some(move_mode) = entities.move_mode.get(&entity_id) {
+        lines.push(format!("move mode {}", move_mode));
+    }
+
+    if let Some(stance) = entities.stance.get(&entity_id) {
+        lines.push(format!("stance {}", stance));
+    }
+
+    if let Some(fov_radius) = entities.fov_radius.get(&entity_id) {
+        lines.push(format!("fov radius {}", fov_radius));
+    }
+
+    if let Some(energy) = entities.energy.get(&entity_id) {
+        lines.push(format!("energy {}", energy));
+    }
+
+    if let Some(stamina) = entities.stamina.get(&entity_id) {
+        lines.push(format!("stamina {}", stamina));
+    }
+
+    if let Some(status) = entities.status.get(&entity_id) {
+        if !status.alive {
+            lines.push("dead".to_string());
+        }
+        if status.frozen > 0 {
+            lines.push(format!("frozen {}", status.frozen));
+        }
+        if status.knocked_out > 0 {
+            lines.push(format!("knocked out {}", status.knocked_out));
+        }
+    }
+
+    if let Some(inventory) = entities.inventory.get(&entity_id) {
+        if inventory.len() > 0 {
+            lines.push("inventory".to_string());
+            for item_id in inventory.iter() {
+                lines.push(format!("  {}", entities.name[item_id]));
+            }
+        }
+    }
+
+    return lines;
+}
@@ -1,10 +1,40 @@
 pub mod game;
+pub mod error;
 pub mod make_map;
 pub mod actions;
 pub mod generation;
 pub mod resolve;
 pub mod procgen;
+pub mod director;
 pub mod step;
 pub mod input;
 pub mod vault;
+pub mod sandbox;
+pub mod loadout;
+pub mod menu;
+pub mod prompt;
+pub mod dialogue;
+pub mod container;
+pub mod shop;
+pub mod selection;
+pub mod objective;
+pub mod score;
+pub mod achievement;
+pub mod help;
+pub mod hint;
+pub mod inspector;
+pub mod minimap;
+pub mod overlay;
+pub mod schedule;
+pub mod narrate;
+pub mod config_watcher;
+pub mod manifest;
+pub mod tiled;
+pub mod map_stats;
+pub mod telemetry;
+pub mod agent;
 pub mod log;
+pub mod checkpoint;
+pub mod ghost;
+pub mod spectate;
+pub mod coop;
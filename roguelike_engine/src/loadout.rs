@@ -0,0 +1,71 @@
+use serde::{Serialize, Deserialize};
+
+use roguelike_core::types::*;
+use roguelike_core::config::Config;
+use roguelike_core::messaging::{Msg, MsgLog};
+
+use crate::game::Game;
+use crate::error::{GameResult, read_yaml};
+use crate::actions::InputAction;
+use crate::generation::make_entity;
+use crate::achievement::{Achievement, Unlocks};
+
+
+/// A starting setup for the player, chosen when a new game begins.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Loadout {
+    pub name: String,
+    pub class: EntityClass,
+    pub hp: Hp,
+    pub energy: u32,
+    pub items: Vec<Item>,
+    #[serde(default)]
+    pub unlock: Option<Achievement>, // achievement needed before the loadout can be chosen
+}
+
+impl Loadout {
+    pub fn from_file(file_name: &str) -> GameResult<Vec<Loadout>> {
+        return read_yaml(file_name);
+    }
+
+    pub fn is_available(&self, unlocks: &Unlocks) -> bool {
+        return self.unlock.map_or(true, |achievement| unlocks.is_unlocked(achievement));
+    }
+}
+
+/// Handle input while choosing a loadout. Choosing a loadout sets up the player
+/// and starts the game. Loadouts that are still locked can't be chosen.
+pub fn handle_input_new_game(input_action: InputAction, game: &mut Game) {
+    match input_action {
+        InputAction::SelectItem(index) => {
+            if index < game.loadouts.len() && game.loadouts[index].is_available(&game.unlocks) {
+                let loadout = game.loadouts[index].clone();
+                apply_loadout(&loadout, &mut game.data, &game.config, &mut game.msg_log);
+
+                game.settings.state = GameState::Playing;
+            }
+        }
+
+        _ => {
+        }
+    }
+}
+
+/// Give the player the stats, skills, and starting items of a loadout.
+pub fn apply_loadout(loadout: &Loadout, data: &mut GameData, config: &Config, msg_log: &mut MsgLog) {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = data.entities.pos[&player_id];
+
+    let hp = std::cmp::max(1, loadout.hp + config.player_health_bonus);
+    data.entities.fighter[&player_id].max_hp = hp;
+    data.entities.fighter[&player_id].hp = hp;
+    data.entities.energy[&player_id] = loadout.energy;
+
+    // the class's skills are given out when the message is resolved
+    msg_log.log(Msg::AddClass(loadout.class));
+
+    for item in loadout.items.iter() {
+        let item_id = make_entity(&mut data.entities, config, item.name(), player_pos, msg_log);
+        data.entities.pick_up_item(player_id, item_id);
+    }
+}
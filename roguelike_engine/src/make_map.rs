@@ -1,9 +1,11 @@
 use std::fs::File;
-use std::io::BufReader;
+use std::io::Read;
 use std::io::Write;
 
 use rexpaint::*;
 
+use flate2::read::GzDecoder;
+
 use log::trace;
 
 use roguelike_core::constants::*;
@@ -17,13 +19,57 @@ use crate::generation::*;
 use crate::game::*;
 use crate::procgen::*;
 use crate::vault::*;
+use crate::objective::choose_objective;
+use crate::director::find_entry_points;
+use crate::tiled::read_map_tiled;
+use crate::error::{GameError, GameResult};
+
+#[cfg(test)]
+use proptest::prelude::*;
+
+
+/// Make the map for the current level. A map file or generator setting which can't be
+/// read is returned as an error, leaving the game without a usable map.
+pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) -> GameResult<()> {
+    let generated = match map_load_config {
+        MapLoadConfig::ProcGen(_) | MapLoadConfig::Rooms(_) | MapLoadConfig::Caves(_) => true,
+        _ => false,
+    };
+
+    load_map(map_load_config, game)?;
+
+    // generated maps are regenerated, continuing from the current rng state, until they can be played
+    if generated {
+        let mut attempts = 1;
+        let mut report = validate_map(game);
+        while !report.passed(&game.config) && attempts < game.config.map_generation_attempts {
+            let generator = map_load_config.to_string();
+            *game.map_failures.entry(generator.clone()).or_insert(0) += 1;
+            eprintln!("Map validation failed for {} ({} failures): {}", generator, game.map_failures[&generator], report);
+
+            load_map(map_load_config, game)?;
+            report = validate_map(game);
+            attempts += 1;
+        }
+
+        if !report.passed(&game.config) {
+            eprintln!("Using map that failed validation after {} attempts: {}", attempts, report);
+        }
 
+        choose_objective(game);
+    }
+
+    return Ok(());
+}
 
-pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) {
+fn load_map(map_load_config: &MapLoadConfig, game: &mut Game) -> GameResult<()> {
     let player_position: Pos;
 
     let player_id = game.data.find_by_name(EntityName::Player).unwrap();
     game.data.clear_except(vec!(player_id));
+    game.data.objective = Objective::KeyAndExit;
+    game.data.reinforcements.clear();
+    game.vaults_placed = 0;
 
     match map_load_config {
         MapLoadConfig::TestMap => {
@@ -40,7 +86,7 @@ pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) {
 
         MapLoadConfig::ProcGen(procgen_params) => {
             let file_name = format!("resources/procgen/{}", procgen_params);
-            let cmds = ProcCmd::from_file(&file_name);
+            let cmds = ProcCmd::from_file(&file_name)?;
 
             let mut template_file = "resources/wfc_seed_2.png".to_string();
             for param in cmds.iter() {
@@ -49,7 +95,23 @@ pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) {
                 }
             }
 
-            game.data.map = generate_bare_map(20, 20, &template_file, &mut game.rng);
+            generate_seeded_map(game, &cmds, 20, 20, &template_file)?;
+            player_position = saturate_map(game, &cmds);
+        }
+
+        MapLoadConfig::Rooms(procgen_params) => {
+            let file_name = format!("resources/procgen/{}", procgen_params);
+            let cmds = ProcCmd::from_file(&file_name)?;
+
+            game.data.map = generate_rooms_map(MAP_WIDTH as u32, MAP_HEIGHT as u32, &game.config, &mut game.rng);
+            player_position = saturate_map(game, &cmds);
+        }
+
+        MapLoadConfig::Caves(procgen_params) => {
+            let file_name = format!("resources/procgen/{}", procgen_params);
+            let cmds = ProcCmd::from_file(&file_name)?;
+
+            game.data.map = generate_caves_map(MAP_WIDTH as u32, MAP_HEIGHT as u32, &game.config, &mut game.rng);
             player_position = saturate_map(game, &cmds);
         }
 
@@ -82,7 +144,7 @@ pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) {
         }
 
         MapLoadConfig::VaultFile(file_name) => {
-            let vault: Vault = parse_vault(&format!("resources/{}", file_name), &game.config);
+            let vault: Vault = parse_vault(&format!("resources/{}", file_name), &game.config).map_err(GameError::Asset)?;
             let (vault_width, vault_height) = vault.data.map.size();
             let map_width = 3 * vault_width;
             let map_height = 3 * vault_height;
@@ -98,21 +160,41 @@ pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) {
         }
 
         MapLoadConfig::FromFile(file_name) => {
-            let maps: Vec<String> = parse_map_file(&format!("resources/{}", file_name));
+            let maps_file = format!("resources/{}", file_name);
+            let maps: Vec<String> = parse_map_file(&maps_file)?;
 
             if game.settings.level_num >= maps.len() {
-                panic!(format!("Map index {} too large ({} available", game.settings.level_num, maps.len()));
+                return Err(GameError::NoMap { file: maps_file, level: game.settings.level_num, count: maps.len() });
             }
 
             let map_name = format!("resources/{}", maps[game.settings.level_num]);
             let mut position =
-                read_map_xp(&game.config, &mut game.data, &mut game.msg_log, &map_name);
+                read_map_xp(&game.config, &mut game.data, &mut game.msg_log, &map_name).map_err(GameError::Asset)?;
+            if position == (0, 0) {
+                position = (game.data.map.width() / 2, game.data.map.height() / 2);
+            }
+            player_position = Pos::from(position);
+        }
+
+        MapLoadConfig::Tiled(file_name) => {
+            let map_name = format!("resources/{}", file_name);
+            let mut position =
+                read_map_tiled(&game.config, &mut game.data, &mut game.msg_log, &map_name).map_err(GameError::Asset)?;
             if position == (0, 0) {
                 position = (game.data.map.width() / 2, game.data.map.height() / 2);
             }
             player_position = Pos::from(position);
         }
 
+        MapLoadConfig::Text(file_name) => {
+            let map_name = format!("resources/{}", file_name);
+            let map_string = std::fs::read_to_string(&map_name).map_err(|err| GameError::read(&map_name, err))?;
+            player_position =
+                read_map_text(&game.config, &mut game.data, &map_string)
+                    .map_err(|err| GameError::Asset(format!("{}: {}", map_name, err)))?
+                    .unwrap_or(Pos::new(game.data.map.width() / 2, game.data.map.height() / 2));
+        }
+
         MapLoadConfig::Random => {
             game.data.map = Map::from_dims(MAP_WIDTH as u32, MAP_HEIGHT as u32);
             let starting_position = make_island(&mut game.data, &game.config, &mut game.msg_log, &mut game.rng);
@@ -152,6 +234,8 @@ pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) {
     //game.msg_log.log(Msg::Moved(player_id, MoveType::Move, player_position));
     game.data.entities.set_pos(player_id, player_position);
 
+    game.data.entry_points = find_entry_points(&game.data, player_position);
+
     if game.config.write_map_distribution {
         let max = (2 * TILE_FILL_METRIC_DIST + 1).pow(2);
         let mut counts = vec![0; max + 1];
@@ -166,24 +250,47 @@ pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) {
             write!(file, "{} {}\n", index, count).unwrap();
         }
     }
+
+    return Ok(());
 }
 
+/// The bytes of each cell in a REXPaint file- a character and two colors.
+const XP_CELL_BYTES: usize = 10;
+
 pub fn read_map_xp(config: &Config,
                    data: &mut GameData,
                    msg_log: &mut MsgLog,
-                   file_name: &str) -> (i32, i32) {
+                   file_name: &str) -> Result<(i32, i32), String> {
     trace!("opening map {}", file_name);
-    let file = File::open(file_name).unwrap();
+    let bytes = std::fs::read(file_name).map_err(|err| format!("Could not open map {}: {}", file_name, err))?;
 
-    let mut buf_reader = BufReader::new(file);
+    return read_map_xp_bytes(config, data, msg_log, &bytes).map_err(|err| format!("{}: {}", file_name, err));
+}
 
+/// Read a map from the contents of a REXPaint file. Every layer has to be the size of
+/// the first, and use only the characters read_map_cell understands for its layer.
+pub fn read_map_xp_bytes(config: &Config,
+                         data: &mut GameData,
+                         msg_log: &mut MsgLog,
+                         bytes: &[u8]) -> Result<(i32, i32), String> {
     trace!("reading in map data");
-    let xp = XpFile::read(&mut buf_reader).unwrap();
+    check_xp_sizes(bytes)?;
+    let xp = XpFile::read(&mut &bytes[..]).map_err(|err| format!("Could not read map: {}", err))?;
 
-    data.map = Map::from_dims(xp.layers[0].width as u32, xp.layers[0].height as u32);
+    let (map_width, map_height) =
+        match xp.layers.first() {
+            Some(layer) if layer.width > 0 && layer.height > 0 => (layer.width, layer.height),
+            _ => return Err("map is empty".to_string()),
+        };
+
+    data.map = Map::from_dims(map_width as u32, map_height as u32);
     let mut player_position = (0, 0);
 
     for (layer_index, layer) in xp.layers.iter().enumerate() {
+        if layer.width != map_width || layer.height != map_height {
+            return Err(format!("layer {} is {}x{}, but the map is {}x{}", layer_index, layer.width, layer.height, map_width, map_height));
+        }
+
         let width = layer.width as i32;
         let height = layer.height as i32;
 
@@ -194,269 +301,681 @@ pub fn read_map_xp(config: &Config,
 
                 let pos = Pos::new(x, y);
 
-                let chr = std::char::from_u32(cell.ch).unwrap();
+                let chr = std::char::from_u32(cell.ch).ok_or(format!("cell {} in layer {} is not a character", cell.ch, layer_index))?;
 
-                match layer_index {
-                    MAP_LAYER_GROUND => {
-                        match chr as u8 {
-                            0 => {
-                            }
+                read_map_cell(config, data, msg_log, layer_index, pos, chr, &mut player_position)?;
+            }
+        }
+    }
 
-                            MAP_GROUND => {
-                            }
+    trace!("map read finished");
 
-                            MAP_WATER => {
-                                data.map[pos] = Tile::water();
-                                data.map[pos].chr = MAP_WATER;
-                            }
+    return Ok(player_position);
+}
 
-                            MAP_RUBBLE => {
-                                data.map[pos].surface = Surface::Rubble;
-                            }
+/// Check that each layer of a REXPaint file holds as many cells as its size says. The
+/// rexpaint crate makes room for a layer before reading it, so a file claiming a huge
+/// layer would otherwise run out of memory rather than failing to read.
+fn check_xp_sizes(bytes: &[u8]) -> Result<(), String> {
+    let mut contents = Vec::new();
+    GzDecoder::new(bytes).read_to_end(&mut contents).map_err(|err| format!("Could not decompress map: {}", err))?;
+
+    let read_u32 = |offset: usize| {
+        return contents.get(offset..offset + 4)
+                       .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]) as usize);
+    };
+
+    // the header is a version and the number of layers
+    let num_layers = read_u32(4).ok_or("map header is cut off".to_string())?;
+
+    let mut offset = 8;
+    for layer_index in 0..num_layers {
+        let (width, height) =
+            match (read_u32(offset), read_u32(offset + 4)) {
+                (Some(width), Some(height)) => (width, height),
+                _ => return Err(format!("layer {} is cut off", layer_index)),
+            };
+        offset += 8;
+
+        let layer_bytes = width.checked_mul(height).and_then(|cells| cells.checked_mul(XP_CELL_BYTES));
+        match layer_bytes {
+            Some(layer_bytes) if layer_bytes <= contents.len() - offset => offset += layer_bytes,
+            _ => return Err(format!("layer {} is {}x{}, but the file is too short to hold it", layer_index, width, height)),
+        }
+    }
 
-                            MAP_GRASS => {
-                                data.map[pos].surface = Surface::Grass;
-                            }
+    return Ok(());
+}
 
-                            _ => {
-                                dbg!(format!("Unexpected character {} in ground layer!", chr as u8));
-                            }
-                        }
-                    }
+/// Set the wall to the left of a tile next to the one being read, if it is on the map.
+fn set_left_wall(map: &mut Map, pos: Pos, wall: Wall) {
+    if map.is_within_bounds(pos) {
+        map[pos].left_wall = wall;
+    }
+}
 
-                    MAP_LAYER_ENVIRONMENT => {
-                        match chr as u8 {
-                            MAP_COLUMN => {
-                                make_column(&mut data.entities, config, pos, msg_log);
-                            }
-
-                            MAP_THIN_WALL_TOP => {
-                                data.map[pos].chr = 0;
-                                data.map[(x, y - 1)].bottom_wall = Wall::ShortWall;
-                            }
-
-                            MAP_THIN_WALL_BOTTOM => {
-                                data.map[pos].chr = 0; 
-                                data.map[pos].bottom_wall = Wall::ShortWall;
-                            }
-
-                            MAP_THIN_WALL_LEFT => {
-                                data.map[pos].chr = 0; 
-                                data.map[pos].left_wall = Wall::ShortWall;
-                            }
-
-                            MAP_THIN_WALL_RIGHT => {
-                                data.map[pos].chr = 0; 
-                                data.map[(x + 1, y)].left_wall = Wall::ShortWall;
-                            }
-
-                            MAP_THIN_WALL_TOP_LEFT => {
-                                data.map[pos].chr = 0; 
-                                data.map[pos].left_wall = Wall::ShortWall;
-                                data.map[(x, y - 1)].bottom_wall = Wall::ShortWall;
-                            }
-
-                            MAP_THIN_WALL_BOTTOM_LEFT => {
-                                data.map[pos].chr = 0; 
-                                data.map[pos].left_wall = Wall::ShortWall;
-                                data.map[pos].bottom_wall = Wall::ShortWall;
-                            }
-
-                            MAP_THIN_WALL_TOP_RIGHT => {
-                                data.map[pos].chr = 0; 
-                                data.map[(x, y - 1)].bottom_wall = Wall::ShortWall;
-                                data.map[(x - 1, y)].left_wall = Wall::ShortWall;
-                            }
-
-                            MAP_THIN_WALL_BOTTOM_RIGHT => {
-                                data.map[pos].chr = 0; 
-                                data.map[pos].bottom_wall = Wall::ShortWall;
-                                data.map[(x + 1, y)].left_wall = Wall::ShortWall;
-                            }
-
-                            MAP_THICK_WALL_TOP => {
-                                data.map[pos].chr = 0; 
-                                data.map[(x, y - 1)].bottom_wall = Wall::ShortWall;
-                            }
-
-                            MAP_THICK_WALL_LEFT => {
-                                data.map[pos].chr = 0; 
-                                data.map[pos].left_wall = Wall::TallWall;
-                            }
-
-                            MAP_THICK_WALL_RIGHT => {
-                                data.map[pos].chr = 0; 
-                                data.map[(x + 1, y)].left_wall = Wall::ShortWall;
-                            }
-
-                            MAP_THICK_WALL_BOTTOM => {
-                                data.map[pos].chr = 0; 
-                                data.map[pos].bottom_wall = Wall::TallWall;
-                            }
-
-                            MAP_THICK_WALL_TOP_LEFT => {
-                                data.map[pos].chr = 0; 
-                                data.map[pos].left_wall = Wall::TallWall;
-                                data.map[(x, y - 1)].bottom_wall = Wall::TallWall;
-                            }
-
-                            MAP_THICK_WALL_BOTTOM_LEFT => {
-                                data.map[pos].chr = 0; 
-                                data.map[pos].bottom_wall = Wall::TallWall;
-                                data.map[pos].left_wall = Wall::TallWall;
-                            }
-
-                            MAP_THICK_WALL_TOP_RIGHT => {
-                                data.map[pos].chr = 0; 
-                                data.map[(x, y - 1)].bottom_wall = Wall::TallWall;
-                                data.map[(x + 1, y)].left_wall = Wall::TallWall;
-                            }
-
-                            MAP_THICK_WALL_BOTTOM_RIGHT => {
-                                data.map[pos].chr = 0; 
-                                data.map[pos].bottom_wall = Wall::TallWall;
-                                data.map[(x + 1, y)].left_wall = Wall::TallWall;
-                            }
-
-                            MAP_DOT_TOP_LEFT => {
-                                data.map[pos].chr = chr as u8;
-                                data.map[pos].block_move = true;
-                            }
-
-                            MAP_DOT_TOP_RIGHT => {
-                                data.map[pos].chr = chr as u8;
-                                data.map[pos].block_move = true;
-                            }
-
-                            MAP_DOT_BOTTOM_LEFT => {
-                                data.map[pos].chr = chr as u8;
-                                data.map[pos].block_move = true;
-                            }
-
-                            MAP_DOT_BOTTOM_RIGHT => {
-                                data.map[pos].chr = chr as u8;
-                                data.map[pos].block_move = true;
-                            }
-
-                            MAP_ROOK => {
-                                data.map[pos].chr = chr as u8;
-                                data.map[pos].block_move = true;
-                            }
-
-                            MAP_ORB => {
-                                data.map[pos].chr = chr as u8;
-                                data.map[pos].block_move = true;
-                            }
-
-                            MAP_EMPTY => {
-                                data.map[pos].chr = MAP_EMPTY_CHAR;
-                            }
-
-                            MAP_STATUE_1 | MAP_STATUE_2 | MAP_STATUE_3 |
-                                MAP_STATUE_4 | MAP_STATUE_5 | MAP_STATUE_6 => {
-                                    data.map[pos].chr = chr as u8;
-                                    data.map[pos].block_move = true;
-                                    data.map[pos].block_sight = true;
-                                }
-
-                            MAP_WIDE_SPIKES | MAP_TALL_SPIKES => {
-                                data.map[pos].chr = chr as u8;
-                                data.map[pos].block_move = true;
-                                    data.map[pos].block_sight = true;
-                            }
-
-                            MAP_WALL => {
-                                data.map[pos].chr = chr as u8;
-                                data.map[pos].block_move = true;
-                                data.map[pos].block_sight = true;
-                            }
-
-                            ENTITY_CLOAK_GUY => {
-                                data.map[pos].chr = chr as u8;
-                                data.map[pos].block_move = true;
-                            }
-
-                            _ => {
-                                data.map[pos].chr = chr as u8;
-                                data.map[pos].block_move = true;
-                            }
-                        }
-                    }
+/// Set the wall below a tile next to the one being read, if it is on the map.
+fn set_bottom_wall(map: &mut Map, pos: Pos, wall: Wall) {
+    if map.is_within_bounds(pos) {
+        map[pos].bottom_wall = wall;
+    }
+}
 
-                    MAP_LAYER_ENTITIES => {
-                        match chr as u8 {
-                            0 => {
-                            }
-
-                            ENTITY_PLAYER => {
-                                player_position = (x as i32, y as i32);
-                            }
-
-                            ENTITY_GOL => {
-                                make_gol(&mut data.entities, config, pos, msg_log);
-                            }
-
-                            ENTITY_EXIT => {
-                                make_exit(&mut data.entities, config, pos, msg_log);
-                            }
-
-                            ENTITY_ELF => {
-                                make_pawn(&mut data.entities, config, pos, msg_log);
-                            }
-
-                            MAP_EMPTY => {
-                                // Nothing to do here...
-                            }
-
-                            ENTITY_DAGGER => {
-                                make_dagger(&mut data.entities, config, pos, msg_log);
-                            }
-
-                            ENTITY_KEY => {
-                                make_key(&mut data.entities, config, pos, msg_log);
-                            }
-
-                            ENTITY_STONE => {
-                                make_stone(&mut data.entities, config, pos, msg_log);
-                            }
-
-                            ENTITY_SHIELD => {
-                                make_shield(&mut data.entities, config, Pos::new(x, y), msg_log);
-                            }
-
-                            ENTITY_HAMMER => {
-                                make_hammer(&mut data.entities, config, Pos::new(x, y), msg_log);
-                            }
- 
-                            ENTITY_SPIKE_TRAP => {
-                                make_spike_trap(&mut data.entities, config, pos, msg_log);
-                            }
-
-                            _ => {
-                                panic!(format!("Unexpected character {} in entities layer!", chr as u8));
-                            }
-                        }
-                    }
+/// Apply one character from a layer of a map file to the tile or entities at a position.
+/// The characters are the ones used in REXPaint maps, which other map formats share.
+pub fn read_map_cell(config: &Config,
+                     data: &mut GameData,
+                     msg_log: &mut MsgLog,
+                     layer_index: usize,
+                     pos: Pos,
+                     chr: char,
+                     player_position: &mut (i32, i32)) -> Result<(), String> {
+    if !data.map.is_within_bounds(pos) {
+        return Err(format!("{} is off the map", pos));
+    }
+
+    let (x, y) = (pos.x, pos.y);
+
+    match layer_index {
+        MAP_LAYER_GROUND => {
+            match chr as u8 {
+                0 => {
+                }
+
+                // older maps paint their ground with patterns, which are plain ground
+                MAP_GROUND | MAP_GROUND_PATTERN | MAP_STAR => {
+                }
+
+                MAP_WATER => {
+                    data.map[pos] = Tile::water();
+                    data.map[pos].chr = MAP_WATER;
+                }
+
+                MAP_RUBBLE => {
+                    data.map[pos].surface = Surface::Rubble;
+                }
+
+                MAP_GRASS => {
+                    data.map[pos].surface = Surface::Grass;
+                }
+
+                _ => {
+                    return Err(format!("Unexpected character {} in ground layer", chr as u32));
+                }
+            }
+        }
+
+        MAP_LAYER_ENVIRONMENT => {
+            match chr as u8 {
+                0 => {
+                }
+
+                MAP_COLUMN => {
+                    make_column(&mut data.entities, config, pos, msg_log);
+                }
+
+                MAP_THIN_WALL_TOP => {
+                    data.map[pos].chr = 0;
+                    set_bottom_wall(&mut data.map, Pos::new(x, y - 1), Wall::ShortWall);
+                }
+
+                MAP_THIN_WALL_BOTTOM => {
+                    data.map[pos].chr = 0; 
+                    data.map[pos].bottom_wall = Wall::ShortWall;
+                }
+
+                MAP_THIN_WALL_LEFT => {
+                    data.map[pos].chr = 0; 
+                    data.map[pos].left_wall = Wall::ShortWall;
+                }
+
+                MAP_THIN_WALL_RIGHT => {
+                    data.map[pos].chr = 0; 
+                    set_left_wall(&mut data.map, Pos::new(x + 1, y), Wall::ShortWall);
+                }
+
+                MAP_THIN_WALL_TOP_LEFT => {
+                    data.map[pos].chr = 0; 
+                    data.map[pos].left_wall = Wall::ShortWall;
+                    set_bottom_wall(&mut data.map, Pos::new(x, y - 1), Wall::ShortWall);
+                }
+
+                MAP_THIN_WALL_BOTTOM_LEFT => {
+                    data.map[pos].chr = 0; 
+                    data.map[pos].left_wall = Wall::ShortWall;
+                    data.map[pos].bottom_wall = Wall::ShortWall;
+                }
+
+                MAP_THIN_WALL_TOP_RIGHT => {
+                    data.map[pos].chr = 0; 
+                    set_bottom_wall(&mut data.map, Pos::new(x, y - 1), Wall::ShortWall);
+                    set_left_wall(&mut data.map, Pos::new(x - 1, y), Wall::ShortWall);
+                }
+
+                MAP_THIN_WALL_BOTTOM_RIGHT => {
+                    data.map[pos].chr = 0; 
+                    data.map[pos].bottom_wall = Wall::ShortWall;
+                    set_left_wall(&mut data.map, Pos::new(x + 1, y), Wall::ShortWall);
+                }
+
+                MAP_THICK_WALL_TOP => {
+                    data.map[pos].chr = 0; 
+                    set_bottom_wall(&mut data.map, Pos::new(x, y - 1), Wall::ShortWall);
+                }
+
+                MAP_THICK_WALL_LEFT => {
+                    data.map[pos].chr = 0; 
+                    data.map[pos].left_wall = Wall::TallWall;
+                }
+
+                MAP_THICK_WALL_RIGHT => {
+                    data.map[pos].chr = 0; 
+                    set_left_wall(&mut data.map, Pos::new(x + 1, y), Wall::ShortWall);
+                }
+
+                MAP_THICK_WALL_BOTTOM => {
+                    data.map[pos].chr = 0; 
+                    data.map[pos].bottom_wall = Wall::TallWall;
+                }
+
+                MAP_THICK_WALL_TOP_LEFT => {
+                    data.map[pos].chr = 0; 
+                    data.map[pos].left_wall = Wall::TallWall;
+                    set_bottom_wall(&mut data.map, Pos::new(x, y - 1), Wall::TallWall);
+                }
+
+                MAP_THICK_WALL_BOTTOM_LEFT => {
+                    data.map[pos].chr = 0; 
+                    data.map[pos].bottom_wall = Wall::TallWall;
+                    data.map[pos].left_wall = Wall::TallWall;
+                }
+
+                MAP_THICK_WALL_TOP_RIGHT => {
+                    data.map[pos].chr = 0; 
+                    set_bottom_wall(&mut data.map, Pos::new(x, y - 1), Wall::TallWall);
+                    set_left_wall(&mut data.map, Pos::new(x + 1, y), Wall::TallWall);
+                }
+
+                MAP_THICK_WALL_BOTTOM_RIGHT => {
+                    data.map[pos].chr = 0; 
+                    data.map[pos].bottom_wall = Wall::TallWall;
+                    set_left_wall(&mut data.map, Pos::new(x + 1, y), Wall::TallWall);
+                }
+
+                MAP_DOT_TOP_LEFT => {
+                    data.map[pos].chr = chr as u8;
+                    data.map[pos].block_move = true;
+                }
+
+                MAP_DOT_TOP_RIGHT => {
+                    data.map[pos].chr = chr as u8;
+                    data.map[pos].block_move = true;
+                }
+
+                MAP_DOT_BOTTOM_LEFT => {
+                    data.map[pos].chr = chr as u8;
+                    data.map[pos].block_move = true;
+                }
+
+                MAP_DOT_BOTTOM_RIGHT => {
+                    data.map[pos].chr = chr as u8;
+                    data.map[pos].block_move = true;
+                }
+
+                MAP_ROOK => {
+                    data.map[pos].chr = chr as u8;
+                    data.map[pos].block_move = true;
+                }
+
+                MAP_ORB => {
+                    data.map[pos].chr = chr as u8;
+                    data.map[pos].block_move = true;
+                }
+
+                MAP_EMPTY => {
+                    data.map[pos].chr = MAP_EMPTY_CHAR;
+                }
 
-                    _ => {
-                        panic!(format!("Layer {} not expected in map file!", layer_index));
+                MAP_STATUE_1 | MAP_STATUE_2 | MAP_STATUE_3 |
+                    MAP_STATUE_4 | MAP_STATUE_5 | MAP_STATUE_6 => {
+                        data.map[pos].chr = chr as u8;
+                        data.map[pos].block_move = true;
+                        data.map[pos].block_sight = true;
                     }
+
+                MAP_WIDE_SPIKES | MAP_TALL_SPIKES => {
+                    data.map[pos].chr = chr as u8;
+                    data.map[pos].block_move = true;
+                        data.map[pos].block_sight = true;
+                }
+
+                MAP_WALL => {
+                    data.map[pos].chr = chr as u8;
+                    data.map[pos].tile_type = TileType::Wall;
+                    data.map[pos].block_move = true;
+                    data.map[pos].block_sight = true;
+                }
+
+                MAP_DOOR_OPEN => {
+                    data.map[pos].set_door(DoorState::Open);
+                }
+
+                MAP_DOOR_CLOSED => {
+                    data.map[pos].set_door(DoorState::Closed);
+                }
+
+                MAP_DOOR_LOCKED => {
+                    data.map[pos].set_door(DoorState::Locked);
+                }
+
+                ENTITY_CLOAK_GUY => {
+                    data.map[pos].chr = chr as u8;
+                    data.map[pos].block_move = true;
+                }
+
+                _ => {
+                    data.map[pos].chr = chr as u8;
+                    data.map[pos].block_move = true;
                 }
             }
         }
+
+        MAP_LAYER_ENTITIES => {
+            match chr as u8 {
+                0 => {
+                }
+
+                ENTITY_PLAYER => {
+                    *player_position = (x as i32, y as i32);
+                }
+
+                ENTITY_GOL => {
+                    make_gol(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_EXIT => {
+                    make_exit(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_ELF => {
+                    make_pawn(&mut data.entities, config, pos, msg_log);
+                }
+
+                MAP_EMPTY => {
+                    // Nothing to do here...
+                }
+
+                ENTITY_DAGGER => {
+                    make_dagger(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_KEY => {
+                    make_key(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_STONE => {
+                    make_stone(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_SHIELD => {
+                    make_shield(&mut data.entities, config, Pos::new(x, y), msg_log);
+                }
+
+                ENTITY_HAMMER => {
+                    make_hammer(&mut data.entities, config, Pos::new(x, y), msg_log);
+                }
+
+                ENTITY_SPIKE_TRAP => {
+                    make_spike_trap(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_TRIPWIRE => {
+                    make_tripwire(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_NET_TRAP => {
+                    make_net_trap(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_PRESSURE_PLATE => {
+                    make_pressure_plate(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_LEVER_UP => {
+                    make_lever(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_GATE_CLOSED => {
+                    make_gate(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_BLADE_TRAP => {
+                    make_blade_trap(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_CHEST => {
+                    make_chest(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_CRATE => {
+                    make_crate(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_ALARM => {
+                    make_alarm(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_RAT => {
+                    make_rat(&mut data.entities, config, pos, msg_log);
+                }
+
+                ENTITY_BIRD => {
+                    make_bird(&mut data.entities, config, pos, msg_log);
+                }
+
+                _ => {
+                    return Err(format!("Unexpected character {} in entities layer", chr as u32));
+                }
+            }
+        }
+
+        MAP_LAYER_LINKS => {
+            // link ids are written as digits, continuing on to letters past 9
+            if let Some(link) = chr.to_digit(36) {
+                data.map[pos].link = link as u8;
+            }
+        }
+
+        _ => {
+            return Err(format!("Layer {} not expected in map file", layer_index));
+        }
     }
 
-    trace!("map read finished");
+    return Ok(());
+}
+
+/// Write the map and entities out as a REXPaint file, using the same layers
+/// as read_map_xp, so a level edited in game or generated can be changed in REXPaint.
+/// Entities that read_map_xp does not understand, those without an entity_map_chr, are
+/// not written.
+pub fn write_map_xp(data: &GameData, file_name: &str) -> GameResult<()> {
+    let width = data.map.width() as usize;
+    let height = data.map.height() as usize;
+
+    let mut xp = XpFile::new(width, height);
+    xp.layers.push(XpLayer::new(width, height));
+    xp.layers.push(XpLayer::new(width, height));
+    xp.layers.push(XpLayer::new(width, height));
+
+    // items held in an inventory are not placed on the map
+    let mut held = Vec::new();
+    for inventory in data.entities.inventory.values() {
+        held.extend(inventory.iter().cloned());
+    }
+
+    for pos in data.map.get_all_pos() {
+        let tile = data.map[pos];
+
+        let ground_chr =
+            if tile.tile_type == TileType::Water {
+                MAP_WATER
+            } else if tile.surface == Surface::Rubble {
+                MAP_RUBBLE
+            } else if tile.surface == Surface::Grass {
+                MAP_GRASS
+            } else {
+                MAP_GROUND
+            };
+
+        let environment_chr =
+            if tile.tile_type == TileType::Wall {
+                MAP_WALL
+            } else if let Some(state) = tile.door_state() {
+                match state {
+                    DoorState::Open => MAP_DOOR_OPEN,
+                    DoorState::Closed => MAP_DOOR_CLOSED,
+                    DoorState::Locked => MAP_DOOR_LOCKED,
+                }
+            } else {
+                match (tile.left_wall, tile.bottom_wall) {
+                    (Wall::Empty, Wall::Empty) => 0,
+                    (Wall::ShortWall, Wall::Empty) => MAP_THIN_WALL_LEFT,
+                    (Wall::TallWall, Wall::Empty) => MAP_THICK_WALL_LEFT,
+                    (Wall::Empty, Wall::ShortWall) => MAP_THIN_WALL_BOTTOM,
+                    (Wall::Empty, Wall::TallWall) => MAP_THICK_WALL_BOTTOM,
+                    (Wall::TallWall, Wall::TallWall) => MAP_THICK_WALL_BOTTOM_LEFT,
+                    _ => MAP_THIN_WALL_BOTTOM_LEFT,
+                }
+            };
+
+        // decorations such as statues are blocking tiles drawn with their own character
+        let environment_chr =
+            if environment_chr == 0 && tile.chr == MAP_EMPTY_CHAR {
+                MAP_EMPTY
+            } else if environment_chr == 0 && tile.block_move && tile.tile_type != TileType::Water {
+                tile.chr
+            } else {
+                environment_chr
+            };
+
+        xp_cell(&mut xp, MAP_LAYER_GROUND, pos).ch = ground_chr as u32;
+        xp_cell(&mut xp, MAP_LAYER_ENVIRONMENT, pos).ch = environment_chr as u32;
+
+        if tile.link != 0 {
+            if let Some(link_chr) = std::char::from_digit(tile.link as u32, 36) {
+                xp_cell(&mut xp, MAP_LAYER_LINKS, pos).ch = link_chr as u32;
+            }
+        }
+    }
 
-    trace!("map updated");
+    for entity_id in data.entities.ids.iter() {
+        if held.contains(entity_id) {
+            continue;
+        }
+
+        let pos = data.entities.pos[entity_id];
+        if !data.map.is_within_bounds(pos) {
+            continue;
+        }
 
-    return player_position;
+        if let Some((layer, chr)) = entity_map_chr(data.entities.name[entity_id]) {
+            xp_cell(&mut xp, layer, pos).ch = chr as u32;
+        }
+    }
+
+    let mut file = File::create(file_name).map_err(|err| GameError::write(file_name, err))?;
+    return xp.write(&mut file).map_err(|err| GameError::write(file_name, err));
+}
+
+/// The layer and character an entity is written as in a map file, if map files can hold it.
+pub fn entity_map_chr(name: EntityName) -> Option<(usize, u8)> {
+    match name {
+        EntityName::Column => Some((MAP_LAYER_ENVIRONMENT, MAP_COLUMN)),
+        EntityName::Player => Some((MAP_LAYER_ENTITIES, ENTITY_PLAYER)),
+        EntityName::Gol => Some((MAP_LAYER_ENTITIES, ENTITY_GOL)),
+        EntityName::Exit => Some((MAP_LAYER_ENTITIES, ENTITY_EXIT)),
+        EntityName::Pawn => Some((MAP_LAYER_ENTITIES, ENTITY_ELF)),
+        EntityName::Dagger => Some((MAP_LAYER_ENTITIES, ENTITY_DAGGER)),
+        EntityName::Key => Some((MAP_LAYER_ENTITIES, ENTITY_KEY)),
+        EntityName::Stone => Some((MAP_LAYER_ENTITIES, ENTITY_STONE)),
+        EntityName::Shield => Some((MAP_LAYER_ENTITIES, ENTITY_SHIELD)),
+        EntityName::Hammer => Some((MAP_LAYER_ENTITIES, ENTITY_HAMMER)),
+        EntityName::SpikeTrap => Some((MAP_LAYER_ENTITIES, ENTITY_SPIKE_TRAP)),
+        EntityName::Tripwire => Some((MAP_LAYER_ENTITIES, ENTITY_TRIPWIRE)),
+        EntityName::NetTrap => Some((MAP_LAYER_ENTITIES, ENTITY_NET_TRAP)),
+        EntityName::BladeTrap => Some((MAP_LAYER_ENTITIES, ENTITY_BLADE_TRAP)),
+        EntityName::PressurePlate => Some((MAP_LAYER_ENTITIES, ENTITY_PRESSURE_PLATE)),
+        EntityName::Lever => Some((MAP_LAYER_ENTITIES, ENTITY_LEVER_UP)),
+        EntityName::Gate => Some((MAP_LAYER_ENTITIES, ENTITY_GATE_CLOSED)),
+        EntityName::Chest => Some((MAP_LAYER_ENTITIES, ENTITY_CHEST)),
+        EntityName::Crate => Some((MAP_LAYER_ENTITIES, ENTITY_CRATE)),
+        EntityName::Alarm => Some((MAP_LAYER_ENTITIES, ENTITY_ALARM)),
+        EntityName::Rat => Some((MAP_LAYER_ENTITIES, ENTITY_RAT)),
+        EntityName::Bird => Some((MAP_LAYER_ENTITIES, ENTITY_BIRD)),
+        _ => None,
+    }
+}
+
+fn xp_cell(xp: &mut XpFile, layer: usize, pos: Pos) -> &mut XpCell {
+    let cell = xp.layers[layer].get_mut(pos.x as usize, pos.y as usize).unwrap();
+    cell.fg = XpColor::new(255, 255, 255);
+    return cell;
+}
+
+#[test]
+fn test_write_map_xp() {
+    let config = Config::from_file("../config.yaml");
+    let mut msg_log = MsgLog::new();
+
+    let mut data = GameData::new(Map::from_dims(6, 5), Entities::new());
+    data.map[(1, 1)] = Tile::water();
+    data.map[(2, 1)] = Tile::grass();
+    data.map[(3, 1)].left_wall = Wall::ShortWall;
+    data.map[(3, 2)].bottom_wall = Wall::TallWall;
+    data.map[(4, 3)] = Tile::wall();
+    data.map[(1, 3)] = Tile::door(DoorState::Closed);
+    data.map[(1, 4)] = Tile::door(DoorState::Locked);
+    data.map[(5, 0)].chr = MAP_STATUE_1;
+    data.map[(5, 0)].block_move = true;
+    data.map[(4, 0)].chr = MAP_EMPTY_CHAR;
+    let player_id = make_player(&mut data.entities, &config, &mut msg_log);
+    data.entities.pos[&player_id] = Pos::new(2, 3);
+    make_gol(&mut data.entities, &config, Pos::new(5, 4), &mut msg_log);
+    make_column(&mut data.entities, &config, Pos::new(0, 4), &mut msg_log);
+    make_pressure_plate(&mut data.entities, &config, Pos::new(0, 0), &mut msg_log);
+    data.map[(0, 0)].link = 2;
+    data.map[(1, 3)].link = 2;
+
+    let file_name = std::env::temp_dir().join("test_write_map_xp.xp");
+    let file_name = file_name.to_str().unwrap();
+    write_map_xp(&data, file_name).unwrap();
+
+    let mut read_data = GameData::empty(0, 0);
+    let player_pos = read_map_xp(&config, &mut read_data, &mut msg_log, file_name).unwrap();
+
+    assert_eq!((2, 3), player_pos);
+    assert_eq!(data.map.size(), read_data.map.size());
+    assert_eq!(TileType::Water, read_data.map[(1, 1)].tile_type);
+    assert_eq!(Surface::Grass, read_data.map[(2, 1)].surface);
+    assert_eq!(Wall::ShortWall, read_data.map[(3, 1)].left_wall);
+    assert_eq!(Wall::TallWall, read_data.map[(3, 2)].bottom_wall);
+    assert!(read_data.map[(4, 3)].block_move);
+    assert!(!read_data.map[(4, 4)].block_move);
+    assert_eq!(MAP_STATUE_1, read_data.map[(5, 0)].chr);
+    assert!(read_data.map[(5, 0)].block_sight);
+    assert_eq!(MAP_EMPTY_CHAR, read_data.map[(4, 0)].chr);
+    assert_eq!(Some(DoorState::Closed), read_data.map[(1, 3)].door_state());
+    assert_eq!(Some(DoorState::Locked), read_data.map[(1, 4)].door_state());
+    assert_eq!(Some(EntityName::Gol), read_data.has_entity(Pos::new(5, 4)).map(|id| read_data.entities.name[&id]));
+    assert_eq!(Some(EntityName::Column), read_data.has_entity(Pos::new(0, 4)).map(|id| read_data.entities.name[&id]));
+    assert_eq!(Some(EntityName::PressurePlate), read_data.has_entity(Pos::new(0, 0)).map(|id| read_data.entities.name[&id]));
+    assert_eq!(vec!(Pos::new(0, 0), Pos::new(1, 3)), read_data.map.linked_positions(2));
+}
+
+/// A REXPaint file with random layers, which may not match each other, filled with
+/// characters map files use and ones they don't.
+#[cfg(test)]
+fn xp_file() -> impl Strategy<Value = XpFile> {
+    let chr = prop_oneof![Just(0u32), 0u32..256, any::<u32>()];
+    let layer = (0usize..6, 0usize..6, prop::collection::vec(chr, 36)).prop_map(|(width, height, chrs)| {
+        let mut layer = XpLayer::new(width, height);
+        for (cell, chr) in layer.cells.iter_mut().zip(chrs) {
+            cell.ch = chr;
+        }
+        return layer;
+    });
+
+    return prop::collection::vec(layer, 0..6).prop_map(|layers| XpFile { version: -1, layers });
+}
+
+#[cfg(test)]
+proptest! {
+    #[test]
+    fn fuzz_read_map_xp(xp in xp_file(), cut in any::<prop::sample::Index>(), truncate in any::<bool>()) {
+        let config = Config::from_file("../config.yaml");
+        let mut msg_log = MsgLog::new();
+
+        let mut bytes = Vec::new();
+        xp.write(&mut bytes).unwrap();
+        if truncate {
+            bytes.truncate(cut.index(bytes.len()));
+        }
+
+        // malformed maps are errors rather than panics
+        let mut data = GameData::empty(0, 0);
+        let _ = read_map_xp_bytes(&config, &mut data, &mut msg_log, &bytes);
+    }
+
+    #[test]
+    fn fuzz_read_map_xp_header(words in prop::collection::vec(any::<u32>(), 0..8), body in prop::collection::vec(any::<u8>(), 0..64)) {
+        let config = Config::from_file("../config.yaml");
+        let mut msg_log = MsgLog::new();
+
+        // a header can claim any number of layers of any size, whatever follows it
+        let mut contents = words.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect::<Vec<u8>>();
+        contents.extend(body);
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(&contents).unwrap();
+        let bytes = encoder.finish().unwrap();
+
+        let mut data = GameData::empty(0, 0);
+        let _ = read_map_xp_bytes(&config, &mut data, &mut msg_log, &bytes);
+    }
+}
+
+#[test]
+fn test_read_map_xp_errors() {
+    let config = Config::from_file("../config.yaml");
+    let mut msg_log = MsgLog::new();
+    let mut data = GameData::empty(0, 0);
+
+    assert!(read_map_xp_bytes(&config, &mut data, &mut msg_log, &[]).is_err());
+
+    // layers have to be the same size
+    let mut xp = XpFile::new(3, 3);
+    xp.layers.push(XpLayer::new(4, 3));
+    let mut bytes = Vec::new();
+    xp.write(&mut bytes).unwrap();
+    assert!(read_map_xp_bytes(&config, &mut data, &mut msg_log, &bytes).is_err());
+
+    // walls along the edge of the map which would set the next tile over are kept on the map
+    let mut xp = XpFile::new(2, 2);
+    xp.layers.push(XpLayer::new(2, 2));
+    xp.layers[MAP_LAYER_ENVIRONMENT].cells[0].ch = MAP_THIN_WALL_TOP as u32;
+    xp.layers[MAP_LAYER_ENVIRONMENT].cells[3].ch = MAP_THICK_WALL_RIGHT as u32;
+    let mut bytes = Vec::new();
+    xp.write(&mut bytes).unwrap();
+    assert_eq!(Ok((0, 0)), read_map_xp_bytes(&config, &mut data, &mut msg_log, &bytes));
+
+    // a layer claiming more cells than the file holds
+    let mut xp = XpFile::new(1, 1);
+    xp.layers[0].width = 100000;
+    let mut bytes = Vec::new();
+    xp.write(&mut bytes).unwrap();
+    assert!(read_map_xp_bytes(&config, &mut data, &mut msg_log, &bytes).is_err());
+
+    // an unknown character in the ground layer
+    let mut xp = XpFile::new(2, 2);
+    xp.layers[MAP_LAYER_GROUND].cells[1].ch = MAP_COLUMN as u32;
+    let mut bytes = Vec::new();
+    xp.write(&mut bytes).unwrap();
+    assert!(read_map_xp_bytes(&config, &mut data, &mut msg_log, &bytes).is_err());
+
+    // the maps shipped with the game are all read without errors
+    for file in std::fs::read_dir("../resources").unwrap() {
+        let path = file.unwrap().path();
+        if path.extension().map_or(false, |ext| ext == "xp") {
+            let mut data = GameData::empty(0, 0);
+            let bytes = std::fs::read(&path).unwrap();
+            assert!(read_map_xp_bytes(&config, &mut data, &mut msg_log, &bytes).is_ok(), "{:?} could not be read", path);
+        }
+    }
 }
 
 /// Read file into a vector of lines
-pub fn parse_map_file(file_name: &str) -> Vec<String> {
+pub fn parse_map_file(file_name: &str) -> GameResult<Vec<String>> {
     let file_contents =
-        std::fs::read_to_string(file_name).expect(&format!("Could not read {}", file_name));
-    return file_contents.lines().map(|s| s.to_string()).collect::<Vec<String>>();
+        std::fs::read_to_string(file_name).map_err(|err| GameError::read(file_name, err))?;
+    return Ok(file_contents.lines().map(|s| s.to_string()).collect::<Vec<String>>());
 }
 
@@ -0,0 +1,130 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+use roguelike_core::config::Config;
+use roguelike_core::map::MapLoadConfig;
+use roguelike_core::locale::DEFAULT_LOCALE;
+
+
+pub const MANIFEST_NAME: &str = "resources/manifest.yaml";
+
+/// The files the game needs in order to start. These are checked before anything
+/// is loaded, so every missing file is reported at once rather than the game
+/// stopping at the first one it tries to load.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AssetManifest {
+    pub textures: Vec<String>,
+    pub fonts: Vec<String>,
+    pub files: Vec<String>,
+    pub directories: Vec<String>,
+}
+
+impl AssetManifest {
+    pub fn from_str(manifest_string: &str) -> Result<AssetManifest, String> {
+        return serde_yaml::from_str(manifest_string).map_err(|err| err.to_string());
+    }
+
+    pub fn from_file(file_name: &str) -> Result<AssetManifest, String> {
+        let mut file =
+            File::open(file_name).map_err(|err| format!("Could not open manifest {}: {}", file_name, err))?;
+        let mut manifest_string = String::new();
+        file.read_to_string(&mut manifest_string)
+            .map_err(|err| format!("Could not read contents of {}: {}", file_name, err))?;
+
+        return AssetManifest::from_str(&manifest_string).map_err(|err| format!("{}: {}", file_name, err));
+    }
+
+    /// Each file and directory needed, along with what kind of asset it is. Along with
+    /// the manifest's own list, this includes the files named in the config.
+    pub fn required(&self, config: &Config) -> Vec<(&'static str, String)> {
+        let mut required = Vec::new();
+
+        required.extend(self.textures.iter().map(|path| ("texture", path.clone())));
+        required.extend(self.fonts.iter().map(|path| ("font", path.clone())));
+        required.push(("font", format!("resources/{}", config.text_font)));
+        required.extend(self.files.iter().map(|path| ("file", path.clone())));
+
+        if config.locale != DEFAULT_LOCALE {
+            required.push(("locale", format!("resources/locale/{}.yaml", config.locale)));
+        }
+
+        match &config.map_load {
+            MapLoadConfig::FromFile(file_name) | MapLoadConfig::Tiled(file_name) |
+            MapLoadConfig::Text(file_name) | MapLoadConfig::VaultFile(file_name) => {
+                required.push(("map", format!("resources/{}", file_name)));
+            }
+
+            MapLoadConfig::ProcGen(file_name) | MapLoadConfig::Rooms(file_name) | MapLoadConfig::Caves(file_name) => {
+                required.push(("map", format!("resources/procgen/{}", file_name)));
+            }
+
+            _ => {
+            }
+        }
+
+        required.extend(self.directories.iter().map(|path| ("directory", path.clone())));
+
+        return required;
+    }
+
+    /// A line for each required file or directory which does not exist.
+    pub fn missing(&self, config: &Config) -> Vec<String> {
+        let mut missing = Vec::new();
+
+        for (kind, path) in self.required(config) {
+            let exists =
+                if kind == "directory" {
+                    Path::new(&path).is_dir()
+                } else {
+                    Path::new(&path).is_file()
+                };
+
+            if !exists {
+                missing.push(format!("{} {}", kind, path));
+            }
+        }
+
+        return missing;
+    }
+}
+
+/// Check the config and every asset in the manifest before starting the game, returning
+/// the config if everything is in place, or a report of every problem found.
+pub fn preflight(manifest_file: &str, config_file: &str) -> Result<Config, String> {
+    let config = Config::load(config_file)?;
+    let manifest = AssetManifest::from_file(manifest_file)?;
+
+    let missing = manifest.missing(&config);
+    if missing.len() > 0 {
+        let mut report = format!("Could not start- {} missing assets:", missing.len());
+        for line in missing {
+            report.push_str(&format!("\n    {}", line));
+        }
+        return Err(report);
+    }
+
+    return Ok(config);
+}
+
+#[test]
+pub fn test_asset_manifest() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+
+    // the game's own manifest should be complete
+    let manifest = AssetManifest::from_file(&format!("../{}", MANIFEST_NAME)).unwrap();
+    let found = manifest.required(&config).iter().all(|(_kind, path)| Path::new(&format!("../{}", path)).exists());
+    assert!(found);
+
+    // every missing file is reported, not just the first
+    let manifest = AssetManifest::from_str("textures: [not_a_texture.png]\nfonts: []\nfiles: [not_a_file.yaml]\ndirectories: [src]\n").unwrap();
+    config.map_load = MapLoadConfig::FromFile("not_a_map.txt".to_string());
+    let missing = manifest.missing(&config);
+    assert!(missing.contains(&"texture not_a_texture.png".to_string()));
+    assert!(missing.contains(&"file not_a_file.yaml".to_string()));
+    assert!(missing.contains(&"map resources/not_a_map.txt".to_string()));
+    assert!(!missing.iter().any(|line| line.starts_with("directory")));
+}
@@ -0,0 +1,153 @@
+use std::fmt;
+use std::ops::Range;
+use std::collections::BTreeMap;
+
+use roguelike_core::types::*;
+use roguelike_core::map::*;
+
+use crate::game::Game;
+use crate::make_map::make_map;
+use crate::error::GameResult;
+use crate::procgen::{StructureType, find_structures, validate_map};
+
+
+/// Measurements of one generated map, used to tune the map generator.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapStats {
+    pub open_fraction: f32,
+    pub structures: BTreeMap<StructureType, usize>,
+    pub vaults: usize,
+    pub key_path: Option<usize>,  // moves from the player to the key, if there is a path
+    pub exit_path: Option<usize>, // moves from the key to the exit, if there is a path
+    pub passed: bool,
+}
+
+pub fn map_stats(game: &Game) -> MapStats {
+    let map = &game.data.map;
+
+    let num_tiles = map.get_all_pos().len();
+    let num_open = map.get_all_pos().iter().filter(|pos| !map[**pos].block_move).count();
+    let open_fraction = if num_tiles > 0 { num_open as f32 / num_tiles as f32 } else { 0.0 };
+
+    let mut structures = BTreeMap::new();
+    for structure in find_structures(map) {
+        *structures.entry(structure.typ).or_insert(0) += 1;
+    }
+
+    let path_length = |start: Pos, end: Pos| {
+        let path = astar_path(map, start, end, None, None);
+        return if path.len() > 0 { Some(path.len() - 1) } else { None };
+    };
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = game.data.entities.pos[&player_id];
+
+    let key_pos = game.data.find_by_name(EntityName::Key).map(|key_id| game.data.entities.pos[&key_id]);
+    let exit_pos = game.data.find_by_name(EntityName::Exit).map(|exit_id| game.data.entities.pos[&exit_id]);
+    let key_path = key_pos.and_then(|key_pos| path_length(player_pos, key_pos));
+    let exit_path = key_pos.zip(exit_pos).and_then(|(key_pos, exit_pos)| path_length(key_pos, exit_pos));
+
+    let passed = validate_map(game).passed(&game.config);
+
+    return MapStats { open_fraction, structures, vaults: game.vaults_placed, key_path, exit_path, passed };
+}
+
+/// The statistics of a number of maps generated one after another, along with
+/// how many attempts it took to generate them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapgenSummary {
+    pub generator: String,
+    pub maps: Vec<MapStats>,
+    pub attempts: usize,
+}
+
+/// Generate a map for each seed in a range, without displaying them, measuring each one.
+/// The given game provides the config and vaults to generate with.
+pub fn mapgen_stats(game: &Game, map_load_config: &MapLoadConfig, seeds: Range<u64>) -> GameResult<MapgenSummary> {
+    let mut summary = MapgenSummary { generator: map_load_config.to_string(), maps: Vec::new(), attempts: 0 };
+
+    for seed in seeds {
+        let mut map_game = Game::new(seed, game.config.clone());
+        map_game.vaults = game.vaults.clone();
+
+        make_map(map_load_config, &mut map_game)?;
+
+        summary.attempts += 1 + map_game.map_failures.values().sum::<usize>();
+        summary.maps.push(map_stats(&map_game));
+    }
+
+    return Ok(summary);
+}
+
+/// A line giving the smallest, average, and largest of a set of values.
+fn distribution(name: &str, values: &Vec<f32>) -> String {
+    if values.len() == 0 {
+        return format!("{}: none", name);
+    }
+
+    let min = values.iter().cloned().fold(f32::MAX, f32::min);
+    let max = values.iter().cloned().fold(f32::MIN, f32::max);
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    return format!("{}: min {:.1}, mean {:.1}, max {:.1}", name, min, mean, max);
+}
+
+impl fmt::Display for MapgenSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} maps from {}", self.maps.len(), self.generator)?;
+
+        let open = self.maps.iter().map(|stats| stats.open_fraction * 100.0).collect();
+        writeln!(f, "{}", distribution("open tile %", &open))?;
+
+        for typ in [StructureType::Single, StructureType::Line, StructureType::Path, StructureType::Complex].iter() {
+            let counts = self.maps.iter().map(|stats| *stats.structures.get(typ).unwrap_or(&0) as f32).collect();
+            writeln!(f, "{}", distribution(&format!("{:?} structures", typ), &counts))?;
+        }
+
+        let vaults = self.maps.iter().map(|stats| stats.vaults as f32).collect();
+        writeln!(f, "{}", distribution("vaults placed", &vaults))?;
+
+        let key_paths: Vec<f32> = self.maps.iter().filter_map(|stats| stats.key_path.map(|len| len as f32)).collect();
+        writeln!(f, "{} ({} maps without one)", distribution("key path length", &key_paths), self.maps.len() - key_paths.len())?;
+
+        let exit_paths: Vec<f32> = self.maps.iter().filter_map(|stats| stats.exit_path.map(|len| len as f32)).collect();
+        writeln!(f, "{} ({} maps without one)", distribution("exit path length", &exit_paths), self.maps.len() - exit_paths.len())?;
+
+        let failures = self.attempts - self.maps.len();
+        let failure_percent = if self.attempts > 0 { 100.0 * failures as f32 / self.attempts as f32 } else { 0.0 };
+        let failed_maps = self.maps.iter().filter(|stats| !stats.passed).count();
+        write!(f, "validation failures: {} of {} attempts ({:.1}%), {} maps used despite failing",
+               failures, self.attempts, failure_percent, failed_maps)
+    }
+}
+
+#[test]
+fn test_map_stats() {
+    use roguelike_core::config::Config;
+    use roguelike_core::messaging::MsgLog;
+    use crate::generation::{make_key, make_exit};
+
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.set_pos(player_id, Pos::new(0, 0));
+
+    let mut msg_log = MsgLog::new();
+    make_key(&mut game.data.entities, &config, Pos::new(3, 0), &mut msg_log);
+    make_exit(&mut game.data.entities, &config, Pos::new(3, 4), &mut msg_log);
+    game.data.map[(5, 5)] = Tile::wall();
+
+    let stats = map_stats(&game);
+    assert_eq!(Some(3), stats.key_path);
+    assert_eq!(Some(4), stats.exit_path);
+    assert_eq!(Some(&1), stats.structures.get(&StructureType::Single));
+    assert_eq!(0.99, stats.open_fraction);
+
+    // maps which are not generated are never regenerated, so each seed takes one attempt
+    let summary = mapgen_stats(&game, &MapLoadConfig::Empty, 0..3).unwrap();
+    assert_eq!(3, summary.maps.len());
+    assert_eq!(3, summary.attempts);
+    assert!(summary.to_string().contains("validation failures: 0 of 3 attempts"));
+}
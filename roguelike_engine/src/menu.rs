@@ -0,0 +1,183 @@
+use roguelike_core::types::*;
+use roguelike_core::constants::{MAX_VOLUME, MAX_SEED_DIGITS};
+
+use crate::game::Game;
+use crate::actions::{InputAction, change_state};
+use crate::prompt::confirm;
+
+
+/// The choices on the main menu, in the order they are listed, as locale keys.
+pub const MAIN_MENU_ITEMS: &[&str] = &["menu.new_run", "menu.new_run_with_seed", "menu.next_difficulty", "menu.options", "menu.quit"];
+
+/// The choices on the pause menu, in the order they are listed, as locale keys.
+pub const PAUSE_MENU_ITEMS: &[&str] = &["menu.resume", "menu.options", "menu.main_menu", "menu.quit"];
+
+/// The choices on the options screen, in the order they are listed, as locale keys.
+pub const OPTIONS_ITEMS: &[&str] = &["menu.volume_up", "menu.volume_down", "menu.next_overlay", "menu.toggle_map", "menu.next_palette"];
+
+/// The choices on the win and lose screens, in the order they are listed, as locale keys.
+pub const GAME_OVER_ITEMS: &[&str] = &["menu.restart_seed", "menu.new_run", "menu.main_menu", "menu.quit"];
+
+/// Handle input on the main menu, pause menu, options screen, and the ends of levels and runs.
+/// Unlike other menus, these can start a new run or level, so they change the game directly.
+pub fn handle_input_menu(input_action: InputAction, game: &mut Game) {
+    match game.settings.state {
+        GameState::MainMenu => handle_input_main_menu(input_action, game),
+        GameState::Paused => handle_input_paused(input_action, game),
+        GameState::Options => handle_input_options(input_action, game),
+        GameState::LevelComplete => handle_input_level_complete(input_action, game),
+        GameState::Win | GameState::Lose => handle_input_game_over(input_action, game),
+        _ => {},
+    }
+}
+
+fn handle_input_main_menu(input_action: InputAction, game: &mut Game) {
+    // while a seed is being typed, digits are part of the seed rather than menu choices
+    if let Some(seed_entry) = &mut game.settings.seed_entry {
+        match input_action {
+            InputAction::SelectItem(digit) => {
+                if seed_entry.len() < MAX_SEED_DIGITS {
+                    seed_entry.push_str(&digit.to_string());
+                }
+            }
+
+            InputAction::Confirm => {
+                if let Ok(seed) = seed_entry.parse::<u64>() {
+                    game.new_run(seed);
+                }
+            }
+
+            InputAction::Esc => {
+                game.settings.seed_entry = None;
+            }
+
+            _ => {
+            }
+        }
+        return;
+    }
+
+    match input_action {
+        InputAction::SelectItem(0) => {
+            let seed = game.rng.rand_u32() as u64;
+            game.new_run(seed);
+        }
+
+        InputAction::SelectItem(1) => {
+            game.settings.seed_entry = Some(String::new());
+        }
+
+        InputAction::SelectItem(2) => {
+            let next = game.settings.difficulty.next();
+            game.set_difficulty(next);
+        }
+
+        InputAction::SelectItem(3) => {
+            game.settings.return_state = GameState::MainMenu;
+            change_state(&mut game.settings, GameState::Options);
+        }
+
+        InputAction::SelectItem(4) => {
+            change_state(&mut game.settings, GameState::Exit);
+        }
+
+        _ => {
+        }
+    }
+}
+
+fn handle_input_paused(input_action: InputAction, game: &mut Game) {
+    match input_action {
+        InputAction::SelectItem(0) | InputAction::Esc => {
+            change_state(&mut game.settings, GameState::Playing);
+        }
+
+        InputAction::SelectItem(1) => {
+            game.settings.return_state = GameState::Paused;
+            change_state(&mut game.settings, GameState::Options);
+        }
+
+        InputAction::SelectItem(2) => {
+            change_state(&mut game.settings, GameState::MainMenu);
+        }
+
+        InputAction::SelectItem(3) => {
+            confirm(&mut game.settings, "Quit the game? This run will be lost.", InputAction::ForceExit);
+        }
+
+        _ => {
+        }
+    }
+}
+
+fn handle_input_level_complete(input_action: InputAction, game: &mut Game) {
+    match input_action {
+        InputAction::Confirm | InputAction::Esc => {
+            game.leave_level_complete();
+        }
+
+        _ => {
+        }
+    }
+}
+
+/// The win and lose screens show how the run went, and let the player play the same
+/// seed again, start a new one, or stop.
+fn handle_input_game_over(input_action: InputAction, game: &mut Game) {
+    match input_action {
+        InputAction::SelectItem(0) => {
+            let seed = game.settings.seed;
+            game.new_run(seed);
+        }
+
+        InputAction::SelectItem(1) => {
+            let seed = game.rng.rand_u32() as u64;
+            game.new_run(seed);
+        }
+
+        InputAction::SelectItem(2) | InputAction::Esc => {
+            change_state(&mut game.settings, GameState::MainMenu);
+        }
+
+        // the run has already ended, so there is nothing to lose by quitting
+        InputAction::SelectItem(3) => {
+            change_state(&mut game.settings, GameState::Exit);
+        }
+
+        _ => {
+        }
+    }
+}
+
+fn handle_input_options(input_action: InputAction, game: &mut Game) {
+    match input_action {
+        InputAction::SelectItem(0) => {
+            game.settings.volume = std::cmp::min(game.settings.volume + 1, MAX_VOLUME);
+        }
+
+        InputAction::SelectItem(1) => {
+            game.settings.volume = game.settings.volume.saturating_sub(1);
+        }
+
+        InputAction::SelectItem(2) => {
+            game.settings.overlays = game.settings.overlays.next();
+        }
+
+        InputAction::SelectItem(3) => {
+            game.settings.render_map = !game.settings.render_map;
+        }
+
+        InputAction::SelectItem(4) => {
+            let next = (game.settings.color_palette + 1) % game.color_palettes().len();
+            game.set_color_palette(next);
+        }
+
+        InputAction::Esc => {
+            let return_state = game.settings.return_state;
+            change_state(&mut game.settings, return_state);
+        }
+
+        _ => {
+        }
+    }
+}
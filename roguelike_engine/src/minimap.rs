@@ -0,0 +1,65 @@
+use roguelike_core::types::*;
+use roguelike_core::map::TileType;
+
+
+/// What the minimap shows on a tile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MinimapCell {
+    Floor,
+    Wall,
+    Water,
+    Exit,
+    Key,
+    Player,
+}
+
+/// The cells of the minimap, one per tile. Only explored tiles are shown, and the
+/// exit and key only once the tile they are on has been explored. Markers come
+/// after the terrain under them, so they are drawn on top.
+pub fn minimap_cells(data: &GameData) -> Vec<(Pos, MinimapCell)> {
+    let mut cells = Vec::new();
+
+    let (width, height) = data.map.size();
+    for y in 0..height {
+        for x in 0..width {
+            let pos = Pos::new(x, y);
+            let tile = data.map[pos];
+            if !tile.explored {
+                continue;
+            }
+
+            let cell =
+                if tile.tile_type == TileType::Exit {
+                    MinimapCell::Exit
+                } else if tile.tile_type == TileType::Water {
+                    MinimapCell::Water
+                } else if tile.block_move {
+                    MinimapCell::Wall
+                } else {
+                    MinimapCell::Floor
+                };
+            cells.push((pos, cell));
+        }
+    }
+
+    for entity_id in data.entities.ids.iter() {
+        let pos = data.entities.pos[entity_id];
+        if !data.map.is_within_bounds(pos) || !data.map[pos].explored {
+            continue;
+        }
+
+        match data.entities.name[entity_id] {
+            EntityName::Exit => cells.push((pos, MinimapCell::Exit)),
+            EntityName::Key => cells.push((pos, MinimapCell::Key)),
+            _ => {},
+        }
+    }
+
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = data.entities.pos[&player_id];
+    if data.map.is_within_bounds(player_pos) {
+        cells.push((player_pos, MinimapCell::Player));
+    }
+
+    return cells;
+}
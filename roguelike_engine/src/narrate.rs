@@ -0,0 +1,246 @@
+use roguelike_core::types::*;
+
+use crate::game::Game;
+use crate::menu::{MAIN_MENU_ITEMS, PAUSE_MENU_ITEMS, OPTIONS_ITEMS, GAME_OVER_ITEMS};
+use crate::help::help_lines;
+use crate::container;
+use crate::shop;
+use crate::selection::SelectionAction;
+
+
+/// Describes the game as lines of text, for screen readers. Each call to narrate
+/// gives only what changed since the last call, so the lines can be read out as
+/// they come: messages from the turn, a new game state and its menu, the player's
+/// movement, and entities coming into or going out of view.
+///
+/// Every line starts with a word saying what kind of line it is- msg, state, menu,
+/// moved, see, lost, or moves- followed by its details.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Narrator {
+    pub state: Option<GameState>,
+    pub menu: Vec<String>,
+    pub player_pos: Option<Pos>,
+    pub visible: Vec<(EntityId, Pos)>,
+}
+
+impl Narrator {
+    pub fn new() -> Narrator {
+        return Narrator::default();
+    }
+
+    pub fn narrate(&mut self, game: &Game) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for msg in game.msg_log.turn_messages.iter() {
+            let msg_line = msg.msg_line(&game.data, &game.locale);
+            if msg_line.len() > 0 {
+                lines.push(format!("msg {}", msg_line));
+            }
+        }
+
+        if self.state != Some(game.settings.state) {
+            self.state = Some(game.settings.state);
+            lines.push(format!("state {}", game.settings.state));
+        }
+
+        // menus are read out again whenever anything on them changes
+        let menu = menu_lines(game);
+        if menu != self.menu {
+            for line in menu.iter() {
+                lines.push(format!("menu {}", line));
+            }
+            self.menu = menu;
+        }
+
+        if game.settings.state == GameState::Playing {
+            lines.extend(self.narrate_map(game));
+        }
+
+        return lines;
+    }
+
+    fn narrate_map(&mut self, game: &Game) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+        let player_pos = game.data.entities.pos[&player_id];
+
+        if let Some(last_pos) = self.player_pos {
+            if last_pos != player_pos {
+                lines.push(format!("moved {} {} to {} {}",
+                                   player_pos.x - last_pos.x,
+                                   player_pos.y - last_pos.y,
+                                   player_pos.x,
+                                   player_pos.y));
+            }
+        }
+        self.player_pos = Some(player_pos);
+
+        let visible = game.data.entities.ids.iter()
+                                            .filter(|id| **id != player_id)
+                                            .filter(|id| game.data.map.is_within_bounds(game.data.entities.pos[*id]))
+                                            .filter(|id| game.data.is_in_fov(player_id, **id, &game.config))
+                                            .map(|id| (*id, game.data.entities.pos[id]))
+                                            .collect::<Vec<(EntityId, Pos)>>();
+
+        for (entity_id, pos) in visible.iter() {
+            let name = game.data.entities.name[entity_id];
+            let offset = (pos.x - player_pos.x, pos.y - player_pos.y);
+
+            match self.visible.iter().find(|(other_id, _pos)| other_id == entity_id) {
+                None => {
+                    lines.push(format!("see {} at {} {}, offset {} {}", name, pos.x, pos.y, offset.0, offset.1));
+                }
+
+                Some((_entity_id, last_pos)) if last_pos != pos => {
+                    lines.push(format!("moves {} to {} {}, offset {} {}", name, pos.x, pos.y, offset.0, offset.1));
+                }
+
+                _ => {
+                }
+            }
+        }
+
+        for (entity_id, _pos) in self.visible.iter() {
+            if !visible.iter().any(|(other_id, _pos)| other_id == entity_id) {
+                if let Some(name) = game.data.entities.name.get(entity_id) {
+                    lines.push(format!("lost {}", name));
+                }
+            }
+        }
+        self.visible = visible;
+
+        return lines;
+    }
+}
+
+/// The choices on the screen for the current game state, numbered as they are chosen.
+pub fn menu_lines(game: &Game) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    match game.settings.state {
+        GameState::MainMenu => {
+            if let Some(seed_entry) = &game.settings.seed_entry {
+                lines.push(format!("seed {}", seed_entry));
+            } else {
+                lines.extend(numbered(MAIN_MENU_ITEMS.iter().map(|item| game.locale.text(item))));
+                lines.push(format!("difficulty {}", game.settings.difficulty));
+            }
+        }
+
+        GameState::Paused => {
+            lines.extend(numbered(PAUSE_MENU_ITEMS.iter().map(|item| game.locale.text(item))));
+        }
+
+        GameState::Options => {
+            lines.extend(numbered(OPTIONS_ITEMS.iter().map(|item| game.locale.text(item))));
+            lines.push(format!("volume {}", game.settings.volume));
+            lines.push(format!("overlay {}", game.settings.overlays));
+            lines.push(format!("map {}", if game.settings.render_map { "on" } else { "off" }));
+        }
+
+        GameState::Help => {
+            lines.extend(help_lines(&game.config).into_iter().filter(|line| line.len() > 0));
+        }
+
+        GameState::Error => {
+            if let Some(error) = &game.settings.error {
+                lines.push(error.to_string());
+            }
+        }
+
+        GameState::Prompt => {
+            if let Some(prompt) = &game.settings.prompt {
+                lines.push(prompt.question.clone());
+                lines.extend(numbered(prompt.options.iter().map(|(text, _action)| text.clone())));
+            }
+        }
+
+        GameState::Dialogue => {
+            if let Some(conversation) = game.settings.conversation {
+                let node = &game.dialogue[conversation.node];
+                lines.push(node.text.clone());
+                lines.extend(numbered(node.responses.iter().map(|response| response.text.clone())));
+            }
+        }
+
+        GameState::Container => {
+            if let Some(container_id) = game.settings.container {
+                lines.push(game.locale.name(game.data.entities.name[&container_id]));
+                lines.extend(numbered(container::transfer_options(container_id, game).into_iter()));
+            }
+        }
+
+        GameState::Shop => {
+            let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+            let coins = game.data.entities.coins.get(&player_id).map_or(0, |coins| *coins);
+            lines.push(format!("shop, {} coins", coins));
+            lines.extend(numbered(shop::shop_options(game).into_iter()));
+        }
+
+        GameState::Selection => {
+            if let Some(SelectionAction::PlaceItem(item_id)) = game.settings.selection.map(|selection| selection.action) {
+                lines.push(format!("put down the {}", game.locale.name(game.data.entities.name[&item_id])));
+            }
+        }
+
+        GameState::NewGame => {
+            lines.extend(numbered(game.loadouts.iter().map(|loadout| {
+                if loadout.is_available(&game.unlocks) {
+                    return format!("{} ({})", loadout.name, loadout.class);
+                } else {
+                    return format!("{} (locked)", loadout.name);
+                }
+            })));
+        }
+
+        GameState::Inventory => {
+            lines.extend(numbered(game.data.entities.inventory[&player_id].iter().map(|item_id| {
+                return game.locale.name(game.data.entities.name[item_id]);
+            })));
+            lines.push(format!("weight {} of {}", game.data.entities.carried_weight(player_id),
+                                                   game.data.entities.carry_capacity(player_id, &game.config)));
+        }
+
+        GameState::SkillMenu => {
+            lines.extend(numbered(game.data.entities.skills[&player_id].iter().map(|skill| {
+                return format!("{:?} ({} energy)", skill, skill.energy_cost());
+            })));
+        }
+
+        GameState::ClassMenu => {
+            lines.extend(numbered(EntityClass::classes().iter().map(|class| format!("{:?}", class))));
+        }
+
+        GameState::LevelComplete => {
+            if let Some(summary) = &game.settings.level_summary {
+                lines.extend(summary.lines());
+            }
+        }
+
+        GameState::Win | GameState::Lose => {
+            if let Some(cause) = &game.settings.death_cause {
+                lines.push(format!("killed by {}", cause));
+            }
+            if let Some(entry) = &game.settings.last_score {
+                lines.push(format!("score {}", entry.score));
+                lines.push(format!("difficulty {}", entry.difficulty));
+                if let Some(rank) = game.settings.rank {
+                    lines.push(format!("rank {}", rank + 1));
+                }
+            }
+            lines.extend(numbered(GAME_OVER_ITEMS.iter().map(|item| game.locale.text(item))));
+        }
+
+        _ => {
+        }
+    }
+
+    return lines;
+}
+
+fn numbered<I: Iterator<Item=String>>(items: I) -> Vec<String> {
+    return items.enumerate().map(|(index, item)| format!("{} {}", index, item)).collect();
+}
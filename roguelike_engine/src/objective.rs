@@ -0,0 +1,239 @@
+use roguelike_core::types::*;
+use roguelike_core::ai::Behavior;
+use roguelike_core::utils::{distance, distance_maximum, rng_range_u32};
+
+use crate::game::Game;
+use crate::generation::make_prisoner;
+
+
+/// Give a newly generated level one of the objectives from the config, setting up
+/// anything the objective needs. Objectives that can't be set up on this level,
+/// such as assassinating a target on a level with no enemies, fall back to KeyAndExit.
+pub fn choose_objective(game: &mut Game) {
+    game.data.objective = Objective::KeyAndExit;
+
+    if game.config.objectives.len() == 0 {
+        return;
+    }
+
+    let index = rng_range_u32(&mut game.rng, 0, game.config.objectives.len() as u32) as usize;
+    let objective_type = game.config.objectives[index];
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = game.data.entities.pos[&player_id];
+
+    match objective_type {
+        ObjectiveType::KeyAndExit => {
+        }
+
+        ObjectiveType::Rescue => {
+            // levels without a prisoner get one, away from the player
+            if game.data.find_by_name(EntityName::Prisoner).is_none() {
+                let positions = game.data.get_clear_pos()
+                                         .into_iter()
+                                         .filter(|pos| distance(player_pos, *pos) > 4)
+                                         .collect::<Vec<Pos>>();
+                if positions.len() > 0 {
+                    let index = rng_range_u32(&mut game.rng, 0, positions.len() as u32) as usize;
+                    make_prisoner(&mut game.data.entities, &game.config, positions[index], &mut game.msg_log);
+                }
+            }
+
+            if game.data.find_by_name(EntityName::Prisoner).is_some() {
+                game.data.objective = Objective::Rescue;
+            }
+        }
+
+        ObjectiveType::Assassinate => {
+            let targets = game.data.entities.ids.iter()
+                                               .filter(|id| game.data.entities.typ[*id] == EntityType::Enemy)
+                                               .filter(|id| game.data.entities.status[*id].alive)
+                                               .map(|id| *id)
+                                               .collect::<Vec<EntityId>>();
+            if targets.len() > 0 {
+                let index = rng_range_u32(&mut game.rng, 0, targets.len() as u32) as usize;
+                game.data.objective = Objective::Assassinate(targets[index]);
+            }
+        }
+
+        ObjectiveType::Steal => {
+            // only items which can't be used up are worth stealing
+            let items = game.data.entities.ids.iter()
+                                             .filter(|id| game.data.entities.item.get(*id).is_some())
+                                             .filter(|id| game.data.entities.item[*id].class() == ItemClass::Primary)
+                                             .filter(|id| !game.data.entities.inventory[&player_id].contains(*id))
+                                             .map(|id| *id)
+                                             .collect::<Vec<EntityId>>();
+            if items.len() > 0 {
+                let index = rng_range_u32(&mut game.rng, 0, items.len() as u32) as usize;
+                game.data.objective = Objective::Steal(items[index], false);
+            }
+        }
+
+        ObjectiveType::Survive => {
+            game.data.objective = Objective::Survive(game.config.survive_turns);
+        }
+    }
+}
+
+/// Keep track of anything the objective needs to know about after each turn.
+pub fn step_objective(data: &mut GameData, took_turn: bool) {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+
+    match data.objective {
+        // a target that broke while being used can no longer be stolen, so the level
+        // is finished the usual way instead
+        Objective::Steal(item_id, _) if item_lost(data, item_id) => {
+            data.objective = Objective::KeyAndExit;
+        }
+
+        Objective::Steal(item_id, false) => {
+            let noticed = data.entities.ids.iter().any(|id| {
+                data.entities.status[id].alive &&
+                data.entities.behavior.get(id) == Some(&Behavior::Attacking(player_id))
+            });
+
+            if noticed {
+                data.objective = Objective::Steal(item_id, true);
+            }
+        }
+
+        Objective::Survive(turns) => {
+            if took_turn && turns > 0 {
+                data.objective = Objective::Survive(turns - 1);
+            }
+        }
+
+        _ => {
+        }
+    }
+}
+
+pub fn objective_met(data: &GameData) -> bool {
+    match data.objective {
+        Objective::KeyAndExit => {
+            return key_and_exit_met(data);
+        }
+
+        Objective::Rescue => {
+            return key_and_exit_met(data) && prisoners_rescued(data);
+        }
+
+        Objective::Assassinate(target_id) => {
+            let target_dead = data.entities.status.get(&target_id).map_or(true, |status| !status.alive);
+            return target_dead && on_exit(data);
+        }
+
+        Objective::Steal(item_id, noticed) => {
+            // once the player is noticed they have to leave the usual way
+            if noticed || item_lost(data, item_id) {
+                return key_and_exit_met(data);
+            }
+
+            let player_id = data.find_by_name(EntityName::Player).unwrap();
+            let has_item = data.entities.inventory[&player_id].contains(&item_id);
+            return has_item && on_exit(data);
+        }
+
+        Objective::Survive(turns) => {
+            return turns == 0;
+        }
+    }
+}
+
+/// Lines describing the current objective, for the info panel.
+pub fn describe_objective(data: &GameData) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    match data.objective {
+        Objective::KeyAndExit => {
+            lines.push("Take the key".to_string());
+            lines.push("to the exit".to_string());
+        }
+
+        Objective::Rescue => {
+            let prisoners = data.entities.ids.iter()
+                                             .filter(|id| data.entities.name[*id] == EntityName::Prisoner)
+                                             .filter(|id| data.entities.status[*id].alive)
+                                             .count();
+            lines.push(format!("Rescue {} prisoner{}", prisoners, if prisoners == 1 { "" } else { "s" }));
+            lines.push("and take the key".to_string());
+            lines.push("to the exit".to_string());
+        }
+
+        Objective::Assassinate(target_id) => {
+            if data.entities.status.get(&target_id).map_or(true, |status| !status.alive) {
+                lines.push("Target killed".to_string());
+                lines.push("Go to the exit".to_string());
+            } else {
+                let pos = data.entities.pos[&target_id];
+                lines.push(format!("Kill the {:?}", data.entities.name[&target_id]));
+                lines.push(format!("at ({}, {})", pos.x, pos.y));
+                lines.push("then go to the exit".to_string());
+            }
+        }
+
+        Objective::Steal(item_id, noticed) => {
+            if noticed || item_lost(data, item_id) {
+                lines.push(if noticed { "Spotted!" } else { "Target broken" }.to_string());
+                lines.push("Take the key".to_string());
+                lines.push("to the exit".to_string());
+            } else {
+                let player_id = data.find_by_name(EntityName::Player).unwrap();
+                if let Some(name) = data.entities.name.get(&item_id) {
+                    lines.push(format!("Steal the {:?}", name));
+                }
+                if !data.entities.inventory[&player_id].contains(&item_id) {
+                    if let Some(pos) = data.entities.pos.get(&item_id) {
+                        lines.push(format!("at ({}, {})", pos.x, pos.y));
+                    }
+                }
+                lines.push("and take it to".to_string());
+                lines.push("the exit unseen".to_string());
+            }
+        }
+
+        Objective::Survive(turns) => {
+            lines.push(format!("Survive {} turns", turns));
+        }
+    }
+
+    return lines;
+}
+
+fn on_exit(data: &GameData) -> bool {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = data.entities.pos[&player_id];
+
+    if let Some(exit_id) = data.find_by_name(EntityName::Exit) {
+        return data.entities.pos[&exit_id] == player_pos;
+    }
+
+    return false;
+}
+
+/// Whether an item to be stolen is gone from the level, such as after breaking.
+fn item_lost(data: &GameData, item_id: EntityId) -> bool {
+    return !data.entities.ids.contains(&item_id) ||
+           data.entities.needs_removal.get(&item_id).map_or(true, |removal| *removal);
+}
+
+fn key_and_exit_met(data: &GameData) -> bool {
+    // loop over objects in inventory, and check whether any
+    // are the key object.
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let has_key = data.is_in_inventory(player_id, Item::Key).is_some();
+
+    return has_key && on_exit(data);
+}
+
+/// Every living prisoner on the level has to be next to the player to be rescued.
+fn prisoners_rescued(data: &GameData) -> bool {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = data.entities.pos[&player_id];
+
+    return data.entities.ids.iter()
+                            .filter(|id| data.entities.name[*id] == EntityName::Prisoner)
+                            .filter(|id| data.entities.status[*id].alive)
+                            .all(|id| distance_maximum(data.entities.pos[id], player_pos) <= 1);
+}
@@ -0,0 +1,258 @@
+use std::fmt;
+use std::str::FromStr;
+use std::collections::{HashMap, HashSet};
+
+use serde::{Serialize, Deserialize};
+
+use roguelike_core::types::*;
+use roguelike_core::config::Config;
+use roguelike_core::ai::Behavior;
+
+
+/// Extra information which can be drawn over the map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Overlay {
+    Sound,     // tiles which heard a sound recently, fading as turns pass
+    EnemyFov,  // tiles the enemies in view could attack
+    Path,      // the path from the player to the mouse
+    Selection, // where the player can move, and the tiles in their view
+    Vision,    // the tiles seen by enemies in view, shaded by how alert they are
+    Threat,    // how many enemies in view could move onto or attack each tile next turn
+    Debug,     // direction grid, player FOV, and floodfill metrics
+}
+
+impl fmt::Display for Overlay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Overlay::Sound => write!(f, "sound"),
+            Overlay::EnemyFov => write!(f, "enemyfov"),
+            Overlay::Path => write!(f, "path"),
+            Overlay::Selection => write!(f, "selection"),
+            Overlay::Vision => write!(f, "vision"),
+            Overlay::Threat => write!(f, "threat"),
+            Overlay::Debug => write!(f, "debug"),
+        }
+    }
+}
+
+impl FromStr for Overlay {
+    type Err = String;
+
+    fn from_str(original_str: &str) -> Result<Self, Self::Err> {
+        let s: &mut str = &mut original_str.to_string();
+        s.make_ascii_lowercase();
+
+        if s == "sound" {
+            return Ok(Overlay::Sound);
+        } else if s == "enemyfov" {
+            return Ok(Overlay::EnemyFov);
+        } else if s == "path" {
+            return Ok(Overlay::Path);
+        } else if s == "selection" {
+            return Ok(Overlay::Selection);
+        } else if s == "vision" {
+            return Ok(Overlay::Vision);
+        } else if s == "threat" {
+            return Ok(Overlay::Threat);
+        } else if s == "debug" {
+            return Ok(Overlay::Debug);
+        }
+
+        return Err(format!("Could not parse '{}' as Overlay", s));
+    }
+}
+
+impl Overlay {
+    pub fn overlays() -> [Overlay; 7] {
+        return [Overlay::Sound, Overlay::EnemyFov, Overlay::Path, Overlay::Selection, Overlay::Vision, Overlay::Threat, Overlay::Debug];
+    }
+
+    fn bit(self) -> u8 {
+        return 1 << Overlay::overlays().iter().position(|overlay| *overlay == self).unwrap();
+    }
+}
+
+/// A set of overlays, such as the ones currently being drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Overlays {
+    bits: u8,
+}
+
+impl fmt::Display for Overlays {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if *self == Overlays::none() {
+            return write!(f, "none");
+        } else if *self == Overlays::gameplay() {
+            return write!(f, "all");
+        }
+
+        let names = self.overlays().iter().map(|overlay| overlay.to_string()).collect::<Vec<String>>();
+        return write!(f, "{}", names.join(", "));
+    }
+}
+
+impl Overlays {
+    pub fn none() -> Overlays {
+        return Overlays::default();
+    }
+
+    pub fn only(overlay: Overlay) -> Overlays {
+        return Overlays { bits: overlay.bit() };
+    }
+
+    /// Every overlay except the debugging one.
+    pub fn gameplay() -> Overlays {
+        let mut overlays = Overlays::none();
+        for overlay in Overlay::overlays().iter() {
+            overlays.set(*overlay, *overlay != Overlay::Debug);
+        }
+        return overlays;
+    }
+
+    pub fn is_on(&self, overlay: Overlay) -> bool {
+        return self.bits & overlay.bit() != 0;
+    }
+
+    pub fn set(&mut self, overlay: Overlay, on: bool) {
+        if on {
+            self.bits |= overlay.bit();
+        } else {
+            self.bits &= !overlay.bit();
+        }
+    }
+
+    pub fn toggle(&mut self, overlay: Overlay) {
+        self.set(overlay, !self.is_on(overlay));
+    }
+
+    pub fn union(&self, other: Overlays) -> Overlays {
+        return Overlays { bits: self.bits | other.bits };
+    }
+
+    pub fn overlays(&self) -> Vec<Overlay> {
+        return Overlay::overlays().iter().filter(|overlay| self.is_on(**overlay)).map(|overlay| *overlay).collect();
+    }
+
+    /// The next set of overlays when cycling through them- from none, to each overlay
+    /// on its own, to all the gameplay overlays, and back to none.
+    pub fn next(&self) -> Overlays {
+        let cycle = Overlays::cycle();
+        if let Some(index) = cycle.iter().position(|overlays| overlays == self) {
+            return cycle[(index + 1) % cycle.len()];
+        }
+        return Overlays::none();
+    }
+
+    fn cycle() -> Vec<Overlays> {
+        let mut cycle = vec!(Overlays::none());
+        cycle.extend(Overlay::overlays().iter().map(|overlay| Overlays::only(*overlay)));
+        cycle.push(Overlays::gameplay());
+        return cycle;
+    }
+}
+
+/// How alert an enemy is, from least to most alert.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Alertness {
+    Idle,
+    Investigating,
+    Attacking,
+}
+
+impl Alertness {
+    pub fn from_behavior(behavior: Behavior) -> Alertness {
+        match behavior {
+            Behavior::Idle | Behavior::Following(_) | Behavior::Fleeing(_) => Alertness::Idle,
+            Behavior::Investigating(_) => Alertness::Investigating,
+            Behavior::Attacking(_) | Behavior::RaisingAlarm(_) => Alertness::Attacking,
+        }
+    }
+}
+
+/// The enemies the player can see which are able to act- not knocked out or frozen.
+fn active_enemies_in_view(data: &GameData, config: &Config) -> Vec<EntityId> {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+
+    return data.entities.ids.iter()
+                            .filter(|entity_id| data.entities.typ[*entity_id] == EntityType::Enemy)
+                            .filter(|entity_id| data.map.is_within_bounds(data.entities.pos[*entity_id]))
+                            .filter(|entity_id| data.entities.status[*entity_id].alive)
+                            .filter(|entity_id| data.entities.status[*entity_id].knocked_out == 0)
+                            .filter(|entity_id| data.entities.status[*entity_id].frozen == 0)
+                            .filter(|entity_id| data.is_in_fov(player_id, **entity_id, config))
+                            .map(|entity_id| *entity_id)
+                            .collect();
+}
+
+/// Every tile seen by an enemy the player can see, along with how alert the most
+/// alert enemy seeing it is. Enemies which are knocked out or frozen see nothing.
+pub fn enemy_vision(data: &GameData, config: &Config) -> HashMap<Pos, Alertness> {
+    let mut vision = HashMap::new();
+
+    for entity_id in active_enemies_in_view(data, config).iter() {
+        let pos = data.entities.pos[entity_id];
+
+        let alertness = data.entities.behavior.get(entity_id)
+                                              .map_or(Alertness::Idle, |behavior| Alertness::from_behavior(*behavior));
+
+        // nothing outside of the enemy's FOV radius can be seen, so only those tiles are checked
+        let radius = data.fov_radius(*entity_id);
+        for y in (pos.y - radius)..=(pos.y + radius) {
+            for x in (pos.x - radius)..=(pos.x + radius) {
+                let other_pos = Pos::new(x, y);
+                if !data.map.is_within_bounds(other_pos) || !data.pos_in_fov(*entity_id, other_pos, config) {
+                    continue;
+                }
+
+                let seen = vision.entry(other_pos).or_insert(alertness);
+                *seen = std::cmp::max(*seen, alertness);
+            }
+        }
+    }
+
+    return vision;
+}
+
+/// For each tile, how many enemies the player can see could move onto it or attack
+/// it on their next turn. Moves and attacks use the same clear path checks as the
+/// attack overlay, so walls and other entities block them.
+pub fn enemy_threat(data: &GameData, config: &Config) -> HashMap<Pos, usize> {
+    let mut threat = HashMap::new();
+
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = data.entities.pos[&player_id];
+
+    let traps_block = false;
+
+    for entity_id in active_enemies_in_view(data, config).iter() {
+        let pos = data.entities.pos[entity_id];
+
+        let mut threatened = HashSet::new();
+
+        if let Some(reach) = data.entities.movement.get(entity_id) {
+            for move_pos in reach.reachables(pos) {
+                if data.map.is_within_bounds(move_pos) && data.clear_path(pos, move_pos, traps_block) {
+                    threatened.insert(move_pos);
+                }
+            }
+        }
+
+        if let Some(reach) = data.entities.attack.get(entity_id) {
+            for attack_pos in reach.reachables(pos) {
+                // the player blocks the path to their own tile, so it is checked up to the player
+                if data.map.is_within_bounds(attack_pos) &&
+                   (data.clear_path(pos, attack_pos, traps_block) ||
+                    (attack_pos == player_pos && data.clear_path_up_to(pos, attack_pos, traps_block) &&
+                     data.map.path_blocked_move(pos, attack_pos).is_none())) {
+                    threatened.insert(attack_pos);
+                }
+            }
+        }
+
+        for threatened_pos in threatened {
+            *threat.entry(threatened_pos).or_insert(0) += 1;
+        }
+    }
+
+    return threat;
+}
@@ -1,6 +1,7 @@
 use std::fs::File;
-use std::io::{Read, BufReader};
-use std::collections::HashSet;
+use std::fmt;
+use std::io::BufReader;
+use std::collections::{HashSet, VecDeque};
 
 use serde::{Serialize, Deserialize};
 
@@ -8,15 +9,19 @@ use pathfinding::directed::astar::astar;
 
 use oorandom::Rand32;
 use rand::rngs::SmallRng;
-use rand::{SeedableRng};
+use rand::{Rng, SeedableRng};
+
+use rayon::prelude::*;
 
 use wfc_image::*;
+use wfc::{ForbidPattern, ForbidInterface, PatternId, Wrap};
 use image;
 use image::GenericImageView;
 
 use euclid::*;
 
 use roguelike_core::constants::*;
+use roguelike_core::config::*;
 use roguelike_core::map::*;
 use roguelike_core::types::*;
 use roguelike_core::utils::*;
@@ -25,6 +30,8 @@ use roguelike_core::line::*;
 use crate::generation::*;
 use crate::game::*;
 use crate::vault::*;
+use crate::director::*;
+use crate::error::{GameError, GameResult, read_yaml};
 
 
 #[derive(Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Debug)]
@@ -58,35 +65,41 @@ pub enum ProcCmd {
     Items(Item, usize, usize),
     MaxItems(usize),
     MaxGates(usize),
-    Traps(Trap, usize, usize),
+    Traps(TrapType, usize, usize),
     MaxTraps(usize),
     Grass((usize, usize), i32), // (min, max), disperse distance
     Rubble(usize),
     Columns(usize),
     SeedFile(String),
     Vaults(usize), // maximum number of vaults
+    SeedVaults(usize), // number of vaults placed before generating the map
+    Loot(Item, usize), // an item containers can be stocked with, and its weight
 }
 
 impl ProcCmd {
-    pub fn from_file(file_name: &str) -> Vec<ProcCmd> {
-        let mut file =
-            File::open(file_name).expect(&format!("Could not open/parse procgen file {}", file_name));
-        let mut procgen_string = String::new();
-        file.read_to_string(&mut procgen_string)
-            .expect(&format!("Could not read contents of {}", file_name));
-
-        let cmds = serde_yaml::from_str(&procgen_string).expect(&format!("Could not parse {} file!", file_name));
-        
-        return cmds;
+    pub fn from_file(file_name: &str) -> GameResult<Vec<ProcCmd>> {
+        return read_yaml(file_name);
     }
 }
 
-pub fn generate_bare_map(width: u32, height: u32, template_file: &str, rng: &mut Rand32) -> Map {
+pub fn generate_bare_map(width: u32, height: u32, template_file: &str, rng: &mut Rand32) -> GameResult<Map> {
+    return generate_bare_map_fixed(width, height, template_file, &Vec::new(), None, rng);
+}
+
+/// Generate a map with WFC, keeping the given tiles as walls (true) or open (false).
+/// If WFC can't satisfy these tiles, the map is generated without them. The image WFC
+/// generates is written to map_image_file, if one is given, for debugging.
+pub fn generate_bare_map_fixed(width: u32,
+                               height: u32,
+                               template_file: &str,
+                               fixed: &Vec<(Pos, bool)>,
+                               map_image_file: Option<&str>,
+                               rng: &mut Rand32) -> GameResult<Map> {
     let mut new_map = Map::from_dims(width, height);
 
-    let file = File::open(template_file).unwrap();
+    let file = File::open(template_file).map_err(|err| GameError::read(template_file, err))?;
     let reader = BufReader::new(file);
-    let seed_image = image::load(reader, image::ImageFormat::Png).unwrap();
+    let seed_image = image::load(reader, image::ImageFormat::Png).map_err(|err| GameError::parse(template_file, err))?;
     let orientations = [Orientation::Original,
                         Orientation::Clockwise90,
                         Orientation::Clockwise180,
@@ -98,16 +111,44 @@ pub fn generate_bare_map(width: u32, height: u32, template_file: &str, rng: &mut
 
     let seed: [u8; 32] = [rng.rand_u32() as u8; 32];
     let mut small_rng = SmallRng::from_seed(seed);
-    let map_image = 
-        wfc_image::generate_image_with_rng(&seed_image,
-                                           core::num::NonZeroU32::new(3).unwrap(),
-                                           wfc_image::Size::new(width, height),
-                                           &orientations, 
-                                           wfc_image::wrap::WrapNone,
-                                           ForbidNothing,
-                                           wfc_image::retry::NumTimes(3),
-                                           &mut small_rng).unwrap();
-    map_image.save("wfc_map.png").unwrap();
+
+    let pattern_size = 3;
+    let image_patterns = ImagePatterns::new(&seed_image, core::num::NonZeroU32::new(pattern_size).unwrap(), &orientations);
+    let forbid = ForbidFixed::new(&image_patterns, &seed_image, &orientations, pattern_size as i32, fixed);
+
+    let output_size = wfc_image::Size::new(width, height);
+
+    // a contradiction while forbidding patterns is not reported by WFC, so
+    // the resulting image is checked against the fixed tiles instead.
+    let mut fixed_image = None;
+    for _ in 0..WFC_FIXED_ATTEMPTS {
+        if let Ok(wave) = image_patterns.collapse_wave_retrying(output_size, wfc_image::wrap::WrapNone, forbid.clone(), wfc_image::retry::NumTimes(3), &mut small_rng) {
+            let image = image_patterns.image_from_wave(&wave);
+            let matches = fixed.iter().all(|(pos, is_wall)| {
+                return (image.get_pixel(pos.x as u32, pos.y as u32).0[0] == 0) == *is_wall;
+            });
+
+            if matches {
+                fixed_image = Some(image);
+                break;
+            }
+        }
+    }
+
+    let map_image;
+    if let Some(image) = fixed_image {
+        map_image = image;
+    } else {
+        eprintln!("WFC could not keep {} fixed tiles, generating without them", fixed.len());
+        let wave = image_patterns.collapse_wave_retrying(output_size, wfc_image::wrap::WrapNone, ForbidNothing, wfc_image::retry::NumTimes(3), &mut small_rng).unwrap();
+        map_image = image_patterns.image_from_wave(&wave);
+    }
+
+    if let Some(map_image_file) = map_image_file {
+        if let Err(err) = map_image.save(map_image_file) {
+            eprintln!("WFC map not written to {}- {}", map_image_file, err);
+        }
+    }
 
     for x in 0..width {
         for y in 0..height {
@@ -119,9 +160,388 @@ pub fn generate_bare_map(width: u32, height: u32, template_file: &str, rng: &mut
          }
     }
 
+    return Ok(new_map);
+}
+
+/// Forbid WFC patterns that would not produce the fixed tiles. The output pixel for
+/// a cell is the top left pixel of its pattern, so patterns are split by that pixel.
+#[derive(Clone)]
+struct ForbidFixed {
+    forbidden: Vec<(Coord, Vec<PatternId>)>,
+}
+
+impl ForbidFixed {
+    fn new(image_patterns: &ImagePatterns,
+           seed_image: &image::DynamicImage,
+           orientations: &[Orientation],
+           pattern_size: i32,
+           fixed: &Vec<(Pos, bool)>) -> ForbidFixed {
+        let (image_width, image_height) = seed_image.dimensions();
+        let last = pattern_size - 1;
+
+        let mut wall_patterns: Vec<PatternId> = Vec::new();
+        let mut open_patterns: Vec<PatternId> = Vec::new();
+
+        let id_grid = image_patterns.id_grid();
+        for x in 0..image_width as i32 {
+            for y in 0..image_height as i32 {
+                let ids = id_grid.get_checked(Coord::new(x, y));
+
+                for orientation in orientations.iter() {
+                    if let Some(pattern_id) = ids.get(*orientation) {
+                        // the image position that ends up in the top left of the oriented pattern
+                        let (offset_x, offset_y) =
+                            match orientation {
+                                Orientation::Original => (0, 0),
+                                Orientation::Clockwise90 => (0, last),
+                                Orientation::Clockwise180 => (last, last),
+                                Orientation::Clockwise270 => (last, 0),
+                                Orientation::DiagonallyFlipped => (0, 0),
+                                Orientation::DiagonallyFlippedClockwise90 => (last, 0),
+                                Orientation::DiagonallyFlippedClockwise180 => (last, last),
+                                Orientation::DiagonallyFlippedClockwise270 => (0, last),
+                            };
+                        let pixel_x = (x + offset_x) as u32 % image_width;
+                        let pixel_y = (y + offset_y) as u32 % image_height;
+
+                        if seed_image.get_pixel(pixel_x, pixel_y).0[0] == 0 {
+                            wall_patterns.push(*pattern_id);
+                        } else {
+                            open_patterns.push(*pattern_id);
+                        }
+                    }
+                }
+            }
+        }
+
+        wall_patterns.sort();
+        wall_patterns.dedup();
+        open_patterns.sort();
+        open_patterns.dedup();
+
+        let mut forbidden = Vec::new();
+        for (pos, is_wall) in fixed.iter() {
+            let coord = Coord::new(pos.x, pos.y);
+            if *is_wall {
+                forbidden.push((coord, open_patterns.clone()));
+            } else {
+                forbidden.push((coord, wall_patterns.clone()));
+            }
+        }
+
+        return ForbidFixed { forbidden };
+    }
+}
+
+impl ForbidPattern for ForbidFixed {
+    fn forbid<W: Wrap, R: Rng>(&mut self, fi: &mut ForbidInterface<W>, rng: &mut R) {
+        for (coord, pattern_ids) in self.forbidden.iter() {
+            for pattern_id in pattern_ids.iter() {
+                // contradictions are caught by checking the generated image
+                if fi.forbid_pattern(*coord, *pattern_id, rng).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Generate a WFC map around vaults placed before generation, so the generated
+/// tiles fit around them, then stamp the vaults into the map.
+pub fn generate_seeded_map(game: &mut Game, cmds: &Vec<ProcCmd>, width: u32, height: u32, template_file: &str) -> GameResult<()> {
+    let num_seeds =
+        cmds.iter().filter_map(|cmd| {
+            if let ProcCmd::SeedVaults(num) = cmd {
+                return Some(*num);
+            };
+            return None;
+    }).next().unwrap_or(0);
+
+    // choose vaults and their placements, avoiding overlaps between them
+    let mut seeds: Vec<(usize, Pos, Rotation, bool)> = Vec::new();
+    let mut seed_rects: Vec<Rect<i32, ()>> = Vec::new();
+    let mut placed: Vec<usize> = Vec::new();
+    let mut fixed: Vec<(Pos, bool)> = Vec::new();
+    for _ in 0..num_seeds {
+        let vault_index;
+        if let Some(index) = choose_vault(&game.vaults, game.settings.level_num, &placed, &mut game.rng) {
+            vault_index = index;
+        } else {
+            break;
+        }
+
+        let vault = &game.vaults[vault_index];
+        let (rotation, mirror) = choose_orientation(vault, &mut game.rng);
+        let vault_map = reorient_map(&vault.data.map, rotation, mirror);
+        let (vault_width, vault_height) = vault_map.size();
+        if vault_width >= width as i32 || vault_height >= height as i32 {
+            continue;
+        }
+
+        let x = rng_range_i32(&mut game.rng, 0, width as i32 - vault_width);
+        let y = rng_range_i32(&mut game.rng, 0, height as i32 - vault_height);
+        let offset = Pos::new(x, y);
+        let rect = Rect::new(offset, Size2D::new(vault_width, vault_height));
+        if seed_rects.iter().any(|other| rect.inflate(1, 1).intersects(other)) {
+            continue;
+        }
+
+        // only the edge of the vault is fixed, as the inside is stamped over the map afterwards
+        for vault_pos in vault_map.get_all_pos() {
+            if vault_pos.x > 0 && vault_pos.y > 0 && vault_pos.x < vault_width - 1 && vault_pos.y < vault_height - 1 {
+                continue;
+            }
+            let tile = vault_map[vault_pos];
+            fixed.push((add_pos(offset, vault_pos), tile.tile_type == TileType::Wall));
+        }
+
+        placed.push(vault_index);
+        seed_rects.push(rect);
+        seeds.push((vault_index, offset, rotation, mirror));
+    }
+
+    let map_image_file = Some(game.config.wfc_map_file.as_str()).filter(|_| game.config.write_wfc_map);
+    game.data.map = generate_bare_map_fixed(width, height, template_file, &fixed, map_image_file, &mut game.rng)?;
+
+    for (vault_index, offset, rotation, mirror) in seeds {
+        let vault = &game.vaults[vault_index];
+        eprintln!("Seeding vault {} at {}", vault_index, offset);
+        let dims = place_vault_with(&mut game.data, vault, offset, rotation, mirror);
+        game.vaults_placed += 1;
+
+        let no_replace = vault.tags.contains(&VaultTag::NoReplace);
+        connect_vault(&mut game.data.map, offset, dims, no_replace);
+    }
+
+    return Ok(());
+}
+
+#[test]
+fn test_generate_bare_map_fixed() {
+    let mut rng = Rand32::new(1);
+
+    // an open area next to a wall
+    let mut fixed = Vec::new();
+    for x in 4..10 {
+        for y in 4..8 {
+            fixed.push((Pos::new(x, y), x == 9));
+        }
+    }
+
+    let map = generate_bare_map_fixed(20, 20, "../resources/wfc_seed_2.png", &fixed, None, &mut rng).unwrap();
+    for (pos, is_wall) in fixed.iter() {
+        assert_eq!(*is_wall, map[*pos].block_move);
+    }
+}
+
+/// Generate a map of rectangular rooms joined by corridors. Rooms are placed at random
+/// and skipped if they overlap an existing room, and each room is joined to the previous one.
+pub fn generate_rooms_map(width: u32, height: u32, config: &Config, rng: &mut Rand32) -> Map {
+    let mut new_map = Map::from_dims(width, height);
+    for pos in new_map.get_all_pos() {
+        new_map[pos] = Tile::wall_with(MAP_WALL as char);
+    }
+
+    let mut rooms: Vec<Rect<i32, ()>> = Vec::new();
+    for _ in 0..config.rooms_max {
+        let room_width = rng_range_i32(rng, config.room_min_size, config.room_max_size + 1);
+        let room_height = rng_range_i32(rng, config.room_min_size, config.room_max_size + 1);
+
+        // keep a wall around the edge of the map
+        if room_width + 2 > width as i32 || room_height + 2 > height as i32 {
+            continue;
+        }
+        let x = rng_range_i32(rng, 1, width as i32 - room_width);
+        let y = rng_range_i32(rng, 1, height as i32 - room_height);
+        let room = Rect::new(Pos::new(x, y), Size2D::new(room_width, room_height));
+
+        // leave at least one wall between rooms
+        if rooms.iter().any(|other| room.inflate(1, 1).intersects(other)) {
+            continue;
+        }
+
+        for x in room.min_x()..room.max_x() {
+            for y in room.min_y()..room.max_y() {
+                new_map[(x, y)] = Tile::empty();
+            }
+        }
+
+        if let Some(prev_room) = rooms.last() {
+            let start = prev_room.center();
+            let end = room.center();
+
+            // choose which leg of the corridor comes first
+            let corner =
+                if rng_bool(rng) {
+                    Pos::new(end.x, start.y)
+                } else {
+                    Pos::new(start.x, end.y)
+                };
+
+            carve_corridor(&mut new_map, start, corner);
+            carve_corridor(&mut new_map, corner, end);
+        }
+
+        rooms.push(room);
+    }
+
     return new_map;
 }
 
+/// Carve a straight corridor between two positions sharing a row or column.
+fn carve_corridor(map: &mut Map, start: Pos, end: Pos) {
+    for x in std::cmp::min(start.x, end.x)..=std::cmp::max(start.x, end.x) {
+        for y in std::cmp::min(start.y, end.y)..=std::cmp::max(start.y, end.y) {
+            map[(x, y)] = Tile::empty();
+        }
+    }
+}
+
+#[test]
+fn test_generate_rooms_map() {
+    let config = Config::from_file("../config.yaml");
+    let mut rng = Rand32::new(1);
+
+    let map = generate_rooms_map(30, 30, &config, &mut rng);
+
+    let open = map.get_all_pos().iter().filter(|pos| !map[**pos].block_move).map(|pos| *pos).collect::<Vec<Pos>>();
+    assert!(open.len() > 0);
+
+    // the edges of the map are always walls
+    for pos in open.iter() {
+        assert!(pos.x > 0 && pos.y > 0 && pos.x < 29 && pos.y < 29);
+    }
+
+    // every room is reachable from every other room
+    let flood = floodfill(&map, open[0], 30 * 30);
+    assert_eq!(open.len(), flood.len());
+}
+
+/// Generate a cave map by filling the map with random walls and smoothing them with
+/// a cellular automata. Separate caves are then joined with tunnels.
+pub fn generate_caves_map(width: u32, height: u32, config: &Config, rng: &mut Rand32) -> Map {
+    let mut new_map = Map::from_dims(width, height);
+
+    let is_edge = |pos: Pos| {
+        return pos.x == 0 || pos.y == 0 || pos.x == width as i32 - 1 || pos.y == height as i32 - 1;
+    };
+
+    for pos in new_map.get_all_pos() {
+        if is_edge(pos) || rng_trial(rng, config.cave_fill_chance) {
+            new_map[pos] = Tile::wall_with(MAP_WALL as char);
+        }
+    }
+
+    for _ in 0..config.cave_iterations {
+        let last_map = new_map.clone();
+
+        for pos in last_map.get_all_pos() {
+            if is_edge(pos) {
+                continue;
+            }
+
+            // tiles off the edge of the map count as walls
+            let num_neighbors = last_map.neighbors(pos).len();
+            let wall_count = 8 - num_neighbors +
+                last_map.neighbors(pos).iter().filter(|next_pos| last_map[**next_pos].block_move).count();
+
+            if wall_count >= 5 {
+                new_map[pos] = Tile::wall_with(MAP_WALL as char);
+            } else if wall_count <= 3 {
+                new_map[pos] = Tile::empty();
+            }
+        }
+    }
+
+    join_regions(&mut new_map);
+
+    return new_map;
+}
+
+/// Find each separate group of open tiles, connected through cardinal movement.
+fn find_regions(map: &Map) -> Vec<Vec<Pos>> {
+    let mut regions: Vec<Vec<Pos>> = Vec::new();
+    let mut seen: HashSet<Pos> = HashSet::new();
+
+    for start_pos in map.get_all_pos() {
+        if map[start_pos].block_move || seen.contains(&start_pos) {
+            continue;
+        }
+
+        let mut region = Vec::new();
+        let mut current: VecDeque<Pos> = VecDeque::new();
+        current.push_back(start_pos);
+        seen.insert(start_pos);
+
+        while let Some(pos) = current.pop_front() {
+            region.push(pos);
+
+            for next_pos in map.cardinal_neighbors(pos) {
+                if !seen.contains(&next_pos) && map.path_blocked_move(pos, next_pos).is_none() {
+                    seen.insert(next_pos);
+                    current.push_back(next_pos);
+                }
+            }
+        }
+
+        regions.push(region);
+    }
+
+    return regions;
+}
+
+/// Connect every region of the map to the largest one by tunneling from each
+/// region to the closest tile already connected.
+fn join_regions(map: &mut Map) {
+    let mut regions = find_regions(map);
+    if regions.len() < 2 {
+        return;
+    }
+
+    regions.sort_by_key(|region| std::cmp::Reverse(region.len()));
+
+    let mut connected: Vec<Pos> = regions[0].clone();
+    for region in regions.iter().skip(1) {
+        let mut closest = (region[0], connected[0]);
+        let mut closest_dist = distance(closest.0, closest.1);
+        for pos in region.iter() {
+            for other_pos in connected.iter() {
+                let dist = distance(*pos, *other_pos);
+                if dist < closest_dist {
+                    closest = (*pos, *other_pos);
+                    closest_dist = dist;
+                }
+            }
+        }
+
+        let (start, end) = closest;
+        let corner = Pos::new(end.x, start.y);
+        carve_corridor(map, start, corner);
+        carve_corridor(map, corner, end);
+
+        connected.extend(region.iter());
+        connected.push(corner);
+    }
+}
+
+#[test]
+fn test_generate_caves_map() {
+    let config = Config::from_file("../config.yaml");
+    let mut rng = Rand32::new(1);
+
+    let map = generate_caves_map(30, 30, &config, &mut rng);
+
+    let open = map.get_all_pos().iter().filter(|pos| !map[**pos].block_move).map(|pos| *pos).collect::<Vec<Pos>>();
+    assert!(open.len() > 0);
+
+    for pos in open.iter() {
+        assert!(pos.x > 0 && pos.y > 0 && pos.x < 29 && pos.y < 29);
+    }
+
+    // all caves are joined into one
+    assert_eq!(1, find_regions(&map).len());
+}
+
 fn check_map(game: &Game) {
     for wall_pos in game.data.map.get_wall_pos() {
         for id in game.data.entities.ids.iter() {
@@ -132,6 +552,107 @@ fn check_map(game: &Game) {
     }
 }
 
+/// The results of checking that a generated map can be played.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MapReport {
+    pub key_reachable: bool,
+    pub exit_reachable: bool,
+    pub entities_in_walls: Vec<EntityId>,
+    pub player_in_water: bool,
+    pub escape_routes: usize,
+}
+
+impl MapReport {
+    pub fn passed(&self, config: &Config) -> bool {
+        return self.key_reachable &&
+               self.exit_reachable &&
+               self.entities_in_walls.len() == 0 &&
+               !self.player_in_water &&
+               self.escape_routes >= config.map_escape_routes;
+    }
+}
+
+impl fmt::Display for MapReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "key reachable {}, exit reachable {}, {} entities in walls, player in water {}, {} escape routes",
+               self.key_reachable,
+               self.exit_reachable,
+               self.entities_in_walls.len(),
+               self.player_in_water,
+               self.escape_routes)
+    }
+}
+
+/// Check that the player can reach the key, and then the exit, and that
+/// the player does not start in a position they can't get out of.
+pub fn validate_map(game: &Game) -> MapReport {
+    let map = &game.data.map;
+    let max_dist = (map.width() * map.height()) as usize;
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = game.data.entities.pos[&player_id];
+
+    let mut key_reachable = false;
+    let mut exit_reachable = false;
+    if let Some(key_id) = game.data.find_by_name(EntityName::Key) {
+        let key_pos = game.data.entities.pos[&key_id];
+        key_reachable = floodfill(map, player_pos, max_dist).contains(&key_pos);
+
+        if let Some(exit_id) = game.data.find_by_name(EntityName::Exit) {
+            let exit_pos = game.data.entities.pos[&exit_id];
+            exit_reachable = key_reachable && floodfill(map, key_pos, max_dist).contains(&exit_pos);
+        }
+    }
+
+    let mut entities_in_walls = Vec::new();
+    for id in game.data.entities.ids.iter() {
+        let pos = game.data.entities.pos[id];
+        if map.is_within_bounds(pos) && map[pos].block_move {
+            entities_in_walls.push(*id);
+        }
+    }
+
+    let player_in_water = map[player_pos].tile_type == TileType::Water;
+
+    let escape_routes =
+        map.neighbors(player_pos)
+           .iter()
+           .filter(|pos| map.path_blocked_move(player_pos, **pos).is_none())
+           .filter(|pos| map[**pos].tile_type != TileType::Water)
+           .filter(|pos| game.data.has_blocking_entity(**pos).is_none())
+           .count();
+
+    return MapReport { key_reachable, exit_reachable, entities_in_walls, player_in_water, escape_routes };
+}
+
+#[test]
+fn test_validate_map() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(1, config.clone());
+    game.data.map = Map::from_dims(10, 10);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.set_pos(player_id, Pos::new(1, 1));
+    make_key(&mut game.data.entities, &config, Pos::new(5, 1), &mut game.msg_log);
+    make_exit(&mut game.data.entities, &config, Pos::new(5, 8), &mut game.msg_log);
+
+    let report = validate_map(&game);
+    assert!(report.passed(&config));
+    assert_eq!(8, report.escape_routes);
+
+    // wall off the exit
+    for x in 0..10 {
+        game.data.map[(x, 6)] = Tile::wall();
+    }
+    game.data.map[(1, 2)] = Tile::water();
+
+    let report = validate_map(&game);
+    assert!(report.key_reachable);
+    assert!(!report.exit_reachable);
+    assert_eq!(7, report.escape_routes);
+    assert!(!report.passed(&config));
+}
+
 pub fn saturate_map(game: &mut Game, cmds: &Vec<ProcCmd>) -> Pos {
     // this is problematic for movement, so ensure they don't occur
     handle_diagonal_full_tile_walls(game);
@@ -194,6 +715,8 @@ pub fn saturate_map(game: &mut Game, cmds: &Vec<ProcCmd>) -> Pos {
 
     place_triggers(game, cmds);
 
+    stock_containers(game, cmds);
+
     // clear the island once more just in case
     clear_island(game, island_radius);
 
@@ -351,6 +874,11 @@ fn place_items(game: &mut Game, cmds: &Vec<ProcCmd>) {
                     Item::Hammer => { make_hammer(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
                     Item::Stone => { make_stone(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
                     Item::Lantern => { make_lantern(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
+                    Item::SmokeBomb => { make_smoke_bomb(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
+                    Item::PoisonBomb => { make_poison_bomb(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
+                    Item::Noisemaker => { make_noisemaker(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
+                    Item::Grapple => { make_grapple(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
+                    Item::Coins => { make_coins(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
                     _ => {},
                 }
             }
@@ -358,18 +886,46 @@ fn place_items(game: &mut Game, cmds: &Vec<ProcCmd>) {
     }
 }
 
-fn place_triggers(game: &mut Game, cmds: &Vec<ProcCmd>) {
-    let potential_pos = game.data.get_clear_pos();
+/// Fill every empty container on the map, including those placed by vaults,
+/// from the level's loot table.
+fn stock_containers(game: &mut Game, cmds: &Vec<ProcCmd>) {
+    let loot = cmds.iter().filter_map(|cmd| {
+        if let ProcCmd::Loot(item, weight) = cmd {
+            return Some((*item, *weight));
+        }
+        return None;
+    }).collect::<Vec<(Item, usize)>>();
 
-    let mut near_walls = HashSet::new();
+    let containers = game.data.entities.ids.iter()
+                                       .filter(|id| game.data.entities.is_container(**id))
+                                       .filter(|id| game.data.entities.inventory[id].len() == 0)
+                                       .map(|id| *id)
+                                       .collect::<Vec<EntityId>>();
 
-    for pos in potential_pos {
-        for neighbor in game.data.map.cardinal_neighbors(pos) {
-            if game.data.map[neighbor].tile_type == TileType::Wall {
-                near_walls.insert(pos);
-            }
-        }
+    for container_id in containers {
+        let num_items = rng_range_u32(&mut game.rng, 1, (CONTAINER_MAX_LOOT + 1) as u32) as usize;
+        stock_container(container_id, &loot, num_items, game);
     }
+}
+
+fn place_triggers(game: &mut Game, cmds: &Vec<ProcCmd>) {
+    let potential_pos = game.data.get_clear_pos();
+
+    let mut gate_positions =
+        potential_pos.into_par_iter()
+                     .map_with(game.data.map.clone(), |map, pos| {
+                         let near_wall =
+                             map.cardinal_neighbors(pos)
+                                .iter()
+                                .any(|neighbor| map[*neighbor].tile_type == TileType::Wall);
+                         if near_wall {
+                             return Some(pos);
+                         }
+                         return None;
+                     })
+                     .filter_map(|pos| pos)
+                     .collect::<Vec<Pos>>();
+    sort_positions(&mut gate_positions);
 
     let max_gates = cmds.iter().filter_map(|cmd| {
         if let ProcCmd::MaxGates(n) = cmd {
@@ -378,8 +934,6 @@ fn place_triggers(game: &mut Game, cmds: &Vec<ProcCmd>) {
         return None;
     }).map(|n| *n).next().unwrap_or(0);
 
-    let mut gate_positions = near_walls.iter().map(|p| *p).collect::<Vec<Pos>>();
-
     // if there are no possible positions, exit early
     if gate_positions.len() == 0 {
         return;
@@ -425,85 +979,344 @@ fn place_traps(game: &mut Game, cmds: &Vec<ProcCmd>) {
                 let pos = potential_pos[index];
 
                 match typ {
-                    Trap::Spikes => { make_spike_trap(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
-                    Trap::Sound => { make_sound_trap(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
-                    Trap::Blink => { make_blink_trap(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
-                    Trap::Freeze => { make_freeze_trap(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
+                    TrapType::Spikes => { make_spike_trap(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
+                    TrapType::Sound => { make_sound_trap(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
+                    TrapType::Blink => { make_blink_trap(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
+                    TrapType::Freeze => { make_freeze_trap(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
+                    TrapType::Tripwire => { make_tripwire(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
+                    TrapType::Net => { make_net_trap(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
+                    TrapType::Blade => { make_blade_trap(&mut game.data.entities, &game.config, pos, &mut game.msg_log); },
                 }
             }
         }
     }
 }
 
+/// Place the level's entities. Monsters are chosen by the spawn director, within a
+/// budget for the level, while other entities such as prisoners and containers are
+/// placed in the numbers given by the procgen file.
 fn place_monsters(game: &mut Game, player_id: EntityId, cmds: &Vec<ProcCmd>) {
     let player_pos = game.data.entities.pos[&player_id];
 
-    // get empty positions, but make sure they are not close to the player
-    let mut potential_pos = 
-        game.data.get_clear_pos()
-                 .iter()
-                 .filter(|p| distance(player_pos, **p) > 4)
-                 .map(|p| *p)
-                 .collect::<Vec<Pos>>();
+    // get empty positions, but make sure they are not close to the player or the key's path
+    let mut potential_pos = spawn_positions(game, player_pos);
+
+    let budget = spawn_budget(game.settings.level_num, game.settings.last_level_noise, &game.config);
+    let mut spawns = choose_spawns(cmds, budget, &mut game.rng);
+    eprintln!("Spawning {} monsters with a budget of {}", spawns.len(), budget);
 
     for cmd in cmds.iter() {
         if let ProcCmd::Entities(typ, min, max) = cmd {
-            let num_gen = rng_range_u32(&mut game.rng, *min as u32, *max as u32) as usize;
-
-            for _ in 0..num_gen {
-                let len = potential_pos.len();
-
-                if len == 0 {
-                    break;
-                }
+            if spawn_cost(*typ).is_none() {
+                let num_gen = rng_range_u32(&mut game.rng, *min as u32, *max as u32) as usize;
+                spawns.extend(std::iter::repeat(*typ).take(num_gen));
+            }
+        }
+    }
 
-                let index = rng_range_u32(&mut game.rng, 0, len as u32) as usize;
-                let pos = potential_pos[index];
+    for typ in spawns.iter() {
+        let len = potential_pos.len();
 
-                let id;
-                match typ {
-                    EntityName::Gol => { id = Some(make_gol(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
-                    EntityName::Pawn => { id = Some(make_pawn(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
-                    EntityName::Spire => { id = Some(make_spire(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
-                    EntityName::Armil => { id = Some(make_armil(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
-                    EntityName::Rook => { id = Some(make_rook(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
-                    _ => { id = None; },
-                }
-                if let Some(id) = id {
-                    if game.data.is_in_fov(id, player_id, &game.config) {
-                        game.data.entities.direction[&id] = 
-                            game.data.entities.direction[&id].reverse();
-                    }
-                }
+        if len == 0 {
+            break;
+        }
 
-                potential_pos.remove(index);
+        let index = rng_range_u32(&mut game.rng, 0, len as u32) as usize;
+        let pos = potential_pos[index];
+
+        let id;
+        match typ {
+            EntityName::Gol => { id = Some(make_gol(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
+            EntityName::Pawn => { id = Some(make_pawn(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
+            EntityName::Spire => { id = Some(make_spire(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
+            EntityName::Armil => { id = Some(make_armil(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
+            EntityName::Rook => { id = Some(make_rook(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
+            EntityName::Knight => { id = Some(make_knight(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
+            EntityName::Prisoner => { id = Some(make_prisoner(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
+            EntityName::Rat => { make_rat(&mut game.data.entities, &game.config, pos, &mut game.msg_log); id = None; },
+            EntityName::Bird => { make_bird(&mut game.data.entities, &game.config, pos, &mut game.msg_log); id = None; },
+            EntityName::Chest => { make_chest(&mut game.data.entities, &game.config, pos, &mut game.msg_log); id = None; },
+            EntityName::Crate => { make_crate(&mut game.data.entities, &game.config, pos, &mut game.msg_log); id = None; },
+            EntityName::Alarm => { make_alarm(&mut game.data.entities, &game.config, pos, &mut game.msg_log); id = None; },
+            _ => { id = None; },
+        }
+        if let Some(id) = id {
+            if game.data.is_in_fov(id, player_id, &game.config) {
+                game.data.entities.direction[&id] = 
+                    game.data.entities.direction[&id].reverse();
             }
         }
+
+        potential_pos.remove(index);
     }
 }
 
-// TODO choose based on frequency given by tags
 fn place_vaults(game: &mut Game, cmds: &Vec<ProcCmd>) {
     for cmd in cmds.iter() {
         if let ProcCmd::Vaults(max) = cmd {
+            let mut placed: Vec<usize> = Vec::new();
+
             for _ in 0..*max {
                 let (width, height) = game.data.map.size();
                 let x = rng_range_i32(&mut game.rng, 0, width);
                 let y = rng_range_i32(&mut game.rng, 0, height);
                 let offset = Pos::new(x, y);
 
-                let vault_index = rng_range_u32(&mut game.rng, 0, game.vaults.len() as u32) as usize;
+                let vault_index;
+                if let Some(index) = choose_vault(&game.vaults, game.settings.level_num, &placed, &mut game.rng) {
+                    vault_index = index;
+                } else {
+                    break;
+                }
+                placed.push(vault_index);
+                game.vaults_placed += 1;
+
                 let vault = &game.vaults[vault_index];
                 eprintln!("Placing vault {} at {}", vault_index, offset);
-                place_vault(&mut game.data, vault, offset, &mut game.rng);
+                let dims = place_vault(&mut game.data, vault, offset, &mut game.rng);
+
+                let no_replace = vault.tags.contains(&VaultTag::NoReplace);
+                if !connect_vault(&mut game.data.map, offset, dims, no_replace) {
+                    eprintln!("Vault {} at {} could not be connected to the map", vault_index, offset);
+                }
             }
         }
     }
 }
 
+/// Choose a vault weighted by its frequency tags, skipping vaults not allowed on
+/// this level and rare vaults that were already placed.
+fn choose_vault(vaults: &Vec<Vault>, level_num: usize, placed: &Vec<usize>, rng: &mut Rand32) -> Option<usize> {
+    let mut weights: Vec<f32> = Vec::new();
+    for (index, vault) in vaults.iter().enumerate() {
+        let already_placed = vault.tags.contains(&VaultTag::Rare) && placed.contains(&index);
+
+        if vault.allowed_on_level(level_num) && !already_placed {
+            weights.push(vault.weight());
+        } else {
+            weights.push(0.0);
+        }
+    }
+
+    let total: f32 = weights.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+
+    let mut choice = rng_range(rng, 0.0, total);
+    for (index, weight) in weights.iter().enumerate() {
+        if *weight > 0.0 && choice < *weight {
+            return Some(index);
+        }
+        choice -= weight;
+    }
+
+    // rounding may leave a little weight left over, so fall back to the last valid vault
+    return weights.iter().rposition(|weight| *weight > 0.0);
+}
+
+#[test]
+fn test_choose_vault() {
+    let mut rng = Rand32::new(1);
+
+    let mut common = Vault::empty();
+    common.tags.push(VaultTag::Common);
+    let mut rare = Vault::empty();
+    rare.tags.push(VaultTag::Rare);
+    let mut late = Vault::empty();
+    late.tags.push(VaultTag::Late);
+    let vaults = vec!(common, rare, late);
+
+    // late vaults are not chosen early on, and a rare vault is only placed once
+    let mut placed = Vec::new();
+    for _ in 0..100 {
+        let index = choose_vault(&vaults, 0, &placed, &mut rng).unwrap();
+        assert!(index != 2);
+        assert!(!(index == 1 && placed.contains(&1)));
+        placed.push(index);
+    }
+    assert!(placed.iter().filter(|index| **index == 0).count() > 90);
+
+    let mut late_chosen = false;
+    for _ in 0..100 {
+        late_chosen |= choose_vault(&vaults, VAULT_LATE_LEVEL, &Vec::new(), &mut rng) == Some(2);
+    }
+    assert!(late_chosen);
+
+    // nothing can be chosen if only a placed rare vault is left
+    assert_eq!(None, choose_vault(&vaults[1..2].to_vec(), 0, &vec!(0), &mut rng));
+}
+
+/// Ensure that the open tiles of a placed vault can be reached from the main region of
+/// the map, the region with the most open tiles outside the vault, carving an entrance
+/// if they cannot. With no_replace set, the vault's own tiles are left alone and only
+/// the map around it is carved.
+/// Returns false if the vault could not be connected.
+pub fn connect_vault(map: &mut Map, offset: Pos, dims: (i32, i32), no_replace: bool) -> bool {
+    let in_vault = |pos: Pos| {
+        return pos.x >= offset.x && pos.x < offset.x + dims.0 &&
+               pos.y >= offset.y && pos.y < offset.y + dims.1;
+    };
+
+    let mut inside: Vec<Pos> = Vec::new();
+    let mut outside: Vec<Pos> = Vec::new();
+    for pos in map.get_all_pos() {
+        if !map[pos].block_move {
+            if in_vault(pos) {
+                inside.push(pos);
+            } else {
+                outside.push(pos);
+            }
+        }
+    }
+
+    // nothing to connect
+    if inside.len() == 0 || outside.len() == 0 {
+        return true;
+    }
+
+    // a vault opening onto a pocket cut off from the rest of the map is not connected
+    let reached: HashSet<Pos> =
+        find_regions(map).into_iter()
+                         .max_by_key(|region| region.iter().filter(|pos| !in_vault(**pos)).count())
+                         .map_or(HashSet::new(), |region| region.into_iter().collect());
+
+    if inside.iter().any(|pos| reached.contains(pos)) {
+        return true;
+    }
+
+    // find the cheapest path out of the vault, preferring existing openings
+    let blocked_step = |pos: Pos, next_pos: Pos, map: &Map| {
+        return map.path_blocked_move(pos, next_pos).is_some();
+    };
+
+    let start_pos = inside[0];
+    let path =
+        astar(&start_pos,
+              |&pos| {
+                  map.cardinal_neighbors(pos)
+                     .iter()
+                     .filter(|next_pos| {
+                         let wall_pos = wall_between(pos, **next_pos);
+                         let wall =
+                             if next_pos.x != pos.x {
+                                 map[wall_pos].left_wall != Wall::Empty
+                             } else {
+                                 map[wall_pos].bottom_wall != Wall::Empty
+                             };
+                         let changes_vault =
+                             (map[**next_pos].block_move && in_vault(**next_pos)) ||
+                             (wall && in_vault(wall_pos));
+                         return !(no_replace && changes_vault);
+                     })
+                     .map(|next_pos| {
+                         if blocked_step(pos, *next_pos, map) {
+                             return (*next_pos, 15);
+                         }
+                         return (*next_pos, 1);
+                     })
+                     .collect::<Vec<(Pos, i32)>>()
+              },
+              |_pos| 0,
+              |pos| reached.contains(pos));
+
+    if let Some((results, _cost)) = path {
+        for index in 1..results.len() {
+            carve_step(map, results[index - 1], results[index]);
+        }
+
+        return true;
+    }
+
+    return false;
+}
+
+/// Clear a tile and the intertile wall between it and the previous, cardinally adjacent, tile.
+fn carve_step(map: &mut Map, pos: Pos, next_pos: Pos) {
+    if map[next_pos].block_move {
+        let surface = map[next_pos].surface;
+        map[next_pos] = Tile::empty();
+        map[next_pos].surface = surface;
+    }
+
+    let wall_pos = wall_between(pos, next_pos);
+    if next_pos.x != pos.x {
+        map[wall_pos].left_wall = Wall::Empty;
+    } else {
+        map[wall_pos].bottom_wall = Wall::Empty;
+    }
+}
+
+/// The tile that holds the intertile wall between two cardinally adjacent tiles.
+fn wall_between(pos: Pos, next_pos: Pos) -> Pos {
+    if next_pos.x > pos.x || next_pos.y < pos.y {
+        return next_pos;
+    } else {
+        return pos;
+    }
+}
+
+#[test]
+fn test_connect_vault() {
+    let mut map = Map::from_dims(10, 10);
+
+    // a 3x3 vault with a wall ring around a single open tile
+    let offset = Pos::new(3, 3);
+    for pos in map.get_all_pos() {
+        if pos.x >= 3 && pos.x < 6 && pos.y >= 3 && pos.y < 6 && pos != Pos::new(4, 4) {
+            map[pos] = Tile::wall();
+        }
+    }
+
+    // the vault cannot be opened without replacing its tiles
+    let mut no_replace_map = map.clone();
+    assert!(!connect_vault(&mut no_replace_map, offset, (3, 3), true));
+    assert_eq!(map, no_replace_map);
+
+    assert!(connect_vault(&mut map, offset, (3, 3), false));
+    let open_walls = map.cardinal_neighbors(Pos::new(4, 4)).iter().filter(|pos| !map[**pos].block_move).count();
+    assert_eq!(1, open_walls);
+
+    // a connected vault is left alone
+    let connected_map = map.clone();
+    assert!(connect_vault(&mut map, offset, (3, 3), false));
+    assert_eq!(connected_map, map);
+
+    // an intertile wall around a vault can be carved through even with no_replace
+    let mut map = Map::from_dims(10, 10);
+    for x in 0..10 {
+        map[(x, 2)].bottom_wall = Wall::ShortWall;
+    }
+    for y in 0..10 {
+        map[(2, y)].left_wall = Wall::TallWall;
+    }
+    assert!(connect_vault(&mut map, Pos::new(0, 0), (2, 3), true));
+
+    // a vault opening onto a walled off pocket is joined to the rest of the map
+    let mut map = Map::from_dims(10, 10);
+    for pos in map.get_all_pos() {
+        let in_vault = pos.x >= 3 && pos.x < 6 && pos.y >= 3 && pos.y < 6;
+        let around_pocket = pos.x == 6 || pos.x == 7;
+        if (in_vault && pos != Pos::new(4, 4) && pos != Pos::new(5, 4)) ||
+           (around_pocket && pos.y >= 3 && pos.y < 6 && pos != Pos::new(6, 4)) {
+            map[pos] = Tile::wall();
+        }
+    }
+    assert!(connect_vault(&mut map, offset, (3, 3), false));
+    let main_region = find_regions(&map).into_iter().find(|region| region.contains(&Pos::new(0, 0))).unwrap();
+    assert!(main_region.contains(&Pos::new(4, 4)));
+}
+
 // TODO rotate and mirror according to tags
-pub fn place_vault(data: &mut GameData, vault: &Vault, offset: Pos, rng: &mut Rand32) {
-                        
+/// Place a vault with a random orientation allowed by its tags, returning the
+/// dimensions of the placed vault.
+pub fn place_vault(data: &mut GameData, vault: &Vault, offset: Pos, rng: &mut Rand32) -> (i32, i32) {
+    let (rotation, mirror) = choose_orientation(vault, rng);
+    return place_vault_with(data, vault, offset, rotation, mirror);
+}
+
+/// Choose a random rotation and mirroring for a vault, as allowed by its tags.
+fn choose_orientation(vault: &Vault, rng: &mut Rand32) -> (Rotation, bool) {
     let mirror = !vault.tags.contains(&VaultTag::NoMirror) && rng_range(rng, 0.0, 1.0) < 0.5;
 
     let mut rotation = Rotation::Degrees0;
@@ -514,20 +1327,27 @@ pub fn place_vault(data: &mut GameData, vault: &Vault, offset: Pos, rng: &mut Ra
         rotation = rotations[index];
     }
 
-    place_vault_with(data, vault, offset, rotation, mirror);
+    return (rotation, mirror);
 }
 
-pub fn place_vault_with(data: &mut GameData, vault: &Vault, offset: Pos, rotation: Rotation, mirror: bool) {
+pub fn place_vault_with(data: &mut GameData, vault: &Vault, offset: Pos, rotation: Rotation, mirror: bool) -> (i32, i32) {
     let mut actual_vault = vault.clone();
     actual_vault.data.map = reorient_map(&actual_vault.data.map, rotation, mirror);
 
     let (width, height) = actual_vault.data.map.size();
 
+    // number the vault's links after those already on the map, so that
+    // placing the same vault twice does not join their mechanisms together.
+    let link_offset = data.map.max_link();
+
     // update map with vault tiles
     for vault_pos in actual_vault.data.map.get_all_pos() {
         let map_pos = add_pos(offset, vault_pos);
         if data.map.is_within_bounds(map_pos) {
             data.map[map_pos] = actual_vault.data.map[vault_pos];
+            if data.map[map_pos].link != 0 {
+                data.map[map_pos].link = data.map[map_pos].link.saturating_add(link_offset);
+            }
 
             for entity_id in data.get_entities_at_pos(map_pos) {
                 if data.entities.typ[&entity_id] == EntityType::Player {
@@ -578,25 +1398,30 @@ pub fn place_vault_with(data: &mut GameData, vault: &Vault, offset: Pos, rotatio
     }
 
     data.entities.merge(&entities);
+
+    return (width, height);
 }
 
 fn place_grass(game: &mut Game, num_grass_to_place: usize, disperse: i32) {
-    let (width, height) = game.data.map.size();
-
-    let mut potential_grass_pos = Vec::new();
-    for x in 0..width {
-        for y in 0..height {
-            let pos = Pos::new(x, y);
-
-            if !game.data.map[pos].block_move {
-                let count = floodfill(&game.data.map, pos, 3).len();
-                if count > 28 && count < 35 {
-                    potential_grass_pos.push(pos);
-                }
-            }
-        }
-    }
-
+    // the floodfill for each tile is independent, so they are checked in parallel.
+    // each thread gets its own copy of the map, as the map's fov cache can't be shared.
+    let mut potential_grass_pos =
+        game.data.map.get_all_pos()
+                     .into_par_iter()
+                     .map_with(game.data.map.clone(), |map, pos| {
+                         if !map[pos].block_move {
+                             let count = floodfill(map, pos, 3).len();
+                             if count > 28 && count < 35 {
+                                 return Some(pos);
+                             }
+                         }
+                         return None;
+                     })
+                     .filter_map(|pos| pos)
+                     .collect::<Vec<Pos>>();
+
+    // sort before sampling so the result only depends on the rng
+    sort_positions(&mut potential_grass_pos);
     shuffle(&mut game.rng, &mut potential_grass_pos);
     let num_grass_to_place = std::cmp::min(num_grass_to_place, potential_grass_pos.len());
     for pos_index in 0..num_grass_to_place {
@@ -615,6 +1440,45 @@ fn place_grass(game: &mut Game, num_grass_to_place: usize, disperse: i32) {
     }
 }
 
+/// Sort positions by row and column, so candidates found in parallel are
+/// always sampled in the same order.
+fn sort_positions(positions: &mut Vec<Pos>) {
+    positions.sort_by_key(|pos| (pos.y, pos.x));
+}
+
+#[test]
+fn test_place_grass_deterministic() {
+    let config = Config::from_file("../config.yaml");
+
+    let mut maps = Vec::new();
+    for _ in 0..2 {
+        let mut game = Game::new(1, config.clone());
+        game.data.map = Map::from_dims(20, 20);
+        for x in 0..20 {
+            game.data.map[(x, 10)] = Tile::wall();
+        }
+        for y in 0..20 {
+            game.data.map[(7, y)] = Tile::wall();
+        }
+
+        place_grass(&mut game, 5, 3);
+        place_triggers(&mut game, &vec!(ProcCmd::MaxGates(3)));
+
+        let grass = game.data.map.get_all_pos()
+                                 .iter()
+                                 .filter(|pos| game.data.map[**pos].surface == Surface::Grass)
+                                 .map(|pos| *pos)
+                                 .collect::<Vec<Pos>>();
+        let gates = game.data.entities.ids.iter()
+                                      .map(|id| game.data.entities.pos[id])
+                                      .collect::<Vec<Pos>>();
+        maps.push((grass, gates));
+    }
+
+    assert!((maps[0].0).len() > 0);
+    assert_eq!(maps[0], maps[1]);
+}
+
 fn find_available_tile(game: &mut Game) -> Option<Pos> {
     let mut avail_pos = None;
 
@@ -671,7 +1535,7 @@ fn clear_path_to(game: &mut Game, start_pos: Pos, target_pos: Pos) {
     }
 }
 
-fn place_key_and_goal(game: &mut Game, player_pos: Pos) {
+pub fn place_key_and_goal(game: &mut Game, player_pos: Pos) {
     // place goal and key
     let key_pos = find_available_tile(game).unwrap();
     game.data.map[key_pos] = Tile::empty();
@@ -690,6 +1554,11 @@ fn place_key_and_goal(game: &mut Game, player_pos: Pos) {
 }
 
 fn clear_island(game: &mut Game, island_radius: i32) {
+    // maps without an island command keep their shape
+    if island_radius <= 0 {
+        return;
+    }
+
     fn dist(pos1: Pos, pos2: Pos) -> f32 {
         return (((pos1.x - pos2.x).pow(2) + (pos1.y - pos2.y).pow(2)) as f32).sqrt();
     }
@@ -716,41 +1585,7 @@ fn clear_island(game: &mut Game, island_radius: i32) {
 }
 
 
-fn process_block(block: Pos, structure: &mut Structure, blocks: &Vec<Pos>, seen: &mut HashSet<Pos>) {
-    let adjacent = adjacent_blocks(block, blocks, seen);
-
-    let mut needs_processing = false;
-    if adjacent.len() == 1 {
-        needs_processing = true;
-        if structure.typ == StructureType::Line && structure.blocks.len() > 1 {
-            let len = structure.blocks.len();
-            if sub_pos(structure.blocks[len - 2], structure.blocks[len - 1]) !=
-               sub_pos(structure.blocks[len - 1], adjacent[0]) {
-               structure.typ = StructureType::Path;
-            }
-        }
-
-    } else if adjacent.len() > 1 {
-        needs_processing = true;
-
-        // this structure must be complex- if there are multiple adj, they are new
-        // meaning we split in at least two directions
-        structure.typ = StructureType::Complex;
-    }
-
-    if needs_processing {
-        for adj in adjacent.iter() {
-            structure.add_block(*adj);
-            seen.insert(*adj);
-        }
-
-        for adj in adjacent.iter() {
-            process_block(*adj, structure, blocks, seen);
-        }
-    }
-}
-
-fn adjacent_blocks(block: Pos, blocks: &Vec<Pos>, seen: &HashSet<Pos>) -> Vec<Pos> {
+fn adjacent_blocks(block: Pos, blocks: &HashSet<Pos>, seen: &HashSet<Pos>) -> Vec<Pos> {
     let mut result = Vec::new();
 
     let adjacents = [move_x(block, 1), move_y(block, 1), move_x(block, -1), move_y(block, -1)];
@@ -775,7 +1610,7 @@ fn test_adjacent_blocks() {
 
     let mut seen = HashSet::new();
 
-    let blocks = find_structural_blocks(&map);
+    let blocks = find_structural_blocks(&map).into_iter().collect::<HashSet<Pos>>();
     assert_eq!(4, adjacent_blocks(Pos::new(2, 2), &blocks, &seen).len());
     assert_eq!(2, adjacent_blocks(Pos::new(1, 1), &blocks, &seen).len());
     assert_eq!(1, adjacent_blocks(Pos::new(2, 1), &blocks, &seen).len());
@@ -797,45 +1632,40 @@ fn find_structural_blocks(map: &Map) -> Vec<Pos> {
     return blocks;
 }
 
-fn find_structures(map: &Map) -> Vec<Structure> {
+/// Find each group of walls connected through cardinal directions, and classify
+/// the group by its shape. This is done with a breadth first search rather than
+/// recursion so large groups of walls do not overflow the stack.
+pub fn find_structures(map: &Map) -> Vec<Structure> {
     let blocks = find_structural_blocks(map);
 
     eprintln!("Blocks in structures: {}", blocks.len());
 
+    let block_set: HashSet<Pos> = blocks.iter().map(|pos| *pos).collect();
+
     let mut structures = Vec::new();
     let mut seen: HashSet<Pos> = HashSet::new();
     for block in blocks.iter() {
-        if !seen.contains(&block) {
-            let mut structure = Structure::new();
-
-            let adjacent = adjacent_blocks(*block, &blocks, &seen);
-
-            if adjacent.len() != 2 {
-                structure.add_block(*block);
-                seen.insert(*block);
-
-                if adjacent.len() == 1 {
-                    // found start of a structure (line, L, or complex)- process structure
-                    structure.typ = StructureType::Line;
-                    process_block(*block, &mut structure, &blocks, &mut seen);
-                } else if adjacent.len() > 2 {
-                    // found part of a complex structure- process all pieces
-                    structure.typ = StructureType::Complex;
+        if seen.contains(block) {
+            continue;
+        }
 
-                    for adj in adjacent.iter() {
-                        seen.insert(*adj);
-                    }
+        let mut structure = Structure::new();
 
-                    for adj in adjacent {
-                        process_block(adj, &mut structure, &blocks, &mut seen);
-                    }
-                }
+        let mut current: VecDeque<Pos> = VecDeque::new();
+        current.push_back(*block);
+        seen.insert(*block);
+        while let Some(pos) = current.pop_front() {
+            structure.add_block(pos);
 
-                structures.push(structure);
+            for adj in adjacent_blocks(pos, &block_set, &seen) {
+                seen.insert(adj);
+                current.push_back(adj);
             }
-            // else we are in the middle of a line, so we will pick it up later
-            // or we have a single block, which uses the default structure type of Single
         }
+
+        classify_structure(&mut structure, &block_set);
+
+        structures.push(structure);
     }
 
     eprintln!("Number of structures: {}", structures.len());
@@ -843,6 +1673,45 @@ fn find_structures(map: &Map) -> Vec<Structure> {
     return structures;
 }
 
+/// Classify a structure by the number of neighbors of each of its blocks. Lines and paths
+/// have two ends and no branches, and their blocks are ordered from one end to the other.
+/// Anything with a branch or a loop is complex.
+fn classify_structure(structure: &mut Structure, block_set: &HashSet<Pos>) {
+    let no_blocks_seen = HashSet::new();
+    let num_adjacent = |pos: Pos| adjacent_blocks(pos, block_set, &no_blocks_seen).len();
+
+    if structure.blocks.len() == 1 {
+        structure.typ = StructureType::Single;
+        return;
+    }
+
+    let branches = structure.blocks.iter().any(|pos| num_adjacent(*pos) > 2);
+    let ends = structure.blocks.iter().filter(|pos| num_adjacent(**pos) == 1).map(|pos| *pos).collect::<Vec<Pos>>();
+    if branches || ends.len() != 2 {
+        structure.typ = StructureType::Complex;
+        return;
+    }
+
+    // walk from one end to the other to order the blocks
+    let mut ordered = vec!(ends[0]);
+    let mut seen: HashSet<Pos> = HashSet::new();
+    seen.insert(ends[0]);
+    while let Some(next) = adjacent_blocks(ordered[ordered.len() - 1], block_set, &seen).first() {
+        seen.insert(*next);
+        ordered.push(*next);
+    }
+    structure.blocks = ordered;
+
+    let first = structure.blocks[0];
+    let straight = structure.blocks.iter().all(|pos| pos.x == first.x) ||
+                   structure.blocks.iter().all(|pos| pos.y == first.y);
+    if straight {
+        structure.typ = StructureType::Line;
+    } else {
+        structure.typ = StructureType::Path;
+    }
+}
+
 #[test]
 fn test_find_simple_structures() {
     let mut map = Map::from_dims(5, 5);
@@ -896,6 +1765,99 @@ fn test_find_complex_structures() {
     assert_eq!(5, structures[0].blocks.len());
 }
 
+#[test]
+fn test_find_structures_shapes() {
+    // a T is complex no matter which block is found first
+    let mut map = Map::from_dims(5, 5);
+    map[(1, 1)] = Tile::wall();
+    map[(2, 1)] = Tile::wall();
+    map[(3, 1)] = Tile::wall();
+    map[(2, 2)] = Tile::wall();
+    map[(2, 3)] = Tile::wall();
+    let structures = find_structures(&map);
+    assert_eq!(1, structures.len());
+    assert_eq!(StructureType::Complex, structures[0].typ);
+    assert_eq!(5, structures[0].blocks.len());
+
+    // a loop has no ends, but is still found
+    let mut map = Map::from_dims(5, 5);
+    for pos in [(1, 1), (2, 1), (3, 1), (3, 2), (3, 3), (2, 3), (1, 3), (1, 2)].iter() {
+        map[*pos] = Tile::wall();
+    }
+    let structures = find_structures(&map);
+    assert_eq!(1, structures.len());
+    assert_eq!(StructureType::Complex, structures[0].typ);
+    assert_eq!(8, structures[0].blocks.len());
+
+    // a large structure does not overflow the stack
+    let mut map = Map::from_dims(200, 200);
+    for pos in map.get_all_pos() {
+        map[pos] = Tile::wall();
+    }
+    let structures = find_structures(&map);
+    assert_eq!(1, structures.len());
+    assert_eq!(200 * 200, structures[0].blocks.len());
+}
+
+#[test]
+fn test_find_structures_random_maps() {
+    let mut rng = Rand32::new(1);
+
+    for _ in 0..50 {
+        let mut map = Map::from_dims(12, 12);
+        for pos in map.get_all_pos() {
+            if rng_trial(&mut rng, 0.4) {
+                map[pos] = Tile::wall();
+            }
+        }
+
+        let structures = find_structures(&map);
+
+        // every wall is in exactly one structure
+        let mut all_blocks = structures.iter().map(|s| s.blocks.clone()).flatten().collect::<Vec<Pos>>();
+        all_blocks.sort_by_key(|pos| (pos.x, pos.y));
+        let num_blocks = all_blocks.len();
+        all_blocks.dedup();
+        assert_eq!(num_blocks, all_blocks.len());
+        assert_eq!(map.get_wall_pos().len(), num_blocks);
+
+        for structure in structures.iter() {
+            // structures are not next to each other
+            for other in structures.iter() {
+                if other != structure {
+                    for pos in structure.blocks.iter() {
+                        assert!(other.blocks.iter().all(|other_pos| distance(*pos, *other_pos) > 1 || pos.x != other_pos.x && pos.y != other_pos.y));
+                    }
+                }
+            }
+
+            match structure.typ {
+                StructureType::Single => {
+                    assert_eq!(1, structure.blocks.len());
+                }
+
+                StructureType::Line | StructureType::Path => {
+                    // each block follows from the last
+                    for index in 1..structure.blocks.len() {
+                        assert_eq!(1, distance(structure.blocks[index - 1], structure.blocks[index]));
+                        assert!(structure.blocks[index - 1].x == structure.blocks[index].x ||
+                                structure.blocks[index - 1].y == structure.blocks[index].y);
+                    }
+
+                    let first = structure.blocks[0];
+                    let straight = structure.blocks.iter().all(|pos| pos.x == first.x) ||
+                                   structure.blocks.iter().all(|pos| pos.y == first.y);
+                    assert_eq!(straight, structure.typ == StructureType::Line);
+                }
+
+                StructureType::Complex => {
+                    assert!(structure.blocks.len() > 3);
+                }
+            }
+        }
+    }
+}
+
 
 pub fn place_block(map: &mut Map, start: Pos, width: i32, tile: Tile) -> Vec<Pos> {
     let mut positions = Vec::new();
@@ -928,7 +1890,7 @@ pub fn place_line(map: &mut Map, start: Pos, end: Pos, tile: Tile) -> Vec<Pos> {
 pub fn add_obstacle(map: &mut Map, pos: Pos, obstacle: Obstacle, rng: &mut Rand32) {
     match obstacle {
         Obstacle::Block => {
-            map.tiles[pos.x as usize][pos.y as usize] = Tile::wall();
+            map[pos] = Tile::wall();
         }
 
         Obstacle::Wall => {
@@ -961,14 +1923,14 @@ pub fn add_obstacle(map: &mut Map, pos: Pos, obstacle: Obstacle, rng: &mut Rand3
 
             if rng_trial(rng, 0.5) {
                 for x in 0..3 {
-                    map.tiles[pos.x as usize + x][pos.y as usize] = Tile::wall();
+                    map[(pos.x + x, pos.y)] = Tile::wall();
                 }
-                map.tiles[pos.x as usize][(pos.y + dir) as usize] = Tile::wall();
+                map[(pos.x, pos.y + dir)] = Tile::wall();
             } else {
                 for y in 0..3 {
-                    map.tiles[pos.x as usize][pos.y as usize + y] = Tile::wall();
+                    map[(pos.x, pos.y + y)] = Tile::wall();
                 }
-                map.tiles[(pos.x + dir) as usize][pos.y as usize] = Tile::wall();
+                map[(pos.x + dir, pos.y)] = Tile::wall();
             }
         }
 
@@ -0,0 +1,56 @@
+use serde::{Serialize, Deserialize};
+
+use roguelike_core::types::GameState;
+
+use crate::game::GameSettings;
+use crate::actions::{InputAction, change_state};
+
+
+/// A question shown over the current screen before doing something that can't be taken back.
+/// Choosing an option plays that option's action from the screen the prompt was opened
+/// over, so the input that opened the prompt is carried out rather than lost.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Prompt {
+    pub question: String,
+    pub options: Vec<(String, InputAction)>,
+    pub return_state: GameState,
+}
+
+/// Open a prompt over the current game state.
+pub fn open_prompt(settings: &mut GameSettings, question: &str, options: Vec<(String, InputAction)>) {
+    println!("CONSOLE: {}", question);
+
+    settings.prompt = Some(Prompt { question: question.to_string(), options, return_state: settings.state });
+    change_state(settings, GameState::Prompt);
+}
+
+/// Ask whether to go ahead with an action, carrying it out only if confirmed.
+pub fn confirm(settings: &mut GameSettings, question: &str, action: InputAction) {
+    open_prompt(settings, question, vec!(("yes".to_string(), action)));
+}
+
+/// Close the prompt, going back to the state it was opened over.
+pub fn close_prompt(settings: &mut GameSettings) {
+    if let Some(prompt) = settings.prompt.take() {
+        change_state(settings, prompt.return_state);
+    }
+}
+
+/// Handle input while a prompt is open. The prompt is closed when an option is chosen
+/// or it is cancelled, returning the chosen option's action if there is one.
+pub fn handle_input_prompt(input_action: InputAction, settings: &mut GameSettings) -> Option<InputAction> {
+    let num_options = settings.prompt.as_ref().map_or(0, |prompt| prompt.options.len());
+
+    let chosen =
+        match input_action {
+            InputAction::SelectItem(index) if index < num_options => Some(index),
+            InputAction::Confirm if num_options > 0 => Some(0),
+            InputAction::Esc => None,
+            _ => return None,
+        };
+
+    let action = chosen.map(|index| settings.prompt.as_ref().unwrap().options[index].1);
+    close_prompt(settings);
+
+    return action;
+}
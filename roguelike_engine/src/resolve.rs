@@ -12,6 +12,7 @@ use roguelike_core::movement::{MoveMode, MoveType, Attack, Movement, Direction,
 use roguelike_core::movement;
 use roguelike_core::config::*;
 use roguelike_core::utils::*;
+use roguelike_core::combat::{attack, hammer_hit, stealth_kill, wear_item};
 use roguelike_core::map::*;
 use roguelike_core::line::line;
 
@@ -35,6 +36,14 @@ pub fn resolve_messages(data: &mut GameData,
                process_interaction(entity_id, pos, data, msg_log, config);
             }
 
+            Msg::OpenDoor(entity_id, pos) => {
+                open_door(entity_id, pos, data);
+            }
+
+            Msg::CloseDoor(entity_id, pos) => {
+                close_door(entity_id, pos, data);
+            }
+
             Msg::Crushed(entity_id, pos) => {
                 crushed(entity_id, pos, data, msg_log, config);
             }
@@ -43,6 +52,8 @@ pub fn resolve_messages(data: &mut GameData,
                 let sound_aoe =
                     aoe_fill(&data.map, AoeEffect::Sound, source_pos, radius, config);
 
+                data.map.add_sound(&sound_aoe);
+
                 let who_heard =
                     data.within_aoe(&sound_aoe);
 
@@ -55,10 +66,23 @@ pub fn resolve_messages(data: &mut GameData,
             }
 
             Msg::ItemThrow(entity_id, item_id, start, end) => {
-                throw_item(entity_id, item_id, start, end, data, msg_log);
+                let land_pos = throw_item(entity_id, item_id, start, end, data, msg_log);
 
+                // a noisemaker lands quietly, and goes off once its fuse runs out.
                 // NOTE the radius here is the stone radius, regardless of item type
-                msg_log.log_front(Msg::Sound(entity_id, end, config.sound_radius_stone, false));
+                if data.entities.item.get(&item_id) == Some(&Item::Noisemaker) {
+                    data.entities.fuse.insert(item_id, config.noisemaker_fuse);
+                } else {
+                    msg_log.log_front(Msg::Distract(entity_id, land_pos, config.sound_radius_stone));
+                }
+            }
+
+            Msg::Distract(cause_id, pos, radius) => {
+                distract(cause_id, pos, radius, data, msg_log, config);
+            }
+
+            Msg::Grapple(entity_id, pos) => {
+                grapple(entity_id, pos, data, msg_log, config);
             }
 
             Msg::JumpWall(entity_id, _start, end) => {
@@ -67,7 +91,7 @@ pub fn resolve_messages(data: &mut GameData,
 
 
             Msg::Blink(entity_id) => {
-                if use_energy(entity_id, data) {
+                if use_energy(entity_id, Skill::Blink, data) {
                     resolve_blink(entity_id, data, rng, msg_log);
                 }
             }
@@ -76,24 +100,53 @@ pub fn resolve_messages(data: &mut GameData,
                 pushed_entity(pusher, pushed, direction, push_amount, move_into, data, config, msg_log);
             }
 
+            Msg::Knockback(attacker, knocked_id, direction, damage) => {
+                knockback(attacker, knocked_id, direction, damage, data, msg_log);
+            }
+
+            Msg::Drowned(entity_id) => {
+                killed_entity(entity_id, data, msg_log, config);
+            }
+
             Msg::Yell(entity_id) => {
                 let pos = data.entities.pos[&entity_id];
                 msg_log.log_front(Msg::Sound(entity_id, pos, config.yell_radius, true));
                 data.entities.took_turn[&entity_id] = true;
             }
 
+            Msg::TookTurn(entity_id) => {
+                data.entities.took_turn[&entity_id] = true;
+            }
+
+            Msg::ReadMessage(entity_id, message) => {
+                data.entities.read_message(entity_id, message);
+            }
+
             Msg::Killed(_attacker, attacked, _damage) => {
                 killed_entity(attacked, data, msg_log, config);
             }
 
-            Msg::Attack(attacker, attacked, _damage) => {
+            Msg::Attack(attacker, attacked, _damage) | Msg::CriticalHit(attacker, attacked, _damage) => {
                 // TODO move attack function here, and remove push Msg::Attack in attack function
                 let pos = data.entities.pos[&attacked];
                 msg_log.log_front(Msg::Sound(attacker, pos, config.sound_radius_attack, true)); 
             }
 
+            Msg::ItemBroke(entity_id, _item_id) => {
+                // the pieces of a broken item are left as rubble where it broke
+                let pos = data.entities.pos[&entity_id];
+                if data.map[pos].surface == Surface::Floor && !data.map[pos].block_move {
+                    data.map[pos].surface = Surface::Rubble;
+                }
+            }
+
+            Msg::StealthKill(attacker, attacked) => {
+                let pos = data.entities.pos[&attacked];
+                msg_log.log_front(Msg::Sound(attacker, pos, config.sound_radius_stealth_kill, true)); 
+            }
+
             Msg::SwordSwing(entity_id, item_id, pos) => {
-                sword_swing(entity_id, item_id, pos, data, msg_log);
+                sword_swing(entity_id, item_id, pos, data, msg_log, rng, config);
             }
 
             Msg::HammerSwing(entity_id, item_id, pos) => {
@@ -109,21 +162,46 @@ pub fn resolve_messages(data: &mut GameData,
                 hammer_hit_wall(entity_id, blocked, data, msg_log, config);
             }
 
+            Msg::WallDestroyed(entity_id, blocked) => {
+                wall_destroyed(entity_id, blocked, data, msg_log, config);
+            }
+
             Msg::TryAttack(entity_id, attack_info, attack_pos) => {
-                resolve_attack(entity_id, attack_info, attack_pos, data, msg_log, config);
+                resolve_attack(entity_id, attack_info, attack_pos, data, msg_log, rng, config);
             }
 
             Msg::TryMove(entity_id, direction, amount, move_mode) => {
-                resolve_try_move(entity_id, direction, amount, move_mode, data, msg_log);
+                resolve_try_move(entity_id, direction, amount, move_mode, data, msg_log, config);
             }
 
             Msg::PickUp(entity_id) => {
                 pick_item_up(entity_id, data, msg_log);
             }
 
+            Msg::PickLock(entity_id, container_id) => {
+                pick_lock(entity_id, container_id, data, msg_log);
+            }
+
+            Msg::Smash(entity_id, container_id) => {
+                smash_container(entity_id, container_id, data, msg_log, config);
+            }
+
+            Msg::AlarmRung(entity_id, alarm_id) => {
+                ring_alarm(entity_id, alarm_id, data, msg_log, config);
+            }
+
+            Msg::Scattered(entity_id) => {
+                scatter(entity_id, data, msg_log, config);
+            }
+
             Msg::StateChange(entity_id, behavior) => {
                 data.entities.behavior[&entity_id] = behavior;
 
+                // each new lead renews an investigating monster's interest
+                if let (Behavior::Investigating(_), true) = (behavior, config.investigate_turns > 0) {
+                    data.entities.interest.insert(entity_id, config.investigate_turns);
+                }
+
                 // if the entity hasn't completed a turn, the state change continues their turn.
                 // NOTE this might be better off as a message! emit it every time a state change
                 // occurs?
@@ -133,14 +211,7 @@ pub fn resolve_messages(data: &mut GameData,
             }
 
             Msg::SpikeTrapTriggered(trap, entity_id) => {
-                data.entities.take_damage(entity_id, SPIKE_DAMAGE);
-
-                if data.entities.fighter[&entity_id].hp <= 0 {
-                    data.entities.status[&entity_id].alive = false;
-                    data.entities.blocks[&entity_id] = false;
-
-                    msg_log.log(Msg::Killed(trap, entity_id, SPIKE_DAMAGE));
-                }
+                apply_damage(Some(trap), entity_id, SPIKE_DAMAGE, data, msg_log);
             }
 
             Msg::SoundTrapTriggered(trap, entity_id) => {
@@ -165,17 +236,77 @@ pub fn resolve_messages(data: &mut GameData,
                 }
             }
 
+            Msg::KnockedOut(entity_id, target_id) => {
+                data.entities.status[&target_id].knocked_out = config.knockout_turns;
+                data.entities.behavior[&target_id] = Behavior::Idle;
+                data.entities.took_turn[&entity_id] = true;
+            }
+
+            Msg::WokeUp(entity_id) => {
+                data.entities.status[&entity_id].knocked_out = 0;
+            }
+
+            Msg::BodyDiscovered(entity_id, body_id) => {
+                raise_alarm(entity_id, body_id, data, msg_log, config);
+            }
+
+            Msg::Ignite(pos) => {
+                if data.map[pos].surface == Surface::Grass {
+                    data.map[pos].surface = Surface::Burning;
+                }
+            }
+
+            Msg::Burned(entity_id) => {
+                apply_damage(None, entity_id, FIRE_DAMAGE, data, msg_log);
+            }
+
+            Msg::GasReleased(item_id, pos, typ) => {
+                release_gas(item_id, pos, typ, data, config);
+            }
+
+            Msg::Poisoned(entity_id) => {
+                apply_damage(None, entity_id, POISON_DAMAGE, data, msg_log);
+            }
+
+            Msg::BurnedOut(pos) => {
+                if data.map[pos].surface == Surface::Burning {
+                    data.map[pos].surface = Surface::Rubble;
+                }
+            }
+
             Msg::FreezeTrapTriggered(trap, cause_id) => {
                 freeze_trap_triggered(trap, cause_id, data, msg_log, config);
             }
 
-            Msg::Untriggered(_trigger, _entity_id) => {
-                // NOTE nothing untriggers yet
-                //untriggered(trigger, data, msg_log);
+            Msg::TripwireTriggered(trap, entity_id) => {
+                let source_pos = data.entities.pos[&trap];
+
+                // the triggering entity is considered the source of the alarm
+                msg_log.log(Msg::Sound(entity_id, source_pos, config.sound_radius_tripwire, true));
+            }
+
+            Msg::NetTrapTriggered(_trap, entity_id) => {
+                msg_log.log(Msg::Froze(entity_id, NET_TRAP_NUM_TURNS));
+            }
+
+            Msg::BladeTrapTriggered(trap, entity_id) => {
+                apply_damage(Some(trap), entity_id, BLADE_DAMAGE, data, msg_log);
+            }
+
+            Msg::Untriggered(trigger, entity_id) => {
+                untriggered(trigger, entity_id, data, msg_log);
             }
 
-            Msg::Triggered(trigger, _entity_id) => {
-                triggered(trigger, data);
+            Msg::Triggered(trigger, entity_id) => {
+                triggered(trigger, entity_id, data, msg_log);
+            }
+
+            Msg::MechanismActivated(mechanism_id, _entity_id) => {
+                activate_mechanism(mechanism_id, data, msg_log, config);
+            }
+
+            Msg::ToggleLinked(_mechanism_id, linked_id) => {
+                toggle_linked(linked_id, data);
             }
 
             Msg::AddClass(class) => {
@@ -216,17 +347,10 @@ pub fn resolve_messages(data: &mut GameData,
                 }
             }
 
-            Msg::SwapPrimaryItem => {
-                // TODO make a message for this, and allow resolve to do this.
-                if item_primary_at(player_id, &mut data.entities, 0) &&
-                   item_primary_at(player_id, &mut data.entities, 1) {
-                       let temp_id = data.entities.inventory[&player_id][0];
-
-                       data.entities.inventory[&player_id][0] = 
-                           data.entities.inventory[&player_id][1];
-
-                       data.entities.inventory[&player_id][1] = temp_id;
-               }
+            Msg::SwapPrimaryItem(entity_id) => {
+                if data.entities.swap_hands(entity_id) {
+                    data.entities.took_turn[&entity_id] = true;
+                }
             }
 
             Msg::MoveMode(entity_id, new_move_mode) => {
@@ -240,16 +364,34 @@ pub fn resolve_messages(data: &mut GameData,
                 change_move_mode(entity_id, increase, data, msg_log);
             }
 
-            Msg::DropItem(entity_id, item_index) => {
-                inventory_drop_item(entity_id, item_index as usize, data, msg_log);
+            Msg::DropItem(entity_id, item_id) => {
+                inventory_drop_item(entity_id, item_id, data, msg_log);
+            }
+
+            Msg::ForcedDrop(entity_id, item_id, pos) => {
+                data.entities.remove_item(entity_id, item_id);
+                data.entities.set_pos(item_id, pos);
+            }
+
+            Msg::PlaceItem(entity_id, item_id, pos) => {
+                // the tile was checked when it was chosen, but something may have moved onto it since
+                if data.has_blocking_entity(pos).filter(|id| *id != entity_id).is_none() && data.item_at_pos(pos).is_none() {
+                    data.entities.remove_item(entity_id, item_id);
+                    data.entities.set_pos(item_id, pos);
+                    data.entities.took_turn[&entity_id] = true;
+                } else {
+                    msg_log.log(Msg::DropFailed(entity_id));
+                }
             }
 
             Msg::GrassThrow(entity_id, direction) => {
-                if use_energy(entity_id, data) {
+                if use_energy(entity_id, Skill::GrassThrow, data) {
 
                     let pos = data.entities.pos[&entity_id];
 
-                    for grass_pos in Cone::new(pos, direction, SKILL_GRASS_THROW_RADIUS as i32) {
+                    // the grass covers the thrower's own tile, and a cone in front of them
+                    let cone = Reach::Cone(SKILL_GRASS_THROW_RADIUS - 1).reachables_in_direction(pos, direction);
+                    for grass_pos in std::iter::once(pos).chain(cone) {
                         if data.map.is_within_bounds(grass_pos) && data.map[grass_pos].tile_type == TileType::Empty {
                             data.map[grass_pos].surface = Surface::Grass;
                         }
@@ -259,14 +401,14 @@ pub fn resolve_messages(data: &mut GameData,
             }
 
             Msg::GrassShoes(entity_id, _action_mode) => {
-                if use_energy(entity_id, data) {
+                if use_energy(entity_id, Skill::GrassShoes, data) {
                     data.entities.status[&entity_id].soft_steps = SKILL_GRASS_SHOES_TURNS;
                     data.entities.took_turn[&entity_id] = true;
                 }
             }
 
             Msg::Illuminate(entity_id, pos, amount) => {
-                if use_energy(entity_id, data) {
+                if use_energy(entity_id, Skill::Illuminate, data) {
                     let light = make_light(&mut data.entities, config, pos, msg_log);
                     data.entities.status[&light].illuminate = amount;
 
@@ -275,7 +417,7 @@ pub fn resolve_messages(data: &mut GameData,
             }
 
             Msg::Heal(entity_id, amount) => {
-                if use_energy(entity_id, data) {
+                if use_energy(entity_id, Skill::Heal, data) {
                     data.entities.fighter[&entity_id].hp = 
                         std::cmp::min(data.entities.fighter[&entity_id].max_hp,
                                       data.entities.fighter[&entity_id].hp + amount as i32);
@@ -285,14 +427,14 @@ pub fn resolve_messages(data: &mut GameData,
             }
 
             Msg::FarSight(entity_id, amount) => {
-                if use_energy(entity_id, data) {
+                if use_energy(entity_id, Skill::FarSight, data) {
                     data.entities.status[&entity_id].extra_fov += amount;
                     data.entities.took_turn[&entity_id] = true;
                 }
             }
 
             Msg::Sprint(entity_id, direction, amount) => {
-                if use_energy(entity_id, data) {
+                if use_energy(entity_id, Skill::Sprint, data) {
                     msg_log.log(Msg::TryMove(entity_id, direction, amount, MoveMode::Run));
                     data.entities.took_turn[&entity_id] = true;
                 }
@@ -304,7 +446,7 @@ pub fn resolve_messages(data: &mut GameData,
 
                 if let Some(blocked) = blocked {
                     if data.has_blocking_entity(rubble_pos).is_none() {
-                        if use_energy(entity_id, data) {
+                        if use_energy(entity_id, Skill::Rubble, data) {
                             resolve_rubble(entity_id, blocked, data, msg_log);
                         }
                     }
@@ -312,7 +454,7 @@ pub fn resolve_messages(data: &mut GameData,
             }
 
             Msg::GrassBlade(entity_id, action_mode, direction) => {
-                if use_energy(entity_id, data) {
+                if use_energy(entity_id, Skill::GrassBlade, data) {
                     let pos = data.entities.pos[&entity_id];
 
                     match action_mode {
@@ -331,7 +473,7 @@ pub fn resolve_messages(data: &mut GameData,
                     for target_id in targets {
                         if data.entities.typ[&target_id] == EntityType::Enemy {
                             let attack = Attack::Stab(target_id, false);
-                            resolve_attack(entity_id, attack, attack_pos, data, msg_log, config);
+                            resolve_attack(entity_id, attack, attack_pos, data, msg_log, rng, config);
 
                             data.entities.took_turn[&entity_id] = true;
                             break;
@@ -343,7 +485,7 @@ pub fn resolve_messages(data: &mut GameData,
             Msg::Reform(entity_id, pos) => {
                 if data.map[pos].surface == Surface::Rubble &&
                    data.has_blocking_entity(pos).is_none() {
-                    if use_energy(entity_id, data) {
+                    if use_energy(entity_id, Skill::Reform, data) {
                         data.map[pos].surface = Surface::Floor;
                         data.map[pos].block_move = true;
                         data.map[pos].chr = MAP_WALL;
@@ -353,7 +495,7 @@ pub fn resolve_messages(data: &mut GameData,
             }
 
             Msg::Swap(entity_id, target_id) => {
-                if use_energy(entity_id, data) {
+                if use_energy(entity_id, Skill::Swap, data) {
 
                     let entity_dir = data.entities.direction[&entity_id];
                     let target_dir = data.entities.direction[&target_id];
@@ -371,7 +513,7 @@ pub fn resolve_messages(data: &mut GameData,
             }
 
             Msg::PassWall(entity_id, pos) => {
-                if use_energy(entity_id, data) {
+                if use_energy(entity_id, Skill::PassWall, data) {
                     data.entities.set_pos(entity_id, pos);
                     msg_log.log(Msg::MoveMode(entity_id, MoveMode::Walk));
                     msg_log.log(Msg::Moved(entity_id, MoveType::Move, pos));
@@ -385,8 +527,11 @@ pub fn resolve_messages(data: &mut GameData,
             }
 
             Msg::ArmDisarmTrap(entity_id, trap_id) => {
-                data.entities.armed[&trap_id] = !data.entities.armed[&trap_id];
-                data.entities.took_turn[&entity_id] = true;
+                arm_disarm_trap(entity_id, trap_id, data, msg_log, rng, config);
+            }
+
+            Msg::Shove(entity_id, shoved_id, direction) => {
+                shove(entity_id, shoved_id, direction, data, msg_log);
             }
 
             Msg::PlaceTrap(entity_id, place_pos, trap_id) => {
@@ -396,7 +541,7 @@ pub fn resolve_messages(data: &mut GameData,
 
 
             Msg::Push(entity_id, direction, amount) => {
-                if use_energy(entity_id, data) {
+                if use_energy(entity_id, Skill::Push, data) {
                     resolve_push_skill(entity_id, direction, amount, data, msg_log);
                 }
             }
@@ -444,48 +589,44 @@ fn hammer_swing(entity_id: EntityId, item_id: EntityId, pos: Pos, data: &mut Gam
 
     if let Some(blocked) = data.map.path_blocked_move(entity_pos, pos) {
         msg_log.log_front(Msg::HammerHitWall(entity_id, blocked));
-        data.used_up_item(entity_id, item_id);
+        wear_item(entity_id, item_id, data, msg_log);
     } else if let Some(hit_entity) = data.has_blocking_entity(pos) {
         // we hit another entity!
         msg_log.log_front(Msg::HammerHitEntity(entity_id, hit_entity));
-        data.used_up_item(entity_id, item_id);
+        wear_item(entity_id, item_id, data, msg_log);
     }
 
     data.entities.took_turn[&entity_id] = true;
 }
 
 fn hammer_hit_entity(entity_id: EntityId, hit_entity: EntityId, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
-    let first = data.entities.pos[&entity_id];
-    let second = data.entities.pos[&hit_entity];
-
-    let dxy = sub_pos(second, first);
-    let direction = Direction::from_dxy(dxy.x, dxy.y).unwrap();
-    let amount = 1;
-    msg_log.log(Msg::Pushed(entity_id, hit_entity, direction, amount, false));
-    msg_log.log_front(Msg::Sound(entity_id, second, config.sound_radius_hammer, true));
+    let hit_pos = data.entities.pos[&hit_entity];
+    msg_log.log_front(Msg::Sound(entity_id, hit_pos, config.sound_radius_hammer, true));
 
-    if let Some(fighter) = data.entities.fighter.get(&hit_entity) {
-        let damage = fighter.hp;
-
-        msg_log.log(Msg::Killed(entity_id, hit_entity, damage));
-    }
+    hammer_hit(entity_id, hit_entity, data, msg_log, config);
 }
 
-fn sword_swing(entity_id: EntityId, item_id: EntityId, pos: Pos, data: &mut GameData, msg_log: &mut MsgLog) {
+fn sword_swing(entity_id: EntityId,
+               item_id: EntityId,
+               pos: Pos,
+               data: &mut GameData,
+               msg_log: &mut MsgLog,
+               rng: &mut Rand32,
+               config: &Config) {
     let mut any_hit_entity = false;
 
     let adj_locs = Reach::single(1).reachables(pos);
     for loc in adj_locs {
         if let Some(target_id) = data.has_blocking_entity(loc) {
             if data.entities.status[&target_id].alive {
-                attack(entity_id, target_id, data, msg_log);
+                attack(entity_id, target_id, data, msg_log, rng, config);
                 any_hit_entity = true;
             }
         }
     }
 
     if any_hit_entity {
-        data.used_up_item(entity_id, item_id);
+        wear_item(entity_id, item_id, data, msg_log);
     }
 
     data.entities.took_turn[&entity_id] = true;
@@ -508,8 +649,13 @@ fn freeze_trap_triggered(trap: EntityId, cause_id: EntityId, data: &mut GameData
     }
 }
 
-fn triggered(trigger: EntityId, data: &mut GameData) {
-    if data.entities.name[&trigger] == EntityName::GateTrigger {
+fn triggered(trigger: EntityId, entity_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog) {
+    if data.entities.name[&trigger] == EntityName::PressurePlate {
+        if !data.entities.status[&trigger].active {
+            data.entities.status[&trigger].active = true;
+            msg_log.log(Msg::MechanismActivated(trigger, entity_id));
+        }
+    } else if data.entities.name[&trigger] == EntityName::GateTrigger {
         if data.entities.status[&trigger].active {
             // raise the gate
             data.entities.status[&trigger].active = false;
@@ -540,28 +686,103 @@ fn triggered(trigger: EntityId, data: &mut GameData) {
     }
 }
 
+fn untriggered(trigger: EntityId, entity_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog) {
+    if data.entities.name[&trigger] == EntityName::PressurePlate {
+        // the plate stays down while anything else is still standing on it
+        let trigger_pos = data.entities.pos[&trigger];
+        if data.has_blocking_entity(trigger_pos).is_none() {
+            data.entities.status[&trigger].active = false;
+            msg_log.log(Msg::MechanismActivated(trigger, entity_id));
+        }
+    }
+}
+
+/// Activate a pressure plate or lever, toggling every door, gate, and trap on
+/// the tiles that share the mechanism's link id.
+fn activate_mechanism(mechanism_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
+    let mechanism_pos = data.entities.pos[&mechanism_id];
+
+    if data.entities.name[&mechanism_id] == EntityName::Lever {
+        let pulled = !data.entities.status[&mechanism_id].active;
+        data.entities.status[&mechanism_id].active = pulled;
+        if pulled {
+            data.entities.chr[&mechanism_id] = ENTITY_LEVER_DOWN as char;
+        } else {
+            data.entities.chr[&mechanism_id] = ENTITY_LEVER_UP as char;
+        }
+    }
+
+    msg_log.log(Msg::Sound(mechanism_id, mechanism_pos, config.sound_radius_mechanism, true));
+
+    let link = data.map[mechanism_pos].link;
+    for linked_pos in data.map.linked_positions(link) {
+        match data.map[linked_pos].door_state() {
+            Some(DoorState::Open) => msg_log.log(Msg::CloseDoor(mechanism_id, linked_pos)),
+            Some(DoorState::Closed) => msg_log.log(Msg::OpenDoor(mechanism_id, linked_pos)),
+            _ => {}
+        }
+
+        for linked_id in data.get_entities_at_pos(linked_pos) {
+            if data.entities.name[&linked_id] == EntityName::Gate ||
+               data.entities.trap.get(&linked_id).is_some() {
+                msg_log.log(Msg::ToggleLinked(mechanism_id, linked_id));
+            }
+        }
+    }
+}
+
+/// Raise or lower a gate, or arm or disarm a trap, from a linked mechanism.
+/// A gate will not close on anything standing under it.
+fn toggle_linked(linked_id: EntityId, data: &mut GameData) {
+    if data.entities.name[&linked_id] == EntityName::Gate {
+        let gate_pos = data.entities.pos[&linked_id];
+        if data.entities.blocks[&linked_id] {
+            data.entities.blocks[&linked_id] = false;
+            data.entities.chr[&linked_id] = ENTITY_GATE_OPEN as char;
+        } else if data.has_blocking_entity(gate_pos).is_none() {
+            data.entities.blocks[&linked_id] = true;
+            data.entities.chr[&linked_id] = ENTITY_GATE_CLOSED as char;
+        }
+    } else if data.entities.trap.get(&linked_id).is_some() {
+        data.entities.armed[&linked_id] = !data.entities.armed[&linked_id];
+        data.entities.trap[&linked_id].rearm_count = 0;
+    }
+}
+
 fn resolve_attack(entity_id: EntityId,
                   attack_info: Attack,
                   attack_pos: Pos,
                   data: &mut GameData,
                   msg_log: &mut MsgLog,
-                  _config: &Config) {
+                  rng: &mut Rand32,
+                  config: &Config) {
     let entity_pos = data.entities.pos[&entity_id];
 
+    // check for a backstab or stealth kill before standing up, as both depend on the attacker sneaking
+    let (backstab, silent) =
+        match attack_info {
+            Attack::Stab(target_id, _) => (can_backstab(data, entity_id, target_id), can_stealth_kill(data, entity_id, target_id)),
+            _ => (false, false),
+        };
+
     // any time an entity attacks, they change to standing stance
     data.entities.stance[&entity_id] = Stance::Standing;
 
     // we already checked that this unwrap is safe before calling this function
     match attack_info {
         Attack::Attack(target_id) => {
-            attack(entity_id, target_id, data, msg_log);
+            attack(entity_id, target_id, data, msg_log, rng, config);
         }
 
         Attack::Stab(target_id, move_into) => {
-            stab(entity_id, target_id, &mut data.entities, msg_log);
+            if silent {
+                stealth_kill(entity_id, target_id, data, msg_log);
+            } else {
+                stab(entity_id, target_id, backstab, &mut data.entities, msg_log);
+            }
 
             if let Some(item_id) = data.using(entity_id, Item::Dagger) {
-                data.used_up_item(entity_id, item_id);
+                wear_item(entity_id, item_id, data, msg_log);
             }
 
             if move_into && entity_pos != attack_pos {
@@ -584,14 +805,57 @@ fn resolve_try_move(entity_id: EntityId,
                     amount: usize,
                     move_mode: MoveMode,
                     data: &mut GameData,
-                    msg_log: &mut MsgLog) {
+                    msg_log: &mut MsgLog,
+                    config: &Config) {
     // blinking uses up movement
     if data.entities.status[&entity_id].blinked {
         return;
     }
 
+    // an entity caught in a net spends its turn struggling
+    if amount > 0 && data.entities.status[&entity_id].frozen > 0 {
+        data.entities.took_turn[&entity_id] = true;
+        return;
+    }
+
+    // an entity without the stamina to run, or carrying too much, can only walk
+    let mut move_mode = move_mode;
+    let mut amount = amount;
+    if move_mode == MoveMode::Run &&
+       (!has_stamina(entity_id, config.stamina_run_cost, data) || data.entities.is_encumbered(entity_id, config)) {
+        move_mode = MoveMode::Walk;
+        amount = std::cmp::min(amount, 1);
+    }
+
     data.entities.move_mode[&entity_id] = move_mode;
 
+    // closed doors stop movement. monsters open them instead of moving, while the
+    // player, their partner, and anything else has to wait for them to be opened.
+    if amount > 0 {
+        let entity_pos = data.entities.pos[&entity_id];
+        let next_pos = direction.offset_pos(entity_pos, 1);
+        if data.map.is_within_bounds(next_pos) {
+            match data.map[next_pos].door_state() {
+                Some(DoorState::Closed) => {
+                    if data.entities.typ[&entity_id] == EntityType::Enemy && data.entities.ai.get(&entity_id).is_some() {
+                        msg_log.log(Msg::OpenDoor(entity_id, next_pos));
+                    }
+                    return;
+                }
+
+                Some(DoorState::Locked) => {
+                    return;
+                }
+
+                _ => {}
+            }
+
+            if data.map[next_pos].tile_type == TileType::Water && data.sinks_in_water(entity_id) {
+                return;
+            }
+        }
+    }
+
     let reach = data.entities.movement[&entity_id];
     let reach = reach.with_dist(1);
 
@@ -602,13 +866,20 @@ fn resolve_try_move(entity_id: EntityId,
             movement::calculate_move(direction, reach, entity_id, data)
         };
 
+    // vaulting a wall takes more out of an entity than running does
+    if let Some(movement) = maybe_movement {
+        if movement.typ == MoveType::JumpWall && !has_stamina(entity_id, config.stamina_vault_cost, data) {
+            return;
+        }
+    }
+
     if let Some(movement) = maybe_movement {
         if let Some(attack) = movement.attack {
             // if the movement creates an attack, attempt to perform the attack
             msg_log.log(Msg::TryAttack(entity_id, attack, movement.pos));
         } else {
             // otherwise attempt to resolve a movement
-            resolve_try_movement(entity_id, direction, amount, move_mode, movement, data, msg_log);
+            resolve_try_movement(entity_id, direction, amount, move_mode, movement, data, msg_log, config);
         }
     } else {
         // monsters that are not idle, but their movement does not change their
@@ -628,7 +899,8 @@ fn resolve_try_movement(entity_id: EntityId,
                         move_mode: MoveMode,
                         movement: Movement,
                         data: &mut GameData,
-                        msg_log: &mut MsgLog) {
+                        msg_log: &mut MsgLog,
+                        config: &Config) {
     let entity_pos = data.entities.pos[&entity_id];
 
     match movement.typ {
@@ -645,6 +917,7 @@ fn resolve_try_movement(entity_id: EntityId,
 
         MoveType::WallKick => {
             data.entities.set_pos(entity_id, movement.pos);
+            use_stamina(entity_id, config.stamina_wall_kick_cost, data);
 
             // NOTE may need to set facing
             // NOTE could check for enemy and attack
@@ -665,7 +938,9 @@ fn resolve_try_movement(entity_id: EntityId,
                 if movement.typ == MoveType::Move {
                     msg_log.log(Msg::Moved(entity_id, movement.typ, movement.pos));
 
-                    if amount > 1 {
+                    // wading through deep water stops a run
+                    let in_water = data.map[movement.pos].tile_type == TileType::Water;
+                    if amount > 1 && !in_water {
                         msg_log.log(Msg::TryMove(entity_id, direction, amount - 1, move_mode));
                     }
                 } else {
@@ -758,47 +1033,80 @@ fn resolve_rubble(entity_id: EntityId, blocked: Blocked, data: &mut GameData, _m
     data.entities.took_turn[&entity_id] = true;
 }
 
+/// A hammer breaks a short wall in one hit. Full walls, both tile walls and tall
+/// intertile walls, take two hits- the first cracks them and the second destroys them.
 fn hammer_hit_wall(entity: EntityId, blocked: Blocked, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
-    let entity_pos = data.entities.pos[&entity];
     let hit_pos = blocked.end_pos;
     if data.map[hit_pos].block_move {
-        if data.map[hit_pos].surface == Surface::Floor {
-            data.map[hit_pos].surface = Surface::Rubble;
+        if data.map[hit_pos].cracked {
+            msg_log.log_front(Msg::WallDestroyed(entity, blocked));
+        } else {
+            data.map[hit_pos].cracked = true;
+            msg_log.log_front(Msg::Sound(entity, hit_pos, config.sound_radius_hammer, true));
         }
+    } else {
+        // a diagonal hit glances off the corner of the walls without breaking them
+        let (wall_loc, left_wall) = match intertile_wall_loc(blocked) {
+            Some(loc) => loc,
+            None => return,
+        };
 
-        data.map[hit_pos].block_move = false;
-        data.map[hit_pos].block_sight = false;
-        data.map[hit_pos].chr = ' ' as u8;
+        let wall = if left_wall {
+            &mut data.map[wall_loc].left_wall
+        } else {
+            &mut data.map[wall_loc].bottom_wall
+        };
 
-        let next_pos = next_from_to(entity_pos, hit_pos);
-        msg_log.log_front(Msg::Crushed(entity, next_pos)); 
-        msg_log.log_front(Msg::Sound(entity, blocked.end_pos, config.sound_radius_attack, true)); 
-    } else {
-        let wall_loc: Pos;
-        let left_wall: bool;
-        if blocked.direction == Direction::Left {
-             wall_loc = blocked.start_pos;
-             left_wall = true;
-        } else if blocked.direction == Direction::Right {
-             wall_loc = blocked.end_pos;
-             left_wall = true;
-         } else if blocked.direction == Direction::Down {
-             wall_loc = blocked.start_pos;
-             left_wall = false;
-         } else if blocked.direction == Direction::Up {
-             wall_loc = blocked.end_pos;
-             left_wall = false;
-         } else {
-            panic!(format!("Hammer direction was not up/down/left/right ({:?})!", blocked));
-         }
+        if *wall == Wall::TallWall {
+            // a tall wall is knocked down to a short wall
+            *wall = Wall::ShortWall;
+            msg_log.log_front(Msg::Sound(entity, hit_pos, config.sound_radius_hammer, true));
+        } else {
+            msg_log.log_front(Msg::WallDestroyed(entity, blocked));
+        }
+    }
+}
 
+/// Remove the wall that was blocked, leaving rubble in place of a tile wall,
+/// and crush whatever was on the other side.
+fn wall_destroyed(entity: EntityId, blocked: Blocked, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
+    let entity_pos = data.entities.pos[&entity];
+    let hit_pos = blocked.end_pos;
+
+    if data.map[hit_pos].block_move {
+        let tile = &mut data.map[hit_pos];
+        tile.tile_type = TileType::Empty;
+        tile.block_move = false;
+        tile.block_sight = false;
+        tile.cracked = false;
+        tile.chr = ' ' as u8;
+        tile.surface = Surface::Rubble;
+
+        let next_pos = next_from_to(entity_pos, hit_pos);
+        msg_log.log_front(Msg::Crushed(entity, next_pos));
+    } else if let Some((wall_loc, left_wall)) = intertile_wall_loc(blocked) {
         if left_wall {
             data.map[wall_loc].left_wall = Wall::Empty;
         } else {
             data.map[wall_loc].bottom_wall = Wall::Empty;
         }
 
-        msg_log.log_front(Msg::Crushed(entity, blocked.end_pos));
+        msg_log.log_front(Msg::Crushed(entity, hit_pos));
+    }
+
+    msg_log.log_front(Msg::Sound(entity, hit_pos, config.sound_radius_wall_destroyed, true));
+}
+
+/// The tile holding an intertile wall that was blocked, and whether it is that
+/// tile's left wall (rather than its bottom wall). A wall blocked on a diagonal
+/// is not a single wall, so there is none.
+fn intertile_wall_loc(blocked: Blocked) -> Option<(Pos, bool)> {
+    match blocked.direction {
+        Direction::Left => return Some((blocked.start_pos, true)),
+        Direction::Right => return Some((blocked.end_pos, true)),
+        Direction::Down => return Some((blocked.start_pos, false)),
+        Direction::Up => return Some((blocked.end_pos, false)),
+        _ => return None,
     }
 }
 
@@ -868,6 +1176,54 @@ fn pushed_entity(pusher: EntityId,
     data.entities.took_turn[&pusher] = true;
 }
 
+/// Knock an entity back a tile, moving it as a normal move would. Being knocked into
+/// a wall or another entity does the given damage instead, and an entity which can't
+/// swim, or is too stunned to, drowns if knocked into water.
+fn knockback(attacker: EntityId, knocked_id: EntityId, direction: Direction, damage: Hp, data: &mut GameData, msg_log: &mut MsgLog) {
+    let pos = data.entities.pos[&knocked_id];
+    let dxy = direction.into_move();
+    let next_pos = add_pos(pos, dxy);
+
+    let move_result = movement::check_collision(pos, dxy.x, dxy.y, data);
+    if move_result.no_collision() {
+        msg_log.log_front(Msg::Moved(knocked_id, MoveType::Move, next_pos));
+
+        // moving turns an entity to face the way it moved, but a knocked back entity keeps its facing
+        let facing = data.entities.direction[&knocked_id];
+        msg_log.log(Msg::SetFacing(knocked_id, facing));
+
+        let stunned = data.entities.status[&knocked_id].frozen > 0;
+        if data.map[next_pos].tile_type == TileType::Water && (stunned || data.sinks_in_water(knocked_id)) {
+            msg_log.log(Msg::Drowned(knocked_id));
+        }
+    } else {
+        data.entities.take_damage(knocked_id, damage);
+        msg_log.log(Msg::KnockedInto(knocked_id, add_pos(pos, dxy), damage));
+
+        if !data.entities.status[&knocked_id].alive {
+            data.entities.blocks[&knocked_id] = false;
+            msg_log.log(Msg::Killed(Some(attacker), knocked_id, damage));
+        }
+    }
+}
+
+/// Hurt an entity that can fight, killing it if it runs out of hp. The source is
+/// what did the damage, such as a trap- fire and gas have no source.
+fn apply_damage(source: Option<EntityId>, target: EntityId, amount: Hp, data: &mut GameData, msg_log: &mut MsgLog) {
+    if data.entities.fighter.get(&target).is_none() {
+        return;
+    }
+
+    data.entities.take_damage(target, amount);
+
+    if data.entities.fighter[&target].hp <= 0 {
+        data.entities.status[&target].alive = false;
+        data.entities.blocks[&target] = false;
+
+        msg_log.log(Msg::Killed(source, target, amount));
+    }
+}
+
 fn crushed(entity_id: EntityId, pos: Pos, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
     data.map[pos].surface = Surface::Rubble;
 
@@ -876,7 +1232,7 @@ fn crushed(entity_id: EntityId, pos: Pos, data: &mut GameData, msg_log: &mut Msg
             continue;
         }
         if let Some(fighter) = data.entities.fighter.get(&crushed_id) {
-            msg_log.log(Msg::Killed(entity_id, crushed_id, fighter.hp));
+            msg_log.log(Msg::Killed(Some(entity_id), crushed_id, fighter.hp));
         } else if data.entities.item.get(&crushed_id).is_none() &&
                   data.entities.name[&crushed_id] != EntityName::Mouse &&
                   data.entities.name[&crushed_id] != EntityName::Cursor {
@@ -888,19 +1244,44 @@ fn crushed(entity_id: EntityId, pos: Pos, data: &mut GameData, msg_log: &mut Msg
     msg_log.log_front(Msg::Sound(entity_id, pos, config.sound_radius_crushed, true));
 }
 
-fn use_energy(entity_id: EntityId, data: &mut GameData) -> bool {
+/// Whether an entity has at least the given stamina. Entities that don't
+/// track stamina never tire.
+fn has_stamina(entity_id: EntityId, amount: u32, data: &GameData) -> bool {
+    if let Some(stamina) = data.entities.stamina.get(&entity_id) {
+        return *stamina >= amount;
+    }
+
+    return true;
+}
+
+fn use_stamina(entity_id: EntityId, amount: u32, data: &mut GameData) {
+    if let Some(stamina) = data.entities.stamina.get_mut(&entity_id) {
+        *stamina = stamina.saturating_sub(amount);
+    }
+}
+
+fn regain_stamina(entity_id: EntityId, data: &mut GameData, config: &Config) {
+    if let Some(stamina) = data.entities.stamina.get_mut(&entity_id) {
+        *stamina = std::cmp::min(*stamina + config.stamina_regen, config.player_stamina);
+    }
+}
+
+/// Spend the energy for a skill, starting its cooldown if the skill could be used.
+fn use_energy(entity_id: EntityId, skill: Skill, data: &mut GameData) -> bool {
     let pos = data.entities.pos[&entity_id];
 
     let class = data.entities.class[&entity_id];
 
+    let cost = skill.energy_cost();
+
     // NOTE this uses the entity's class, not the skill's class
-    let has_energy = data.entities.energy[&entity_id] > 0;
+    let has_energy = data.entities.energy[&entity_id] >= cost;
     let mut enough_energy: bool = false;
     match class {
         EntityClass::General => {
-            if data.entities.energy[&entity_id] > 0 {
+            if has_energy {
                 enough_energy = true;
-                data.entities.energy[&entity_id] -= 1;
+                data.entities.energy[&entity_id] -= cost;
             }
         }
 
@@ -908,7 +1289,7 @@ fn use_energy(entity_id: EntityId, data: &mut GameData) -> bool {
             let free_energy = data.map[pos].surface == Surface::Grass;
             if free_energy || has_energy {
                 if !free_energy && has_energy {
-                    data.entities.energy[&entity_id] -= 1;
+                    data.entities.energy[&entity_id] -= cost;
                 }
 
                 enough_energy = true;
@@ -920,7 +1301,7 @@ fn use_energy(entity_id: EntityId, data: &mut GameData) -> bool {
             let free_energy = data.map[pos].surface == Surface::Rubble;
             if free_energy || has_energy {
                 if !free_energy && has_energy {
-                    data.entities.energy[&entity_id] -= 1;
+                    data.entities.energy[&entity_id] -= cost;
                 }
 
                 enough_energy = true;
@@ -929,20 +1310,26 @@ fn use_energy(entity_id: EntityId, data: &mut GameData) -> bool {
         }
 
         EntityClass::Clockwork => {
-            if data.entities.energy[&entity_id] > 0 {
+            if has_energy {
                 enough_energy = true;
-                data.entities.energy[&entity_id] -= 1;
+                data.entities.energy[&entity_id] -= cost;
             }
         }
 
         EntityClass::Hierophant => {
-            if data.entities.energy[&entity_id] > 0 {
+            if has_energy {
                 enough_energy = true;
-                data.entities.energy[&entity_id] -= 1;
+                data.entities.energy[&entity_id] -= cost;
             }
         }
     }
 
+    if enough_energy {
+        if let Some(cooldowns) = data.entities.cooldowns.get_mut(&entity_id) {
+            cooldowns.insert(skill, skill.cooldown());
+        }
+    }
+
     return enough_energy;
 }
 
@@ -950,11 +1337,88 @@ fn pick_item_up(entity_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog)
     let entity_pos = data.entities.pos[&entity_id];
 
     if let Some(item_id) = data.item_at_pos(entity_pos) {
+        // coins go into the entity's purse rather than taking up a hand
+        if data.entities.item.get(&item_id) == Some(&Item::Coins) {
+            let amount = data.entities.coins.get(&item_id).map_or(0, |coins| *coins);
+            let carried = data.entities.coins.get(&entity_id).map_or(0, |coins| *coins);
+            data.entities.coins.insert(entity_id, carried + amount);
+            data.entities.mark_for_removal(item_id);
+            msg_log.log(Msg::PickedUpCoins(entity_id, amount));
+            return;
+        }
+
         data.entities.pick_up_item(entity_id, item_id);
         msg_log.log(Msg::PickedUp(entity_id, item_id));
     }
 }
 
+/// Each turn spent picking a lock brings it closer to opening, without making a sound.
+fn pick_lock(entity_id: EntityId, container_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog) {
+    if let Some(turns) = data.entities.locked.get_mut(&container_id) {
+        *turns = turns.saturating_sub(1);
+
+        if *turns == 0 {
+            msg_log.log(Msg::OpenContainer(entity_id, container_id));
+        }
+    }
+
+    data.entities.took_turn[&entity_id] = true;
+}
+
+/// Smashing a container is loud, and leaves its contents scattered around the rubble.
+fn smash_container(entity_id: EntityId, container_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
+    let pos = data.entities.pos[&container_id];
+
+    msg_log.log(Msg::Sound(entity_id, pos, config.sound_radius_smash, true));
+
+    let contents = data.entities.inventory[&container_id].drain(..).collect::<Vec<EntityId>>();
+    let mut spill_positions = vec!(pos);
+    spill_positions.extend(floodfill(&data.map, pos, 1).into_iter().filter(|spill_pos| *spill_pos != pos));
+    for (index, item_id) in contents.iter().enumerate() {
+        data.entities.set_pos(*item_id, spill_positions[index % spill_positions.len()]);
+    }
+
+    data.entities.blocks[&container_id] = false;
+    data.entities.mark_for_removal(container_id);
+
+    if data.map[pos].surface == Surface::Floor {
+        data.map[pos].surface = Surface::Rubble;
+    }
+
+    data.entities.took_turn[&entity_id] = true;
+}
+
+/// Ringing an alarm is heard across much of the level, and calls a wave of reinforcements
+/// which arrive one after another at the level's entry points.
+fn ring_alarm(entity_id: EntityId, alarm_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
+    let pos = data.entities.pos[&alarm_id];
+
+    data.entities.armed[&alarm_id] = false;
+    msg_log.log(Msg::Sound(entity_id, pos, config.sound_radius_alarm_bell, true));
+
+    if data.entry_points.len() == 0 {
+        return;
+    }
+
+    for index in 0..config.alarm_wave_size {
+        let entry_pos = data.entry_points[index % data.entry_points.len()];
+        let turns = config.alarm_wave_delay + index * config.alarm_wave_interval;
+        data.reinforcements.push(Reinforcement { name: config.alarm_wave_monster, pos: entry_pos, target: pos, turns });
+    }
+}
+
+/// A disturbed critter makes a racket as it scatters. Birds fly off and leave the level.
+fn scatter(entity_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
+    let pos = data.entities.pos[&entity_id];
+
+    if data.entities.name[&entity_id] == EntityName::Bird {
+        msg_log.log(Msg::Sound(entity_id, pos, config.sound_radius_bird, true));
+        data.entities.mark_for_removal(entity_id);
+    } else {
+        msg_log.log(Msg::Sound(entity_id, pos, config.sound_radius_rat, true));
+    }
+}
+
 fn place_trap(trap_id: EntityId, place_pos: Pos, data: &mut GameData) {
     data.entities.set_pos(trap_id, place_pos);
     data.entities.armed[&trap_id] = true;
@@ -965,7 +1429,7 @@ fn throw_item(player_id: EntityId,
               start_pos: Pos,
               end_pos: Pos,
               data: &mut GameData,
-              msg_log: &mut MsgLog) {
+              msg_log: &mut MsgLog) -> Pos {
     let throw_line = line(start_pos, end_pos);
 
     // get target position in direction of player click
@@ -984,6 +1448,89 @@ fn throw_item(player_id: EntityId,
 
     data.entities.remove_item(player_id, item_id);
     data.entities.took_turn[&player_id] = true;
+
+    // a thrown lantern sets light to any grass it lands in, and bombs burst where they land
+    match data.entities.item.get(&item_id) {
+        Some(Item::Lantern) => msg_log.log(Msg::Ignite(end_pos)),
+        Some(Item::SmokeBomb) => msg_log.log(Msg::GasReleased(item_id, end_pos, GasType::Smoke)),
+        Some(Item::PoisonBomb) => msg_log.log(Msg::GasReleased(item_id, end_pos, GasType::Poison)),
+        _ => {}
+    }
+
+    return end_pos;
+}
+
+/// A noise that draws the attention of any monster that hears it, sending
+/// it to investigate the source even if it was busy with something else.
+fn distract(cause_id: EntityId, pos: Pos, radius: usize, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
+    let sound_aoe = aoe_fill(&data.map, AoeEffect::Sound, pos, radius, config);
+
+    for obj_id in data.within_aoe(&sound_aoe) {
+        if obj_id != cause_id && data.entities.ai.get(&obj_id).is_some() {
+            data.entities.messages[&obj_id].push(Message::Distraction(pos));
+        }
+    }
+
+    msg_log.log_front(Msg::Sound(cause_id, pos, radius, true));
+}
+
+/// Throw a grappling hook in the direction of the given position. If it catches on a
+/// wall or column within reach, the entity is pulled to the tile just before it,
+/// crossing any water in between.
+fn grapple(entity_id: EntityId, pos: Pos, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
+    let start = data.entities.pos[&entity_id];
+    let direction =
+        match Direction::from_positions(start, pos) {
+            Some(direction) => direction,
+            None => return,
+        };
+
+    let mut landing_pos = start;
+    for dist in 1..=config.grapple_range {
+        let next_pos = direction.offset_pos(start, dist as i32);
+        if !data.map.is_within_bounds(next_pos) {
+            return;
+        }
+
+        let is_anchor =
+            data.map[next_pos].block_move ||
+            data.get_entities_at_pos(next_pos).iter().any(|id| data.entities.name[id] == EntityName::Column);
+
+        if is_anchor {
+            if landing_pos != start {
+                msg_log.log(Msg::Grappled(entity_id, next_pos));
+                msg_log.log(Msg::Moved(entity_id, MoveType::Move, landing_pos));
+                msg_log.log(Msg::Sound(entity_id, next_pos, config.sound_radius_grapple, true));
+            }
+            return;
+        }
+
+        // the line is blocked by something that can't be pulled past
+        if data.has_blocking_entity(next_pos).is_some() {
+            return;
+        }
+
+        landing_pos = next_pos;
+    }
+}
+
+/// Burst a bomb, filling its tile with gas and spilling some into the tiles around it.
+/// The bomb is used up.
+fn release_gas(item_id: EntityId, pos: Pos, typ: GasType, data: &mut GameData, config: &Config) {
+    let amount =
+        match typ {
+            GasType::Smoke => config.smoke_bomb_amount,
+            GasType::Poison => config.poison_bomb_amount,
+        };
+
+    data.map.add_gas(pos, typ, amount);
+    for neighbor in data.map.reachable_neighbors(pos) {
+        if data.map.is_within_bounds(neighbor) {
+            data.map.add_gas(neighbor, typ, amount / 2.0);
+        }
+    }
+
+    data.entities.needs_removal[&item_id] = true;
 }
 
 fn find_blink_pos(pos: Pos, rng: &mut Rand32, data: &mut GameData) -> Option<Pos> {
@@ -1029,12 +1576,12 @@ fn change_move_mode(entity_id: EntityId,
 }
 
 fn inventory_drop_item(entity_id: EntityId,
-                       item_index: usize,
+                       item_id: EntityId,
                        data: &mut GameData,
                        msg_log: &mut MsgLog) {
     let player_pos = data.entities.pos[&entity_id];
 
-    if let Some(item_id) = data.entities.inventory[&entity_id].get(item_index).map(|v| *v) {
+    if data.entities.inventory[&entity_id].contains(&item_id) {
         // Find a place to drop the item, without placing it on the same tile
         // as another item.
         let mut found_tile = false;
@@ -1074,16 +1621,125 @@ fn process_interaction(entity_id: EntityId,
         if let Some(_item_id) = data.item_at_pos(pos) {
             msg_log.log(Msg::PickUp(entity_id));
         }
+    } else if let Some(state) = data.map[interact_pos].door_state() {
+        if state == DoorState::Open {
+            msg_log.log(Msg::CloseDoor(entity_id, interact_pos));
+        } else {
+            msg_log.log(Msg::OpenDoor(entity_id, interact_pos));
+        }
+    } else if let Some(shoved_id) = pushable_at(interact_pos, data) {
+        if let Some(direction) = Direction::from_positions(pos, interact_pos) {
+            msg_log.log(Msg::Shove(entity_id, shoved_id, direction));
+        }
     } else {
         for other_id in data.has_entity(interact_pos) {
-            if data.entities.trap.get(&other_id).is_some() {
+            if can_knock_out(data, entity_id, other_id) {
+                msg_log.log(Msg::KnockedOut(entity_id, other_id));
+                break;
+            } else if data.entities.trap.get(&other_id).is_some() {
                 msg_log.log(Msg::ArmDisarmTrap(entity_id, other_id));
                 break;
+            } else if data.entities.name[&other_id] == EntityName::Prisoner &&
+                      data.entities.status[&other_id].alive {
+                // the conversation itself is held by the game, which sees this message
+                msg_log.log(Msg::Talk(entity_id, other_id));
+                break;
+            } else if data.entities.name[&other_id] == EntityName::Lever {
+                msg_log.log(Msg::MechanismActivated(other_id, entity_id));
+                data.entities.took_turn[&entity_id] = true;
+                break;
+            } else if data.entities.is_container(other_id) {
+                // a locked container is picked quietly while sneaking, and smashed open otherwise.
+                // the transfer menu for an open container is shown by the game, which sees this message
+                if data.entities.locked.get(&other_id).map_or(false, |turns| *turns > 0) {
+                    if data.entities.move_mode.get(&entity_id) == Some(&MoveMode::Sneak) {
+                        msg_log.log(Msg::PickLock(entity_id, other_id));
+                    } else {
+                        msg_log.log(Msg::Smash(entity_id, other_id));
+                    }
+                } else {
+                    msg_log.log(Msg::OpenContainer(entity_id, other_id));
+                }
+                break;
             }
         }
     }
 }
 
+/// A monster that finds a knocked out monster wakes it, and calls out,
+/// sending any idle monsters nearby to investigate.
+fn raise_alarm(entity_id: EntityId, body_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
+    let entity_pos = data.entities.pos[&entity_id];
+    let body_pos = data.entities.pos[&body_id];
+
+    msg_log.log(Msg::Sound(entity_id, entity_pos, config.sound_radius_alarm, true));
+
+    for other_id in data.entities.ids.iter() {
+        if *other_id != body_id &&
+           data.entities.typ[other_id] == EntityType::Enemy &&
+           data.entities.status[other_id].alive &&
+           data.entities.behavior.get(other_id) == Some(&Behavior::Idle) &&
+           distance(entity_pos, data.entities.pos[other_id]) <= config.sound_radius_alarm as i32 {
+            msg_log.log(Msg::StateChange(*other_id, Behavior::Investigating(body_pos)));
+        }
+    }
+
+    msg_log.log(Msg::WokeUp(body_id));
+    data.entities.took_turn[&entity_id] = true;
+}
+
+fn pushable_at(pos: Pos, data: &GameData) -> Option<EntityId> {
+    if let Some(other_id) = data.has_blocking_entity(pos) {
+        if data.entities.pushable.get(&other_id) == Some(&true) {
+            return Some(other_id);
+        }
+    }
+
+    return None;
+}
+
+/// Shove a heavy object one tile, if the tile behind it is free. Anything
+/// underneath the object at its new position is crushed.
+fn shove(entity_id: EntityId, shoved_id: EntityId, direction: Direction, data: &mut GameData, msg_log: &mut MsgLog) {
+    let shoved_pos = data.entities.pos[&shoved_id];
+    let next_pos = direction.offset_pos(shoved_pos, 1);
+
+    if data.map.is_within_bounds(next_pos) &&
+       data.map.path_blocked_move(shoved_pos, next_pos).is_none() &&
+       data.has_blocking_entity(next_pos).is_none() {
+        data.entities.set_pos(shoved_id, next_pos);
+        msg_log.log_front(Msg::Crushed(shoved_id, next_pos));
+        data.entities.took_turn[&entity_id] = true;
+    }
+}
+
+/// Open a door. Locked doors can only be opened by an entity holding the key.
+fn open_door(entity_id: EntityId, pos: Pos, data: &mut GameData) {
+    match data.map[pos].door_state() {
+        Some(DoorState::Closed) => {
+            data.map[pos].set_door(DoorState::Open);
+            data.entities.took_turn[&entity_id] = true;
+        }
+
+        Some(DoorState::Locked) => {
+            if data.is_in_inventory(entity_id, Item::Key).is_some() {
+                data.map[pos].set_door(DoorState::Open);
+                data.entities.took_turn[&entity_id] = true;
+            }
+        }
+
+        _ => {}
+    }
+}
+
+/// Close an open door, as long as nothing is standing in the doorway.
+fn close_door(entity_id: EntityId, pos: Pos, data: &mut GameData) {
+    if data.map[pos].door_state() == Some(DoorState::Open) && data.has_entities(pos).len() == 0 {
+        data.map[pos].set_door(DoorState::Closed);
+        data.entities.took_turn[&entity_id] = true;
+    }
+}
+
 fn use_item(entity_id: EntityId,
             pos: Pos,
             item_id: EntityId,
@@ -1092,7 +1748,7 @@ fn use_item(entity_id: EntityId,
     let item = data.entities.item[&item_id];
 
     match item {
-        Item::Stone => {
+        Item::Stone | Item::SmokeBomb | Item::PoisonBomb | Item::Noisemaker => {
             let start = data.entities.pos[&entity_id];
             let direction = Direction::from_positions(start, pos).unwrap();
             let max_end = direction.offset_pos(start, PLAYER_THROW_DIST as i32);
@@ -1101,7 +1757,11 @@ fn use_item(entity_id: EntityId,
             msg_log.log(Msg::ItemThrow(entity_id, item_id, start, end));
         }
 
-        Item::Key => {
+        Item::Key | Item::Coins => {
+        }
+
+        Item::Grapple => {
+            msg_log.log(Msg::Grapple(entity_id, pos));
         }
 
         Item::Dagger => {
@@ -1143,6 +1803,11 @@ fn use_item(entity_id: EntityId,
             place_trap(item_id, pos, data);
             data.entities.took_turn[&entity_id] = true;
         }
+
+        Item::Tripwire | Item::NetTrap | Item::BladeTrap => {
+            place_trap(item_id, pos, data);
+            data.entities.took_turn[&entity_id] = true;
+        }
     }
 }
 
@@ -1167,10 +1832,18 @@ fn make_move_sound(entity_id: EntityId,
         sound_radius -= config.sound_grass_radius;
     }
 
+    if data.map[pos].tile_type == TileType::Water {
+        sound_radius += config.sound_water_radius;
+    }
+
     if data.entities.status[&entity_id].soft_steps > 0 {
         sound_radius -= 1;
     }
 
+    if data.entities.is_encumbered(entity_id, config) {
+        sound_radius += config.sound_encumbered_radius;
+    }
+
     msg_log.log_front(Msg::Sound(entity_id, pos, sound_radius, true));
     msg_log.log_front(Msg::Sound(entity_id, original_pos, sound_radius, true));
 }
@@ -1187,26 +1860,55 @@ fn process_moved_message(entity_id: EntityId,
     data.entities.set_pos(entity_id, pos);
     data.entities.took_turn[&entity_id] = true;
 
-    if let Some(move_mode) = data.entities.move_mode.get(&entity_id) {
+    if let Some(move_mode) = data.entities.move_mode.get(&entity_id).copied() {
         if let Some(stance) = data.entities.stance.get(&entity_id) {
             if move_type == MoveType::Pass {
-                data.entities.stance[&entity_id] = stance.waited(*move_mode);
-            } else if *move_mode == MoveMode::Run {
+                data.entities.stance[&entity_id] = stance.waited(move_mode);
+            } else if move_mode == MoveMode::Run {
                 data.entities.stance[&entity_id] = Stance::Running;
-            } else if *move_mode == MoveMode::Sneak {
+
+                // running tramples grass flat
+                if data.map[pos].surface == Surface::Grass {
+                    data.map[pos].surface = Surface::Dirt;
+                }
+            } else if move_mode == MoveMode::Sneak {
                 data.entities.stance[&entity_id] = Stance::Crouching;
-            } else if *move_mode == MoveMode::Walk && *stance == Stance::Crouching {
+            } else if move_mode == MoveMode::Walk && *stance == Stance::Crouching {
                 data.entities.stance[&entity_id] = Stance::Standing;
             }
         }
 
-        // make a noise based on how fast the entity is moving and the terrain
-        if pos != original_pos {
-            make_move_sound(entity_id, original_pos, pos, *move_mode, data, msg_log, config);
+        // make a noise based on how fast the entity is moving and the terrain. critters
+        // are too small to be heard moving about.
+        if pos != original_pos && data.entities.faction.get(&entity_id) != Some(&Faction::Wildlife) {
+            make_move_sound(entity_id, original_pos, pos, move_mode, data, msg_log, config);
+        }
+
+        // running and vaulting tire an entity out, while sneaking or resting lets them recover
+        if move_type == MoveType::JumpWall {
+            use_stamina(entity_id, config.stamina_vault_cost, data);
+        } else if move_type == MoveType::Pass || move_mode == MoveMode::Sneak {
+            regain_stamina(entity_id, data, config);
+        } else if move_mode == MoveMode::Run {
+            use_stamina(entity_id, config.stamina_run_cost, data);
         }
     } else if pos != original_pos {
-        msg_log.log_front(Msg::Sound(entity_id, original_pos, config.sound_radius_monster, true));
-        msg_log.log_front(Msg::Sound(entity_id, pos, config.sound_radius_monster, true));
+        let mut sound_radius = config.sound_radius_monster;
+        if data.map[pos].tile_type == TileType::Water {
+            sound_radius += config.sound_water_radius;
+        }
+
+        msg_log.log_front(Msg::Sound(entity_id, original_pos, sound_radius, true));
+        msg_log.log_front(Msg::Sound(entity_id, pos, sound_radius, true));
+    }
+
+    // swimming into deep water means leaving the heaviest item on the shore
+    if data.map[pos].tile_type == TileType::Water &&
+       data.map.is_within_bounds(original_pos) &&
+       data.map[original_pos].tile_type != TileType::Water {
+        if let Some(item_id) = data.entities.heaviest_item(entity_id) {
+            msg_log.log_front(Msg::ForcedDrop(entity_id, item_id, original_pos));
+        }
     }
 
     // check if player walks on energy
@@ -1221,7 +1923,7 @@ fn process_moved_message(entity_id: EntityId,
     }
 
     if original_pos != pos {
-        resolve_triggered_traps(entity_id, original_pos, data, msg_log);
+        resolve_triggered_traps(entity_id, original_pos, data, msg_log, config);
     }
 
     // if entity is a monster, which is also alert, and there is a path to the player,
@@ -1241,35 +1943,14 @@ fn process_moved_message(entity_id: EntityId,
 fn resolve_triggered_traps(entity_id: EntityId,
                            original_pos: Pos,
                            data: &mut GameData,
-                           msg_log: &mut MsgLog) {
+                           msg_log: &mut MsgLog,
+                           config: &Config) {
     // get a list of triggered traps
     let traps: Vec<EntityId> = data.entities.triggered_traps(data.entities.pos[&entity_id]);
 
     // Check if the entity hit a trap
     for trap in traps.iter() {
-        match data.entities.trap[trap] {
-            Trap::Spikes => {
-                msg_log.log(Msg::SpikeTrapTriggered(*trap, entity_id));
-                data.entities.mark_for_removal(*trap);
-            }
-
-            Trap::Sound => {
-                msg_log.log(Msg::SoundTrapTriggered(*trap, entity_id));
-                data.entities.needs_removal[trap] = true;
-                data.entities.mark_for_removal(*trap);
-            }
-
-            Trap::Blink => {
-                data.entities.status[&entity_id].blinked = true;
-                msg_log.log(Msg::BlinkTrapTriggered(*trap, entity_id));
-                data.entities.mark_for_removal(*trap);
-            }
-
-            Trap::Freeze => {
-                msg_log.log(Msg::FreezeTrapTriggered(*trap, entity_id));
-                data.entities.mark_for_removal(*trap);
-            }
-        }
+        trigger_trap(*trap, entity_id, data, msg_log, config);
     }
 
     // Resolve triggers
@@ -1288,7 +1969,7 @@ fn resolve_triggered_traps(entity_id: EntityId,
            }
 
            if !data.entities.status[key].active {
-               if let Some(wall_pos) = data.entities.gate_pos[key] {
+               if let Some(Some(wall_pos)) = data.entities.gate_pos.get(key).copied() {
                    if data.has_entity(wall_pos).is_none() {
                        data.map[wall_pos] = Tile::wall();
                        data.entities.gate_pos[key] = None;
@@ -1300,6 +1981,86 @@ fn resolve_triggered_traps(entity_id: EntityId,
 }
 
 
+/// Set off a trap. Traps that rearm themselves are disarmed until they are ready
+/// again, and all other traps are used up.
+fn trigger_trap(trap: EntityId, entity_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
+    match data.entities.trap[&trap].typ {
+        TrapType::Spikes => {
+            msg_log.log(Msg::SpikeTrapTriggered(trap, entity_id));
+        }
+
+        TrapType::Sound => {
+            msg_log.log(Msg::SoundTrapTriggered(trap, entity_id));
+        }
+
+        TrapType::Blink => {
+            data.entities.status[&entity_id].blinked = true;
+            msg_log.log(Msg::BlinkTrapTriggered(trap, entity_id));
+        }
+
+        TrapType::Freeze => {
+            msg_log.log(Msg::FreezeTrapTriggered(trap, entity_id));
+        }
+
+        TrapType::Tripwire => {
+            msg_log.log(Msg::TripwireTriggered(trap, entity_id));
+        }
+
+        TrapType::Net => {
+            msg_log.log(Msg::NetTrapTriggered(trap, entity_id));
+        }
+
+        TrapType::Blade => {
+            msg_log.log(Msg::BladeTrapTriggered(trap, entity_id));
+        }
+    }
+
+    let rearm_turns = data.entities.trap[&trap].rearm_turns;
+    if rearm_turns == 0 {
+        data.entities.mark_for_removal(trap);
+    } else {
+        data.entities.armed[&trap] = false;
+        data.entities.trap[&trap].rearm_count = rearm_turns;
+    }
+
+    // anyone who sees a trap go off knows where it is from then on
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let trap_pos = data.entities.pos[&trap];
+    for other_id in data.entities.ids.clone() {
+        let can_know = other_id == player_id || data.entities.ai.get(&other_id).is_some();
+        if can_know && data.entities.status[&other_id].alive && data.pos_in_fov(other_id, trap_pos, config) {
+            let known_traps = data.entities.known_traps.get_or_insert_with(other_id, Vec::new);
+            if !known_traps.contains(&trap) {
+                known_traps.push(trap);
+            }
+        }
+    }
+}
+
+/// Arm a disarmed trap, or try to disarm an armed one. Disarming a
+/// trap has a chance of setting it off instead.
+fn arm_disarm_trap(entity_id: EntityId,
+                   trap_id: EntityId,
+                   data: &mut GameData,
+                   msg_log: &mut MsgLog,
+                   rng: &mut Rand32,
+                   config: &Config) {
+    data.entities.took_turn[&entity_id] = true;
+
+    if data.entities.armed[&trap_id] {
+        if rng_trial(rng, config.trap_disarm_fail_chance) {
+            msg_log.log(Msg::DisarmFailed(entity_id, trap_id));
+            trigger_trap(trap_id, entity_id, data, msg_log, config);
+        } else {
+            data.entities.armed[&trap_id] = false;
+            data.entities.trap[&trap_id].rearm_count = 0;
+        }
+    } else {
+        data.entities.armed[&trap_id] = true;
+        data.entities.trap[&trap_id].rearm_count = 0;
+    }
+}
+
 fn resolve_ai_attack(entity_id: EntityId,
                      target_id: EntityId,
                      data: &mut GameData,
@@ -0,0 +1,267 @@
+use std::fmt;
+use std::str::FromStr;
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+use roguelike_core::types::*;
+use roguelike_core::map::*;
+use roguelike_core::utils::*;
+
+use crate::game::Game;
+use crate::actions::InputAction;
+use crate::generation::*;
+use crate::make_map::{write_map_xp, entity_map_chr};
+use crate::vault::write_vault;
+use crate::prompt::confirm;
+use crate::step::remove_entity;
+
+
+/// The things that can be placed on the map while in the sandbox.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaletteEntry {
+    Floor,
+    Wall,
+    ShortWall,
+    Water,
+    Grass,
+    Rubble,
+    Door,
+    Entity(EntityName),
+}
+
+impl fmt::Display for PaletteEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaletteEntry::Floor => write!(f, "floor"),
+            PaletteEntry::Wall => write!(f, "wall"),
+            PaletteEntry::ShortWall => write!(f, "shortwall"),
+            PaletteEntry::Water => write!(f, "water"),
+            PaletteEntry::Grass => write!(f, "grass"),
+            PaletteEntry::Rubble => write!(f, "rubble"),
+            PaletteEntry::Door => write!(f, "door"),
+            PaletteEntry::Entity(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl FromStr for PaletteEntry {
+    type Err = String;
+
+    fn from_str(original_str: &str) -> Result<Self, Self::Err> {
+        let s: &mut str = &mut original_str.to_string();
+        s.make_ascii_lowercase();
+
+        for entry in PALETTE.iter() {
+            if entry.to_string() == *s {
+                return Ok(*entry);
+            }
+        }
+
+        return Err(format!("Could not parse '{}' as PaletteEntry", s));
+    }
+}
+
+impl Default for PaletteEntry {
+    fn default() -> PaletteEntry {
+        return PaletteEntry::Wall;
+    }
+}
+
+impl PaletteEntry {
+    pub fn index(self) -> usize {
+        return PALETTE.iter().position(|entry| *entry == self).unwrap();
+    }
+
+    pub fn next(self) -> PaletteEntry {
+        return PALETTE[(self.index() + 1) % PALETTE.len()];
+    }
+
+    pub fn prev(self) -> PaletteEntry {
+        return PALETTE[(self.index() + PALETTE.len() - 1) % PALETTE.len()];
+    }
+}
+
+pub const PALETTE: &[PaletteEntry] = &[
+    PaletteEntry::Floor,
+    PaletteEntry::Wall,
+    PaletteEntry::ShortWall,
+    PaletteEntry::Water,
+    PaletteEntry::Grass,
+    PaletteEntry::Rubble,
+    PaletteEntry::Door,
+    PaletteEntry::Entity(EntityName::Gol),
+    PaletteEntry::Entity(EntityName::Pawn),
+    PaletteEntry::Entity(EntityName::Rook),
+    PaletteEntry::Entity(EntityName::Knight),
+    PaletteEntry::Entity(EntityName::Spire),
+    PaletteEntry::Entity(EntityName::Armil),
+    PaletteEntry::Entity(EntityName::Prisoner),
+    PaletteEntry::Entity(EntityName::Rat),
+    PaletteEntry::Entity(EntityName::Bird),
+    PaletteEntry::Entity(EntityName::Column),
+    PaletteEntry::Entity(EntityName::SoundTrap),
+    PaletteEntry::Entity(EntityName::SpikeTrap),
+    PaletteEntry::Entity(EntityName::BlinkTrap),
+    PaletteEntry::Entity(EntityName::FreezeTrap),
+    PaletteEntry::Entity(EntityName::Tripwire),
+    PaletteEntry::Entity(EntityName::NetTrap),
+    PaletteEntry::Entity(EntityName::BladeTrap),
+    PaletteEntry::Entity(EntityName::PressurePlate),
+    PaletteEntry::Entity(EntityName::Lever),
+    PaletteEntry::Entity(EntityName::Chest),
+    PaletteEntry::Entity(EntityName::Crate),
+    PaletteEntry::Entity(EntityName::Alarm),
+    PaletteEntry::Entity(EntityName::Gate),
+    PaletteEntry::Entity(EntityName::Stone),
+    PaletteEntry::Entity(EntityName::Key),
+    PaletteEntry::Entity(EntityName::Dagger),
+    PaletteEntry::Entity(EntityName::Sword),
+    PaletteEntry::Entity(EntityName::Hammer),
+    PaletteEntry::Entity(EntityName::Shield),
+    PaletteEntry::Entity(EntityName::Lantern),
+    PaletteEntry::Entity(EntityName::SmokeBomb),
+    PaletteEntry::Entity(EntityName::PoisonBomb),
+    PaletteEntry::Entity(EntityName::Noisemaker),
+    PaletteEntry::Entity(EntityName::Grapple),
+    PaletteEntry::Entity(EntityName::Coins),
+    PaletteEntry::Entity(EntityName::Energy),
+    PaletteEntry::Entity(EntityName::Exit),
+];
+
+/// Handle input while in the sandbox. Unlike other game states, the sandbox
+/// changes the map and entities directly rather than emitting messages for a turn.
+pub fn handle_input_sandbox(input_action: InputAction, game: &mut Game) {
+    match input_action {
+        InputAction::Move(dir, _) | InputAction::CursorMove(dir, _, _) => {
+            if let Some(cursor_pos) = game.settings.cursor {
+                let new_pos = add_pos(cursor_pos, dir.into_move());
+                game.settings.cursor = Some(game.data.map.clamp(new_pos));
+            }
+        }
+
+        InputAction::MapClick(_map_loc, map_cell) => {
+            game.settings.cursor = Some(game.data.map.clamp(map_cell));
+            sandbox_place(game);
+        }
+
+        InputAction::IncreaseMoveMode => {
+            game.settings.palette = game.settings.palette.next();
+        }
+
+        InputAction::DecreaseMoveMode => {
+            game.settings.palette = game.settings.palette.prev();
+        }
+
+        InputAction::Pass(_) => {
+            sandbox_place(game);
+        }
+
+        InputAction::Pickup => {
+            if let Some(cursor_pos) = game.settings.cursor {
+                sandbox_remove(game, cursor_pos);
+            }
+        }
+
+        InputAction::SaveMap => {
+            let xp_file = format!("resources/{}.xp", game.config.editor_file);
+            let vault_file = format!("resources/{}.csv", game.config.editor_file);
+
+            let exists = Path::new(&xp_file).exists() || Path::new(&vault_file).exists();
+            if exists && !game.settings.prompt_confirmed {
+                let question = format!("Overwrite the saved map {}?", game.config.editor_file);
+                confirm(&mut game.settings, &question, InputAction::SaveMap);
+                return;
+            }
+
+            // a map which can't be saved is reported, and the sandbox carries on
+            if let Err(err) = write_map_xp(&game.data, &xp_file).and_then(|_| write_vault(&game.data, &vault_file)) {
+                println!("CONSOLE: Map not saved- {}", err);
+                return;
+            }
+            println!("CONSOLE: Saved map to {} and {}", xp_file, vault_file);
+
+            let unsaved = unsaved_entities(&game.data);
+            if unsaved.len() > 0 {
+                let names = unsaved.iter().map(|name| name.to_string()).collect::<Vec<String>>();
+                println!("CONSOLE: Map files can't hold {}, so they were left out", names.join(", "));
+            }
+        }
+
+        _ => {
+        }
+    }
+}
+
+/// The names of the entities on the map which the map files have no character for, and
+/// so are lost when the map is saved. Items being carried are not placed on the map, and
+/// the cursor and mouse are not part of it.
+pub fn unsaved_entities(data: &GameData) -> Vec<EntityName> {
+    let mut names = Vec::new();
+    for entity_id in data.entities.ids.iter() {
+        let name = data.entities.name[entity_id];
+        let held = data.entities.inventory.values().any(|inventory| inventory.contains(entity_id));
+
+        if !held && name != EntityName::Cursor && name != EntityName::Mouse && entity_map_chr(name).is_none() && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    return names;
+}
+
+/// Place the selected palette entry at the cursor.
+fn sandbox_place(game: &mut Game) {
+    if let Some(pos) = game.settings.cursor {
+        match game.settings.palette {
+            PaletteEntry::Floor => game.data.map[pos] = Tile::empty(),
+            PaletteEntry::Wall => game.data.map[pos] = Tile::wall(),
+            PaletteEntry::ShortWall => game.data.map[pos] = Tile::short_wall(),
+            PaletteEntry::Water => game.data.map[pos] = Tile::water(),
+            PaletteEntry::Grass => game.data.map[pos] = Tile::grass(),
+            PaletteEntry::Rubble => game.data.map[pos] = Tile::rubble(),
+            PaletteEntry::Door => game.data.map[pos] = Tile::door(DoorState::Closed),
+            PaletteEntry::Entity(name) => {
+                sandbox_make_entity(game, name, pos);
+            }
+        }
+
+        // placed tiles are always visible, as the sandbox is used to set up the map
+        game.data.map[pos].explored = true;
+    }
+}
+
+fn sandbox_make_entity(game: &mut Game, name: EntityName, pos: Pos) -> EntityId {
+    let entities = &mut game.data.entities;
+    let config = &game.config;
+    let msg_log = &mut game.msg_log;
+
+    match name {
+        EntityName::Rook => return make_rook(entities, config, pos, msg_log),
+        EntityName::Armil => return make_armil(entities, config, pos, msg_log),
+        EntityName::Lantern => return make_lantern(entities, config, pos, msg_log),
+        _ => return make_entity(entities, config, name, pos, msg_log),
+    }
+}
+
+/// Remove every entity at the given position other than the player
+/// and their inventory, and clear the tile back to the floor.
+fn sandbox_remove(game: &mut Game, pos: Pos) {
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    for entity_id in game.data.get_entities_at_pos(pos) {
+        let name = game.data.entities.name[&entity_id];
+        if entity_id == player_id ||
+           name == EntityName::Cursor ||
+           game.data.entities.inventory[&player_id].contains(&entity_id) {
+            continue;
+        }
+
+        remove_entity(entity_id, &mut game.data, &mut game.settings, &mut game.msg_log);
+    }
+
+    let explored = game.data.map[pos].explored;
+    game.data.map[pos] = Tile::empty();
+    game.data.map[pos].explored = explored;
+}
+
@@ -0,0 +1,55 @@
+use roguelike_core::types::*;
+use roguelike_core::config::Config;
+
+
+/// The AI entities in the order they take their turns, after the player has taken theirs.
+/// Stepping the game goes through this same list, so anything forecasting the order
+/// entities act in should use it as well.
+pub fn ai_turn_order(data: &GameData) -> Vec<EntityId> {
+    return data.entities.active_ais();
+}
+
+/// Every entity which acts in a turn, in order- the player first, then each AI.
+pub fn turn_order(data: &GameData) -> Vec<EntityId> {
+    let mut order = Vec::new();
+
+    if let Some(player_id) = data.find_by_name(EntityName::Player) {
+        order.push(player_id);
+    }
+    order.extend(ai_turn_order(data));
+
+    return order;
+}
+
+/// Whether an entity gets to act the given number of turns from now, where 0 is this
+/// turn. Frozen and knocked out entities skip their turns until those count down.
+pub fn acts_in(data: &GameData, entity_id: EntityId, turns_ahead: usize) -> bool {
+    if data.entities.typ[&entity_id] == EntityType::Player {
+        return true;
+    }
+
+    let status = &data.entities.status[&entity_id];
+    return status.frozen <= turns_ahead && status.knocked_out <= turns_ahead;
+}
+
+/// The order the player and the entities they can see will act in, for this turn and
+/// each of the following turns up to the number of turns given.
+pub fn turn_forecast(data: &GameData, config: &Config, num_turns: usize) -> Vec<Vec<EntityId>> {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+
+    let visible = turn_order(data).into_iter()
+                                  .filter(|entity_id| *entity_id == player_id ||
+                                                      (data.map.is_within_bounds(data.entities.pos[entity_id]) &&
+                                                       data.is_in_fov(player_id, *entity_id, config)))
+                                  .collect::<Vec<EntityId>>();
+
+    let mut forecast = Vec::new();
+    for turns_ahead in 0..num_turns {
+        forecast.push(visible.iter()
+                             .filter(|entity_id| acts_in(data, **entity_id, turns_ahead))
+                             .map(|entity_id| *entity_id)
+                             .collect());
+    }
+
+    return forecast;
+}
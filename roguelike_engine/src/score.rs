@@ -0,0 +1,189 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+use roguelike_core::types::*;
+use roguelike_core::constants::*;
+use roguelike_core::config::Difficulty;
+use roguelike_core::messaging::Msg;
+
+use crate::error::{GameError, GameResult, read_yaml};
+
+
+/// What the player has done over the course of a run, used to score it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunStats {
+    pub levels: usize,
+    pub noise: usize, // total radius of the sounds the player made
+    pub kills: usize,
+    pub knockouts: usize,
+    pub items_used: usize,
+}
+
+impl RunStats {
+    /// What was done since the given stats were taken, such as during a single level.
+    pub fn since(&self, start: &RunStats) -> RunStats {
+        return RunStats { levels: self.levels.saturating_sub(start.levels),
+                          noise: self.noise.saturating_sub(start.noise),
+                          kills: self.kills.saturating_sub(start.kills),
+                          knockouts: self.knockouts.saturating_sub(start.knockouts),
+                          items_used: self.items_used.saturating_sub(start.items_used) };
+    }
+
+    /// Count anything the player did in a message.
+    pub fn record(&mut self, msg: &Msg, player_id: EntityId) {
+        match msg {
+            Msg::Sound(cause_id, _pos, radius, _animate) if *cause_id == player_id => {
+                self.noise += radius;
+            }
+
+            Msg::Killed(Some(attacker), _attacked, _hp) if *attacker == player_id => {
+                self.kills += 1;
+            }
+
+            Msg::KnockedOut(entity_id, _target_id) if *entity_id == player_id => {
+                self.knockouts += 1;
+            }
+
+            Msg::UseItem(entity_id, _pos, _item_id) if *entity_id == player_id => {
+                self.items_used += 1;
+            }
+
+            Msg::ItemThrow(entity_id, _item_id, _start, _end) if *entity_id == player_id => {
+                self.items_used += 1;
+            }
+
+            _ => {
+            }
+        }
+    }
+
+    /// Score a run. Finishing levels scores the most, while taking longer, making noise,
+    /// and leaning on items cost a little. Killing costs more, and a run that never
+    /// kills anything gets a bonus for ghosting the whole way through.
+    pub fn score(&self, turns: usize, won: bool) -> i64 {
+        let mut score = self.levels as i64 * SCORE_PER_LEVEL;
+
+        if won {
+            score += SCORE_WIN_BONUS;
+        }
+
+        if self.kills == 0 {
+            score += SCORE_GHOST_BONUS;
+        } else {
+            score -= self.kills as i64 * SCORE_PER_KILL;
+        }
+
+        score -= turns as i64 * SCORE_PER_TURN;
+        score -= self.noise as i64 * SCORE_PER_NOISE;
+        score -= self.items_used as i64 * SCORE_PER_ITEM_USED;
+
+        return std::cmp::max(0, score);
+    }
+}
+
+/// How the level just finished went, shown before moving on to the next one.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LevelSummary {
+    pub level: usize, // the level finished, counting from 1
+    pub turns: usize,
+    pub stats: RunStats, // what the player did on the level alone
+    pub hp: Hp,
+    pub max_hp: Hp,
+}
+
+impl LevelSummary {
+    /// The summary as it is shown on the screen between levels.
+    pub fn lines(&self) -> Vec<String> {
+        return vec!(format!("level {} complete", self.level),
+                    format!("turns {}, noise {}", self.turns, self.stats.noise),
+                    format!("kills {}, knockouts {}", self.stats.kills, self.stats.knockouts),
+                    format!("items used {}", self.stats.items_used),
+                    format!("hp {}/{}", self.hp, self.max_hp));
+    }
+}
+
+/// What killed the player, given the messages from the turn they died in. Fire and
+/// poison kill without an attacker, so they are found from the player's messages.
+pub fn death_cause(msgs: &Vec<Msg>, data: &GameData, player_id: EntityId) -> Option<String> {
+    for msg in msgs.iter() {
+        if let Msg::Killed(attacker, attacked, _damage) = msg {
+            if *attacked != player_id {
+                continue;
+            }
+
+            if let Some(attacker) = attacker {
+                return Some(data.entities.name.get(attacker).map_or("unknown".to_string(), |name| name.to_string()));
+            }
+
+            if msgs.iter().any(|msg| *msg == Msg::Burned(player_id)) {
+                return Some("fire".to_string());
+            } else if msgs.iter().any(|msg| *msg == Msg::Poisoned(player_id)) {
+                return Some("poison".to_string());
+            }
+        }
+    }
+
+    return None;
+}
+
+/// A finished run on the leaderboard.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub seed: u64,
+    pub score: i64,
+    pub won: bool,
+    pub turns: usize,
+    pub stats: RunStats,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    #[serde(default)]
+    pub thumbnail: Option<String>, // an image of the map at the end of the run
+}
+
+/// The best scores for each seed that has been played.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub entries: Vec<ScoreEntry>,
+}
+
+impl Leaderboard {
+    /// Load the leaderboard, starting an empty one if the file doesn't exist yet.
+    pub fn from_file(file_name: &str) -> GameResult<Leaderboard> {
+        if !Path::new(file_name).exists() {
+            return Ok(Leaderboard::default());
+        }
+
+        return read_yaml(file_name);
+    }
+
+    pub fn save(&self, file_name: &str) -> GameResult<()> {
+        let leaderboard_string = serde_yaml::to_string(self).map_err(|err| GameError::write(file_name, err))?;
+        return fs::write(file_name, leaderboard_string).map_err(|err| GameError::write(file_name, err));
+    }
+
+    /// Add a run, keeping only the best LEADERBOARD_SIZE scores for its seed.
+    /// Returns the run's rank for its seed, if it made the board.
+    pub fn add(&mut self, entry: ScoreEntry) -> Option<usize> {
+        let seed = entry.seed;
+        self.entries.push(entry.clone());
+        self.entries.sort_by(|first, second| second.score.cmp(&first.score));
+
+        let mut count = 0;
+        self.entries.retain(|other| {
+            if other.seed != seed {
+                return true;
+            }
+            count += 1;
+            return count <= LEADERBOARD_SIZE;
+        });
+
+        return self.for_seed(seed).iter().position(|other| **other == entry);
+    }
+
+    /// The scores for a seed, best first.
+    pub fn for_seed(&self, seed: u64) -> Vec<&ScoreEntry> {
+        return self.entries.iter().filter(|entry| entry.seed == seed).collect();
+    }
+}
@@ -0,0 +1,112 @@
+use serde::{Serialize, Deserialize};
+
+use roguelike_core::types::*;
+use roguelike_core::messaging::{Msg, MsgLog};
+use roguelike_core::utils::distance_maximum;
+
+use crate::game::GameSettings;
+use crate::actions::{InputAction, change_state};
+
+
+/// What happens on the tile chosen in a selection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SelectionAction {
+    PlaceItem(EntityId),
+}
+
+/// Choosing a tile around the player for an action to happen on, such as where to
+/// put down an item. Tiles which are blocked can't be chosen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Selection {
+    pub action: SelectionAction,
+    pub radius: i32,
+}
+
+impl Selection {
+    pub fn new(action: SelectionAction, radius: i32) -> Selection {
+        return Selection { action, radius };
+    }
+
+    /// Whether a tile can be chosen- it must be within the selection's radius of the
+    /// player, and not be a wall, hold a blocking entity other than the player, or
+    /// already have an item on it.
+    pub fn is_valid(&self, pos: Pos, data: &GameData) -> bool {
+        let player_id = data.find_by_name(EntityName::Player).unwrap();
+        let player_pos = data.entities.pos[&player_id];
+
+        if !data.map.is_within_bounds(pos) || distance_maximum(player_pos, pos) > self.radius {
+            return false;
+        }
+
+        let blocking = data.has_blocking_entity(pos).filter(|id| *id != player_id);
+        return !data.map[pos].block_move && blocking.is_none() && data.item_at_pos(pos).is_none();
+    }
+
+    /// The tiles which can be chosen.
+    pub fn valid_positions(&self, data: &GameData) -> Vec<Pos> {
+        let player_id = data.find_by_name(EntityName::Player).unwrap();
+        let player_pos = data.entities.pos[&player_id];
+
+        let mut positions = Vec::new();
+        for y in -self.radius..=self.radius {
+            for x in -self.radius..=self.radius {
+                let pos = Pos::new(player_pos.x + x, player_pos.y + y);
+                if self.is_valid(pos, data) {
+                    positions.push(pos);
+                }
+            }
+        }
+
+        return positions;
+    }
+}
+
+pub fn start_selection(selection: Selection, settings: &mut GameSettings) {
+    settings.selection = Some(selection);
+    change_state(settings, GameState::Selection);
+}
+
+pub fn end_selection(settings: &mut GameSettings) {
+    settings.selection = None;
+    change_state(settings, GameState::Playing);
+}
+
+/// Handle input while choosing a tile. A direction chooses the tile next to the
+/// player, and passing chooses the player's own tile. Choosing a blocked tile is
+/// refused, leaving the selection open to try again.
+pub fn handle_input_selection(input_action: InputAction,
+                              data: &GameData,
+                              settings: &mut GameSettings,
+                              msg_log: &mut MsgLog) {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = data.entities.pos[&player_id];
+
+    let chosen_pos =
+        match input_action {
+            InputAction::Move(direction, _) => Some(direction.offset_pos(player_pos, 1)),
+            InputAction::Pass(_) => Some(player_pos),
+            InputAction::MapClick(_loc, cell) => Some(cell),
+
+            InputAction::Esc => {
+                end_selection(settings);
+                return;
+            }
+
+            _ => None,
+        };
+
+    if let (Some(pos), Some(selection)) = (chosen_pos, settings.selection) {
+        if !selection.is_valid(pos, data) {
+            msg_log.log(Msg::DropFailed(player_id));
+            return;
+        }
+
+        match selection.action {
+            SelectionAction::PlaceItem(item_id) => {
+                msg_log.log(Msg::PlaceItem(player_id, item_id, pos));
+            }
+        }
+
+        end_selection(settings);
+    }
+}
@@ -0,0 +1,144 @@
+use serde::{Serialize, Deserialize};
+
+use roguelike_core::types::*;
+use roguelike_core::config::Config;
+
+use crate::game::Game;
+use crate::error::{GameResult, read_yaml};
+use crate::actions::{InputAction, change_state};
+use crate::generation::make_entity;
+
+
+/// An item for sale, and how many coins it costs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShopItem {
+    pub item: Item,
+    pub price: usize,
+}
+
+/// What the shopkeeper sells between levels, starting from a given level.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShopStock {
+    pub level: usize, // the first level this stock is sold after
+    pub items: Vec<ShopItem>,
+    pub repair_price: usize, // coins to repair each worn item
+}
+
+impl ShopStock {
+    pub fn from_file(file_name: &str) -> GameResult<Vec<ShopStock>> {
+        return read_yaml(file_name);
+    }
+}
+
+/// The stock sold after the given level- the one with the highest starting level
+/// not past it. There is no shop before the first stock's level.
+pub fn stock_for_level(stocks: &Vec<ShopStock>, level_num: usize) -> Option<&ShopStock> {
+    return stocks.iter()
+                 .filter(|stock| stock.level <= level_num)
+                 .max_by_key(|stock| stock.level);
+}
+
+/// The uses an item has when it is new, if it wears out at all.
+pub fn max_durability(item: Item, config: &Config) -> Option<u32> {
+    match item {
+        Item::Dagger => return Some(config.durability_dagger),
+        Item::Sword => return Some(config.durability_sword),
+        Item::Hammer => return Some(config.durability_hammer),
+        Item::Shield => return Some(config.durability_shield),
+        _ => return None,
+    }
+}
+
+/// The player's items which have been worn down by use.
+pub fn worn_items(game: &Game) -> Vec<EntityId> {
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    let mut worn = Vec::new();
+    for item_id in game.data.entities.inventory[&player_id].iter() {
+        let item = game.data.entities.item[item_id];
+        if let (Some(max), Some(durability)) = (max_durability(item, &game.config), game.data.entities.durability.get(item_id)) {
+            if *durability < max {
+                worn.push(*item_id);
+            }
+        }
+    }
+
+    return worn;
+}
+
+/// Visit the shopkeeper after finishing a level, before the next one is made.
+pub fn open_shop(game: &mut Game) {
+    change_state(&mut game.settings, GameState::Shop);
+}
+
+pub fn leave_shop(game: &mut Game) {
+    change_state(&mut game.settings, GameState::Playing);
+    game.start_next_level();
+}
+
+/// Handle input in the shop. The menu lists the items for sale, then repairing the
+/// player's worn items, then leaving for the next level. Anything the player can't
+/// afford is refused.
+pub fn handle_input_shop(input_action: InputAction, game: &mut Game) {
+    let stock =
+        match stock_for_level(&game.shop, game.settings.level_num) {
+            Some(stock) => stock.clone(),
+            None => {
+                leave_shop(game);
+                return;
+            }
+        };
+
+    match input_action {
+        InputAction::SelectItem(index) => {
+            let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+            let coins = game.data.entities.coins.get(&player_id).map_or(0, |coins| *coins);
+
+            if index < stock.items.len() {
+                let shop_item = &stock.items[index];
+                if shop_item.price <= coins {
+                    let player_pos = game.data.entities.pos[&player_id];
+                    let item_id = make_entity(&mut game.data.entities, &game.config, shop_item.item.name(), player_pos, &mut game.msg_log);
+                    game.data.entities.pick_up_item(player_id, item_id);
+                    game.data.entities.coins[&player_id] = coins - shop_item.price;
+                }
+            } else if index == stock.items.len() {
+                let worn = worn_items(game);
+                let price = stock.repair_price * worn.len();
+                if worn.len() > 0 && price <= coins {
+                    for item_id in worn {
+                        let item = game.data.entities.item[&item_id];
+                        game.data.entities.durability[&item_id] = max_durability(item, &game.config).unwrap();
+                    }
+                    game.data.entities.coins[&player_id] = coins - price;
+                }
+            } else if index == stock.items.len() + 1 {
+                leave_shop(game);
+            }
+        }
+
+        InputAction::Esc => {
+            leave_shop(game);
+        }
+
+        _ => {
+        }
+    }
+}
+
+/// The choices in the shop menu, in the order they are selected.
+pub fn shop_options(game: &Game) -> Vec<String> {
+    let mut options = Vec::new();
+
+    if let Some(stock) = stock_for_level(&game.shop, game.settings.level_num) {
+        for shop_item in stock.items.iter() {
+            options.push(format!("buy {} ({} coins)", game.locale.name(shop_item.item.name()), shop_item.price));
+        }
+
+        let num_worn = worn_items(game).len();
+        options.push(format!("repair {} items ({} coins)", num_worn, stock.repair_price * num_worn));
+    }
+    options.push("leave".to_string());
+
+    return options;
+}
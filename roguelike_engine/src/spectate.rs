@@ -0,0 +1,313 @@
+use std::io::{Write, BufRead, BufReader};
+use std::net::{TcpListener, TcpStream, SocketAddr};
+use std::time::Duration;
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use roguelike_core::types::*;
+use roguelike_core::messaging::Msg;
+use roguelike_core::map::Tile;
+use roguelike_core::constants::SPECTATOR_WRITE_TIMEOUT_MS;
+
+use crate::game::{Game, GameSettings};
+
+
+/// What a spectator is shown of the game- everything needed to draw it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpectatorView {
+    pub data: GameData,
+    pub settings: GameSettings,
+}
+
+/// A change to part of the game, as JSON. Objects and arrays which keep their shape
+/// only list the fields and items which changed, so a turn sends little more than
+/// the entities that moved and the tiles that were explored.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Delta {
+    Set(Value),
+    Remove,
+    Fields(BTreeMap<String, Delta>),
+    Items(Vec<(usize, Delta)>),
+}
+
+/// The change from one value to another, or None if they are the same.
+pub fn diff(old: &Value, new: &Value) -> Option<Delta> {
+    if old == new {
+        return None;
+    }
+
+    match (old, new) {
+        (Value::Object(old_fields), Value::Object(new_fields)) => {
+            let mut fields = BTreeMap::new();
+            for (key, new_value) in new_fields.iter() {
+                match old_fields.get(key) {
+                    Some(old_value) => {
+                        if let Some(delta) = diff(old_value, new_value) {
+                            fields.insert(key.clone(), delta);
+                        }
+                    }
+
+                    None => {
+                        fields.insert(key.clone(), Delta::Set(new_value.clone()));
+                    }
+                }
+            }
+
+            for key in old_fields.keys() {
+                if !new_fields.contains_key(key) {
+                    fields.insert(key.clone(), Delta::Remove);
+                }
+            }
+
+            return Some(Delta::Fields(fields));
+        }
+
+        (Value::Array(old_items), Value::Array(new_items)) if old_items.len() == new_items.len() => {
+            let items = old_items.iter()
+                                 .zip(new_items.iter())
+                                 .enumerate()
+                                 .filter_map(|(index, (old_item, new_item))| diff(old_item, new_item).map(|delta| (index, delta)))
+                                 .collect();
+            return Some(Delta::Items(items));
+        }
+
+        _ => {
+            return Some(Delta::Set(new.clone()));
+        }
+    }
+}
+
+/// Change a value by a delta made with diff. A delta which doesn't fit the value, such as
+/// an item past the end of an array, is an error.
+pub fn apply(value: &mut Value, delta: Delta) -> Result<(), String> {
+    match delta {
+        Delta::Set(new_value) => {
+            *value = new_value;
+        }
+
+        Delta::Remove => {
+            return Err("a field can only be removed from an object".to_string());
+        }
+
+        Delta::Fields(fields) => {
+            let object = value.as_object_mut().ok_or("fields changed on a value which is not an object")?;
+            for (key, delta) in fields {
+                if delta == Delta::Remove {
+                    object.remove(&key);
+                } else {
+                    apply(object.entry(key).or_insert(Value::Null), delta)?;
+                }
+            }
+        }
+
+        Delta::Items(items) => {
+            let array = value.as_array_mut().ok_or("items changed on a value which is not an array")?;
+            for (index, delta) in items {
+                let item = array.get_mut(index).ok_or(format!("item {} changed past the end of an array", index))?;
+                apply(item, delta)?;
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+/// What changed in a turn. The turn's messages say what happened, the map's journal gives
+/// the tiles that changed, and the entities are sent as a change to those last sent. The
+/// map is the bulk of the game, so a turn never sends more of it than was edited.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TurnDelta {
+    pub messages: Vec<Msg>,
+    pub tiles: Vec<(Pos, Tile)>,
+    pub entities: Delta,
+    pub objective: Objective,
+    pub settings: GameSettings,
+}
+
+/// A line sent to spectators. They are sent the whole game when they join or the level
+/// changes, and then what changed each turn.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SpectatorFrame {
+    Start(Value),
+    Turn(TurnDelta),
+}
+
+/// Streams the game to spectators connecting over TCP, as a line of JSON for each frame.
+/// Sending to a spectator gives up after a short time, dropping them, so one who stops
+/// reading can't hold up the game.
+pub struct SpectatorServer {
+    listener: Option<TcpListener>,
+    joining: Vec<TcpStream>, // connections accepted elsewhere, such as a co-op partner's
+    spectators: Vec<TcpStream>,
+    sent_entities: Value, // the entities as the spectators last saw them
+    sent_edits: usize, // how much of the map's journal the spectators have seen
+    sent_level: Option<usize>,
+    sent_state: GameState,
+}
+
+impl SpectatorServer {
+    pub fn bind(address: &str) -> Result<SpectatorServer, String> {
+        let listener = TcpListener::bind(address).map_err(|err| format!("Could not listen for spectators on {}: {}", address, err))?;
+        listener.set_nonblocking(true).map_err(|err| err.to_string())?;
+
+        let mut server = SpectatorServer::new();
+        server.listener = Some(listener);
+        return Ok(server);
+    }
+
+    /// A server with no address of its own, streaming only to the connections it is given.
+    pub fn new() -> SpectatorServer {
+        return SpectatorServer { listener: None,
+                                 joining: Vec::new(),
+                                 spectators: Vec::new(),
+                                 sent_entities: Value::Null,
+                                 sent_edits: 0,
+                                 sent_level: None,
+                                 sent_state: GameState::Playing };
+    }
+
+    pub fn address(&self) -> Option<SocketAddr> {
+        return self.listener.as_ref().and_then(|listener| listener.local_addr().ok());
+    }
+
+    /// Stream to a connection from the next update on.
+    pub fn join(&mut self, stream: TcpStream) -> Result<(), String> {
+        stream.set_nonblocking(false).map_err(|err| err.to_string())?;
+        stream.set_write_timeout(Some(Duration::from_millis(SPECTATOR_WRITE_TIMEOUT_MS))).map_err(|err| err.to_string())?;
+        self.joining.push(stream);
+        return Ok(());
+    }
+
+    pub fn num_spectators(&self) -> usize {
+        return self.spectators.len();
+    }
+
+    /// Send spectators what changed since the last turn, or since the screen changed. Anyone
+    /// who has just joined is sent the whole game, and anyone who has left is dropped.
+    pub fn update(&mut self, game: &Game) -> Result<(), String> {
+        if let Some(listener) = &self.listener {
+            let mut accepted = Vec::new();
+            while let Ok((stream, _address)) = listener.accept() {
+                accepted.push(stream);
+            }
+            for stream in accepted {
+                self.join(stream)?;
+            }
+        }
+        let joined = std::mem::take(&mut self.joining);
+
+        let changed = game.msg_log.turn_messages.len() > 0 || game.settings.state != self.sent_state;
+        if joined.len() == 0 && (!changed || self.spectators.len() == 0) {
+            return Ok(());
+        }
+
+        // a new level, or a turn undone, can't be sent as edits to the map the spectators have
+        let journal = &game.data.map.journal;
+        let restart = self.sent_level != Some(game.settings.level_num) || journal.edits.len() < self.sent_edits;
+
+        let entities = serde_json::to_value(&game.data.entities).map_err(|err| err.to_string())?;
+
+        if restart && self.spectators.len() > 0 {
+            let line = start_line(game)?;
+            send_line(&mut self.spectators, &line);
+        } else if changed && self.spectators.len() > 0 {
+            let mut tiles: Vec<(Pos, Tile)> = journal.edits[self.sent_edits..].iter().map(|edit| (edit.pos, edit.to)).collect();
+            tiles.extend(journal.pending.iter().map(|(pos, _tile)| (*pos, game.data.map[*pos])));
+
+            let delta = TurnDelta { messages: game.msg_log.turn_messages.iter().cloned().collect(),
+                                    tiles,
+                                    entities: diff(&self.sent_entities, &entities).unwrap_or(Delta::Items(Vec::new())),
+                                    objective: game.data.objective,
+                                    settings: game.settings.clone() };
+            let line = frame_line(&SpectatorFrame::Turn(delta))?;
+            send_line(&mut self.spectators, &line);
+        }
+
+        if joined.len() > 0 {
+            let mut joined = joined;
+            send_line(&mut joined, &start_line(game)?);
+            self.spectators.extend(joined);
+        }
+
+        self.sent_entities = entities;
+        self.sent_edits = journal.edits.len();
+        self.sent_level = Some(game.settings.level_num);
+        self.sent_state = game.settings.state;
+
+        return Ok(());
+    }
+}
+
+/// Write a line to each stream, dropping those which have closed or fallen too far behind.
+fn send_line(streams: &mut Vec<TcpStream>, line: &str) {
+    streams.retain_mut(|stream| stream.write_all(line.as_bytes()).is_ok());
+}
+
+fn start_line(game: &Game) -> Result<String, String> {
+    let view = SpectatorView { data: game.data.clone(), settings: game.settings.clone() };
+    let view = serde_json::to_value(&view).map_err(|err| err.to_string())?;
+    return frame_line(&SpectatorFrame::Start(view));
+}
+
+fn frame_line(frame: &SpectatorFrame) -> Result<String, String> {
+    let line = serde_json::to_string(frame).map_err(|err| err.to_string())?;
+    return Ok(format!("{}\n", line));
+}
+
+/// Read the frames sent by a spectator server, one to a line, until the connection closes.
+pub fn read_frames(stream: TcpStream, mut on_frame: impl FnMut(SpectatorFrame) -> bool) -> Result<(), String> {
+    for line in BufReader::new(stream).lines() {
+        let line = line.map_err(|err| err.to_string())?;
+        let frame = serde_json::from_str(&line).map_err(|err| format!("Could not read spectator frame: {}", err))?;
+        if !on_frame(frame) {
+            break;
+        }
+    }
+
+    return Ok(());
+}
+
+/// The game as a spectator sees it, kept up to date from the frames it is sent.
+pub struct Spectator {
+    entities: Value,
+}
+
+impl Spectator {
+    pub fn new() -> Spectator {
+        return Spectator { entities: Value::Null };
+    }
+
+    /// Show a frame in the spectator's game, returning the messages of the turn it was sent for.
+    /// The spectator only watches, so their game never runs any turns itself.
+    pub fn receive(&mut self, frame: SpectatorFrame, game: &mut Game) -> Result<Vec<Msg>, String> {
+        match frame {
+            SpectatorFrame::Start(view) => {
+                let view: SpectatorView = serde_json::from_value(view).map_err(|err| err.to_string())?;
+                self.entities = serde_json::to_value(&view.data.entities).map_err(|err| err.to_string())?;
+                game.data = view.data;
+                game.settings = view.settings;
+                return Ok(Vec::new());
+            }
+
+            SpectatorFrame::Turn(delta) => {
+                apply(&mut self.entities, delta.entities)?;
+                game.data.entities = serde_json::from_value(self.entities.clone()).map_err(|err| err.to_string())?;
+
+                for (pos, tile) in delta.tiles {
+                    if !game.data.map.is_within_bounds(pos) {
+                        return Err(format!("tile ({}, {}) changed off the map", pos.x, pos.y));
+                    }
+                    game.data.map[pos] = tile;
+                }
+
+                game.data.objective = delta.objective;
+                game.settings = delta.settings;
+                game.explore();
+
+                return Ok(delta.messages);
+            }
+        }
+    }
+}
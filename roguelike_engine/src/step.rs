@@ -1,5 +1,7 @@
 use logging_timer::timer;
 
+use rayon::prelude::*;
+
 use roguelike_core::types::*;
 use roguelike_core::config::*;
 use roguelike_core::ai::*;
@@ -7,20 +9,75 @@ use roguelike_core::map::*;
 use roguelike_core::messaging::{Msg, MsgLog};
 use roguelike_core::movement::{Direction, MoveMode};
 #[cfg(test)]
+use roguelike_core::constants::TRIPWIRE_REARM_TURNS;
 use roguelike_core::utils::*;
 
 
 use crate::game::*;
-use crate::actions::InputAction;
+use crate::actions::{InputAction, change_state};
 use crate::generation::*;
 use crate::resolve::resolve_messages;
+use crate::objective::{objective_met, step_objective};
+use crate::schedule::ai_turn_order;
 #[cfg(test)]
 use crate::make_map::*;
+#[cfg(test)]
+use crate::sandbox::PaletteEntry;
+#[cfg(test)]
+use crate::loadout::Loadout;
+#[cfg(test)]
+use crate::dialogue::DialogueNode;
+#[cfg(test)]
+use crate::shop::{ShopStock, ShopItem};
+#[cfg(test)]
+use crate::objective::{choose_objective, describe_objective};
+#[cfg(test)]
+use crate::score::{RunStats, ScoreEntry, Leaderboard};
+#[cfg(test)]
+use roguelike_core::constants::LEADERBOARD_SIZE;
+#[cfg(test)]
+use crate::achievement::Achievement;
+#[cfg(test)]
+use crate::input::alpha_up_to_action;
+#[cfg(test)]
+use crate::help::help_lines;
+#[cfg(test)]
+use crate::hint::action_hints;
+#[cfg(test)]
+use crate::minimap::{minimap_cells, MinimapCell};
+#[cfg(test)]
+use crate::overlay::{Overlay, Overlays, Alertness, enemy_vision, enemy_threat};
+#[cfg(test)]
+use crate::narrate::Narrator;
+#[cfg(test)]
+use crate::schedule::turn_forecast;
+#[cfg(test)]
+use roguelike_core::locale::Locale;
+#[cfg(test)]
+use crate::menu::{MAIN_MENU_ITEMS, PAUSE_MENU_ITEMS, OPTIONS_ITEMS, GAME_OVER_ITEMS};
+#[cfg(test)]
+use crate::inspector::inspect_entity;
+#[cfg(test)]
+use crate::telemetry::*;
+#[cfg(test)]
+use crate::agent::*;
+#[cfg(test)]
+use crate::error::GameError;
+#[cfg(test)]
+use crate::checkpoint::*;
+#[cfg(test)]
+use crate::prompt::confirm;
+#[cfg(test)]
+use crate::ghost::{ghost_file, read_ghost};
+#[cfg(test)]
+use crate::spectate::*;
+#[cfg(test)]
+use crate::coop::CoopHost;
 
 
 pub fn step_logic(game: &mut Game) -> bool {
     // clean up removable entities
-    clean_entities(&mut game.data.entities, &mut game.msg_log);
+    clean_entities(game);
 
     let player_id = game.data.find_by_name(EntityName::Player).unwrap();
 
@@ -29,11 +86,15 @@ pub fn step_logic(game: &mut Game) -> bool {
         game.data.entities.status[id].blinked = false;
     }
 
+    // changes to the map are recorded along with who made them
+    let turn = game.settings.turn_count;
+    game.data.map.begin_edits(turn, Some(player_id));
+
     game.msg_log.log_front(Msg::StartTurn);
 
     resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
 
-    let won_level = level_exit_condition_met(&game.data);
+    let won_level = objective_met(&game.data);
 
     // resolve enemy action
     let monster = timer!("MONSTER");
@@ -44,11 +105,16 @@ pub fn step_logic(game: &mut Game) -> bool {
     }
     drop(monster);
 
+    game.data.map.begin_edits(turn, None);
+
     // send PlayerTurn action in case there is cleanup to perform, or another system
     // needs to know that the turn is finished.
     game.msg_log.log(Msg::PlayerTurn);
     resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
 
+    let took_turn = game.data.entities.took_turn[&player_id];
+    step_objective(&mut game.data, took_turn);
+
     // check status effects
     for entity_id in game.data.entities.ids.iter() {
         if let Some(mut status) = game.data.entities.status.get_mut(entity_id) {
@@ -56,6 +122,13 @@ pub fn step_logic(game: &mut Game) -> bool {
                 status.frozen -= 1;
             }
 
+            if status.knocked_out > 0 {
+                status.knocked_out -= 1;
+                if status.knocked_out == 0 {
+                    game.msg_log.log(Msg::WokeUp(*entity_id));
+                }
+            }
+
             if status.soft_steps > 0 {
                 status.soft_steps -= 1;
             }
@@ -64,35 +137,154 @@ pub fn step_logic(game: &mut Game) -> bool {
 
     if game.data.entities.took_turn[&player_id] {
         game.settings.turn_count += 1;
+
+        // skills recharge a little each turn
+        for cooldowns in game.data.entities.cooldowns.values_mut() {
+            for turns in cooldowns.values_mut() {
+                if *turns > 0 {
+                    *turns -= 1;
+                }
+            }
+        }
+
+        // investigating monsters slowly lose interest in what they were looking for
+        for entity_id in game.data.entities.ids.iter() {
+            if let (Some(Behavior::Investigating(_)), Some(interest)) =
+                (game.data.entities.behavior.get(entity_id), game.data.entities.interest.get_mut(entity_id)) {
+                if *interest > 0 {
+                    *interest -= 1;
+                }
+            }
+        }
+
+        // traps which reset themselves count down until they are armed again
+        for entity_id in game.data.entities.ids.iter() {
+            if let Some(trap) = game.data.entities.trap.get_mut(entity_id) {
+                if trap.rearm_count > 0 {
+                    trap.rearm_count -= 1;
+
+                    if trap.rearm_count == 0 {
+                        game.data.entities.armed[entity_id] = true;
+                    }
+                }
+            }
+        }
+
+        step_fire(game);
+        step_gas(game);
+        game.data.map.decay_sound(game.config.sound_decay);
+        step_fuses(game);
+        step_reinforcements(game);
     }
 
     // perform count down of entities waiting to be removed
     game.data.entities.count_down();
 
-    return level_exit_condition_met(&game.data);
+    game.data.map.commit_edits();
+
+    return objective_met(&game.data);
 }
 
-/// Check whether the exit condition for the game is met.
-fn level_exit_condition_met(data: &GameData) -> bool {
-    // loop over objects in inventory, and check whether any
-    // are the key object.
-    let player_id = data.find_by_name(EntityName::Player).unwrap();
-    let player_pos = data.entities.pos[&player_id];
+/// Burn anything standing in a fire, spread each fire into the grass around it,
+/// and let some fires burn out. Grass that catches fire this turn does not spread
+/// the fire any further until the next turn.
+fn step_fire(game: &mut Game) {
+    let burning = game.data.map.get_all_pos().into_iter()
+                                             .filter(|pos| game.data.map[*pos].surface == Surface::Burning)
+                                             .collect::<Vec<Pos>>();
+
+    for pos in burning {
+        for entity_id in game.data.get_entities_at_pos(pos) {
+            if game.data.entities.status[&entity_id].alive && game.data.entities.fighter.get(&entity_id).is_some() {
+                game.msg_log.log(Msg::Burned(entity_id));
+            }
+        }
+
+        for neighbor in game.data.map.reachable_neighbors(pos) {
+            if game.data.map.is_within_bounds(neighbor) &&
+               game.data.map[neighbor].surface == Surface::Grass &&
+               rng_trial(&mut game.rng, game.config.fire_spread_chance) {
+                game.msg_log.log(Msg::Ignite(neighbor));
+            }
+        }
+
+        if rng_trial(&mut game.rng, game.config.fire_burn_out_chance) {
+            game.msg_log.log(Msg::BurnedOut(pos));
+        }
+    }
+
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+}
+
+/// Poison anything standing in a poison cloud, and then let the gas drift and thin out.
+fn step_gas(game: &mut Game) {
+    for entity_id in game.data.entities.ids.iter() {
+        let pos = game.data.entities.pos[entity_id];
+        if game.data.map.is_within_bounds(pos) &&
+           game.data.map.gas(pos).poison >= game.config.gas_threshold &&
+           game.data.entities.status[entity_id].alive &&
+           game.data.entities.fighter.get(entity_id).is_some() {
+            game.msg_log.log(Msg::Poisoned(*entity_id));
+        }
+    }
+
+    game.data.map.diffuse_gas(game.config.gas_spread, game.config.gas_decay);
+
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+}
 
-    let mut exit_condition = false;
-    if let Some(exit_id) = data.find_by_name(EntityName::Exit) {
-        let exit_pos = data.entities.pos[&exit_id];
+/// Count down the fuses of items lying on the map, setting them off when they run out.
+fn step_fuses(game: &mut Game) {
+    let mut went_off = Vec::new();
+    for entity_id in game.data.entities.ids.iter() {
+        let pos = game.data.entities.pos[entity_id];
+        if !game.data.map.is_within_bounds(pos) {
+            continue;
+        }
+
+        if let Some(fuse) = game.data.entities.fuse.get_mut(entity_id) {
+            if *fuse > 0 {
+                *fuse -= 1;
+            }
+
+            if *fuse == 0 {
+                went_off.push(*entity_id);
+            }
+        }
+    }
 
-        let has_key = data.is_in_inventory(player_id, Item::Key).is_some();
+    for entity_id in went_off {
+        game.data.entities.fuse.remove(&entity_id);
 
-        let on_exit_tile = exit_pos == player_pos;
+        let pos = game.data.entities.pos[&entity_id];
+        game.msg_log.log(Msg::Distract(entity_id, pos, game.config.sound_radius_noisemaker));
+    }
+
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+}
 
-        exit_condition = has_key && on_exit_tile;
+/// Count down the monsters on their way after an alarm. Once one's turns run out it
+/// arrives at its entry point, unless something is standing there, and goes to
+/// investigate where the alarm was raised.
+fn step_reinforcements(game: &mut Game) {
+    let mut waiting = Vec::new();
+    for reinforcement in game.data.reinforcements.clone() {
+        if reinforcement.turns > 0 {
+            waiting.push(Reinforcement { turns: reinforcement.turns - 1, ..reinforcement });
+        } else if game.data.has_blocking_entity(reinforcement.pos).is_some() {
+            waiting.push(reinforcement);
+        } else {
+            let entity_id = make_entity(&mut game.data.entities, &game.config, reinforcement.name, reinforcement.pos, &mut game.msg_log);
+            game.data.entities.behavior.insert(entity_id, Behavior::Investigating(reinforcement.target));
+            game.msg_log.log(Msg::Reinforcement(entity_id, reinforcement.pos));
+        }
     }
+    game.data.reinforcements = waiting;
 
-    return exit_condition;
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
 }
 
+/// Check whether the exit condition for the game is met.
 #[test]
 pub fn test_game_step() {
     let mut config = Config::from_file("../config.yaml");
@@ -101,7 +293,7 @@ pub fn test_game_step() {
     let mut input_action;
 
     let player_id = game.data.find_by_name(EntityName::Player).unwrap();
-    make_map(&MapLoadConfig::Empty, &mut game);
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
     game.data.entities.pos[&player_id] = Pos::new(0, 0);
 
     input_action = InputAction::Move(Direction::Right, MoveMode::Walk);
@@ -125,6 +317,112 @@ pub fn test_game_step() {
     assert_eq!(Pos::new(0, 0), player_pos);
 }
 
+#[test]
+pub fn test_undo_turn() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    // undo does nothing outside of god mode
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    game.step_game(InputAction::Undo, 0.1);
+    assert_eq!(Pos::new(1, 0), game.data.entities.pos[&player_id]);
+
+    game.step_game(InputAction::GodMode, 0.1);
+
+    game.step_game(InputAction::Move(Direction::Down, MoveMode::Walk), 0.1);
+    game.step_game(InputAction::Move(Direction::Down, MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(1, 2), game.data.entities.pos[&player_id]);
+    let turn_count = game.settings.turn_count;
+
+    game.step_game(InputAction::Undo, 0.1);
+    assert_eq!(Pos::new(1, 1), game.data.entities.pos[&player_id]);
+    assert_eq!(turn_count - 1, game.settings.turn_count);
+
+    game.step_game(InputAction::Undo, 0.1);
+    assert_eq!(Pos::new(1, 0), game.data.entities.pos[&player_id]);
+
+    // the turn taken before god mode was enabled was not recorded
+    game.step_game(InputAction::Undo, 0.1);
+    assert_eq!(Pos::new(1, 0), game.data.entities.pos[&player_id]);
+}
+
+#[test]
+pub fn test_sandbox() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    game.step_game(InputAction::Sandbox, 0.1);
+    assert_eq!(GameState::Sandbox, game.settings.state);
+    assert_eq!(Some(Pos::new(0, 0)), game.settings.cursor);
+
+    // place a wall to the right of the player
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    game.settings.palette = "wall".parse::<PaletteEntry>().unwrap();
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(TileType::Wall, game.data.map[(1, 0)].tile_type);
+
+    // place a gol below the player, and check that no turn was taken
+    game.step_game(InputAction::CursorMove(Direction::DownLeft, false, false), 0.1);
+    game.settings.palette = PaletteEntry::Entity(EntityName::Gol);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    let gol_id = game.data.has_entity(Pos::new(0, 1)).unwrap();
+    assert_eq!(EntityName::Gol, game.data.entities.name[&gol_id]);
+    assert_eq!(0, game.settings.turn_count);
+    assert_eq!(Pos::new(0, 0), game.data.entities.pos[&player_id]);
+
+    // removing does not touch the player
+    game.step_game(InputAction::Pickup, 0.1);
+    assert!(!game.data.entities.ids.contains(&gol_id));
+    game.step_game(InputAction::CursorMove(Direction::Up, false, false), 0.1);
+    game.step_game(InputAction::Pickup, 0.1);
+    assert!(game.data.entities.ids.contains(&player_id));
+
+    // a map which can't be saved, here as there is no resources directory, doesn't stop the sandbox
+    game.step_game(InputAction::SaveMap, 0.1);
+    assert_eq!(GameState::Sandbox, game.settings.state);
+
+    // the saved map loads back as it was edited, other than entities the map files can't hold
+    game.data.map[(2, 2)].bottom_wall = Wall::TallWall;
+    game.settings.cursor = Some(Pos::new(3, 3));
+    game.settings.palette = PaletteEntry::Entity(EntityName::Pawn);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    game.settings.cursor = Some(Pos::new(4, 3));
+    game.settings.palette = PaletteEntry::Entity(EntityName::Lantern);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(vec!(EntityName::Lantern), crate::sandbox::unsaved_entities(&game.data));
+
+    let file_name = std::env::temp_dir().join("test_sandbox_map.xp");
+    let file_name = file_name.to_str().unwrap();
+    write_map_xp(&game.data, file_name).unwrap();
+    let mut read_data = GameData::empty(0, 0);
+    let player_pos = read_map_xp(&game.config, &mut read_data, &mut game.msg_log, file_name).unwrap();
+    assert_eq!((0, 0), player_pos);
+    for pos in game.data.map.get_all_pos() {
+        assert_eq!(game.data.map[pos].tile_type, read_data.map[pos].tile_type);
+        assert_eq!(game.data.map[pos].left_wall, read_data.map[pos].left_wall);
+        assert_eq!(game.data.map[pos].bottom_wall, read_data.map[pos].bottom_wall);
+    }
+    let pawn_id = read_data.has_entity(Pos::new(3, 3)).unwrap();
+    assert_eq!(EntityName::Pawn, read_data.entities.name[&pawn_id]);
+    assert_eq!(None, read_data.has_entity(Pos::new(4, 3)));
+
+    // play from here- the wall blocks movement
+    game.step_game(InputAction::Sandbox, 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(0, 0), game.data.entities.pos[&player_id]);
+}
+
 pub fn test_running() {
     let config = Config::from_file("../config.yaml");
     let mut game = Game::new(0, config.clone());
@@ -168,147 +466,2342 @@ pub fn test_running() {
 }
 
 #[test]
-pub fn test_hammer_small_wall() {
-    let config = Config::from_file("../config.yaml");
+pub fn test_doors() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
     let mut game = Game::new(0, config.clone());
-    let mut input_action;
 
     let player_id = game.data.find_by_name(EntityName::Player).unwrap();
-    game.data.map = Map::from_dims(10, 10);
-    let player_pos = Pos::new(4, 4);
-    game.data.entities.pos[&player_id] = player_pos;
-
-
-    game.data.map[player_pos].bottom_wall = Wall::ShortWall;
-
-    let gol_pos = Pos::new(4, 5);
-    let gol = make_gol(&mut game.data.entities, &game.config, gol_pos, &mut game.msg_log);
-
-    let hammer = make_hammer(&mut game.data.entities, &game.config, Pos::new(4, 7), &mut game.msg_log);
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
 
-    game.data.entities.inventory[&player_id].push_front(hammer);
+    game.data.map[(1, 0)] = Tile::door(DoorState::Closed);
+    game.data.map[(0, 1)] = Tile::door(DoorState::Locked);
+
+    // walking into a closed door does not open it or move through it
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(0, 0), game.data.entities.pos[&player_id]);
+    assert_eq!(Some(DoorState::Closed), game.data.map[(1, 0)].door_state());
+
+    // interacting opens the door, and the player can then walk through
+    game.step_game(InputAction::Interact(Some(Direction::Right)), 0.1);
+    assert_eq!(Some(DoorState::Open), game.data.map[(1, 0)].door_state());
+
+    // the map's journal records who opened the door
+    let edit = *game.data.map.journal.edits.last().unwrap();
+    assert_eq!((Some(player_id), Pos::new(1, 0)), (edit.entity_id, edit.pos));
+    assert_eq!(Some(DoorState::Closed), edit.from.door_state());
+    assert_eq!(Some(DoorState::Open), edit.to.door_state());
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(1, 0), game.data.entities.pos[&player_id]);
+
+    // a door can't be closed while standing in it
+    game.step_game(InputAction::Interact(None), 0.1);
+    assert_eq!(Some(DoorState::Open), game.data.map[(1, 0)].door_state());
+
+    game.step_game(InputAction::Move(Direction::Left, MoveMode::Walk), 0.1);
+    game.step_game(InputAction::Interact(Some(Direction::Right)), 0.1);
+    assert_eq!(Some(DoorState::Closed), game.data.map[(1, 0)].door_state());
+
+    // a locked door needs a key
+    game.step_game(InputAction::Interact(Some(Direction::Down)), 0.1);
+    assert_eq!(Some(DoorState::Locked), game.data.map[(0, 1)].door_state());
+
+    let key = make_key(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, key);
+    game.step_game(InputAction::Interact(Some(Direction::Down)), 0.1);
+    assert_eq!(Some(DoorState::Open), game.data.map[(0, 1)].door_state());
+
+    // monsters open doors by walking into them, but wildlife can't
+    game.data.map[(4, 3)] = Tile::door(DoorState::Closed);
+    game.data.map[(4, 5)] = Tile::door(DoorState::Closed);
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(3, 3), &mut game.msg_log);
+    let rat = make_rat(&mut game.data.entities, &game.config, Pos::new(3, 5), &mut game.msg_log);
+    game.msg_log.log(Msg::TryMove(gol, Direction::Right, 1, MoveMode::Walk));
+    game.msg_log.log(Msg::TryMove(rat, Direction::Right, 1, MoveMode::Walk));
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+    assert_eq!(Some(DoorState::Open), game.data.map[(4, 3)].door_state());
+    assert_eq!(Some(DoorState::Closed), game.data.map[(4, 5)].door_state());
+    assert_eq!(Pos::new(3, 5), game.data.entities.pos[&rat]);
+}
 
-    input_action = InputAction::UseItem(Direction::Down, 0);
-    game.step_game(input_action, 0.1);
+#[test]
+pub fn test_pressure_plate() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
 
-    // gol is no longer in entities list after being crushed
-    assert!(game.data.entities.is_dead(gol));
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
 
-    assert!(game.msg_log.turn_messages.iter().any(|msg| {
-        matches!(msg, Msg::HammerHitWall(_, _))
-    }));
+    make_pressure_plate(&mut game.data.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    let gate = make_gate(&mut game.data.entities, &game.config, Pos::new(3, 0), &mut game.msg_log);
+    game.data.map[(2, 2)] = Tile::door(DoorState::Closed);
+    game.data.map[(1, 0)].link = 1;
+    game.data.map[(3, 0)].link = 1;
+    game.data.map[(2, 2)].link = 1;
+
+    // stepping on the plate opens the linked door and raises the gate
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(1, 0), game.data.entities.pos[&player_id]);
+    assert_eq!(Some(DoorState::Open), game.data.map[(2, 2)].door_state());
+    assert!(!game.data.entities.blocks[&gate]);
+
+    // stepping off closes them again
+    game.step_game(InputAction::Move(Direction::Left, MoveMode::Walk), 0.1);
+    assert_eq!(Some(DoorState::Closed), game.data.map[(2, 2)].door_state());
+    assert!(game.data.entities.blocks[&gate]);
+
+    // the closed gate can't be walked through or pushed
+    game.data.entities.pos[&player_id] = Pos::new(2, 0);
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(2, 0), game.data.entities.pos[&player_id]);
+    assert_eq!(Pos::new(3, 0), game.data.entities.pos[&gate]);
+}
 
-    assert_eq!(Surface::Rubble, game.data.map[gol_pos].surface);
+#[test]
+pub fn test_lever() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
 
-    let pawn_pos = Pos::new(3, 4);
-    let pawn = make_pawn(&mut game.data.entities, &game.config, pawn_pos, &mut game.msg_log);
-    assert_eq!(true, game.data.entities.status[&pawn].alive);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
 
-    // add the hammer back and hit the pawn with it to test hitting entities
-    let hammer = make_hammer(&mut game.data.entities, &game.config, Pos::new(4, 7), &mut game.msg_log);
-    game.data.entities.inventory[&player_id].push_front(hammer);
+    make_lever(&mut game.data.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    let spikes = vec!(make_spike_trap(&mut game.data.entities, &game.config, Pos::new(0, 3), &mut game.msg_log),
+                      make_spike_trap(&mut game.data.entities, &game.config, Pos::new(1, 3), &mut game.msg_log));
+    game.data.map[(1, 0)].link = 1;
+    game.data.map[(0, 3)].link = 1;
+    game.data.map[(1, 3)].link = 1;
 
-    input_action = InputAction::UseItem(Direction::Left, 0);
-    game.step_game(input_action, 0.1);
+    // pulling the lever disarms the row of spikes, and pulling it again rearms them
+    game.step_game(InputAction::Interact(Some(Direction::Right)), 0.1);
+    assert!(spikes.iter().all(|spike| !game.data.entities.armed[spike]));
 
-    input_action = InputAction::MapClick(pawn_pos, pawn_pos);
-    game.step_game(input_action, 0.1);
+    game.step_game(InputAction::Interact(Some(Direction::Right)), 0.1);
+    assert!(spikes.iter().all(|spike| game.data.entities.armed[spike]));
+}
 
-    assert!(game.data.entities.is_dead(pawn));
+#[test]
+pub fn test_fire_spreads() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.fire_spread_chance = 1.0;
+    config.fire_burn_out_chance = 0.0;
+    let mut game = Game::new(0, config.clone());
 
-    assert!(game.msg_log.turn_messages.iter().any(|msg| {
-        *msg == Msg::HammerHitEntity(player_id, pawn)
-    }));
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 2);
 
-    assert_ne!(Surface::Rubble, game.data.map[pawn_pos].surface);
+    for pos in vec!((1, 0), (2, 0), (3, 0), (4, 0)) {
+        game.data.map[pos] = Tile::grass();
+    }
+    game.data.map[(2, 0)].left_wall = Wall::ShortWall;
+
+    let lantern = make_lantern(&mut game.data.entities, &game.config, Pos::new(0, 2), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, lantern);
+
+    // the thrown lantern lights the grass, which spreads along the grass one tile a turn
+    game.msg_log.log(Msg::ItemThrow(player_id, lantern, Pos::new(0, 2), Pos::new(2, 0)));
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Surface::Burning, game.data.map[(2, 0)].surface);
+    assert_eq!(Surface::Burning, game.data.map[(3, 0)].surface);
+    assert_eq!(Surface::Grass, game.data.map[(4, 0)].surface);
+
+    // fire does not spread past walls
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Surface::Burning, game.data.map[(4, 0)].surface);
+    assert_eq!(Surface::Grass, game.data.map[(1, 0)].surface);
+
+    // standing in fire hurts
+    let hp = game.data.entities.fighter[&player_id].hp;
+    game.data.entities.pos[&player_id] = Pos::new(3, 0);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(game.data.entities.fighter[&player_id].hp < hp);
+
+    // fire kills without an attacker, so a pawn held in the fire is not its own killer
+    game.data.entities.pos[&player_id] = Pos::new(0, 2);
+    let pawn = make_pawn(&mut game.data.entities, &game.config, Pos::new(3, 0), &mut game.msg_log);
+    game.data.entities.fighter[&pawn].hp = 1;
+    game.data.entities.status[&pawn].frozen = 2;
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(!game.data.entities.status[&pawn].alive);
+    assert!(game.msg_log.turn_messages.iter().any(|msg| matches!(msg, Msg::Killed(None, id, _) if *id == pawn)));
+    assert!(!game.msg_log.turn_messages.iter().any(|msg| matches!(msg, Msg::Killed(Some(_), id, _) if *id == pawn)));
+
+    // fires eventually burn out to rubble
+    game.data.entities.pos[&player_id] = Pos::new(0, 2);
+    game.config.fire_burn_out_chance = 1.0;
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Surface::Rubble, game.data.map[(2, 0)].surface);
+    assert_eq!(Surface::Rubble, game.data.map[(4, 0)].surface);
 }
 
-fn step_ai(game: &mut Game) {
-    let ai_ids: Vec<EntityId> = game.data.entities.active_ais();
-
-    for key in ai_ids.iter() {
-       ai_take_turn(*key, &mut game.data, &game.config, &mut game.msg_log);
-
-       resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+#[test]
+pub fn test_gas_clouds() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
 
-       // if there are remaining messages for an entity, clear them
-       game.data.entities.messages[key].clear();
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 3);
+
+    let target_pos = Pos::new(4, 3);
+    assert!(game.data.pos_in_fov(player_id, target_pos, &game.config));
+
+    let smoke_bomb = make_smoke_bomb(&mut game.data.entities, &game.config, Pos::new(0, 3), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, smoke_bomb);
+
+    // the smoke bomb bursts where it lands, and the smoke hides what is behind it
+    game.msg_log.log(Msg::ItemThrow(player_id, smoke_bomb, Pos::new(0, 3), Pos::new(2, 3)));
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(game.data.map.gas(Pos::new(2, 3)).smoke > 0.0);
+    assert!(!game.data.pos_in_fov(player_id, target_pos, &game.config));
+
+    // the smoke drifts outwards and thins out
+    let smoke_amount = game.data.map.gas(Pos::new(2, 3)).smoke;
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(game.data.map.gas(Pos::new(2, 3)).smoke < smoke_amount);
+    assert!(game.data.map.gas(Pos::new(4, 3)).smoke > 0.0);
+
+    // and eventually clears
+    for _ in 0..40 {
+        game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
     }
+    assert!(game.data.map.gas(Pos::new(2, 3)).is_empty());
+    assert!(game.data.pos_in_fov(player_id, target_pos, &game.config));
+
+    // standing in poison hurts
+    let hp = game.data.entities.fighter[&player_id].hp;
+    game.data.map.add_gas(Pos::new(0, 3), GasType::Poison, game.config.poison_bomb_amount);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(game.data.entities.fighter[&player_id].hp < hp);
 }
 
 #[test]
-fn test_ai_idle_player_in_fov() {
-    let config = Config::from_file("../config.yaml");
-    let mut game = Game::new(0, config);
-    make_map(&MapLoadConfig::Empty, &mut game);
-
-    let start_pos = Pos::new(1, 1);
-    let gol = make_gol(&mut game.data.entities, &game.config, start_pos, &mut game.msg_log);
+pub fn test_water() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
 
     let player_id = game.data.find_by_name(EntityName::Player).unwrap();
-    game.data.entities.pos[&player_id] = add_pos(start_pos, Pos::new(1, 1));
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
 
-    game.msg_log.clear();
-    ai_idle(gol, &mut game.data, &mut game.msg_log, &game.config);
+    for x in 1..5 {
+        game.data.map[(x, 0)] = Tile::water();
+    }
 
-    let player_pos = game.data.entities.pos[&player_id];
+    let dagger = make_dagger(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, dagger);
+    let hammer = make_hammer(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, hammer);
+
+    // wading into water leaves the heaviest item on the shore
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(1, 0), game.data.entities.pos[&player_id]);
+    assert_eq!(Pos::new(0, 0), game.data.entities.pos[&hammer]);
+    assert!(!game.data.entities.inventory[&player_id].contains(&hammer));
+    assert!(game.data.entities.inventory[&player_id].contains(&dagger));
+
+    // running through water only moves one tile
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Run), 0.1);
+    assert_eq!(Pos::new(2, 0), game.data.entities.pos[&player_id]);
+
+    // gols will not walk into water
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(8, 8), &mut game.msg_log);
+    game.data.map[(7, 8)] = Tile::water();
+    game.msg_log.log(Msg::TryMove(gol, Direction::Left, 1, MoveMode::Walk));
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+    assert_eq!(Pos::new(8, 8), game.data.entities.pos[&gol]);
+}
 
-    assert_eq!(game.msg_log.messages[0], Msg::FaceTowards(gol, player_pos));
-    assert_eq!(game.msg_log.messages[1], Msg::StateChange(gol, Behavior::Attacking(player_id)));
+#[test]
+pub fn test_grass_concealment() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(2, 0);
+    game.data.map[(2, 0)] = Tile::grass();
+
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(5, 0), &mut game.msg_log);
+    game.data.entities.direction[&gol] = Direction::Left;
+
+    // standing in grass does not hide the player
+    game.data.entities.stance[&player_id] = Stance::Standing;
+    assert!(ai_is_in_fov(gol, player_id, &mut game.data, &game.config));
+
+    // crouching in grass does, unless the gol is close by
+    game.data.entities.stance[&player_id] = Stance::Crouching;
+    assert!(!ai_is_in_fov(gol, player_id, &mut game.data, &game.config));
+
+    game.data.entities.pos[&gol] = Pos::new(4, 0);
+    assert!(ai_is_in_fov(gol, player_id, &mut game.data, &game.config));
+
+    // crouching on bare floor is not hidden either
+    game.data.entities.pos[&gol] = Pos::new(5, 0);
+    game.data.map[(2, 0)] = Tile::empty();
+    assert!(ai_is_in_fov(gol, player_id, &mut game.data, &game.config));
+
+    // running over grass tramples it
+    game.data.entities.pos[&gol] = Pos::new(9, 9);
+    game.data.entities.pos[&player_id] = Pos::new(0, 5);
+    game.data.map[(1, 5)] = Tile::grass();
+    game.data.map[(2, 5)] = Tile::grass();
+    game.data.map[(4, 5)] = Tile::grass();
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Run), 0.1);
+    assert_eq!(Pos::new(2, 5), game.data.entities.pos[&player_id]);
+    assert_eq!(Surface::Dirt, game.data.map[(1, 5)].surface);
+    assert_eq!(Surface::Dirt, game.data.map[(2, 5)].surface);
+    assert_eq!(Surface::Grass, game.data.map[(4, 5)].surface);
 }
 
 #[test]
-fn test_ai_idle_was_attacked() {
-    let config = Config::from_file("../config.yaml");
-    let mut game = Game::new(0, config);
-    make_map(&MapLoadConfig::Empty, &mut game);
+pub fn test_cover() {
+    use roguelike_core::movement::Attack;
+    use roguelike_core::constants::MAX_COVER;
 
-    let start_pos = Pos::new(0, 0);
-    let gol = make_gol(&mut game.data.entities, &game.config, start_pos, &mut game.msg_log);
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
 
     let player_id = game.data.find_by_name(EntityName::Player).unwrap();
-    game.data.entities.pos[&player_id] = add_pos(start_pos, Pos::new(1, 1));
-
-    game.msg_log.clear();
-    // move the player a tile away
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(2, 0);
+    game.data.map[(3, 0)] = Tile::short_wall();
+
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(6, 0), &mut game.msg_log);
+    game.data.entities.direction[&gol] = Direction::Left;
+
+    // a short wall hides a crouching player, but not a standing one
+    game.data.entities.stance[&player_id] = Stance::Standing;
+    assert!(ai_is_in_fov(gol, player_id, &mut game.data, &game.config));
+    game.data.entities.stance[&player_id] = Stance::Crouching;
+    assert!(!ai_is_in_fov(gol, player_id, &mut game.data, &game.config));
+
+    // ranged attacks at a player behind cover sometimes miss
+    game.data.entities.stance[&player_id] = Stance::Standing;
+    game.data.map[(2, 0)] = Tile::grass();
+    game.data.map[(4, 0)] = Tile::grass();
+    assert_eq!(Visibility::Partial(MAX_COVER as u8), game.data.map.line_of_sight(Pos::new(6, 0), Pos::new(2, 0)));
+
+    let mut hits = 0;
+    let mut misses = 0;
+    for _ in 0..20 {
+        let hp = game.data.entities.fighter[&player_id].max_hp;
+        game.data.entities.fighter[&player_id].hp = hp;
+        game.msg_log.log(Msg::TryAttack(gol, Attack::Attack(player_id), Pos::new(2, 0)));
+        resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+
+        if game.data.entities.fighter[&player_id].hp < hp {
+            hits += 1;
+        }
+        misses += game.msg_log.turn_messages.iter().filter(|msg| matches!(msg, Msg::AttackMissed(_, _))).count();
+        game.msg_log.turn_messages.clear();
+    }
+    assert!(misses > hits);
+    assert_eq!(20, hits + misses);
+}
 
-    game.data.entities.pos[&player_id] = add_pos(start_pos, Pos::new(3, 0));
+#[test]
+pub fn test_knight_attack() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
 
-    // place a wall between the player and the gol
-    game.data.map[(2, 0)] = Tile::wall();
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    let player_pos = Pos::new(5, 5);
+    game.data.entities.pos[&player_id] = player_pos;
 
-    // check that no messages are created as the monster can't see the player
-    ai_idle(gol, &mut game.data, &mut game.msg_log, &game.config);
-    dbg!(&game.msg_log.messages);
-    assert_eq!(0, game.msg_log.messages.len());
+    let knight = make_knight(&mut game.data.entities, &game.config, Pos::new(7, 6), &mut game.msg_log);
+    game.data.entities.face(knight, player_pos);
+    let reach = game.data.entities.attack[&knight];
 
-    // if the player attacks, the monster turns and state changes to attacking
-    game.data.entities.messages[&gol].push(Message::Attack(player_id));
-    ai_idle(gol, &mut game.data, &mut game.msg_log, &game.config);
+    // knights attack with a jump, so they can hit the player from an L shape but not from beside them
+    assert_eq!(Some(player_pos), ai_can_hit_target(&mut game.data, knight, player_pos, &reach, &game.config));
 
-    let player_pos = game.data.entities.pos[&player_id];
-    assert_eq!(game.msg_log.messages[0], Msg::FaceTowards(gol, player_pos));
-    assert_eq!(game.msg_log.messages[1], Msg::StateChange(gol, Behavior::Attacking(player_id)));
+    game.data.entities.set_pos(knight, Pos::new(6, 5));
+    game.data.entities.face(knight, player_pos);
+    assert_eq!(None, ai_can_hit_target(&mut game.data, knight, player_pos, &reach, &game.config));
 }
 
 #[test]
-fn test_ai_idle_heard_sound() {
-    let config = Config::from_file("../config.yaml");
-    let mut game = Game::new(0, config);
-    make_map(&MapLoadConfig::Empty, &mut game);
+pub fn test_attack_resolution() {
+    use roguelike_core::combat::*;
+    use roguelike_core::constants::CRITICAL_MULTIPLIER;
+    use roguelike_core::movement::Attack;
 
-    let start_pos = Pos::new(0, 0);
-    let gol = make_gol(&mut game.data.entities, &game.config, start_pos, &mut game.msg_log);
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
 
     let player_id = game.data.find_by_name(EntityName::Player).unwrap();
-    game.data.entities.pos[&player_id] = add_pos(start_pos, Pos::new(1, 1));
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(5, 5);
+    game.data.entities.direction[&player_id] = Direction::Right;
+    game.data.entities.move_mode[&player_id] = MoveMode::Walk;
+
+    let pawn = make_pawn(&mut game.data.entities, &game.config, Pos::new(6, 5), &mut game.msg_log);
+
+    // a pawn watching the player is harder to hit, though less so when sneaking up on it
+    game.data.entities.direction[&pawn] = Direction::Left;
+    assert_eq!(100 - config.hit_chance_facing_penalty, hit_chance(player_id, pawn, &game.data, &game.config));
+    game.data.entities.move_mode[&player_id] = MoveMode::Sneak;
+    assert_eq!(100 - config.hit_chance_facing_penalty + config.hit_chance_stealth_bonus,
+               hit_chance(player_id, pawn, &game.data, &game.config));
+
+    // hitting it from behind always lands, and is a critical hit
+    game.data.entities.direction[&pawn] = Direction::Right;
+    let power = game.data.entities.fighter[&player_id].power;
+    assert_eq!(100, hit_chance(player_id, pawn, &game.data, &game.config));
+    assert_eq!(AttackOutcome::Critical(power * CRITICAL_MULTIPLIER),
+               roll_attack(player_id, pawn, &game.data, &mut game.rng, &game.config));
+
+    // piercing attacks ignore some of the target's defense
+    game.data.entities.fighter[&pawn].defense = 4;
+    assert_eq!(power - 2, attack_damage(player_id, pawn, DamageType::Pierce, false, &game.data));
+    assert_eq!(power - 4, attack_damage(player_id, pawn, DamageType::Slash, false, &game.data));
+
+    // shields block attacks from in front, but not from behind
+    game.config.hit_chance_facing_penalty = 0;
+    let shield = make_shield(&mut game.data.entities, &game.config, Pos::new(5, 5), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, shield);
+    game.data.entities.direction[&player_id] = Direction::Right;
+    assert_eq!(AttackOutcome::ShieldBlocked, roll_attack(pawn, player_id, &game.data, &mut game.rng, &game.config));
+    game.data.entities.direction[&player_id] = Direction::Left;
+    assert_eq!(AttackOutcome::Critical(2), roll_attack(pawn, player_id, &game.data, &mut game.rng, &game.config));
+
+    // blocking knocks the blocker back a tile, and being knocked into a wall hurts
+    game.data.entities.direction[&player_id] = Direction::Right;
+    game.msg_log.log(Msg::TryAttack(pawn, Attack::Attack(player_id), Pos::new(5, 5)));
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+    assert_eq!(Pos::new(4, 5), game.data.entities.pos[&player_id]);
+    assert_eq!(Direction::Right, game.data.entities.direction[&player_id]);
 
-    game.msg_log.clear();
+    game.data.map[(3, 5)] = Tile::wall();
+    let hp = game.data.entities.fighter[&player_id].hp;
+    attack(pawn, player_id, &mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+    assert_eq!(Pos::new(4, 5), game.data.entities.pos[&player_id]);
+    assert_eq!(hp - game.config.knockback_damage, game.data.entities.fighter[&player_id].hp);
+
+    // blunt attacks crush the shield instead
+    let rook = make_rook(&mut game.data.entities, &game.config, Pos::new(5, 4), &mut game.msg_log);
+    assert_eq!(DamageType::Blunt, damage_type(rook, &game.data));
+    attack(rook, player_id, &mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+    assert!(game.data.using(player_id, Item::Shield).is_none());
+    assert!(game.msg_log.messages.contains(&Msg::ShieldCrushed(rook, player_id)));
+}
 
-    // move the player a tile away
-    game.data.entities.pos[&player_id] = add_pos(start_pos, Pos::new(3, 0));
+#[test]
+pub fn test_stamina() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.player_stamina = 2;
+    config.stamina_run_cost = 1;
+    config.stamina_regen = 1;
+    let mut game = Game::new(0, config.clone());
 
-    // place a wall between the player and the gol
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    game.data.entities.stamina[&player_id] = 2;
+
+    // running uses up stamina for each tile run
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Run), 0.1);
+    assert_eq!(Pos::new(2, 0), game.data.entities.pos[&player_id]);
+    assert_eq!(0, game.data.entities.stamina[&player_id]);
+
+    // without stamina the player can only walk
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Run), 0.1);
+    assert_eq!(Pos::new(3, 0), game.data.entities.pos[&player_id]);
+    assert_eq!(MoveMode::Walk, game.data.entities.move_mode[&player_id]);
+
+    // resting recovers stamina, up to the maximum
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(1, game.data.entities.stamina[&player_id]);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(2, game.data.entities.stamina[&player_id]);
+
+    // vaulting a short wall takes more stamina than running
+    game.data.map[(4, 0)].left_wall = Wall::ShortWall;
+    game.data.entities.stamina[&player_id] = 1;
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Run), 0.1);
+    assert_eq!(Pos::new(3, 0), game.data.entities.pos[&player_id]);
+
+    game.data.entities.stamina[&player_id] = 2;
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Run), 0.1);
+    assert_eq!(Pos::new(4, 0), game.data.entities.pos[&player_id]);
+    assert_eq!(0, game.data.entities.stamina[&player_id]);
+}
+
+#[test]
+pub fn test_backstab() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    // walking up behind a gol does not attack it
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    game.data.entities.direction[&gol] = Direction::Right;
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert!(game.data.entities.status[&gol].alive);
+
+    // sneaking up behind it takes it down, even without a dagger
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    game.data.entities.direction[&gol] = Direction::Right;
+    game.data.entities.behavior[&gol] = Behavior::Investigating(Pos::new(5, 0));
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Sneak), 0.1);
+    assert!(!game.data.entities.status[&gol].alive);
+
+    // but not when the gol is facing the player
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(5, 5), &mut game.msg_log);
+    game.data.entities.pos[&player_id] = Pos::new(4, 5);
+    game.data.entities.direction[&gol] = Direction::Left;
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Sneak), 0.1);
+    assert!(game.data.entities.status[&gol].alive);
+}
+
+#[test]
+pub fn test_stealth_kill() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.durability_dagger = 2;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let dagger = make_dagger(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, dagger);
+
+    // sneaking into an unaware gol with a dagger kills it quietly, even from the front
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    game.data.entities.direction[&gol] = Direction::Left;
+    game.data.entities.behavior[&gol] = Behavior::Investigating(Pos::new(5, 0));
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Sneak), 0.1);
+    assert!(!game.data.entities.status[&gol].alive);
+    assert!(game.msg_log.turn_messages.contains(&Msg::StealthKill(player_id, gol)));
+    assert!(game.msg_log.turn_messages.iter().all(|msg| !matches!(msg, Msg::Sound(_, _, radius, _) if *radius == game.config.sound_radius_attack)));
+
+    // the kill dulls the dagger, but it can still be used
+    assert_eq!(1, game.data.entities.durability[&dagger]);
+    assert!(game.data.entities.inventory[&player_id].contains(&dagger));
+
+    // walking into an unaware gol is a normal stab, which uses up the last of the dagger
+    game.data.entities.pos[&player_id] = Pos::new(4, 5);
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(5, 5), &mut game.msg_log);
+    game.data.entities.direction[&gol] = Direction::Left;
+    game.data.entities.behavior[&gol] = Behavior::Investigating(Pos::new(8, 5));
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert!(!game.msg_log.turn_messages.contains(&Msg::StealthKill(player_id, gol)));
+    assert!(!game.data.entities.inventory[&player_id].contains(&dagger));
+}
+
+#[test]
+pub fn test_encumbrance() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let hammer = make_hammer(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, hammer);
+    let shield = make_shield(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, shield);
+    assert_eq!(7, game.data.entities.carried_weight(player_id));
+    assert!(!game.data.entities.is_encumbered(player_id, &game.config));
+
+    // carrying more than the player's capacity keeps them from running, and makes them louder
+    for _ in 0..2 {
+        let stone = make_stone(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+        game.data.entities.pick_up_item(player_id, stone);
+    }
+    assert!(game.data.entities.is_encumbered(player_id, &game.config));
+
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Run), 0.1);
+    assert_eq!(Pos::new(1, 0), game.data.entities.pos[&player_id]);
+    assert_eq!(MoveMode::Walk, game.data.entities.move_mode[&player_id]);
+
+    let radius = game.config.sound_radius_walk + game.config.sound_encumbered_radius;
+    assert!(game.msg_log.turn_messages.contains(&Msg::Sound(player_id, Pos::new(1, 0), radius, true)));
+}
+
+#[test]
+pub fn test_held_items() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let dagger = make_dagger(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, dagger);
+    let stone = make_stone(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, stone);
+    let sword = make_sword(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, sword);
+
+    // weapons go into the player's hands, and everything else into their pack
+    assert_eq!(Some(sword), game.data.entities.held_item(player_id, Hand::Primary));
+    assert_eq!(Some(dagger), game.data.entities.held_item(player_id, Hand::Secondary));
+    assert_eq!(vec!(stone), game.data.entities.pack_items(player_id));
+    assert_eq!(Some(sword), game.data.using(player_id, Item::Sword));
+
+    // swapping hands takes a turn, and the new primary item is the one used
+    let turn_count = game.settings.turn_count;
+    game.step_game(InputAction::SwapPrimaryItem, 0.1);
+    assert_eq!(turn_count + 1, game.settings.turn_count);
+    assert_eq!(Some(dagger), game.data.using(player_id, Item::Dagger));
+    assert_eq!(Some(sword), game.data.entities.held_item(player_id, Hand::Secondary));
+
+    // picking up a weapon with full hands drops the primary item
+    let hammer = make_hammer(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, hammer);
+    assert_eq!(Some(hammer), game.data.entities.held_item(player_id, Hand::Primary));
+    assert_eq!(Pos::new(0, 0), game.data.entities.pos[&dagger]);
+    assert!(!game.data.entities.inventory[&player_id].contains(&dagger));
+
+    // with only one weapon held there is nothing to swap, so no turn is taken
+    game.data.entities.remove_item(player_id, sword);
+    let turn_count = game.settings.turn_count;
+    game.step_game(InputAction::SwapPrimaryItem, 0.1);
+    assert_eq!(turn_count, game.settings.turn_count);
+    assert_eq!(None, game.data.entities.held_item(player_id, Hand::Secondary));
+}
+
+#[test]
+pub fn test_drop_selection() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(5, 5);
+
+    let stone = make_stone(&mut game.data.entities, &game.config, Pos::new(5, 5), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, stone);
+    make_crate(&mut game.data.entities, &game.config, Pos::new(6, 5), &mut game.msg_log);
+
+    // choosing an item to drop asks where to put it
+    game.step_game(InputAction::DropItem, 0.1);
+    game.step_game(InputAction::SelectItem(0), 0.1);
+    assert_eq!(GameState::Selection, game.settings.state);
+
+    // a blocked tile is refused
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(GameState::Selection, game.settings.state);
+    assert!(game.data.entities.inventory[&player_id].contains(&stone));
+
+    game.step_game(InputAction::Move(Direction::Left, MoveMode::Walk), 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert_eq!(Pos::new(4, 5), game.data.entities.pos[&stone]);
+    assert_eq!(Pos::new(5, 5), game.data.entities.pos[&player_id]);
+    assert!(!game.data.entities.inventory[&player_id].contains(&stone));
+}
+
+#[test]
+pub fn test_containers() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.lockpick_turns = 2;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    // containers are stocked from a loot table
+    let chest = make_chest(&mut game.data.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    stock_container(chest, &vec!((Item::Stone, 1)), 1, &mut game);
+    let stone = game.data.entities.inventory[&chest][0];
+    assert_eq!(EntityName::Stone, game.data.entities.name[&stone]);
+    assert_eq!(Pos::new(-1, -1), game.data.entities.pos[&stone]);
+
+    // interacting with a chest opens it, and items can be moved in and out
+    game.step_game(InputAction::Interact(Some(Direction::Right)), 0.1);
+    assert_eq!(GameState::Container, game.settings.state);
+    game.step_game(InputAction::SelectItem(0), 0.1);
+    assert!(game.data.entities.inventory[&player_id].contains(&stone));
+    assert_eq!(0, game.data.entities.inventory[&chest].len());
+    game.step_game(InputAction::SelectItem(0), 0.1);
+    assert!(game.data.entities.inventory[&chest].contains(&stone));
+    game.step_game(InputAction::Esc, 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+
+    // a crate's lock is picked quietly over a few turns while sneaking
+    game.data.entities.pos[&player_id] = Pos::new(4, 5);
+    let crate_id = make_crate(&mut game.data.entities, &game.config, Pos::new(5, 5), &mut game.msg_log);
+    game.data.entities.move_mode[&player_id] = MoveMode::Sneak;
+    game.step_game(InputAction::Interact(Some(Direction::Right)), 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+    game.step_game(InputAction::Interact(Some(Direction::Right)), 0.1);
+    assert_eq!(GameState::Container, game.settings.state);
+    assert!(!game.msg_log.turn_messages.iter().any(|msg| matches!(msg, Msg::Sound(..))));
+    game.step_game(InputAction::Esc, 0.1);
+
+    // smashing a crate is loud, and spills its contents
+    game.data.entities.pos[&player_id] = Pos::new(4, 8);
+    let crate_id2 = make_crate(&mut game.data.entities, &game.config, Pos::new(5, 8), &mut game.msg_log);
+    stock_container(crate_id2, &vec!((Item::Dagger, 1)), 1, &mut game);
+    let dagger = game.data.entities.inventory[&crate_id2][0];
+    game.data.entities.move_mode[&player_id] = MoveMode::Walk;
+    game.step_game(InputAction::Interact(Some(Direction::Right)), 0.1);
+    assert!(game.msg_log.turn_messages.contains(&Msg::Sound(player_id, Pos::new(5, 8), game.config.sound_radius_smash, true)));
+    assert!(game.data.entities.is_dead(crate_id2));
+    assert_eq!(Pos::new(5, 8), game.data.entities.pos[&dagger]);
+    assert!(!game.data.entities.is_dead(crate_id));
+}
+
+#[test]
+pub fn test_knock_out() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.knockout_turns = 3;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    game.data.entities.direction[&gol] = Direction::Right;
+
+    // interacting with an unaware gol knocks it out rather than killing it
+    game.step_game(InputAction::Interact(Some(Direction::Right)), 0.1);
+    assert!(game.data.entities.status[&gol].alive);
+    assert!(game.data.entities.status[&gol].knocked_out > 0);
+
+    // it comes to after a few turns
+    for _ in 0..3 {
+        game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    }
+    assert_eq!(0, game.data.entities.status[&gol].knocked_out);
+
+    // another gol that finds a body wakes it and sends others to investigate
+    game.data.entities.pos[&player_id] = Pos::new(0, 9);
+    game.data.entities.behavior[&gol] = Behavior::Idle;
+    game.data.entities.status[&gol].knocked_out = 5;
+    game.data.entities.pos[&gol] = Pos::new(5, 5);
+
+    let finder = make_gol(&mut game.data.entities, &game.config, Pos::new(7, 5), &mut game.msg_log);
+    game.data.entities.direction[&finder] = Direction::Left;
+    let other = make_gol(&mut game.data.entities, &game.config, Pos::new(9, 0), &mut game.msg_log);
+    game.data.entities.direction[&other] = Direction::Up;
+
+    game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    assert_eq!(0, game.data.entities.status[&gol].knocked_out);
+    assert!(matches!(game.data.entities.behavior.get(&other), Some(Behavior::Investigating(_))));
+}
+
+#[test]
+pub fn test_distractions() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.noisemaker_fuse = 2;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 9);
+
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(8, 1), &mut game.msg_log);
+    game.data.entities.direction[&gol] = Direction::Up;
+
+    // a thrown stone draws the gol to where it landed
+    let stone = make_stone(&mut game.data.entities, &game.config, Pos::new(0, 9), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, stone);
+    game.msg_log.log(Msg::ItemThrow(player_id, stone, Pos::new(0, 9), Pos::new(6, 3)));
+    game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    assert!(matches!(game.data.entities.behavior[&gol], Behavior::Investigating(_)));
+
+    // a noisemaker only goes off once its fuse runs out
+    game.data.entities.pos[&gol] = Pos::new(8, 1);
+    game.data.entities.behavior[&gol] = Behavior::Idle;
+    game.data.entities.direction[&gol] = Direction::Up;
+    game.data.entities.messages[&gol].clear();
+
+    let noisemaker = make_noisemaker(&mut game.data.entities, &game.config, Pos::new(0, 9), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, noisemaker);
+    game.msg_log.log(Msg::ItemThrow(player_id, noisemaker, Pos::new(0, 9), Pos::new(6, 3)));
+    game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    assert_eq!(Behavior::Idle, game.data.entities.behavior[&gol]);
+
+    game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    let noisemaker_pos = game.data.entities.pos[&noisemaker];
+    assert_eq!(Behavior::Investigating(noisemaker_pos), game.data.entities.behavior[&gol]);
+
+    // the noisemaker is still there to be picked up again
+    assert!(game.data.entities.ids.contains(&noisemaker));
+}
+
+#[test]
+pub fn test_grapple() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.grapple_range = 5;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 5);
+
+    let grapple = make_grapple(&mut game.data.entities, &game.config, Pos::new(0, 5), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, grapple);
+
+    // the hook catches on a wall across the water and pulls the player over
+    game.data.map[(1, 5)] = Tile::water();
+    game.data.map[(2, 5)] = Tile::water();
+    game.data.map[(3, 5)] = Tile::water();
+    game.data.map[(5, 5)] = Tile::wall();
+    game.msg_log.log(Msg::UseItem(player_id, Pos::new(1, 5), grapple));
+    game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    assert_eq!(Pos::new(4, 5), game.data.entities.pos[&player_id]);
+
+    // a column works as an anchor too
+    make_column(&mut game.data.entities, &game.config, Pos::new(4, 1), &mut game.msg_log);
+    game.msg_log.log(Msg::UseItem(player_id, Pos::new(4, 4), grapple));
+    game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    assert_eq!(Pos::new(4, 2), game.data.entities.pos[&player_id]);
+
+    // with nothing in reach to catch on, the player stays put
+    game.msg_log.log(Msg::UseItem(player_id, Pos::new(3, 2), grapple));
+    game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    assert_eq!(Pos::new(4, 2), game.data.entities.pos[&player_id]);
+}
+
+#[test]
+pub fn test_skill_cooldown() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    game.data.entities.add_skill(player_id, Skill::FarSight);
+    game.data.entities.energy[&player_id] = 3;
+
+    game.step_game(InputAction::SkillFacing(ActionMode::Primary, 0), 0.1);
+    assert_eq!(2, game.data.entities.energy[&player_id]);
+    assert_eq!(Skill::FarSight.cooldown() - 1, game.data.entities.skill_cooldown(player_id, Skill::FarSight));
+
+    // using the skill again before it recharges is refused, and costs nothing
+    game.step_game(InputAction::SkillFacing(ActionMode::Primary, 0), 0.1);
+    assert_eq!(2, game.data.entities.energy[&player_id]);
+
+    while game.data.entities.skill_cooldown(player_id, Skill::FarSight) > 0 {
+        game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    }
+
+    game.step_game(InputAction::SkillFacing(ActionMode::Primary, 0), 0.1);
+    assert_eq!(1, game.data.entities.energy[&player_id]);
+}
+
+#[test]
+pub fn test_quick_slots() {
+    use std::time::Instant;
+    use crate::input::{Input, InputEvent, KeyDir, QUICK_SLOT_KEY};
+
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    game.data.entities.add_skill(player_id, Skill::FarSight);
+    game.data.entities.energy[&player_id] = 3;
+    let stone = make_stone(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, stone);
+
+    // items and skills are put on the hotbar from their menus
+    game.step_game(InputAction::Inventory, 0.1);
+    game.step_game(InputAction::AssignQuickSlot(0), 0.1);
+    game.step_game(InputAction::SkillMenu, 0.1);
+    game.step_game(InputAction::AssignQuickSlot(0), 0.1);
+    game.step_game(InputAction::Esc, 0.1);
+    assert_eq!(Some(QuickSlot::Item(Item::Stone)), game.settings.quick_slots[0]);
+    assert_eq!(Some(QuickSlot::Skill(Skill::FarSight)), game.settings.quick_slots[1]);
+
+    // number keys move unless the quick slot key is held
+    let time = Instant::now();
+    let mut input = Input::new();
+    input.handle_event(&mut game.settings, InputEvent::Char('2', KeyDir::Down), time, &config);
+    let action = input.handle_event(&mut game.settings, InputEvent::Char('2', KeyDir::Up), time, &config);
+    assert_eq!(InputAction::Move(Direction::Down, MoveMode::Walk), action);
+
+    input.handle_event(&mut game.settings, InputEvent::Char(QUICK_SLOT_KEY, KeyDir::Down), time, &config);
+    input.handle_event(&mut game.settings, InputEvent::Char('2', KeyDir::Down), time, &config);
+    let action = input.handle_event(&mut game.settings, InputEvent::Char('2', KeyDir::Up), time, &config);
+    assert_eq!(InputAction::QuickSlot(1), action);
+
+    game.step_game(action, 0.1);
+    assert_eq!(2, game.data.entities.energy[&player_id]);
+
+    game.step_game(InputAction::QuickSlot(0), 0.1);
+    assert!(game.msg_log.turn_messages.iter().any(|msg| matches!(msg, Msg::UseItem(_, _, item_id) if *item_id == stone)));
+
+    // assigning something already on the hotbar takes it off
+    game.step_game(InputAction::SkillMenu, 0.1);
+    game.step_game(InputAction::AssignQuickSlot(0), 0.1);
+    assert_eq!(None, game.settings.quick_slots[1]);
+}
+
+#[test]
+pub fn test_choose_loadout() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    game.loadouts = Loadout::from_file("../resources/loadouts.yaml").unwrap();
+    assert!(game.loadouts.len() >= 2);
+    game.settings.state = GameState::NewGame;
+
+    // other input is ignored until a loadout is chosen
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(0, 0), game.data.entities.pos[&player_id]);
+
+    game.step_game(InputAction::SelectItem(1), 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+
+    let loadout = game.loadouts[1].clone();
+    assert_eq!(loadout.class, game.data.entities.class[&player_id]);
+    assert_eq!(loadout.hp, game.data.entities.fighter[&player_id].hp);
+    assert_eq!(loadout.energy, game.data.entities.energy[&player_id]);
+    assert!(game.data.entities.skills[&player_id].len() > 0);
+    assert_eq!(loadout.items.len(), game.data.entities.inventory[&player_id].len());
+    for item in loadout.items.iter() {
+        assert!(game.data.is_in_inventory(player_id, *item).is_some());
+    }
+}
+
+#[test]
+pub fn test_rescue_prisoner() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.objective = Objective::Rescue;
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    game.dialogue = DialogueNode::from_file("../resources/dialogue.yaml").unwrap();
+
+    let prisoner = make_prisoner(&mut game.data.entities, &config, Pos::new(1, 0), &mut game.msg_log);
+    let exit_pos = Pos::new(0, 5);
+    make_exit(&mut game.data.entities, &config, exit_pos, &mut game.msg_log);
+    let key = make_key(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, key);
+
+    // interacting with the prisoner starts a conversation
+    game.step_game(InputAction::Interact(Some(Direction::Right)), 0.1);
+    assert_eq!(GameState::Dialogue, game.settings.state);
+
+    // asking them to follow ends the conversation once it runs out of nodes
+    let follow_index = game.dialogue[0].responses.iter().position(|response| response.action.is_some()).unwrap();
+    game.step_game(InputAction::SelectItem(follow_index), 0.1);
+    while game.settings.state == GameState::Dialogue {
+        game.step_game(InputAction::SelectItem(0), 0.1);
+    }
+    assert_eq!(Behavior::Following(player_id), game.data.entities.behavior[&prisoner]);
+
+    // the prisoner keeps up as the player walks towards the exit
+    for _ in 0..4 {
+        game.step_game(InputAction::Move(Direction::Down, MoveMode::Walk), 0.1);
+        let prisoner_pos = game.data.entities.pos[&prisoner];
+        assert!(distance_maximum(prisoner_pos, game.data.entities.pos[&player_id]) <= 2);
+    }
+
+    // reaching the exit with a prisoner left behind doesn't finish the level
+    let player_pos = game.data.entities.pos[&player_id];
+    let prisoner_pos = game.data.entities.pos[&prisoner];
+    game.data.entities.pos[&player_id] = exit_pos;
+    game.data.entities.pos[&prisoner] = Pos::new(5, 0);
+    assert!(!objective_met(&game.data));
+
+    game.data.objective = Objective::KeyAndExit;
+    assert!(objective_met(&game.data));
+    game.data.objective = Objective::Rescue;
+
+    game.data.entities.pos[&player_id] = player_pos;
+    game.data.entities.pos[&prisoner] = prisoner_pos;
+
+    // bringing the prisoner along does
+    game.step_game(InputAction::Move(Direction::Down, MoveMode::Walk), 0.1);
+    assert_eq!(1, game.settings.level_num);
+}
+
+#[test]
+pub fn test_objectives() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    let exit_pos = Pos::new(0, 1);
+    make_exit(&mut game.data.entities, &config, exit_pos, &mut game.msg_log);
+
+    // assassination needs the target dead before the exit counts
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(8, 8), &mut game.msg_log);
+    game.data.objective = Objective::Assassinate(gol);
+    game.data.entities.pos[&player_id] = exit_pos;
+    assert!(!objective_met(&game.data));
+    game.data.entities.status[&gol].alive = false;
+    assert!(objective_met(&game.data));
+
+    // stealing needs the item carried out without being noticed
+    game.data.entities.status[&gol].alive = true;
+    let sword = make_sword(&mut game.data.entities, &config, Pos::new(5, 5), &mut game.msg_log);
+    game.data.objective = Objective::Steal(sword, false);
+    assert!(!objective_met(&game.data));
+    game.data.entities.pick_up_item(player_id, sword);
+    assert!(objective_met(&game.data));
+
+    game.data.entities.behavior[&gol] = Behavior::Attacking(player_id);
+    step_objective(&mut game.data, true);
+    assert_eq!(Objective::Steal(sword, true), game.data.objective);
+    assert!(!objective_met(&game.data));
+
+    // once noticed, the key and exit finish the level instead
+    let key = make_key(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, key);
+    assert!(objective_met(&game.data));
+
+    // a target which breaks can't be stolen, so the key and exit finish the level instead
+    use roguelike_core::combat::wear_item;
+    let shield = make_shield(&mut game.data.entities, &config, Pos::new(6, 6), &mut game.msg_log);
+    game.data.entities.behavior[&gol] = Behavior::Idle;
+    game.data.objective = Objective::Steal(shield, false);
+    game.data.entities.pick_up_item(player_id, shield);
+    game.data.entities.durability[&shield] = 1;
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    wear_item(player_id, shield, &mut game.data, &mut game.msg_log);
+    assert!(describe_objective(&game.data).contains(&"Target broken".to_string()));
+
+    // the broken target is removed at the end of the turn
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(!game.data.entities.ids.contains(&shield));
+    assert_eq!(Objective::KeyAndExit, game.data.objective);
+    game.data.entities.pos[&player_id] = exit_pos;
+    assert!(objective_met(&game.data));
+
+    // and is described without it
+    game.data.objective = Objective::Steal(shield, false);
+    assert!(describe_objective(&game.data).contains(&"Target broken".to_string()));
+    step_objective(&mut game.data, true);
+    assert_eq!(Objective::KeyAndExit, game.data.objective);
+
+    // surviving only needs time to pass
+    game.data.objective = Objective::Survive(2);
+    step_objective(&mut game.data, false);
+    assert_eq!(Objective::Survive(2), game.data.objective);
+    step_objective(&mut game.data, true);
+    step_objective(&mut game.data, true);
+    assert!(objective_met(&game.data));
+}
+
+#[test]
+pub fn test_choose_objective() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    // there is nothing to assassinate, so the level falls back to the key and exit
+    game.config.objectives = vec!(ObjectiveType::Assassinate);
+    choose_objective(&mut game);
+    assert_eq!(Objective::KeyAndExit, game.data.objective);
+
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(8, 8), &mut game.msg_log);
+    choose_objective(&mut game);
+    assert_eq!(Objective::Assassinate(gol), game.data.objective);
+
+    // rescue levels are given a prisoner if they have none
+    game.config.objectives = vec!(ObjectiveType::Rescue);
+    choose_objective(&mut game);
+    assert_eq!(Objective::Rescue, game.data.objective);
+    assert!(game.data.find_by_name(EntityName::Prisoner).is_some());
+
+    game.config.objectives = vec!(ObjectiveType::Survive);
+    choose_objective(&mut game);
+    assert_eq!(Objective::Survive(game.config.survive_turns), game.data.objective);
+}
+
+#[test]
+fn test_follower_avoids_enemy_fov() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config);
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(4, 0);
+
+    let prisoner = make_prisoner(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.behavior[&prisoner] = Behavior::Following(player_id);
+
+    // a gol looking up the column at x = 1 can see the direct path, so the
+    // prisoner should step around it rather than through it.
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(1, 6), &mut game.msg_log);
+    game.data.entities.direction[&gol] = Direction::Up;
+    game.data.map[(2, 6)] = Tile::wall();
+    game.data.map[(0, 6)] = Tile::wall();
+
+    game.msg_log.clear();
+    ai_follow(prisoner, player_id, &mut game.data, &mut game.msg_log, &game.config);
+    assert_eq!(1, game.msg_log.messages.len());
+    if let Msg::TryMove(_, direction, _, _) = game.msg_log.messages[0] {
+        let next_pos = direction.offset_pos(Pos::new(0, 0), 1);
+        assert!(!game.data.pos_in_fov(gol, next_pos, &game.config));
+    } else {
+        panic!("Follower did not move!");
+    }
+}
+
+#[test]
+pub fn test_score_run() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(7, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    // moving makes noise, which is counted against the run
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert!(game.settings.stats.noise > 0);
+    assert_eq!(0, game.settings.stats.kills);
+
+    // dying ends the run, adding it to the leaderboard
+    make_spike_trap(&mut game.data.entities, &config, Pos::new(2, 0), &mut game.msg_log);
+    game.data.entities.fighter[&player_id].hp = 1;
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert!(!game.data.entities.status[&player_id].alive);
+    assert_eq!(GameState::Lose, game.settings.state);
+
+    let entry = game.settings.last_score.clone().unwrap();
+    assert_eq!(7, entry.seed);
+    assert!(!entry.won);
+    assert_eq!(game.settings.stats.score(game.settings.turn_count, false), entry.score);
+    assert_eq!(Some(0), game.settings.rank);
+    assert_eq!(1, game.leaderboard.for_seed(7).len());
+
+    // the image of the end of the run is kept with its leaderboard entry
+    game.set_thumbnail("thumbnails/run.png");
+    assert_eq!(Some("thumbnails/run.png".to_string()), game.leaderboard.for_seed(7)[0].thumbnail);
+
+    game.step_game(InputAction::Esc, 0.1);
+    assert_eq!(GameState::MainMenu, game.settings.state);
+}
+
+#[test]
+pub fn test_death_screen() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.async_map_generation = false;
+
+    let die = |game: &mut Game| {
+        let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+        game.data.entities.pos[&player_id] = Pos::new(0, 0);
+        game.data.entities.fighter[&player_id].hp = 1;
+        make_spike_trap(&mut game.data.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+        game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    };
+
+    let mut game = Game::new(7, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    die(&mut game);
+    assert_eq!(GameState::Lose, game.settings.state);
+    let cause = game.settings.death_cause.clone().unwrap();
+    assert_eq!(EntityName::SpikeTrap.to_string(), cause);
+    assert!(Narrator::new().narrate(&game).iter().any(|line| line.ends_with(&format!("killed by {}", cause))));
+
+    // restarting plays the same seed from the start
+    game.step_game(InputAction::SelectItem(0), 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert_eq!(7, game.settings.seed);
+    assert_eq!(0, game.settings.turn_count);
+    assert_eq!(None, game.settings.death_cause);
+
+    // a new run chooses a new seed
+    die(&mut game);
+    game.step_game(InputAction::SelectItem(1), 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert!(game.settings.last_score.is_none());
+
+    die(&mut game);
+    game.step_game(InputAction::SelectItem(3), 0.1);
+    assert_eq!(GameState::Exit, game.settings.state);
+}
+
+#[test]
+pub fn test_leaderboard() {
+    let stats = RunStats { levels: 2, noise: 10, kills: 0, knockouts: 1, items_used: 1 };
+    let ghost_score = stats.score(100, false);
+    let killer_score = RunStats { kills: 2, ..stats }.score(100, false);
+    assert!(ghost_score > killer_score);
+    assert!(stats.score(100, true) > ghost_score);
+
+    let mut leaderboard = Leaderboard::default();
+    for score in 0..(LEADERBOARD_SIZE as i64 + 2) {
+        let entry = ScoreEntry { seed: 1, score, won: false, turns: 0, stats, difficulty: Difficulty::Normal, thumbnail: None };
+        leaderboard.add(entry);
+    }
+    leaderboard.add(ScoreEntry { seed: 2, score: 0, won: false, turns: 0, stats, difficulty: Difficulty::Normal, thumbnail: None });
+
+    // only the best scores are kept for each seed, best first
+    let scores = leaderboard.for_seed(1);
+    assert_eq!(LEADERBOARD_SIZE, scores.len());
+    assert_eq!(LEADERBOARD_SIZE as i64 + 1, scores[0].score);
+    assert_eq!(1, leaderboard.for_seed(2).len());
+
+    let rank = leaderboard.add(ScoreEntry { seed: 1, score: 5, won: false, turns: 0, stats, difficulty: Difficulty::Normal, thumbnail: None });
+    assert!(rank.unwrap() > 0);
+    let rank = leaderboard.add(ScoreEntry { seed: 1, score: -1, won: false, turns: 0, stats, difficulty: Difficulty::Normal, thumbnail: None });
+    assert_eq!(None, rank);
+
+    // a leaderboard which can't be written is an error rather than a crash
+    assert!(matches!(leaderboard.save("no_such_dir/leaderboard.yaml"), Err(GameError::Write { .. })));
+}
+
+#[test]
+pub fn test_achievements() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.async_map_generation = false;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    make_exit(&mut game.data.entities, &config, Pos::new(1, 0), &mut game.msg_log);
+    let key = make_key(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, key);
+
+    // the locked loadouts can't be chosen yet
+    game.loadouts = Loadout::from_file("../resources/loadouts.yaml").unwrap();
+    let locked = game.loadouts.iter().position(|loadout| loadout.unlock == Some(Achievement::Pacifist)).unwrap();
+    assert!(!game.loadouts[locked].is_available(&game.unlocks));
+
+    // finishing a level without being seen
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(1, game.settings.level_num);
+    assert!(game.unlocks.is_unlocked(Achievement::Unseen));
+    assert_eq!(vec!(Achievement::Unseen), game.settings.new_unlocks);
+
+    // being seen means the next level doesn't count
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(5, 5), &mut game.msg_log);
+    game.settings.tracker.record(&Msg::StateChange(gol, Behavior::Attacking(player_id)), player_id);
+    assert!(game.settings.tracker.level_finished(Objective::KeyAndExit).is_empty());
+
+    // winning without killing. Unlocks which can't be saved don't stop the game
+    game.unlocks_file = Some("no_such_dir/unlocks.yaml".to_string());
+    game.end_run(true);
+    assert!(matches!(game.unlocks.save("no_such_dir/unlocks.yaml"), Err(GameError::Write { .. })));
+    assert!(game.unlocks.is_unlocked(Achievement::Escaped));
+    assert!(game.unlocks.is_unlocked(Achievement::Pacifist));
+    assert!(game.loadouts[locked].is_available(&game.unlocks));
+
+    // unlocks carry over to the next run
+    game.new_run(1);
+    assert!(game.unlocks.is_unlocked(Achievement::Pacifist));
+    assert!(game.settings.new_unlocks.is_empty());
+    assert_eq!(GameState::NewGame, game.settings.state);
+    game.step_game(InputAction::SelectItem(locked), 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+}
+
+#[test]
+pub fn test_pause_and_options() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    game.step_game(InputAction::Esc, 0.1);
+    assert_eq!(GameState::Paused, game.settings.state);
+
+    // the game does not move on while paused
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(0, 0), game.data.entities.pos[&player_id]);
+
+    game.step_game(InputAction::SelectItem(1), 0.1);
+    assert_eq!(GameState::Options, game.settings.state);
+
+    let volume = game.settings.volume;
+    game.step_game(InputAction::SelectItem(1), 0.1);
+    assert_eq!(volume - 1, game.settings.volume);
+
+    let overlays = game.settings.overlays;
+    game.step_game(InputAction::SelectItem(2), 0.1);
+    assert_eq!(overlays.next(), game.settings.overlays);
+
+    // leaving the options goes back to the pause menu, and from there back to the game
+    game.step_game(InputAction::Esc, 0.1);
+    assert_eq!(GameState::Paused, game.settings.state);
+    game.step_game(InputAction::SelectItem(0), 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+
+    // asking to quit can be backed out of
+    game.step_game(InputAction::Exit, 0.1);
+    assert_eq!(GameState::Prompt, game.settings.state);
+    game.step_game(InputAction::Esc, 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+}
+
+#[test]
+pub fn test_help_screen() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    // the help lists every bound key, and the sound radii from the config
+    assert_eq!(InputAction::Help, alpha_up_to_action('?'));
+    let lines = help_lines(&game.config);
+    assert!(lines.iter().any(|line| line.starts_with("g ")));
+    assert!(lines.iter().any(|line| line.contains(&format!("radius {} sneaking", config.sound_radius_sneak))));
+
+    game.step_game(InputAction::Help, 0.1);
+    assert_eq!(GameState::Help, game.settings.state);
+
+    // scrolling moves through the help instead of moving the player
+    game.step_game(InputAction::Move(Direction::Down, MoveMode::Walk), 0.1);
+    assert_eq!(1, game.settings.help_scroll);
+    assert_eq!(Pos::new(0, 0), game.data.entities.pos[&player_id]);
+    game.step_game(InputAction::Move(Direction::Up, MoveMode::Walk), 0.1);
+    game.step_game(InputAction::Move(Direction::Up, MoveMode::Walk), 0.1);
+    assert_eq!(0, game.settings.help_scroll);
+
+    game.step_game(InputAction::Esc, 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+
+    // help opened over a menu goes back to that menu
+    game.step_game(InputAction::Esc, 0.1);
+    game.step_game(InputAction::Help, 0.1);
+    game.step_game(InputAction::Help, 0.1);
+    assert_eq!(GameState::Paused, game.settings.state);
+}
+
+#[test]
+pub fn test_color_palettes() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let prisoner = make_prisoner(&mut game.data.entities, &game.config, Pos::new(1, 1), &mut game.msg_log);
+    let default_color = game.config.color_light_orange;
+
+    // every palette only names colors the config has
+    for palette in config.palettes.iter() {
+        let mut palette_config = config.clone();
+        palette_config.apply_palette(palette);
+    }
+
+    let index = config.palettes.iter().position(|palette| palette.colors.contains_key("color_light_orange")).unwrap() + 1;
+    game.set_color_palette(index);
+    let palette_color = config.palettes[index - 1].colors["color_light_orange"];
+    assert_eq!(palette_color, game.config.color_light_orange);
+    assert_eq!(palette_color, game.data.entities.color[&prisoner]);
+
+    // switching back restores the config's own colors, and entities with them
+    game.set_color_palette(0);
+    assert_eq!(default_color, game.config.color_light_orange);
+    assert_eq!(default_color, game.data.entities.color[&prisoner]);
+
+    // the options screen cycles through the palettes
+    game.settings.state = GameState::Options;
+    game.step_game(InputAction::SelectItem(4), 0.1);
+    assert_eq!(1, game.settings.color_palette);
+}
+
+#[test]
+pub fn test_narration() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let mut narrator = Narrator::new();
+    let lines = narrator.narrate(&game);
+    assert!(lines.contains(&"state playing".to_string()));
+
+    // only changes are narrated
+    assert_eq!(0, narrator.narrate(&game).len());
+
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    make_gol(&mut game.data.entities, &game.config, Pos::new(3, 0), &mut game.msg_log);
+    game.msg_log.clear();
+    let lines = narrator.narrate(&game);
+    assert!(lines.contains(&"moved 1 0 to 1 0".to_string()));
+    assert!(lines.contains(&"see gol at 3 0, offset 2 0".to_string()));
+
+    // menus are read out with their choices
+    game.step_game(InputAction::Esc, 0.1);
+    let lines = narrator.narrate(&game);
+    assert!(lines.contains(&"state paused".to_string()));
+    assert!(lines.contains(&"menu 0 resume".to_string()));
+}
+
+#[test]
+pub fn test_overlays() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    assert_eq!(Overlays::none(), game.settings.shown_overlays());
+
+    // cycling goes through each overlay on its own, then all of them, then back to none
+    for overlay in Overlay::overlays().iter() {
+        game.step_game(InputAction::CycleOverlay, 0.1);
+        assert_eq!(vec!(*overlay), game.settings.overlays.overlays());
+    }
+    game.step_game(InputAction::CycleOverlay, 0.1);
+    assert_eq!("all", game.settings.overlays.to_string());
+    assert!(!game.settings.overlays.is_on(Overlay::Debug));
+    game.step_game(InputAction::CycleOverlay, 0.1);
+    assert_eq!(Overlays::none(), game.settings.overlays);
+
+    // holding the overlay key shows the gameplay overlays along with the chosen ones
+    game.settings.overlays = Overlays::only(Overlay::Debug);
+    game.step_game(InputAction::OverlayOn, 0.1);
+    assert!(game.settings.shown_overlays().is_on(Overlay::Sound));
+    assert!(game.settings.shown_overlays().is_on(Overlay::Debug));
+    game.step_game(InputAction::OverlayOff, 0.1);
+    assert_eq!(Overlays::only(Overlay::Debug), game.settings.shown_overlays());
+}
+
+#[test]
+pub fn test_enemy_vision() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(3, 3), &mut game.msg_log);
+
+    // an idle golem sees some tiles, all shaded as idle
+    let vision = enemy_vision(&game.data, &game.config);
+    assert!(vision.len() > 0);
+    assert!(vision.values().all(|alertness| *alertness == Alertness::Idle));
+
+    // an attacking golem grades the same tiles as attacking
+    game.data.entities.behavior[&gol] = Behavior::Attacking(player_id);
+    let attacking = enemy_vision(&game.data, &game.config);
+    assert_eq!(vision.len(), attacking.len());
+    assert!(attacking.values().all(|alertness| *alertness == Alertness::Attacking));
+
+    // a knocked out golem sees nothing
+    game.data.entities.status[&gol].knocked_out = 2;
+    assert!(enemy_vision(&game.data, &game.config).is_empty());
+}
+
+#[test]
+pub fn test_enemy_threat() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(3, 3), &mut game.msg_log);
+
+    // one golem threatens the tiles next to it, but not its own
+    let threat = enemy_threat(&game.data, &game.config);
+    assert_eq!(Some(&1), threat.get(&Pos::new(4, 3)));
+    assert_eq!(None, threat.get(&Pos::new(3, 3)));
+
+    // a second golem nearby doubles up the tiles they share
+    make_gol(&mut game.data.entities, &game.config, Pos::new(4, 2), &mut game.msg_log);
+    let threat = enemy_threat(&game.data, &game.config);
+    assert_eq!(Some(&2), threat.get(&Pos::new(3, 2)));
+
+    // a wall blocks the golem from reaching past it
+    game.data.map[(2, 3)] = Tile::wall();
+    let threat = enemy_threat(&game.data, &game.config);
+    assert_eq!(None, threat.get(&Pos::new(2, 3)));
+
+    // frozen golems are no threat
+    game.data.entities.status[&gol].frozen = 2;
+    let threat = enemy_threat(&game.data, &game.config);
+    assert_eq!(None, threat.get(&Pos::new(3, 4)));
+}
+
+#[test]
+pub fn test_turn_forecast() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(3, 3), &mut game.msg_log);
+    let pawn = make_pawn(&mut game.data.entities, &game.config, Pos::new(2, 1), &mut game.msg_log);
+
+    // the player acts first, then each enemy in the order they take their turns
+    let forecast = turn_forecast(&game.data, &game.config, 2);
+    assert_eq!(vec!(player_id, gol, pawn), forecast[0]);
+    assert_eq!(forecast[0], forecast[1]);
+
+    // a frozen enemy skips this turn, but acts again on the next one
+    game.data.entities.status[&gol].frozen = 1;
+    let forecast = turn_forecast(&game.data, &game.config, 2);
+    assert_eq!(vec!(player_id, pawn), forecast[0]);
+    assert_eq!(vec!(player_id, gol, pawn), forecast[1]);
+
+    // enemies out of view are not forecast
+    game.data.entities.pos[&pawn] = Pos::new(20, 20);
+    let forecast = turn_forecast(&game.data, &game.config, 2);
+    assert_eq!(vec!(player_id), forecast[0]);
+}
+
+#[test]
+pub fn test_remove_entity_references() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    let dagger = make_dagger(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, dagger);
+    game.data.entities.selected_item.insert(player_id, dagger);
+
+    // removing the dagger takes it out of the inventory that held it
+    game.data.entities.remove_entity(dagger);
+    assert!(!game.data.entities.inventory[&player_id].contains(&dagger));
+    assert_eq!(None, game.data.entities.selected_item.get(&player_id));
+
+    // a new entity may reuse the dagger's slot, but the dagger's id finds nothing
+    let stone = make_stone(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    assert_ne!(dagger, stone);
+    assert_eq!(None, game.data.entities.pos.get(&dagger));
+    assert!(game.data.entities.is_dead(dagger));
+}
+
+#[test]
+pub fn test_remove_entity_pipeline() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let pawn_pos = Pos::new(5, 5);
+    let pawn = make_pawn(&mut game.data.entities, &game.config, pawn_pos, &mut game.msg_log);
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(7, 7), &mut game.msg_log);
+    game.data.entities.behavior[&gol] = Behavior::Attacking(pawn);
+    game.data.entities.messages[&gol].push(Message::Attack(pawn));
+
+    // an AI attacking a removed entity goes to where it was, and forgets its messages
+    remove_entity(pawn, &mut game.data, &mut game.settings, &mut game.msg_log);
+    assert_eq!(Behavior::Investigating(pawn_pos), game.data.entities.behavior[&gol]);
+    assert!(game.data.entities.messages[&gol].is_empty());
+    assert!(game.msg_log.messages.contains(&Msg::RemovedEntity(pawn)));
+
+    // removing an entity twice does nothing
+    remove_entity(pawn, &mut game.data, &mut game.settings, &mut game.msg_log);
+
+    // entities marked for removal go through the same removal at the start of a turn
+    game.data.entities.needs_removal[&gol] = true;
+    game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    assert!(!game.data.entities.ids.contains(&gol));
+    assert!(game.msg_log.turn_messages.contains(&Msg::RemovedEntity(gol)));
+}
+
+#[test]
+pub fn test_sound_grid() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    let player_pos = Pos::new(5, 5);
+    game.data.entities.pos[&player_id] = player_pos;
+
+    // a yell is loudest where it starts, and fades toward the edge of its radius
+    game.step_game(InputAction::Yell, 0.1);
+    let at_source = game.data.map.sound(player_pos);
+    assert!(at_source > 0.0);
+    assert!(game.data.map.sound(Pos::new(6, 5)) < at_source);
+    assert_eq!(0.0, game.data.map.sound(Pos::new(0, 0)));
+
+    // the sound fades away as turns pass
+    game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    assert!(game.data.map.sound(player_pos) < at_source);
+    for _ in 0..10 {
+        game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    }
+    assert_eq!(0.0, game.data.map.sound(player_pos));
+}
+
+#[test]
+pub fn test_minimap() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    game.data.map[(1, 0)] = Tile::wall();
+    game.data.map[(1, 0)].explored = true;
+    game.data.map[(2, 0)].explored = true;
+    make_key(&mut game.data.entities, &game.config, Pos::new(2, 0), &mut game.msg_log);
+    make_exit(&mut game.data.entities, &game.config, Pos::new(3, 0), &mut game.msg_log);
+
+    // only explored tiles are shown, and the exit is not known until its tile is explored
+    let cells = minimap_cells(&game.data);
+    assert!(cells.contains(&(Pos::new(1, 0), MinimapCell::Wall)));
+    assert!(cells.contains(&(Pos::new(2, 0), MinimapCell::Key)));
+    assert!(cells.contains(&(Pos::new(0, 0), MinimapCell::Player)));
+    assert!(!cells.iter().any(|(pos, _cell)| *pos == Pos::new(3, 0)));
+
+    game.data.map[(3, 0)].explored = true;
+    assert!(minimap_cells(&game.data).contains(&(Pos::new(3, 0), MinimapCell::Exit)));
+
+    game.step_game(InputAction::Minimap, 0.1);
+    assert!(!game.settings.minimap);
+
+    game.step_game(InputAction::PerfOverlay, 0.1);
+    assert!(game.settings.perf_overlay);
+}
+
+#[test]
+pub fn test_locale() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let dagger = make_dagger(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    let msg = Msg::PickedUp(player_id, dagger);
+    assert_eq!("player picked up a dagger", msg.msg_line(&game.data, &game.locale));
+
+    // a partial translation is used where it has text, and English elsewhere
+    let mut locale = Locale::from_str("test", "name.dagger: dague\nmenu.resume: reprendre\n");
+    locale.fallback = Some(Box::new(Locale::english()));
+    game.locale = locale;
+    assert_eq!("player picked up a dague", msg.msg_line(&game.data, &game.locale));
+
+    game.step_game(InputAction::Esc, 0.1);
+    let lines = Narrator::new().narrate(&game);
+    assert!(lines.contains(&"menu 0 reprendre".to_string()));
+    assert!(lines.contains(&"menu 1 options".to_string()));
+
+    // the English locale file has text for every menu item
+    let english = Locale::english();
+    for item in MAIN_MENU_ITEMS.iter().chain(PAUSE_MENU_ITEMS.iter()).chain(OPTIONS_ITEMS.iter()).chain(GAME_OVER_ITEMS.iter()) {
+        assert!(english.strings.contains_key(*item));
+    }
+}
+
+#[test]
+pub fn test_confirm_prompt() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let key = make_key(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, key);
+
+    // dropping the key asks first, and cancelling keeps it
+    game.step_game(InputAction::DropItemByIndex(0), 0.1);
+    assert_eq!(GameState::Prompt, game.settings.state);
+    assert!(game.data.entities.inventory[&player_id].contains(&key));
+
+    game.step_game(InputAction::Esc, 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert!(game.data.entities.inventory[&player_id].contains(&key));
+
+    // confirming carries out the throw that opened the prompt
+    game.step_game(InputAction::ItemFacing(ActionMode::Alternate, 0), 0.1);
+    game.step_game(InputAction::Confirm, 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert!(!game.data.entities.inventory[&player_id].contains(&key));
+
+    // quitting goes through once confirmed
+    game.step_game(InputAction::Exit, 0.1);
+    assert_eq!(GameState::Prompt, game.settings.state);
+    game.step_game(InputAction::SelectItem(0), 0.1);
+    assert_eq!(GameState::Exit, game.settings.state);
+}
+
+#[test]
+pub fn test_main_menu_seed() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.settings.state = GameState::MainMenu;
+    game.settings.volume = 3;
+    game.settings.turn_count = 10;
+
+    game.step_game(InputAction::SelectItem(1), 0.1);
+    assert_eq!(Some("".to_string()), game.settings.seed_entry);
+
+    game.step_game(InputAction::SelectItem(4), 0.1);
+    game.step_game(InputAction::SelectItem(2), 0.1);
+    assert_eq!(Some("42".to_string()), game.settings.seed_entry);
+
+    game.step_game(InputAction::Confirm, 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert_eq!(None, game.settings.seed_entry);
+    assert_eq!(0, game.settings.turn_count);
+
+    // options carry over to the new run
+    assert_eq!(3, game.settings.volume);
+}
+
+#[test]
+pub fn test_difficulty() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.difficulty = Difficulty::Normal;
+    config.difficulties.insert(Difficulty::Hard,
+                               DifficultyConfig { spawn_budget: Some(20),
+                                                  fov_radius_monster: Some(7),
+                                                  player_health_bonus: Some(2),
+                                                  ..DifficultyConfig::default() });
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.settings.state = GameState::MainMenu;
+
+    // the main menu cycles to the next difficulty, changing only the settings it gives
+    game.step_game(InputAction::SelectItem(2), 0.1);
+    assert_eq!(Difficulty::Hard, game.settings.difficulty);
+    assert_eq!(20, game.config.spawn_budget);
+    assert_eq!(7, game.config.fov_radius_monster);
+    assert_eq!(config.investigate_turns, game.config.investigate_turns);
+
+    // the difficulty carries over to the new run, and the player starts with its extra health
+    game.step_game(InputAction::SelectItem(0), 0.1);
+    assert_eq!(Difficulty::Hard, game.settings.difficulty);
+    assert_eq!(20, game.config.spawn_budget);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    assert_eq!(config.player_health + 2, game.data.entities.fighter[&player_id].max_hp);
+
+    // switching back restores the config file's own settings
+    game.set_difficulty(Difficulty::Normal);
+    assert_eq!(config.spawn_budget, game.config.spawn_budget);
+    assert_eq!(config.fov_radius_monster, game.config.fov_radius_monster);
+    assert_eq!(config.player_health_bonus, game.config.player_health_bonus);
+}
+
+#[test]
+pub fn test_shove_column() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let column = make_column(&mut game.data.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    game.data.map[(3, 0)] = Tile::wall();
+
+    // shoving the column moves it one tile, crushing the floor beneath it
+    game.step_game(InputAction::Interact(Some(Direction::Right)), 0.1);
+    assert_eq!(Pos::new(2, 0), game.data.entities.pos[&column]);
+    assert_eq!(Pos::new(0, 0), game.data.entities.pos[&player_id]);
+    assert_eq!(Surface::Rubble, game.data.map[(2, 0)].surface);
+    assert!(game.msg_log.turn_messages.iter().any(|msg| matches!(msg, Msg::Sound(_, _, _, _))));
+
+    // the column can't be shoved into a wall
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(1, 0), game.data.entities.pos[&player_id]);
+    game.step_game(InputAction::Interact(Some(Direction::Right)), 0.1);
+    assert_eq!(Pos::new(2, 0), game.data.entities.pos[&column]);
+    assert!(game.data.entities.ids.contains(&column));
+}
+
+#[test]
+pub fn test_trap_rearm() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let tripwire = make_tripwire(&mut game.data.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    assert!(game.data.trap_hidden_from(player_id, tripwire));
+
+    // the tripwire sounds an alarm, and the player now knows where it is
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert!(game.msg_log.turn_messages.iter().any(|msg| matches!(msg, Msg::TripwireTriggered(_, _))));
+    assert!(!game.data.trap_hidden_from(player_id, tripwire));
+    assert!(game.data.entities.ids.contains(&tripwire));
+    assert_eq!(false, game.data.entities.armed[&tripwire]);
+
+    // the tripwire rearms itself after a few turns
+    for _ in 0..TRIPWIRE_REARM_TURNS {
+        game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    }
+    assert_eq!(true, game.data.entities.armed[&tripwire]);
+}
+
+#[test]
+pub fn test_disarm_trap() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.trap_disarm_fail_chance = 0.0;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let net = make_net_trap(&mut game.data.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    game.step_game(InputAction::Interact(Some(Direction::Right)), 0.1);
+    assert_eq!(false, game.data.entities.armed[&net]);
+
+    game.step_game(InputAction::Interact(Some(Direction::Right)), 0.1);
+    assert_eq!(true, game.data.entities.armed[&net]);
+
+    // a failed disarm sets the trap off on the player, holding them in place
+    game.config.trap_disarm_fail_chance = 1.0;
+    game.step_game(InputAction::Interact(Some(Direction::Right)), 0.1);
+    assert!(game.msg_log.turn_messages.iter().any(|msg| matches!(msg, Msg::DisarmFailed(_, _))));
+    assert!(game.data.entities.status[&player_id].frozen > 0);
+
+    game.step_game(InputAction::Move(Direction::Down, MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(0, 0), game.data.entities.pos[&player_id]);
+}
+
+#[test]
+pub fn test_action_hints() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.map = Map::from_dims(10, 10);
+    let player_pos = Pos::new(4, 4);
+    game.data.entities.pos[&player_id] = player_pos;
+
+    // nothing to do in an empty room
+    assert_eq!(0, action_hints(&game.data).len());
+
+    make_dagger(&mut game.data.entities, &game.config, player_pos, &mut game.msg_log);
+    make_gol(&mut game.data.entities, &game.config, Pos::new(3, 4), &mut game.msg_log);
+    game.data.map[(5, 5)] = Tile::door(DoorState::Closed);
+
+    let hints = action_hints(&game.data);
+    assert!(hints.contains(&"g: pick up dagger".to_string()));
+    assert!(hints.contains(&"alt 4: knock out gol".to_string()));
+    assert!(hints.contains(&"alt 3: open door".to_string()));
+
+    // a hammer lists the walls it can knock down, using its item key
+    game.data.map[(4, 3)] = Tile::wall();
+    let hammer = make_hammer(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, hammer);
+    let hints = action_hints(&game.data);
+    assert!(hints.iter().any(|hint| hint.starts_with("z ") && hint.contains("8") && hint.ends_with("hammer wall")));
+}
+
+#[test]
+pub fn test_ai_avoids_known_traps() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    game.data.map = Map::from_dims(10, 10);
+
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(4, 4), &mut game.msg_log);
+    let spikes = make_spike_trap(&mut game.data.entities, &game.config, Pos::new(5, 4), &mut game.msg_log);
+    let net = make_net_trap(&mut game.data.entities, &game.config, Pos::new(5, 5), &mut game.msg_log);
+
+    // visible traps are avoided, but hidden ones only once they have been seen going off
+    assert!(ai_avoids_trap(gol, spikes, &game.data));
+    assert!(!ai_avoids_trap(gol, net, &game.data));
+
+    game.data.entities.known_traps.insert(gol, vec!(net));
+    assert!(ai_avoids_trap(gol, net, &game.data));
+
+    game.data.entities.armed[&net] = false;
+    assert!(!ai_avoids_trap(gol, net, &game.data));
+}
+
+#[test]
+pub fn test_hammer_small_wall() {
+    use roguelike_core::constants::{HAMMER_DAMAGE, HAMMER_STUN_TURNS};
+
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    let mut input_action;
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.map = Map::from_dims(10, 10);
+    let player_pos = Pos::new(4, 4);
+    game.data.entities.pos[&player_id] = player_pos;
+
+
+    game.data.map[player_pos].bottom_wall = Wall::ShortWall;
+
+    let gol_pos = Pos::new(4, 5);
+    let gol = make_gol(&mut game.data.entities, &game.config, gol_pos, &mut game.msg_log);
+
+    let hammer = make_hammer(&mut game.data.entities, &game.config, Pos::new(4, 7), &mut game.msg_log);
+
+    game.data.entities.inventory[&player_id].push_front(hammer);
+
+    input_action = InputAction::UseItem(Direction::Down, 0);
+    game.step_game(input_action, 0.1);
+
+    // gol is no longer in entities list after being crushed
+    assert!(game.data.entities.is_dead(gol));
+
+    assert!(game.msg_log.turn_messages.iter().any(|msg| {
+        matches!(msg, Msg::HammerHitWall(_, _))
+    }));
+
+    assert_eq!(Surface::Rubble, game.data.map[gol_pos].surface);
+
+    let pawn_pos = Pos::new(3, 4);
+    let pawn = make_pawn(&mut game.data.entities, &game.config, pawn_pos, &mut game.msg_log);
+    assert_eq!(true, game.data.entities.status[&pawn].alive);
+
+    // add the hammer back and hit the pawn with it to test hitting entities
+    let hammer = make_hammer(&mut game.data.entities, &game.config, Pos::new(4, 7), &mut game.msg_log);
+    game.data.entities.inventory[&player_id].push_front(hammer);
+
+    input_action = InputAction::UseItem(Direction::Left, 0);
+    game.step_game(input_action, 0.1);
+
+    input_action = InputAction::MapClick(pawn_pos, pawn_pos);
+    game.step_game(input_action, 0.1);
+
+    // the pawn survives, but is stunned and knocked back
+    assert!(!game.data.entities.is_dead(pawn));
+    assert_eq!(Pos::new(2, 4), game.data.entities.pos[&pawn]);
+    assert_eq!(16 - HAMMER_DAMAGE, game.data.entities.fighter[&pawn].hp);
+
+    assert!(game.msg_log.turn_messages.iter().any(|msg| {
+        *msg == Msg::HammerHitEntity(player_id, pawn)
+    }));
+    assert!(game.msg_log.turn_messages.contains(&Msg::Froze(pawn, HAMMER_STUN_TURNS)));
+
+    assert_ne!(Surface::Rubble, game.data.map[pawn_pos].surface);
+}
+
+#[test]
+pub fn test_item_durability() {
+    use roguelike_core::combat::wear_item;
+
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.durability_sword = 3;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    let player_pos = Pos::new(2, 2);
+    game.data.entities.pos[&player_id] = player_pos;
+
+    let sword = make_sword(&mut game.data.entities, &game.config, player_pos, &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, sword);
+    game.msg_log.clear();
+
+    // the first use only wears the sword down
+    wear_item(player_id, sword, &mut game.data, &mut game.msg_log);
+    assert_eq!(2, game.data.entities.durability[&sword]);
+    assert!(game.msg_log.messages.is_empty());
+
+    // the log warns when it is nearly worn out
+    wear_item(player_id, sword, &mut game.data, &mut game.msg_log);
+    assert_eq!(Some(&Msg::ItemWorn(player_id, sword, 1)), game.msg_log.messages.back());
+
+    // and it breaks on its last use, leaving rubble behind
+    wear_item(player_id, sword, &mut game.data, &mut game.msg_log);
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+    assert!(!game.data.entities.inventory[&player_id].contains(&sword));
+    assert!(game.msg_log.turn_messages.contains(&Msg::ItemBroke(player_id, sword)));
+    assert_eq!(Surface::Rubble, game.data.map[player_pos].surface);
+}
+
+#[test]
+pub fn test_hammer_knockback() {
+    use roguelike_core::combat::hammer_hit;
+    use roguelike_core::constants::{HAMMER_DAMAGE, HAMMER_KNOCKBACK_DAMAGE};
+
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.map = Map::from_dims(10, 10);
+    game.data.entities.pos[&player_id] = Pos::new(4, 4);
+
+    // a pawn knocked into a wall takes extra damage
+    let pawn = make_pawn(&mut game.data.entities, &game.config, Pos::new(5, 4), &mut game.msg_log);
+    game.data.map[(6, 4)] = Tile::wall();
+    hammer_hit(player_id, pawn, &mut game.data, &mut game.msg_log, &game.config);
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+
+    let damage = HAMMER_DAMAGE + game.config.knockback_damage + HAMMER_KNOCKBACK_DAMAGE;
+    assert_eq!(Pos::new(5, 4), game.data.entities.pos[&pawn]);
+    assert!(game.data.entities.is_dead(pawn));
+    assert!(game.msg_log.turn_messages.contains(&Msg::KnockedInto(pawn, Pos::new(6, 4), damage - HAMMER_DAMAGE)));
+
+    // a stunned rook knocked into water drowns, even though rooks can wade through it
+    let rook = make_rook(&mut game.data.entities, &game.config, Pos::new(4, 5), &mut game.msg_log);
+    game.data.map[(4, 6)] = Tile::water();
+    hammer_hit(player_id, rook, &mut game.data, &mut game.msg_log, &game.config);
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+
+    assert_eq!(Pos::new(4, 6), game.data.entities.pos[&rook]);
+    assert!(game.data.entities.is_dead(rook));
+    assert!(game.msg_log.turn_messages.contains(&Msg::Drowned(rook)));
+}
+
+#[test]
+pub fn test_hammer_full_wall() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.map = Map::from_dims(10, 10);
+    let player_pos = Pos::new(4, 4);
+    game.data.entities.pos[&player_id] = player_pos;
+
+    let wall_pos = Pos::new(5, 4);
+    game.data.map[wall_pos] = Tile::wall();
+    game.data.map[player_pos].bottom_wall = Wall::TallWall;
+
+    // a full wall is cracked by the first hit and destroyed by the second
+    for hit in 0..2 {
+        let hammer = make_hammer(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+        game.data.entities.inventory[&player_id].push_front(hammer);
+        game.step_game(InputAction::UseItem(Direction::Right, 0), 0.1);
+
+        let destroyed = game.msg_log.turn_messages.iter().any(|msg| matches!(msg, Msg::WallDestroyed(_, _)));
+        if hit == 0 {
+            assert!(!destroyed);
+            assert!(game.data.map[wall_pos].cracked);
+            assert!(game.data.map[wall_pos].block_move);
+        } else {
+            assert!(destroyed);
+        }
+    }
+    assert_eq!(TileType::Empty, game.data.map[wall_pos].tile_type);
+    assert_eq!(Surface::Rubble, game.data.map[wall_pos].surface);
+    assert!(!game.data.map[wall_pos].block_move);
+    assert!(!game.data.map[wall_pos].block_sight);
+
+    // a tall intertile wall is knocked down to a short wall, and then broken
+    for expected_wall in [Wall::ShortWall, Wall::Empty].iter() {
+        let hammer = make_hammer(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+        game.data.entities.inventory[&player_id].push_front(hammer);
+        game.step_game(InputAction::UseItem(Direction::Down, 0), 0.1);
+
+        assert_eq!(*expected_wall, game.data.map[player_pos].bottom_wall);
+    }
+
+    // a diagonal hit glances off the corner of a wall
+    game.data.map[player_pos].bottom_wall = Wall::TallWall;
+    game.data.map[Pos::new(5, 4)].left_wall = Wall::TallWall;
+    let hammer = make_hammer(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.data.entities.inventory[&player_id].push_front(hammer);
+    game.step_game(InputAction::UseItem(Direction::DownRight, 0), 0.1);
+    assert_eq!(Wall::TallWall, game.data.map[player_pos].bottom_wall);
+    assert_eq!(Wall::TallWall, game.data.map[Pos::new(5, 4)].left_wall);
+}
+
+fn step_ai(game: &mut Game) {
+    game.data.ai_budget.start_turn(game.config.ai_path_budget);
+
+    let ai_ids: Vec<EntityId> = ai_turn_order(&game.data);
+
+    // the messages each AI has seen when it decides on its turn
+    let mut seen: Vec<Vec<Message>> = ai_ids.iter().map(|id| game.data.entities.messages[id].clone()).collect();
+
+    // each AI decides on their turn from the state at the start of the AI's turns, all
+    // in parallel. Their decisions are then resolved one at a time in turn order, so the
+    // outcome does not depend on how the threads were scheduled.
+    let mut decisions: Vec<MsgLog> = Vec::new();
+    let mut decided_from: Vec<(Pos, Behavior)> = Vec::new();
+    if game.config.ai_parallel {
+        decided_from = ai_ids.iter().map(|id| (game.data.entities.pos[id], game.data.entities.behavior[id])).collect();
+
+        game.data.ai_budget.planning = true;
+
+        let data = &game.data;
+        let config = &game.config;
+        decisions = ai_ids.par_iter().map(|key| {
+            let mut msg_log = MsgLog::new();
+            ai_take_turn(*key, data, config, &mut msg_log);
+            return msg_log;
+        }).collect();
+
+        game.data.ai_budget.planning = false;
+    }
+
+    let turn = game.settings.turn_count;
+    for (index, key) in ai_ids.iter().enumerate() {
+       game.data.map.begin_edits(turn, Some(*key));
+
+       // an AI killed or removed earlier in the turn, such as by another monster or a
+       // trap, no longer gets to act on its decision.
+       if game.data.entities.is_dead(*key) || !game.data.entities.status[key].alive {
+           if let Some(messages) = game.data.entities.messages.get_mut(key) {
+               messages.retain(|message| !seen[index].contains(message));
+           }
+           continue;
+       }
+
+       // a decision made from a position or behavior the AI is no longer in is stale,
+       // so the AI decides again from the current state.
+       let current = (game.data.entities.pos[key], game.data.entities.behavior[key]);
+       let stale = decided_from.get(index).map_or(false, |from| *from != current);
+
+       if let (Some(decision), false) = (decisions.get_mut(index), stale) {
+           game.msg_log.messages.append(&mut decision.messages);
+       } else {
+           seen[index] = game.data.entities.messages[key].clone();
+           ai_take_turn(*key, &game.data, &game.config, &mut game.msg_log);
+       }
+
+       resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+
+       // if there are remaining messages for an entity, clear them. messages which arrived
+       // after the AI decided, such as being attacked by another monster, wait for its next turn.
+       game.data.entities.messages[key].retain(|message| !seen[index].contains(message));
+    }
+}
+
+#[test]
+fn test_ai_path_budget() {
+    let mut config = Config::from_file("../config.yaml");
+    config.ai_path_budget = 1;
+    let mut game = Game::new(0, config);
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = Pos::new(5, 3);
+    game.data.entities.pos[&player_id] = player_pos;
+
+    let first = make_gol(&mut game.data.entities, &game.config, Pos::new(1, 1), &mut game.msg_log);
+    let second = make_gol(&mut game.data.entities, &game.config, Pos::new(1, 5), &mut game.msg_log);
+    for gol in [first, second].iter() {
+        game.data.entities.behavior[gol] = Behavior::Attacking(player_id);
+    }
+    game.msg_log.clear();
+
+    step_ai(&mut game);
+
+    // the budget runs out part way through the turn, but both golems still close in
+    assert_eq!(1, game.data.ai_budget.num_trips());
+    for (gol, start_pos) in [(first, Pos::new(1, 1)), (second, Pos::new(1, 5))].iter() {
+        let gol_pos = game.data.entities.pos[gol];
+        assert!(distance(gol_pos, player_pos) < distance(*start_pos, player_pos));
+    }
+
+    // without a limit the budget never trips
+    game.config.ai_path_budget = 0;
+    step_ai(&mut game);
+    assert_eq!(1, game.data.ai_budget.num_trips());
+}
+
+#[test]
+fn test_ai_parallel() {
+    let mut config = Config::from_file("../config.yaml");
+    config.ai_parallel = true;
+    let mut game = Game::new(0, config);
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(10, 5);
+
+    // golems spread out, each investigating a different spot, out of earshot of each other
+    for y in [0, 8, 16].iter() {
+        let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(0, *y), &mut game.msg_log);
+        game.data.entities.behavior[&gol] = Behavior::Investigating(Pos::new(3, *y + 1));
+    }
+    game.msg_log.clear();
+
+    let mut sequential = game.clone();
+    sequential.config.ai_parallel = false;
+
+    // deciding in parallel comes out the same as deciding one at a time
+    for _ in 0..3 {
+        step_ai(&mut game);
+        step_ai(&mut sequential);
+    }
+    assert_eq!(sequential.data.entities.pos, game.data.entities.pos);
+    assert_eq!(sequential.data.entities.behavior, game.data.entities.behavior);
+}
+
+#[test]
+fn test_ai_parallel_killed_in_turn() {
+    let mut config = Config::from_file("../config.yaml");
+    config.ai_parallel = true;
+    let mut game = Game::new(0, config);
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    // a pawn and a gol each decide to attack the other, and the pawn acts first
+    let pawn = make_pawn(&mut game.data.entities, &game.config, Pos::new(5, 5), &mut game.msg_log);
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(6, 5), &mut game.msg_log);
+    game.data.entities.behavior[&pawn] = Behavior::Attacking(gol);
+    game.data.entities.behavior[&gol] = Behavior::Attacking(pawn);
+    game.data.entities.direction[&pawn] = Direction::Right;
+    game.data.entities.direction[&gol] = Direction::Left;
+    game.data.entities.fighter[&gol].hp = 1;
+    game.msg_log.clear();
+
+    let pawn_hp = game.data.entities.fighter[&pawn].hp;
+    step_ai(&mut game);
+
+    // the gol dies to the pawn's attack, and does not get to act on its decision
+    assert!(game.data.entities.is_dead(gol));
+    assert_eq!(pawn_hp, game.data.entities.fighter[&pawn].hp);
+    assert!(!game.msg_log.turn_messages.iter().any(|msg| match msg {
+        Msg::TryMove(entity_id, _, _, _) | Msg::TryAttack(entity_id, _, _) => *entity_id == gol,
+        _ => false,
+    }));
+}
+
+#[test]
+fn test_ai_idle_player_in_fov() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config);
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+
+    let start_pos = Pos::new(1, 1);
+    let gol = make_gol(&mut game.data.entities, &game.config, start_pos, &mut game.msg_log);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = add_pos(start_pos, Pos::new(1, 1));
+
+    game.msg_log.clear();
+    ai_idle(gol, &mut game.data, &mut game.msg_log, &game.config);
+
+    let player_pos = game.data.entities.pos[&player_id];
+
+    assert_eq!(game.msg_log.messages[0], Msg::FaceTowards(gol, player_pos));
+    assert_eq!(game.msg_log.messages[1], Msg::StateChange(gol, Behavior::Attacking(player_id)));
+}
+
+#[test]
+fn test_ai_idle_was_attacked() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config);
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+
+    let start_pos = Pos::new(0, 0);
+    let gol = make_gol(&mut game.data.entities, &game.config, start_pos, &mut game.msg_log);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = add_pos(start_pos, Pos::new(1, 1));
+
+    game.msg_log.clear();
+    // move the player a tile away
+
+    game.data.entities.pos[&player_id] = add_pos(start_pos, Pos::new(3, 0));
+
+    // place a wall between the player and the gol
+    game.data.map[(2, 0)] = Tile::wall();
+
+    // check that no messages are created as the monster can't see the player
+    ai_idle(gol, &mut game.data, &mut game.msg_log, &game.config);
+    dbg!(&game.msg_log.messages);
+    assert_eq!(0, game.msg_log.messages.len());
+
+    // if the player attacks, the monster turns and state changes to attacking
+    game.data.entities.messages[&gol].push(Message::Attack(player_id));
+    ai_idle(gol, &mut game.data, &mut game.msg_log, &game.config);
+
+    let player_pos = game.data.entities.pos[&player_id];
+    assert_eq!(game.msg_log.messages[0], Msg::FaceTowards(gol, player_pos));
+    assert_eq!(game.msg_log.messages[1], Msg::ReadMessage(gol, Message::Attack(player_id)));
+    assert_eq!(game.msg_log.messages[2], Msg::StateChange(gol, Behavior::Attacking(player_id)));
+}
+
+#[test]
+fn test_ai_idle_heard_sound() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config);
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+
+    let start_pos = Pos::new(0, 0);
+    let gol = make_gol(&mut game.data.entities, &game.config, start_pos, &mut game.msg_log);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = add_pos(start_pos, Pos::new(1, 1));
+
+    game.msg_log.clear();
+
+    // move the player a tile away
+    game.data.entities.pos[&player_id] = add_pos(start_pos, Pos::new(3, 0));
+
+    // place a wall between the player and the gol
     game.data.map[(2, 0)] = Tile::wall();
 
     // check that no messages are created as the monster can't see the player
@@ -321,16 +2814,17 @@ fn test_ai_idle_heard_sound() {
     game.data.entities.messages[&gol].push(Message::Sound(player_id, sound_pos));
     ai_idle(gol, &mut game.data, &mut game.msg_log, &game.config);
 
-    assert_eq!(2, game.msg_log.messages.len());
+    assert_eq!(3, game.msg_log.messages.len());
     assert_eq!(game.msg_log.messages[0], Msg::FaceTowards(gol, sound_pos));
-    assert_eq!(game.msg_log.messages[1], Msg::StateChange(gol, Behavior::Investigating(sound_pos)));
+    assert_eq!(game.msg_log.messages[1], Msg::ReadMessage(gol, Message::Sound(player_id, sound_pos)));
+    assert_eq!(game.msg_log.messages[2], Msg::StateChange(gol, Behavior::Investigating(sound_pos)));
 }
 
 #[test]
 fn test_ai_investigate_player_in_fov() {
     let config = Config::from_file("../config.yaml");
     let mut game = Game::new(0, config);
-    make_map(&MapLoadConfig::Empty, &mut game);
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
 
 
     let start_pos = Pos::new(0, 0);
@@ -355,7 +2849,7 @@ fn test_ai_investigate_player_in_fov() {
 fn test_ai_investigate_not_in_fov_heard_sound() {
     let config = Config::from_file("../config.yaml");
     let mut game = Game::new(0, config);
-    make_map(&MapLoadConfig::Empty, &mut game);
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
 
 
     let start_pos = Pos::new(0, 0);
@@ -380,15 +2874,16 @@ fn test_ai_investigate_not_in_fov_heard_sound() {
     game.data.entities.messages[&gol].push(Message::Sound(player_id, sound_pos));
     ai_investigate(player_pos, gol, &mut game.data, &mut game.msg_log, &game.config);
 
-    assert_eq!(1, game.msg_log.messages.len());
-    assert_eq!(game.msg_log.messages[0], Msg::StateChange(gol, Behavior::Investigating(sound_pos)));
+    assert_eq!(2, game.msg_log.messages.len());
+    assert_eq!(game.msg_log.messages[0], Msg::ReadMessage(gol, Message::Sound(player_id, sound_pos)));
+    assert_eq!(game.msg_log.messages[1], Msg::StateChange(gol, Behavior::Investigating(sound_pos)));
 }
 
 #[test]
 fn test_ai_investigate_moves() {
     let config = Config::from_file("../config.yaml");
     let mut game = Game::new(0, config);
-    make_map(&MapLoadConfig::Empty, &mut game);
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
 
 
     let start_pos = Pos::new(0, 0);
@@ -408,10 +2903,12 @@ fn test_ai_investigate_moves() {
     game.data.entities.messages[&gol].push(Message::Sound(player_id, sound_pos));
     ai_investigate(sound_pos, gol, &mut game.data, &mut game.msg_log, &game.config);
 
-    assert_eq!(1, game.msg_log.messages.len());
-    assert_eq!(game.msg_log.messages[0], Msg::StateChange(gol, Behavior::Investigating(sound_pos)));
+    assert_eq!(2, game.msg_log.messages.len());
+    assert_eq!(game.msg_log.messages[0], Msg::ReadMessage(gol, Message::Sound(player_id, sound_pos)));
+    assert_eq!(game.msg_log.messages[1], Msg::StateChange(gol, Behavior::Investigating(sound_pos)));
 
-    // if they investigate again, they try to move to the sound
+    // if they investigate again, once the sound has been read, they try to move to the sound
+    game.data.entities.read_message(gol, Message::Sound(player_id, sound_pos));
     game.msg_log.clear();
     ai_investigate(sound_pos, gol, &mut game.data, &mut game.msg_log, &game.config);
     assert_eq!(1, game.msg_log.messages.len());
@@ -419,17 +2916,780 @@ fn test_ai_investigate_moves() {
     assert_eq!(Msg::TryMove(gol, direction, 1, MoveMode::Walk), game.msg_log.messages[0]);
 }
 
-fn clean_entities(entities: &mut Entities, msg_log: &mut MsgLog) {
+/// Remove the entities marked for removal, such as those whose count down ran out.
+fn clean_entities(game: &mut Game) {
     let mut remove_ids: Vec<EntityId> = Vec::new();
-    for id in entities.ids.iter() {
-        if entities.needs_removal[id] {
+    for id in game.data.entities.ids.iter() {
+        if game.data.entities.needs_removal[id] {
             remove_ids.push(*id);
         }
     }
 
     for id in remove_ids {
-        msg_log.log(Msg::RemovedEntity(id));
-        entities.remove_entity(id);
+        remove_entity(id, &mut game.data, &mut game.settings, &mut game.msg_log);
+    }
+}
+
+/// Remove an entity while the game is being played. Along with its components, the
+/// references other entities and the game settings hold to it are removed, and a
+/// RemovedEntity message lets the display drop its animations.
+pub fn remove_entity(entity_id: EntityId, data: &mut GameData, settings: &mut GameSettings, msg_log: &mut MsgLog) {
+    if !data.entities.ids.contains(&entity_id) {
+        return;
+    }
+
+    if settings.conversation.map_or(false, |conversation| conversation.speaker == entity_id) {
+        settings.conversation = None;
+        if settings.state == GameState::Dialogue {
+            change_state(settings, GameState::Playing);
+        }
+    }
+
+    msg_log.log(Msg::RemovedEntity(entity_id));
+    data.entities.remove_entity(entity_id);
+}
+
+
+#[test]
+pub fn test_map_generation_loading() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::TestMap;
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    assert_eq!((10, 10), game.data.map.size());
+
+    game.start_map_generation();
+    assert_eq!(GameState::Loading, game.settings.state);
+
+    // moving does nothing while loading
+    while game.settings.state == GameState::Loading {
+        game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    }
+
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert_eq!((11, 12), game.data.map.size());
+    assert!(game.map_generation.is_none());
+}
+
+#[test]
+pub fn test_shop() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.async_map_generation = false;
+    config.win_level = 0;
+    let mut game = Game::new(0, config.clone());
+    game.shop = vec!(ShopStock { level: 1,
+                                 items: vec!(ShopItem { item: Item::Dagger, price: 3 }),
+                                 repair_price: 1 });
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    // coins are picked up into the player's purse rather than their hands
+    make_coins(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.step_game(InputAction::Pickup, 0.1);
+    assert_eq!(game.config.coins_per_pile, game.data.entities.coins[&player_id]);
+    assert_eq!(0, game.data.entities.inventory[&player_id].len());
+    game.data.entities.coins[&player_id] = 4;
+
+    // finishing a level visits the shop, and a purchase costs coins
+    game.finish_level();
+    assert_eq!(GameState::LevelComplete, game.settings.state);
+    game.step_game(InputAction::Confirm, 0.1);
+    assert_eq!(GameState::Shop, game.settings.state);
+    game.step_game(InputAction::SelectItem(0), 0.1);
+    assert_eq!(1, game.data.entities.coins[&player_id]);
+    let dagger = game.data.is_in_inventory(player_id, Item::Dagger).unwrap();
+
+    // items can't be bought without enough coins
+    game.step_game(InputAction::SelectItem(0), 0.1);
+    assert_eq!(1, game.data.entities.inventory[&player_id].len());
+
+    // worn items are repaired to full durability
+    game.data.entities.durability[&dagger] = 1;
+    game.step_game(InputAction::SelectItem(1), 0.1);
+    assert_eq!(game.config.durability_dagger, game.data.entities.durability[&dagger]);
+    assert_eq!(0, game.data.entities.coins[&player_id]);
+
+    // leaving starts the next level, and purchases are carried into it
+    game.step_game(InputAction::SelectItem(2), 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert_eq!(1, game.settings.level_num);
+    assert!(game.data.entities.inventory[&player_id].contains(&dagger));
+}
+
+#[test]
+pub fn test_level_complete() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.async_map_generation = false;
+    config.win_level = 0;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    make_exit(&mut game.data.entities, &config, Pos::new(2, 0), &mut game.msg_log);
+    let key = make_key(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, key);
+    let dagger = make_dagger(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, dagger);
+    game.data.entities.fighter[&player_id].hp -= 1;
+    let hp = game.data.entities.fighter[&player_id].hp;
+
+    // reaching the exit shows how the level went before the next one starts
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(GameState::LevelComplete, game.settings.state);
+    assert_eq!(1, game.settings.level_num);
+
+    let summary = game.settings.level_summary.unwrap();
+    assert_eq!(1, summary.level);
+    assert_eq!(2, summary.turns);
+    assert!(summary.stats.noise > 0);
+    assert_eq!(hp, summary.hp);
+    assert!(Narrator::new().narrate(&game).iter().any(|line| line.ends_with("level 1 complete")));
+
+    // moving is ignored until the player continues
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(GameState::LevelComplete, game.settings.state);
+
+    // the next level keeps the player's items and hp, but not the key
+    game.step_game(InputAction::Confirm, 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert!(game.data.entities.inventory[&player_id].contains(&dagger));
+    assert!(game.data.is_in_inventory(player_id, Item::Key).is_none());
+    assert_eq!(hp, game.data.entities.fighter[&player_id].hp);
+
+    // the next level's stats start from nothing
+    assert_eq!(game.settings.stats, game.settings.level_start_stats);
+    assert_eq!(game.settings.turn_count, game.settings.level_start_turn);
+}
+
+#[test]
+pub fn test_checkpoint() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.async_map_generation = false;
+    config.win_level = 0;
+    let mut game = Game::new(0, config.clone());
+
+    let checkpoint_file = std::env::temp_dir().join("test_checkpoint.yaml");
+    let checkpoint_file = checkpoint_file.to_str().unwrap();
+    let _ = std::fs::remove_file(checkpoint_file);
+    game.set_checkpoint_file(checkpoint_file);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    make_exit(&mut game.data.entities, &config, Pos::new(2, 0), &mut game.msg_log);
+    let key = make_key(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, key);
+
+    // starting the next level saves a checkpoint
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    game.step_game(InputAction::Confirm, 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+
+    let checkpoint = Checkpoint::load(checkpoint_file).unwrap();
+    assert_eq!(1, checkpoint.settings.level_num);
+    assert_eq!(game.data.entities.ids, checkpoint.data.entities.ids);
+
+    // resuming puts the run back as it was when the level started
+    let level_start_turn = game.settings.turn_count;
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_ne!(level_start_turn, game.settings.turn_count);
+
+    confirm(&mut game.settings, "Resume?", InputAction::ResumeCheckpoint);
+    game.step_game(InputAction::Confirm, 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert_eq!(checkpoint.data.entities.pos, game.data.entities.pos);
+    assert_eq!(level_start_turn, game.settings.turn_count);
+    assert_eq!(checkpoint.rng, game.rng.state());
+
+    // a finished run has nothing to resume
+    game.end_run(false);
+    assert!(!std::path::Path::new(checkpoint_file).exists());
+    assert!(game.resume_checkpoint().is_err());
+}
+
+#[test]
+pub fn test_ghost() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.async_map_generation = false;
+    config.ghost_replay = true;
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+
+    let ghost_dir = std::env::temp_dir().join("test_ghosts");
+    let _ = std::fs::remove_dir_all(&ghost_dir);
+    let ghost_dir = ghost_dir.to_str().unwrap();
+    game.set_ghost_dir(ghost_dir);
+
+    // the first run on a seed has nothing to race
+    game.new_run(5);
+    assert!(game.ghost.is_none());
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    game.end_run(false);
+    assert_eq!(3, read_ghost(&ghost_file(ghost_dir, 5)).unwrap().len());
+
+    // playing the seed again brings back that run, a turn at a time
+    game.new_run(5);
+    assert!(game.ghost.is_some());
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let start_pos = game.data.entities.pos[&player_id];
+    assert_eq!(Some(start_pos), game.ghost.as_ref().unwrap().player_pos(game.settings.level_num));
+
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Some(add_pos(start_pos, Pos::new(1, 0))), game.ghost.as_ref().unwrap().player_pos(game.settings.level_num));
+
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    let ghost = game.ghost.as_ref().unwrap();
+    assert!(ghost.finished());
+    assert_eq!(Some(add_pos(start_pos, Pos::new(2, 0))), ghost.player_pos(game.settings.level_num));
+
+    // a quieter run scores better, and becomes the ghost to race next time
+    game.end_run(false);
+    assert_eq!(Some(0), game.settings.rank);
+    assert_eq!(4, read_ghost(&ghost_file(ghost_dir, 5)).unwrap().len());
+
+    std::fs::remove_dir_all(ghost_dir).unwrap();
+}
+
+#[test]
+pub fn test_spectate() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    let mut server = SpectatorServer::bind("127.0.0.1:0").unwrap();
+    let stream = std::net::TcpStream::connect(server.address().unwrap()).unwrap();
+
+    // a spectator joining is sent the whole game
+    server.update(&game).unwrap();
+    assert_eq!(1, server.num_spectators());
+
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    game.data.map[(3, 3)] = Tile::wall();
+    server.update(&game).unwrap();
+
+    // nothing is sent on a step where nothing happened
+    game.msg_log.clear();
+    game.step_game(InputAction::None, 0.1);
+    server.update(&game).unwrap();
+    drop(server);
+
+    let mut frames = Vec::new();
+    read_frames(stream, |frame| { frames.push(frame); return true; }).unwrap();
+    assert_eq!(2, frames.len());
+    assert!(matches!(frames[0], SpectatorFrame::Start(_)));
+
+    // the turn is sent as a change to the game the spectator was sent, with its messages
+    let mut spectator_game = Game::new(1, config.clone());
+    let mut spectator = Spectator::new();
+    spectator.receive(frames[0].clone(), &mut spectator_game).unwrap();
+    assert_ne!(game.data.entities.pos[&player_id], spectator_game.data.entities.pos[&player_id]);
+
+    let msgs = spectator.receive(frames[1].clone(), &mut spectator_game).unwrap();
+    assert!(msgs.iter().any(|msg| matches!(msg, Msg::Moved(entity_id, _, _) if *entity_id == player_id)));
+    assert_eq!(game.data.entities.pos[&player_id], spectator_game.data.entities.pos[&player_id]);
+    assert_eq!(game.data.map.tiles, spectator_game.data.map.tiles);
+    assert_eq!(game.settings.turn_count, spectator_game.settings.turn_count);
+
+    // a spectator who stops reading is dropped rather than holding up the game
+    let mut server = SpectatorServer::bind("127.0.0.1:0").unwrap();
+    let _stalled = std::net::TcpStream::connect(server.address().unwrap()).unwrap();
+    for _ in 0..100 {
+        server.update(&game).unwrap();
+        if server.num_spectators() > 0 {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(1, server.num_spectators());
+    for _ in 0..1000 {
+        let start = std::time::Instant::now();
+        game.settings.turn_count += 1;
+        for _ in 0..1000 {
+            game.msg_log.turn_messages.push_back(Msg::Sound(player_id, Pos::new(0, 0), 1, false));
+        }
+        server.update(&game).unwrap();
+        game.msg_log.clear();
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+        if server.num_spectators() == 0 {
+            break;
+        }
+    }
+    assert_eq!(0, server.num_spectators());
+}
+
+#[test]
+pub fn test_coop() {
+    use std::io::{Write, BufRead};
+
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(5, 5);
+
+    let mut host = CoopHost::bind("127.0.0.1:0").unwrap();
+    let mut partner_stream = std::net::TcpStream::connect(host.address().unwrap()).unwrap();
+    for _ in 0..100 {
+        host.step_game(&mut game, InputAction::None, 0.1).unwrap();
+        if host.connected() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert!(host.connected());
+
+    // the partner appears beside the player, and is streamed the game
+    let partner_id = game.data.find_by_name(EntityName::Partner).unwrap();
+    let partner_pos = game.data.entities.pos[&partner_id];
+    assert_eq!(1, distance(Pos::new(5, 5), partner_pos));
+
+    let mut line = String::new();
+    std::io::BufReader::new(partner_stream.try_clone().unwrap()).read_line(&mut line).unwrap();
+    assert!(matches!(serde_json::from_str(&line).unwrap(), SpectatorFrame::Start(_)));
+
+    // the host's move waits for the partner's
+    let turn_count = game.settings.turn_count;
+    host.step_game(&mut game, InputAction::Move(Direction::Right, MoveMode::Walk), 0.1).unwrap();
+    assert!(host.waiting());
+    assert_eq!(turn_count, game.settings.turn_count);
+    assert_eq!(Pos::new(5, 5), game.data.entities.pos[&player_id]);
+
+    let away = Direction::from_positions(Pos::new(5, 5), partner_pos).unwrap();
+    writeln!(partner_stream, "{}", InputAction::Move(away, MoveMode::Walk)).unwrap();
+    for _ in 0..100 {
+        host.step_game(&mut game, InputAction::None, 0.1).unwrap();
+        if !host.waiting() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    // both moves are played as a single turn
+    assert_eq!(turn_count + 1, game.settings.turn_count);
+    assert_eq!(Pos::new(6, 5), game.data.entities.pos[&player_id]);
+    assert_eq!(add_pos(partner_pos, away.into_move()), game.data.entities.pos[&partner_id]);
+
+    // once the partner leaves, the host plays alone, and a move they were waiting on is forgotten
+    host.step_game(&mut game, InputAction::Move(Direction::Right, MoveMode::Walk), 0.1).unwrap();
+    assert!(host.waiting());
+    drop(partner_stream);
+    for _ in 0..100 {
+        host.step_game(&mut game, InputAction::None, 0.1).unwrap();
+        if !host.connected() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert!(!host.connected());
+    assert!(!host.waiting());
+    assert_eq!(None, game.data.find_by_name(EntityName::Partner));
+    assert_eq!(Pos::new(6, 5), game.data.entities.pos[&player_id]);
+    host.step_game(&mut game, InputAction::Move(Direction::Right, MoveMode::Walk), 0.1).unwrap();
+    assert_eq!(turn_count + 2, game.settings.turn_count);
+}
+
+#[test]
+pub fn test_delta() {
+    let old = serde_json::json!({ "pos": [[1, 2], [3, 4]], "hp": 5, "gone": true });
+    let new = serde_json::json!({ "pos": [[1, 2], [3, 5]], "hp": 5, "name": "gol" });
+
+    let delta = diff(&old, &new).unwrap();
+    let mut fields = std::collections::BTreeMap::new();
+    fields.insert("pos".to_string(), Delta::Items(vec!((1, Delta::Items(vec!((1, Delta::Set(serde_json::json!(5)))))))));
+    fields.insert("gone".to_string(), Delta::Remove);
+    fields.insert("name".to_string(), Delta::Set(serde_json::json!("gol")));
+    assert_eq!(Delta::Fields(fields), delta);
+
+    let mut value = old.clone();
+    apply(&mut value, delta).unwrap();
+    assert_eq!(new, value);
+
+    assert_eq!(None, diff(&new, &new));
+    assert!(apply(&mut serde_json::json!(1), Delta::Items(vec!((0, Delta::Remove)))).is_err());
+}
+
+#[test]
+pub fn test_crash_marker() {
+    let marker_file = std::env::temp_dir().join("test_running.marker");
+    let marker_file = marker_file.to_str().unwrap();
+    let _ = std::fs::remove_file(marker_file);
+
+    // a session which ends cleanly leaves no mark for the next one
+    assert!(!start_session(marker_file));
+    end_session(marker_file);
+    assert!(!start_session(marker_file));
+
+    // one which never ends looks like a crash
+    assert!(start_session(marker_file));
+
+    end_session(marker_file);
+}
+
+#[test]
+pub fn test_alarm_reinforcements() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.alarm_distance = 4;
+    config.alarm_wave_monster = EntityName::Pawn;
+    config.alarm_wave_size = 2;
+    config.alarm_wave_delay = 1;
+    config.alarm_wave_interval = 1;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    assert!(game.data.entry_points.len() > 0);
+
+    // an alerted enemy runs to a nearby alarm before attacking
+    let alarm = make_alarm(&mut game.data.entities, &game.config, Pos::new(5, 5), &mut game.msg_log);
+    let pawn = make_pawn(&mut game.data.entities, &game.config, Pos::new(5, 7), &mut game.msg_log);
+    game.data.entities.behavior[&pawn] = Behavior::Attacking(player_id);
+
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Behavior::RaisingAlarm(alarm), game.data.entities.behavior[&pawn]);
+    assert_eq!(Pos::new(5, 6), game.data.entities.pos[&pawn]);
+
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Some(&false), game.data.entities.armed.get(&alarm));
+    assert_eq!(Behavior::Attacking(player_id), game.data.entities.behavior[&pawn]);
+    assert_eq!(2, game.data.reinforcements.len());
+
+    // the wave arrives at the entry points over the next few turns, heading for the alarm
+    game.data.entities.remove_entity(pawn);
+    let mut arrived = Vec::new();
+    for _ in 0..4 {
+        game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+        for msg in game.msg_log.turn_messages.iter() {
+            if let Msg::Reinforcement(entity_id, pos) = msg {
+                assert!(game.data.entry_points.contains(pos));
+                if !arrived.contains(entity_id) {
+                    arrived.push(*entity_id);
+                }
+            }
+        }
+    }
+    assert_eq!(2, arrived.len());
+    assert_eq!(0, game.data.reinforcements.len());
+    assert_eq!(EntityName::Pawn, game.data.entities.name[&arrived[0]]);
+}
+
+#[test]
+pub fn test_faction_infighting() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let pawn = make_pawn(&mut game.data.entities, &game.config, Pos::new(5, 5), &mut game.msg_log);
+    let knight = make_knight(&mut game.data.entities, &game.config, Pos::new(5, 8), &mut game.msg_log);
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(7, 5), &mut game.msg_log);
+    game.data.entities.direction[&pawn] = Direction::Right;
+    game.data.entities.direction[&gol] = Direction::Right;
+
+    assert_eq!(Relation::Friendly, game.data.relation(pawn, knight, &game.config));
+    assert_eq!(Relation::Hostile, game.data.relation(pawn, gol, &game.config));
+    assert_eq!(Relation::Hostile, game.data.relation(gol, player_id, &game.config));
+
+    // factions which aren't listed as hostile leave each other alone
+    config.faction_hostility.clear();
+    assert_eq!(Relation::Neutral, game.data.relation(pawn, gol, &config));
+
+    // an alerted elf turns on a gol it comes across, even with the player out of sight
+    game.data.entities.behavior[&pawn] = Behavior::Investigating(Pos::new(9, 5));
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Behavior::Attacking(gol), game.data.entities.behavior[&pawn]);
+
+    // the gol fights back once it is attacked
+    for _ in 0..3 {
+        game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    }
+    let gol_fighter = game.data.entities.fighter[&gol];
+    assert!(gol_fighter.hp < gol_fighter.max_hp);
+    assert_eq!(Behavior::Attacking(pawn), game.data.entities.behavior[&gol]);
+}
+
+#[test]
+pub fn test_critters_scatter() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.critter_disturb_distance = 2;
+    config.sound_radius_rat = 3;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    game.data.entities.stance[&player_id] = Stance::Standing;
+
+    let rat = make_rat(&mut game.data.entities, &game.config, Pos::new(5, 5), &mut game.msg_log);
+    let bird = make_bird(&mut game.data.entities, &game.config, Pos::new(8, 0), &mut game.msg_log);
+    let pawn = make_pawn(&mut game.data.entities, &game.config, Pos::new(5, 8), &mut game.msg_log);
+    game.data.entities.direction[&pawn] = Direction::Down;
+
+    // an undisturbed critter wanders about quietly
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Behavior::Idle, game.data.entities.behavior[&rat]);
+    assert_eq!(Behavior::Idle, game.data.entities.behavior[&pawn]);
+
+    // a crouching player can creep past
+    game.data.entities.pos[&rat] = Pos::new(5, 5);
+    game.data.entities.pos[&player_id] = Pos::new(4, 4);
+    game.data.entities.stance[&player_id] = Stance::Crouching;
+    game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    assert_eq!(Behavior::Idle, game.data.entities.behavior[&rat]);
+
+    // but a standing player sends it running, and the noise draws the pawn over
+    game.data.entities.pos[&rat] = Pos::new(5, 5);
+    game.data.entities.stance[&player_id] = Stance::Standing;
+    game.step_game(InputAction::Pass(MoveMode::Run), 0.1);
+    assert!(game.msg_log.turn_messages.contains(&Msg::Scattered(rat)));
+    assert_eq!(Behavior::Fleeing(Pos::new(4, 4)), game.data.entities.behavior[&rat]);
+    assert!(distance(Pos::new(4, 4), game.data.entities.pos[&rat]) > 1);
+
+    game.data.entities.stance[&player_id] = Stance::Crouching;
+    game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    assert!(game.msg_log.turn_messages.contains(&Msg::StateChange(pawn, Behavior::Investigating(Pos::new(5, 5)))));
+
+    // birds fly off when disturbed
+    assert!(game.data.entities.ids.contains(&bird));
+    game.data.entities.pos[&player_id] = Pos::new(8, 2);
+    game.data.entities.stance[&player_id] = Stance::Standing;
+    game.step_game(InputAction::Pass(MoveMode::Run), 0.1);
+    assert!(game.msg_log.turn_messages.contains(&Msg::Scattered(bird)));
+    assert!(game.data.entities.needs_removal[&bird]);
+}
+
+#[test]
+pub fn test_investigation_loses_interest() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.investigate_turns = 2;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(9, 0);
+
+    let pawn = make_pawn(&mut game.data.entities, &game.config, Pos::new(1, 1), &mut game.msg_log);
+    game.msg_log.log(Msg::StateChange(pawn, Behavior::Investigating(Pos::new(8, 8))));
+    game.data.entities.took_turn[&pawn] = true;
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+    assert_eq!(Some(&2), game.data.entities.interest.get(&pawn));
+
+    // with nothing new to go on, the pawn gives up part way there
+    for _ in 0..3 {
+        game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    }
+    assert_eq!(Behavior::Idle, game.data.entities.behavior[&pawn]);
+    assert!(game.data.entities.pos[&pawn] != Pos::new(8, 8));
+
+    // a new clue renews its interest
+    game.msg_log.log(Msg::Sound(player_id, Pos::new(5, 5), 4, false));
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+    game.step_game(InputAction::Pass(MoveMode::Sneak), 0.1);
+    assert_eq!(Behavior::Investigating(Pos::new(5, 5)), game.data.entities.behavior[&pawn]);
+    assert_eq!(Some(&1), game.data.entities.interest.get(&pawn));
+}
+
+#[test]
+pub fn test_entity_inspector() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let pawn_pos = Pos::new(5, 5);
+    let pawn = make_pawn(&mut game.data.entities, &game.config, pawn_pos, &mut game.msg_log);
+
+    // clicking only inspects in god mode
+    game.step_game(InputAction::MapClick(pawn_pos, pawn_pos), 0.1);
+    assert_eq!(None, game.settings.inspecting);
+
+    game.settings.god_mode = true;
+    game.step_game(InputAction::MapClick(pawn_pos, pawn_pos), 0.1);
+    assert_eq!(Some(pawn), game.settings.inspecting);
+
+    let lines = inspect_entity(pawn, &game.data);
+    let fighter = game.data.entities.fighter[&pawn];
+    assert!(lines.contains(&format!("hp {}/{}", fighter.hp, fighter.max_hp)));
+    assert!(lines.contains(&format!("{}", game.data.entities.behavior[&pawn])));
+
+    // the lines follow the entity as it changes
+    game.data.entities.fighter[&pawn].hp -= 1;
+    let lines = inspect_entity(pawn, &game.data);
+    assert!(lines.contains(&format!("hp {}/{}", fighter.hp - 1, fighter.max_hp)));
+
+    // clicking an empty tile closes the inspector
+    game.step_game(InputAction::MapClick(Pos::new(8, 8), Pos::new(8, 8)), 0.1);
+    assert_eq!(None, game.settings.inspecting);
+}
+
+#[test]
+pub fn test_save_config() {
+    let file_name = std::env::temp_dir().join("test_save_config.yaml").to_str().unwrap().to_string();
+    std::fs::copy("../config.yaml", &file_name).unwrap();
+
+    let mut config = Config::from_file(&file_name);
+    config.difficulty = Difficulty::Normal;
+    config.difficulties.insert(Difficulty::Hard,
+                               DifficultyConfig { spawn_budget: Some(config.spawn_budget + 10),
+                                                  ..DifficultyConfig::default() });
+    let mut game = Game::new(0, config.clone());
+    game.set_difficulty(Difficulty::Hard);
+
+    // settings changed while playing are saved, but not the difficulty's settings
+    game.config.set_field("frame_rate", ConfigValue::Int(config.frame_rate as i64 + 5)).unwrap();
+    game.save_config(&file_name).unwrap();
+
+    let saved = Config::from_file(&file_name);
+    assert_eq!(config.frame_rate + 5, saved.frame_rate);
+    assert_eq!(config.spawn_budget, saved.spawn_budget);
+
+    std::fs::remove_file(&file_name).unwrap();
+}
+
+#[test]
+pub fn test_telemetry() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(4, 0), &mut game.msg_log);
+    let dagger = make_dagger(&mut game.data.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.msg_log.clear();
+
+    let file_name = std::env::temp_dir().join("test_telemetry.jsonl").to_str().unwrap().to_string();
+    let _ = std::fs::remove_file(&file_name);
+    let mut telemetry = Telemetry::new(&file_name).unwrap();
+
+    // a turn's line is written once the turn is over, with the events picked out of its messages
+    game.msg_log.turn_messages.push_back(Msg::StateChange(gol, Behavior::Attacking(player_id)));
+    game.msg_log.turn_messages.push_back(Msg::Attack(gol, player_id, 2));
+    game.msg_log.turn_messages.push_back(Msg::UseItem(player_id, Pos::new(1, 0), dagger));
+    telemetry.record(&game).unwrap();
+    game.msg_log.clear();
+    game.settings.turn_count += 1;
+    telemetry.record(&game).unwrap();
+
+    // the end of a run is written only once
+    game.end_run(false);
+    telemetry.record(&game).unwrap();
+    telemetry.record(&game).unwrap();
+
+    let records = read_telemetry(&file_name).unwrap();
+    assert_eq!(2, records.len());
+    if let TelemetryRecord::Turn(turn) = &records[0] {
+        assert_eq!(1, turn.turn);
+        assert_eq!(3, turn.messages.len());
+        assert_eq!(vec!(DamageEvent { source: EntityName::Gol, target: EntityName::Player, amount: 2 }), turn.damage);
+        assert_eq!(1, turn.detections.len());
+        assert_eq!(4, turn.detections[0].distance);
+    } else {
+        panic!("expected a turn record");
     }
+
+    let summary = summarize_telemetry(&records);
+    assert_eq!(1, summary.runs);
+    assert_eq!(0, summary.wins);
+    assert_eq!(Some(&1), summary.detections[&EntityName::Gol].distances.get(&4));
+    assert_eq!((1, 2), summary.damage[&EntityName::Gol]);
+    assert_eq!(Some(&1), summary.items.get(&Item::Dagger));
+
+    std::fs::remove_file(&file_name).unwrap();
+}
+
+#[test]
+pub fn test_agents() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.win_level = 1;
+    let mut game = Game::new(0, config.clone());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    game.data.objective = Objective::KeyAndExit;
+    make_key(&mut game.data.entities, &game.config, Pos::new(4, 2), &mut game.msg_log);
+    make_exit(&mut game.data.entities, &game.config, Pos::new(1, 6), &mut game.msg_log);
+    game.data.map[(2, 1)] = Tile::wall();
+
+    // the seeker walks to the key and then to the exit
+    let run = play_agent(&mut SeekerAgent, &mut game, 100);
+    assert_eq!(AgentOutcome::Won, run.outcome);
+    assert_eq!(1, run.levels);
+
+    // a player killed by a gol reports it as the cause
+    let mut game = Game::new(0, config.clone());
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    make_map(&MapLoadConfig::Empty, &mut game).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    game.data.entities.fighter[&player_id].hp = 1;
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    game.data.entities.behavior[&gol] = Behavior::Attacking(player_id);
+    let run = play_agent(&mut RandomAgent::new(0), &mut game, 100);
+    assert_eq!(AgentOutcome::Died("gol".to_string()), run.outcome);
+
+    // empty maps have no key, so every run gives up
+    let summary = agent_stats(&mut RandomAgent::new(0), &game, &MapLoadConfig::Empty, 0..2, 50).unwrap();
+    assert_eq!(2, summary.runs.len());
+    assert!(summary.runs.iter().all(|run| run.outcome == AgentOutcome::TimedOut));
+    assert!(summary.to_string().contains("won: 0 (0.0%), timed out: 2"));
 }
 
+
+#[test]
+fn test_map_load_errors() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::FromFile("no_such_map_list.txt".to_string());
+    config.async_map_generation = false;
+    let mut game = Game::new(0, config.clone());
+
+    // a missing map file is returned rather than panicking
+    let result = make_map(&config.map_load, &mut game);
+    assert!(matches!(result, Err(GameError::Read { .. })));
+
+    // a level which fails to load shows the error until the player exits
+    game.settings.state = GameState::Playing;
+    game.start_next_level();
+    assert_eq!(GameState::Error, game.settings.state);
+    assert!(game.settings.error.as_ref().unwrap().to_string().contains("no_such_map_list.txt"));
+
+    game.step_game(InputAction::Esc, 0.1);
+    assert_eq!(GameState::Exit, game.settings.state);
+
+    // so does a level generated on its own thread
+    config.async_map_generation = true;
+    let mut game = Game::new(0, config.clone());
+    game.start_next_level();
+    while game.settings.state == GameState::Loading {
+        game.step_game(InputAction::None, 0.1);
+    }
+    assert_eq!(GameState::Error, game.settings.state);
+}
@@ -0,0 +1,320 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+
+use roguelike_core::types::*;
+use roguelike_core::messaging::Msg;
+use roguelike_core::movement::MoveMode;
+use roguelike_core::config::Difficulty;
+use roguelike_core::utils::distance;
+use roguelike_core::ai::Behavior;
+
+use crate::game::Game;
+
+
+/// The player's state at the end of a turn.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerState {
+    pub pos: Pos,
+    pub hp: Hp,
+    pub stamina: u32,
+    pub move_mode: MoveMode,
+    pub noise: usize, // noise made so far in the run
+}
+
+/// A monster noticing something- either the player, which it starts attacking, or
+/// something it goes to investigate.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Detection {
+    pub monster: EntityName,
+    pub attacking: bool,
+    pub distance: i32, // from the monster to the player
+    pub fov_radius: i32, // the monster's
+    pub player_move_mode: MoveMode,
+}
+
+/// Damage done to an entity, and what did it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DamageEvent {
+    pub source: EntityName,
+    pub target: EntityName,
+    pub amount: Hp,
+}
+
+/// An item used or thrown.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ItemUse {
+    pub user: EntityName,
+    pub item: Item,
+}
+
+/// Everything that happened in a turn. The messages are kept as they were, and the
+/// events used for balancing are picked out of them while the entities they name
+/// still exist.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TurnRecord {
+    pub seed: u64,
+    pub level: usize,
+    pub turn: usize,
+    pub player: PlayerState,
+    pub detections: Vec<Detection>,
+    pub damage: Vec<DamageEvent>,
+    pub items: Vec<ItemUse>,
+    pub messages: Vec<Msg>,
+}
+
+/// How a run ended.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RunEndRecord {
+    pub seed: u64,
+    pub won: bool,
+    pub score: i64,
+    pub turns: usize,
+    pub difficulty: Difficulty,
+}
+
+/// A line of a telemetry file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TelemetryRecord {
+    Turn(TurnRecord),
+    RunEnd(RunEndRecord),
+}
+
+/// Writes a line of JSON to a telemetry file for each turn played, and for each run's
+/// end. The file is appended to, so it collects many runs for the analyzer to summarize.
+pub struct Telemetry {
+    file: File,
+    last_turn: usize,
+    run_ended: bool,
+    detections: Vec<Detection>,
+    damage: Vec<DamageEvent>,
+    items: Vec<ItemUse>,
+    messages: Vec<Msg>,
+}
+
+impl Telemetry {
+    pub fn new(file_name: &str) -> Result<Telemetry, String> {
+        let file = OpenOptions::new().create(true)
+                                     .append(true)
+                                     .open(file_name)
+                                     .map_err(|err| format!("Could not open telemetry file {}: {}", file_name, err))?;
+
+        return Ok(Telemetry { file,
+                              last_turn: 0,
+                              run_ended: false,
+                              detections: Vec::new(),
+                              damage: Vec::new(),
+                              items: Vec::new(),
+                              messages: Vec::new() });
+    }
+
+    /// Record the messages from a step of the game. A turn can take several steps, so
+    /// its line is written once the turn count changes.
+    pub fn record(&mut self, game: &Game) -> Result<(), String> {
+        for msg in game.msg_log.turn_messages.iter() {
+            self.record_msg(*msg, game);
+            self.messages.push(*msg);
+        }
+
+        if game.settings.turn_count != self.last_turn {
+            self.last_turn = game.settings.turn_count;
+
+            if let Some(player) = player_state(game) {
+                let turn = TurnRecord { seed: game.settings.seed,
+                                        level: game.settings.level_num,
+                                        turn: game.settings.turn_count,
+                                        player,
+                                        detections: std::mem::take(&mut self.detections),
+                                        damage: std::mem::take(&mut self.damage),
+                                        items: std::mem::take(&mut self.items),
+                                        messages: std::mem::take(&mut self.messages) };
+                self.write(&TelemetryRecord::Turn(turn))?;
+            }
+        }
+
+        // the score is kept until the next run starts
+        if let (Some(entry), false) = (&game.settings.last_score, self.run_ended) {
+            let run_end = RunEndRecord { seed: entry.seed,
+                                         won: entry.won,
+                                         score: entry.score,
+                                         turns: entry.turns,
+                                         difficulty: entry.difficulty };
+            self.write(&TelemetryRecord::RunEnd(run_end))?;
+        }
+        self.run_ended = game.settings.last_score.is_some();
+
+        return Ok(());
+    }
+
+    fn record_msg(&mut self, msg: Msg, game: &Game) {
+        let entities = &game.data.entities;
+
+        match msg {
+            Msg::StateChange(monster_id, behavior) => {
+                let attacking =
+                    match behavior {
+                        Behavior::Attacking(target_id) => Some(entities.typ.get(&target_id) == Some(&EntityType::Player)),
+                        Behavior::Investigating(_) => Some(false),
+                        _ => None,
+                    };
+
+                let player_id = game.data.find_by_name(EntityName::Player);
+                if let (Some(attacking), Some(player_id)) = (attacking, player_id) {
+                    // attacking anything but the player is monsters fighting each other
+                    if attacking || !matches!(behavior, Behavior::Attacking(_)) {
+                        self.detections.push(Detection { monster: entities.name[&monster_id],
+                                                         attacking,
+                                                         distance: distance(entities.pos[&monster_id], entities.pos[&player_id]),
+                                                         fov_radius: entities.fov_radius.get(&monster_id).map_or(0, |radius| *radius),
+                                                         player_move_mode: entities.move_mode.get(&player_id).map_or(MoveMode::Walk, |mode| *mode) });
+                    }
+                }
+            }
+
+            Msg::Attack(source_id, target_id, amount) |
+            Msg::CriticalHit(source_id, target_id, amount) |
+            Msg::Knockback(source_id, target_id, _, amount) => {
+                if let (Some(source), Some(target), true) = (entities.name.get(&source_id), entities.name.get(&target_id), amount > 0) {
+                    self.damage.push(DamageEvent { source: *source, target: *target, amount });
+                }
+            }
+
+            Msg::UseItem(user_id, _, item_id) | Msg::ItemThrow(user_id, item_id, _, _) => {
+                if let (Some(user), Some(item)) = (entities.name.get(&user_id), entities.item.get(&item_id)) {
+                    self.items.push(ItemUse { user: *user, item: *item });
+                }
+            }
+
+            _ => {
+            }
+        }
+    }
+
+    fn write(&mut self, record: &TelemetryRecord) -> Result<(), String> {
+        let line = serde_json::to_string(record).map_err(|err| err.to_string())?;
+        return writeln!(self.file, "{}", line).map_err(|err| err.to_string());
+    }
+}
+
+fn player_state(game: &Game) -> Option<PlayerState> {
+    let player_id = game.data.find_by_name(EntityName::Player)?;
+    let entities = &game.data.entities;
+
+    return Some(PlayerState { pos: entities.pos[&player_id],
+                              hp: entities.fighter.get(&player_id).map_or(0, |fighter| fighter.hp),
+                              stamina: entities.stamina.get(&player_id).map_or(0, |stamina| *stamina),
+                              move_mode: entities.move_mode.get(&player_id).map_or(MoveMode::Walk, |mode| *mode),
+                              noise: game.settings.stats.noise });
+}
+
+/// Read every record in a telemetry file. Lines which can't be read, such as one cut
+/// off when the game was closed, are skipped.
+pub fn read_telemetry(file_name: &str) -> Result<Vec<TelemetryRecord>, String> {
+    let file = File::open(file_name).map_err(|err| format!("Could not open telemetry file {}: {}", file_name, err))?;
+
+    let mut records = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|err| format!("Could not read {}: {}", file_name, err))?;
+        if let Ok(record) = serde_json::from_str(&line) {
+            records.push(record);
+        }
+    }
+
+    return Ok(records);
+}
+
+/// How often, and from how far, a type of monster noticed something.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DetectionStats {
+    pub attacking: usize,
+    pub investigating: usize,
+    pub distances: BTreeMap<i32, usize>, // distance to the player when attacking, and how often
+}
+
+/// Totals across every run in a telemetry file, for balancing perception radii and
+/// the damage and items in the game.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TelemetrySummary {
+    pub runs: usize,
+    pub wins: usize,
+    pub turns: usize,
+    pub detections: BTreeMap<EntityName, DetectionStats>,
+    pub detection_move_modes: BTreeMap<String, usize>, // the player's move mode when attacked
+    pub damage: BTreeMap<EntityName, (usize, Hp)>, // hits and total damage by source
+    pub items: BTreeMap<Item, usize>,
+}
+
+pub fn summarize_telemetry(records: &Vec<TelemetryRecord>) -> TelemetrySummary {
+    let mut summary = TelemetrySummary::default();
+
+    for record in records.iter() {
+        match record {
+            TelemetryRecord::Turn(turn) => {
+                summary.turns += 1;
+
+                for detection in turn.detections.iter() {
+                    let stats = summary.detections.entry(detection.monster).or_insert(DetectionStats::default());
+                    if detection.attacking {
+                        stats.attacking += 1;
+                        *stats.distances.entry(detection.distance).or_insert(0) += 1;
+                        *summary.detection_move_modes.entry(detection.player_move_mode.to_string()).or_insert(0) += 1;
+                    } else {
+                        stats.investigating += 1;
+                    }
+                }
+
+                for damage in turn.damage.iter() {
+                    let (hits, total) = summary.damage.entry(damage.source).or_insert((0, 0));
+                    *hits += 1;
+                    *total += damage.amount;
+                }
+
+                for item_use in turn.items.iter() {
+                    *summary.items.entry(item_use.item).or_insert(0) += 1;
+                }
+            }
+
+            TelemetryRecord::RunEnd(run_end) => {
+                summary.runs += 1;
+                if run_end.won {
+                    summary.wins += 1;
+                }
+            }
+        }
+    }
+
+    return summary;
+}
+
+impl fmt::Display for TelemetrySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} runs ({} won), {} turns", self.runs, self.wins, self.turns)?;
+
+        writeln!(f, "detections:")?;
+        for (monster, stats) in self.detections.iter() {
+            let attacks = stats.distances.iter().map(|(distance, count)| distance * *count as i32).sum::<i32>();
+            let mean = if stats.attacking > 0 { attacks as f32 / stats.attacking as f32 } else { 0.0 };
+            let max = stats.distances.keys().last().map_or(0, |distance| *distance);
+            writeln!(f, "  {}: {} attacking (distance mean {:.1}, max {}), {} investigating",
+                     monster, stats.attacking, mean, max, stats.investigating)?;
+        }
+        for (move_mode, count) in self.detection_move_modes.iter() {
+            writeln!(f, "  player {}: {}", move_mode, count)?;
+        }
+
+        writeln!(f, "damage:")?;
+        for (source, (hits, total)) in self.damage.iter() {
+            writeln!(f, "  {}: {} hits, {} damage", source, hits, total)?;
+        }
+
+        write!(f, "items:")?;
+        for (item, count) in self.items.iter() {
+            write!(f, "\n  {}: {}", item, count)?;
+        }
+
+        return Ok(());
+    }
+}
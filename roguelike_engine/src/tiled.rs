@@ -0,0 +1,192 @@
+use std::fs;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use roguelike_core::types::*;
+use roguelike_core::map::*;
+use roguelike_core::constants::*;
+use roguelike_core::config::Config;
+use roguelike_core::messaging::MsgLog;
+use roguelike_core::movement::Direction;
+
+use crate::make_map::{read_map_cell, entity_map_chr};
+
+
+/// A map saved by the Tiled editor in its JSON format.
+///
+/// Tile layers are named after the layers of a REXPaint map- ground, environment,
+/// entities, and links- and use the 16x16 tileset resources/rexpaint16x16.png, so each
+/// tile stands for the same character it would in a REXPaint map. Tile layers must be
+/// saved with the CSV layer format.
+///
+/// Entities can also be placed in any object layer, with each object named after the
+/// entity it places, such as "gol" or "dagger". An object with a "facing" property,
+/// such as "downleft", starts facing that direction.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct TiledMap {
+    pub width: u32,
+    pub height: u32,
+    pub tilewidth: u32,
+    pub tileheight: u32,
+    pub layers: Vec<TiledLayer>,
+    #[serde(default)]
+    pub tilesets: Vec<TiledTileset>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct TiledTileset {
+    pub firstgid: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct TiledLayer {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub typ: String,
+    #[serde(default)]
+    pub data: Vec<u32>,
+    #[serde(default)]
+    pub objects: Vec<TiledObject>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct TiledObject {
+    #[serde(default)]
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    #[serde(default)]
+    pub gid: Option<u32>,
+    #[serde(default)]
+    pub properties: Vec<TiledProperty>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct TiledProperty {
+    pub name: String,
+    pub value: serde_yaml::Value,
+}
+
+// the top bits of a tile id record whether the tile is flipped
+const TILED_FLIP_MASK: u32 = 0x1FFF_FFFF;
+
+impl TiledMap {
+    /// Parse a Tiled JSON map. JSON is read as YAML, which it is a subset of.
+    pub fn from_str(map_string: &str) -> Result<TiledMap, String> {
+        return serde_yaml::from_str(map_string).map_err(|err| err.to_string());
+    }
+
+    pub fn from_file(file_name: &str) -> Result<TiledMap, String> {
+        let map_string = fs::read_to_string(file_name).map_err(|err| format!("Could not read {}: {}", file_name, err))?;
+        return TiledMap::from_str(&map_string).map_err(|err| format!("{}: {}", file_name, err));
+    }
+}
+
+/// Load a Tiled map into the game, returning the player's position, or (0, 0) if the
+/// map does not place the player.
+pub fn read_map_tiled(config: &Config,
+                      data: &mut GameData,
+                      msg_log: &mut MsgLog,
+                      file_name: &str) -> Result<(i32, i32), String> {
+    let tiled = TiledMap::from_file(file_name)?;
+
+    data.map = Map::from_dims(tiled.width, tiled.height);
+    let mut player_position = (0, 0);
+
+    let first_gid = tiled.tilesets.get(0).map_or(1, |tileset| tileset.firstgid);
+
+    for layer in tiled.layers.iter() {
+        if layer.typ == "tilelayer" {
+            let layer_index =
+                match layer.name.to_ascii_lowercase().as_str() {
+                    "ground" => MAP_LAYER_GROUND,
+                    "environment" => MAP_LAYER_ENVIRONMENT,
+                    "entities" => MAP_LAYER_ENTITIES,
+                    "links" => MAP_LAYER_LINKS,
+                    _ => return Err(format!("Tile layer '{}' is not one of ground, environment, entities, or links", layer.name)),
+                };
+
+            for (index, gid) in layer.data.iter().enumerate() {
+                let gid = gid & TILED_FLIP_MASK;
+                if gid < first_gid {
+                    continue;
+                }
+
+                let pos = Pos::new(index as i32 % tiled.width as i32, index as i32 / tiled.width as i32);
+                let chr = std::char::from_u32(gid - first_gid).ok_or(format!("Tile {} is not a character", gid))?;
+                read_map_cell(config, data, msg_log, layer_index, pos, chr, &mut player_position)?;
+            }
+        } else if layer.typ == "objectgroup" {
+            for object in layer.objects.iter() {
+                let name = EntityName::from_str(&object.name)?;
+                let (layer_index, chr) =
+                    entity_map_chr(name).ok_or(format!("Entity '{}' can not be placed in a map", object.name))?;
+
+                // tile objects are positioned by their bottom left corner rather than their top left
+                let y = if object.gid.is_some() { object.y - tiled.tileheight as f32 } else { object.y };
+                let pos = Pos::new((object.x / tiled.tilewidth as f32) as i32, (y / tiled.tileheight as f32) as i32);
+
+                read_map_cell(config, data, msg_log, layer_index, pos, chr as char, &mut player_position)?;
+
+                for property in object.properties.iter() {
+                    if property.name == "facing" {
+                        let facing = property.value.as_str().ok_or("The facing property should be a direction".to_string())?;
+                        let direction = Direction::from_str(facing)?;
+
+                        let placed = data.entities.ids.iter().rev().find(|id| {
+                            return data.entities.pos[*id] == pos && data.entities.name[*id] == name;
+                        });
+                        if let Some(entity_id) = placed.copied() {
+                            data.entities.direction.insert(entity_id, direction);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    return Ok(player_position);
+}
+
+#[test]
+fn test_read_map_tiled() {
+    let config = Config::from_file("../config.yaml");
+    let mut msg_log = MsgLog::new();
+
+    // tile ids are one more than the character they stand for, as the tileset starts at 1
+    let map_string = format!(r#"{{
+        "width": 3, "height": 2, "tilewidth": 16, "tileheight": 16,
+        "tilesets": [{{ "firstgid": 1, "source": "rexpaint16x16.tsx" }}],
+        "layers": [
+            {{ "name": "ground", "type": "tilelayer", "data": [0, {}, 0, 0, 0, {}] }},
+            {{ "name": "environment", "type": "tilelayer", "data": [{}, 0, 0, 0, 0, 0] }},
+            {{ "name": "entities", "type": "objectgroup", "objects": [
+                {{ "name": "player", "x": 16, "y": 16 }},
+                {{ "name": "gol", "x": 32, "y": 16, "properties": [{{ "name": "facing", "type": "string", "value": "left" }}] }},
+                {{ "name": "dagger", "gid": 1, "x": 0, "y": 32 }}
+            ]}}
+        ]
+    }}"#, MAP_WATER + 1, MAP_GRASS + 1, MAP_WALL + 1);
+
+    let file_name = std::env::temp_dir().join("test_read_map_tiled.json");
+    let file_name = file_name.to_str().unwrap();
+    fs::write(file_name, map_string).unwrap();
+
+    let mut data = GameData::empty(0, 0);
+    let player_pos = read_map_tiled(&config, &mut data, &mut msg_log, file_name).unwrap();
+
+    assert_eq!((1, 1), player_pos);
+    assert_eq!((3, 2), data.map.size());
+    assert_eq!(TileType::Water, data.map[(1, 0)].tile_type);
+    assert_eq!(Surface::Grass, data.map[(2, 1)].surface);
+    assert!(data.map[(0, 0)].block_move);
+
+    let gol_id = data.has_entity(Pos::new(2, 1)).unwrap();
+    assert_eq!(EntityName::Gol, data.entities.name[&gol_id]);
+    assert_eq!(Direction::Left, data.entities.direction[&gol_id]);
+    assert_eq!(Some(EntityName::Dagger), data.has_entity(Pos::new(0, 1)).map(|id| data.entities.name[&id]));
+
+    fs::write(file_name, r#"{ "width": 1, "height": 1, "tilewidth": 16, "tileheight": 16, "layers": [{ "name": "floor", "type": "tilelayer", "data": [0] }] }"#).unwrap();
+    assert!(read_map_tiled(&config, &mut data, &mut msg_log, file_name).is_err());
+}
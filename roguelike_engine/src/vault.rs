@@ -7,6 +7,10 @@ use roguelike_core::types::*;
 use roguelike_core::config::*;
 
 use crate::generation::*;
+use crate::error::{GameError, GameResult};
+
+#[cfg(test)]
+use proptest::prelude::*;
 
 
 #[derive(Copy, Clone, PartialOrd, PartialEq, Debug)]
@@ -17,6 +21,8 @@ pub enum VaultTag {
     NoMirror,
     NoReplace,
     Common,
+    Early,
+    Late,
 }
 
 impl FromStr for VaultTag {
@@ -39,6 +45,10 @@ impl FromStr for VaultTag {
             return Ok(VaultTag::NoReplace);
         } else if s == "common" {
             return Ok(VaultTag::Common);
+        } else if s == "early" {
+            return Ok(VaultTag::Early);
+        } else if s == "late" {
+            return Ok(VaultTag::Late);
         }
 
         return Err(format!("Could not decode vault tag '{}'", original_str));
@@ -61,12 +71,47 @@ impl Vault {
     pub fn empty() -> Vault {
         return Vault { data: GameData::empty(0, 0), tags: Vec::new() };
     }
+
+    /// The relative likelihood of choosing this vault. Untagged vaults are common.
+    pub fn weight(&self) -> f32 {
+        if self.tags.contains(&VaultTag::Rare) {
+            return VAULT_WEIGHT_RARE;
+        } else if self.tags.contains(&VaultTag::Medium) {
+            return VAULT_WEIGHT_MEDIUM;
+        } else {
+            return VAULT_WEIGHT_COMMON;
+        }
+    }
+
+    /// Whether the vault's depth tags allow it on the given level.
+    pub fn allowed_on_level(&self, level_num: usize) -> bool {
+        if self.tags.contains(&VaultTag::Early) && level_num >= VAULT_LATE_LEVEL {
+            return false;
+        }
+
+        if self.tags.contains(&VaultTag::Late) && level_num < VAULT_LATE_LEVEL {
+            return false;
+        }
+
+        return true;
+    }
 }
 
 
+/// The characters tile_from_ascii understands for a tile.
+const VAULT_TILE_CHARS: &str = " \t.;%#\"`+'=IpgoS*!&/^LHv";
+
+/// The characters understood for the wall to the left of a tile, and below a tile.
+const VAULT_LEFT_WALL_CHARS: &str = " |\u{c780}\u{2014}";
+const VAULT_BOTTOM_WALL_CHARS: &str = " _|\u{2014}";
+
 fn remove_commas(s: String) -> String {
     let s = s.chars().collect::<Vec<char>>();
     let mut new_s = String::new();
+    if s.len() == 0 {
+        return new_s;
+    }
+
     let mut index = 0;
     if s[0] == ',' {
         new_s.push(' ');
@@ -98,19 +143,30 @@ fn test_remove_commas() {
     assert_eq!("  9".to_string(), remove_commas(" , ,9".to_string()));
     assert_eq!("0 9".to_string(), remove_commas("0, ,9".to_string()));
     assert_eq!("% %".to_string(), remove_commas("%,,%".to_string()));
+    assert_eq!("".to_string(), remove_commas("".to_string()));
 }
 
 /// Read Vault file into Vault structure
-pub fn parse_vault(file_name: &str, config: &Config) -> Vault {
-    //eprintln!("{}", file_name);
-
+pub fn parse_vault(file_name: &str, config: &Config) -> Result<Vault, String> {
     let file_contents =
-        std::fs::read_to_string(file_name).expect(&format!("Could not read {}", file_name));
+        std::fs::read_to_string(file_name).map_err(|err| format!("Could not read {}: {}", file_name, err))?;
+
+    return parse_vault_str(&file_contents, config).map_err(|err| format!("{}: {}", file_name, err));
+}
 
+/// Read a vault from the contents of a vault file. Use lint_vault to find every problem
+/// in a file- this stops at the first.
+pub fn parse_vault_str(file_contents: &str, config: &Config) -> Result<Vault, String> {
     let mut lines = Vec::new();
     let mut tags: Vec<VaultTag> = Vec::new();
+    let mut links: Vec<(u8, Pos)> = Vec::new();
 
     for line in file_contents.lines() {
+        if line.starts_with("link") {
+            links.extend(parse_link_line(line)?);
+            continue;
+        }
+
         let cleaned_line = remove_commas(line.to_string());
 
         if cleaned_line.starts_with("::") {
@@ -118,7 +174,7 @@ pub fn parse_vault(file_name: &str, config: &Config) -> Vault {
                 if tag_str.starts_with("::") {
                     break;
                 }
-                tags.push(VaultTag::from_str(tag_str).unwrap());
+                tags.push(VaultTag::from_str(tag_str)?);
             }
             break;
         }
@@ -127,14 +183,163 @@ pub fn parse_vault(file_name: &str, config: &Config) -> Vault {
         lines.push(char_line);
     }
 
-    let vault = parse_ascii_chars(lines, config);
+    let mut vault = parse_ascii_chars(lines, config)?;
 
-    return vault;
+    for (link, pos) in links {
+        if !vault.data.map.is_within_bounds(pos) {
+            return Err(format!("link {} at {} is outside the vault", link, pos));
+        }
+        vault.data.map[pos].link = link;
+    }
+
+    return Ok(vault);
 }
 
-fn parse_ascii_chars(lines: Vec<Vec<char>>, config: &Config) -> Vault {
+/// Check a vault file for problems that parse_vault would fail on or skip over-
+/// malformed link lines, unknown tags, unknown characters, and rows too short for
+/// the grid. Each problem is given with the file name and line number it is on.
+pub fn lint_vault(file_name: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let file_contents =
+        match std::fs::read_to_string(file_name) {
+            Ok(contents) => contents,
+            Err(err) => return vec!(format!("{}: could not read file: {}", file_name, err)),
+        };
+
+    // grid lines along with their line numbers, counting from 1
+    let mut lines: Vec<(usize, Vec<char>)> = Vec::new();
+    for (index, line) in file_contents.lines().enumerate() {
+        let line_num = index + 1;
+
+        if line.starts_with("link") {
+            let values = line.split(|chr| chr == ',' || chr == ' ').filter(|value| value.len() > 0).skip(1).collect::<Vec<&str>>();
+            if values.iter().any(|value| value.parse::<i32>().is_err()) {
+                problems.push(format!("{}:{}: link line has a value that is not a number", file_name, line_num));
+            } else if values.len() < 3 || values.len() % 2 == 0 {
+                problems.push(format!("{}:{}: link line should be 'link id x y x y ...'", file_name, line_num));
+            }
+            continue;
+        }
+
+        if line.is_empty() {
+            problems.push(format!("{}:{}: empty line in grid", file_name, line_num));
+            continue;
+        }
+
+        let cleaned_line = remove_commas(line.to_string());
+        if cleaned_line.starts_with("::") {
+            for tag_str in cleaned_line.split_at(2).1.trim().split(" ") {
+                if tag_str.starts_with("::") {
+                    break;
+                }
+                if let Err(err) = VaultTag::from_str(tag_str) {
+                    problems.push(format!("{}:{}: {}", file_name, line_num, err));
+                }
+            }
+            break;
+        }
+
+        lines.push((line_num, cleaned_line.chars().collect()));
+    }
+
+    if lines.len() < 2 {
+        problems.push(format!("{}: no grid found", file_name));
+        return problems;
+    }
+
+    if lines.len() % 2 != 0 {
+        problems.push(format!("{}:{}: grid has an odd number of lines- each row of tiles needs a line for its bottom walls",
+                              file_name, lines[lines.len() - 1].0));
+    }
+
+    // the first line sets the width, and characters past the grid are ignored
+    let width = (lines[0].1.len() - 1) / 2;
+    for row in lines.chunks(2) {
+        let (tile_line_num, tile_line) = &row[0];
+        if tile_line.len() < width * 2 + 1 {
+            problems.push(format!("{}:{}: row is shorter than the first row", file_name, tile_line_num));
+            continue;
+        }
+
+        for x in 0..width {
+            let left_wall = tile_line[x * 2];
+            let tile_chr = tile_line[x * 2 + 1];
+
+            if !VAULT_LEFT_WALL_CHARS.contains(left_wall) {
+                problems.push(format!("{}:{}: unknown wall '{}' in column {}", file_name, tile_line_num, left_wall, x));
+            }
+            if !VAULT_TILE_CHARS.contains(tile_chr) {
+                problems.push(format!("{}:{}: unknown tile '{}' in column {}", file_name, tile_line_num, tile_chr, x));
+            }
+        }
+
+        if let Some((wall_line_num, wall_line)) = row.get(1) {
+            for x in 0..width {
+                match wall_line.get(x * 2 + 1) {
+                    Some(bottom_wall) if !VAULT_BOTTOM_WALL_CHARS.contains(*bottom_wall) => {
+                        problems.push(format!("{}:{}: unknown wall '{}' in column {}", file_name, wall_line_num, bottom_wall, x));
+                    }
+
+                    None => {
+                        problems.push(format!("{}:{}: row is shorter than the first row", file_name, wall_line_num));
+                        break;
+                    }
+
+                    _ => {
+                    }
+                }
+            }
+        }
+    }
+
+    return problems;
+}
+
+/// Lint every vault file in a directory, giving all of the problems found.
+pub fn lint_vaults(path: &str) -> Vec<String> {
+    let mut file_names = std::fs::read_dir(path).expect(&format!("Could not read {}", path))
+                                               .filter_map(|entry| entry.ok())
+                                               .map(|entry| entry.path().to_str().unwrap().to_string())
+                                               .filter(|file_name| file_name.ends_with(".csv"))
+                                               .collect::<Vec<String>>();
+    file_names.sort();
+
+    return file_names.iter().flat_map(|file_name| lint_vault(file_name)).collect();
+}
+
+/// Parse a line of the form 'link id x y x y ...', giving the tiles that share
+/// a mechanism link. The values may be separated by commas or spaces.
+fn parse_link_line(line: &str) -> Result<Vec<(u8, Pos)>, String> {
+    let values = line.split(|chr| chr == ',' || chr == ' ')
+                     .filter(|value| value.len() > 0)
+                     .skip(1)
+                     .map(|value| value.parse::<i32>().map_err(|_| format!("Could not parse link value '{}'", value)))
+                     .collect::<Result<Vec<i32>, String>>()?;
+
+    if values.len() < 3 || values.len() % 2 == 0 {
+        return Err(format!("link line should be 'link id x y x y ...', not '{}'", line));
+    }
+
+    let link = values[0] as u8;
+    return Ok(values[1..].chunks(2)
+                         .map(|pair| (link, Pos::new(pair[0], pair[1])))
+                         .collect::<Vec<(u8, Pos)>>());
+}
+
+fn parse_ascii_chars(lines: Vec<Vec<char>>, config: &Config) -> Result<Vault, String> {
     let height = lines.len() / 2;
-    let width = (lines[0].len() - 1) / 2;
+    let width = lines.first().map_or(0, |line| line.len().saturating_sub(1) / 2);
+    if width == 0 || height == 0 {
+        return Err("no grid found".to_string());
+    }
+
+    // the first line sets the width, and characters past the grid are ignored
+    for (index, line) in lines.iter().take(height * 2).enumerate() {
+        if line.len() < width * 2 {
+            return Err(format!("grid line {} is too short for the grid", index + 1));
+        }
+    }
 
     let tile_map = vec![vec![Tile::empty(); height]; width];
     let mut vault = Vault::new(tile_map, Vec::new());
@@ -144,15 +349,15 @@ fn parse_ascii_chars(lines: Vec<Vec<char>>, config: &Config) -> Vault {
             let tile_chr = lines[y * 2][x * 2 + 1];
             let left_wall = lines[y * 2][x * 2];
             let bottom_wall = lines[y * 2 + 1][x * 2 + 1];
-            let tile = tile_from_ascii(tile_chr, left_wall, bottom_wall, Pos::new(x as i32, y as i32), &mut vault, config);
+            let tile = tile_from_ascii(tile_chr, left_wall, bottom_wall, Pos::new(x as i32, y as i32), &mut vault.data.entities, config);
             vault.data.map[(x as i32, y as i32)] = tile;
         }
     }
 
-    return vault;
+    return Ok(vault);
 }
 
-fn tile_from_ascii(tile_chr: char, left_wall: char, bottom_wall: char, pos: Pos, vault: &mut Vault, config: &Config) -> Tile {
+fn tile_from_ascii(tile_chr: char, left_wall: char, bottom_wall: char, pos: Pos, entities: &mut Entities, config: &Config) -> Tile {
     let mut tile;
     match tile_chr {
         ' ' | '\t' | '.' => {
@@ -175,33 +380,100 @@ fn tile_from_ascii(tile_chr: char, left_wall: char, bottom_wall: char, pos: Pos,
             tile = Tile::grass();
         }
 
+        '+' => {
+            tile = Tile::door(DoorState::Closed);
+        }
+
+        '\'' => {
+            tile = Tile::door(DoorState::Open);
+        }
+
+        '=' => {
+            tile = Tile::door(DoorState::Locked);
+        }
+
         'I' => {
             tile = Tile::empty();
             let mut msg_log = MsgLog::new();
-            make_column(&mut vault.data.entities, config, pos, &mut msg_log);
+            make_column(entities, config, pos, &mut msg_log);
         }
 
         'p' => {
             tile = Tile::empty();
             let mut msg_log = MsgLog::new();
-            make_pawn(&mut vault.data.entities, config, pos, &mut msg_log);
+            make_pawn(entities, config, pos, &mut msg_log);
         }
 
         'g' => {
             tile = Tile::empty();
             let mut msg_log = MsgLog::new();
-            make_gol(&mut vault.data.entities, config, pos, &mut msg_log);
+            make_gol(entities, config, pos, &mut msg_log);
         }
 
         'o' => {
             tile = Tile::empty();
             let mut msg_log = MsgLog::new();
-            make_stone(&mut vault.data.entities, config, pos, &mut msg_log);
+            make_stone(entities, config, pos, &mut msg_log);
         }
 
         '*' => {
             tile = Tile::empty();
-            // TODO trap
+            let mut msg_log = MsgLog::new();
+            make_spike_trap(entities, config, pos, &mut msg_log);
+        }
+
+        '!' => {
+            tile = Tile::empty();
+            let mut msg_log = MsgLog::new();
+            make_tripwire(entities, config, pos, &mut msg_log);
+        }
+
+        '&' => {
+            tile = Tile::empty();
+            let mut msg_log = MsgLog::new();
+            make_net_trap(entities, config, pos, &mut msg_log);
+        }
+
+        '/' => {
+            tile = Tile::empty();
+            let mut msg_log = MsgLog::new();
+            make_blade_trap(entities, config, pos, &mut msg_log);
+        }
+
+        '^' => {
+            tile = Tile::empty();
+            let mut msg_log = MsgLog::new();
+            make_pressure_plate(entities, config, pos, &mut msg_log);
+        }
+
+        'L' => {
+            tile = Tile::empty();
+            let mut msg_log = MsgLog::new();
+            make_lever(entities, config, pos, &mut msg_log);
+        }
+
+        'H' => {
+            tile = Tile::empty();
+            let mut msg_log = MsgLog::new();
+            make_gate(entities, config, pos, &mut msg_log);
+        }
+
+        'C' => {
+            tile = Tile::empty();
+            let mut msg_log = MsgLog::new();
+            make_chest(entities, config, pos, &mut msg_log);
+        }
+
+        'c' => {
+            tile = Tile::empty();
+            let mut msg_log = MsgLog::new();
+            make_crate(entities, config, pos, &mut msg_log);
+        }
+
+        'A' => {
+            tile = Tile::empty();
+            let mut msg_log = MsgLog::new();
+            make_alarm(entities, config, pos, &mut msg_log);
         }
 
         'S' => {
@@ -212,7 +484,7 @@ fn tile_from_ascii(tile_chr: char, left_wall: char, bottom_wall: char, pos: Pos,
         'v' => {
             tile = Tile::empty();
             let mut msg_log = MsgLog::new();
-            make_dagger(&mut vault.data.entities, config, pos, &mut msg_log);
+            make_dagger(entities, config, pos, &mut msg_log);
         }
 
         _ => {
@@ -232,3 +504,399 @@ fn tile_from_ascii(tile_chr: char, left_wall: char, bottom_wall: char, pos: Pos,
     return tile;
 }
 
+
+/// Write a map out in the vault format read by parse_vault. Tiles are comma
+/// separated, as in the vault spreadsheets, and only the entities that
+/// tile_from_ascii understands are written. Tall walls are written as short walls, as
+/// vaults have no tall walls, so a map only round-trips in full through write_map_xp.
+pub fn write_vault(data: &GameData, file_name: &str) -> GameResult<()> {
+    let (width, height) = data.map.size();
+
+    let mut contents = String::new();
+    for y in 0..height {
+        let mut tile_line = Vec::new();
+        let mut wall_line = Vec::new();
+
+        for x in 0..width {
+            let pos = Pos::new(x, y);
+            let tile = data.map[pos];
+
+            if tile.left_wall == Wall::Empty {
+                tile_line.push(' ');
+            } else {
+                tile_line.push('|');
+            }
+            tile_line.push(ascii_from_tile(data, pos));
+
+            wall_line.push(' ');
+            if tile.bottom_wall == Wall::Empty {
+                wall_line.push(' ');
+            } else {
+                wall_line.push('_');
+            }
+        }
+        tile_line.push(' ');
+        wall_line.push(' ');
+
+        contents.push_str(&join_commas(&tile_line));
+        contents.push('\n');
+        contents.push_str(&join_commas(&wall_line));
+        contents.push('\n');
+    }
+
+    for link in 1..=data.map.max_link() {
+        let linked_positions = data.map.linked_positions(link);
+        if linked_positions.len() > 0 {
+            let mut link_line = vec!("link".to_string(), link.to_string());
+            for pos in linked_positions {
+                link_line.push(pos.x.to_string());
+                link_line.push(pos.y.to_string());
+            }
+            contents.push_str(&link_line.join(","));
+            contents.push('\n');
+        }
+    }
+
+    return std::fs::write(file_name, contents).map_err(|err| GameError::write(file_name, err));
+}
+
+/// Join characters with commas, leaving spaces as empty cells. This is the
+/// inverse of remove_commas.
+fn join_commas(chrs: &Vec<char>) -> String {
+    let cells = chrs.iter().map(|chr| if *chr == ' ' { "".to_string() } else { chr.to_string() });
+    return cells.collect::<Vec<String>>().join(",");
+}
+
+fn ascii_from_tile(data: &GameData, pos: Pos) -> char {
+    for entity_id in data.entities.ids.iter() {
+        if data.entities.pos[entity_id] == pos {
+            if let Some(chr) = ascii_from_entity(data.entities.name[entity_id]) {
+                return chr;
+            }
+        }
+    }
+
+    return ascii_from_map_tile(data.map[pos]);
+}
+
+fn ascii_from_entity(name: EntityName) -> Option<char> {
+    match name {
+        EntityName::Column => Some('I'),
+        EntityName::Pawn => Some('p'),
+        EntityName::Gol => Some('g'),
+        EntityName::Stone => Some('o'),
+        EntityName::Dagger => Some('v'),
+        EntityName::SpikeTrap => Some('*'),
+        EntityName::Tripwire => Some('!'),
+        EntityName::NetTrap => Some('&'),
+        EntityName::BladeTrap => Some('/'),
+        EntityName::PressurePlate => Some('^'),
+        EntityName::Lever => Some('L'),
+        EntityName::Gate => Some('H'),
+        EntityName::Chest => Some('C'),
+        EntityName::Crate => Some('c'),
+        EntityName::Alarm => Some('A'),
+        _ => None,
+    }
+}
+
+fn ascii_from_map_tile(tile: Tile) -> char {
+    if tile.tile_type == TileType::Wall {
+        return '#';
+    } else if tile.tile_type == TileType::Water {
+        return '%';
+    } else if tile.door_state() == Some(DoorState::Closed) {
+        return '+';
+    } else if tile.door_state() == Some(DoorState::Open) {
+        return '\'';
+    } else if tile.door_state() == Some(DoorState::Locked) {
+        return '=';
+    } else if tile.surface == Surface::Rubble {
+        return ';';
+    } else if tile.surface == Surface::Grass {
+        return '`';
+    }
+
+    return '.';
+}
+
+/// Load a map from text, such as a map written inline in a test. The text is a grid
+/// with one character per tile, using the same characters as vault files, followed by
+/// an optional blank line and a second grid of the same size giving the entities on
+/// each tile, where '@' is the player and '.' is nothing. Link lines, as in vault
+/// files, may follow. Walls between tiles can not be written in this format.
+///
+/// Returns the player's position, if the entity grid places the player. Malformed
+/// link lines, and links to tiles outside the map, are errors, and leave the map as it was.
+pub fn read_map_text(config: &Config, data: &mut GameData, map_string: &str) -> Result<Option<Pos>, String> {
+    let mut tile_lines = Vec::new();
+    let mut entity_lines = Vec::new();
+    let mut links = Vec::new();
+
+    let mut in_entities = false;
+    for line in map_string.lines() {
+        let line = line.trim();
+        if line.starts_with("link") {
+            links.extend(parse_link_line(line)?);
+        } else if line.is_empty() {
+            in_entities = tile_lines.len() > 0;
+        } else if in_entities {
+            entity_lines.push(line.chars().collect::<Vec<char>>());
+        } else {
+            tile_lines.push(line.chars().collect::<Vec<char>>());
+        }
+    }
+
+    let width = tile_lines.iter().map(|line| line.len()).max().unwrap_or(0);
+    let map = Map::from_dims(width as u32, tile_lines.len() as u32);
+    if let Some((link, pos)) = links.iter().find(|(_link, pos)| !map.is_within_bounds(*pos)) {
+        return Err(format!("link {} at {} is outside the map", link, pos));
+    }
+    data.map = map;
+
+    for (y, line) in tile_lines.iter().enumerate() {
+        for (x, chr) in line.iter().enumerate() {
+            let pos = Pos::new(x as i32, y as i32);
+            data.map[pos] = tile_from_ascii(*chr, ' ', ' ', pos, &mut data.entities, config);
+        }
+    }
+
+    let mut player_pos = None;
+    for (y, line) in entity_lines.iter().enumerate() {
+        for (x, chr) in line.iter().enumerate() {
+            let pos = Pos::new(x as i32, y as i32);
+            if *chr == '@' {
+                player_pos = Some(pos);
+            } else if *chr != '.' {
+                // only the entity is kept, leaving the tile from the tile grid
+                tile_from_ascii(*chr, ' ', ' ', pos, &mut data.entities, config);
+            }
+        }
+    }
+
+    for (link, pos) in links {
+        data.map[pos].link = link;
+    }
+
+    return Ok(player_pos);
+}
+
+/// Write a map in the text format read by read_map_text.
+pub fn write_map_text(data: &GameData) -> String {
+    let (width, height) = data.map.size();
+
+    let mut tile_lines = Vec::new();
+    let mut entity_lines = Vec::new();
+    for y in 0..height {
+        let mut tile_line = String::new();
+        let mut entity_line = String::new();
+
+        for x in 0..width {
+            let pos = Pos::new(x, y);
+            tile_line.push(ascii_from_map_tile(data.map[pos]));
+
+            let entity_chr =
+                data.entities.ids.iter().filter(|entity_id| data.entities.pos[*entity_id] == pos).filter_map(|entity_id| {
+                    if data.entities.typ[entity_id] == EntityType::Player {
+                        return Some('@');
+                    }
+                    return ascii_from_entity(data.entities.name[entity_id]);
+                }).next();
+            entity_line.push(entity_chr.unwrap_or('.'));
+        }
+
+        tile_lines.push(tile_line);
+        entity_lines.push(entity_line);
+    }
+
+    let mut contents = tile_lines.join("\n");
+    contents.push_str("\n\n");
+    contents.push_str(&entity_lines.join("\n"));
+    contents.push('\n');
+
+    for link in 1..=data.map.max_link() {
+        let linked_positions = data.map.linked_positions(link);
+        if linked_positions.len() > 0 {
+            let positions = linked_positions.iter().map(|pos| format!("{} {}", pos.x, pos.y)).collect::<Vec<String>>();
+            contents.push_str(&format!("link {} {}\n", link, positions.join(" ")));
+        }
+    }
+
+    return contents;
+}
+
+#[test]
+fn test_write_vault() {
+    let config = Config::from_file("../config.yaml");
+    let mut msg_log = MsgLog::new();
+
+    let mut data = GameData::new(Map::from_dims(5, 4), Entities::new());
+    data.map[(0, 0)] = Tile::wall();
+    data.map[(1, 0)] = Tile::water();
+    data.map[(2, 0)] = Tile::grass();
+    data.map[(3, 0)] = Tile::rubble();
+    data.map[(4, 0)] = Tile::door(DoorState::Closed);
+    data.map[(4, 1)] = Tile::door(DoorState::Open);
+    data.map[(4, 2)] = Tile::door(DoorState::Locked);
+    data.map[(0, 2)].left_wall = Wall::ShortWall;
+    data.map[(4, 3)].bottom_wall = Wall::ShortWall;
+    make_gol(&mut data.entities, &config, Pos::new(2, 2), &mut msg_log);
+    make_column(&mut data.entities, &config, Pos::new(3, 3), &mut msg_log);
+    make_blade_trap(&mut data.entities, &config, Pos::new(1, 1), &mut msg_log);
+    make_lever(&mut data.entities, &config, Pos::new(0, 3), &mut msg_log);
+    data.map[(0, 3)].link = 1;
+    data.map[(4, 0)].link = 1;
+
+    let file_name = std::env::temp_dir().join("test_write_vault.csv");
+    let file_name = file_name.to_str().unwrap();
+    write_vault(&data, file_name).unwrap();
+
+    let vault = parse_vault(file_name, &config).unwrap();
+
+    assert_eq!(data.map.size(), vault.data.map.size());
+    for pos in data.map.get_all_pos() {
+        assert_eq!(data.map[pos].tile_type, vault.data.map[pos].tile_type);
+        assert_eq!(data.map[pos].surface, vault.data.map[pos].surface);
+        assert_eq!(data.map[pos].left_wall, vault.data.map[pos].left_wall);
+        assert_eq!(data.map[pos].bottom_wall, vault.data.map[pos].bottom_wall);
+        assert_eq!(data.map[pos].link, vault.data.map[pos].link);
+    }
+
+    let gol_id = vault.data.has_entity(Pos::new(2, 2)).unwrap();
+    assert_eq!(EntityName::Gol, vault.data.entities.name[&gol_id]);
+    let column_id = vault.data.has_entity(Pos::new(3, 3)).unwrap();
+    assert_eq!(EntityName::Column, vault.data.entities.name[&column_id]);
+    let trap_id = vault.data.has_entity(Pos::new(1, 1)).unwrap();
+    assert_eq!(EntityName::BladeTrap, vault.data.entities.name[&trap_id]);
+}
+
+#[test]
+fn test_read_map_text() {
+    let config = Config::from_file("../config.yaml");
+    let mut msg_log = MsgLog::new();
+
+    let mut data = GameData::new(Map::from_dims(1, 1), Entities::new());
+    let player_id = make_player(&mut data.entities, &config, &mut msg_log);
+
+    let player_pos = read_map_text(&config, &mut data, "
+        #####
+        #.%;#
+        #`+.#
+        #####
+
+        .....
+        .@g..
+        ...^.
+        .....
+        link 1 3 2 2 2
+    ").unwrap();
+
+    assert_eq!(Some(Pos::new(1, 1)), player_pos);
+    assert_eq!((5, 4), data.map.size());
+    assert_eq!(TileType::Wall, data.map[(0, 0)].tile_type);
+    assert_eq!(TileType::Water, data.map[(2, 1)].tile_type);
+    assert_eq!(Surface::Rubble, data.map[(3, 1)].surface);
+    assert_eq!(Surface::Grass, data.map[(1, 2)].surface);
+    assert_eq!(Some(DoorState::Closed), data.map[(2, 2)].door_state());
+    assert_eq!(vec!(Pos::new(2, 2), Pos::new(3, 2)), data.map.linked_positions(1));
+
+    let gol_id = data.has_entity(Pos::new(2, 1)).unwrap();
+    assert_eq!(EntityName::Gol, data.entities.name[&gol_id]);
+    assert_eq!(Some(EntityName::PressurePlate), data.has_entity(Pos::new(3, 2)).map(|id| data.entities.name[&id]));
+
+    // writing the map out and reading it back gives the same text
+    data.entities.pos[&player_id] = player_pos.unwrap();
+    let text = write_map_text(&data);
+
+    let mut read_data = GameData::new(Map::from_dims(1, 1), Entities::new());
+    assert_eq!(Ok(player_pos), read_map_text(&config, &mut read_data, &text));
+    make_player(&mut read_data.entities, &config, &mut msg_log);
+    let read_player_id = read_data.find_by_name(EntityName::Player).unwrap();
+    read_data.entities.pos[&read_player_id] = player_pos.unwrap();
+    assert_eq!(text, write_map_text(&read_data));
+}
+
+#[test]
+fn test_lint_vault() {
+    let file_name = std::env::temp_dir().join("test_lint_vault.csv");
+    let file_name = file_name.to_str().unwrap();
+
+    std::fs::write(file_name, ",.,,#,\n,,,_,\n,.,|,g,\n,,,,\nlink,1,0,0,1,1\n:: rare ::\n").unwrap();
+    assert_eq!(Vec::<String>::new(), lint_vault(file_name));
+
+    std::fs::write(file_name, ",.,,#,\n,,,_,\n,X,|,\n,,,,\nlink,1,0,a\n:: rare sometimes ::\n").unwrap();
+    let problems = lint_vault(file_name);
+    assert!(problems.contains(&format!("{}:3: row is shorter than the first row", file_name)));
+    assert!(problems.contains(&format!("{}:5: link line has a value that is not a number", file_name)));
+    assert!(problems.iter().any(|problem| problem.starts_with(&format!("{}:6: ", file_name)) && problem.contains("sometimes")));
+
+    std::fs::write(file_name, ",.,,#,\n,,,_,\n,X,|,g,\n").unwrap();
+    let problems = lint_vault(file_name);
+    assert!(problems.contains(&format!("{}:3: unknown tile 'X' in column 0", file_name)));
+    assert!(problems.iter().any(|problem| problem.contains("odd number of lines")));
+}
+
+#[test]
+fn test_parse_vault_errors() {
+    let config = Config::from_file("../config.yaml");
+
+    assert!(parse_vault_str(",.,,#,\n,,,_,\nlink,1,0,0,1,0\n:: rare ::\n", &config).is_ok());
+
+    assert_eq!(Err("no grid found".to_string()), parse_vault_str("", &config).map(|_| ()));
+    assert!(parse_vault_str(",.,,#,\n,,,_,\nlink,1,5,5\n", &config).is_err());
+    assert!(parse_vault_str(",.,,#,\n,,,_,\nlink,1,0\n", &config).is_err());
+    assert!(parse_vault_str(",.,,#,\n,,,_,\n:: sometimes ::\n", &config).is_err());
+    assert!(parse_vault_str(",.,,#,\n\n,.,,#,\n,,,_,\n", &config).is_err());
+
+    let file_name = std::env::temp_dir().join("test_parse_vault_missing.csv");
+    assert!(parse_vault(file_name.to_str().unwrap(), &config).is_err());
+}
+
+#[test]
+fn test_read_map_text_errors() {
+    let config = Config::from_file("../config.yaml");
+    let mut data = GameData::new(Map::from_dims(1, 1), Entities::new());
+
+    assert!(read_map_text(&config, &mut data, "#.#\n#.#\nlink 1 1 0 1 1\n").is_ok());
+    assert_eq!((3, 2), data.map.size());
+
+    assert!(read_map_text(&config, &mut data, "#.#\n#.#\nlink 1 a b\n").is_err());
+    assert!(read_map_text(&config, &mut data, "#.#\n#.#\nlink 1 0\n").is_err());
+    assert!(read_map_text(&config, &mut data, "#.#\n#.#\nlink 1 5 5\n").is_err());
+    assert!(read_map_text(&config, &mut data, "#.#\n#.#\nlink 1 -1 0\n").is_err());
+
+    // a map that fails to read leaves the last map in place
+    assert_eq!((3, 2), data.map.size());
+}
+
+/// Lines of a vault file- grid lines made of the characters vaults use, link lines, and
+/// tag lines, all with mistakes in them, along with anything at all.
+#[cfg(test)]
+fn vault_line() -> impl Strategy<Value = String> {
+    return prop_oneof![
+        "[ ,.;%#`+'=IpgoS*!&/^LHvCcA|_X]{0,24}",
+        "link[, ][-0-9a, ]{0,16}",
+        ":: ?(rare|medium|norot|late|bogus| ){0,4}(::)?",
+        ".{0,24}",
+    ];
+}
+
+#[cfg(test)]
+proptest! {
+    #[test]
+    fn fuzz_parse_vault(lines in prop::collection::vec(vault_line(), 0..16)) {
+        let config = Config::from_file("../config.yaml");
+
+        // malformed vaults are errors rather than panics
+        let _ = parse_vault_str(&lines.join("\n"), &config);
+    }
+
+    #[test]
+    fn fuzz_read_map_text(lines in prop::collection::vec(vault_line(), 0..16)) {
+        let config = Config::from_file("../config.yaml");
+        let mut data = GameData::new(Map::from_dims(1, 1), Entities::new());
+
+        // malformed maps are errors rather than panics
+        let _ = read_map_text(&config, &mut data, &lines.join("\n"));
+    }
+}
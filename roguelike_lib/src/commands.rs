@@ -9,6 +9,8 @@ use roguelike_core::messaging::*;
 use roguelike_engine::input::*;
 use roguelike_engine::game::*;
 use roguelike_engine::generation::*;
+use roguelike_engine::step::remove_entity;
+use roguelike_engine::make_map::write_map_xp;
 
 
 // TODO
@@ -52,6 +54,7 @@ pub enum GameCmd {
     Give(Item),
     ListEntities,
     ListEntitiesPos(i32, i32),
+    SaveMap(String),
     Key(char, KeyDir),
     Ctrl(KeyDir),
     Alt(KeyDir),
@@ -158,6 +161,10 @@ impl FromStr for GameCmd {
         } else if cmd == "shift" {
             let dir = args[1].parse::<KeyDir>().unwrap();
             return Ok(GameCmd::Shift(dir));
+        } else if cmd == "save_map" {
+            // the file name keeps its case
+            let file_name = string.split(" ").nth(1).ok_or("OUTPUT: error 'save_map' needs a file name!".to_string())?;
+            return Ok(GameCmd::SaveMap(file_name.to_string()));
         } else if cmd == "exit" {
             return Ok(GameCmd::Exit);
         }
@@ -214,6 +221,8 @@ impl GameCmd {
             return "alt";
         } else if matches!(self, GameCmd::Shift(_)) {
             return "shift";
+        } else if matches!(self, GameCmd::SaveMap(_)) {
+            return "save_map";
         } else if matches!(self, GameCmd::Exit) {
             return "exit";
         } else {
@@ -286,6 +295,13 @@ pub fn execute_game_command(command: &GameCmd, game: &mut Game) -> String {
             return "".to_string();
         }
 
+        GameCmd::SaveMap(file_name) => {
+            if let Err(err) = write_map_xp(&game.data, file_name) {
+                return format!("{} error {}", name, err);
+            }
+            return format!("{} {}", name, file_name);
+        }
+
         GameCmd::Exit => {
             game.settings.running = false;
             return "".to_string();
@@ -302,14 +318,13 @@ pub fn execute_game_command(command: &GameCmd, game: &mut Game) -> String {
         }
 
         GameCmd::Remove(id) => {
-            game.data.entities.remove_entity(*id);
-            game.msg_log.log(Msg::RemovedEntity(*id));
+            remove_entity(*id, &mut game.data, &mut game.settings, &mut game.msg_log);
             return "".to_string();
         }
 
         GameCmd::Kill(id) => {
             let player_id = game.data.find_by_name(EntityName::Player).unwrap();
-            game.msg_log.log(Msg::Killed(player_id, *id, 1000));
+            game.msg_log.log(Msg::Killed(Some(player_id), *id, 1000));
             return "".to_string();
         }
 
@@ -45,7 +45,7 @@ pub extern "C" fn create_game(seed: u64, config_name: *mut i8, map_name: *mut i8
         let map_str = map_cstr.to_str().unwrap();
         trace!("loading map {}", map_str);
 
-        let pos = read_map_xp(&game.config, &mut game.data, &mut game.msg_log, map_str);
+        let pos = read_map_xp(&game.config, &mut game.data, &mut game.msg_log, map_str).unwrap_or_else(|err| panic!("{}", err));
 
         let player_id = game.data.find_by_name(EntityName::Player).unwrap();
         game.data.entities.pos[&player_id] = Pos::from(pos);
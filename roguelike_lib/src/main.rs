@@ -5,23 +5,24 @@ use std::sync::mpsc::{self, Receiver};
 use std::thread;
 use std::time::Duration;
 
-use roguelike_core::config::*;
 use roguelike_engine::game::*;
 use roguelike_engine::make_map::*;
 use roguelike_engine::log::*;
+use roguelike_engine::config_watcher::*;
 
 use crate::commands::*;
 
 
-const CONFIG_NAME: &str = "config.yaml";
-
 pub fn main() {
-    let config = Config::from_file(CONFIG_NAME);
+    let mut config_watcher = ConfigWatcher::new(CONFIG_NAME);
+    let config = config_watcher.load();
     let seed = 1;
     let mut game = Game::new(seed, config.clone());
-    game.load_vaults("resources/vaults/");
-
-    make_map(&config.map_load, &mut game);
+    let loaded = game.load_vaults("resources/vaults/").and_then(|_| make_map(&config.map_load, &mut game));
+    if let Err(err) = loaded {
+        eprintln!("{}", err);
+        return;
+    }
 
     let io_recv = spawn_input_reader();
 
@@ -41,6 +42,10 @@ pub fn main() {
         while let Some(msg) = game.msg_log.pop() {
             log.log_msg(&format!("{}", msg));
         }
+
+        if let Some(ConfigChange::Invalid(error)) = config_watcher.check(&mut game) {
+            log.log_output(&format!("Config not reloaded- {}", error));
+        }
     }
 }
 
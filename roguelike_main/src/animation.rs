@@ -13,6 +13,16 @@ pub type SpriteIndex = f32;
 pub enum Effect {
     Sound(Aoe, f32), // area of effect, time since start
     Beam(usize, Pos, Pos), // start, end
+    Debris(usize, Pos), // frames remaining, position of the destroyed wall
+    StealthKill(usize, Pos), // frames remaining, position of the victim
+}
+
+impl Effect {
+    /// Attacks and destroyed walls are shown with effects that input can wait on. Sound
+    /// effects are played for most moves, and are not waited on.
+    pub fn blocks_input(&self) -> bool {
+        return !matches!(self, Effect::Sound(_, _));
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -113,6 +123,26 @@ pub enum Animation {
 }
 
 impl Animation {
+    /// Whether the animation shows something that happened in the last turn, and so
+    /// input can be held until it finishes. Looping animations never finish.
+    pub fn blocks_input(&self) -> bool {
+        return matches!(self, Animation::Once(_) | Animation::Between(_, _, _, _, _));
+    }
+
+    /// A short description of the animation, for the entity inspector.
+    pub fn description(&self) -> String {
+        match self {
+            Animation::Loop(sprite_anim) => return format!("loop {}", sprite_anim.name),
+            Animation::Between(sprite_anim, start, end, _, _) =>
+                return format!("{} ({}, {}) to ({}, {})", sprite_anim.name, start.x, start.y, end.x, end.y),
+            Animation::Once(sprite_anim) => return format!("once {}", sprite_anim.name),
+            Animation::PlayEffect(Effect::Sound(_, _)) => return "sound effect".to_string(),
+            Animation::PlayEffect(Effect::Beam(_, _, _)) => return "beam effect".to_string(),
+            Animation::PlayEffect(Effect::Debris(_, _)) => return "debris effect".to_string(),
+            Animation::PlayEffect(Effect::StealthKill(_, _)) => return "stealth kill effect".to_string(),
+        }
+    }
+
     pub fn sprite_anim_mut(&mut self) -> Option<&mut SpriteAnim> {
         match self {
             Animation::Loop(sprite_anim) => return Some(sprite_anim),
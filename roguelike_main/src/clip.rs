@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+use std::fs::File;
+
+use roguelike_core::config::Config;
+
+
+/// The last few seconds of frames drawn, shrunk down, so they can be saved as a GIF
+/// when something worth sharing happens. Frames are RGB pixels, three bytes each.
+#[derive(Clone, Debug)]
+pub struct ClipRecorder {
+    pub frames: VecDeque<Vec<u8>>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ClipRecorder {
+    pub fn new() -> ClipRecorder {
+        return ClipRecorder { frames: VecDeque::new(), width: 0, height: 0 };
+    }
+
+    /// The number of frames kept, or 0 if clips are turned off.
+    pub fn max_frames(config: &Config) -> usize {
+        return (config.clip_seconds * config.frame_rate as f32) as usize;
+    }
+
+    /// Shrink a frame to the clip width and add it, dropping the oldest frame once there
+    /// are enough to fill the clip.
+    pub fn record(&mut self, pixels: &[u8], width: u32, height: u32, config: &Config) {
+        let max_frames = ClipRecorder::max_frames(config);
+        if max_frames == 0 || width == 0 || height == 0 {
+            self.frames.clear();
+            return;
+        }
+
+        let clip_width = std::cmp::min(config.clip_width, width);
+        let clip_height = std::cmp::max(1, height * clip_width / width);
+
+        // frames of different sizes can't go in the same clip
+        if clip_width != self.width || clip_height != self.height {
+            self.frames.clear();
+            self.width = clip_width;
+            self.height = clip_height;
+        }
+
+        let mut frame = Vec::with_capacity((clip_width * clip_height * 3) as usize);
+        for y in 0..clip_height {
+            for x in 0..clip_width {
+                let index = ((x * width / clip_width) + (y * height / clip_height) * width) as usize * 3;
+                frame.extend_from_slice(&pixels[index..(index + 3)]);
+            }
+        }
+        self.frames.push_back(frame);
+
+        while self.frames.len() > max_frames {
+            self.frames.pop_front();
+        }
+    }
+
+    /// Write the frames kept as a looping GIF. This can take a while, so it is best
+    /// done on a copy of the recorder, away from the game's thread.
+    pub fn save_gif(&self, file_name: &str, frame_rate: usize) -> Result<(), String> {
+        if let Some(dir) = std::path::Path::new(file_name).parent() {
+            std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+
+        let (width, height) = (self.width as u16, self.height as u16);
+
+        // GIF frame delays are in hundredths of a second
+        let delay = (100 / std::cmp::max(1, frame_rate)) as u16;
+
+        let file = File::create(file_name).map_err(|err| format!("Could not create {}: {}", file_name, err))?;
+        let mut encoder = gif::Encoder::new(file, width, height, &[]).map_err(|err| err.to_string())?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(|err| err.to_string())?;
+
+        for pixels in self.frames.iter() {
+            let mut frame = gif::Frame::from_rgb_speed(width, height, pixels, 10);
+            frame.delay = delay;
+            encoder.write_frame(&frame).map_err(|err| err.to_string())?;
+        }
+
+        return Ok(());
+    }
+}
+
+#[test]
+pub fn test_clip_recorder() {
+    let mut config = Config::from_file("../config.yaml");
+    config.frame_rate = 10;
+    config.clip_seconds = 0.5;
+    config.clip_width = 2;
+
+    // a 4x2 frame with a different color in each column
+    let pixels: Vec<u8> = (0..2).flat_map(|_y| (0..4u8).flat_map(|x| vec!(x, x, x))).collect();
+
+    let mut recorder = ClipRecorder::new();
+    for _ in 0..8 {
+        recorder.record(&pixels, 4, 2, &config);
+    }
+
+    // only enough frames for the clip length are kept, at the clip's size
+    assert_eq!(5, recorder.frames.len());
+    assert_eq!((2, 1), (recorder.width, recorder.height));
+    assert_eq!(vec!(0, 0, 0, 2, 2, 2), recorder.frames[0]);
+
+    config.clip_seconds = 0.0;
+    recorder.record(&pixels, 4, 2, &config);
+    assert_eq!(0, recorder.frames.len());
+}
@@ -0,0 +1,432 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::{BlendMode, Texture};
+
+use egui::{Context, RawInput, Pos2, Rect, Color32, ClippedPrimitive, TextureId, ImageData, Modifiers, vec2};
+use egui::epaint::{Primitive, Vertex, ImageDelta};
+
+use roguelike_core::config::ConfigValue;
+
+use roguelike_engine::game::Game;
+use roguelike_engine::config_watcher::CONFIG_NAME;
+use roguelike_engine::inspector::inspect_entity;
+
+use crate::display::Display;
+
+
+/// How many of the random number generator's upcoming values are shown.
+const RNG_PEEK_COUNT: usize = 8;
+
+/// Pixels scrolled for each step of the mouse wheel.
+const SCROLL_SPEED: f32 = 24.0;
+
+/// Height of the scrolling list of entities, in pixels.
+const ENTITY_LIST_HEIGHT: f32 = 240.0;
+
+/// Height of the scrolling list of config settings, in pixels.
+const CONFIG_LIST_HEIGHT: f32 = 320.0;
+
+/// A texture egui has asked for, kept as pixels so its meshes can be drawn in software.
+struct DebugTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color32>,
+}
+
+impl DebugTexture {
+    fn sample(&self, u: f32, v: f32) -> Color32 {
+        let x = std::cmp::min((u * self.width as f32).max(0.0) as usize, self.width - 1);
+        let y = std::cmp::min((v * self.height as f32).max(0.0) as usize, self.height - 1);
+        return self.pixels[y * self.width + x];
+    }
+}
+
+/// Windows for tweaking the config, looking through the entities, and watching the random
+/// number generator while the game runs. F10 shows and hides them.
+///
+/// The SDL2 renderer can't draw arbitrary triangles, so egui's meshes are drawn into a
+/// buffer of pixels which is copied over the canvas once the game has been drawn.
+pub struct DebugUi {
+    pub visible: bool,
+    ctx: Context,
+    events: Vec<egui::Event>,
+    modifiers: Modifiers,
+    start: Instant,
+    textures: HashMap<TextureId, DebugTexture>,
+    pixels: Vec<Color32>,
+    target: Option<(Texture, u32, u32)>,
+    config_filter: String,
+    config_status: String, // the result of the last config change or save
+}
+
+impl DebugUi {
+    pub fn new() -> DebugUi {
+        return DebugUi { visible: false,
+                         ctx: Context::default(),
+                         events: Vec::new(),
+                         modifiers: Modifiers::default(),
+                         start: Instant::now(),
+                         textures: HashMap::new(),
+                         pixels: Vec::new(),
+                         target: None,
+                         config_filter: String::new(),
+                         config_status: String::new() };
+    }
+
+    /// Pass an input event to the debug windows. Returns true if the windows used it,
+    /// in which case the game should not see it.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        if let Event::KeyDown { keycode: Some(Keycode::F10), repeat: false, .. } = event {
+            self.visible = !self.visible;
+            return true;
+        }
+
+        if !self.visible {
+            return false;
+        }
+
+        match event {
+            Event::MouseMotion { x, y, .. } => {
+                self.events.push(egui::Event::PointerMoved(Pos2::new(*x as f32, *y as f32)));
+                return self.ctx.is_pointer_over_area();
+            }
+
+            Event::MouseButtonDown { mouse_btn, x, y, .. } | Event::MouseButtonUp { mouse_btn, x, y, .. } => {
+                if let Some(button) = egui_button(*mouse_btn) {
+                    let pressed = matches!(event, Event::MouseButtonDown { .. });
+                    self.events.push(egui::Event::PointerButton { pos: Pos2::new(*x as f32, *y as f32),
+                                                                  button,
+                                                                  pressed,
+                                                                  modifiers: self.modifiers });
+                }
+                return self.ctx.is_pointer_over_area();
+            }
+
+            Event::MouseWheel { x, y, .. } => {
+                self.events.push(egui::Event::Scroll(vec2(*x as f32 * SCROLL_SPEED, *y as f32 * SCROLL_SPEED)));
+                return self.ctx.is_pointer_over_area();
+            }
+
+            Event::TextInput { text, .. } => {
+                self.events.push(egui::Event::Text(text.clone()));
+                return self.ctx.wants_keyboard_input();
+            }
+
+            Event::KeyDown { keycode: Some(keycode), keymod, repeat, .. } |
+            Event::KeyUp { keycode: Some(keycode), keymod, repeat, .. } => {
+                self.modifiers = egui_modifiers(*keymod);
+                if let Some(key) = egui_key(*keycode) {
+                    let pressed = matches!(event, Event::KeyDown { .. });
+                    self.events.push(egui::Event::Key { key, pressed, repeat: *repeat, modifiers: self.modifiers });
+                }
+                return self.ctx.wants_keyboard_input();
+            }
+
+            _ => {
+                return false;
+            }
+        }
+    }
+
+    /// Run the debug windows for this frame and draw them over the canvas.
+    pub fn render(&mut self, display: &mut Display, game: &mut Game) -> Result<(), String> {
+        if !self.visible {
+            return Ok(());
+        }
+
+        let canvas = &mut display.targets.canvas_panel.target;
+        let (width, height) = canvas.output_size()?;
+
+        let raw_input = RawInput { screen_rect: Some(Rect::from_min_size(Pos2::ZERO, vec2(width as f32, height as f32))),
+                                   time: Some(self.start.elapsed().as_secs_f64()),
+                                   modifiers: self.modifiers,
+                                   events: std::mem::take(&mut self.events),
+                                   ..RawInput::default() };
+        let config_filter = &mut self.config_filter;
+        let config_status = &mut self.config_status;
+        let output = self.ctx.run(raw_input, |ctx| {
+            config_window(ctx, game, config_filter, config_status);
+            entity_window(ctx, game);
+            rng_window(ctx, game);
+        });
+
+        for (texture_id, delta) in output.textures_delta.set {
+            self.set_texture(texture_id, delta);
+        }
+
+        let primitives = self.ctx.tessellate(output.shapes);
+        self.paint(&primitives, width as usize, height as usize);
+
+        for texture_id in output.textures_delta.free {
+            self.textures.remove(&texture_id);
+        }
+
+        if self.target.as_ref().map_or(true, |(_texture, target_width, target_height)| (*target_width, *target_height) != (width, height)) {
+            let mut texture = canvas.texture_creator()
+                                    .create_texture_streaming(PixelFormatEnum::ABGR8888, width, height)
+                                    .map_err(|err| err.to_string())?;
+            texture.set_blend_mode(BlendMode::Blend);
+            self.target = Some((texture, width, height));
+        }
+
+        let (texture, _width, _height) = self.target.as_mut().unwrap();
+        let pixels = &self.pixels;
+        texture.with_lock(None, |buffer, pitch| {
+            for y in 0..height as usize {
+                for x in 0..width as usize {
+                    let offset = y * pitch + x * 4;
+                    buffer[offset..offset + 4].copy_from_slice(&pixels[y * width as usize + x].to_srgba_unmultiplied());
+                }
+            }
+        })?;
+        canvas.copy(texture, None, None)?;
+
+        return Ok(());
+    }
+
+    fn set_texture(&mut self, texture_id: TextureId, delta: ImageDelta) {
+        let (width, height, pixels) =
+            match &delta.image {
+                ImageData::Color(image) => (image.width(), image.height(), image.pixels.clone()),
+                ImageData::Font(image) => (image.width(), image.height(), image.srgba_pixels(None).collect::<Vec<Color32>>()),
+            };
+
+        // a delta with a position only updates part of a texture
+        if let Some([x_offset, y_offset]) = delta.pos {
+            if let Some(texture) = self.textures.get_mut(&texture_id) {
+                for y in 0..height {
+                    for x in 0..width {
+                        texture.pixels[(y_offset + y) * texture.width + x_offset + x] = pixels[y * width + x];
+                    }
+                }
+            }
+        } else {
+            self.textures.insert(texture_id, DebugTexture { width, height, pixels });
+        }
+    }
+
+    fn paint(&mut self, primitives: &[ClippedPrimitive], width: usize, height: usize) {
+        self.pixels.clear();
+        self.pixels.resize(width * height, Color32::TRANSPARENT);
+
+        let screen = Rect::from_min_size(Pos2::ZERO, vec2(width as f32, height as f32));
+        for primitive in primitives.iter() {
+            if let Primitive::Mesh(mesh) = &primitive.primitive {
+                if let Some(texture) = self.textures.get(&mesh.texture_id) {
+                    let clip = primitive.clip_rect.intersect(screen);
+                    for triangle in mesh.indices.chunks(3) {
+                        let vertices = [&mesh.vertices[triangle[0] as usize],
+                                        &mesh.vertices[triangle[1] as usize],
+                                        &mesh.vertices[triangle[2] as usize]];
+                        draw_triangle(&mut self.pixels, width, clip, texture, vertices);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Every number and true/false setting in the config, which take effect as soon as they
+/// are changed. The filter narrows the list down by name, and the changes can be saved
+/// back to the config file.
+fn config_window(ctx: &Context, game: &mut Game, filter: &mut String, status: &mut String) {
+    egui::Window::new("Config").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("filter");
+            ui.text_edit_singleline(filter);
+        });
+
+        egui::ScrollArea::vertical().max_height(CONFIG_LIST_HEIGHT).show(ui, |ui| {
+            for (name, mut value) in game.config.fields() {
+                if !name.contains(filter.as_str()) {
+                    continue;
+                }
+
+                let changed =
+                    ui.horizontal(|ui| {
+                        let response =
+                            match &mut value {
+                                ConfigValue::Bool(value) => ui.checkbox(value, ""),
+                                ConfigValue::Int(value) => ui.add(egui::DragValue::new(value)),
+                                ConfigValue::Float(value) => ui.add(egui::DragValue::new(value).speed(0.1)),
+                            };
+                        ui.label(&name);
+                        return response.changed();
+                    }).inner;
+
+                if changed {
+                    *status = game.config.set_field(&name, value).err().unwrap_or_default();
+                }
+            }
+        });
+
+        ui.separator();
+        if ui.button("save to config file").clicked() {
+            *status =
+                match game.save_config(CONFIG_NAME) {
+                    Ok(()) => format!("saved {}", CONFIG_NAME),
+                    Err(error) => error,
+                };
+        }
+        ui.label(status.as_str());
+    });
+}
+
+/// Every entity on the level, with the chosen one's components listed below them.
+/// The chosen entity is shared with the inspector in the info panel.
+fn entity_window(ctx: &Context, game: &mut Game) {
+    egui::Window::new("Entities").show(ctx, |ui| {
+        egui::ScrollArea::vertical().max_height(ENTITY_LIST_HEIGHT).show(ui, |ui| {
+            for entity_id in game.data.entities.ids.iter() {
+                let pos = game.data.entities.pos[entity_id];
+                let text = format!("{} {} ({}, {})", entity_id, game.data.entities.name[entity_id], pos.x, pos.y);
+                if ui.selectable_label(game.settings.inspecting == Some(*entity_id), text).clicked() {
+                    game.settings.inspecting = Some(*entity_id);
+                }
+            }
+        });
+
+        if let Some(entity_id) = game.settings.inspecting {
+            ui.separator();
+            for line in inspect_entity(entity_id, &game.data) {
+                ui.label(line);
+            }
+        }
+    });
+}
+
+/// The game's random number generator, and the values it will give next. The values
+/// come from a copy, so looking at them doesn't change what happens in the game.
+fn rng_window(ctx: &Context, game: &mut Game) {
+    egui::Window::new("Rng").show(ctx, |ui| {
+        let (state, increment) = game.rng.state();
+        ui.label(format!("seed {}", game.settings.seed));
+        ui.label(format!("state {:016x}", state));
+        ui.label(format!("increment {:016x}", increment));
+
+        ui.separator();
+        let mut rng = game.rng;
+        for _ in 0..RNG_PEEK_COUNT {
+            ui.label(format!("{}", rng.rand_u32()));
+        }
+    });
+}
+
+/// Draw one of egui's triangles, blending it over the pixels already drawn. A pixel is
+/// drawn when its center is within both the triangle and the clip rectangle.
+fn draw_triangle(pixels: &mut Vec<Color32>, width: usize, clip: Rect, texture: &DebugTexture, vertices: [&Vertex; 3]) {
+    let [a, b, c] = vertices;
+
+    let area = edge(a.pos, b.pos, c.pos);
+    if area == 0.0 || clip.is_negative() {
+        return;
+    }
+
+    let min_x = a.pos.x.min(b.pos.x).min(c.pos.x).max(clip.min.x).floor() as usize;
+    let max_x = a.pos.x.max(b.pos.x).max(c.pos.x).min(clip.max.x).ceil() as usize;
+    let min_y = a.pos.y.min(b.pos.y).min(c.pos.y).max(clip.min.y).floor() as usize;
+    let max_y = a.pos.y.max(b.pos.y).max(c.pos.y).min(clip.max.y).ceil() as usize;
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let point = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+
+            // dividing by the area gives the same weights for either winding
+            let weight_a = edge(b.pos, c.pos, point) / area;
+            let weight_b = edge(c.pos, a.pos, point) / area;
+            let weight_c = edge(a.pos, b.pos, point) / area;
+            if weight_a < 0.0 || weight_b < 0.0 || weight_c < 0.0 {
+                continue;
+            }
+
+            let u = weight_a * a.uv.x + weight_b * b.uv.x + weight_c * c.uv.x;
+            let v = weight_a * a.uv.y + weight_b * b.uv.y + weight_c * c.uv.y;
+            let texel = texture.sample(u, v);
+
+            let mut color = [0u8; 4];
+            for channel in 0..4 {
+                let vertex_color = weight_a * a.color[channel] as f32 +
+                                   weight_b * b.color[channel] as f32 +
+                                   weight_c * c.color[channel] as f32;
+                color[channel] = (vertex_color * texel[channel] as f32 / 255.0).round() as u8;
+            }
+
+            // egui's colors are premultiplied, so blending is just adding what shows through
+            let dst = pixels[y * width + x];
+            let remaining = 255 - color[3] as u32;
+            let mut blended = [0u8; 4];
+            for channel in 0..4 {
+                blended[channel] = (color[channel] as u32 + dst[channel] as u32 * remaining / 255) as u8;
+            }
+            pixels[y * width + x] = Color32::from_rgba_premultiplied(blended[0], blended[1], blended[2], blended[3]);
+        }
+    }
+}
+
+/// Twice the signed area of the triangle a, b, p.
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    return (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+}
+
+fn egui_button(button: MouseButton) -> Option<egui::PointerButton> {
+    match button {
+        MouseButton::Left => return Some(egui::PointerButton::Primary),
+        MouseButton::Right => return Some(egui::PointerButton::Secondary),
+        MouseButton::Middle => return Some(egui::PointerButton::Middle),
+        _ => return None,
+    }
+}
+
+fn egui_modifiers(keymod: Mod) -> Modifiers {
+    let ctrl = keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+    return Modifiers { alt: keymod.intersects(Mod::LALTMOD | Mod::RALTMOD),
+                       ctrl,
+                       shift: keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+                       mac_cmd: false,
+                       command: ctrl };
+}
+
+/// The keys used for editing text and moving around in the debug windows.
+fn egui_key(keycode: Keycode) -> Option<egui::Key> {
+    match keycode {
+        Keycode::Left => return Some(egui::Key::ArrowLeft),
+        Keycode::Right => return Some(egui::Key::ArrowRight),
+        Keycode::Up => return Some(egui::Key::ArrowUp),
+        Keycode::Down => return Some(egui::Key::ArrowDown),
+        Keycode::Backspace => return Some(egui::Key::Backspace),
+        Keycode::Delete => return Some(egui::Key::Delete),
+        Keycode::Home => return Some(egui::Key::Home),
+        Keycode::End => return Some(egui::Key::End),
+        Keycode::Tab => return Some(egui::Key::Tab),
+        Keycode::Return | Keycode::KpEnter => return Some(egui::Key::Enter),
+        Keycode::Escape => return Some(egui::Key::Escape),
+        _ => return None,
+    }
+}
+
+#[test]
+pub fn test_draw_triangle() {
+    let texture = DebugTexture { width: 1, height: 1, pixels: vec!(Color32::WHITE) };
+    let vertex = |x: f32, y: f32| Vertex { pos: Pos2::new(x, y), uv: Pos2::ZERO, color: Color32::RED };
+
+    // a triangle covering the top left half of a 4x4 image, clipped to its left 3 columns
+    let mut pixels = vec![Color32::TRANSPARENT; 16];
+    let clip = Rect::from_min_max(Pos2::ZERO, Pos2::new(3.0, 4.0));
+    draw_triangle(&mut pixels, 4, clip, &texture, [&vertex(0.0, 0.0), &vertex(4.0, 0.0), &vertex(0.0, 4.0)]);
+
+    assert_eq!(Color32::RED, pixels[0]);
+    assert_eq!(Color32::RED, pixels[2]);
+    assert_eq!(Color32::TRANSPARENT, pixels[3]);
+    assert_eq!(Color32::RED, pixels[3 * 4]);
+    assert_eq!(Color32::TRANSPARENT, pixels[3 * 4 + 3]);
+
+    // the winding of the triangle doesn't matter
+    let mut reversed = vec![Color32::TRANSPARENT; 16];
+    draw_triangle(&mut reversed, 4, clip, &texture, [&vertex(0.0, 4.0), &vertex(4.0, 0.0), &vertex(0.0, 0.0)]);
+    assert_eq!(pixels, reversed);
+}
@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 
 use bmp::Image;
 
@@ -6,6 +6,8 @@ use sdl2::render::{Texture, WindowCanvas, TextureCreator, BlendMode};
 use sdl2::video::WindowContext;
 use sdl2::rect::{Rect};
 use sdl2::pixels::{PixelFormatEnum, Color as Sdl2Color};
+use sdl2::surface::Surface;
+use sdl2::image::SaveSurface;
 
 use indexmap::map::IndexMap;
 
@@ -59,6 +61,38 @@ impl Display {
         shot.save(format!("{}.bmp", name)).unwrap();
     }
 
+    /// Save the frame drawn on the canvas as a PNG. This has to be done before the
+    /// frame is presented, as the canvas's contents are not kept after that.
+    pub fn save_screenshot_png(&mut self, file_name: &str) -> Result<(), String> {
+        let (mut pixels, width, height) = self.read_canvas()?;
+        return save_png(&mut pixels, width, height, file_name);
+    }
+
+    /// The RGB pixels of the frame drawn on the canvas, along with its width and height.
+    /// Like screenshots, this has to be done before the frame is presented.
+    pub fn read_canvas(&mut self) -> Result<(Vec<u8>, u32, u32), String> {
+        let (width, height) = self.targets.canvas_panel.target.output_size()?;
+        let pixels = self.targets.canvas_panel.target.read_pixels(None, PixelFormatEnum::RGB24)?;
+        return Ok((pixels, width, height));
+    }
+
+    /// Save a small image of the map panel as a PNG. The map panel keeps the last
+    /// map drawn, so this works even once a menu covers the map.
+    pub fn save_thumbnail(&mut self, file_name: &str) -> Result<(), String> {
+        let mut thumbnail =
+            self.targets.texture_creator.create_texture_target(PixelFormatEnum::RGBA8888, THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT)
+                                        .map_err(|err| err.to_string())?;
+
+        let map_texture = &self.targets.map_panel.target;
+        let mut pixels = Ok(Vec::new());
+        self.targets.canvas_panel.target.with_texture_canvas(&mut thumbnail, |canvas| {
+            pixels = canvas.copy(map_texture, None, None)
+                           .and_then(|_| canvas.read_pixels(None, PixelFormatEnum::RGB24));
+        }).map_err(|err| err.to_string())?;
+
+        return save_png(&mut pixels?, THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT, file_name);
+    }
+
     pub fn add_spritesheet(&mut self, name: String, texture: Texture) {
         let sprite_key = self.state.next_sprite_key;
         let sprite_sheet = SpriteSheet::new(name, texture);
@@ -66,6 +100,17 @@ impl Display {
         self.state.sprites.insert(sprite_key, sprite_sheet);
     }
 
+    /// Swap the texture of the sprite sheet with this name, keeping its key so sprites
+    /// already using it stay valid, or add it if there is no sprite sheet with this name.
+    pub fn replace_spritesheet(&mut self, name: String, texture: Texture) {
+        if self.sprite_exists(&name) {
+            let sprite_key = self.state.lookup_spritekey(&name);
+            self.state.sprites[&sprite_key] = SpriteSheet::new(name, texture);
+        } else {
+            self.add_spritesheet(name, texture);
+        }
+    }
+
     pub fn sprite_exists(&self, name: &str) -> bool {
         for (_key, sprite_sheet) in self.state.sprites.iter() {
             if sprite_sheet.name == *name {
@@ -111,8 +156,15 @@ impl Display {
     pub fn get_idle_animation(&mut self, entity_id: EntityId, data: &mut GameData, config: &Config) -> Option<Animation> {
         let name = data.entities.name[&entity_id];
 
-        if name == EntityName::Player || name == EntityName::Gol || name == EntityName::Pawn || name == EntityName::Rook {
-            let name = data.entities.name[&entity_id];
+        if data.entities.status.get(&entity_id).map_or(false, |status| status.knocked_out > 0) {
+            if let Some(anim) = self.knocked_out_animation(entity_id, data) {
+                return Some(anim);
+            }
+        }
+
+        if name == EntityName::Player || name == EntityName::Partner || name == EntityName::Gol || name == EntityName::Pawn || name == EntityName::Rook {
+            // the co-op partner is drawn with the player's sprites, in their own color
+            let name = if name == EntityName::Partner { EntityName::Player } else { name };
             let stance = data.entities.stance[&entity_id];
             let direction = data.entities.direction[&entity_id];
 
@@ -142,28 +194,33 @@ impl Display {
         return None;
     }
 
+    /// A knocked out entity lies still on the last frame of its death animation.
+    pub fn knocked_out_animation(&mut self, entity_id: EntityId, data: &mut GameData) -> Option<Animation> {
+        let sprite_name = format!("{:?}_death", data.entities.name[&entity_id]);
+        if self.sprite_exists(&sprite_name) {
+            let sprite_key = self.state.lookup_spritekey(&sprite_name);
+            let last_index = self.state.sprites[&sprite_key].num_sprites as f32 - 1.0;
+            let sprite = SpriteAnim::new(sprite_name, sprite_key, last_index, last_index, 0.0);
+            return Some(Animation::Loop(sprite));
+        }
+
+        return None;
+    }
+
     pub fn clear_level_state(&mut self) {
         self.state.impressions.clear();
         self.state.prev_turn_fov.clear();
         self.state.current_turn_fov.clear();
-        self.state.sound_tiles.clear();
     }
 
     pub fn process_message(&mut self, msg: Msg, data: &mut GameData, config: &Config) {
         match msg {
-            Msg::StartTurn => {
-                self.state.sound_tiles.clear();
-            }
-
             Msg::Sound(cause_id, source_pos, radius, should_animate) => {
                 if should_animate {
                     // NOTE this is a duplicate computation, also done in logic message processing
                     let sound_aoe =
                         aoe_fill(&data.map, AoeEffect::Sound, source_pos, radius, config);
 
-                    // Add to this turn's sound tiles list
-                    self.state.sound_tiles.extend(sound_aoe.positions().iter());
-
                     let player_id = data.find_by_name(EntityName::Player).unwrap();
                     let player_pos = data.entities.pos[&player_id];
 
@@ -224,6 +281,24 @@ impl Display {
                 }
             }
 
+            Msg::KnockedOut(_entity_id, target_id) => {
+                self.state.clear_animations(target_id);
+
+                let sprite_name = format!("{:?}_death", data.entities.name[&target_id]);
+                if self.sprite_exists(&sprite_name) {
+                    let sprite = self.new_sprite(sprite_name, 1.0);
+                    self.state.play_animation(target_id, Animation::Once(sprite));
+                }
+
+                if let Some(anim) = self.knocked_out_animation(target_id, data) {
+                    self.state.append_animation(target_id, anim);
+                }
+            }
+
+            Msg::WokeUp(entity_id) => {
+                self.play_idle_animation(entity_id, data, config);
+            }
+
             Msg::HammerSwing(entity_id, _item_id, _pos) => {
                 if data.entities.typ[&entity_id] == EntityType::Player {
                     // TODO need hammer animation
@@ -238,6 +313,11 @@ impl Display {
                 }
             }
 
+            Msg::WallDestroyed(_entity_id, blocked) => {
+                let debris_effect = Effect::Debris(config.debris_duration, blocked.end_pos);
+                self.state.play_effect(debris_effect);
+            }
+
             Msg::Stabbed(entity_id, _hit_entity) => {
                 if data.entities.typ[&entity_id] == EntityType::Player {
                     // TODO need dagger animation
@@ -252,6 +332,25 @@ impl Display {
                 }
             }
 
+            Msg::ItemBroke(entity_id, _item_id) => {
+                let pos = data.entities.pos[&entity_id];
+                let debris_effect = Effect::Debris(config.debris_duration, pos);
+                self.state.play_effect(debris_effect);
+            }
+
+            Msg::SwapPrimaryItem(entity_id) => {
+                if data.entities.typ[&entity_id] == EntityType::Player &&
+                   data.entities.held_item(entity_id, Hand::Secondary).is_some() {
+                    self.state.hand_swap = HAND_SWAP_SECONDS;
+                }
+            }
+
+            Msg::StealthKill(_attacker, attacked) => {
+                let attacked_pos = data.entities.pos[&attacked];
+                let kill_effect = Effect::StealthKill(config.stealth_kill_duration, attacked_pos);
+                self.state.play_effect(kill_effect);
+            }
+
             Msg::SwordSwing(entity_id, _item_id, _pos) => {
                 if data.entities.typ[&entity_id] == EntityType::Player {
                     if let Some(idle_anim) = self.get_idle_animation(entity_id, data, config) {
@@ -260,7 +359,7 @@ impl Display {
                 }
             }
 
-            Msg::Attack(attacker, attacked, _damage) => {
+            Msg::Attack(attacker, attacked, _damage) | Msg::CriticalHit(attacker, attacked, _damage) => {
                 if data.entities.typ[&attacker] == EntityType::Player {
                     // TODO need attack animation
                     //let attack_sprite =
@@ -620,6 +719,15 @@ impl<T> Panel<T> {
     }
 }
 
+/// Write RGB pixels to a PNG file, creating its directory if needed.
+fn save_png(pixels: &mut Vec<u8>, width: u32, height: u32, file_name: &str) -> Result<(), String> {
+    if let Some(dir) = std::path::Path::new(file_name).parent() {
+        std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+
+    let surface = Surface::from_data(pixels, width, height, width * 3, PixelFormatEnum::RGB24)?;
+    return surface.save(file_name);
+}
 
 pub struct DisplayTargets {
     pub canvas_panel: Panel<WindowCanvas>,
@@ -688,10 +796,91 @@ impl DisplayTargets {
         };
     }
 
-    pub fn mouse_pos(&self, x: i32, y: i32, map_width: i32, map_height: i32) -> Option<(i32, i32)> {
-        let map_rect = self.canvas_panel.get_rect_from_area(&self.map_area);
-        return cell_within_rect(map_rect, (map_width, map_height), (x, y));
+}
+
+/// The part of the map shown in the map area, in map cells, and the rectangle of the
+/// screen it is drawn to. Zooming in shows fewer cells, around a center cell, while
+/// zooming out shrinks the whole map within the map area.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MapView {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub dst: Rect,
+}
+
+impl MapView {
+    /// The view of a map at a zoom, keeping the center cell in the middle of the
+    /// view unless that would show past the edge of the map.
+    pub fn new(map_size: (i32, i32), map_rect: Rect, zoom: f32, center: Pos) -> MapView {
+        let map_width = map_size.0 as f32;
+        let map_height = map_size.1 as f32;
+
+        if zoom >= 1.0 {
+            let width = map_width / zoom;
+            let height = map_height / zoom;
+
+            let x = (center.x as f32 + 0.5 - width / 2.0).max(0.0).min(map_width - width);
+            let y = (center.y as f32 + 0.5 - height / 2.0).max(0.0).min(map_height - height);
+
+            return MapView { x, y, width, height, dst: map_rect };
+        }
+
+        let dst_width = (map_rect.width() as f32 * zoom) as u32;
+        let dst_height = (map_rect.height() as f32 * zoom) as u32;
+        let dst = Rect::new(map_rect.x() + (map_rect.width() - dst_width) as i32 / 2,
+                            map_rect.y() + (map_rect.height() - dst_height) as i32 / 2,
+                            dst_width,
+                            dst_height);
+
+        return MapView { x: 0.0, y: 0.0, width: map_width, height: map_height, dst };
     }
+
+    /// The pixels of the map panel within the view, given the size of a map panel cell.
+    pub fn src_rect(&self, cell_dims: (u32, u32)) -> Rect {
+        let (cell_width, cell_height) = (cell_dims.0 as f32, cell_dims.1 as f32);
+
+        return Rect::new((self.x * cell_width) as i32,
+                         (self.y * cell_height) as i32,
+                         (self.width * cell_width) as u32,
+                         (self.height * cell_height) as u32);
+    }
+
+    /// The map cell under a pixel on the screen, if the pixel is within the view.
+    pub fn cell_at(&self, pixel_pos: (i32, i32)) -> Option<(i32, i32)> {
+        if !self.dst.contains_point(pixel_pos) {
+            return None;
+        }
+
+        let x = self.x + (pixel_pos.0 - self.dst.x()) as f32 * self.width / self.dst.width() as f32;
+        let y = self.y + (pixel_pos.1 - self.dst.y()) as f32 * self.height / self.dst.height() as f32;
+
+        return Some((x as i32, y as i32));
+    }
+}
+
+#[test]
+pub fn test_map_view_zoom() {
+    let map_rect = Rect::new(0, 0, 400, 200);
+
+    // at a zoom of one the whole map fits the map area
+    let view = MapView::new((40, 20), map_rect, 1.0, Pos::new(5, 5));
+    assert_eq!((0.0, 0.0, 40.0, 20.0), (view.x, view.y, view.width, view.height));
+    assert_eq!(Some((4, 2)), view.cell_at((45, 25)));
+
+    // zooming in shows half the map around the center, kept within the map
+    let view = MapView::new((40, 20), map_rect, 2.0, Pos::new(20, 10));
+    assert_eq!((10.5, 5.5, 20.0, 10.0), (view.x, view.y, view.width, view.height));
+    let view = MapView::new((40, 20), map_rect, 2.0, Pos::new(1, 1));
+    assert_eq!((0.0, 0.0), (view.x, view.y));
+    assert_eq!(Some((2, 1)), view.cell_at((45, 25)));
+
+    // zooming out shrinks the map into the middle of the map area
+    let view = MapView::new((40, 20), map_rect, 0.5, Pos::new(5, 5));
+    assert_eq!(Rect::new(100, 50, 200, 100), view.dst);
+    assert_eq!(None, view.cell_at((10, 10)));
+    assert_eq!(Some((0, 0)), view.cell_at((100, 50)));
 }
 
 pub struct DisplayState {
@@ -702,9 +891,9 @@ pub struct DisplayState {
     // currently active effects
     pub effects: Vec<Effect>,
 
-    // animation information
+    // animations playing for each entity, started and stopped by the messages
+    // from each turn. The core game data holds no animation state.
     pub animations: IndexMap<EntityId, VecDeque<Animation>>,
-    pub next_anim_key: i64,
 
     // sprites drawn this frame
     pub drawn_sprites: IndexMap<EntityId, Sprite>,
@@ -716,8 +905,19 @@ pub struct DisplayState {
     pub prev_turn_fov: Vec<EntityId>,
     pub current_turn_fov: Vec<EntityId>,
 
-    // tiles that heard a sound
-    pub sound_tiles: Vec<Pos>,
+    // color palette the background was last drawn with
+    pub color_palette: usize,
+
+    // text drawn with a TTF font, for panels and menus
+    pub text: TextRenderer,
+
+    // zoom of the map, which moves smoothly toward the zoom chosen with the mouse wheel
+    pub zoom: f32,
+    pub zoom_target: f32,
+    pub map_view: Option<MapView>,
+
+    // seconds left in the animation of the player swapping the items in their hands
+    pub hand_swap: f32,
 }
 
 impl DisplayState {
@@ -728,15 +928,48 @@ impl DisplayState {
             next_sprite_key: 0,
             effects: Vec::new(),
             animations: IndexMap::<EntityId, VecDeque<Animation>>::new(),
-            next_anim_key: 0,
             drawn_sprites: IndexMap::new(),
             impressions: Vec::new(),
             prev_turn_fov: Vec::new(),
             current_turn_fov: Vec::new(),
-            sound_tiles: Vec::new(),
+            color_palette: 0,
+            text: TextRenderer::new(),
+            zoom: 1.0,
+            zoom_target: 1.0,
+            map_view: None,
+            hand_swap: 0.0,
         };
     }
 
+    /// The number of sprites and glyphs drawn since this was last called.
+    pub fn take_draw_calls(&mut self) -> usize {
+        let mut draw_calls = self.text.draw_calls;
+        self.text.draw_calls = 0;
+
+        for sprite_sheet in self.sprites.values_mut() {
+            draw_calls += sprite_sheet.draw_calls;
+            sprite_sheet.draw_calls = 0;
+        }
+
+        return draw_calls;
+    }
+
+    /// Zoom the map in or out by a number of mouse wheel notches.
+    pub fn zoom_by(&mut self, notches: f32) {
+        self.zoom_target = (self.zoom_target + notches * MAP_ZOOM_STEP).max(MIN_MAP_ZOOM).min(MAX_MAP_ZOOM);
+    }
+
+    /// Move the zoom part of the way toward the chosen zoom, so zooming is smooth.
+    pub fn update_zoom(&mut self, dt: f32) {
+        let amount = (dt * MAP_ZOOM_SPEED).min(1.0);
+        self.zoom += (self.zoom_target - self.zoom) * amount;
+    }
+
+    /// The map cell under a pixel on the screen, in the view last drawn.
+    pub fn mouse_pos(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        return self.map_view.and_then(|view| view.cell_at((x, y)));
+    }
+
     pub fn lookup_spritekey(&self, name: &str) -> SpriteKey {
         for (key, sprite_sheet) in self.sprites.iter() {
             if sprite_sheet.name == *name {
@@ -748,6 +981,8 @@ impl DisplayState {
     }
 
     pub fn update_animations(&mut self, dt: f32) {
+        self.hand_swap = (self.hand_swap - dt).max(0.0);
+
         for anims in self.animations.values_mut() {
             if let Some(anim) = anims.get_mut(0) {
                 if let Some(sprite_anim) = anim.sprite_anim_mut() {
@@ -785,6 +1020,33 @@ impl DisplayState {
         self.animations[&entity_id].push_back(animation);
     }
 
+    /// Whether any animation or effect the player can see is still showing the last
+    /// turn. Animations of entities out of view are not drawn, and so never finish.
+    pub fn blocking_animations(&self, data: &GameData, config: &Config) -> bool {
+        if self.effects.iter().any(|effect| effect.blocks_input()) {
+            return true;
+        }
+
+        let player_id = data.find_by_name(EntityName::Player).unwrap();
+        return self.animations.iter().any(|(entity_id, anims)| {
+            return anims.front().map_or(false, |anim| anim.blocks_input()) &&
+                   data.entities.ids.contains(entity_id) &&
+                   data.is_in_fov(player_id, *entity_id, config);
+        });
+    }
+
+    /// Finish every animation and effect that input would wait on, moving on to
+    /// whatever is played after them.
+    pub fn skip_animations(&mut self) {
+        self.effects.retain(|effect| !effect.blocks_input());
+
+        for anims in self.animations.values_mut() {
+            while anims.front().map_or(false, |anim| anim.blocks_input()) {
+                anims.pop_front();
+            }
+        }
+    }
+
     pub fn clear_animations(&mut self, entity_id: EntityId) {
         self.animations[&entity_id].clear();
     }
@@ -808,6 +1070,71 @@ impl Impression {
 }
 
 
+/// Text drawn from a TTF font rather than the 16x16 sprite sheet, so it can show any
+/// character the font has, such as accented letters or CJK, each at its own width.
+/// Glyphs are rendered once, for the characters the current locale uses.
+pub struct TextRenderer {
+    pub glyphs: HashMap<char, Texture>,
+    pub line_height: u32,
+    pub locale: String, // the locale the glyphs were rendered for
+    pub draw_calls: usize, // glyphs drawn since this was last reset
+}
+
+impl TextRenderer {
+    pub fn new() -> TextRenderer {
+        return TextRenderer {
+            glyphs: HashMap::new(),
+            line_height: 1,
+            locale: "".to_string(),
+            draw_calls: 0,
+        };
+    }
+
+    pub fn draw_text_list(&mut self,
+                         panel: &mut Panel<&mut WindowCanvas>,
+                         text_list: &Vec<String>,
+                         cell: Pos,
+                         color: Color) {
+        for (index, text) in text_list.iter().enumerate() {
+            let text_cell = Pos::new(cell.x, cell.y + index as i32);
+            self.draw_text(panel, text, text_cell, color);
+        }
+    }
+
+    /// Draw text starting at a cell, with each line as tall as a cell. Glyphs are
+    /// placed by their own width, and characters the font is missing are drawn as '?'.
+    pub fn draw_text(&mut self,
+                     panel: &mut Panel<&mut WindowCanvas>,
+                     text: &str,
+                     cell: Pos,
+                     color: Color) {
+        let (cell_width, cell_height) = panel.cell_dims();
+        let scale = cell_height as f32 / self.line_height as f32;
+
+        let mut x = cell.x * cell_width as i32;
+        let y = cell.y * cell_height as i32;
+
+        panel.target.set_blend_mode(BlendMode::Blend);
+
+        for chr in text.chars() {
+            let chr = if self.glyphs.contains_key(&chr) { chr } else { '?' };
+
+            if let Some(glyph) = self.glyphs.get_mut(&chr) {
+                let width = (glyph.query().width as f32 * scale) as u32;
+
+                glyph.set_color_mod(color.r, color.g, color.b);
+                glyph.set_alpha_mod(color.a);
+
+                let dst = Rect::new(x, y, width, cell_height);
+                panel.target.copy(glyph, None, Some(dst)).unwrap();
+                self.draw_calls += 1;
+
+                x += width as i32;
+            }
+        }
+    }
+}
+
 pub struct SpriteSheet {
     pub texture: Texture,
     pub name: String,
@@ -816,6 +1143,7 @@ pub struct SpriteSheet {
     pub cols: usize,
     pub width: usize,
     pub height: usize,
+    pub draw_calls: usize, // sprites drawn since this was last reset
 }
 
 impl SpriteSheet {
@@ -836,6 +1164,7 @@ impl SpriteSheet {
             cols,
             width,
             height,
+            draw_calls: 0,
         };
     }
 
@@ -921,6 +1250,7 @@ impl SpriteSheet {
         self.texture.set_color_mod(color.r, color.g, color.b);
         self.texture.set_alpha_mod(color.a);
 
+        self.draw_calls += 1;
         panel.target.copy_ex(&self.texture,
                              Some(src),
                              Some(dst),
@@ -1001,6 +1331,7 @@ impl SpriteSheet {
         self.texture.set_color_mod(color.r, color.g, color.b);
         self.texture.set_alpha_mod(color.a);
 
+        self.draw_calls += 1;
         panel.target.copy_ex(&self.texture,
                              Some(src),
                              Some(dst),
@@ -10,7 +10,7 @@ use roguelike_engine::input::*;
 use crate::display::*;
 
 
-pub fn translate_event(event: Event, game: &mut Game, display: &mut Display) -> Option<InputEvent> {
+pub fn translate_event(event: Event, _game: &mut Game, display: &mut Display) -> Option<InputEvent> {
     match event {
         Event::Quit {..} => {
             return Some(InputEvent::Quit);
@@ -58,6 +58,12 @@ pub fn translate_event(event: Event, game: &mut Game, display: &mut Display) ->
                     return Some(InputEvent::Tab);
                 } else if key == Keycode::Escape {
                     return Some(InputEvent::Esc);
+                } else if key == Keycode::F3 {
+                    return Some(InputEvent::PerfOverlay);
+                } else if key == Keycode::F11 {
+                    return Some(InputEvent::SaveClip);
+                } else if key == Keycode::F12 {
+                    return Some(InputEvent::Screenshot);
                 } else if key == Keycode::LShift || key == Keycode::RShift {
                     return Some(InputEvent::Shift(KeyDir::Up));
                 } else if key == Keycode::KpEnter || key == Keycode::Return {
@@ -77,6 +83,12 @@ pub fn translate_event(event: Event, game: &mut Game, display: &mut Display) ->
             return Some(InputEvent::MousePos(x, y));
         }
 
+        Event::MouseWheel {y, ..} => {
+            display.mouse_state.wheel = y as f32;
+            display.state.zoom_by(y as f32);
+            return None;
+        }
+
         Event::MouseButtonDown {mouse_btn, ..} => {
             let click;
             match mouse_btn {
@@ -99,9 +111,7 @@ pub fn translate_event(event: Event, game: &mut Game, display: &mut Display) ->
             };
 
             let mouse_pos = Pos::new(display.mouse_state.x, display.mouse_state.y);
-            let (map_width, map_height) = game.data.map.size();
-            let option_mouse_cell =
-                display.targets.mouse_pos(display.mouse_state.x, display.mouse_state.y, map_width, map_height);
+            let option_mouse_cell = display.state.mouse_pos(display.mouse_state.x, display.mouse_state.y);
             let target_pos = option_mouse_cell.map(|pair| Pos::from(pair));
             return Some(InputEvent::MouseButton(click, mouse_pos, target_pos, KeyDir::Down));
         }
@@ -198,6 +208,8 @@ pub fn keycode_to_char(key: Keycode) -> Option<char> {
         Keycode::LeftBracket => Some('['),
         Keycode::RightBracket => Some(']'),
         Keycode::Backquote => Some('`'),
+        Keycode::Slash => Some('?'),
+        Keycode::F1 => Some('?'),
         _ => None,
     }
 }
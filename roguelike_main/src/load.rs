@@ -1,4 +1,7 @@
 use std::fs;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::SystemTime;
 
 use sdl2::image::LoadTexture;
 use sdl2::render::{WindowCanvas, Texture, TextureCreator};
@@ -42,6 +45,84 @@ pub fn load_sprite(texture_creator: &TextureCreator<WindowContext>,
     display.add_spritesheet(sprite_name.to_string(), texture);
 }
 
+/// Load a sprite again after its file changes, swapping the texture of the sprite
+/// sheet with that name so animations already playing it keep working.
+pub fn reload_sprite(texture_creator: &TextureCreator<WindowContext>,
+                     display: &mut Display,
+                     path: &str,
+                     sprite_name: &str) -> Result<(), String> {
+    let texture = texture_creator.load_texture(path)?;
+    display.replace_spritesheet(sprite_name.to_string(), texture);
+    return Ok(());
+}
+
+/// A change to an asset file, found by an AssetWatcher.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AssetEvent {
+    Sprite(String, String), // a png was added or modified- its path, and its sprite name
+    Map(String),            // the map file was modified
+}
+
+/// Watches the sprite directory and the map file, so an asset can be reloaded when
+/// its file is added or modified without restarting the game.
+pub struct AssetWatcher {
+    pub sprite_dir: String,
+    pub map_file: String,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl AssetWatcher {
+    /// Start watching from the files as they are now, so only later changes are reported.
+    pub fn new(sprite_dir: &str, map_file: &str) -> AssetWatcher {
+        let mut watcher = AssetWatcher { sprite_dir: sprite_dir.to_string(),
+                                         map_file: map_file.to_string(),
+                                         modified: HashMap::new() };
+        watcher.changes();
+        return watcher;
+    }
+
+    /// The assets whose files were added or modified since the last check.
+    pub fn changes(&mut self) -> Vec<AssetEvent> {
+        let mut events = Vec::new();
+
+        let mut sprite_files = Vec::new();
+        find_sprite_files(PathBuf::from(&self.sprite_dir), &mut sprite_files);
+        for path in sprite_files {
+            if self.file_changed(path.clone()) {
+                let sprite_name = path.file_stem().unwrap().to_str().unwrap().to_string();
+                events.push(AssetEvent::Sprite(path.to_str().unwrap().to_string(), sprite_name));
+            }
+        }
+
+        if self.file_changed(PathBuf::from(&self.map_file)) {
+            events.push(AssetEvent::Map(self.map_file.clone()));
+        }
+
+        return events;
+    }
+
+    fn file_changed(&mut self, path: PathBuf) -> bool {
+        let modified = fs::metadata(&path).and_then(|metadata| metadata.modified());
+        if let Ok(modified) = modified {
+            return self.modified.insert(path, modified) != Some(modified);
+        }
+        return false;
+    }
+}
+
+fn find_sprite_files(path: PathBuf, sprite_files: &mut Vec<PathBuf>) {
+    if let Ok(entries) = fs::read_dir(path) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                find_sprite_files(path, sprite_files);
+            } else if path.extension().map_or(false, |extension| extension == "png") {
+                sprite_files.push(path);
+            }
+        }
+    }
+}
+
 /// load a ttf font file and render all ascii characters onto a 16x16 grid.
 /// the resulting texture is then used for rendering by copying character squares
 /// for individual ascii characters.
@@ -78,3 +159,29 @@ pub fn load_font(ttf_context: &Sdl2TtfContext,
     return font_texture;
 }
 
+
+/// Render each character the text needs from a ttf font, along with all printable
+/// ascii characters, so text can be drawn in any script the font supports.
+pub fn load_text_renderer(ttf_context: &Sdl2TtfContext,
+                          texture_creator: &TextureCreator<WindowContext>,
+                          file_name: &str,
+                          font_size: u16,
+                          characters: &str) -> TextRenderer {
+    let font = ttf_context.load_font(format!("resources/{}", file_name), font_size).expect("Could not load font file!");
+
+    let mut text = TextRenderer::new();
+    text.line_height = font.height() as u32;
+
+    for chr in (' '..='~').chain(characters.chars()) {
+        if chr.is_control() || text.glyphs.contains_key(&chr) {
+            continue;
+        }
+
+        if let Ok(chr_surface) = font.render_char(chr).blended(sdl2::pixels::Color::WHITE) {
+            let chr_texture = chr_surface.as_texture(&texture_creator).unwrap();
+            text.glyphs.insert(chr, chr_texture);
+        }
+    }
+
+    return text;
+}
@@ -6,14 +6,18 @@ mod keyboard;
 mod load;
 mod replay;
 mod animation;
+mod clip;
+mod profiler;
+#[cfg(feature = "debug-ui")]
+mod debug_ui;
 
-use std::fs;
+use std::collections::VecDeque;
 use std::io::{BufRead, Write};
 use std::time::{Duration, Instant, SystemTime};
-use std::path::Path;
 use std::str::FromStr;
 use std::thread;
 use std::sync::mpsc::{self, Receiver};
+use std::net::TcpStream;
 
 use log::LevelFilter;
 use simple_logging;
@@ -21,8 +25,12 @@ use logging_timer::timer;
 
 use gumdrop::Options;
 
+use sdl2::ttf::Sdl2TtfContext;
+use sdl2::render::TextureCreator;
+use sdl2::video::WindowContext;
+
 use roguelike_core::types::*;
-use roguelike_core::config::Config;
+use roguelike_core::config::{Config, NarrationOutput};
 use roguelike_core::constants::*;
 use roguelike_core::map::MapLoadConfig;
 
@@ -32,6 +40,18 @@ use roguelike_engine::actions::*;
 use roguelike_engine::input::*;
 use roguelike_engine::make_map::{make_map, read_map_xp};
 use roguelike_engine::log::*;
+use roguelike_engine::narrate::Narrator;
+use roguelike_engine::config_watcher::*;
+use roguelike_engine::manifest::{preflight, MANIFEST_NAME};
+use roguelike_engine::vault::lint_vaults;
+use roguelike_engine::map_stats::mapgen_stats;
+use roguelike_engine::telemetry::*;
+use roguelike_engine::agent::{make_agent, agent_stats};
+use roguelike_engine::error::GameResult;
+use roguelike_engine::prompt::confirm;
+use roguelike_engine::checkpoint::*;
+use roguelike_engine::spectate::*;
+use roguelike_engine::coop::CoopHost;
 
 use roguelike_lib::commands::*;
 
@@ -40,9 +60,10 @@ use crate::render::*;
 use crate::display::*;
 use crate::load::*;
 use crate::replay::*;
-
-
-pub const CONFIG_NAME: &str = "config.yaml";
+use crate::clip::ClipRecorder;
+use crate::profiler::*;
+#[cfg(feature = "debug-ui")]
+use crate::debug_ui::DebugUi;
 
 
 #[derive(Debug, Clone, Options)]
@@ -77,6 +98,33 @@ pub struct GameOptions {
     #[options(help = "procgen map config", short="g")]
     pub procgen_map: Option<String>,
 
+    #[options(help = "check every vault file for problems and exit", no_short)]
+    pub check_vaults: bool,
+
+    #[options(help = "generate the given number of maps, starting at the seed, print statistics about them, and exit", no_short)]
+    pub mapgen_stats: Option<u64>,
+
+    #[options(help = "play the given number of runs with an agent, starting at the seed, print how they ended, and exit", no_short)]
+    pub agent_runs: Option<u64>,
+
+    #[options(help = "the agent to play with --agent-runs- random or seeker (the default)", no_short)]
+    pub agent: Option<String>,
+
+    #[options(help = "summarize the detections, damage, and item uses in a telemetry file, and exit", no_short)]
+    pub telemetry_summary: Option<String>,
+
+    #[options(help = "stream the game to spectators connecting to the given address, such as 0.0.0.0:7878", no_short)]
+    pub serve: Option<String>,
+
+    #[options(help = "watch a game streamed with --serve from the given address", no_short)]
+    pub spectate: Option<String>,
+
+    #[options(help = "host a co-op game on the given address, such as 0.0.0.0:7879, for a partner to join", no_short)]
+    pub coop_host: Option<String>,
+
+    #[options(help = "join a co-op game hosted with --coop-host at the given address", no_short)]
+    pub coop_join: Option<String>,
+
     #[options(help = "display help text")]
     pub help: bool,
 }
@@ -85,6 +133,21 @@ pub struct GameOptions {
 fn main() {
     let opts = GameOptions::parse_args_default_or_exit();
 
+    if opts.check_vaults {
+        let problems = lint_vaults("resources/vaults/");
+        for problem in problems.iter() {
+            eprintln!("{}", problem);
+        }
+        eprintln!("{} problems found in vault files", problems.len());
+        std::process::exit(if problems.len() > 0 { 1 } else { 0 });
+    }
+
+    if let Some(telemetry_file) = &opts.telemetry_summary {
+        let records = read_telemetry(telemetry_file).unwrap();
+        println!("{}", summarize_telemetry(&records));
+        return;
+    }
+
     let seed: u64 =
         if let Some(given_seed) = opts.seed {
             given_seed
@@ -98,14 +161,87 @@ fn main() {
 
     eprintln!("Seed: {} (0x{:X})", seed, seed);
 
+    if let Some(num_maps) = opts.mapgen_stats {
+        let config = Config::from_file(CONFIG_NAME);
+        let mut game = Game::new(seed, config.clone());
+        exit_on_error(game.load_vaults("resources/vaults/"));
+
+        let map_config = map_load_config(&opts, &config);
+        println!("{}", exit_on_error(mapgen_stats(&game, &map_config, seed..(seed + num_maps))));
+        return;
+    }
+
+    if let Some(num_runs) = opts.agent_runs {
+        let config = Config::from_file(CONFIG_NAME);
+        let mut game = Game::new(seed, config.clone());
+        exit_on_error(game.load_vaults("resources/vaults/"));
+        exit_on_error(game.load_dialogue("resources/dialogue.yaml"));
+        exit_on_error(game.load_shop("resources/shop.yaml"));
+
+        let agent_name = opts.agent.clone().unwrap_or("seeker".to_string());
+        let mut agent = make_agent(&agent_name, seed).expect(&format!("Unknown agent {}", agent_name));
+
+        let map_config = map_load_config(&opts, &config);
+        println!("{}", exit_on_error(agent_stats(&mut *agent, &game, &map_config, seed..(seed + num_runs), AGENT_MAX_STEPS)));
+        return;
+    }
+
     let log_level =
         opts.log_level.clone().map_or(LevelFilter::Off,
                                       |level_str| LevelFilter::from_str(&level_str).expect("Log level unexpected!"));
     simple_logging::log_to_file("game.log", log_level).unwrap();
 
+    // check for every asset before opening the window, reporting everything missing at once
+    if let Err(report) = preflight(MANIFEST_NAME, CONFIG_NAME) {
+        eprintln!("{}", report);
+        std::process::exit(1);
+    }
+
     run(seed, opts).unwrap();
 }
 
+/// Report an error loading a map or asset from a subcommand, which has no window to show it in.
+fn exit_on_error<T>(result: GameResult<T>) -> T {
+    match result {
+        Ok(value) => return value,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Load the files the game needs beside the config and sprites.
+fn load_assets(game: &mut Game) -> GameResult<()> {
+    game.load_vaults("resources/vaults/")?;
+    game.load_loadouts("resources/loadouts.yaml")?;
+    game.load_dialogue("resources/dialogue.yaml")?;
+    game.load_shop("resources/shop.yaml")?;
+    game.load_locale("resources/locale")?;
+    game.load_leaderboard("leaderboard.yaml")?;
+    game.load_unlocks("unlocks.yaml")?;
+    return Ok(());
+}
+
+/// The map configuration to use- the one given on the command line, if any, or else the config's.
+fn map_load_config(opts: &GameOptions, config: &Config) -> MapLoadConfig {
+    let mut map_config: MapLoadConfig;
+
+    if let Some(procgen_map) = opts.procgen_map.clone() {
+        map_config = MapLoadConfig::ProcGen(procgen_map);
+    } else {
+        map_config = config.map_load.clone();
+    }
+
+    if let Some(map_config_str) = &opts.map_config {
+        let cli_map_config = map_config_str.parse::<MapLoadConfig>()
+                                           .expect("Could not parse map config option!");
+        map_config = cli_map_config;
+    }
+
+    return map_config;
+}
+
 pub fn run(seed: u64, opts: GameOptions) -> Result<(), String> {
     /* Create SDL Context */
     let sdl_context = sdl2::init()?;
@@ -139,24 +275,12 @@ pub fn run(seed: u64, opts: GameOptions) -> Result<(), String> {
     let config = Config::from_file(CONFIG_NAME);
     let mut game = Game::new(seed, config.clone());
 
-    game.load_vaults("resources/vaults/");
+    let assets_loaded = load_assets(&mut game);
 
     make_mouse(&mut game.data.entities, &game.config, &mut game.msg_log);
 
     /* Create Map */
-    let mut map_config: MapLoadConfig;
-
-    if let Some(procgen_map) = opts.procgen_map.clone() {
-        map_config = MapLoadConfig::ProcGen(procgen_map);
-    } else {
-        map_config = config.map_load.clone();
-    }
-
-    if let Some(map_config_str) = &opts.map_config {
-        let cli_map_config = map_config_str.parse::<MapLoadConfig>()
-                                           .expect("Could not parse map config option!");
-        map_config = cli_map_config;
-    }
+    let map_config = map_load_config(&opts, &config);
 
     // save map config to a file
     let mut map_config_file = std::fs::File::create(MAP_CONFIG_NAME).unwrap();
@@ -164,10 +288,11 @@ pub fn run(seed: u64, opts: GameOptions) -> Result<(), String> {
 
     /* Run Game or Take Screenshot */
     if opts.screenshot {
-        make_map(&map_config, &mut game);
+        assets_loaded.and_then(|_| make_map(&map_config, &mut game)).map_err(|err| err.to_string())?;
         take_screenshot(&mut game, &mut display).unwrap();
         return Ok(());
     } else if let Some(record_name) = opts.check {
+        assets_loaded.map_err(|err| err.to_string())?;
         let delay = opts.delay.unwrap_or(0);
         let mut event_pump = sdl_context.event_pump().unwrap();
 
@@ -177,6 +302,7 @@ pub fn run(seed: u64, opts: GameOptions) -> Result<(), String> {
             return check_single_record(&mut game, &mut display, &mut event_pump, &record_name, delay);
         }
     } else if let Some(record_name) = opts.rerecord {
+        assets_loaded.map_err(|err| err.to_string())?;
         let delay = opts.delay.unwrap_or(0);
         let mut event_pump = sdl_context.event_pump().unwrap();
         if record_name == "all" {
@@ -184,34 +310,99 @@ pub fn run(seed: u64, opts: GameOptions) -> Result<(), String> {
         } else {
             return rerecord_single(&mut game, &mut display, &mut event_pump, &record_name, delay);
         }
+    } else if let Some(address) = opts.spectate {
+        assets_loaded.map_err(|err| err.to_string())?;
+        let event_pump = sdl_context.event_pump().unwrap();
+        return spectate_loop(game, display, &address, false, event_pump);
+    } else if let Some(address) = opts.coop_join {
+        assets_loaded.map_err(|err| err.to_string())?;
+        let event_pump = sdl_context.event_pump().unwrap();
+        return spectate_loop(game, display, &address, true, event_pump);
     } else {
-        make_map(&map_config, &mut game);
+        // the window is open by now, so a map or asset that can't be loaded is shown on the error screen
+        if let Err(err) = assets_loaded.and_then(|_| make_map(&map_config, &mut game)) {
+            game.show_error(err);
+        } else if opts.replay.is_none() {
+            // a new game starts at the main menu, unless replaying a game or playing a given seed.
+            if opts.seed.is_none() {
+                game.settings.state = GameState::MainMenu;
+            } else if game.loadouts.len() > 0 {
+                game.settings.state = GameState::NewGame;
+            }
+
+            // the run is saved at the start of each level, and offered back if the game crashed
+            game.set_checkpoint_file(CHECKPOINT_NAME);
+            game.set_ghost_dir(GHOST_DIR);
+            let crashed = start_session(CRASH_MARKER_NAME);
+            if crashed && std::path::Path::new(CHECKPOINT_NAME).exists() {
+                confirm(&mut game.settings, "The game did not exit cleanly. Resume from the last checkpoint?", InputAction::ResumeCheckpoint);
+            } else if opts.seed.is_some() {
+                game.save_checkpoint();
+            }
+        }
+
         let event_pump = sdl_context.event_pump().unwrap();
-        return game_loop(game, display, opts, event_pump);
+        return game_loop(game, display, opts, event_pump, &ttf_context, &texture_creator);
     }
 }
 
-pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, mut event_pump: sdl2::EventPump) -> Result<(), String> {
+pub fn game_loop(mut game: Game,
+                 mut display: Display,
+                 opts: GameOptions,
+                 mut event_pump: sdl2::EventPump,
+                 ttf_context: &Sdl2TtfContext,
+                 texture_creator: &TextureCreator<WindowContext>) -> Result<(), String> {
     // read in the recorded action log, if one is provided
     let mut starting_actions = Vec::new();
     if let Some(replay_file) = &opts.replay {
         starting_actions = read_action_log(&replay_file);
     }
 
-    let mut config_modified_time = fs::metadata(CONFIG_NAME).unwrap().modified().unwrap();
+    let mut config_watcher = ConfigWatcher::new(CONFIG_NAME);
+    let mut asset_watcher = AssetWatcher::new("resources/animations", &format!("resources/{}", game.config.map_file));
 
     let mut log = Log::new();
     let mut recording = Recording::new(&game);
+    let mut telemetry = None;
+    if game.config.telemetry {
+        telemetry = Some(Telemetry::new(&game.config.telemetry_file)?);
+    }
+
+    let mut spectator_server = None;
+    if let Some(address) = &opts.serve {
+        spectator_server = Some(SpectatorServer::bind(address)?);
+        log.log_console(&format!("Streaming to spectators on {}", address));
+    }
+
+    let mut coop_host = None;
+    if let Some(address) = &opts.coop_host {
+        coop_host = Some(CoopHost::bind(address)?);
+        log.log_console(&format!("Hosting co-op on {}", address));
+    }
+
+    /* Set up Narration */
+    let mut narrator = Narrator::new();
+    let mut narration_socket = None;
+    if let NarrationOutput::Socket(address) = &game.config.narration {
+        narration_socket = Some(TcpStream::connect(address).expect(&format!("Could not connect to narration socket {}", address)));
+    }
 
     /* Setup FPS Throttling */
-    let frame_ms = 1000 / game.config.frame_rate as u64;
-    let fps_throttler = Throttler::new(Duration::from_millis(frame_ms));
+    let mut fps_throttler = Throttler::new(frame_duration(game.config.frame_rate));
 
     /* Set up Input Handling */
     let io_recv = spawn_input_reader();
 
+    /* Set up Waiting for Animations */
+    let mut waiting_actions: VecDeque<InputAction> = VecDeque::new();
+
     /* Main Game Loop */
     let mut frame_time = Instant::now();
+    let mut screenshot = false;
+    let mut clip_recorder = ClipRecorder::new();
+    let mut profiler = Profiler::new();
+    #[cfg(feature = "debug-ui")]
+    let mut debug_ui = DebugUi::new();
     while game.settings.running {
         let _loop_timer = timer!("GAME_LOOP");
 
@@ -219,12 +410,21 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, mut ev
         let mut input_action: InputAction = InputAction::None;
         {
             let _input_timer = timer!("INPUT");
+            let phase_start = Instant::now();
 
             // check for commands to execute
             process_commands(&io_recv, &mut game, &mut log);
 
             //input_action = process_input_events(frame_time, &mut event_pump, &mut game, &mut display);
             for sdl2_event in event_pump.poll_iter() {
+                // events used by the debug windows are kept from the game
+                #[cfg(feature = "debug-ui")]
+                {
+                    if debug_ui.handle_event(&sdl2_event) {
+                        continue;
+                    }
+                }
+
                 if let Some(event) = keyboard::translate_event(sdl2_event, &mut game, &mut display) {
                     if game.config.recording && matches!(event, InputEvent::Char('[', KeyDir::Up)) {
                         game = recording.backward();
@@ -238,12 +438,41 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, mut ev
                     }
                 }
             }
+
+            profiler.record(Phase::Input, phase_start);
         }
 
+        // the misc and logic sections are shown together as the logic phase
+        let logic_start = Instant::now();
+
         /* Misc */
         {
             let _misc_timer = timer!("MISC");
 
+            // when waiting for animations, the player's actions are held until the animations
+            // showing the last turn finish, so fast input does not get ahead of them.
+            if input_action == InputAction::SkipAnimations {
+                display.state.skip_animations();
+            } else if input_action == InputAction::Screenshot {
+                // the screenshot is taken once the frame is drawn, so the action goes no further
+                screenshot = true;
+                input_action = InputAction::None;
+            } else if input_action == InputAction::SaveClip {
+                save_clip(&clip_recorder, &game.config, &mut log);
+                input_action = InputAction::None;
+            } else if game.config.wait_for_animations && input_action != InputAction::None {
+                waiting_actions.push_back(input_action);
+                input_action = InputAction::None;
+            }
+
+            if waiting_actions.len() > 0 {
+                let animating = game.settings.render_map &&
+                                display.state.blocking_animations(&game.data, &game.config);
+                if !animating || !game.config.wait_for_animations {
+                    input_action = waiting_actions.pop_front().unwrap();
+                }
+            }
+
             // if there are starting actions to read, pop one off to play
             if let Some(action) = starting_actions.pop() {
                 input_action = action;
@@ -258,33 +487,94 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, mut ev
             let _logic_timer = timer!("LOGIC");
             let dt = Instant::now().duration_since(frame_time).as_secs_f32();
             frame_time = Instant::now();
-            game.step_game(input_action, dt);
-            
+            if let Some(coop_host) = &mut coop_host {
+                if let Err(error) = coop_host.step_game(&mut game, input_action, dt) {
+                    log.log_console(&format!("Co-op error- {}", error));
+                }
+            } else {
+                game.step_game(input_action, dt);
+            }
+
             if game.config.recording && input_action != InputAction::None {
                 recording.action(&game, input_action);
             }
 
+            if let Some(telemetry) = &mut telemetry {
+                if let Err(error) = telemetry.record(&game) {
+                    log.log_console(&format!("Could not write telemetry- {}", error));
+                }
+            }
+
+            if let Some(spectator_server) = &mut spectator_server {
+                if let Err(error) = spectator_server.update(&game) {
+                    log.log_console(&format!("Could not stream to spectators- {}", error));
+                }
+            }
+
             for msg_index in 0..game.msg_log.turn_messages.len() {
                 let msg = game.msg_log.turn_messages[msg_index];
-                let msg_line = &msg.msg_line(&game.data);
+                let msg_line = &msg.msg_line(&game.data, &game.locale);
                 if msg_line.len() > 0 {
                     log.log_console(msg_line);
                 }
                 log.log_msg(&format!("{}", msg));
             }
 
+            if game.config.narration != NarrationOutput::Off {
+                for line in narrator.narrate(&game) {
+                    if let Some(socket) = &mut narration_socket {
+                        // a screen reader that has gone away should not stop the game
+                        let _ = writeln!(socket, "{}", line);
+                    } else {
+                        log.log_narration(&line);
+                    }
+                }
+            }
+
             if game.settings.state == GameState::Win {
                 display.clear_level_state();
                 recording.clear();
             } else if game.settings.state == GameState::Exit {
                 game.settings.running = false;
             }
+
+            profiler.record(Phase::Logic, logic_start);
         }
 
         /* Display */
         {
             let _display_timer = timer!("DISPLAY");
-            update_display(&mut game, &mut display)?;
+            let phase_start = Instant::now();
+
+            // glyphs are rendered for the locale's characters, so a new locale needs new glyphs
+            if display.state.text.locale != game.locale.name {
+                display.state.text = load_text_renderer(ttf_context,
+                                                        texture_creator,
+                                                        &game.config.text_font,
+                                                        game.config.text_font_size,
+                                                        &game.locale.characters());
+                display.state.text.locale = game.locale.name.clone();
+            }
+
+            update_display(&mut game,
+                           &mut display,
+                           screenshot,
+                           &mut clip_recorder,
+                           &mut profiler,
+                           #[cfg(feature = "debug-ui")] &mut debug_ui)?;
+            screenshot = false;
+
+            // a run which made the leaderboard keeps an image of how the map looked when it ended
+            let needs_thumbnail = game.settings.last_score.as_ref().map_or(false, |entry| entry.thumbnail.is_none());
+            if game.settings.rank.is_some() && needs_thumbnail {
+                let file_name = timestamped_file_name(THUMBNAIL_DIR, &format!("run_{}", game.settings.seed), "png");
+                match display.save_thumbnail(&file_name) {
+                    Ok(()) => game.set_thumbnail(&file_name),
+                    Err(error) => log.log_console(&format!("Could not save thumbnail {}- {}", file_name, error)),
+                }
+            }
+
+            profiler.record(Phase::Display, phase_start);
         }
 
         game.msg_log.clear();
@@ -292,14 +582,32 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, mut ev
         /* Configuration */
         {
             let _config_timer = timer!("CONFIG");
-            reload_config(&mut config_modified_time, &mut game);
+            reload_config(&mut config_watcher, &mut game, &mut log, &mut fps_throttler);
+        }
+
+        /* Assets */
+        {
+            let _asset_timer = timer!("ASSETS");
+            reload_assets(&mut asset_watcher, &mut game, &mut display, &mut log, texture_creator);
         }
 
         /* Wait until the next tick to loop */
         {
             let _wait_timer = timer!("WAIT");
+            let phase_start = Instant::now();
             fps_throttler.wait();
+            profiler.record(Phase::Wait, phase_start);
+        }
+
+        profiler.end_frame();
+    }
+
+    // a clean exit saves the run in progress, and removes the mark a crash would leave behind
+    if game.checkpoint_file.is_some() {
+        if game.settings.last_score.is_none() && game.settings.turn_count > 0 {
+            game.save_checkpoint();
         }
+        end_session(CRASH_MARKER_NAME);
     }
 
     // NOTE we could also just put these files in the right place to begin with...
@@ -311,6 +619,68 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, mut ev
     return Ok(());
 }
 
+/// Watch a game streamed by another player, drawing each frame as it arrives. The game is
+/// played by the host, so a spectator only leaves, while a co-op partner's input is sent to
+/// the host to move their character.
+pub fn spectate_loop(mut game: Game,
+                     mut display: Display,
+                     address: &str,
+                     send_input: bool,
+                     mut event_pump: sdl2::EventPump) -> Result<(), String> {
+    let stream = TcpStream::connect(address).map_err(|err| format!("Could not connect to {}: {}", address, err))?;
+    let mut input_stream = stream.try_clone().map_err(|err| err.to_string())?;
+    eprintln!("Spectating {}", address);
+
+    let (frame_send, frame_recv) = mpsc::channel();
+    thread::spawn(move || {
+        let result = read_frames(stream, |frame| frame_send.send(frame).is_ok());
+        if let Err(err) = result {
+            eprintln!("Spectating stopped- {}", err);
+        }
+    });
+
+    let mut spectator = Spectator::new();
+    let fps_throttler = Throttler::new(frame_duration(game.config.frame_rate));
+    let mut clip_recorder = ClipRecorder::new();
+    let mut profiler = Profiler::new();
+    #[cfg(feature = "debug-ui")]
+    let mut debug_ui = DebugUi::new();
+    let mut started = false;
+    loop {
+        let frame_time = Instant::now();
+        for sdl2_event in event_pump.poll_iter() {
+            if let Some(event) = keyboard::translate_event(sdl2_event, &mut game, &mut display) {
+                let input_action = game.input.handle_event(&mut game.settings, event, frame_time, &game.config);
+                if matches!(input_action, InputAction::Exit | InputAction::ForceExit | InputAction::Esc) {
+                    return Ok(());
+                } else if send_input && input_action != InputAction::None {
+                    writeln!(input_stream, "{}", input_action).map_err(|err| format!("Lost connection to {}: {}", address, err))?;
+                }
+            }
+        }
+
+        for frame in frame_recv.try_iter() {
+            for msg in spectator.receive(frame, &mut game)? {
+                game.msg_log.log(msg);
+            }
+            started = true;
+        }
+
+        // nothing is drawn until the first frame arrives with a game to draw
+        if started {
+            update_display(&mut game,
+                           &mut display,
+                           false,
+                           &mut clip_recorder,
+                           &mut profiler,
+                           #[cfg(feature = "debug-ui")] &mut debug_ui)?;
+        }
+        game.msg_log.clear();
+
+        fps_throttler.wait();
+    }
+}
+
 fn save_record(record_name: &str) {
     // create log directory if it doesn't exist
     let path = format!("resources/test_logs/{}", record_name);
@@ -327,23 +697,80 @@ fn save_record(record_name: &str) {
             .expect("Could not save map config!");
 }
 
-fn reload_config(config_modified_time: &mut SystemTime, game: &mut Game) {
-    /* Reload map if configured to do so */
-    if game.config.load_map_file_every_frame && Path::new("resources/map.xp").exists() {
-        let player = game.data.find_by_name(EntityName::Player).unwrap();
+/// Save the frames recorded as a GIF. The GIF is written in the background, and any
+/// problem writing it is printed rather than stopping the game.
+fn save_clip(clip_recorder: &ClipRecorder, config: &Config, log: &mut Log) {
+    if clip_recorder.frames.len() == 0 {
+        log.log_console("No frames recorded for a clip- is clip_seconds set in the config?");
+        return;
+    }
+
+    let file_name = timestamped_file_name(CLIP_DIR, "clip", "gif");
+    log.log_console(&format!("Saving clip {}", file_name));
+
+    let clip = clip_recorder.clone();
+    let frame_rate = config.frame_rate;
+    thread::spawn(move || {
+        if let Err(error) = clip.save_gif(&file_name, frame_rate) {
+            eprintln!("Could not save clip {}- {}", file_name, error);
+        }
+    });
+}
+
+/// A file name in the given directory which includes the current time, so each one is new.
+fn timestamped_file_name(dir: &str, name: &str, extension: &str) -> String {
+    let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+    return format!("{}/{}_{}_{:03}.{}", dir, name, now.as_secs(), now.subsec_millis(), extension);
+}
+
+fn frame_duration(frame_rate: usize) -> Duration {
+    return Duration::from_millis(1000 / frame_rate as u64);
+}
+
+fn reload_config(config_watcher: &mut ConfigWatcher, game: &mut Game, log: &mut Log, fps_throttler: &mut Throttler) {
+    match config_watcher.check(game) {
+        Some(ConfigChange::Reloaded(old_config)) => {
+            if old_config.frame_rate != game.config.frame_rate {
+                *fps_throttler = Throttler::new(frame_duration(game.config.frame_rate));
+            }
+        }
 
-        let map_file = format!("resources/{}", game.config.map_file);
-        game.data.entities.clear();
-        let player_pos = read_map_xp(&game.config, &mut game.data, &mut game.msg_log, &map_file);
-        game.data.entities.set_pos(player, Pos::from(player_pos));
+        // a config with a mistake in it is reported, and the last good config kept
+        Some(ConfigChange::Invalid(error)) => {
+            log.log_console(&format!("Config not reloaded- {}", error));
+        }
+
+        None => {
+        }
     }
+}
+
+/// Reload any sprite or map file that was added or modified since the last frame.
+fn reload_assets(asset_watcher: &mut AssetWatcher,
+                 game: &mut Game,
+                 display: &mut Display,
+                 log: &mut Log,
+                 texture_creator: &TextureCreator<WindowContext>) {
+    for event in asset_watcher.changes() {
+        match event {
+            AssetEvent::Sprite(path, sprite_name) => {
+                // a sprite saved part way through is reported, and loaded again when it is finished
+                if let Err(error) = reload_sprite(texture_creator, display, &path, &sprite_name) {
+                    log.log_console(&format!("Sprite {} not reloaded- {}", sprite_name, error));
+                }
+            }
+
+            AssetEvent::Map(map_file) => {
+                if game.config.reload_map_file {
+                    let player = game.data.find_by_name(EntityName::Player).unwrap();
 
-    /* Reload Configuration */
-    if let Ok(current_config_modified_time) = fs::metadata(CONFIG_NAME) {
-        let current_config_modified_time = current_config_modified_time.modified().unwrap();
-        if current_config_modified_time != *config_modified_time {
-            *config_modified_time = current_config_modified_time;
-            game.config = Config::from_file(CONFIG_NAME);
+                    game.data.entities.clear();
+                    match read_map_xp(&game.config, &mut game.data, &mut game.msg_log, &map_file) {
+                        Ok(player_pos) => game.data.entities.set_pos(player, Pos::from(player_pos)),
+                        Err(error) => log.log_console(&format!("Map {} not reloaded- {}", map_file, error)),
+                    }
+                }
+            }
         }
     }
 }
@@ -359,7 +786,12 @@ pub fn take_screenshot(game: &mut Game, display: &mut Display) -> Result<(), Str
     return Ok(());
 }
 
-fn update_display(game: &mut Game, display: &mut Display) -> Result<(), String> {
+fn update_display(game: &mut Game,
+                  display: &mut Display,
+                  screenshot: bool,
+                  clip_recorder: &mut ClipRecorder,
+                  profiler: &mut Profiler,
+                  #[cfg(feature = "debug-ui")] debug_ui: &mut DebugUi) -> Result<(), String> {
     for msg in game.msg_log.turn_messages.iter() {
         display.process_message(*msg, &mut game.data, &game.config);
     }
@@ -367,6 +799,28 @@ fn update_display(game: &mut Game, display: &mut Display) -> Result<(), String>
     /* Draw the Game to the Screen */
     render_all(display, game)?;
 
+    profiler.draw_calls = display.state.take_draw_calls();
+    if game.settings.perf_overlay {
+        render_perf_overlay(display, game, &profiler.lines(game.data.entities.ids.len(), game.data.ai_budget.num_trips()));
+    }
+
+    #[cfg(feature = "debug-ui")]
+    debug_ui.render(display, game)?;
+
+    if screenshot {
+        let file_name = timestamped_file_name(SCREENSHOT_DIR, "screenshot", "png");
+        if let Err(error) = display.save_screenshot_png(&file_name) {
+            eprintln!("Could not save screenshot {}- {}", file_name, error);
+        }
+    }
+
+    if ClipRecorder::max_frames(&game.config) > 0 {
+        let (pixels, width, height) = display.read_canvas()?;
+        clip_recorder.record(&pixels, width, height, &game.config);
+    } else {
+        clip_recorder.frames.clear();
+    }
+
     display.update_display();
 
     return Ok(());
@@ -0,0 +1,106 @@
+use std::time::Instant;
+
+
+// how much each new measurement moves the averages shown, so they are readable rather than flickering
+const PROFILE_SMOOTHING: f32 = 0.1;
+
+/// The parts of each frame which are timed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Input,
+    Logic,
+    Display,
+    Wait,
+}
+
+impl Phase {
+    pub fn phases() -> [Phase; 4] {
+        return [Phase::Input, Phase::Logic, Phase::Display, Phase::Wait];
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Input => "input",
+            Phase::Logic => "logic",
+            Phase::Display => "display",
+            Phase::Wait => "wait",
+        }
+    }
+}
+
+/// Where the time goes each frame, along with how much was drawn, for the
+/// performance overlay. Times are averaged over recent frames, in milliseconds.
+#[derive(Clone, Debug)]
+pub struct Profiler {
+    pub phase_ms: [f32; 4],
+    pub frame_ms: f32,
+    pub draw_calls: usize,
+    frame_start: Instant,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        return Profiler { phase_ms: [0.0; 4], frame_ms: 0.0, draw_calls: 0, frame_start: Instant::now() };
+    }
+
+    /// Record the time spent in a phase which started at the given time.
+    pub fn record(&mut self, phase: Phase, start: Instant) {
+        let index = Phase::phases().iter().position(|other| *other == phase).unwrap();
+        let ms = start.elapsed().as_secs_f32() * 1000.0;
+        self.phase_ms[index] = smooth(self.phase_ms[index], ms);
+    }
+
+    /// Record the time since the last frame ended.
+    pub fn end_frame(&mut self) {
+        let ms = self.frame_start.elapsed().as_secs_f32() * 1000.0;
+        self.frame_ms = smooth(self.frame_ms, ms);
+        self.frame_start = Instant::now();
+    }
+
+    pub fn fps(&self) -> f32 {
+        if self.frame_ms <= 0.0 {
+            return 0.0;
+        }
+        return 1000.0 / self.frame_ms;
+    }
+
+    /// The lines of text shown in the performance overlay.
+    pub fn lines(&self, num_entities: usize, ai_trips: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        lines.push(format!("fps {:.1} ({:.1} ms)", self.fps(), self.frame_ms));
+        for (phase, ms) in Phase::phases().iter().zip(self.phase_ms.iter()) {
+            lines.push(format!("{:<8}{:.2} ms", phase.name(), ms));
+        }
+        lines.push(format!("entities {}", num_entities));
+        lines.push(format!("draws {}", self.draw_calls));
+        lines.push(format!("ai trips {}", ai_trips));
+
+        return lines;
+    }
+}
+
+fn smooth(average: f32, value: f32) -> f32 {
+    return average + (value - average) * PROFILE_SMOOTHING;
+}
+
+#[test]
+pub fn test_profiler() {
+    let mut profiler = Profiler::new();
+    profiler.frame_ms = 40.0;
+    profiler.phase_ms = [1.0, 2.0, 3.0, 34.0];
+    profiler.draw_calls = 120;
+
+    assert_eq!(25.0, profiler.fps());
+
+    let lines = profiler.lines(7, 2);
+    assert_eq!("fps 25.0 (40.0 ms)", lines[0]);
+    assert_eq!("logic   2.00 ms", lines[2]);
+    assert_eq!("entities 7", lines[5]);
+    assert_eq!("draws 120", lines[6]);
+    assert_eq!("ai trips 2", lines[7]);
+
+    // a new time moves the average toward it, rather than replacing it
+    profiler.record(Phase::Input, Instant::now());
+    assert!(profiler.phase_ms[0] < 1.0 && profiler.phase_ms[0] > 0.8);
+}
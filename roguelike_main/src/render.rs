@@ -1,7 +1,7 @@
 use oorandom::Rand32;
 
 use sdl2::render::{BlendMode, WindowCanvas};
-use sdl2::rect::Rect;
+use sdl2::rect::{Rect, Point};
 use sdl2::pixels::{Color as Sdl2Color};
 
 use roguelike_core::types::*;
@@ -9,12 +9,23 @@ use roguelike_core::map::*;
 use roguelike_core::constants::*;
 use roguelike_core::movement::*;
 use roguelike_core::config::*;
-use roguelike_core::utils::{item_primary_at, lerp_color, sub_pos, reach_by_mode, map_fill_metric};
+use roguelike_core::utils::{lerp_color, sub_pos, reach_by_mode, map_fill_metric};
 use roguelike_core::perlin::Perlin;
 use roguelike_core::line::line;
 use roguelike_core::ai::*;
 
 use roguelike_engine::game::*;
+use roguelike_engine::sandbox::PALETTE;
+use roguelike_engine::objective::describe_objective;
+use roguelike_engine::menu::{MAIN_MENU_ITEMS, PAUSE_MENU_ITEMS, OPTIONS_ITEMS, GAME_OVER_ITEMS};
+use roguelike_engine::help::help_lines;
+use roguelike_engine::hint::action_hints;
+use roguelike_engine::inspector::inspect_entity;
+use roguelike_engine::minimap::{minimap_cells, MinimapCell};
+use roguelike_engine::overlay::{Overlay, Alertness, enemy_vision, enemy_threat};
+use roguelike_engine::schedule::turn_forecast;
+use roguelike_engine::container;
+use roguelike_engine::shop;
 
 use crate::display::*;
 use crate::animation::{Sprite, Effect, Animation, AnimationResult};
@@ -22,11 +33,23 @@ use crate::animation::{Sprite, Effect, Animation, AnimationResult};
 
 // 10ms to display on last check
 pub fn render_all(display: &mut Display, game: &mut Game)  -> Result<(), String> {
-    display.targets.canvas_panel.target.set_draw_color(Sdl2Color::RGB(0, 0, 0));
+    display.targets.canvas_panel.target.set_draw_color(sdl2_color(game.config.color_background));
     display.targets.canvas_panel.target.clear();
 
+    // the background is only drawn again when it changes, which includes changing palettes
+    if display.state.color_palette != game.settings.color_palette {
+        display.state.color_palette = game.settings.color_palette;
+        display.targets.background_panel.dirty = true;
+    }
+
     display.state.update_animations(game.settings.dt);
 
+    // a map or asset which failed to load may leave no map to draw, so only the error is shown
+    if game.settings.state == GameState::Error {
+        render_menus(display, game);
+        return Ok(());
+    }
+
     /* Split Screen Into Sections */
     let map_rect = display.targets.canvas_panel.get_rect_from_area(&display.targets.map_area);
 
@@ -37,8 +60,19 @@ pub fn render_all(display: &mut Display, game: &mut Game)  -> Result<(), String>
     render_panels(display, game, map_rect);
 
     /* Paste Panels on Screen */
-    let map_size = game.data.map.size();
-    render_screen(&mut display.targets, map_size, map_rect);
+    display.state.update_zoom(game.settings.dt);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let map_view = MapView::new(game.data.map.size(), map_rect, display.state.zoom, game.data.entities.pos[&player_id]);
+    display.state.map_view = Some(map_view);
+    render_screen(&mut display.targets, map_view);
+
+    if game.settings.minimap && game.settings.render_map {
+        render_minimap(&mut display.targets, game, map_rect);
+    }
+
+    if game.settings.render_map && game.settings.state == GameState::Playing {
+        render_quick_slots(display, game);
+    }
 
     /* Draw Menus */
     render_menus(display, game);
@@ -58,7 +92,7 @@ fn render_panels(display: &mut Display, game: &mut Game, _map_rect: Rect) {
         let panel = background.unit();
 
         canvas.with_texture_canvas(&mut display.targets.map_panel.target, |canvas| {
-            canvas.set_draw_color(Sdl2Color::RGB(0, 0, 0));
+            canvas.set_draw_color(sdl2_color(game.config.color_background));
             canvas.clear();
 
             canvas.copy(&background.target, None, None).unwrap();
@@ -72,7 +106,9 @@ fn render_panels(display: &mut Display, game: &mut Game, _map_rect: Rect) {
             render_entity_type(EntityType::Enemy, &mut panel, display_state, game);
             render_entity_type(EntityType::Column, &mut panel, display_state, game);
             render_entity_type(EntityType::Player, &mut panel, display_state, game);
+            render_ghost(&mut panel, display_state, game);
             render_entity_type(EntityType::Other, &mut panel, display_state, game);
+            render_gas(&mut panel, game);
             render_impressions(&mut panel, display_state, game);
             render_effects(&mut panel, display_state, game);
             render_overlays(&mut panel, display_state, game, mouse_map_pos);
@@ -97,7 +133,11 @@ fn render_panels(display: &mut Display, game: &mut Game, _map_rect: Rect) {
 
         canvas.with_texture_canvas(&mut display.targets.inventory_panel.target, |canvas| {
             let mut panel = panel.with_target(canvas);
-            render_inventory(&mut panel, display_state, game);
+            if game.settings.state == GameState::Sandbox {
+                render_palette(&mut panel, display_state, game);
+            } else {
+                render_inventory(&mut panel, display_state, game);
+            }
         }).unwrap();
     }
 
@@ -114,11 +154,11 @@ fn render_panels(display: &mut Display, game: &mut Game, _map_rect: Rect) {
 }
 
 
-fn render_screen(targets: &mut DisplayTargets, map_size: (i32, i32), map_rect: Rect) {
+fn render_screen(targets: &mut DisplayTargets, map_view: MapView) {
     // TODO just make the map panel the right size in the first place
     // and re-create it when the map changes.
-    let src = targets.map_panel.get_rect_up_left(map_size.0 as usize, map_size.1 as usize);
-    targets.canvas_panel.target.copy(&targets.map_panel.target, src, map_rect).unwrap();
+    let src = map_view.src_rect(targets.map_panel.cell_dims());
+    targets.canvas_panel.target.copy(&targets.map_panel.target, src, map_view.dst).unwrap();
 
     /* Draw Inventory Panel */
     let dst = targets.canvas_panel.get_rect_within(&targets.inventory_area,
@@ -136,6 +176,106 @@ fn render_screen(targets: &mut DisplayTargets, map_size: (i32, i32), map_rect: R
     targets.canvas_panel.target.copy(&targets.player_panel.target, None, dst).unwrap();
 }
 
+/// Draw the explored part of the map at one pixel per tile in the top right corner of
+/// the map area, with the player, the exit, and the key once they have been seen.
+fn render_minimap(targets: &mut DisplayTargets, game: &mut Game, map_rect: Rect) {
+    let (map_width, map_height) = game.data.map.size();
+    let x_offset = map_rect.x() + map_rect.width() as i32 - map_width - MINIMAP_MARGIN;
+    let y_offset = map_rect.y() + MINIMAP_MARGIN;
+
+    let canvas = &mut targets.canvas_panel.target;
+
+    let outline = Rect::new(x_offset - 1, y_offset - 1, map_width as u32 + 2, map_height as u32 + 2);
+    canvas.set_draw_color(sdl2_color(game.config.color_background));
+    canvas.fill_rect(outline).unwrap();
+    canvas.set_draw_color(sdl2_color(game.config.color_bar_outline));
+    canvas.draw_rect(outline).unwrap();
+
+    for (pos, cell) in minimap_cells(&game.data) {
+        let color =
+            match cell {
+                MinimapCell::Floor => game.config.color_medium_grey,
+                MinimapCell::Wall => game.config.color_light_grey,
+                MinimapCell::Water => game.config.color_water,
+                MinimapCell::Exit => game.config.color_orange,
+                MinimapCell::Key => game.config.color_red,
+                MinimapCell::Player => game.config.color_mint_green,
+            };
+
+        canvas.set_draw_color(sdl2_color(color));
+        canvas.draw_point(Point::new(x_offset + pos.x, y_offset + pos.y)).unwrap();
+    }
+}
+
+/// Draw the hotbar along the bottom of the map. Item slots show how many of that item
+/// the player has, and slots which can't be used right now are greyed out.
+fn render_quick_slots(display: &mut Display, game: &mut Game) {
+    let canvas_panel = &mut display.targets.canvas_panel;
+    let panel = canvas_panel.with_target(());
+    let mut panel = panel.with_target(&mut canvas_panel.target);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let energy = game.data.entities.energy[&player_id];
+
+    let mut slots: Vec<(String, Color)> = Vec::new();
+    for (index, quick_slot) in game.settings.quick_slots.iter().enumerate() {
+        let (text, usable) =
+            match quick_slot {
+                Some(QuickSlot::Item(item)) => {
+                    let count = game.data.entities.inventory[&player_id].iter()
+                                    .filter(|item_id| game.data.entities.item.get(item_id) == Some(item))
+                                    .count();
+                    (format!("{} {} x{}", index + 1, item, count), count > 0)
+                }
+
+                Some(QuickSlot::Skill(skill)) => {
+                    let cooldown = game.data.entities.skill_cooldown(player_id, *skill);
+                    (format!("{} {:?}", index + 1, skill), cooldown == 0 && energy >= skill.energy_cost())
+                }
+
+                None => {
+                    (format!("{} -", index + 1), false)
+                }
+            };
+
+        let color =
+            if usable {
+                game.config.color_light_grey
+            } else {
+                game.config.color_medium_grey
+            };
+        slots.push((text, color));
+    }
+
+    let (cell_width, cell_height) = panel.cell_dims();
+    let y_pos = panel.cells.1 as i32 - 2;
+    let width = slots.iter().map(|(text, _color)| text.len() + 2).sum::<usize>() as u32 + 1;
+    let background = Rect::new(0, y_pos * cell_height as i32, width * cell_width, 2 * cell_height);
+    panel.target.set_draw_color(sdl2_color(game.config.color_background));
+    panel.target.fill_rect(background).unwrap();
+
+    let mut x_pos = 1;
+    for (text, color) in slots.iter() {
+        display.state.text.draw_text(&mut panel, text, Pos::new(x_pos, y_pos), *color);
+        x_pos += text.len() as i32 + 2;
+    }
+}
+
+/// Draw the performance overlay's lines in the top left of the screen.
+pub fn render_perf_overlay(display: &mut Display, game: &mut Game, lines: &Vec<String>) {
+    let canvas_panel = &mut display.targets.canvas_panel;
+    let panel = canvas_panel.with_target(());
+    let mut panel = panel.with_target(&mut canvas_panel.target);
+
+    let (cell_width, cell_height) = panel.cell_dims();
+    let width = lines.iter().map(|line| line.len()).max().unwrap_or(0) as u32 + 2;
+    let background = Rect::new(0, 0, width * cell_width, (lines.len() as u32 + 2) * cell_height);
+    panel.target.set_draw_color(sdl2_color(game.config.color_background));
+    panel.target.fill_rect(background).unwrap();
+
+    display.state.text.draw_text_list(&mut panel, lines, Pos::new(1, 1), game.config.color_light_grey);
+}
+
 fn render_menus(display: &mut Display, game: &mut Game) {
     let canvas_panel = &mut display.targets.canvas_panel;
     let display_state = &mut display.state;
@@ -154,8 +294,32 @@ fn render_menus(display: &mut Display, game: &mut Game) {
             render_skill_menu(&mut panel, display_state, game);
         } else if game.settings.state == GameState::ClassMenu {
             render_class_menu(&mut panel, display_state, game);
-        } else if game.settings.state == GameState::ConfirmQuit {
-            render_confirm_quit(&mut panel, display_state, game);
+        } else if game.settings.state == GameState::Prompt {
+            render_prompt(&mut panel, display_state, game);
+        } else if game.settings.state == GameState::Dialogue {
+            render_dialogue(&mut panel, display_state, game);
+        } else if game.settings.state == GameState::Container {
+            render_container(&mut panel, display_state, game);
+        } else if game.settings.state == GameState::Shop {
+            render_shop(&mut panel, display_state, game);
+        } else if game.settings.state == GameState::Win || game.settings.state == GameState::Lose {
+            render_game_over(&mut panel, display_state, game);
+        } else if game.settings.state == GameState::Loading {
+            render_loading(&mut panel, display_state, game);
+        } else if game.settings.state == GameState::LevelComplete {
+            render_level_complete(&mut panel, display_state, game);
+        } else if game.settings.state == GameState::NewGame {
+            render_new_game_menu(&mut panel, display_state, game);
+        } else if game.settings.state == GameState::MainMenu {
+            render_main_menu(&mut panel, display_state, game);
+        } else if game.settings.state == GameState::Paused {
+            render_pause_menu(&mut panel, display_state, game);
+        } else if game.settings.state == GameState::Options {
+            render_options(&mut panel, display_state, game);
+        } else if game.settings.state == GameState::Help {
+            render_help(&mut panel, display_state, game);
+        } else if game.settings.state == GameState::Error {
+            render_error(&mut panel, display_state, game);
         } else {
             draw_menu = false;
         }
@@ -174,8 +338,8 @@ fn render_placard(panel: &mut Panel<&mut WindowCanvas>,
                   config: &Config) {
     let color = config.color_mint_green;
     
-    // Draw a black background
-    panel.target.set_draw_color(Sdl2Color::RGBA(0, 0, 0, 255));
+    // Draw the background
+    panel.target.set_draw_color(sdl2_color(config.color_background));
     panel.target.clear();
     let (width, height) = panel.target.output_size().unwrap();
     let (cell_width, cell_height) = panel.cell_dims();
@@ -202,10 +366,7 @@ fn render_placard(panel: &mut Panel<&mut WindowCanvas>,
 
     let text_pos = Pos::new(text_start, 0);
 
-    let sprite_key = display_state.lookup_spritekey("tiles");
-    let tile_sprite = &mut display_state.sprites[&sprite_key];
-
-    tile_sprite.draw_text(panel, &text, text_pos, config.color_dark_blue);
+    display_state.text.draw_text(panel, &text, text_pos, config.color_dark_blue);
 }
 
 fn render_pips(panel: &mut Panel<&mut WindowCanvas>,
@@ -292,12 +453,17 @@ fn render_player_info(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut
         };
         let health_percent = hp as f32 / fighter.max_hp as f32;
 
-        render_bar(panel, display_state, health_percent, 2, game.config.color_red, Color::white());
+        render_bar(panel, display_state, health_percent, 2, game.config.color_red, game.config.color_bar_outline);
     }
 
     let energy = game.data.entities.energy[&player_id];
     render_pips(panel, display_state, energy, 3, game.config.color_light_green);
 
+    if let Some(stamina) = game.data.entities.stamina.get(&player_id) {
+        let stamina_percent = *stamina as f32 / game.config.player_stamina as f32;
+        render_bar(panel, display_state, stamina_percent, 4, game.config.color_light_orange, game.config.color_bar_outline);
+    }
+
     list.push(format!(" ({}, {})", 
                       game.data.entities.pos[&player_id].x,
                       game.data.entities.pos[&player_id].y));
@@ -310,6 +476,12 @@ fn render_player_info(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut
     let stance = game.data.entities.stance[&player_id];
     list.push(format!("{}", stance));
 
+    // the held items are drawn separately below, so they can animate when swapped
+    list.push(format!(""));
+    let hands_line = list.len();
+    list.push(format!(""));
+    list.push(format!(""));
+
     list.push(format!(""));
     if let Some(class) = game.data.entities.class.get(&player_id) {
         list.push(format!("{}", class));
@@ -319,18 +491,82 @@ fn render_player_info(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut
 
     list.push(format!(""));
     list.push(format!("turn {}", game.settings.turn_count));
+    list.push(format!("coins {}", game.data.entities.coins.get(&player_id).map_or(0, |coins| *coins)));
+
+    list.push(format!(""));
+    list.extend(describe_objective(&game.data));
 
     let text_pos = Pos::new(1, 5);
+    display_state.text.draw_text_list(panel, &list, text_pos, color);
 
-    let sprite_key = display_state.lookup_spritekey("tiles");
-    let tile_sprite = &mut display_state.sprites[&sprite_key];
-    tile_sprite.draw_text_list(panel, &list, text_pos, color);
+    render_held_items(panel, display_state, game, player_id, Pos::new(1, 5 + hands_line as i32));
+}
+
+/// Draw the items in the player's hands. When they are swapped, the old items fade
+/// out of their hands and fade back in having traded places.
+fn render_held_items(panel: &mut Panel<&mut WindowCanvas>,
+                     display_state: &mut DisplayState,
+                     game: &mut Game,
+                     player_id: EntityId,
+                     pos: Pos) {
+    let progress = 1.0 - display_state.hand_swap / HAND_SWAP_SECONDS;
+
+    let mut held = [game.data.entities.held_item(player_id, Hand::Primary),
+                    game.data.entities.held_item(player_id, Hand::Secondary)];
+    let fade;
+    if progress < 0.5 {
+        held.swap(0, 1);
+        fade = progress * 2.0;
+    } else {
+        fade = 1.0 - (progress - 0.5) * 2.0;
+    }
+    let color = lerp_color(game.config.color_soft_green, game.config.color_background, fade);
+
+    for (index, hand) in [Hand::Primary, Hand::Secondary].iter().enumerate() {
+        let item_name =
+            match held[index] {
+                Some(item_id) => game.locale.name(game.data.entities.name[&item_id]),
+                None => "-".to_string(),
+            };
+
+        let text = format!("{} {}", hand, item_name);
+        display_state.text.draw_text(panel, &text, Pos::new(pos.x, pos.y + index as i32), color);
+    }
+}
+
+/// List the components of the entity clicked on in god mode, along with the animations
+/// queued up for it. This is drawn each frame, so it follows the entity as it changes.
+fn render_inspector(panel: &mut Panel<&mut WindowCanvas>,
+                    display_state: &mut DisplayState,
+                    game: &mut Game,
+                    entity_id: EntityId) {
+    render_placard(panel, display_state, "Inspector", &game.config);
+
+    let mut text_list = inspect_entity(entity_id, &game.data);
+
+    if let Some(animations) = display_state.animations.get(&entity_id) {
+        if animations.len() > 0 {
+            text_list.push("animations".to_string());
+            for animation in animations.iter() {
+                text_list.push(format!("  {}", animation.description()));
+            }
+        }
+    }
+
+    let text_pos = Pos::new(1, 1);
+    let text_color = game.config.color_soft_green;
+    display_state.text.draw_text_list(panel, &text_list, text_pos, text_color);
 }
 
 fn render_info(panel: &mut Panel<&mut WindowCanvas>,
                display_state: &mut DisplayState,
                game: &mut Game,
                _mouse_xy: Option<Pos>) {
+    if let (true, Some(entity_id)) = (game.settings.god_mode, game.settings.inspecting) {
+        render_inspector(panel, display_state, game, entity_id);
+        return;
+    }
+
     render_placard(panel, display_state, "Info", &game.config);
 
     if let Some(info_pos) = game.settings.cursor {
@@ -348,12 +584,7 @@ fn render_info(panel: &mut Panel<&mut WindowCanvas>,
 
         let text_pos = Pos::new(1, y_pos);
 
-        let sprite_key = display_state.lookup_spritekey("tiles");
-
-        {
-            let tile_sprite = &mut display_state.sprites[&sprite_key];
-            tile_sprite.draw_text_list(panel, &text_list, text_pos, text_color);
-        }
+        display_state.text.draw_text_list(panel, &text_list, text_pos, text_color);
         text_list.clear();
 
         y_pos += 1;
@@ -375,7 +606,7 @@ fn render_info(panel: &mut Panel<&mut WindowCanvas>,
                                health_percent,
                                y_pos,
                                game.config.color_red,
-                               Color::white());
+                               game.config.color_bar_outline);
 
                     y_pos += 2;
                 }
@@ -401,9 +632,8 @@ fn render_info(panel: &mut Panel<&mut WindowCanvas>,
             }
         }
 
-        let tile_sprite = &mut display_state.sprites[&sprite_key];
         let text_pos = Pos::new(1, y_pos);
-        tile_sprite.draw_text_list(panel, &text_list, text_pos, text_color);
+        display_state.text.draw_text_list(panel, &text_list, text_pos, text_color);
         text_list.push(format!(""));
         text_list.clear();
 
@@ -430,8 +660,33 @@ fn render_info(panel: &mut Panel<&mut WindowCanvas>,
                 text_list.push(format!("blocked"));
             }
 
-            tile_sprite.draw_text_list(panel, &text_list, text_pos, text_color);
+            display_state.text.draw_text_list(panel, &text_list, text_pos, text_color);
+        }
+    } else {
+        // outside of cursor mode, show what can be done from where the player is standing
+        let hints = action_hints(&game.data);
+
+        let text_pos = Pos::new(1, 1);
+        let text_color = game.config.color_soft_green;
+        display_state.text.draw_text_list(panel, &hints, text_pos, text_color);
+
+        // below the hints, forecast the order the player and the entities in view will act in
+        let mut forecast_lines = Vec::new();
+        for (turns_ahead, order) in turn_forecast(&game.data, &game.config, FORECAST_TURNS).iter().enumerate() {
+            let names = order.iter()
+                             .map(|entity_id| game.locale.name(game.data.entities.name[entity_id]))
+                             .collect::<Vec<String>>();
+
+            match turns_ahead {
+                0 => forecast_lines.push("This turn".to_string()),
+                1 => forecast_lines.push("Next turn".to_string()),
+                _ => forecast_lines.push(format!("In {} turns", turns_ahead)),
+            }
+            forecast_lines.push(format!("  {}", names.join(", ")));
         }
+
+        let text_pos = Pos::new(1, MAX_HINTS as i32 + 2);
+        display_state.text.draw_text_list(panel, &forecast_lines, text_pos, text_color);
     }
 }
 
@@ -441,20 +696,29 @@ fn render_skill_menu(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut D
     // Render header
     render_placard(panel, display_state, "Skills", &game.config);
 
-    let mut list = Vec::new();
+    let energy = game.data.entities.energy[&player_id];
 
+    // each skill is listed with its cost, and greyed out while it can't be used
+    let mut y_pos = 2;
     for (index, skill) in game.data.entities.skills[&player_id].iter().enumerate() {
-        list.push(format!("{} {:?}", index, skill));
-    }
+        let cooldown = game.data.entities.skill_cooldown(player_id, *skill);
 
-    let y_pos = 2;
-    let text_pos = Pos::new(2, y_pos);
-    let color = game.config.color_light_grey;
+        let mut text = format!("{} {:?} ({} energy)", index, skill, skill.energy_cost());
+        if cooldown > 0 {
+            text.push_str(&format!(" {} turns", cooldown));
+        }
 
-    let sprite_key = display_state.lookup_spritekey("tiles");
-    let tile_sprite = &mut display_state.sprites[&sprite_key];
+        let color =
+            if cooldown > 0 || energy < skill.energy_cost() {
+                game.config.color_medium_grey
+            } else {
+                game.config.color_light_grey
+            };
+
+        display_state.text.draw_text(panel, &text, Pos::new(2, y_pos), color);
 
-    tile_sprite.draw_text_list(panel, &list, text_pos, color);
+        y_pos += 1;
+    }
 }
 
 fn render_class_menu(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
@@ -471,30 +735,415 @@ fn render_class_menu(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut D
     let text_pos = Pos::new(2, y_pos);
     let color = game.config.color_light_grey;
 
-    let sprite_key = display_state.lookup_spritekey("tiles");
-    let tile_sprite = &mut display_state.sprites[&sprite_key];
+    display_state.text.draw_text_list(panel, &list, text_pos, color);
+}
+
+fn render_new_game_menu(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+    // Render header
+    render_placard(panel, display_state, "Choose Loadout", &game.config);
+
+    let mut list = Vec::new();
+
+    for (index, loadout) in game.loadouts.iter().enumerate() {
+        if !loadout.is_available(&game.unlocks) {
+            list.push(format!("{} {} (locked)", index, loadout.name));
+            list.push(format!("  {}", loadout.unlock.unwrap().description()));
+            list.push("".to_string());
+            continue;
+        }
+
+        list.push(format!("{} {} ({})", index, loadout.name, loadout.class));
+
+        let items = loadout.items.iter().map(|item| item.to_string()).collect::<Vec<String>>();
+        list.push(format!("  {} hp, {} energy", loadout.hp, loadout.energy));
+        list.push(format!("  {}", items.join(", ")));
+        list.push("".to_string());
+    }
+
+    let y_pos = 2;
+    let text_pos = Pos::new(2, y_pos);
+    let color = game.config.color_light_grey;
+
+    display_state.text.draw_text_list(panel, &list, text_pos, color);
+}
+
+fn render_main_menu(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+    // Render header
+    render_placard(panel, display_state, "Rust Roguelike", &game.config);
+
+    let mut list = Vec::new();
+
+    if let Some(seed_entry) = &game.settings.seed_entry {
+        list.push("seed:".to_string());
+        list.push(format!("{}_", seed_entry));
+        list.push("".to_string());
+        list.push("enter: start".to_string());
+        list.push("esc: back".to_string());
+    } else {
+        for (index, item) in MAIN_MENU_ITEMS.iter().enumerate() {
+            list.push(format!("{} {}", index, game.locale.text(item)));
+        }
+        list.push("".to_string());
+        list.push(format!("difficulty {}", game.settings.difficulty));
+    }
+
+    let y_pos = 2;
+    let text_pos = Pos::new(2, y_pos);
+    let color = game.config.color_light_grey;
+
+    display_state.text.draw_text_list(panel, &list, text_pos, color);
+}
+
+fn render_pause_menu(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+    // Render header
+    render_placard(panel, display_state, "Paused", &game.config);
+
+    let mut list = Vec::new();
+
+    for (index, item) in PAUSE_MENU_ITEMS.iter().enumerate() {
+        list.push(format!("{} {}", index, game.locale.text(item)));
+    }
+
+    let y_pos = 2;
+    let text_pos = Pos::new(2, y_pos);
+    let color = game.config.color_light_grey;
 
-    tile_sprite.draw_text_list(panel, &list, text_pos, color);
+    display_state.text.draw_text_list(panel, &list, text_pos, color);
 }
 
-fn render_confirm_quit(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+fn render_options(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
     // Render header
-    render_placard(panel, display_state, "Quit?", &game.config);
+    render_placard(panel, display_state, "Options", &game.config);
 
     let mut list = Vec::new();
 
-    list.push("q: quit game".to_string());
+    for (index, item) in OPTIONS_ITEMS.iter().enumerate() {
+        list.push(format!("{} {}", index, game.locale.text(item)));
+    }
+    list.push("".to_string());
+
+    list.push(format!("volume {}/{}", game.settings.volume, MAX_VOLUME));
+    list.push(format!("overlay {}", game.settings.overlays));
+    list.push(format!("map {}", if game.settings.render_map { "on" } else { "off" }));
+    if let Some(palette) = game.color_palettes().get(game.settings.color_palette) {
+        list.push(format!("palette {}", palette.name));
+    }
     list.push("".to_string());
-    list.push("esc: continue".to_string());
+
+    list.push("F1 or ?: keys and help".to_string());
 
     let y_pos = 2;
     let text_pos = Pos::new(2, y_pos);
     let color = game.config.color_light_grey;
 
-    let sprite_key = display_state.lookup_spritekey("tiles");
-    let tile_sprite = &mut display_state.sprites[&sprite_key];
+    display_state.text.draw_text_list(panel, &list, text_pos, color);
+}
+
+fn render_help(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+    // Render header
+    render_placard(panel, display_state, "Help", &game.config);
+
+    let mut list = Vec::new();
+
+    // lines are scrolled through whole, so a long line never starts part way down the screen
+    for line in help_lines(&game.config).iter().skip(game.settings.help_scroll) {
+        let wrapped = wrap_text(line, PROMPT_WIDTH);
+        if list.len() + wrapped.len() > HELP_LINES {
+            break;
+        }
+
+        if wrapped.len() == 0 {
+            list.push("".to_string());
+        }
+        list.extend(wrapped);
+    }
+    list.push("".to_string());
+    list.push("8/2: scroll, esc: back".to_string());
+
+    let y_pos = 2;
+    let text_pos = Pos::new(2, y_pos);
+    let color = game.config.color_light_grey;
+
+    display_state.text.draw_text_list(panel, &list, text_pos, color);
+}
+
+fn render_prompt(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+    // Render header
+    render_placard(panel, display_state, "Are you sure?", &game.config);
+
+    let mut list = Vec::new();
+
+    if let Some(prompt) = &game.settings.prompt {
+        list.extend(wrap_text(&prompt.question, PROMPT_WIDTH));
+        list.push("".to_string());
 
-    tile_sprite.draw_text_list(panel, &list, text_pos, color);
+        for (index, (text, _action)) in prompt.options.iter().enumerate() {
+            list.push(format!("{}: {}", index, text));
+        }
+    }
+    list.push("esc: cancel".to_string());
+
+    let y_pos = 2;
+    let text_pos = Pos::new(2, y_pos);
+    let color = game.config.color_light_grey;
+
+    display_state.text.draw_text_list(panel, &list, text_pos, color);
+}
+
+fn render_dialogue(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+    let mut list = Vec::new();
+
+    if let Some(conversation) = game.settings.conversation {
+        // Render header
+        let speaker = format!("{:?}", game.data.entities.name[&conversation.speaker]);
+        render_placard(panel, display_state, &speaker, &game.config);
+
+        let node = &game.dialogue[conversation.node];
+        list.extend(wrap_text(&node.text, PROMPT_WIDTH));
+        list.push("".to_string());
+
+        for (index, response) in node.responses.iter().enumerate() {
+            let mut lines = wrap_text(&response.text, PROMPT_WIDTH - 3).into_iter();
+            if let Some(first) = lines.next() {
+                list.push(format!("{}: {}", index, first));
+            }
+            for line in lines {
+                list.push(format!("   {}", line));
+            }
+        }
+    }
+    list.push("esc: leave".to_string());
+
+    let y_pos = 2;
+    let text_pos = Pos::new(2, y_pos);
+    let color = game.config.color_light_grey;
+
+    display_state.text.draw_text_list(panel, &list, text_pos, color);
+}
+
+fn render_container(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+    let mut list = Vec::new();
+
+    if let Some(container_id) = game.settings.container {
+        // Render header
+        let name = game.locale.name(game.data.entities.name[&container_id]);
+        render_placard(panel, display_state, &name, &game.config);
+
+        let options = container::transfer_options(container_id, game);
+        if options.len() == 0 {
+            list.push("empty".to_string());
+        }
+        for (index, option) in options.iter().enumerate() {
+            list.push(format!("{}: {}", index, option));
+        }
+        list.push("".to_string());
+    }
+    list.push("esc: close".to_string());
+
+    let y_pos = 2;
+    let text_pos = Pos::new(2, y_pos);
+    let color = game.config.color_light_grey;
+
+    display_state.text.draw_text_list(panel, &list, text_pos, color);
+}
+
+fn render_shop(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+    let mut list = Vec::new();
+
+    // Render header
+    render_placard(panel, display_state, "Shop", &game.config);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let coins = game.data.entities.coins.get(&player_id).map_or(0, |coins| *coins);
+    list.push(format!("coins: {}", coins));
+    list.push("".to_string());
+
+    for (index, option) in shop::shop_options(game).iter().enumerate() {
+        list.push(format!("{}: {}", index, option));
+    }
+    list.push("".to_string());
+    list.push("esc: leave".to_string());
+
+    let y_pos = 2;
+    let text_pos = Pos::new(2, y_pos);
+    let color = game.config.color_light_grey;
+
+    display_state.text.draw_text_list(panel, &list, text_pos, color);
+}
+
+/// Split text into lines of at most the given width, breaking between words.
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if line.len() > 0 && line.len() + word.len() + 1 > width {
+            lines.push(line);
+            line = String::new();
+        }
+
+        if line.len() > 0 {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+
+    return lines;
+}
+
+fn render_game_over(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+    // Render header
+    if game.settings.state == GameState::Win {
+        render_placard(panel, display_state, "Escaped!", &game.config);
+    } else {
+        render_placard(panel, display_state, "Caught", &game.config);
+    }
+
+    let mut list = Vec::new();
+
+    if let Some(cause) = &game.settings.death_cause {
+        list.push(format!("killed by {}", cause));
+    }
+
+    if let Some(entry) = &game.settings.last_score {
+        list.push(format!("score {}", entry.score));
+        list.push(format!("levels {}, turns {}", entry.stats.levels, entry.turns));
+        list.push(format!("kills {}, knockouts {}", entry.stats.kills, entry.stats.knockouts));
+        list.push(format!("noise {}, items {}", entry.stats.noise, entry.stats.items_used));
+        list.push(format!("difficulty {}", entry.difficulty));
+        list.push("".to_string());
+
+        list.push(format!("seed {}", entry.seed));
+        let headers = vec!("#", "score", "lvl", "turns");
+        let rows = game.leaderboard.for_seed(entry.seed).iter().enumerate().map(|(index, other)| {
+            let rank = if Some(index) == game.settings.rank {
+                format!("{}*", index + 1)
+            } else {
+                format!("{}", index + 1)
+            };
+            return vec!(rank, other.score.to_string(), other.stats.levels.to_string(), other.turns.to_string());
+        }).collect::<Vec<Vec<String>>>();
+        list.extend(format_table(&headers, &rows));
+        list.push("".to_string());
+    }
+
+    for achievement in game.settings.new_unlocks.iter() {
+        list.push(format!("unlocked: {}", achievement.description()));
+    }
+    if game.settings.new_unlocks.len() > 0 {
+        list.push("".to_string());
+    }
+
+    for (index, item) in GAME_OVER_ITEMS.iter().enumerate() {
+        list.push(format!("{} {}", index, game.locale.text(item)));
+    }
+
+    let y_pos = 2;
+    let text_pos = Pos::new(2, y_pos);
+    let color = game.config.color_light_grey;
+
+    display_state.text.draw_text_list(panel, &list, text_pos, color);
+}
+
+fn render_level_complete(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+    render_placard(panel, display_state, "Level Complete", &game.config);
+
+    let mut list = Vec::new();
+    if let Some(summary) = &game.settings.level_summary {
+        list.extend(summary.lines());
+    }
+    list.push("".to_string());
+    list.push("enter: continue".to_string());
+
+    let text_pos = Pos::new(2, 2);
+    let color = game.config.color_light_grey;
+
+    display_state.text.draw_text_list(panel, &list, text_pos, color);
+}
+
+fn render_error(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+    render_placard(panel, display_state, "Error", &game.config);
+
+    let mut list = Vec::new();
+    if let Some(error) = &game.settings.error {
+        list.extend(wrap_text(&error.to_string(), PROMPT_WIDTH));
+    }
+    list.push("".to_string());
+    list.push("enter: exit".to_string());
+
+    let text_pos = Pos::new(2, 2);
+    let color = game.config.color_light_grey;
+
+    display_state.text.draw_text_list(panel, &list, text_pos, color);
+}
+
+/// Lay out rows of text as a table, padding each column to its widest entry.
+fn format_table(headers: &Vec<&str>, rows: &Vec<Vec<String>>) -> Vec<String> {
+    let mut widths = headers.iter().map(|header| header.len()).collect::<Vec<usize>>();
+    for row in rows.iter() {
+        for (column, text) in row.iter().enumerate() {
+            widths[column] = std::cmp::max(widths[column], text.len());
+        }
+    }
+
+    let mut lines = Vec::new();
+    let header_row = headers.iter().map(|header| header.to_string()).collect::<Vec<String>>();
+    for row in std::iter::once(&header_row).chain(rows.iter()) {
+        let cells = row.iter()
+                       .zip(widths.iter())
+                       .map(|(text, width)| format!("{:<width$}", text, width = width))
+                       .collect::<Vec<String>>();
+        lines.push(cells.join(" ").trim_end().to_string());
+    }
+
+    return lines;
+}
+
+fn render_loading(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+    render_placard(panel, display_state, "Loading", &game.config);
+
+    // cycle the dots so it is clear that the game has not stalled
+    let num_dots = (game.settings.time * 2.0) as usize % 4;
+    let text = format!("generating level{}", ".".repeat(num_dots));
+
+    let text_pos = Pos::new(2, 2);
+    let color = game.config.color_light_grey;
+
+    display_state.text.draw_text(panel, &text, text_pos, color);
+}
+
+/// Draw the sandbox palette, scrolled so the selected entry is visible.
+fn render_palette(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+    render_placard(panel, display_state, "Sandbox", &game.config);
+
+    let selected = game.settings.palette.index();
+
+    // leave room for the placard and its border
+    let num_rows = panel.cells.1 as usize - 3;
+    let first = if selected >= num_rows {
+        selected + 1 - num_rows
+    } else {
+        0
+    };
+
+    let mut y_pos = 2;
+    for (index, entry) in PALETTE.iter().enumerate().skip(first).take(num_rows) {
+        let color;
+        let marker;
+        if index == selected {
+            color = game.config.color_ice_blue;
+            marker = ">";
+        } else {
+            color = game.config.color_mint_green;
+            marker = " ";
+        }
+
+        let text_pos = Pos::new(1, y_pos);
+        display_state.text.draw_text(panel, &format!("{}{}", marker, entry), text_pos, color);
+
+        y_pos += 1;
+    }
 }
 
 /// Render an inventory section within the given area
@@ -540,13 +1189,13 @@ fn render_inventory(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut Di
         // place object name
         let text_pos = Pos::new(2, y_pos);
         let item_marker =
-            if index == 0 && item_primary_at(player_id, &mut game.data.entities, 0) {
+            if game.data.entities.held_item(player_id, Hand::Primary) == Some(obj_id) {
                 "<"
             } else {
                 ""
             };
-        let item_text = format!(" {:?} {}", game.data.entities.name[&obj_id], item_marker);
-        tile_sprite.draw_text(panel, &item_text, text_pos, color);
+        let item_text = format!(" {} {}", game.locale.name(game.data.entities.name[&obj_id]), item_marker);
+        display_state.text.draw_text(panel, &item_text, text_pos, color);
         
         y_pos += 1;
 
@@ -555,8 +1204,21 @@ fn render_inventory(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut Di
 
     if game.data.entities.inventory[&player_id].len() == 0 {
         let text_pos = Pos::new(1, y_pos);
-        tile_sprite.draw_text(panel, &format!("empty"), text_pos, game.config.color_ice_blue);
+        display_state.text.draw_text(panel, &format!("empty"), text_pos, game.config.color_ice_blue);
+        y_pos += 1;
     }
+
+    // the total weight carried turns red once it is over what the player can carry
+    let weight = game.data.entities.carried_weight(player_id);
+    let capacity = game.data.entities.carry_capacity(player_id, &game.config);
+    let weight_color =
+        if weight > capacity {
+            game.config.color_red
+        } else {
+            game.config.color_ice_blue
+        };
+    let text_pos = Pos::new(1, y_pos + 1);
+    display_state.text.draw_text(panel, &format!("weight {}/{}", weight, capacity), text_pos, weight_color);
 }
 
 /// render the background files, including water tiles
@@ -575,7 +1237,7 @@ fn render_background(display: &mut Display, game: &mut Game) {
 
     let panel = display.targets.background_panel.unit();
     canvas.with_texture_canvas(&mut display.targets.background_panel.target, |canvas| {
-        canvas.set_draw_color(Sdl2Color::RGB(0, 0, 0));
+        canvas.set_draw_color(sdl2_color(game.config.color_background));
         canvas.clear();
 
         let mut panel = panel.with_target(canvas);
@@ -592,23 +1254,31 @@ fn render_background(display: &mut Display, game: &mut Game) {
                     sprite.draw_char(&mut panel,
                                      MAP_EMPTY_CHAR as char,
                                      map_pos,
-                                     Color::white());
+                                     game.config.color_tile);
                 } else {
-                    sprite.draw_char(&mut panel, MAP_EMPTY_CHAR as char, map_pos, Color::white());
+                    sprite.draw_char(&mut panel, MAP_EMPTY_CHAR as char, map_pos, game.config.color_water);
                 }
             }
         }
     }).unwrap();
 }
 
-fn render_surface(panel: &mut Panel<&mut WindowCanvas>, sprite: &mut SpriteSheet, surface: Surface, pos: Pos) {
+fn render_surface(panel: &mut Panel<&mut WindowCanvas>, sprite: &mut SpriteSheet, surface: Surface, pos: Pos, config: &Config) {
     match surface {
         Surface::Rubble => {
-            sprite.draw_char(panel, MAP_RUBBLE as char, pos, Color::white());
+            sprite.draw_char(panel, MAP_RUBBLE as char, pos, config.color_rubble);
         }
 
         Surface::Grass => {
-            sprite.draw_char(panel, MAP_GRASS as char, pos, Color::white()); //game.config.color_light_green);
+            sprite.draw_char(panel, MAP_GRASS as char, pos, config.color_grass);
+        }
+
+        Surface::Burning => {
+            sprite.draw_char(panel, MAP_GRASS as char, pos, config.color_orange);
+        }
+
+        Surface::Dirt => {
+            sprite.draw_char(panel, MAP_GROUND as char, pos, config.color_light_brown);
         }
 
         Surface::Floor => {
@@ -706,7 +1376,7 @@ fn render_map(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayS
 
             // draw an outline around the tile
             {
-                let mut outline_color = Color::white();
+                let mut outline_color = game.config.color_grid;
                 outline_color.a /= 8;
                 draw_outline_tile(panel, pos, outline_color);
             }
@@ -723,15 +1393,16 @@ fn render_map(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayS
             // if the tile is not empty or water, draw it
             if tile.tile_type == TileType::Water {
                 let sprite = &mut display_state.sprites[&sprite_key];
-                sprite.draw_char(panel, MAP_WATER as char, pos, Color::white());
+                sprite.draw_char(panel, MAP_WATER as char, pos, game.config.color_water);
             } else if chr != MAP_EMPTY_CHAR {
+                let color = if tile.cracked { game.config.color_cracked_wall } else { game.config.color_tile };
                 let sprite = &mut display_state.sprites[&sprite_key];
-                sprite.draw_char(panel, chr as char, pos, Color::white());
+                sprite.draw_char(panel, chr as char, pos, color);
             }
 
             {
                 let sprite = &mut display_state.sprites[&sprite_key];
-                render_surface(panel, sprite, tile.surface, pos);
+                render_surface(panel, sprite, tile.surface, pos, &game.config);
             }
 
             render_wall_shadow(pos, panel, display_state, game);
@@ -749,7 +1420,7 @@ fn render_map(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayS
                    game.data.pos_in_fov(player_id, pos, &game.config);
                 game.data.entities.status[&player_id].extra_fov -= 1;
 
-                let mut blackout_color = Color::black();
+                let mut blackout_color = game.config.color_fog;
                 if is_in_fov_ext {
                     blackout_color.a = game.config.fov_edge_alpha
                 } else if game.data.map[pos].explored {
@@ -767,10 +1438,10 @@ fn render_itertile_walls(panel: &mut Panel<&mut WindowCanvas>,
                          map: &Map,
                          sprite: &mut SpriteSheet,
                          pos: Pos,
-                         _config: &Config) {
+                         config: &Config) {
     let (x, y) = pos.to_tuple();
     let tile = map[pos];
-    let wall_color = Color::white();
+    let wall_color = config.color_wall;
 
     // Lower walls
     if tile.bottom_wall == Wall::ShortWall {
@@ -825,7 +1496,7 @@ fn render_effects(panel: &mut Panel<&mut WindowCanvas>,
         let mut effect = display_state.effects[index].clone();
         match &mut effect {
             Effect::Sound(sound_aoe, sound_dt) => {
-                let mut highlight_color = game.config.color_warm_grey;
+                let mut highlight_color = game.config.color_sound;
 
                 let radius = sound_aoe.positions.len();
                 let sound_interval = game.config.sound_timeout / radius as f32;
@@ -889,7 +1560,7 @@ fn render_effects(panel: &mut Panel<&mut WindowCanvas>,
                     tile_sprite.draw_sprite_at_cell(panel,
                                                     sprite_index as usize,
                                                     pos,
-                                                    Color::white(),
+                                                    game.config.color_attack_effect,
                                                     rotation,
                                                     false,
                                                     false);
@@ -901,6 +1572,43 @@ fn render_effects(panel: &mut Panel<&mut WindowCanvas>,
                     *remaining -= 1;
                 }
             }
+
+            Effect::Debris(remaining, pos) => {
+                let sprite_key = display_state.lookup_spritekey("tiles");
+                let tile_sprite = &mut display_state.sprites[&sprite_key];
+
+                // debris is scattered around the wall, fading out as the effect ends
+                let mut debris_color = game.config.color_debris;
+                debris_color.a = ((255 * *remaining) / game.config.debris_duration.max(1)) as u8;
+
+                let mut debris_positions = game.data.map.neighbors(*pos);
+                debris_positions.push(*pos);
+                for debris_pos in debris_positions {
+                    if !game.data.map[debris_pos].block_move &&
+                       game.data.pos_in_fov(player_id, debris_pos, &game.config) {
+                        tile_sprite.draw_char(panel, MAP_RUBBLE as char, debris_pos, debris_color);
+                    }
+                }
+
+                if *remaining == 0 {
+                    effect_complete = true;
+                } else {
+                    *remaining -= 1;
+                }
+            }
+
+            Effect::StealthKill(remaining, pos) => {
+                // a dark flash on the victim's tile that fades out, with no beam or sound ring
+                let mut kill_color = game.config.color_stealth_kill;
+                kill_color.a = ((255 * *remaining) / game.config.stealth_kill_duration.max(1)) as u8;
+                draw_tile_highlight(panel, *pos, kill_color);
+
+                if *remaining == 0 {
+                    effect_complete = true;
+                } else {
+                    *remaining -= 1;
+                }
+            }
         }
         display_state.effects[index] = effect;
 
@@ -930,6 +1638,11 @@ fn render_entity(panel: &mut Panel<&mut WindowCanvas>,
            return None;
     }
 
+    // hidden traps are not drawn until the player has seen them go off
+    if game.data.trap_hidden_from(player_id, entity_id) && !game.settings.god_mode {
+        return None;
+    }
+
     let is_in_fov = 
        game.data.is_in_fov(player_id, entity_id, &game.config) ||
        game.settings.god_mode;
@@ -992,6 +1705,33 @@ fn render_entity(panel: &mut Panel<&mut WindowCanvas>,
     return animation_result.sprite;
 }
 
+/// Shade explored tiles with the gas in them, thicker clouds being more opaque.
+fn render_gas(panel: &mut Panel<&mut WindowCanvas>, game: &mut Game) {
+    let (width, height) = game.data.map.size();
+    for y in 0..height {
+        for x in 0..width {
+            let pos = Pos::new(x, y);
+            if !game.data.map[pos].explored {
+                continue;
+            }
+
+            let gas = game.data.map.gas(pos);
+
+            let mut smoke_color = game.config.color_light_grey;
+            smoke_color.a = (gas.smoke.min(1.0) * 200.0) as u8;
+            if smoke_color.a > 0 {
+                draw_tile_highlight(panel, pos, smoke_color);
+            }
+
+            let mut poison_color = game.config.color_mint_green;
+            poison_color.a = (gas.poison.min(1.0) * 200.0) as u8;
+            if poison_color.a > 0 {
+                draw_tile_highlight(panel, pos, poison_color);
+            }
+        }
+    }
+}
+
 fn render_impressions(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
     // check for entities that have left FOV and make an impression for them
     let mut index = 0;
@@ -1002,6 +1742,23 @@ fn render_impressions(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut
     }
 }
 
+/// Draw the ghost of the best earlier run on this seed as a see-through copy of the player.
+fn render_ghost(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+    let ghost_pos = game.ghost.as_ref().and_then(|ghost| ghost.player_pos(game.settings.level_num));
+
+    if let Some(ghost_pos) = ghost_pos {
+        let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+        let player_pos = game.data.entities.pos[&player_id];
+        let alpha = game.data.entities.color[&player_id].a;
+
+        game.data.entities.color[&player_id].a = game.config.ghost_alpha;
+        game.data.entities.pos[&player_id] = ghost_pos;
+        render_entity(panel, player_id, display_state, game);
+        game.data.entities.color[&player_id].a = alpha;
+        game.data.entities.pos[&player_id] = player_pos;
+    }
+}
+
 fn render_entity_type(typ: EntityType, panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
     let mut index = 0;
     while index < game.data.entities.ids.len() {
@@ -1025,10 +1782,12 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
     let player_id = game.data.find_by_name(EntityName::Player).unwrap();
     let player_pos = game.data.entities.pos[&player_id];
 
+    let overlays = game.settings.shown_overlays();
+
     let sprite_key = display_state.lookup_spritekey("tiles");
 
     // render a grid of numbers if enabled
-    if game.config.overlay_directions {
+    if game.config.overlay_directions || overlays.is_on(Overlay::Debug) {
         let tile_sprite = &mut display_state.sprites[&sprite_key];
 
         let map_width = game.data.map.width();
@@ -1042,7 +1801,7 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
                 if x_diff.abs() < 5 && y_diff.abs() < 5 {
                     let res: i8 = x_diff as i8 - y_diff as i8;
                     if res <= 0 {
-                        tile_sprite.draw_char(panel, MAP_GROUND as char, pos, game.config.color_light_green);
+                        tile_sprite.draw_char(panel, MAP_GROUND as char, pos, game.config.color_overlay_debug);
                     } else {
                         tile_sprite.draw_char(panel, MAP_GROUND as char, pos, game.config.color_light_grey);
                     }
@@ -1052,6 +1811,16 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
         }
     }
 
+    // tiles which can be chosen while selecting a tile
+    if let Some(selection) = game.settings.selection {
+        let mut selection_color = game.config.color_overlay_selection;
+        selection_color.a = game.config.grid_alpha_overlay;
+
+        for pos in selection.valid_positions(&game.data) {
+            draw_tile_highlight(panel, pos, selection_color);
+        }
+    }
+
     // render cursor if enabled
     if game.config.use_cursor {
         // render cursor itself
@@ -1085,7 +1854,7 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
     }
 
     // render FOV if enabled
-    if game.config.overlay_player_fov {
+    if game.config.overlay_player_fov || overlays.is_on(Overlay::Debug) {
         let tile_sprite = &mut display_state.sprites[&sprite_key];
 
         let map_width = game.data.map.width();
@@ -1097,19 +1866,15 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
                 let is_in_fov =
                     game.data.pos_in_fov(player_id, pos, &game.config);
                 if is_in_fov {
-                    tile_sprite.draw_char(panel, MAP_GROUND as char, pos, game.config.color_light_green);
+                    tile_sprite.draw_char(panel, MAP_GROUND as char, pos, game.config.color_overlay_debug);
                 }
             }
         }
     }
 
-    // Draw player action overlay. Could draw arrows to indicate how to reach each location
-    let mut highlight_color: Color = game.config.color_warm_grey;
-    highlight_color.a = game.config.highlight_player_move;
-
     // draw direction overlays
     if game.config.draw_directional_arrow {
-        let direction_color = Color::white();
+        let direction_color = game.config.color_direction;
         let tile_sprite = &mut display_state.sprites[&sprite_key];
         let mut index = 0;
         while index < game.data.entities.ids.len() {
@@ -1188,7 +1953,7 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
     }
 
     // render attack overlay highlighting squares that an entity can attack
-    if game.settings.overlay {
+    if overlays.is_on(Overlay::EnemyFov) {
         let keys = game.data.entities.ids.iter().map(|id| *id).collect::<Vec<EntityId>>();
         for entity_id in keys {
             let pos = game.data.entities.pos[&entity_id];
@@ -1205,6 +1970,31 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
         }
     }
 
+    // shade the tiles enemies can see by how alert they are
+    if overlays.is_on(Overlay::Vision) {
+        for (pos, alertness) in enemy_vision(&game.data, &game.config) {
+            let mut vision_color =
+                match alertness {
+                    Alertness::Idle => game.config.color_overlay_vision_idle,
+                    Alertness::Investigating => game.config.color_overlay_vision_investigating,
+                    Alertness::Attacking => game.config.color_overlay_vision_attacking,
+                };
+            vision_color.a = game.config.highlight_alpha_vision;
+
+            draw_tile_highlight(panel, pos, vision_color);
+        }
+    }
+
+    // shade each tile by how many enemies could reach it next turn
+    if overlays.is_on(Overlay::Threat) {
+        for (pos, count) in enemy_threat(&game.data, &game.config) {
+            let mut threat_color = game.config.color_overlay_threat;
+            threat_color.a = std::cmp::min(count * game.config.highlight_alpha_threat as usize, 255) as u8;
+
+            draw_tile_highlight(panel, pos, threat_color);
+        }
+    }
+
     // draw mouse path overlays
     if let Some(mouse_id) = game.data.find_by_name(EntityName::Mouse) {
         let tile_sprite = &mut display_state.sprites[&sprite_key];
@@ -1212,11 +2002,14 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
         let mouse_pos = game.data.entities.pos[&mouse_id];
         let player_pos = game.data.entities.pos[&player_id];
 
-        if game.config.draw_star_path {
+        let mut path_color = game.config.color_overlay_path;
+        path_color.a = game.config.highlight_player_move;
+
+        if game.config.draw_star_path || overlays.is_on(Overlay::Path) {
             // get a path to the mouse path, regardless of distance
             let path = astar_path(&game.data.map, player_pos, mouse_pos, None, None);
             for pos in path {
-                tile_sprite.draw_char(panel, MAP_EMPTY_CHAR as char, pos, highlight_color);
+                tile_sprite.draw_char(panel, MAP_EMPTY_CHAR as char, pos, path_color);
             }
         }
 
@@ -1227,14 +2020,14 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
                 let line = line(player_pos, mouse_pos).into_iter();
                 for pos in line {
                     let pos = Pos::from(pos);
-                    tile_sprite.draw_char(panel, MAP_EMPTY_CHAR as char, pos, highlight_color);
+                    tile_sprite.draw_char(panel, MAP_EMPTY_CHAR as char, pos, path_color);
                 }
             }
         }
     }
 
     // Draw player movement overlay
-    if game.settings.overlay {
+    if overlays.is_on(Overlay::Selection) {
         for move_action in Direction::move_actions().iter() {
             // for all movements except staying still
             // calculate the move that would occur
@@ -1257,16 +2050,22 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
     }
 
     // Draw sound tiles overlay
-    if game.settings.overlay {
+    if overlays.is_on(Overlay::Sound) {
         // NOTE this currently does not take into account FOV!
-        for pos in display_state.sound_tiles.iter() {
-            draw_tile_highlight(panel, *pos, highlight_color);
+        for pos in game.data.map.get_all_pos() {
+            let sound = game.data.map.sound(pos);
+            if sound > 0.0 {
+                let mut sound_color = game.config.color_overlay_sound;
+                sound_color.a = (game.config.highlight_player_move as f32 * sound) as u8;
+
+                draw_tile_highlight(panel, pos, sound_color);
+            }
         }
     }
 
     // Outline tiles within FOV for clarity
-    if game.settings.overlay {
-        let mut highlight_color_fov = game.config.color_light_orange;
+    if overlays.is_on(Overlay::Selection) {
+        let mut highlight_color_fov = game.config.color_overlay_selection;
         highlight_color_fov.a = game.config.grid_alpha_visible;
 
         for y in 0..game.data.map.height() {
@@ -1287,7 +2086,7 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
     // low 30s tend to be next to a shear wall
     // 40 are nearly fully open
     // 49 may be fully open
-    if game.config.overlay_floodfill {
+    if game.config.overlay_floodfill || overlays.is_on(Overlay::Debug) {
         let font_key = display_state.lookup_spritekey("font");
 
         let mut highlight_color = game.config.color_light_orange;
@@ -1324,7 +2123,7 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
             if game.data.is_in_fov(player_id, *entity_id, &game.config) {
                 let mut status_drawn: bool = false;
                 if let Some(status) = game.data.entities.status.get(entity_id) {
-                    if status.frozen > 0 {
+                    if status.frozen > 0 || status.knocked_out > 0 {
                         status_drawn = true;
                         tile_sprite.draw_sprite_direction(panel,
                                                           ASTERISK as usize,
@@ -1339,7 +2138,7 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
                 if status_drawn {
                     if let Some(behavior) = game.data.entities.behavior.get(entity_id) {
                         match behavior {
-                            Behavior::Idle => {
+                            Behavior::Idle | Behavior::Following(_) | Behavior::Fleeing(_) => {
                             }
 
                             Behavior::Investigating(_) => {
@@ -1352,7 +2151,7 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
                                                                   0.0);
                             }
 
-                            Behavior::Attacking(_) => {
+                            Behavior::Attacking(_) | Behavior::RaisingAlarm(_) => {
                                 tile_sprite.draw_sprite_direction(panel,
                                                                   EXCLAMATION_POINT as usize,
                                                                   Some(Direction::UpRight),
@@ -1417,6 +2216,11 @@ fn tile_color(config: &Config, _x: i32, _y: i32, tile: &Tile, visible: bool) ->
             config.color_orange,
         (TileType::Exit, false) =>
             config.color_red,
+
+        (TileType::Door(_), true) =>
+            config.color_light_brown,
+        (TileType::Door(_), false) =>
+            config.color_dark_brown,
     };
 
     return color;
@@ -1431,7 +2235,7 @@ fn render_attack_overlay(panel: &mut Panel<&mut WindowCanvas>,
 
     let object_pos = game.data.entities.pos[&entity_id];
 
-    let mut attack_highlight_color = game.config.color_red;
+    let mut attack_highlight_color = game.config.color_overlay_enemy_fov;
     attack_highlight_color.a = game.config.highlight_alpha_attack;
 
     let sprite_key = display_state.lookup_spritekey("tiles");
@@ -1478,7 +2282,6 @@ fn render_fov_overlay(panel: &mut Panel<&mut WindowCanvas>,
             let visible = game.data.pos_in_fov(entity_id, map_pos, &game.config) &&
                           game.data.pos_in_fov(player_id, map_pos, &game.config);
 
-
             if visible {
                 draw_outline_tile(panel, map_pos, highlight_color);
             }
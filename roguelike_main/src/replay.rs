@@ -91,7 +91,7 @@ pub fn test_recording() {
     let mut game = Game::new(0, config.clone());
     let mut input_action;
 
-    make_map(&config.map_load, &mut game);
+    make_map(&config.map_load, &mut game).unwrap();
 
     let player_id = game.data.find_by_name(EntityName::Player).unwrap();
     let starting_pos = game.data.entities.pos[&player_id];
@@ -199,7 +199,7 @@ fn check_record(game: &mut Game, display: &mut Display, event_pump: &mut sdl2::E
     let map_config_string = std::fs::read_to_string(map_config_path).unwrap();
     let map_config = map_config_string.parse::<MapLoadConfig>().expect("Could not parse map config");
     eprintln!("Using map config: {}", &map_config);
-    make_map(&map_config, game);
+    make_map(&map_config, game).unwrap_or_else(|err| panic!("{}", err));
 
     let action_path = format!("{}/{}", path, Log::ACTION_LOG_NAME);
     let actions = read_action_log(&action_path);
@@ -365,7 +365,7 @@ fn rerecord(game: &mut Game, display: &mut Display, event_pump: &mut sdl2::Event
     let map_config_string = std::fs::read_to_string(map_config_path).unwrap();
     let map_config = map_config_string.parse::<MapLoadConfig>().expect("Could not parse map config");
     eprintln!("Using map config: {}", &map_config);
-    make_map(&map_config, game);
+    make_map(&map_config, game).map_err(|err| err.to_string())?;
 
     let action_path = format!("{}/{}", path, Log::ACTION_LOG_NAME);
     let actions = read_action_log(&action_path);